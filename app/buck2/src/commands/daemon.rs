@@ -8,6 +8,7 @@
  */
 
 use std::fs::File;
+use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::PathBuf;
 use std::process;
@@ -28,6 +29,8 @@ use buck2_common::invocation_paths::InvocationPaths;
 use buck2_common::memory;
 use buck2_core::buck2_env;
 use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::logging::LogConfigurationReloadHandle;
 use buck2_server::builtin_docs::docs::docs_command;
@@ -171,7 +174,15 @@ pub(crate) fn write_process_info(
     daemon_dir: &DaemonDir,
     process_info: &DaemonProcessInfo,
 ) -> anyhow::Result<()> {
-    let file = File::create(daemon_dir.buckd_info())?;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        // The process info includes the auth token, so only the owner may read it.
+        options.mode(0o600);
+    }
+    let file = options.open(daemon_dir.buckd_info())?;
     serde_json::to_writer(&file, &process_info)?;
     Ok(())
 }
@@ -256,7 +267,23 @@ impl DaemonCommand {
         // Even if we don't redirect output, we still need to create stdout/stderr files,
         // because tailer opens them. This is untidy.
         let stdout = File::create(stdout_path)?;
-        let stderr = File::create(stderr_path)?;
+        let stderr_prev_path = paths.daemon_log_prev();
+        // Keep the log of the previous daemon around in buck-out, e.g. to find out why it died.
+        // Older logs are dropped, and `rotate_daemon_log_thread` keeps the log of this daemon
+        // bounded.
+        if fs_util::try_exists(&stderr_path)? {
+            if let Err(e) = Self::rotate_daemon_log(&stderr_path, &stderr_prev_path) {
+                tracing::warn!("Failed to keep the log of the previous daemon: {:#}", e);
+            }
+        }
+        // Appending, so that writes carry on at the start of the file after it is truncated
+        // by the rotation.
+        let stderr = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(stderr_path.as_path())?;
+        stderr.set_len(0)?;
+        let log_max_bytes = buck2_env!("BUCK2_DAEMON_LOG_MAX_BYTES", type=u64, default=100 << 20)?;
 
         let auth_token = gen_auth_token();
 
@@ -393,6 +420,8 @@ impl DaemonCommand {
                 hard_shutdown_sender: hard_shutdown_sender.clone(),
             });
             let daemon_dir = paths.daemon_dir()?;
+            let stderr_path = daemon_dir.buckd_stderr();
+            let stderr_prev_path = paths.daemon_log_prev();
 
             listener.set_nonblocking(true)?;
             let listener = tokio::net::TcpListener::from_std(listener)?;
@@ -432,6 +461,10 @@ impl DaemonCommand {
                 )
             })?;
 
+            thread_spawn("rotate-daemon-log", move || {
+                Self::rotate_daemon_log_thread(stderr_path, stderr_prev_path, log_max_bytes)
+            })?;
+
             tracing::info!("Initialization complete, running the server.");
 
             select! {
@@ -483,6 +516,45 @@ impl DaemonCommand {
         })
     }
 
+    /// Move the contents of the daemon log to `stderr_prev_path` in buck-out, replacing the
+    /// previous one. The log is copied and truncated rather than renamed, because the daemon
+    /// keeps writing to it, and buck-out may be on another filesystem than the daemon dir.
+    fn rotate_daemon_log(
+        stderr_path: &AbsNormPath,
+        stderr_prev_path: &AbsNormPath,
+    ) -> anyhow::Result<()> {
+        if let Some(dir) = stderr_prev_path.parent() {
+            fs_util::create_dir_all(dir)?;
+        }
+        fs_util::copy(stderr_path, stderr_prev_path)?;
+        OpenOptions::new()
+            .write(true)
+            .open(stderr_path.as_path())?
+            .set_len(0)?;
+        Ok(())
+    }
+
+    /// The daemon writes its stderr for as long as it runs, so periodically rotate it once it
+    /// gets larger than `max_bytes`.
+    fn rotate_daemon_log_thread(
+        stderr_path: AbsNormPathBuf,
+        stderr_prev_path: AbsNormPathBuf,
+        max_bytes: u64,
+    ) {
+        loop {
+            std::thread::sleep(Duration::from_secs(60));
+            let rotate = || -> anyhow::Result<()> {
+                if fs_util::metadata(&stderr_path)?.len() > max_bytes {
+                    Self::rotate_daemon_log(&stderr_path, &stderr_prev_path)?;
+                }
+                Ok(())
+            };
+            if let Err(e) = rotate() {
+                tracing::warn!("Failed to rotate the daemon log: {:#}", e);
+            }
+        }
+    }
+
     pub(crate) fn exec(
         self,
         init: fbinit::FacebookInit,
@@ -496,9 +568,7 @@ impl DaemonCommand {
         let project_root = paths.project_root();
         let daemon_dir = paths.daemon_dir()?;
 
-        if !daemon_dir.path.is_dir() {
-            fs_util::create_dir_all(&daemon_dir.path)?;
-        }
+        daemon_dir.create()?;
 
         // TODO(nga): this breaks relative paths in `--no-buckd`.
         //   `--no-buckd` should capture correct directories earlier.
@@ -574,7 +644,9 @@ mod tests {
     use buck2_common::init::DaemonStartupConfig;
     use buck2_common::invocation_paths::InvocationPaths;
     use buck2_common::invocation_roots::InvocationRoots;
+    use buck2_core::fs::fs_util;
     use buck2_core::fs::paths::file_name::FileNameBuf;
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
     use buck2_core::fs::project::ProjectRootTemp;
     use buck2_core::logging::LogConfigurationReloadHandle;
     use buck2_server::daemon::daemon_tcp::create_listener;
@@ -587,6 +659,7 @@ mod tests {
     use tokio::runtime::Handle;
 
     use crate::commands::daemon::BuckdServerDependenciesImpl;
+    use crate::commands::daemon::DaemonCommand;
 
     // `fbinit_tokio` is not on crates, so we cannot use `#[fbinit::test]`.
     #[tokio::test]
@@ -694,4 +767,29 @@ mod tests {
             .expect("handle join failed")
             .expect("daemon returned error");
     }
+
+    #[test]
+    fn test_rotate_daemon_log() {
+        let project_root = ProjectRootTemp::new().unwrap();
+        let root = project_root.path().root();
+        let stderr_path = root.join(ForwardRelativePath::unchecked_new("buckd.stderr"));
+        let stderr_prev_path = root.join(ForwardRelativePath::unchecked_new(
+            "buck-out/v2/daemon_log/buckd.stderr.prev",
+        ));
+
+        fs_util::write(&stderr_path, "first daemon").unwrap();
+        DaemonCommand::rotate_daemon_log(&stderr_path, &stderr_prev_path).unwrap();
+        assert_eq!("", fs_util::read_to_string(&stderr_path).unwrap());
+        assert_eq!(
+            "first daemon",
+            fs_util::read_to_string(&stderr_prev_path).unwrap()
+        );
+
+        fs_util::write(&stderr_path, "second daemon").unwrap();
+        DaemonCommand::rotate_daemon_log(&stderr_path, &stderr_prev_path).unwrap();
+        assert_eq!(
+            "second daemon",
+            fs_util::read_to_string(&stderr_prev_path).unwrap()
+        );
+    }
 }
@@ -0,0 +1,72 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use anyhow::Context as _;
+use buck2_client_ctx::immediate_config::ImmediateConfigContext;
+use buck2_core::fs::paths::file_name::FileNameBuf;
+
+const DEFAULT_ISOLATION_DIR: &str = "v2";
+
+pub(crate) fn parse_isolation_dir(s: &str) -> anyhow::Result<FileNameBuf> {
+    FileNameBuf::try_from(s.to_owned()).context("isolation dir must be a directory name")
+}
+
+/// The isolation dir to use when none is passed: `v2`, or `v2-<user>` if the project sets
+/// `buck2.per_user_isolation_dir = true`, so that users building in a shared checkout each get
+/// their own daemon and buck-out.
+pub(crate) fn default_isolation_dir(
+    immediate_config: &ImmediateConfigContext,
+) -> anyhow::Result<FileNameBuf> {
+    let name = if immediate_config.per_user_isolation_dir()? {
+        format!("{}-{}", DEFAULT_ISOLATION_DIR, sanitize(&current_user()?))
+    } else {
+        DEFAULT_ISOLATION_DIR.to_owned()
+    };
+    parse_isolation_dir(&name)
+}
+
+/// User names can contain characters that aren't allowed, or are awkward, in file names.
+fn sanitize(user: &str) -> String {
+    user.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn current_user() -> anyhow::Result<String> {
+    let uid = nix::unistd::geteuid();
+    // Users without a passwd entry (e.g. in containers) still get a distinct directory.
+    Ok(match nix::unistd::User::from_uid(uid)? {
+        Some(user) => user.name,
+        None => uid.to_string(),
+    })
+}
+
+#[cfg(windows)]
+fn current_user() -> anyhow::Result<String> {
+    std::env::var("USERNAME").context("`USERNAME` is not set")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!("alice", sanitize("alice"));
+        assert_eq!("DOMAIN_bob", sanitize("DOMAIN\\bob"));
+        assert_eq!("j.doe-1", sanitize("j.doe-1"));
+    }
+}
@@ -15,6 +15,7 @@
 use anyhow::Context as _;
 use buck2_audit::AuditCommand;
 use buck2_client::args::expand_argfiles_with_context;
+use buck2_client::commands::attach::AttachCommand;
 use buck2_client::commands::build::BuildCommand;
 use buck2_client::commands::bxl::BxlCommand;
 use buck2_client::commands::clean::CleanCommand;
@@ -68,25 +69,25 @@ use crate::commands::daemon::DaemonCommand;
 use crate::commands::docs::DocsCommand;
 use crate::commands::forkserver::ForkserverCommand;
 use crate::commands::internal_test_runner::InternalTestRunnerCommand;
+use crate::isolation_dir::default_isolation_dir;
+use crate::isolation_dir::parse_isolation_dir;
 use crate::process_context::ProcessContext;
 
 mod check_user_allowed;
 mod cli_style;
 pub mod commands;
+mod isolation_dir;
 mod no_buckd;
 pub mod panic;
 pub mod process_context;
 
-fn parse_isolation_dir(s: &str) -> anyhow::Result<FileNameBuf> {
-    FileNameBuf::try_from(s.to_owned()).context("isolation dir must be a directory name")
-}
-
 /// Options of `buck2` command, before subcommand.
 #[derive(Clone, Debug, clap::Parser)]
 #[clap(next_help_heading = "Universal Options")]
 struct BeforeSubcommandOptions {
     /// The name of the directory that Buck2 creates within buck-out for writing outputs and daemon
-    /// information. If one is not provided, Buck2 creates a directory with the default name.
+    /// information. If one is not provided, Buck2 creates a directory with the default name:
+    /// `v2`, or `v2-<user>` if the project sets `buck2.per_user_isolation_dir = true`.
     ///
     /// Instances of Buck2 share a daemon if and only if their isolation directory is identical.
     /// The isolation directory also influences the output paths provided by Buck2,
@@ -94,10 +95,9 @@ struct BeforeSubcommandOptions {
     #[clap(
         value_parser = parse_isolation_dir,
         env("BUCK_ISOLATION_DIR"),
-        long,
-        default_value="v2"
+        long
     )]
-    isolation_dir: FileNameBuf,
+    isolation_dir: Option<FileNameBuf>,
 
     /// How verbose buck should be while logging.
     ///
@@ -125,7 +125,8 @@ struct BeforeSubcommandOptions {
 
     /// Metadata key-value pairs to inject into Buck2's logging. Client metadata must be of the
     /// form `key=value`, where `key` is a snake_case identifier, and will be sent to backend
-    /// datasets.
+    /// datasets. They are also available to rules as `ctx.build_info.client_metadata`, and
+    /// `ci=true` sets `ctx.build_info.ci`.
     #[clap(long, global = true)]
     client_metadata: Vec<ClientMetadata>,
 
@@ -242,6 +243,7 @@ pub(crate) enum CommandKind {
     #[clap(subcommand)]
     Audit(AuditCommand),
     Aquery(AqueryCommand),
+    Attach(AttachCommand),
     Build(BuildCommand),
     Bxl(BxlCommand),
     // TODO(nga): implement `buck2 help-buckconfig` too
@@ -295,9 +297,15 @@ impl CommandKind {
     ) -> ExitResult {
         let roots = find_invocation_roots(process.working_dir.path());
         let paths = roots
-            .map(|r| InvocationPaths {
-                roots: r,
-                isolation: common_opts.isolation_dir.clone(),
+            .and_then(|r| {
+                let isolation = match &common_opts.isolation_dir {
+                    Some(isolation) => isolation.clone(),
+                    None => default_isolation_dir(immediate_config)?,
+                };
+                Ok(InvocationPaths {
+                    roots: r,
+                    isolation,
+                })
             })
             .map_err(buck2_error::Error::from);
 
@@ -354,6 +362,7 @@ impl CommandKind {
                 .into(),
             CommandKind::InternalTestRunner(cmd) => cmd.exec(matches, command_ctx).into(),
             CommandKind::Aquery(cmd) => cmd.exec(matches, command_ctx),
+            CommandKind::Attach(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Build(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Bxl(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Test(cmd) => cmd.exec(matches, command_ctx),
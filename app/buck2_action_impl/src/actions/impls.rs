@@ -7,11 +7,13 @@
  * of this source tree.
  */
 
+pub(crate) mod archive;
 pub(crate) mod cas_artifact;
 pub(crate) mod copy;
 pub(crate) mod download_file;
 pub(crate) mod offline;
 pub(crate) mod run;
+pub(crate) mod stamp;
 pub(crate) mod symlinked_dir;
 pub(crate) mod write;
 pub(crate) mod write_json;
@@ -0,0 +1,367 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::borrow::Cow;
+use std::ops::ControlFlow;
+
+use allocative::Allocative;
+use anyhow::Context as _;
+use async_trait::async_trait;
+use buck2_artifact::artifact::build_artifact::BuildArtifact;
+use buck2_build_api::actions::box_slice_set::BoxSliceSet;
+use buck2_build_api::actions::execute::action_executor::ActionExecutionMetadata;
+use buck2_build_api::actions::execute::action_executor::ActionOutputs;
+use buck2_build_api::actions::execute::error::ExecuteError;
+use buck2_build_api::actions::Action;
+use buck2_build_api::actions::ActionExecutable;
+use buck2_build_api::actions::ActionExecutionCtx;
+use buck2_build_api::actions::IncrementalActionExecutable;
+use buck2_build_api::actions::UnregisteredAction;
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_build_api::interpreter::rule_defs::cmd_args::value_as::ValueAsCommandLineLike;
+use buck2_build_api::interpreter::rule_defs::cmd_args::CommandLineArgLike;
+use buck2_build_api::interpreter::rule_defs::cmd_args::CommandLineContext;
+use buck2_build_api::interpreter::rule_defs::cmd_args::DefaultCommandLineContext;
+use buck2_core::category::Category;
+use buck2_error::BuckErrorContext;
+use buck2_execute::artifact::fs::ExecutorFs;
+use buck2_execute::execute::request::CommandExecutionInput;
+use buck2_execute::execute::request::CommandExecutionOutput;
+use buck2_execute::execute::request::CommandExecutionPaths;
+use buck2_execute::execute::request::CommandExecutionRequest;
+use dupe::Dupe;
+use gazebo::prelude::*;
+use indexmap::indexmap;
+use indexmap::IndexMap;
+use indexmap::IndexSet;
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use sorted_vector_map::SortedVectorMap;
+use starlark::values::OwnedFrozenValue;
+
+#[derive(Debug, buck2_error::Error)]
+enum ArchiveActionValidationError {
+    #[error("An archive action needs the artifact to archive or extract")]
+    NoSource,
+    #[error("Exactly one output must be specified for an archive action, got {0}")]
+    WrongNumberOfOutputs(usize),
+    #[error("Only artifact inputs are supported in archive actions, got {0}")]
+    UnsupportedInput(ArtifactGroup),
+}
+
+#[derive(Debug, buck2_error::Error)]
+enum ArchiveError {
+    #[error("Unknown archive format `{0}`, expected `tar.zst` or `zip`")]
+    UnknownFormat(String),
+    #[error("Cannot infer the archive format of `{0}` from its extension, pass `format`")]
+    CannotInferFormat(String),
+}
+
+#[derive(Debug, Clone, Copy, Dupe, Allocative)]
+pub(crate) enum ArchiveFormat {
+    TarZst,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub(crate) fn parse(format: &str) -> anyhow::Result<Self> {
+        match format {
+            "tar.zst" => Ok(Self::TarZst),
+            "zip" => Ok(Self::Zip),
+            _ => Err(ArchiveError::UnknownFormat(format.to_owned()).into()),
+        }
+    }
+
+    /// The format of an archive named `path`, based on its extension.
+    pub(crate) fn from_path(path: &str) -> anyhow::Result<Self> {
+        if path.ends_with(".tar.zst") || path.ends_with(".tzst") {
+            Ok(Self::TarZst)
+        } else if path.ends_with(".zip") {
+            Ok(Self::Zip)
+        } else {
+            Err(ArchiveError::CannotInferFormat(path.to_owned()).into())
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::TarZst => "tar.zst",
+            Self::Zip => "zip",
+        }
+    }
+}
+
+#[derive(Debug, Allocative)]
+pub(crate) enum ArchiveOperation {
+    /// Pack the input (a file or a directory) into an archive.
+    Create,
+    /// Unpack the input archive into a directory.
+    Extract,
+}
+
+impl ArchiveOperation {
+    /// The subcommand of the archiver performing this operation.
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Extract => "extract",
+        }
+    }
+}
+
+#[derive(Debug, Allocative)]
+pub(crate) struct UnregisteredArchiveAction {
+    operation: ArchiveOperation,
+    format: ArchiveFormat,
+    /// Whether the result may be uploaded to the cache, like `allow_cache_upload` of `run`.
+    allow_cache_upload: bool,
+}
+
+impl UnregisteredArchiveAction {
+    pub(crate) fn new(
+        operation: ArchiveOperation,
+        format: ArchiveFormat,
+        allow_cache_upload: bool,
+    ) -> Self {
+        Self {
+            operation,
+            format,
+            allow_cache_upload,
+        }
+    }
+}
+
+impl UnregisteredAction for UnregisteredArchiveAction {
+    fn register(
+        self: Box<Self>,
+        inputs: IndexSet<ArtifactGroup>,
+        outputs: IndexSet<BuildArtifact>,
+        starlark_data: Option<OwnedFrozenValue>,
+        _error_handler: Option<OwnedFrozenValue>,
+    ) -> anyhow::Result<Box<dyn Action>> {
+        let archiver = starlark_data.internal_error("module data to be present")?;
+        Ok(Box::new(ArchiveAction::new(
+            *self, archiver, inputs, outputs,
+        )?))
+    }
+}
+
+/// Creates or extracts an archive by running the archiver tool (`buck2_archive`) as a regular
+/// command, so that it can run remotely and be cached like any other action. The archiver
+/// doesn't depend on the `tar` or `zip` installed on the machine executing it, nor on file
+/// modification times or directory listing order: entries are written in path order, with a
+/// fixed modification time and owner, and permissions that only depend on whether the file is
+/// executable.
+#[derive(Debug, Allocative)]
+struct ArchiveAction {
+    inner: UnregisteredArchiveAction,
+    /// The archiver command line, as `StarlarkCmdArgs`.
+    archiver: OwnedFrozenValue,
+    /// The artifact to archive or extract, followed by the inputs of the archiver.
+    inputs: BoxSliceSet<ArtifactGroup>,
+    outputs: BoxSliceSet<BuildArtifact>,
+}
+
+impl ArchiveAction {
+    fn new(
+        inner: UnregisteredArchiveAction,
+        archiver: OwnedFrozenValue,
+        inputs: IndexSet<ArtifactGroup>,
+        outputs: IndexSet<BuildArtifact>,
+    ) -> anyhow::Result<Self> {
+        match inputs.first() {
+            Some(ArtifactGroup::Artifact(..) | ArtifactGroup::Promise(..)) => {}
+            Some(other) => {
+                return Err(ArchiveActionValidationError::UnsupportedInput(other.dupe()).into());
+            }
+            None => return Err(ArchiveActionValidationError::NoSource.into()),
+        };
+
+        if outputs.len() != 1 {
+            return Err(ArchiveActionValidationError::WrongNumberOfOutputs(outputs.len()).into());
+        }
+
+        Ok(ArchiveAction {
+            inner,
+            archiver,
+            inputs: BoxSliceSet::from(inputs),
+            outputs: BoxSliceSet::from(outputs),
+        })
+    }
+
+    fn src(&self) -> &ArtifactGroup {
+        self.inputs
+            .iter()
+            .next()
+            .expect("a source input by construction")
+    }
+
+    fn output(&self) -> &BuildArtifact {
+        self.outputs
+            .iter()
+            .next()
+            .expect("a single artifact by construction")
+    }
+
+    fn archiver(&self) -> anyhow::Result<&dyn CommandLineArgLike> {
+        Ok(ValueAsCommandLineLike::unpack_value_err(self.archiver.value())?.0)
+    }
+
+    /// The archiver, and its arguments: `create|extract --format <format> <src> <output>`.
+    fn command_line(
+        &self,
+        ctx: &dyn ActionExecutionCtx,
+    ) -> anyhow::Result<(Vec<String>, Vec<String>)> {
+        let executor_fs = ctx.executor_fs();
+        let mut cli_ctx = DefaultCommandLineContext::new(&executor_fs);
+
+        let mut exe = Vec::new();
+        self.archiver()?
+            .add_to_command_line(&mut exe, &mut cli_ctx)?;
+
+        let (src, _) = ctx
+            .artifact_values(self.src())
+            .iter()
+            .into_singleton()
+            .context("Input did not dereference to exactly one artifact")?;
+        let dest = ctx.fs().resolve_build(self.output().get_path());
+        let args = vec![
+            self.inner.operation.as_str().to_owned(),
+            "--format".to_owned(),
+            self.inner.format.as_str().to_owned(),
+            cli_ctx.resolve_artifact(src)?.into_string(),
+            cli_ctx.resolve_project_path(dest)?.into_string(),
+        ];
+        Ok((exe, args))
+    }
+}
+
+#[async_trait]
+impl Action for ArchiveAction {
+    fn kind(&self) -> buck2_data::ActionKind {
+        match self.inner.operation {
+            ArchiveOperation::Create => buck2_data::ActionKind::Archive,
+            ArchiveOperation::Extract => buck2_data::ActionKind::Extract,
+        }
+    }
+
+    fn inputs(&self) -> anyhow::Result<Cow<'_, [ArtifactGroup]>> {
+        Ok(Cow::Borrowed(self.inputs.as_slice()))
+    }
+
+    fn outputs(&self) -> anyhow::Result<Cow<'_, [BuildArtifact]>> {
+        Ok(Cow::Borrowed(self.outputs.as_slice()))
+    }
+
+    fn as_executable(&self) -> ActionExecutable<'_> {
+        ActionExecutable::Incremental(self)
+    }
+
+    fn category(&self) -> &Category {
+        static ARCHIVE_CATEGORY: Lazy<Category> =
+            Lazy::new(|| Category::try_from("archive").unwrap());
+        static EXTRACT_CATEGORY: Lazy<Category> =
+            Lazy::new(|| Category::try_from("extract").unwrap());
+
+        match self.inner.operation {
+            ArchiveOperation::Create => &ARCHIVE_CATEGORY,
+            ArchiveOperation::Extract => &EXTRACT_CATEGORY,
+        }
+    }
+
+    fn identifier(&self) -> Option<&str> {
+        Some(self.output().get_path().path().as_str())
+    }
+
+    fn aquery_attributes(&self, fs: &ExecutorFs) -> IndexMap<String, String> {
+        let mut archiver = Vec::<String>::new();
+        self.archiver()
+            .unwrap()
+            .add_to_command_line(&mut archiver, &mut DefaultCommandLineContext::new(fs))
+            .unwrap();
+        indexmap! {
+            "format".to_owned() => self.inner.format.as_str().to_owned(),
+            "archiver".to_owned() => format!("[{}]", archiver.iter().join(", ")),
+            "allow_cache_upload".to_owned() => self.inner.allow_cache_upload.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl IncrementalActionExecutable for ArchiveAction {
+    async fn execute(
+        &self,
+        ctx: &mut dyn ActionExecutionCtx,
+    ) -> Result<(ActionOutputs, ActionExecutionMetadata), ExecuteError> {
+        let (exe, args) = self.command_line(ctx)?;
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|group| {
+                CommandExecutionInput::Artifact(Box::new(ctx.artifact_values(group).dupe()))
+            })
+            .collect();
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|b| CommandExecutionOutput::BuildArtifact {
+                path: b.get_path().dupe(),
+                output_type: b.output_type(),
+            })
+            .collect();
+        let req = CommandExecutionRequest::new(
+            exe,
+            args,
+            CommandExecutionPaths::new(inputs, outputs, ctx.fs(), ctx.digest_config())?,
+            SortedVectorMap::new(),
+        )
+        .with_allow_cache_upload(self.inner.allow_cache_upload);
+
+        let prepared_action = ctx.prepare_action(&req)?;
+        let manager = ctx.command_execution_manager();
+        let mut result = match ctx.action_cache(manager, &req, &prepared_action).await {
+            ControlFlow::Break(result) => result,
+            ControlFlow::Continue(manager) => {
+                ctx.exec_cmd(manager, &req, &prepared_action, None).await
+            }
+        };
+
+        if result.was_success() && self.inner.allow_cache_upload {
+            let upload_result = ctx
+                .cache_upload(&prepared_action.action_and_blobs, &result, None)
+                .await?;
+            result.did_cache_upload = upload_result.did_cache_upload;
+        }
+
+        ctx.unpack_command_execution_result(&req, result, true, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_path() {
+        assert!(matches!(
+            ArchiveFormat::from_path("foo.tar.zst").unwrap(),
+            ArchiveFormat::TarZst
+        ));
+        assert!(matches!(
+            ArchiveFormat::from_path("foo.tzst").unwrap(),
+            ArchiveFormat::TarZst
+        ));
+        assert!(matches!(
+            ArchiveFormat::from_path("dir/foo.zip").unwrap(),
+            ArchiveFormat::Zip
+        ));
+        assert!(ArchiveFormat::from_path("foo.tar.gz").is_err());
+        assert!(ArchiveFormat::parse("tgz").is_err());
+    }
+}
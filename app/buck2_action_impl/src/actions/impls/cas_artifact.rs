@@ -204,6 +204,18 @@ impl IncrementalActionExecutable for CasArtifactAction {
             return self.execute_for_offline(ctx).await.map_err(Into::into);
         }
 
+        // Without network access, the artifact can only come from the offline cache.
+        if ctx.run_action_knobs().offline {
+            if offline::has_offline_cache_entry(ctx, &self.output)? {
+                return self.execute_for_offline(ctx).await.map_err(Into::into);
+            }
+            return Err(anyhow::Error::from(offline::OfflineError::CasArtifact {
+                digest: self.inner.digest.dupe(),
+                path: ctx.fs().resolve_build(self.output.get_path()),
+            })
+            .into());
+        }
+
         let expiration = ctx
             .re_client()
             .get_digest_expirations(vec![self.inner.digest.to_re()], self.inner.re_use_case)
@@ -25,12 +25,21 @@ use buck2_build_api::actions::IncrementalActionExecutable;
 use buck2_build_api::actions::UnregisteredAction;
 use buck2_build_api::artifact_groups::ArtifactGroup;
 use buck2_core::category::Category;
+use buck2_core::directory::Directory;
+use buck2_core::directory::DirectoryEntry;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_execute::artifact::artifact_dyn::ArtifactDyn;
 use buck2_execute::artifact_utils::ArtifactValueBuilder;
+use buck2_execute::artifact_value::ArtifactValue;
 use buck2_execute::execute::command_executor::ActionExecutionTimingData;
 use buck2_execute::materialize::materializer::CopiedArtifact;
 use dupe::Dupe;
+use dupe::OptionDupedExt;
 use gazebo::prelude::*;
+use globset::GlobBuilder;
+use globset::GlobSet;
+use globset::GlobSetBuilder;
 use indexmap::IndexSet;
 use once_cell::sync::Lazy;
 use starlark::values::OwnedFrozenValue;
@@ -43,6 +52,14 @@ enum CopyActionValidationError {
     WrongNumberOfOutputs(usize),
     #[error("Only artifact inputs are supported in copy actions, got {0}")]
     UnsupportedInput(ArtifactGroup),
+    #[error("Invalid glob `{0}`")]
+    InvalidGlob(String),
+}
+
+#[derive(Debug, buck2_error::Error)]
+enum CopyActionExecutionError {
+    #[error("`include` and `exclude` can only be used to copy a directory, but `{0}` is not one")]
+    FilterOnNonDirectory(String),
 }
 
 #[derive(Debug, Allocative)]
@@ -51,14 +68,53 @@ pub(crate) enum CopyMode {
     Symlink,
 }
 
+/// Restricts a directory copy to the files whose path relative to the directory matches
+/// `include` (if set) and doesn't match `exclude`.
+#[derive(Debug, Allocative)]
+pub(crate) struct CopyFilter {
+    #[allocative(skip)]
+    include: Option<GlobSet>,
+    #[allocative(skip)]
+    exclude: GlobSet,
+}
+
+impl CopyFilter {
+    pub(crate) fn new(include: Option<&[String]>, exclude: &[String]) -> anyhow::Result<Self> {
+        Ok(Self {
+            include: include.map(glob_set).transpose()?,
+            exclude: glob_set(exclude)?,
+        })
+    }
+
+    fn matches(&self, path: &ForwardRelativePath) -> bool {
+        let path = path.as_str();
+        self.include.as_ref().map_or(true, |i| i.is_match(path)) && !self.exclude.is_match(path)
+    }
+}
+
+/// Like Buck globs, `*` doesn't match `/` but `**` does.
+fn glob_set(patterns: &[String]) -> anyhow::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(
+            GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .with_context(|| CopyActionValidationError::InvalidGlob(pattern.clone()))?,
+        );
+    }
+    Ok(builder.build()?)
+}
+
 #[derive(Allocative)]
 pub(crate) struct UnregisteredCopyAction {
     copy: CopyMode,
+    filter: Option<CopyFilter>,
 }
 
 impl UnregisteredCopyAction {
-    pub(crate) fn new(copy: CopyMode) -> Self {
-        Self { copy }
+    pub(crate) fn new(copy: CopyMode, filter: Option<CopyFilter>) -> Self {
+        Self { copy, filter }
     }
 }
 
@@ -70,13 +126,19 @@ impl UnregisteredAction for UnregisteredCopyAction {
         _starlark_data: Option<OwnedFrozenValue>,
         _error_handler: Option<OwnedFrozenValue>,
     ) -> anyhow::Result<Box<dyn Action>> {
-        Ok(Box::new(CopyAction::new(self.copy, inputs, outputs)?))
+        Ok(Box::new(CopyAction::new(
+            self.copy,
+            self.filter,
+            inputs,
+            outputs,
+        )?))
     }
 }
 
 #[derive(Debug, Allocative)]
 struct CopyAction {
     copy: CopyMode,
+    filter: Option<CopyFilter>,
     inputs: BoxSliceSet<ArtifactGroup>,
     outputs: BoxSliceSet<BuildArtifact>,
 }
@@ -84,6 +146,7 @@ struct CopyAction {
 impl CopyAction {
     fn new(
         copy: CopyMode,
+        filter: Option<CopyFilter>,
         inputs: IndexSet<ArtifactGroup>,
         outputs: IndexSet<BuildArtifact>,
     ) -> anyhow::Result<Self> {
@@ -103,6 +166,7 @@ impl CopyAction {
         } else {
             Ok(CopyAction {
                 copy,
+                filter,
                 inputs: BoxSliceSet::from(inputs),
                 outputs: BoxSliceSet::from(outputs),
             })
@@ -153,6 +217,53 @@ impl Action for CopyAction {
     }
 }
 
+/// Copies (or symlinks) the files of the directory `src_value` matching `filter` one by one, so
+/// that the output only contains them and the directories leading to them.
+fn add_filtered(
+    builder: &mut ArtifactValueBuilder,
+    copy: &CopyMode,
+    filter: &CopyFilter,
+    src_value: &ArtifactValue,
+    src: &ProjectRelativePath,
+    dest: &ProjectRelativePath,
+) -> anyhow::Result<Vec<CopiedArtifact>> {
+    let dir = match src_value.entry() {
+        DirectoryEntry::Dir(dir) => dir,
+        DirectoryEntry::Leaf(_) => {
+            return Err(CopyActionExecutionError::FilterOnNonDirectory(src.to_string()).into());
+        }
+    };
+    let mut copied = Vec::new();
+    let mut walk = dir.unordered_walk();
+    while let Some((path, entry)) = walk.next() {
+        let member = match entry {
+            DirectoryEntry::Leaf(member) => member,
+            DirectoryEntry::Dir(_) => continue,
+        };
+        let path = path.get();
+        if !filter.matches(&path) {
+            continue;
+        }
+        let file_value = ArtifactValue::new(
+            DirectoryEntry::Leaf(member.dupe()),
+            src_value.deps().duped(),
+        );
+        let file_src = src.join(&path);
+        let file_dest = dest.join(&path);
+        // Symlinks are declared to the materializer like copies, as in the unfiltered case.
+        let entry = match copy {
+            CopyMode::Copy => builder.add_copied(&file_value, &file_src, &file_dest)?,
+            CopyMode::Symlink => builder.add_symlinked(&file_value, &file_src, &file_dest)?,
+        };
+        copied.push(CopiedArtifact::new(
+            file_src,
+            file_dest,
+            entry.map_dir(|d| d.as_immutable()),
+        ));
+    }
+    Ok(copied)
+}
+
 #[async_trait]
 impl IncrementalActionExecutable for CopyAction {
     async fn execute(
@@ -169,35 +280,38 @@ impl IncrementalActionExecutable for CopyAction {
         let src = input.resolve_path(artifact_fs)?;
         let dest = artifact_fs.resolve_build(self.output().get_path());
 
-        let value = {
-            let fs = artifact_fs.fs();
-            let mut builder = ArtifactValueBuilder::new(fs, ctx.digest_config());
-            match self.copy {
-                CopyMode::Copy => {
-                    builder.add_copied(src_value, src.as_ref(), dest.as_ref())?;
-                }
-                CopyMode::Symlink => {
-                    builder.add_symlinked(src_value, src.as_ref(), dest.as_ref())?;
+        let fs = artifact_fs.fs();
+        let mut builder = ArtifactValueBuilder::new(fs, ctx.digest_config());
+        let (value, copied) = match &self.filter {
+            None => {
+                match self.copy {
+                    CopyMode::Copy => {
+                        builder.add_copied(src_value, src.as_ref(), dest.as_ref())?;
+                    }
+                    CopyMode::Symlink => {
+                        builder.add_symlinked(src_value, src.as_ref(), dest.as_ref())?;
+                    }
                 }
-            }
-
-            builder.build(dest.as_ref())?
-        };
-
-        ctx.materializer()
-            .declare_copy(
-                dest.clone(),
-                value.dupe(),
+                let value = builder.build(dest.as_ref())?;
                 // FIXME(JakobDegen): This is wrong in cases where the input artifact is a source
                 // directory with ignored paths, as the materializer will incorrectly assume that
                 // the source directory matches the artifact value when it doesn't.
-                vec![CopiedArtifact::new(
+                let copied = vec![CopiedArtifact::new(
                     src,
-                    dest,
+                    dest.clone(),
                     value.entry().dupe().map_dir(|d| d.as_immutable()),
-                )],
-                ctx.cancellation_context(),
-            )
+                )];
+                (value, copied)
+            }
+            Some(filter) => {
+                let copied =
+                    add_filtered(&mut builder, &self.copy, filter, src_value, &src, &dest)?;
+                (builder.build(dest.as_ref())?, copied)
+            }
+        };
+
+        ctx.materializer()
+            .declare_copy(dest, value.dupe(), copied, ctx.cancellation_context())
             .await?;
 
         Ok((
@@ -212,8 +326,87 @@ impl IncrementalActionExecutable for CopyAction {
 
 #[cfg(test)]
 mod tests {
+    use buck2_core::directory::DirectoryEntry;
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_core::fs::project_rel_path::ProjectRelativePath;
+    use buck2_execute::artifact_utils::ArtifactValueBuilder;
+    use buck2_execute::artifact_value::ArtifactValue;
+    use buck2_execute::digest_config::DigestConfig;
+    use buck2_execute::directory::ActionDirectoryBuilder;
+    use buck2_execute::directory::ActionDirectoryMember;
+    use buck2_execute::directory::INTERNER;
+
+    use crate::actions::impls::copy::add_filtered;
+    use crate::actions::impls::copy::CopyFilter;
+    use crate::actions::impls::copy::CopyMode;
+
     // TODO: This needs proper tests, but right now it's kind of a pain to get the
     //       action framework up and running to test actions
     #[test]
     fn copies_file() {}
+
+    #[test]
+    fn test_copy_filter() -> anyhow::Result<()> {
+        let path = |p| ForwardRelativePath::new(p).unwrap();
+
+        let filter = CopyFilter::new(
+            Some(&["*.h".to_owned(), "include/**".to_owned()]),
+            &["**/internal_*".to_owned()],
+        )?;
+        assert!(filter.matches(path("foo.h")));
+        assert!(!filter.matches(path("sub/foo.h")));
+        assert!(filter.matches(path("include/a/b.c")));
+        assert!(!filter.matches(path("include/a/internal_b.h")));
+        assert!(!filter.matches(path("foo.c")));
+
+        let filter = CopyFilter::new(None, &["*.o".to_owned()])?;
+        assert!(filter.matches(path("foo.c")));
+        assert!(!filter.matches(path("foo.o")));
+
+        assert!(CopyFilter::new(Some(&["a[".to_owned()]), &[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_filtered_symlink() -> anyhow::Result<()> {
+        let path = |p| ProjectRelativePath::new(p).unwrap();
+        let fs = ProjectRootTemp::new()?;
+        let digest_config = DigestConfig::testing_default();
+
+        let mut dir = ActionDirectoryBuilder::empty();
+        for file in ["a.h", "a.c"] {
+            dir.insert(
+                ForwardRelativePath::new(file)?,
+                DirectoryEntry::Leaf(ActionDirectoryMember::File(digest_config.empty_file())),
+            )?;
+        }
+        let src_value = ArtifactValue::dir(
+            dir.fingerprint(digest_config.as_directory_serializer())
+                .shared(&*INTERNER),
+        );
+
+        let filter = CopyFilter::new(Some(&["*.h".to_owned()]), &[])?;
+        let mut builder = ArtifactValueBuilder::new(fs.path(), digest_config);
+        let copied = add_filtered(
+            &mut builder,
+            &CopyMode::Symlink,
+            &filter,
+            &src_value,
+            path("src"),
+            path("out/dest"),
+        )?;
+
+        // The filtered symlinks must be declared to the materializer, or they are never created.
+        assert_eq!(1, copied.len());
+        assert_eq!(path("src/a.h"), copied[0].src.as_ref());
+        assert_eq!(path("out/dest/a.h"), copied[0].dest.as_ref());
+        match copied[0].dest_entry.as_ref() {
+            DirectoryEntry::Leaf(ActionDirectoryMember::Symlink(s)) => {
+                assert_eq!("../../src/a.h", s.target().as_str())
+            }
+            _ => panic!("Expected a symlink"),
+        }
+        Ok(())
+    }
 }
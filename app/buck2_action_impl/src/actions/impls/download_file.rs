@@ -30,11 +30,14 @@ use buck2_common::file_ops::FileMetadata;
 use buck2_common::file_ops::TrackedFileDigest;
 use buck2_common::io::trace::TracingIoProvider;
 use buck2_core::category::Category;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_execute::artifact_value::ArtifactValue;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::execute::command_executor::ActionExecutionTimingData;
 use buck2_execute::materialize::http::http_download;
 use buck2_execute::materialize::http::http_head;
+use buck2_execute::materialize::http::verify_existing_download;
 use buck2_execute::materialize::http::Checksum;
 use buck2_execute::materialize::materializer::HttpDownloadInfo;
 use buck2_http::HttpClient;
@@ -220,6 +223,74 @@ impl DownloadFileAction {
             },
         ))
     }
+
+    /// Execute this action without network access, reusing the file from the offline cache or
+    /// from an earlier download if it is still in buck-out.
+    async fn execute_without_network(
+        &self,
+        ctx: &mut dyn ActionExecutionCtx,
+    ) -> anyhow::Result<(ActionOutputs, ActionExecutionMetadata)> {
+        if offline::has_offline_cache_entry(ctx, self.output())? {
+            return self.execute_for_offline(ctx).await;
+        }
+
+        let artifact_fs = ctx.fs();
+        let project_fs = artifact_fs.fs();
+        let rel_path = artifact_fs.resolve_build(self.output().get_path());
+        let digest_config = ctx.digest_config();
+
+        let metadata = ctx
+            .blocking_executor()
+            .execute_io_inline(|| {
+                reuse_existing_download(
+                    project_fs,
+                    digest_config,
+                    &rel_path,
+                    &self.inner.url,
+                    &self.inner.checksum,
+                    self.inner.is_executable,
+                )
+            })
+            .await?;
+        ctx.materializer()
+            .declare_existing(vec![(rel_path, ArtifactValue::file(metadata.dupe()))])
+            .await?;
+
+        Ok((
+            ActionOutputs::from_single(
+                self.output().get_path().dupe(),
+                ArtifactValue::file(metadata),
+            ),
+            ActionExecutionMetadata {
+                execution_kind: ActionExecutionKind::Simple,
+                timing: ActionExecutionTimingData::default(),
+            },
+        ))
+    }
+}
+
+/// Reuses the file an earlier download left at `path`, if it still matches `checksum`.
+fn reuse_existing_download(
+    fs: &ProjectRoot,
+    digest_config: DigestConfig,
+    path: &ProjectRelativePath,
+    url: &str,
+    checksum: &Checksum,
+    is_executable: bool,
+) -> anyhow::Result<FileMetadata> {
+    let digest = verify_existing_download(fs, digest_config, path, checksum)?.ok_or_else(|| {
+        offline::OfflineError::Download {
+            url: url.to_owned(),
+            path: path.to_buf(),
+        }
+    })?;
+    if is_executable {
+        fs.set_executable(path)?;
+    }
+    Ok(FileMetadata {
+        digest,
+        is_executable,
+    })
 }
 
 #[async_trait]
@@ -270,6 +341,10 @@ impl IncrementalActionExecutable for DownloadFileAction {
             return self.execute_for_offline(ctx).await.map_err(Into::into);
         }
 
+        if ctx.run_action_knobs().offline {
+            return self.execute_without_network(ctx).await.map_err(Into::into);
+        }
+
         let client = ctx.http_client();
         let url = self.url(&client);
 
@@ -349,8 +424,57 @@ impl IncrementalActionExecutable for DownloadFileAction {
 
 #[cfg(test)]
 mod tests {
+    use buck2_core::fs::project::ProjectRootTemp;
+
+    use super::*;
+
     // TODO: This needs proper tests, but right now it's kind of a pain to get the
     //       action framework up and running to test actions
     #[test]
     fn downloads_file() {}
+
+    #[test]
+    fn test_reuse_existing_download() -> anyhow::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let digest_config = DigestConfig::testing_default();
+        let path = ProjectRelativePath::new("buck-out/v2/gen/foo/download")?;
+        let checksum = Checksum::new(
+            None,
+            Some("c3ab8ff13720e8ad9047dd39466b3c8974e592c2fa383d4a3960714caef0c4f2"),
+        )?;
+        let reuse = || {
+            reuse_existing_download(
+                temp.path(),
+                digest_config,
+                path,
+                "https://example.com/foobar",
+                &checksum,
+                true,
+            )
+        };
+
+        let err = reuse().unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Cannot download `https://example.com/foobar` while offline"),
+            "{}",
+            err
+        );
+        let err = buck2_error::Error::from(err);
+        assert_eq!(Some(buck2_error::Tier::Input), err.get_tier());
+        assert!(err.tags().contains(&buck2_error::ErrorTag::Offline));
+
+        temp.path().write_file(path, "foobar", false)?;
+        let metadata = reuse()?;
+        assert_eq!(
+            TrackedFileDigest::from_content(b"foobar", digest_config.cas_digest_config()),
+            metadata.digest
+        );
+        assert!(metadata.is_executable);
+
+        // Left by a download of another version of the file.
+        temp.path().write_file(path, "foobaz", false)?;
+        assert!(reuse().is_err());
+        Ok(())
+    }
 }
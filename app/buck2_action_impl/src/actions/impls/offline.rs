@@ -10,7 +10,9 @@
 use buck2_artifact::artifact::build_artifact::BuildArtifact;
 use buck2_build_api::actions::execute::action_executor::ActionOutputs;
 use buck2_build_api::actions::ActionExecutionCtx;
+use buck2_common::file_ops::FileDigest;
 use buck2_common::file_ops::FileDigestConfig;
+use buck2_core::fs::fs_util;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_execute::artifact_value::ArtifactValue;
 use buck2_execute::directory::INTERNER;
@@ -18,6 +20,41 @@ use buck2_execute::entry::build_entry_from_disk;
 use buck2_execute::materialize::materializer::CopiedArtifact;
 use dupe::Dupe;
 
+/// Errors for network actions which can't reuse an earlier output in offline mode.
+#[derive(Debug, buck2_error::Error)]
+pub(crate) enum OfflineError {
+    #[error(
+        "Cannot download `{url}` while offline: `{path}` was not downloaded by an earlier build, and is not in the offline cache"
+    )]
+    #[buck2(input, tag = Offline)]
+    Download {
+        url: String,
+        path: ProjectRelativePathBuf,
+    },
+    #[error(
+        "Cannot fetch `{digest}` from the CAS while offline: `{path}` is not in the offline cache"
+    )]
+    #[buck2(input, tag = Offline)]
+    CasArtifact {
+        digest: FileDigest,
+        path: ProjectRelativePathBuf,
+    },
+}
+
+/// Whether the output BuildArtifact was copied to the offline cache by an
+/// earlier build, so that `declare_copy_from_offline_cache` can restore it.
+pub(crate) fn has_offline_cache_entry(
+    ctx: &dyn ActionExecutionCtx,
+    output: &BuildArtifact,
+) -> anyhow::Result<bool> {
+    let offline_cache_path = ctx
+        .fs()
+        .resolve_offline_output_cache_path(output.get_path());
+    Ok(fs_util::try_exists(
+        ctx.fs().fs().resolve(&offline_cache_path),
+    )?)
+}
+
 /// Declares a copy materialization to copy the output BuildArtifact to the
 /// offline cache for use in an offline build. Returns the project-relative path
 /// to the offline cached file.
@@ -86,3 +123,31 @@ async fn declare_copy_materialization(
         )
         .await
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_common::cas_digest::testing;
+
+    use super::*;
+
+    #[test]
+    fn test_offline_error() {
+        let digest = FileDigest::from_content(b"foo", testing::sha1());
+        let err = OfflineError::CasArtifact {
+            digest: digest.dupe(),
+            path: ProjectRelativePathBuf::unchecked_new("buck-out/v2/gen/foo/artifact".to_owned()),
+        };
+        assert_eq!(
+            format!(
+                "Cannot fetch `{}` from the CAS while offline: `buck-out/v2/gen/foo/artifact` is not in the offline cache",
+                digest
+            ),
+            err.to_string()
+        );
+
+        // Offline failures are the user's to fix, e.g. by building once with network access.
+        let err = buck2_error::Error::from(err);
+        assert_eq!(Some(buck2_error::Tier::Input), err.get_tier());
+        assert!(err.tags().contains(&buck2_error::ErrorTag::Offline));
+    }
+}
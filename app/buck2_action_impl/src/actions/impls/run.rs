@@ -10,6 +10,8 @@
 use std::borrow::Cow;
 use std::fmt::Display;
 use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::time::Duration;
 
 use allocative::Allocative;
 use anyhow::Context;
@@ -19,6 +21,7 @@ use buck2_build_api::actions::box_slice_set::BoxSliceSet;
 use buck2_build_api::actions::execute::action_executor::ActionExecutionMetadata;
 use buck2_build_api::actions::execute::action_executor::ActionOutputs;
 use buck2_build_api::actions::execute::error::ExecuteError;
+use buck2_build_api::actions::impls::action_retry_policy::ActionRetryPolicy;
 use buck2_build_api::actions::impls::expanded_command_line::ExpandedCommandLine;
 use buck2_build_api::actions::Action;
 use buck2_build_api::actions::ActionExecutable;
@@ -35,16 +38,20 @@ use buck2_build_api::interpreter::rule_defs::cmd_args::CommandLineContext;
 use buck2_build_api::interpreter::rule_defs::cmd_args::DefaultCommandLineContext;
 use buck2_build_api::interpreter::rule_defs::cmd_args::SimpleCommandLineArtifactVisitor;
 use buck2_build_api::interpreter::rule_defs::provider::builtin::worker_info::WorkerInfo;
+use buck2_common::cas_digest::CasDigestData;
 use buck2_core::category::Category;
+use buck2_core::directory::FingerprintedDirectory;
 use buck2_core::execution_types::executor_config::RemoteExecutorDependency;
 use buck2_core::fs::buck_out_path::BuckOutPath;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
 use buck2_error::BuckErrorContext;
 use buck2_events::dispatch::span_async;
 use buck2_execute::artifact::fs::ExecutorFs;
+use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::execute::action_digest::ActionDigest;
 use buck2_execute::execute::cache_uploader::force_cache_upload;
 use buck2_execute::execute::environment_inheritance::EnvironmentInheritance;
+use buck2_execute::execute::inputs_directory::inputs_directory;
 use buck2_execute::execute::manager::CommandExecutionManager;
 use buck2_execute::execute::request::ActionMetadataBlob;
 use buck2_execute::execute::request::CommandExecutionInput;
@@ -81,16 +88,22 @@ use starlark::values::UnpackValue;
 use starlark::values::ValueLifetimeless;
 use starlark::values::ValueLike;
 use starlark::values::ValueOf;
+use starlark_map::sorted_map::SortedMap;
 
 use self::dep_files::DepFileBundle;
 use crate::actions::impls::run::dep_files::make_dep_file_bundle;
 use crate::actions::impls::run::dep_files::populate_dep_files;
 use crate::actions::impls::run::dep_files::DepFilesCommandLineVisitor;
 use crate::actions::impls::run::dep_files::RunActionDepFiles;
+use crate::actions::impls::run::hermeticity::validate_command_line;
+use crate::actions::impls::run::hermeticity::DeclaredPaths;
+use crate::actions::impls::run::input_budget::check_input_size_budget;
 use crate::actions::impls::run::metadata::metadata_content;
 
 pub(crate) mod audit_dep_files;
 pub(crate) mod dep_files;
+mod hermeticity;
+pub(crate) mod input_budget;
 mod metadata;
 
 #[derive(Debug, buck2_error::Error)]
@@ -164,6 +177,12 @@ pub(crate) struct UnregisteredRunAction {
     pub(crate) executor_preference: ExecutorPreference,
     pub(crate) always_print_stderr: bool,
     pub(crate) weight: WeightClass,
+    pub(crate) memory_mb: Option<usize>,
+    pub(crate) timeout: Option<Duration>,
+    #[allocative(skip)]
+    pub(crate) retry_policy: Option<Arc<ActionRetryPolicy>>,
+    pub(crate) clean_env: Option<bool>,
+    pub(crate) env_allowlist: Vec<String>,
     pub(crate) low_pass_filter: bool,
     pub(crate) dep_files: RunActionDepFiles,
     pub(crate) metadata_param: Option<MetadataParameter>,
@@ -171,8 +190,12 @@ pub(crate) struct UnregisteredRunAction {
     pub(crate) allow_cache_upload: bool,
     pub(crate) allow_dep_file_cache_upload: bool,
     pub(crate) force_full_hybrid_if_capable: bool,
+    pub(crate) remote_cache_read_only: bool,
     pub(crate) unique_input_inodes: bool,
     pub(crate) remote_execution_dependencies: Vec<RemoteExecutorDependency>,
+    pub(crate) remote_execution_properties: SortedMap<String, String>,
+    /// The budget for the total size of the inputs, from `build.action_input_mebibyte_budgets`.
+    pub(crate) max_input_bytes: Option<u64>,
 }
 
 impl UnregisteredAction for UnregisteredRunAction {
@@ -227,6 +250,7 @@ struct UnpackedWorkerValues<'v> {
     exe: &'v dyn CommandLineArgLike,
     id: WorkerId,
     concurrency: Option<usize>,
+    remote: bool,
 }
 
 struct UnpackedRunActionValues<'v> {
@@ -269,6 +293,7 @@ impl RunAction {
             exe: worker.exe_command_line(),
             id: WorkerId(worker.id),
             concurrency: worker.concurrency(),
+            remote: worker.remote(),
         });
 
         Ok(UnpackedRunActionValues {
@@ -304,6 +329,7 @@ impl RunAction {
                 exe: worker_rendered,
                 id: worker.id,
                 concurrency: worker.concurrency,
+                remote_key: None,
             })
         } else {
             None
@@ -374,8 +400,13 @@ impl RunAction {
         let executor_fs = ctx.executor_fs();
         let fs = executor_fs.fs();
 
-        let (expanded, worker) =
+        let (expanded, mut worker) =
             self.expand_command_line_and_worker(&ctx.executor_fs(), visitor)?;
+        if let Some(worker) = &mut worker {
+            if ctx.uses_remote_persistent_workers() {
+                worker.remote_key = self.worker_remote_key(worker, ctx)?;
+            }
+        }
 
         // TODO (@torozco): At this point, might as well just receive the list already. Finding
         // those things in a HashMap is just not very useful.
@@ -400,7 +431,12 @@ impl RunAction {
             let env = cli_ctx
                 .resolve_project_path(fs.buck_out_path_resolver().resolve_gen(&path))?
                 .into_string();
-            let (data, digest) = metadata_content(fs, &artifact_inputs, ctx.digest_config())?;
+            let (data, digest) = metadata_content(
+                fs,
+                &artifact_inputs,
+                self.outputs.as_slice(),
+                ctx.digest_config(),
+            )?;
             inputs.push(CommandExecutionInput::ActionMetadata(ActionMetadataBlob {
                 data,
                 digest,
@@ -417,6 +453,37 @@ impl RunAction {
         ));
         inputs.push(CommandExecutionInput::ScratchPath(scratch));
 
+        if self.clean_env(ctx) {
+            // Pass the allowlisted variables explicitly rather than letting the command inherit
+            // them, so that their values are part of the action digest.
+            let knobs = ctx.run_action_knobs();
+            let allowed = allowlisted_env(
+                knobs.env_allowlist.iter().chain(&self.inner.env_allowlist),
+                |var| expanded.env.contains_key(var) || extra_env.iter().any(|(k, _)| k == var),
+                |var| std::env::var(var).ok(),
+            );
+            extra_env.extend(allowed);
+        }
+
+        if ctx.run_action_knobs().validate_command_lines {
+            let mut declared_inputs = Vec::new();
+            for group in &artifact_inputs {
+                for (artifact, _) in group.iter() {
+                    declared_inputs.push(artifact.get_path().resolve(fs)?);
+                }
+            }
+            let declared_outputs = self
+                .outputs
+                .iter()
+                .map(|b| (fs.resolve_build(b.get_path()), b.get_path().path()));
+            validate_command_line(
+                &expanded,
+                worker.as_ref(),
+                &DeclaredPaths::new(declared_inputs, declared_outputs),
+                fs,
+            )?;
+        }
+
         let paths = CommandExecutionPaths::new(
             inputs,
             self.outputs
@@ -429,6 +496,14 @@ impl RunAction {
             ctx.fs(),
             ctx.digest_config(),
         )?;
+        if let Some(max_input_bytes) = self.inner.max_input_bytes {
+            check_input_size_budget(
+                self.inner.category.as_str(),
+                max_input_bytes,
+                paths.input_files_bytes(),
+                &artifact_inputs,
+            )?;
+        }
 
         Ok(PreparedRunAction {
             expanded,
@@ -438,6 +513,45 @@ impl RunAction {
         })
     }
 
+    /// Identifies the workers which RE may reuse for this action, if the worker allows running on
+    /// RE: those spawned from the same command line, with the same inputs.
+    fn worker_remote_key(
+        &self,
+        worker: &WorkerSpec,
+        ctx: &dyn ActionExecutionCtx,
+    ) -> anyhow::Result<Option<String>> {
+        let Some(values) = Self::unpack(&self.starlark_values)?.worker else {
+            return Ok(None);
+        };
+        if !values.remote {
+            return Ok(None);
+        }
+
+        let mut visitor = SimpleCommandLineArtifactVisitor::new();
+        values.exe.visit_artifacts(&mut visitor)?;
+        let inputs: Vec<CommandExecutionInput> = visitor
+            .inputs
+            .iter()
+            .map(|group| {
+                CommandExecutionInput::Artifact(Box::new(ctx.artifact_values(group).dupe()))
+            })
+            .collect();
+        let digest_config = ctx.digest_config();
+        let directory = inputs_directory(&inputs, ctx.fs())?
+            .fingerprint(digest_config.as_directory_serializer());
+        Ok(Some(worker_remote_key_digest(
+            &worker.exe,
+            directory.fingerprint().raw_digest().as_bytes(),
+            digest_config,
+        )))
+    }
+
+    fn clean_env(&self, ctx: &dyn ActionExecutionCtx) -> bool {
+        self.inner
+            .clean_env
+            .unwrap_or_else(|| ctx.run_action_knobs().clean_env)
+    }
+
     pub(crate) async fn check_cache_result_is_useable(
         &self,
         ctx: &mut dyn ActionExecutionCtx,
@@ -497,6 +611,41 @@ impl RunAction {
     }
 }
 
+/// The key of a worker on RE: a digest of its command line (with each argument terminated by a NUL
+/// byte, so that `["a b"]` and `["a", "b"]` differ) and of the fingerprint of its inputs.
+fn worker_remote_key_digest(
+    exe: &[String],
+    inputs_fingerprint: &[u8],
+    digest_config: DigestConfig,
+) -> String {
+    let mut digester = CasDigestData::digester(digest_config.cas_digest_config());
+    for arg in exe {
+        digester.update(arg.as_bytes());
+        digester.update(&[0]);
+    }
+    digester.update(inputs_fingerprint);
+    digester.finalize().raw_digest().to_string()
+}
+
+/// The allowlisted variables passed to a command run with a clean environment, with their values
+/// from `lookup`. Variables already set for the command, unset ones and duplicates are skipped.
+fn allowlisted_env<'a>(
+    allowlist: impl IntoIterator<Item = &'a String>,
+    is_set: impl Fn(&str) -> bool,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Vec<(String, String)> {
+    let mut env: Vec<(String, String)> = Vec::new();
+    for var in allowlist {
+        if is_set(var) || env.iter().any(|(k, _)| k == var) {
+            continue;
+        }
+        if let Some(value) = lookup(var) {
+            env.push((var.clone(), value));
+        }
+    }
+    env
+}
+
 pub(crate) struct PreparedRunAction {
     expanded: ExpandedCommandLine,
     extra_env: Vec<(String, String)>,
@@ -598,11 +747,35 @@ impl Action for RunAction {
             .add_to_command_line(&mut cli_rendered, &mut ctx)
             .unwrap();
         let cmd = format!("[{}]", cli_rendered.iter().join(", "));
-        indexmap! {
+        // Commands with the same arguments but a different environment can produce different
+        // outputs, so `audit action-graph-hash`, which hashes these attributes, must cover it.
+        let env = values
+            .env
+            .iter()
+            .map(|(k, v)| {
+                let mut value = String::new();
+                v.add_to_command_line(
+                    &mut SpaceSeparatedCommandLineBuilder::wrap_string(&mut value),
+                    &mut ctx,
+                )
+                .unwrap();
+                format!("{}={}", k, value)
+            })
+            .join(", ");
+        let mut attrs = indexmap! {
             "cmd".to_owned() => cmd,
+            "env".to_owned() => format!("[{}]", env),
             "executor_preference".to_owned() => self.inner.executor_preference.to_string(),
             "always_print_stderr".to_owned() => self.inner.always_print_stderr.to_string(),
             "weight".to_owned() => self.inner.weight.to_string(),
+            "timeout".to_owned() => match self.inner.timeout {
+                None => "None".to_owned(),
+                Some(x) => format!("{}s", x.as_secs()),
+            },
+            "memory_mb".to_owned() => match self.inner.memory_mb {
+                None => "None".to_owned(),
+                Some(x) => x.to_string(),
+            },
             "dep_files".to_owned() => self.inner.dep_files.to_string(),
             "metadata_param".to_owned() => match &self.inner.metadata_param {
                 None => "None".to_owned(),
@@ -611,7 +784,19 @@ impl Action for RunAction {
             "no_outputs_cleanup".to_owned() => self.inner.no_outputs_cleanup.to_string(),
             "allow_cache_upload".to_owned() => self.inner.allow_cache_upload.to_string(),
             "allow_dep_file_cache_upload".to_owned() => self.inner.allow_dep_file_cache_upload.to_string(),
+            "remote_cache_read_only".to_owned() => self.inner.remote_cache_read_only.to_string(),
+            "remote_execution_properties".to_owned() => self
+                .inner
+                .remote_execution_properties
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .join(","),
+        };
+        // Otherwise the action uses the build-wide policy, added by aquery.
+        if let Some(retry_policy) = &self.inner.retry_policy {
+            attrs.insert("retry_policy".to_owned(), retry_policy.to_string());
         }
+        attrs
     }
 
     fn error_handler(&self) -> Option<OwnedFrozenValue> {
@@ -642,17 +827,28 @@ impl IncrementalActionExecutable for RunAction {
         // Run actions are assumed to be shared
         let host_sharing_requirements = HostSharingRequirements::Shared(self.inner.weight);
 
-        let req = prepared_run_action
+        let mut req = prepared_run_action
             .into_command_execution_request()
             .with_prefetch_lossy_stderr(true)
             .with_executor_preference(self.inner.executor_preference)
             .with_host_sharing_requirements(host_sharing_requirements)
+            .with_memory_mb(self.inner.memory_mb)
             .with_low_pass_filter(self.inner.low_pass_filter)
             .with_outputs_cleanup(!self.inner.no_outputs_cleanup)
-            .with_local_environment_inheritance(EnvironmentInheritance::local_command_exclusions())
+            .with_local_environment_inheritance(if self.clean_env(ctx) {
+                EnvironmentInheritance::empty()
+            } else {
+                EnvironmentInheritance::local_command_exclusions()
+            })
             .with_force_full_hybrid_if_capable(self.inner.force_full_hybrid_if_capable)
+            .with_remote_cache_read_only(self.inner.remote_cache_read_only)
+            .with_allow_cache_upload(self.inner.allow_cache_upload || force_cache_upload()?)
             .with_unique_input_inodes(self.inner.unique_input_inodes)
-            .with_remote_execution_dependencies(self.inner.remote_execution_dependencies.clone());
+            .with_remote_execution_dependencies(self.inner.remote_execution_dependencies.clone())
+            .with_remote_execution_properties(self.inner.remote_execution_properties.clone());
+        if let Some(timeout) = self.inner.timeout.or(knobs.default_timeout) {
+            req = req.with_timeout(timeout);
+        }
 
         let (mut dep_file_bundle, req) = if let Some(visitor) = dep_file_visitor {
             let bundle = make_dep_file_bundle(ctx, visitor, cmdline_digest, req.paths())?;
@@ -714,7 +910,13 @@ impl IncrementalActionExecutable for RunAction {
                     }
                 };
 
-                ctx.exec_cmd(manager, &req, &prepared_action).await
+                ctx.exec_cmd(
+                    manager,
+                    &req,
+                    &prepared_action,
+                    self.inner.retry_policy.as_deref(),
+                )
+                .await
             }
         };
 
@@ -727,6 +929,7 @@ impl IncrementalActionExecutable for RunAction {
         let upload_dep_file = self.inner.allow_dep_file_cache_upload && dep_file_bundle.is_some();
         if result.was_success()
             && !result.was_served_by_remote_dep_file_cache()
+            && !self.inner.remote_cache_read_only
             && (self.inner.allow_cache_upload || upload_dep_file || force_cache_upload()?)
         {
             let dep_file_entry = match &mut dep_file_bundle {
@@ -761,3 +964,49 @@ impl IncrementalActionExecutable for RunAction {
         Ok((outputs, metadata))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worker_remote_key_digest() {
+        let digest_config = DigestConfig::testing_default();
+        let key = |exe: &[&str], inputs: &[u8]| {
+            let exe: Vec<String> = exe.iter().map(|s| (*s).to_owned()).collect();
+            worker_remote_key_digest(&exe, inputs, digest_config)
+        };
+
+        assert_eq!(
+            key(&["javac", "--persistent_worker"], b"inputs"),
+            key(&["javac", "--persistent_worker"], b"inputs")
+        );
+        assert_ne!(
+            key(&["javac", "--persistent_worker"], b"inputs"),
+            key(&["javac", "--persistent_worker"], b"other inputs")
+        );
+        assert_ne!(key(&["a b"], b"inputs"), key(&["a", "b"], b"inputs"));
+    }
+
+    #[test]
+    fn test_allowlisted_env() {
+        let daemon_env = |var: &str| match var {
+            "PATH" => Some("/usr/bin".to_owned()),
+            "HOME" => Some("/home/user".to_owned()),
+            "LANG" => Some("C".to_owned()),
+            _ => None,
+        };
+        // From `build.action_env_allowlist`, then from the action's `env_allowlist`.
+        let allowlist = ["PATH", "TMPDIR", "HOME", "LANG", "PATH"].map(|v| v.to_owned());
+
+        assert_eq!(
+            vec![
+                ("PATH".to_owned(), "/usr/bin".to_owned()),
+                ("HOME".to_owned(), "/home/user".to_owned()),
+            ],
+            // The action sets `LANG` itself.
+            allowlisted_env(&allowlist, |var| var == "LANG", daemon_env)
+        );
+        assert!(allowlisted_env(std::iter::empty(), |_| false, daemon_env).is_empty());
+    }
+}
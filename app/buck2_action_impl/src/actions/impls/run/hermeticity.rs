@@ -0,0 +1,296 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Checks that a command line only refers to the files its action declares, so that commands
+//! which would only work on the machine that built them fail when they are prepared instead of
+//! failing (or worse, not failing) on RE.
+
+use std::collections::HashSet;
+
+use buck2_build_api::actions::impls::expanded_command_line::ExpandedCommandLine;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_execute::execute::request::WorkerSpec;
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(input)]
+enum CommandLineValidationError {
+    #[error(
+        "Command line argument `{arg}` contains the absolute path of the project root `{root}`. Use \
+        artifacts, or paths relative to the project root, instead"
+    )]
+    AbsolutePath { arg: String, root: String },
+    #[error(
+        "Command line argument `{arg}` refers to `{path}` in buck-out, which is not an input or \
+        output of this action. Add the artifact to the command line, or to its hidden inputs"
+    )]
+    UndeclaredBuckOutPath { arg: String, path: String },
+}
+
+/// The paths an action may refer to: its inputs and outputs, and the paths inside them.
+pub(crate) struct DeclaredPaths {
+    paths: HashSet<String>,
+    /// The directories of the outputs inside the output directory of the action's owner, e.g. for
+    /// `cmd_args(out, parent = 1)`. They are created for this action, but may contain the
+    /// outputs of other actions of the same target, so only the directories themselves are
+    /// allowed.
+    output_dirs: HashSet<String>,
+}
+
+impl DeclaredPaths {
+    /// Outputs are given with their path relative to the output directory of their owner.
+    pub(crate) fn new<'a>(
+        inputs: impl IntoIterator<Item = ProjectRelativePathBuf>,
+        outputs: impl IntoIterator<Item = (ProjectRelativePathBuf, &'a ForwardRelativePath)>,
+    ) -> Self {
+        let mut paths: HashSet<String> =
+            inputs.into_iter().map(|p| p.as_str().to_owned()).collect();
+        let mut output_dirs = HashSet::new();
+        for (output, relative) in outputs {
+            let mut dir = output.as_str();
+            for _ in relative.iter() {
+                match dir.rsplit_once('/') {
+                    Some((parent, _)) => dir = parent,
+                    None => break,
+                }
+                output_dirs.insert(dir.to_owned());
+            }
+            paths.insert(output.as_str().to_owned());
+        }
+        Self { paths, output_dirs }
+    }
+
+    /// Whether `path` is declared, or is inside a declared directory, or is a directory of a
+    /// declared output. The other directories containing declared paths are not allowed: they
+    /// contain other actions' outputs too.
+    fn allows(&self, path: &str) -> bool {
+        if self.output_dirs.contains(path) {
+            return true;
+        }
+        let mut path = path;
+        loop {
+            if self.paths.contains(path) {
+                return true;
+            }
+            match path.rsplit_once('/') {
+                Some((parent, _)) => path = parent,
+                None => return false,
+            }
+        }
+    }
+}
+
+/// The characters which end a path embedded in a command line argument, such as in
+/// `--out=buck-out/v2/gen/foo,bar`.
+fn ends_path(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '"' | '\'' | ',' | ';' | ':' | '=' | '(' | ')')
+}
+
+/// The paths in buck-out which `arg` refers to, relative to the project root.
+fn buck_out_paths<'a>(arg: &'a str, buck_out: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    arg.match_indices(buck_out).filter_map(move |(start, _)| {
+        // Only match whole path components, e.g. not `my-buck-out/v2/...`.
+        if arg[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c != '/' && !ends_path(c))
+        {
+            return None;
+        }
+        let rest = &arg[start..];
+        let path = &rest[..rest.find(ends_path).unwrap_or(rest.len())];
+        Some(path.trim_end_matches('/'))
+    })
+}
+
+pub(crate) fn validate_command_line(
+    expanded: &ExpandedCommandLine,
+    worker: Option<&WorkerSpec>,
+    declared: &DeclaredPaths,
+    fs: &ArtifactFs,
+) -> anyhow::Result<()> {
+    let root = fs.fs().root().as_path().to_string_lossy();
+    let buck_out = format!("{}/", fs.buck_out_path_resolver().root());
+
+    let args = expanded
+        .exe
+        .iter()
+        .chain(&expanded.args)
+        .chain(expanded.env.values())
+        .chain(worker.into_iter().flat_map(|w| &w.exe));
+    for arg in args {
+        if arg.contains(&*root) {
+            return Err(CommandLineValidationError::AbsolutePath {
+                arg: arg.clone(),
+                root: root.into_owned(),
+            }
+            .into());
+        }
+        if let Some(path) = buck_out_paths(arg, &buck_out).find(|path| !declared.allows(path)) {
+            return Err(CommandLineValidationError::UndeclaredBuckOutPath {
+                arg: arg.clone(),
+                path: path.to_owned(),
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::CellResolver;
+    use buck2_core::fs::buck_out_path::BuckOutPathResolver;
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+    use buck2_core::fs::project::ProjectRoot;
+    use buck2_execute::execute::request::WorkerId;
+
+    use super::*;
+
+    #[test]
+    fn test_buck_out_paths() {
+        let buck_out = "buck-out/v2/";
+        assert_eq!(
+            buck_out_paths("--out=buck-out/v2/gen/foo,buck-out/v2/gen/bar/", buck_out)
+                .collect::<Vec<_>>(),
+            vec!["buck-out/v2/gen/foo", "buck-out/v2/gen/bar"]
+        );
+        assert_eq!(
+            buck_out_paths("../../buck-out/v2/gen/foo", buck_out).collect::<Vec<_>>(),
+            vec!["buck-out/v2/gen/foo"]
+        );
+        assert_eq!(
+            buck_out_paths("my-buck-out/v2/gen/foo", buck_out).count(),
+            0
+        );
+    }
+
+    fn declared_paths() -> DeclaredPaths {
+        DeclaredPaths::new(
+            [ProjectRelativePathBuf::unchecked_new(
+                "buck-out/v2/gen/root/bar/headers".to_owned(),
+            )],
+            [(
+                ProjectRelativePathBuf::unchecked_new(
+                    "buck-out/v2/gen/root/foo/__foo__/dir/out".to_owned(),
+                ),
+                ForwardRelativePath::unchecked_new("dir/out"),
+            )],
+        )
+    }
+
+    #[test]
+    fn test_declared_paths() {
+        let declared = declared_paths();
+        assert!(declared.allows("buck-out/v2/gen/root/foo/__foo__/dir/out"));
+        assert!(declared.allows("buck-out/v2/gen/root/bar/headers/bar.h"));
+        assert!(!declared.allows("buck-out/v2/gen/root/bar"));
+        assert!(!declared.allows("buck-out/v2/gen"));
+        assert!(!declared.allows("buck-out/v2/gen/root/baz/out"));
+        assert!(!declared.allows("buck-out/v2/gen/root/foo/__foo__/dir/out2"));
+    }
+
+    #[test]
+    fn test_declared_paths_output_dirs() {
+        let declared = declared_paths();
+        // The directories of an output, up to the output directory of its owner.
+        assert!(declared.allows("buck-out/v2/gen/root/foo/__foo__/dir"));
+        assert!(declared.allows("buck-out/v2/gen/root/foo/__foo__"));
+        assert!(!declared.allows("buck-out/v2/gen/root/foo"));
+        // Not what they contain.
+        assert!(!declared.allows("buck-out/v2/gen/root/foo/__foo__/other"));
+    }
+
+    fn artifact_fs() -> ArtifactFs {
+        ArtifactFs::new(
+            CellResolver::testing_with_name_and_path(
+                CellName::testing_new("root"),
+                CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("".to_owned())),
+            ),
+            BuckOutPathResolver::new(ProjectRelativePathBuf::unchecked_new(
+                "buck-out/v2".to_owned(),
+            )),
+            ProjectRoot::new_unchecked(
+                AbsNormPathBuf::new(PathBuf::from(if cfg!(windows) {
+                    "C:\\repo"
+                } else {
+                    "/repo"
+                }))
+                .unwrap(),
+            ),
+        )
+    }
+
+    fn validate(args: &[&str], env: &[(&str, &str)], worker: &[&str]) -> anyhow::Result<()> {
+        let expanded = ExpandedCommandLine {
+            exe: vec!["tool".to_owned()],
+            args: args.iter().map(|a| (*a).to_owned()).collect(),
+            env: env
+                .iter()
+                .map(|(k, v)| ((*k).to_owned(), (*v).to_owned()))
+                .collect(),
+        };
+        let worker = WorkerSpec {
+            id: WorkerId(0),
+            exe: worker.iter().map(|a| (*a).to_owned()).collect(),
+            concurrency: None,
+            remote_key: None,
+        };
+        validate_command_line(&expanded, Some(&worker), &declared_paths(), &artifact_fs())
+    }
+
+    #[test]
+    fn test_validate_command_line() {
+        validate(
+            &[
+                "--out=buck-out/v2/gen/root/foo/__foo__/dir/out",
+                "--out-dir=buck-out/v2/gen/root/foo/__foo__/dir",
+                "-I",
+                "buck-out/v2/gen/root/bar/headers",
+                "src/main.c",
+            ],
+            &[("HEADER", "buck-out/v2/gen/root/bar/headers/bar.h")],
+            &["worker"],
+        )
+        .unwrap();
+
+        let err = validate(&["-I", "buck-out/v2/gen"], &[], &[]).unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<CommandLineValidationError>(),
+                Some(CommandLineValidationError::UndeclaredBuckOutPath { path, .. })
+                    if path == "buck-out/v2/gen"
+            ),
+            "{:#}",
+            err
+        );
+        // Environment variables and the worker's command line are checked too.
+        assert!(validate(&[], &[("OUT", "buck-out/v2/gen/root/baz/out")], &[]).is_err());
+        assert!(validate(&[], &[], &["--cache=buck-out/v2/cache"]).is_err());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_validate_command_line_absolute_path() {
+        let err = validate(&["--src=/repo/src/main.c"], &[], &[]).unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<CommandLineValidationError>(),
+                Some(CommandLineValidationError::AbsolutePath { .. })
+            ),
+            "{:#}",
+            err
+        );
+    }
+}
@@ -0,0 +1,190 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Enforcement of `build.action_input_budgets` and `build.action_input_mebibyte_budgets` (see
+//! `buck2_common::action_input_budgets`). The number of inputs is checked when the action is
+//! registered, but their size is only known once they are built, so it is checked before the
+//! action runs (and before its inputs are uploaded to remote execution).
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use buck2_artifact::artifact::artifact_type::Artifact;
+use buck2_artifact::artifact::artifact_type::OutputArtifact;
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_build_api::artifact_groups::ArtifactGroupValues;
+use buck2_build_api::interpreter::rule_defs::artifact_tagging::ArtifactTag;
+use buck2_build_api::interpreter::rule_defs::cmd_args::CommandLineArgLike;
+use buck2_build_api::interpreter::rule_defs::cmd_args::CommandLineArtifactVisitor;
+use buck2_execute::output_size::OutputSize;
+
+/// How many contributors to list when an action is over its budget.
+const MAX_CONTRIBUTORS: usize = 10;
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(input)]
+enum InputBudgetError {
+    #[error(
+        "Action with category `{category}` has {count} inputs, which is over its budget of \
+        {max_inputs} set in `build.action_input_budgets`. This is likely a bug in the rule. The \
+        largest contributors of inputs are:\n{contributors}"
+    )]
+    OverBudget {
+        category: String,
+        count: u64,
+        max_inputs: u64,
+        contributors: String,
+    },
+    #[error(
+        "Action with category `{category}` has {bytes} bytes of inputs, which is over its budget \
+        of {max_input_bytes} bytes set in `build.action_input_mebibyte_budgets`. This is likely a \
+        bug in the rule. The largest contributors of inputs, in bytes, are:\n{contributors}"
+    )]
+    OverSizeBudget {
+        category: String,
+        bytes: u64,
+        max_input_bytes: u64,
+        contributors: String,
+    },
+}
+
+/// Collects the distinct inputs of a command line, with transitive sets expanded.
+#[derive(Default)]
+struct InputCountingVisitor {
+    inputs: HashSet<ArtifactGroup>,
+}
+
+impl CommandLineArtifactVisitor for InputCountingVisitor {
+    fn visit_input(&mut self, input: ArtifactGroup, _tag: Option<&ArtifactTag>) {
+        self.inputs.insert(input);
+    }
+
+    fn visit_output(&mut self, _artifact: OutputArtifact, _tag: Option<&ArtifactTag>) {}
+
+    fn expand_transitive_sets(&self) -> bool {
+        true
+    }
+}
+
+/// The target that produces `artifact`, or the package of a source file.
+fn artifact_contributor(artifact: &Artifact) -> String {
+    match artifact.owner() {
+        Some(owner) => owner.to_string(),
+        None => match artifact.get_source() {
+            Some(source) => source.get_path().package().to_string(),
+            None => artifact.to_string(),
+        },
+    }
+}
+
+/// The target that produces `input`, or the package of a source file.
+fn contributor(input: &ArtifactGroup) -> String {
+    match input {
+        ArtifactGroup::Artifact(artifact) => artifact_contributor(artifact),
+        ArtifactGroup::Promise(promise) => promise.owner().to_string(),
+        // Not produced when transitive sets are expanded.
+        ArtifactGroup::TransitiveSetProjection(projection) => projection.to_string(),
+    }
+}
+
+/// The contributors with the most inputs first, formatted one per line.
+fn format_top_contributors(counts: HashMap<String, u64>) -> String {
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+
+    let mut s = String::new();
+    for (contributor, count) in counts.iter().take(MAX_CONTRIBUTORS) {
+        writeln!(s, "  {:>10}  {}", count, contributor).unwrap();
+    }
+    if counts.len() > MAX_CONTRIBUTORS {
+        writeln!(s, "  ... and {} more", counts.len() - MAX_CONTRIBUTORS).unwrap();
+    }
+    s
+}
+
+/// Fails if the command lines of an action in `category` have more than `max_inputs` inputs.
+/// Transitive sets are expanded to count the artifacts they contain, which is only worth doing
+/// for the categories that have a budget.
+pub(crate) fn check_input_budget(
+    category: &str,
+    max_inputs: u64,
+    command_lines: &[&dyn CommandLineArgLike],
+) -> anyhow::Result<()> {
+    let mut visitor = InputCountingVisitor::default();
+    for command_line in command_lines {
+        command_line.visit_artifacts(&mut visitor)?;
+    }
+    if visitor.inputs.len() as u64 <= max_inputs {
+        return Ok(());
+    }
+
+    let mut counts = HashMap::new();
+    for input in &visitor.inputs {
+        *counts.entry(contributor(input)).or_insert(0) += 1;
+    }
+    Err(InputBudgetError::OverBudget {
+        category: category.to_owned(),
+        count: visitor.inputs.len() as u64,
+        max_inputs,
+        contributors: format_top_contributors(counts),
+    }
+    .into())
+}
+
+/// Fails if the inputs of an action in `category`, which total `bytes`, are larger than
+/// `max_input_bytes`.
+pub(crate) fn check_input_size_budget(
+    category: &str,
+    max_input_bytes: u64,
+    bytes: u64,
+    inputs: &[&ArtifactGroupValues],
+) -> anyhow::Result<()> {
+    if bytes <= max_input_bytes {
+        return Ok(());
+    }
+
+    let mut sizes = HashMap::new();
+    for group in inputs {
+        for (artifact, value) in group.iter() {
+            *sizes.entry(artifact_contributor(artifact)).or_insert(0) +=
+                value.calc_output_count_and_bytes().bytes;
+        }
+    }
+    Err(InputBudgetError::OverSizeBudget {
+        category: category.to_owned(),
+        bytes,
+        max_input_bytes,
+        contributors: format_top_contributors(sizes),
+    }
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_top_contributors() {
+        let counts = HashMap::from([
+            ("root//:small".to_owned(), 1),
+            ("root//:big".to_owned(), 100),
+            ("root//:also_small".to_owned(), 1),
+        ]);
+        assert_eq!(
+            "         100  root//:big\n           1  root//:also_small\n           1  root//:small\n",
+            format_top_contributors(counts)
+        );
+
+        let counts = (0..12).map(|i| (format!("root//:t{:02}", i), i)).collect();
+        let formatted = format_top_contributors(counts);
+        assert!(formatted.starts_with("          11  root//:t11\n"));
+        assert!(formatted.ends_with("           2  root//:t02\n  ... and 2 more\n"));
+    }
+}
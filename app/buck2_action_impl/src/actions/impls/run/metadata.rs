@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use buck2_artifact::artifact::build_artifact::BuildArtifact;
 use buck2_build_api::artifact_groups::ArtifactGroupValues;
 use buck2_common::file_ops::TrackedFileDigest;
 use buck2_core::directory::Directory;
@@ -22,6 +23,7 @@ use buck2_execute::execute::paths_with_digest::PathsWithDigestBuilder;
 pub(crate) fn metadata_content(
     fs: &ArtifactFs,
     inputs: &[&ArtifactGroupValues],
+    outputs: &[BuildArtifact],
     digest_config: DigestConfig,
 ) -> anyhow::Result<(PathsWithDigestBlobData, TrackedFileDigest)> {
     let mut blob_builder = PathsWithDigestBuilder::default();
@@ -47,6 +49,14 @@ pub(crate) fn metadata_content(
         }
     }
 
+    for output in outputs {
+        blob_builder.add_output(
+            fs.resolve_build(output.get_path())
+                .into_forward_relative_path_buf(),
+            output.output_type(),
+        );
+    }
+
     let blob = blob_builder.build()?;
 
     let digest = TrackedFileDigest::from_content(&blob.0.0, digest_config.cas_digest_config());
@@ -0,0 +1,217 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::borrow::Cow;
+use std::slice;
+use std::time::Instant;
+
+use allocative::Allocative;
+use anyhow::Context as _;
+use async_trait::async_trait;
+use buck2_artifact::artifact::build_artifact::BuildArtifact;
+use buck2_build_api::actions::execute::action_executor::ActionExecutionKind;
+use buck2_build_api::actions::execute::action_executor::ActionExecutionMetadata;
+use buck2_build_api::actions::execute::action_executor::ActionOutputs;
+use buck2_build_api::actions::execute::error::ExecuteError;
+use buck2_build_api::actions::Action;
+use buck2_build_api::actions::ActionExecutable;
+use buck2_build_api::actions::ActionExecutionCtx;
+use buck2_build_api::actions::IncrementalActionExecutable;
+use buck2_build_api::actions::UnregisteredAction;
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_build_api::stamp::StampInfo;
+use buck2_core::category::Category;
+use buck2_execute::artifact::fs::ExecutorFs;
+use buck2_execute::execute::command_executor::ActionExecutionTimingData;
+use buck2_execute::materialize::materializer::WriteRequest;
+use dupe::Dupe;
+use indexmap::indexmap;
+use indexmap::IndexMap;
+use indexmap::IndexSet;
+use once_cell::sync::Lazy;
+use starlark::values::OwnedFrozenValue;
+
+#[derive(Debug, buck2_error::Error)]
+enum StampActionValidationError {
+    #[error("StampAction received inputs")]
+    TooManyInputs,
+    #[error("StampAction received no outputs")]
+    NoOutputs,
+    #[error("StampAction received more than one output")]
+    TooManyOutputs,
+}
+
+#[derive(Debug, buck2_error::Error)]
+enum StampActionError {
+    #[error("Stamp information is not available in this command")]
+    NoStampInfo,
+    #[error("Unknown stamp key `{0}`")]
+    UnknownKey(String),
+}
+
+#[derive(Allocative, Debug)]
+pub(crate) struct UnregisteredStampAction {
+    /// Validated against `StampInfo::KEYS` during analysis.
+    pub(crate) keys: Vec<String>,
+}
+
+impl UnregisteredAction for UnregisteredStampAction {
+    fn register(
+        self: Box<Self>,
+        inputs: IndexSet<ArtifactGroup>,
+        outputs: IndexSet<BuildArtifact>,
+        _starlark_data: Option<OwnedFrozenValue>,
+        _error_handler: Option<OwnedFrozenValue>,
+    ) -> anyhow::Result<Box<dyn Action>> {
+        Ok(Box::new(StampAction::new(inputs, outputs, *self)?))
+    }
+}
+
+#[derive(Debug, Allocative)]
+struct StampAction {
+    output: BuildArtifact,
+    inner: UnregisteredStampAction,
+}
+
+impl StampAction {
+    fn new(
+        inputs: IndexSet<ArtifactGroup>,
+        outputs: IndexSet<BuildArtifact>,
+        inner: UnregisteredStampAction,
+    ) -> anyhow::Result<Self> {
+        let mut outputs = outputs.into_iter();
+
+        let output = match (outputs.next(), outputs.next()) {
+            (Some(o), None) => o,
+            (None, ..) => return Err(StampActionValidationError::NoOutputs.into()),
+            (Some(..), Some(..)) => return Err(StampActionValidationError::TooManyOutputs.into()),
+        };
+
+        if !inputs.is_empty() {
+            return Err(StampActionValidationError::TooManyInputs.into());
+        }
+
+        Ok(StampAction { output, inner })
+    }
+}
+
+/// One `<key> <value>` line per key, in the order they were requested.
+fn stamp_contents(keys: &[String], info: &StampInfo) -> anyhow::Result<String> {
+    let mut contents = String::new();
+    for key in keys {
+        let value = info
+            .get(key)
+            .ok_or_else(|| StampActionError::UnknownKey(key.clone()))?;
+        contents.push_str(key);
+        contents.push(' ');
+        contents.push_str(&value);
+        contents.push('\n');
+    }
+    Ok(contents)
+}
+
+#[async_trait]
+impl Action for StampAction {
+    fn kind(&self) -> buck2_data::ActionKind {
+        buck2_data::ActionKind::Stamp
+    }
+
+    fn inputs(&self) -> anyhow::Result<Cow<'_, [ArtifactGroup]>> {
+        Ok(Cow::Borrowed(&[]))
+    }
+
+    fn outputs(&self) -> anyhow::Result<Cow<'_, [BuildArtifact]>> {
+        Ok(Cow::Borrowed(slice::from_ref(&self.output)))
+    }
+
+    fn as_executable(&self) -> ActionExecutable<'_> {
+        ActionExecutable::Incremental(self)
+    }
+
+    fn category(&self) -> &Category {
+        static STAMP_CATEGORY: Lazy<Category> = Lazy::new(|| Category::try_from("stamp").unwrap());
+
+        &STAMP_CATEGORY
+    }
+
+    fn identifier(&self) -> Option<&str> {
+        Some(self.output.get_path().path().as_str())
+    }
+
+    fn uses_stamp_info(&self) -> bool {
+        true
+    }
+
+    fn aquery_attributes(&self, _fs: &ExecutorFs) -> IndexMap<String, String> {
+        indexmap! {
+            "keys".to_owned() => self.inner.keys.join(","),
+        }
+    }
+}
+
+#[async_trait]
+impl IncrementalActionExecutable for StampAction {
+    async fn execute(
+        &self,
+        ctx: &mut dyn ActionExecutionCtx,
+    ) -> Result<(ActionOutputs, ActionExecutionMetadata), ExecuteError> {
+        let fs = ctx.fs();
+        let info = ctx
+            .stamp_info()
+            .ok_or_else(|| anyhow::Error::from(StampActionError::NoStampInfo))?;
+
+        let mut execution_start = None;
+
+        let value = ctx
+            .materializer()
+            .declare_write(Box::new(|| {
+                execution_start = Some(Instant::now());
+                Ok(vec![WriteRequest {
+                    path: fs.resolve_build(self.output.get_path()),
+                    content: stamp_contents(&self.inner.keys, info)?.into_bytes(),
+                    is_executable: false,
+                }])
+            }))
+            .await?
+            .into_iter()
+            .next()
+            .context("Stamp did not execute")?;
+
+        let wall_time = execution_start
+            .context("Action did not set execution_start")?
+            .elapsed();
+
+        Ok((
+            ActionOutputs::new(indexmap![self.output.get_path().dupe() => value]),
+            ActionExecutionMetadata {
+                execution_kind: ActionExecutionKind::Simple,
+                timing: ActionExecutionTimingData { wall_time },
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stamp_contents() {
+        let info = StampInfo {
+            revision: Some("abc123".to_owned()),
+            timestamp: 1700000000,
+        };
+        let keys = vec!["timestamp".to_owned(), "revision".to_owned()];
+        assert_eq!(
+            stamp_contents(&keys, &info).unwrap(),
+            "timestamp 1700000000\nrevision abc123\n"
+        );
+        assert!(stamp_contents(&["user".to_owned()], &info).is_err());
+    }
+}
@@ -191,6 +191,7 @@ impl Action for WriteJsonAction {
                 Ok(v) => v,
                 Err(e) => format!("ERROR: constructing contents ({})", e)
             },
+            "pretty".to_owned() => self.inner.pretty.to_string(),
             "absolute".to_owned() => self.inner.absolute.to_string(),
         }
     }
@@ -9,17 +9,21 @@
 
 use buck2_build_api::interpreter::rule_defs::context::ANALYSIS_ACTIONS_METHODS_ACTIONS;
 
+use crate::context::archive::analysis_actions_methods_archive;
 use crate::context::copy::analysis_actions_methods_copy;
 use crate::context::download::analysis_actions_methods_download;
 use crate::context::dynamic_output::analysis_actions_methods_dynamic_output;
 use crate::context::run::analysis_actions_methods_run;
+use crate::context::stamp::analysis_actions_methods_stamp;
 use crate::context::unsorted::analysis_actions_methods_unsorted;
 use crate::context::write::analysis_actions_methods_write;
 
+mod archive;
 mod copy;
 mod download;
 mod dynamic_output;
 mod run;
+mod stamp;
 mod unsorted;
 mod write;
 
@@ -31,10 +35,12 @@ mod write;
 /// to output artifacts.
 pub(crate) fn init_analysis_action_methods_actions() {
     ANALYSIS_ACTIONS_METHODS_ACTIONS.init(|methods| {
+        analysis_actions_methods_archive(methods);
         analysis_actions_methods_copy(methods);
         analysis_actions_methods_download(methods);
         analysis_actions_methods_dynamic_output(methods);
         analysis_actions_methods_run(methods);
+        analysis_actions_methods_stamp(methods);
         analysis_actions_methods_unsorted(methods);
         analysis_actions_methods_write(methods);
     });
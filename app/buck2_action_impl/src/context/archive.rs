@@ -0,0 +1,132 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_build_api::interpreter::rule_defs::artifact::associated::AssociatedArtifacts;
+use buck2_build_api::interpreter::rule_defs::artifact::output_artifact_like::OutputArtifactArg;
+use buck2_build_api::interpreter::rule_defs::artifact::starlark_artifact_like::ValueAsArtifactLike;
+use buck2_build_api::interpreter::rule_defs::artifact::starlark_declared_artifact::StarlarkDeclaredArtifact;
+use buck2_build_api::interpreter::rule_defs::cmd_args::CommandLineArgLike;
+use buck2_build_api::interpreter::rule_defs::cmd_args::SimpleCommandLineArtifactVisitor;
+use buck2_build_api::interpreter::rule_defs::cmd_args::StarlarkCmdArgs;
+use buck2_build_api::interpreter::rule_defs::context::AnalysisActions;
+use buck2_build_api::interpreter::rule_defs::provider::builtin::run_info::RunInfo;
+use buck2_execute::execute::request::OutputType;
+use indexmap::indexset;
+use starlark::environment::MethodsBuilder;
+use starlark::eval::Evaluator;
+use starlark::starlark_module;
+use starlark::values::ValueOf;
+use starlark::values::ValueTyped;
+
+use crate::actions::impls::archive::ArchiveFormat;
+use crate::actions::impls::archive::ArchiveOperation;
+use crate::actions::impls::archive::UnregisteredArchiveAction;
+
+fn archive_impl<'v>(
+    eval: &mut Evaluator<'v, '_, '_>,
+    this: &AnalysisActions<'v>,
+    output: OutputArtifactArg<'v>,
+    src: ValueAsArtifactLike<'v>,
+    archiver: ValueOf<'v, &'v RunInfo<'v>>,
+    operation: ArchiveOperation,
+    format: ArchiveFormat,
+    output_type: OutputType,
+    allow_cache_upload: bool,
+) -> anyhow::Result<ValueTyped<'v, StarlarkDeclaredArtifact>> {
+    // The archive action expects its source first, followed by the inputs of the archiver.
+    let mut inputs = indexset![src.0.get_artifact_group()?];
+    let archiver = StarlarkCmdArgs::try_from_value(*archiver)?;
+    let mut artifact_visitor = SimpleCommandLineArtifactVisitor::new();
+    archiver.visit_artifacts(&mut artifact_visitor)?;
+    inputs.extend(artifact_visitor.inputs);
+
+    let mut this = this.state();
+    let (declaration, output_artifact) = this.get_or_declare_output(eval, output, output_type)?;
+
+    this.register_action(
+        inputs,
+        indexset![output_artifact],
+        UnregisteredArchiveAction::new(operation, format, allow_cache_upload),
+        Some(eval.heap().alloc(archiver)),
+        None,
+    )?;
+
+    Ok(declaration.into_declared_artifact(AssociatedArtifacts::new()))
+}
+
+#[starlark_module]
+pub(crate) fn analysis_actions_methods_archive(methods: &mut MethodsBuilder) {
+    /// Creates an archive of the source `artifact` at `output` and returns the output `artifact`.
+    /// The archive holds the contents of `src` if it is a directory, or `src` itself if it is a
+    /// file.
+    ///
+    /// * `archiver`: the `buck2_archive` tool, which runs like the command of a `run` action, so
+    ///   the archive can be built remotely and cached.
+    /// * `format`: `"tar.zst"` (the default) or `"zip"`. Zip archives can't hold symlinks.
+    /// * `allow_cache_upload`: whether to upload the archive to the cache when it is built
+    ///   locally, as for `run`. The archiver is deterministic, so archives are usually worth
+    ///   sharing.
+    ///
+    /// The archive is deterministic, whatever `tar` or `zip` the machine running it has: entries
+    /// are sorted by path, and have a fixed modification time and owner. Only whether files are
+    /// executable is preserved of their permissions.
+    fn archive<'v>(
+        this: &AnalysisActions<'v>,
+        #[starlark(require = pos)] output: OutputArtifactArg<'v>,
+        #[starlark(require = pos)] src: ValueAsArtifactLike<'v>,
+        #[starlark(require = named)] archiver: ValueOf<'v, &'v RunInfo<'v>>,
+        #[starlark(require = named, default = "tar.zst")] format: &str,
+        #[starlark(require = named, default = false)] allow_cache_upload: bool,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> anyhow::Result<ValueTyped<'v, StarlarkDeclaredArtifact>> {
+        archive_impl(
+            eval,
+            this,
+            output,
+            src,
+            archiver,
+            ArchiveOperation::Create,
+            ArchiveFormat::parse(format)?,
+            OutputType::File,
+            allow_cache_upload,
+        )
+    }
+
+    /// Extracts the `src` archive into the `output` directory and returns the output `artifact`.
+    ///
+    /// * `archiver`: the `buck2_archive` tool, as for `archive`.
+    /// * `format` (optional): `"tar.zst"` or `"zip"`. Defaults to the format matching the
+    ///   extension of `src` (`.tar.zst`, `.tzst` or `.zip`).
+    /// * `allow_cache_upload`: as for `archive`.
+    fn extract<'v>(
+        this: &AnalysisActions<'v>,
+        #[starlark(require = pos)] output: OutputArtifactArg<'v>,
+        #[starlark(require = pos)] src: ValueAsArtifactLike<'v>,
+        #[starlark(require = named)] archiver: ValueOf<'v, &'v RunInfo<'v>>,
+        #[starlark(require = named)] format: Option<&str>,
+        #[starlark(require = named, default = false)] allow_cache_upload: bool,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> anyhow::Result<ValueTyped<'v, StarlarkDeclaredArtifact>> {
+        let format = match format {
+            Some(format) => ArchiveFormat::parse(format)?,
+            None => ArchiveFormat::from_path(src.0.basename(eval.heap())?.as_str())?,
+        };
+        archive_impl(
+            eval,
+            this,
+            output,
+            src,
+            archiver,
+            ArchiveOperation::Extract,
+            format,
+            OutputType::Directory,
+            allow_cache_upload,
+        )
+    }
+}
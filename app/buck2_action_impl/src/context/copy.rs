@@ -19,8 +19,10 @@ use starlark::environment::MethodsBuilder;
 use starlark::eval::Evaluator;
 use starlark::starlark_module;
 use starlark::values::dict::DictOf;
+use starlark::values::list::UnpackList;
 use starlark::values::ValueTyped;
 
+use crate::actions::impls::copy::CopyFilter;
 use crate::actions::impls::copy::CopyMode;
 use crate::actions::impls::copy::UnregisteredCopyAction;
 use crate::actions::impls::symlinked_dir::UnregisteredSymlinkedDirAction;
@@ -45,6 +47,19 @@ fn create_dir_tree<'v>(
     Ok(declaration.into_declared_artifact(unioned_associated_artifacts))
 }
 
+fn dir_filter(
+    include: Option<UnpackList<String>>,
+    exclude: Option<UnpackList<String>>,
+) -> anyhow::Result<Option<CopyFilter>> {
+    if include.is_none() && exclude.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(CopyFilter::new(
+        include.as_ref().map(|i| i.items.as_slice()),
+        exclude.as_ref().map_or(&[], |e| e.items.as_slice()),
+    )?))
+}
+
 fn copy_file_impl<'v>(
     eval: &mut Evaluator<'v, '_, '_>,
     this: &AnalysisActions<'v>,
@@ -52,6 +67,7 @@ fn copy_file_impl<'v>(
     src: ValueAsArtifactLike<'v>,
     copy: CopyMode,
     output_type: OutputType,
+    filter: Option<CopyFilter>,
 ) -> anyhow::Result<ValueTyped<'v, StarlarkDeclaredArtifact>> {
     let src = src.0;
 
@@ -63,7 +79,7 @@ fn copy_file_impl<'v>(
     this.register_action(
         indexset![artifact],
         indexset![output_artifact],
-        UnregisteredCopyAction::new(copy),
+        UnregisteredCopyAction::new(copy, filter),
         None,
         None,
     )?;
@@ -95,6 +111,7 @@ pub(crate) fn analysis_actions_methods_copy(methods: &mut MethodsBuilder) {
             src,
             CopyMode::Copy,
             OutputType::FileOrDirectory,
+            None,
         )
     }
 
@@ -116,24 +133,44 @@ pub(crate) fn analysis_actions_methods_copy(methods: &mut MethodsBuilder) {
             src,
             CopyMode::Symlink,
             OutputType::FileOrDirectory,
+            None,
         )
     }
 
     /// Make a copy of a directory.
+    ///
+    /// * `include` (optional): only copy the files whose path relative to `src` matches one of
+    ///   these globs (`*` doesn't match `/`, `**` matches any number of directories)
+    /// * `exclude` (optional): don't copy the files whose path matches one of these globs
     fn copy_dir<'v>(
         this: &AnalysisActions<'v>,
         #[starlark(require = pos)] dest: OutputArtifactArg<'v>,
         #[starlark(require = pos)] src: ValueAsArtifactLike<'v>,
+        #[starlark(require = named)] include: Option<UnpackList<String>>,
+        #[starlark(require = named)] exclude: Option<UnpackList<String>>,
         eval: &mut Evaluator<'v, '_, '_>,
     ) -> anyhow::Result<ValueTyped<'v, StarlarkDeclaredArtifact>> {
-        copy_file_impl(eval, this, dest, src, CopyMode::Copy, OutputType::Directory)
+        copy_file_impl(
+            eval,
+            this,
+            dest,
+            src,
+            CopyMode::Copy,
+            OutputType::Directory,
+            dir_filter(include, exclude)?,
+        )
     }
 
     /// Create a symlink to a directory.
+    ///
+    /// If `include` or `exclude` are set (as for `copy_dir`), the result is instead a directory
+    /// containing symlinks to each of the matching files of `src`.
     fn symlink_dir<'v>(
         this: &AnalysisActions<'v>,
         #[starlark(require = pos)] dest: OutputArtifactArg<'v>,
         #[starlark(require = pos)] src: ValueAsArtifactLike<'v>,
+        #[starlark(require = named)] include: Option<UnpackList<String>>,
+        #[starlark(require = named)] exclude: Option<UnpackList<String>>,
         eval: &mut Evaluator<'v, '_, '_>,
     ) -> anyhow::Result<ValueTyped<'v, StarlarkDeclaredArtifact>> {
         copy_file_impl(
@@ -143,6 +180,7 @@ pub(crate) fn analysis_actions_methods_copy(methods: &mut MethodsBuilder) {
             src,
             CopyMode::Symlink,
             OutputType::Directory,
+            dir_filter(include, exclude)?,
         )
     }
 
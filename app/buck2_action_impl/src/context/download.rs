@@ -39,6 +39,8 @@ enum CasArtifactError {
     InvalidDigest(String),
     #[error("is_tree and is_directory are mutually exclusive")]
     TreeAndDirectory,
+    #[error("Not a valid UNIX timestamp: `{0}`")]
+    InvalidTimestamp(i64),
 }
 
 #[starlark_module]
@@ -114,7 +116,10 @@ pub(crate) fn analysis_actions_methods_download(methods: &mut MethodsBuilder) {
 
         let use_case = RemoteExecutorUseCase::new(use_case.to_owned());
 
-        let expires_after_timestamp = Utc.timestamp_opt(expires_after_timestamp, 0).unwrap();
+        let expires_after_timestamp = Utc
+            .timestamp_opt(expires_after_timestamp, 0)
+            .single()
+            .ok_or(CasArtifactError::InvalidTimestamp(expires_after_timestamp))?;
 
         let kind = match (is_tree, is_directory) {
             (true, true) => return Err(CasArtifactError::TreeAndDirectory.into()),
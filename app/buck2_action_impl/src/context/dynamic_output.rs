@@ -65,6 +65,7 @@ pub(crate) fn analysis_actions_methods_dynamic_output(methods: &mut MethodsBuild
     ///   * The function will usually be a `def`, as `lambda` in Starlark does not allow statements,
     /// making it quite underpowered. For full details see
     /// https://buck2.build/docs/rule_authors/dynamic_dependencies/.
+    /// * The function may itself call `dynamic_output`, e.g. to read an artifact it just produced.
     ///
     /// Besides dynamic dependencies, there is a second use case for `dynamic_output`: say that you
     /// have some output artifact, and that the analysis to produce the action that outputs that
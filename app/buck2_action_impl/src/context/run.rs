@@ -9,9 +9,11 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Context;
 use buck2_artifact::artifact::artifact_type::OutputArtifact;
+use buck2_build_api::actions::impls::action_retry_policy::ActionRetryPolicy;
 use buck2_build_api::artifact_groups::ArtifactGroup;
 use buck2_build_api::interpreter::rule_defs::artifact_tagging::ArtifactTag;
 use buck2_build_api::interpreter::rule_defs::cmd_args::value_as::ValueAsCommandLineLike;
@@ -44,6 +46,7 @@ use starlark_map::small_map;
 use starlark_map::small_map::SmallMap;
 
 use crate::actions::impls::run::dep_files::RunActionDepFiles;
+use crate::actions::impls::run::input_budget::check_input_budget;
 use crate::actions::impls::run::new_executor_preference;
 use crate::actions::impls::run::MetadataParameter;
 use crate::actions::impls::run::StarlarkRunActionValues;
@@ -57,6 +60,14 @@ enum RunActionError {
     InvalidWeight(i32),
     #[error("`weight` and `weight_percentage` cannot both be passed")]
     DuplicateWeightsSpecified,
+    #[error("`memory_mb` must be a positive integer, got `{0}`")]
+    InvalidMemory(i32),
+    #[error("`timeout` must be a positive number of seconds, got `{0}`")]
+    InvalidTimeout(i32),
+    #[error("`retries` must be a non-negative integer, got `{0}`")]
+    InvalidRetries(i32),
+    #[error("`retry_on` requires `retries` to be set")]
+    RetryOnWithoutRetries,
     #[error("`dep_files` value with key `{}` has an invalid count of associated outputs. Expected 1, got {}.", .key, .count)]
     InvalidDepFileOutputs { key: String, count: usize },
     #[error("`dep_files` with keys `{}` and {} are using the same tag", .first, .second)]
@@ -82,21 +93,50 @@ pub(crate) fn analysis_actions_methods_run(methods: &mut MethodsBuilder) {
     /// * `arguments`: must be of type `cmd_args`, or a type convertible to such (such as a list of
     ///   strings and artifacts) and must contain at least one `.as_output()` artifact
     /// * `category`: category and identifier - when used together, identify the action in Buck2's
-    ///   event stream, and must be unique for a given target
+    ///   event stream, and must be unique for a given target. If `build.action_input_budgets` sets
+    ///   a budget for the category, analysis fails when the action has more inputs than that. If
+    ///   `build.action_input_mebibyte_budgets` does, the action fails before running when its
+    ///   inputs are larger than that
     /// * `weight`: used to note how heavy the command is and will typically be set to a higher
     ///   value to indicate that less such commands should be run in parallel (if running locally)
+    /// * `memory_mb`: how much memory the command is expected to use, in megabytes. When running
+    ///   locally with a memory budget configured (`build.local_memory_budget_mb`), commands only
+    ///   start once enough of the budget is available, so memory-hungry commands (such as links)
+    ///   aren't all run at the same time
+    /// * `timeout`: number of seconds after which the command is killed and the action fails
+    ///   with a timeout error (stdout and stderr produced so far are still reported). This applies
+    ///   both locally and on remote execution. Defaults to `build.action_timeout_s`, if set
+    /// * `retries`: how many times to re-run the command if it fails, for known-flaky steps
+    ///   (e.g. codesigning or tools touching the network). Retries wait with an exponential
+    ///   backoff, and every attempt is recorded in the event log. This replaces the build-wide
+    ///   `[retry_failure_signatures]` policy for this action
+    /// * `retry_on`: list of regular expressions restricting `retries` to failures matching one
+    ///   of them (matched against the error for infra errors, and against `exit code: N` and
+    ///   stderr otherwise). If not set, any failure is retried. Timeouts are never retried
+    /// * `clean_env`: if set, the command runs with an empty environment, except for `env` and
+    ///   the variables listed in `env_allowlist` (and `build.action_env_allowlist`), whose values
+    ///   are taken from the Buck2 daemon's environment and become part of the action digest.
+    ///   Otherwise, local commands inherit most of the daemon's environment. Defaults to
+    ///   `build.clean_action_env`
+    /// * `env_allowlist`: variables to pass through from the daemon's environment when
+    ///   `clean_env` is in effect
     /// * `no_outputs_cleanup`: if this flag is set then Buck2 won't clean the outputs of a previous
     ///   build that might be present on a disk; in which case, command from arguments should be
     ///   responsible for the cleanup (that is useful, for example, when an action is supporting
-    ///   incremental mode and its outputs are based on result from a previous build)
-    /// * `metadata_env_var` and `meadata_path` should be used together: both set or both unset
+    ///   incremental mode and its outputs are based on result from a previous build). Previous
+    ///   outputs only exist on the local machine, so such actions should usually be `local_only`
+    /// * `remote_cache_read_only`: if set, the result of this action is never written to the
+    ///   remote cache, whether it executes locally or remotely (results can still be read from
+    ///   it). This takes precedence over `allow_cache_upload` and `allow_dep_file_cache_upload`,
+    ///   and is useful for noisy actions whose results are unlikely to be reused
+    /// * `metadata_env_var` and `metadata_path` should be used together: both set or both unset
     ///     * `metadata_path`: defines a path relative to the result directory for a file with
     ///       action metadata, which will be created right before the command will be run.
     ///     * Metadata contains the path relative to the Buck2 project root and hash digest for
     ///       every action input (this excludes symlinks as they could be resolved by a user script
-    ///       if needed). The resolved path relative to the Buck2 project for the metadata file will
-    ///       be passed to command from arguments, via the environment variable, with its name set
-    ///       by `metadata_env_var`
+    ///       if needed), as well as the path and type of every declared output. The resolved
+    ///       path relative to the Buck2 project for the metadata file will be passed to command
+    ///       from arguments, via the environment variable, with its name set by `metadata_env_var`
     ///     * Both `metadata_env_var` and `metadata_path` are useful when making actions behave in
     ///       an incremental manner (for details, see [Incremental
     ///       Actions](https://buck2.build/docs/rule_authors/incremental_actions/))
@@ -117,6 +157,9 @@ pub(crate) fn analysis_actions_methods_run(methods: &mut MethodsBuilder) {
     ///   Each dependency is dictionary with the following keys:
     ///     * `smc_tier`: name of the SMC tier to call by RE Scheduler.
     ///     * `id`: name of the dependency.
+    /// * `remote_execution_properties`: RE platform properties for this action, overriding those of
+    ///   the execution platform with the same name and extending the others (e.g. to request a
+    ///   larger machine or a GPU pool for specific actions). They are part of the action digest
     ///
     /// When actions execute, they'll do so from the root of the repository. As they execute,
     /// actions have exclusive access to their output directory.
@@ -126,7 +169,9 @@ pub(crate) fn analysis_actions_methods_run(methods: &mut MethodsBuilder) {
     /// directory (i.e. relative to the project). This path is guaranteed to exist when the action
     /// executes.
     ///
-    /// When actions run locally, the scratch path is also used as the `TMPDIR`.
+    /// When actions run locally, the scratch path is also used as the `TMPDIR`, and it is deleted
+    /// once the action finishes. On remote execution, it is an empty directory in the inputs of the
+    /// action.
     fn run<'v>(
         this: &AnalysisActions<'v>,
         #[starlark(require = pos)] arguments: Value<'v>,
@@ -140,6 +185,12 @@ pub(crate) fn analysis_actions_methods_run(methods: &mut MethodsBuilder) {
         #[starlark(require = named, default = false)] always_print_stderr: bool,
         #[starlark(require = named)] weight: Option<i32>,
         #[starlark(require = named)] weight_percentage: Option<i32>,
+        #[starlark(require = named)] memory_mb: Option<i32>,
+        #[starlark(require = named)] timeout: Option<i32>,
+        #[starlark(require = named)] retries: Option<i32>,
+        #[starlark(require = named)] retry_on: Option<UnpackList<String>>,
+        #[starlark(require = named)] clean_env: Option<bool>,
+        #[starlark(require = named)] env_allowlist: Option<UnpackList<String>>,
         #[starlark(require = named)] dep_files: Option<SmallMap<&'v str, &'v ArtifactTag>>,
         #[starlark(require = named)] metadata_env_var: Option<String>,
         #[starlark(require = named)] metadata_path: Option<String>,
@@ -148,6 +199,7 @@ pub(crate) fn analysis_actions_methods_run(methods: &mut MethodsBuilder) {
         #[starlark(require = named, default = false)] allow_cache_upload: bool,
         #[starlark(require = named, default = false)] allow_dep_file_cache_upload: bool,
         #[starlark(require = named, default = false)] force_full_hybrid_if_capable: bool,
+        #[starlark(require = named, default = false)] remote_cache_read_only: bool,
         #[starlark(require = named)] exe: Option<
             Either<ValueOf<'v, &'v WorkerRunInfo<'v>>, ValueOf<'v, &'v RunInfo<'v>>>,
         >,
@@ -156,6 +208,8 @@ pub(crate) fn analysis_actions_methods_run(methods: &mut MethodsBuilder) {
         eval: &mut Evaluator<'v, '_, '_>,
         #[starlark(require = named, default=UnpackList::default())]
         remote_execution_dependencies: UnpackList<SmallMap<&'v str, &'v str>>,
+        #[starlark(require = named, default = SmallMap::new())]
+        remote_execution_properties: SmallMap<String, String>,
     ) -> anyhow::Result<NoneType> {
         struct RunCommandArtifactVisitor {
             inner: SimpleCommandLineArtifactVisitor,
@@ -246,6 +300,46 @@ pub(crate) fn analysis_actions_methods_run(methods: &mut MethodsBuilder) {
             }
         };
 
+        let memory_mb = match memory_mb {
+            None => None,
+            Some(v) if v < 1 => return Err(RunActionError::InvalidMemory(v).into()),
+            Some(v) => Some(v as usize),
+        };
+
+        let timeout = match timeout {
+            None => None,
+            Some(v) if v < 1 => return Err(RunActionError::InvalidTimeout(v).into()),
+            Some(v) => Some(Duration::from_secs(v as u64)),
+        };
+
+        let retry_policy = match (retries, retry_on) {
+            (None, None) => None,
+            (None, Some(_)) => return Err(RunActionError::RetryOnWithoutRetries.into()),
+            (Some(v), _) if v < 0 => return Err(RunActionError::InvalidRetries(v).into()),
+            (Some(v), retry_on) => {
+                let retry_on = retry_on.map(|l| l.items).unwrap_or_default();
+                Some(Arc::new(
+                    ActionRetryPolicy::for_action(v as u32, &retry_on)
+                        .context("Invalid `retry_on`")?,
+                ))
+            }
+        };
+
+        let max_inputs = this.state().action_input_budget(&category);
+        if let Some(max_inputs) = max_inputs {
+            let mut command_lines: Vec<&dyn CommandLineArgLike> =
+                vec![&starlark_args, &starlark_exe];
+            if let Some(env) = &env {
+                for v in env.typed.values() {
+                    command_lines.push(ValueAsCommandLineLike::unpack_value_err(*v)?.0);
+                }
+            }
+            check_input_budget(&category, max_inputs, &command_lines)?;
+        }
+        // The size of the inputs is only known once they are built, so it is checked when the
+        // action runs.
+        let max_input_bytes = this.state().action_input_size_budget(&category);
+
         let starlark_env = match env {
             None => Value::new_none(),
             Some(env) => {
@@ -331,6 +425,11 @@ pub(crate) fn analysis_actions_methods_run(methods: &mut MethodsBuilder) {
             executor_preference,
             always_print_stderr,
             weight,
+            memory_mb,
+            timeout,
+            retry_policy,
+            clean_env,
+            env_allowlist: env_allowlist.map(|l| l.items).unwrap_or_default(),
             low_pass_filter,
             dep_files: dep_files_configuration,
             metadata_param,
@@ -338,8 +437,11 @@ pub(crate) fn analysis_actions_methods_run(methods: &mut MethodsBuilder) {
             allow_cache_upload,
             allow_dep_file_cache_upload,
             force_full_hybrid_if_capable,
+            remote_cache_read_only,
             unique_input_inodes,
             remote_execution_dependencies: re_dependencies,
+            remote_execution_properties: remote_execution_properties.into_iter().collect(),
+            max_input_bytes,
         };
         this.state().register_action(
             artifacts.inputs,
@@ -0,0 +1,86 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use buck2_build_api::interpreter::rule_defs::artifact::associated::AssociatedArtifacts;
+use buck2_build_api::interpreter::rule_defs::artifact::output_artifact_like::OutputArtifactArg;
+use buck2_build_api::interpreter::rule_defs::artifact::starlark_declared_artifact::StarlarkDeclaredArtifact;
+use buck2_build_api::interpreter::rule_defs::context::AnalysisActions;
+use buck2_build_api::stamp::StampInfo;
+use buck2_execute::execute::request::OutputType;
+use indexmap::indexset;
+use indexmap::IndexSet;
+use starlark::environment::MethodsBuilder;
+use starlark::eval::Evaluator;
+use starlark::starlark_module;
+use starlark::values::ValueTyped;
+
+use crate::actions::impls::stamp::UnregisteredStampAction;
+
+#[derive(Debug, buck2_error::Error)]
+enum StampFileError {
+    #[error("Unknown stamp key `{0}`, expected one of: {}", StampInfo::KEYS.join(", "))]
+    UnknownKey(String),
+    #[error("Duplicate stamp key `{0}`")]
+    DuplicateKey(String),
+}
+
+#[starlark_module]
+pub(crate) fn analysis_actions_methods_stamp(methods: &mut MethodsBuilder) {
+    /// Returns an `artifact` holding volatile information about the build, one `<key> <value>`
+    /// line per key.
+    ///
+    /// * `keys` (optional): which values to write, in order. Defaults to all of them:
+    ///     * `revision`: the revision of the repository, or an empty string if it is not known
+    ///     * `timestamp`: when the command started, in seconds since the epoch
+    ///
+    /// The values are only filled in when stamping is enabled with the buckconfig
+    /// `buck2.stamp = true`. Otherwise, `revision` is empty and `timestamp` is 0, so the output
+    /// never changes.
+    ///
+    /// When stamping is enabled, the action is never cached and runs again on every command.
+    /// Actions which use its output only run again if its contents changed, so to keep builds
+    /// incremental, only stamp the keys you need: `revision` only changes when the repository
+    /// does, while `timestamp` changes on every command.
+    fn stamp_file<'v>(
+        this: &AnalysisActions<'v>,
+        #[starlark(require = pos)] output: OutputArtifactArg<'v>,
+        #[starlark(require = named)] keys: Option<Vec<String>>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> anyhow::Result<ValueTyped<'v, StarlarkDeclaredArtifact>> {
+        let keys = match keys {
+            Some(keys) => {
+                let mut seen = IndexSet::new();
+                for key in &keys {
+                    if !StampInfo::KEYS.contains(&key.as_str()) {
+                        return Err(StampFileError::UnknownKey(key.clone()).into());
+                    }
+                    if !seen.insert(key.as_str()) {
+                        return Err(StampFileError::DuplicateKey(key.clone()).into());
+                    }
+                }
+                keys
+            }
+            None => StampInfo::KEYS.iter().map(|k| (*k).to_owned()).collect(),
+        };
+
+        let mut this = this.state();
+        let (declaration, output_artifact) =
+            this.get_or_declare_output(eval, output, OutputType::File)?;
+
+        this.register_action(
+            IndexSet::new(),
+            indexset![output_artifact],
+            UnregisteredStampAction { keys },
+            None,
+            None,
+        )?;
+
+        Ok(declaration.into_declared_artifact(AssociatedArtifacts::new()))
+    }
+}
@@ -96,7 +96,9 @@ pub(crate) fn analysis_actions_methods_unsorted(builder: &mut MethodsBuilder) {
         )
     }
 
-    /// Allocate a new input tag. Used with the `dep_files` argument to `run`.
+    /// Allocate a new artifact tag. Wrap inputs and outputs with `tag.tag_artifacts(...)` or
+    /// `tag.tag_inputs(...)` to group them, e.g. with the `dep_files` argument to `run`, to
+    /// associate a dep file (the tagged output) with the inputs it covers (the tagged inputs).
     fn artifact_tag<'v>(this: &AnalysisActions<'v>) -> anyhow::Result<ArtifactTag> {
         let _ = this;
         Ok(ArtifactTag::new())
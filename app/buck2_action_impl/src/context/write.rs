@@ -77,6 +77,11 @@ pub(crate) fn analysis_actions_methods_write(methods: &mut MethodsBuilder) {
     ///   rendering artifact paths. You generally shouldn't use this if you plan to use this action
     ///   as the input for anything else, as this would effectively result in losing all shared
     ///   caching. (defaults to `False`)
+    ///
+    /// The content is only checked to be valid JSON during analysis: it is serialized straight
+    /// into the output when the action runs, without ever being rendered as a Starlark string.
+    /// For large values (e.g. manifests listing many artifacts), this is much cheaper than
+    /// `write(output, json.encode(content))`.
     fn write_json<'v>(
         this: &AnalysisActions<'v>,
         #[starlark(require = pos)] output: OutputArtifactArg<'v>,
@@ -34,6 +34,8 @@ use buck2_build_api::interpreter::rule_defs::artifact::starlark_artifact::Starla
 use buck2_build_api::interpreter::rule_defs::artifact::starlark_artifact_value::StarlarkArtifactValue;
 use buck2_build_api::interpreter::rule_defs::artifact::starlark_declared_artifact::StarlarkDeclaredArtifact;
 use buck2_build_api::interpreter::rule_defs::context::AnalysisContext;
+use buck2_common::action_input_budgets::ActionInputBudgets;
+use buck2_common::action_input_budgets::HasActionInputBudgets;
 use buck2_core::base_deferred_key::BaseDeferredKey;
 use buck2_error::internal_error;
 use buck2_error::BuckErrorContext;
@@ -226,6 +228,7 @@ impl Deferred for DynamicLambda {
             eval_bxl_for_dynamic_output(key, self, deferred_ctx, dice).await
         } else {
             let proto_rule = "dynamic_lambda".to_owned();
+            let action_input_budgets = dice.action_input_budgets().await?;
 
             let start_event = buck2_data::AnalysisStart {
                 target: Some(buck2_data::analysis_start::Target::DynamicLambda(
@@ -247,8 +250,12 @@ impl Deferred for DynamicLambda {
                         let mut eval = Evaluator::new(&env);
                         eval.set_print_handler(&print);
                         eval.set_soft_error_handler(&Buck2StarlarkSoftErrorHandler);
-                        let dynamic_lambda_ctx_data =
-                            dynamic_lambda_ctx_data(self, deferred_ctx, &env)?;
+                        let dynamic_lambda_ctx_data = dynamic_lambda_ctx_data(
+                            self,
+                            deferred_ctx,
+                            action_input_budgets,
+                            &env,
+                        )?;
                         let ctx = AnalysisContext::prepare(
                             heap,
                             dynamic_lambda_ctx_data.lambda.attributes()?,
@@ -256,6 +263,7 @@ impl Deferred for DynamicLambda {
                             dynamic_lambda_ctx_data.lambda.plugins()?,
                             dynamic_lambda_ctx_data.registry,
                             dynamic_lambda_ctx_data.digest_config,
+                            None,
                         );
 
                         DynamicLambda::invoke_dynamic_output_lambda(
@@ -324,6 +332,7 @@ pub struct DynamicLambdaCtxData<'v> {
 pub fn dynamic_lambda_ctx_data<'v>(
     dynamic_lambda: &'v DynamicLambda,
     deferred_ctx: &mut dyn DeferredCtx,
+    action_input_budgets: Option<Arc<ActionInputBudgets>>,
     env: &'v Module,
 ) -> anyhow::Result<DynamicLambdaCtxData<'v>> {
     let heap = env.heap();
@@ -365,6 +374,7 @@ pub fn dynamic_lambda_ctx_data<'v>(
         deferred,
     )?;
     registry.set_action_key(Arc::from(deferred_ctx.get_action_key()));
+    registry.set_action_input_budgets(action_input_budgets);
 
     let mut artifacts = SmallMap::with_capacity(dynamic_lambda.dynamic.len());
     let fs = deferred_ctx.project_filesystem();
@@ -7,10 +7,15 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
 use buck2_build_api::analysis::registry::AnalysisRegistry;
 use buck2_build_api::interpreter::rule_defs::context::AnalysisContext;
 use buck2_build_api::interpreter::rule_defs::plugins::AnalysisPlugins;
 use buck2_build_api::interpreter::rule_defs::register_rule_defs;
+use buck2_build_api::invocation_info::InvocationInfo;
+use buck2_common::action_input_budgets::ActionInputBudgets;
 use buck2_core::base_deferred_key::BaseDeferredKey;
 use buck2_core::configuration::data::ConfigurationData;
 use buck2_core::execution_types::execution::ExecutionPlatformResolution;
@@ -35,6 +40,14 @@ use starlark::StarlarkResultExt;
 fn run_ctx_test(
     content: &str,
     result_handler: impl FnOnce(anyhow::Result<Value>) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    run_ctx_test_with_budgets(content, None, result_handler)
+}
+
+fn run_ctx_test_with_budgets(
+    content: &str,
+    action_input_budgets: Option<ActionInputBudgets>,
+    result_handler: impl FnOnce(anyhow::Result<Value>) -> anyhow::Result<()>,
 ) -> anyhow::Result<()> {
     let func_mod = Module::new();
     let globals = GlobalsBuilder::standard().with(register_rule_defs).build();
@@ -64,10 +77,11 @@ fn run_ctx_test(
     eval.set_loader(&file_loader);
     let label = TargetLabel::testing_parse("root//foo/bar:some_name")
         .configure(ConfigurationData::testing_new());
-    let registry = AnalysisRegistry::new_from_owner(
+    let mut registry = AnalysisRegistry::new_from_owner(
         BaseDeferredKey::TargetLabel(label.dupe()),
         ExecutionPlatformResolution::unspecified(),
     )?;
+    registry.set_action_input_budgets(action_input_budgets.map(Arc::new));
     let attributes =
         ValueOfUnchecked::new_checked(eval.heap().alloc(AllocStruct([("name", "some_name")])))?;
     let plugins = eval
@@ -82,6 +96,13 @@ fn run_ctx_test(
         Some(plugins),
         registry,
         DigestConfig::testing_default(),
+        Some(Arc::new(InvocationInfo {
+            uuid: "some-uuid".to_owned(),
+            client_metadata: BTreeMap::from([
+                ("ci".to_owned(), "true".to_owned()),
+                ("id".to_owned(), "some_client".to_owned()),
+            ]),
+        })),
     ));
 
     let returned = eval
@@ -107,6 +128,23 @@ fn ctx_instantiates() -> anyhow::Result<()> {
     })
 }
 
+#[test]
+fn build_info() -> anyhow::Result<()> {
+    let content = indoc!(
+        r#"
+         def test(ctx):
+             assert_eq("some-uuid", ctx.build_info.uuid)
+             assert_eq(True, ctx.build_info.ci)
+             assert_eq({"ci": "true", "id": "some_client"}, ctx.build_info.client_metadata)
+             return None
+         "#
+    );
+    run_ctx_test(content, |ret| {
+        ret?;
+        Ok(())
+    })
+}
+
 #[test]
 fn declare_output_declares_outputs() -> anyhow::Result<()> {
     let content = indoc!(
@@ -220,3 +258,83 @@ fn declare_output_require_bound() -> anyhow::Result<()> {
         ),
     })
 }
+
+#[test]
+fn run_over_input_budget() -> anyhow::Result<()> {
+    let content = indoc!(
+        r#"
+         def test(c):
+             srcs = [c.actions.write("src{}".format(i), "") for i in range(3)]
+             out = c.actions.declare_output("out")
+             c.actions.run(["cat", srcs, out.as_output()], category = "cxx_link")
+         "#
+    );
+
+    let budgets = ActionInputBudgets::new(Some("cxx_link=2"), None)?;
+    let expect = "Action with category `cxx_link` has 3 inputs, which is over its budget of 2";
+    run_ctx_test_with_budgets(content, Some(budgets), |ret| match ret {
+        Err(e) if format!("{:#}", e).contains(expect) => {
+            // All the inputs are written by the target itself.
+            assert!(format!("{:#}", e).contains("3  root//foo/bar:some_name"));
+            Ok(())
+        }
+        _ => panic!(
+            "Expected a specific failure containing `{}`, got {:?}",
+            expect, ret
+        ),
+    })
+}
+
+#[test]
+fn run_within_input_budget() -> anyhow::Result<()> {
+    let content = indoc!(
+        r#"
+         def test(c):
+             srcs = [c.actions.write("src{}".format(i), "") for i in range(3)]
+             out = c.actions.declare_output("out")
+             c.actions.run(["cat", srcs, srcs[0], out.as_output()], category = "cxx_link")
+         "#
+    );
+
+    let budgets = ActionInputBudgets::new(Some("cxx_link=3"), None)?;
+    run_ctx_test_with_budgets(content, Some(budgets), |ret| {
+        ret?;
+        Ok(())
+    })
+}
+
+#[test]
+fn run_with_timeout() -> anyhow::Result<()> {
+    let content = indoc!(
+        r#"
+         def test(c):
+             out = c.actions.declare_output("out")
+             c.actions.run(["touch", out.as_output()], category = "test_category", timeout = 60)
+         "#
+    );
+
+    run_ctx_test(content, |ret| {
+        ret?;
+        Ok(())
+    })
+}
+
+#[test]
+fn run_with_invalid_timeout() -> anyhow::Result<()> {
+    let content = indoc!(
+        r#"
+         def test(c):
+             out = c.actions.declare_output("out")
+             c.actions.run(["touch", out.as_output()], category = "test_category", timeout = 0)
+         "#
+    );
+
+    let expect = "`timeout` must be a positive number of seconds, got `0`";
+    run_ctx_test(content, |ret| match ret {
+        Err(e) if format!("{:#}", e).contains(expect) => Ok(()),
+        _ => panic!(
+            "Expected a specific failure containing `{}`, got {:?}",
+            expect, ret
+        ),
+    })
+}
@@ -52,6 +52,7 @@ use dupe::IterDupedExt;
 use futures::FutureExt;
 use smallvec::SmallVec;
 
+use crate::analysis::env::get_native_rule_spec;
 use crate::analysis::env::get_user_defined_rule_spec;
 use crate::analysis::env::run_analysis;
 use crate::analysis::env::RuleSpec;
@@ -218,6 +219,17 @@ pub async fn get_rule_spec(
     Ok(get_user_defined_rule_spec(module.env().dupe(), func))
 }
 
+async fn get_rule_spec_for_rule_type(
+    ctx: &mut DiceComputations<'_>,
+    rule_type: &RuleType,
+) -> anyhow::Result<Box<dyn RuleSpec + Send>> {
+    match rule_type {
+        RuleType::Starlark(func) => Ok(Box::new(get_rule_spec(ctx, func).await?)),
+        RuleType::Native(rule_type) => Ok(Box::new(get_native_rule_spec(*rule_type))),
+        RuleType::Forward => Err(internal_error!("Forward nodes have no rule implementation")),
+    }
+}
+
 async fn get_analysis_result(
     ctx: &mut DiceComputations<'_>,
     target: &ConfiguredTargetLabel,
@@ -243,7 +255,7 @@ async fn get_analysis_result_inner(
     let configured_node = configured_node.as_ref();
 
     let ((res, now), spans): ((anyhow::Result<_>, Instant), _) = match configured_node.rule_type() {
-        RuleType::Starlark(func) => {
+        rule_type @ (RuleType::Starlark(_) | RuleType::Native(_)) => {
             let (dep_analysis, query_results, profile_mode) = ctx
                 .try_compute3(
                     |ctx| get_dep_analysis(configured_node, ctx).boxed(),
@@ -257,10 +269,10 @@ async fn get_analysis_result_inner(
 
             let now = Instant::now();
             let (res, spans) = async_record_root_spans(async {
-                let rule_spec = get_rule_spec(ctx, func).await?;
+                let rule_spec = get_rule_spec_for_rule_type(ctx, rule_type).await?;
                 let start_event = buck2_data::AnalysisStart {
                     target: Some(target.as_proto().into()),
-                    rule: func.to_string(),
+                    rule: rule_type.to_string(),
                 };
 
                 span_async(start_event, async {
@@ -283,7 +295,7 @@ async fn get_analysis_result_inner(
                                         dep_analysis,
                                         query_results,
                                         configured_node.execution_platform_resolution(),
-                                        &rule_spec,
+                                        &*rule_spec,
                                         configured_node,
                                         &profile_mode,
                                     )
@@ -305,7 +317,7 @@ async fn get_analysis_result_inner(
                         result,
                         buck2_data::AnalysisEnd {
                             target: Some(target.as_proto().into()),
-                            rule: func.to_string(),
+                            rule: rule_type.to_string(),
                             profile,
                             declared_actions,
                             declared_artifacts,
@@ -316,6 +328,18 @@ async fn get_analysis_result_inner(
             })
             .await;
 
+            // Rule implementation failures are usually best routed to the owners of the target.
+            let res = match configured_node.oncall() {
+                Some(oncall) => res.with_context(|| {
+                    format!(
+                        "Target `{}` is owned by oncall `{}`, contact them for help",
+                        target.unconfigured(),
+                        oncall
+                    )
+                }),
+                None => res,
+            };
+
             ((res, now), spans)
         }
         RuleType::Forward => {
@@ -364,8 +388,8 @@ fn all_deps(nodes: &[ConfiguredTargetNode]) -> LabelIndexedSet<ConfiguredTargetN
                 RuleType::Starlark(_) => {
                     result.insert(node.dupe());
                 }
-                RuleType::Forward => {
-                    // No starlark code ran on forward node.
+                RuleType::Native(_) | RuleType::Forward => {
+                    // No starlark code ran on native or forward nodes.
                 }
             }
 
@@ -17,9 +17,16 @@ use buck2_build_api::analysis::registry::AnalysisRegistry;
 use buck2_build_api::analysis::AnalysisResult;
 use buck2_build_api::interpreter::rule_defs::cmd_args::value::FrozenCommandLineArg;
 use buck2_build_api::interpreter::rule_defs::context::AnalysisContext;
+use buck2_build_api::interpreter::rule_defs::native_rules::native_rule_providers;
+use buck2_build_api::interpreter::rule_defs::pprint::PprintOwner;
 use buck2_build_api::interpreter::rule_defs::provider::builtin::template_placeholder_info::FrozenTemplatePlaceholderInfo;
 use buck2_build_api::interpreter::rule_defs::provider::collection::FrozenProviderCollectionValue;
 use buck2_build_api::interpreter::rule_defs::provider::collection::ProviderCollection;
+use buck2_build_api::invocation_info::HasInvocationInfo;
+use buck2_build_api::invocation_info::HasPerTransactionInvocationInfo;
+use buck2_build_api::phase_budgets::HasPhaseBudgets;
+use buck2_common::action_input_budgets::HasActionInputBudgets;
+use buck2_common::relative_label_policy::HasRelativeLabelPolicy;
 use buck2_core::base_deferred_key::BaseDeferredKey;
 use buck2_core::execution_types::execution::ExecutionPlatformResolution;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
@@ -40,6 +47,7 @@ use buck2_interpreter::starlark_profiler::profiler::StarlarkProfilerOpt;
 use buck2_interpreter::types::rule::FROZEN_PROMISE_ARTIFACT_MAPPINGS_GET_IMPL;
 use buck2_interpreter::types::rule::FROZEN_RULE_GET_IMPL;
 use buck2_node::nodes::configured::ConfiguredTargetNodeRef;
+use buck2_node::rule_type::NativeRuleType;
 use buck2_node::rule_type::StarlarkRuleType;
 use dice::DiceComputations;
 use dupe::Dupe;
@@ -253,6 +261,7 @@ async fn run_analysis_with_env_underlying(
 ) -> anyhow::Result<AnalysisResult> {
     let env = Module::new();
     let print = EventDispatcherPrintHandler(get_dispatcher());
+    let pprint_owner = PprintOwner(node.label().to_string());
 
     let (attributes, plugins) = {
         let resolution_ctx = RuleAnalysisAttrResolutionContext {
@@ -268,10 +277,14 @@ async fn run_analysis_with_env_underlying(
         )
     };
 
-    let registry = AnalysisRegistry::new_from_owner(
+    let mut registry = AnalysisRegistry::new_from_owner(
         BaseDeferredKey::TargetLabel(node.label().dupe()),
         analysis_env.execution_platform.dupe(),
     )?;
+    let package = node.label().pkg();
+    let relative_labels_allowed = dice.relative_labels_allowed(package.as_cell_path()).await?;
+    registry.set_anon_target_base_package(package, relative_labels_allowed);
+    registry.set_action_input_budgets(dice.action_input_budgets().await?);
 
     let mut profiler_opt = profile_mode.profile_mode().map(|profile_mode| {
         StarlarkProfiler::new(
@@ -286,6 +299,14 @@ async fn run_analysis_with_env_underlying(
         Some(profiler) => StarlarkProfilerOpt::for_profiler(profiler),
     };
 
+    // Only held while evaluating the rule implementation: like the debugger permit below, it
+    // must not be held while running the promises, which may need other analyses.
+    let analysis_permit = dice
+        .per_transaction_data()
+        .get_phase_budgets()
+        .acquire_analysis()
+        .await;
+
     let (dice, mut eval, ctx, list_res) = with_starlark_eval_provider(
         dice,
         &mut profiler,
@@ -294,6 +315,7 @@ async fn run_analysis_with_env_underlying(
             let (mut eval, _) = provider.make(&env)?;
             eval.set_print_handler(&print);
             eval.set_soft_error_handler(&Buck2StarlarkSoftErrorHandler);
+            eval.extra = Some(&pprint_owner);
 
             let ctx = AnalysisContext::prepare(
                 eval.heap(),
@@ -302,6 +324,8 @@ async fn run_analysis_with_env_underlying(
                 Some(plugins.into()),
                 registry,
                 dice.global_data().get_digest_config(),
+                dice.per_transaction_data()
+                    .get_per_transaction_invocation_info(),
             );
 
             let list_res = analysis_env.rule_spec.invoke(&mut eval, ctx)?;
@@ -320,6 +344,7 @@ async fn run_analysis_with_env_underlying(
         },
     )
     .await?;
+    drop(analysis_permit);
 
     ctx.actions
         .run_promises(
@@ -329,6 +354,12 @@ async fn run_analysis_with_env_underlying(
         )
         .await?;
 
+    // The rule read the invocation info synchronously from the per-transaction data, which is
+    // the value of the injected key, so the dependency can be recorded afterwards.
+    if ctx.build_info_read() {
+        dice.get_invocation_info().await?;
+    }
+
     // TODO: Convert the ValueError from `try_from_value` better than just printing its Debug
     let res_typed = ProviderCollection::try_from_value(list_res)?;
     {
@@ -442,3 +473,27 @@ pub fn get_user_defined_rule_spec(
         name: rule_type.name.clone(),
     }
 }
+
+/// The rule spec of a rule implemented by buck2 itself, whose analysis doesn't run Starlark.
+pub fn get_native_rule_spec(rule_type: NativeRuleType) -> impl RuleSpec {
+    struct Impl(NativeRuleType);
+
+    impl RuleSpec for Impl {
+        fn invoke<'v>(
+            &self,
+            eval: &mut Evaluator<'v, '_, '_>,
+            ctx: ValueTyped<'v, AnalysisContext<'v>>,
+        ) -> anyhow::Result<Value<'v>> {
+            native_rule_providers(self.0, &ctx, eval.heap())
+        }
+
+        fn promise_artifact_mappings<'v>(
+            &self,
+            _eval: &mut Evaluator<'v, '_, '_>,
+        ) -> anyhow::Result<SmallMap<String, Value<'v>>> {
+            Ok(SmallMap::new())
+        }
+    }
+
+    Impl(rule_type)
+}
@@ -54,6 +54,12 @@ enum ResolveMacroError {
     ExpectedRunInfo(String),
     #[error("Can't expand unrecognized macros (`{0}`).")]
     UnrecognizedMacroUnimplemented(String),
+    #[error(
+        "`$(location {0})` expands to nothing because the target has no default outputs. \
+        Refer to one of its sub-targets instead: [{sub_targets}]",
+        sub_targets = .1.join(", ")
+    )]
+    LocationHasNoDefaultOutputs(ConfiguredProvidersLabel, Vec<String>),
 }
 
 pub trait ConfiguredStringWithMacrosExt {
@@ -116,7 +122,21 @@ fn resolve_configured_macro(
         ConfiguredMacro::Location(target) => {
             let providers_value = ctx.get_dep(target)?;
             let providers = providers_value.provider_collection();
-            Ok(ResolvedMacro::Location(providers.default_info()))
+            let default_info = providers.default_info();
+            // Multiple default outputs expand in the order the rule declared them, so only the
+            // empty case is ambiguous: silently expanding to an empty string hides the mistake.
+            if default_info.default_outputs().is_empty() {
+                return Err(ResolveMacroError::LocationHasNoDefaultOutputs(
+                    target.clone(),
+                    default_info
+                        .sub_targets()
+                        .keys()
+                        .map(|name| format!("`{}`", name))
+                        .collect(),
+                )
+                .into());
+            }
+            Ok(ResolvedMacro::Location(default_info))
         }
         ConfiguredMacro::Exe { label, .. } => {
             // Don't need to consider exec_dep as it already was applied when configuring the label.
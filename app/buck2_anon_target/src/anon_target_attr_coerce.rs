@@ -15,6 +15,10 @@ use buck2_build_api::interpreter::rule_defs::artifact::starlark_artifact_like::V
 use buck2_build_api::interpreter::rule_defs::artifact::starlark_promise_artifact::StarlarkPromiseArtifact;
 use buck2_build_api::interpreter::rule_defs::provider::dependency::Dependency;
 use buck2_build_api::interpreter::rule_defs::resolved_macro::ResolvedStringWithMacros;
+use buck2_core::package::PackageLabel;
+use buck2_core::pattern::pattern::lex_target_pattern;
+use buck2_core::pattern::pattern::PatternData;
+use buck2_core::pattern::pattern_type::ProvidersPatternExtra;
 use buck2_core::provider::label::ProvidersLabel;
 use buck2_core::soft_error;
 use buck2_interpreter::types::configured_providers_label::StarlarkProvidersLabel;
@@ -164,6 +168,10 @@ impl AnonTargetAttrTypeCoerce for AttrType {
                     Ok(AnonTargetAttr::Label(ProvidersLabel::default_for(
                         label.label().clone(),
                     )))
+                } else if let Some(s) = value.unpack_str() {
+                    Ok(AnonTargetAttr::Label(to_anon_target_relative_label(
+                        s, ctx,
+                    )?))
                 } else {
                     Err(AnonTargetCoercionError::type_error(
                         "providers_label or target_label",
@@ -203,6 +211,16 @@ pub(crate) enum AnonTargetCoercionError {
         "`transition_dep`, and `toolchain_dep` are not supported. By design, anon targets do not support configurations/transitions."
     )]
     OnlyIdentityDepSupported,
+    #[error(
+        "Only relative labels like `:foo` can be passed as strings to `attrs.label()`, got `{0}`"
+    )]
+    LabelStringNotRelative(String),
+    #[error("Relative label `{0}` can only be used in anon targets created by rule analysis")]
+    RelativeLabelWithoutBasePackage(String),
+    #[error(
+        "Relative label `{1}` is not allowed in package `{0}` by `project.forbid_relative_labels`, use `{0}{1}` instead"
+    )]
+    RelativeLabelForbidden(PackageLabel, String),
 }
 
 impl AnonTargetCoercionError {
@@ -225,6 +243,33 @@ impl AnonTargetCoercionError {
     }
 }
 
+/// Resolves a label like `:foo[bar]` against the package of the target whose analysis created
+/// the anon target.
+fn to_anon_target_relative_label(value: &str, ctx: &AnonAttrCtx) -> anyhow::Result<ProvidersLabel> {
+    if !ProvidersLabel::maybe_relative_label(value) {
+        return Err(AnonTargetCoercionError::LabelStringNotRelative(value.to_owned()).into());
+    }
+    let package = ctx.base_package.as_ref().ok_or_else(|| {
+        AnonTargetCoercionError::RelativeLabelWithoutBasePackage(value.to_owned())
+    })?;
+    if !ctx.relative_labels_allowed {
+        return Err(AnonTargetCoercionError::RelativeLabelForbidden(
+            package.dupe(),
+            value.to_owned(),
+        )
+        .into());
+    }
+    match lex_target_pattern::<ProvidersPatternExtra>(value, false)?
+        .pattern
+        .reject_ambiguity()?
+    {
+        PatternData::TargetInPackage {
+            target_name, extra, ..
+        } => Ok(extra.into_providers_label(package.dupe(), target_name.as_ref())),
+        _ => Err(AnonTargetCoercionError::LabelStringNotRelative(value.to_owned()).into()),
+    }
+}
+
 fn to_anon_target_any(value: Value, ctx: &AnonAttrCtx) -> anyhow::Result<AnonTargetAttr> {
     if value.is_none() {
         Ok(AnonTargetAttr::None)
@@ -390,3 +435,55 @@ fn to_anon_target_list(
         )))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::execution_types::execution::ExecutionPlatformResolution;
+
+    use super::*;
+
+    fn ctx(base_package: Option<&str>, relative_labels_allowed: bool) -> AnonAttrCtx {
+        AnonAttrCtx {
+            execution_platform_resolution: ExecutionPlatformResolution::unspecified(),
+            base_package: base_package.map(PackageLabel::testing_parse),
+            relative_labels_allowed,
+        }
+    }
+
+    #[test]
+    fn test_relative_label() {
+        let ctx = ctx(Some("root//foo/bar"), true);
+        assert_eq!(
+            "root//foo/bar:baz",
+            to_anon_target_relative_label(":baz", &ctx)
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "root//foo/bar:baz[qux]",
+            to_anon_target_relative_label(":baz[qux]", &ctx)
+                .unwrap()
+                .to_string()
+        );
+        assert!(to_anon_target_relative_label("root//foo/bar:baz", &ctx).is_err());
+        assert!(to_anon_target_relative_label("baz", &ctx).is_err());
+    }
+
+    #[test]
+    fn test_relative_label_errors() {
+        let err = to_anon_target_relative_label(":baz", &ctx(None, true)).unwrap_err();
+        assert!(
+            err.to_string().contains("created by rule analysis"),
+            "{}",
+            err
+        );
+
+        let err =
+            to_anon_target_relative_label(":baz", &ctx(Some("root//foo/bar"), false)).unwrap_err();
+        assert!(
+            err.to_string().contains("use `root//foo/bar:baz` instead"),
+            "{}",
+            err
+        );
+    }
+}
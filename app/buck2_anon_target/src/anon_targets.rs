@@ -34,6 +34,7 @@ use buck2_build_api::interpreter::rule_defs::context::AnalysisContext;
 use buck2_build_api::interpreter::rule_defs::plugins::AnalysisPlugins;
 use buck2_build_api::interpreter::rule_defs::provider::collection::FrozenProviderCollectionValue;
 use buck2_build_api::interpreter::rule_defs::provider::collection::ProviderCollection;
+use buck2_common::action_input_budgets::HasActionInputBudgets;
 use buck2_configured::nodes::calculation::find_execution_platform_by_configuration;
 use buck2_core::base_deferred_key::BaseDeferredKey;
 use buck2_core::base_deferred_key::BaseDeferredKeyDyn;
@@ -98,6 +99,10 @@ use crate::promise_artifacts::PromiseArtifactRegistry;
 pub struct AnonTargetsRegistry<'v> {
     // We inherit the execution platform of our parent
     execution_platform: ExecutionPlatformResolution,
+    /// The package of the target being analysed, if any, which relative labels in attributes are
+    /// resolved against.
+    base_package: Option<PackageLabel>,
+    relative_labels_allowed: bool,
     promises: AnonPromises<'v>,
     promise_artifact_registry: PromiseArtifactRegistry,
 }
@@ -137,6 +142,8 @@ impl AnonTargetKey {
 
     pub(crate) fn new<'v>(
         execution_platform: &ExecutionPlatformResolution,
+        base_package: Option<&PackageLabel>,
+        relative_labels_allowed: bool,
         rule: ValueTyped<'v, FrozenRuleCallable>,
         attributes: DictOf<'v, &'v str, Value<'v>>,
     ) -> anyhow::Result<Self> {
@@ -147,7 +154,8 @@ impl AnonTargetKey {
         let attrs_spec = rule.attributes();
         let mut attrs = OrderedMap::with_capacity(attrs_spec.len());
 
-        let anon_attr_ctx = AnonAttrCtx::new(execution_platform);
+        let anon_attr_ctx =
+            AnonAttrCtx::new(execution_platform, base_package, relative_labels_allowed);
 
         for (k, v) in entries {
             if k == "name" {
@@ -304,6 +312,7 @@ impl AnonTargetKey {
         );
 
         let rule_impl = get_rule_spec(dice, self.0.rule_type()).await?;
+        let action_input_budgets = dice.action_input_budgets().await?;
         let env = Module::new();
         let print = EventDispatcherPrintHandler(get_dispatcher());
 
@@ -346,10 +355,11 @@ impl AnonTargetKey {
                             env.heap().alloc(AllocStruct(resolved_attrs)),
                         )?;
 
-                        let registry = AnalysisRegistry::new_from_owner(
+                        let mut registry = AnalysisRegistry::new_from_owner(
                             BaseDeferredKey::AnonTarget(self.0.dupe()),
                             exec_resolution,
                         )?;
+                        registry.set_action_input_budgets(action_input_budgets);
 
                         let ctx = AnalysisContext::prepare(
                             eval.heap(),
@@ -364,6 +374,8 @@ impl AnonTargetKey {
                             ),
                             registry,
                             dice.global_data().get_digest_config(),
+                            // Anon targets are shared between the targets which create them.
+                            None,
                         );
 
                         let list_res = rule_impl.invoke(&mut eval, ctx)?;
@@ -458,12 +470,20 @@ impl AnonTargetKey {
 /// Several attribute functions need a context, make one that is mostly useless.
 pub(crate) struct AnonAttrCtx {
     pub(crate) execution_platform_resolution: ExecutionPlatformResolution,
+    pub(crate) base_package: Option<PackageLabel>,
+    pub(crate) relative_labels_allowed: bool,
 }
 
 impl AnonAttrCtx {
-    fn new(execution_platform_resolution: &ExecutionPlatformResolution) -> Self {
+    fn new(
+        execution_platform_resolution: &ExecutionPlatformResolution,
+        base_package: Option<&PackageLabel>,
+        relative_labels_allowed: bool,
+    ) -> Self {
         Self {
             execution_platform_resolution: execution_platform_resolution.clone(),
+            base_package: base_package.cloned(),
+            relative_labels_allowed,
         }
     }
 
@@ -519,6 +539,8 @@ pub(crate) fn init_anon_target_registry_new() {
     ANON_TARGET_REGISTRY_NEW.init(|_phantom, execution_platform| {
         Box::new(AnonTargetsRegistry {
             execution_platform,
+            base_package: None,
+            relative_labels_allowed: true,
             promises: AnonPromises::default(),
             promise_artifact_registry: PromiseArtifactRegistry::new(),
         })
@@ -548,7 +570,13 @@ impl<'v> AnonTargetsRegistry<'v> {
         rule: ValueTyped<'v, FrozenRuleCallable>,
         attributes: DictOf<'v, &'v str, Value<'v>>,
     ) -> anyhow::Result<AnonTargetKey> {
-        AnonTargetKey::new(&self.execution_platform, rule, attributes)
+        AnonTargetKey::new(
+            &self.execution_platform,
+            self.base_package.as_ref(),
+            self.relative_labels_allowed,
+            rule,
+            attributes,
+        )
     }
 
     pub(crate) fn register_one(
@@ -578,6 +606,11 @@ impl<'v> AnonTargetsRegistryDyn<'v> for AnonTargetsRegistry<'v> {
         self
     }
 
+    fn set_base_package(&mut self, package: PackageLabel, relative_labels_allowed: bool) {
+        self.base_package = Some(package);
+        self.relative_labels_allowed = relative_labels_allowed;
+    }
+
     fn consumer_analysis_artifacts(&self) -> Vec<PromiseArtifact> {
         self.promise_artifact_registry.consumer_analysis_artifacts()
     }
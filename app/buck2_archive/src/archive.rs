@@ -0,0 +1,331 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use walkdir::WalkDir;
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub(crate) enum ArchiveFormat {
+    #[value(name = "tar.zst")]
+    TarZst,
+    #[value(name = "zip")]
+    Zip,
+}
+
+enum ArchiveEntryKind {
+    Dir,
+    File { is_executable: bool },
+    Symlink,
+}
+
+struct ArchiveEntry {
+    /// The path of the entry in the archive, `/`-separated.
+    name: String,
+    disk_path: PathBuf,
+    kind: ArchiveEntryKind,
+}
+
+/// Packs `src` into the `dest` archive. Entries are written in path order, with a fixed
+/// modification time and owner, and permissions that only depend on whether the file is
+/// executable, so the archive only depends on the contents of `src`.
+pub(crate) fn create(format: ArchiveFormat, src: &Path, dest: &Path) -> anyhow::Result<()> {
+    let entries = archive_entries(src)?;
+    match format {
+        ArchiveFormat::TarZst => create_tar_zst(&entries, dest),
+        ArchiveFormat::Zip => create_zip(&entries, dest),
+    }
+    .with_context(|| format!("Creating `{}` from `{}`", dest.display(), src.display()))
+}
+
+pub(crate) fn extract(format: ArchiveFormat, src: &Path, dest: &Path) -> anyhow::Result<()> {
+    if src.is_dir() {
+        return Err(anyhow::anyhow!(
+            "Cannot extract `{}`: it is a directory",
+            src.display()
+        ));
+    }
+    fs::create_dir_all(dest).with_context(|| format!("create_dir_all({})", dest.display()))?;
+    match format {
+        ArchiveFormat::TarZst => extract_tar_zst(src, dest),
+        ArchiveFormat::Zip => extract_zip(src, dest),
+    }
+    .with_context(|| format!("Extracting `{}`", src.display()))
+}
+
+fn entry_kind(metadata: &fs::Metadata) -> ArchiveEntryKind {
+    if metadata.is_symlink() {
+        ArchiveEntryKind::Symlink
+    } else if metadata.is_dir() {
+        ArchiveEntryKind::Dir
+    } else {
+        ArchiveEntryKind::File {
+            is_executable: is_executable(metadata),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// The entries to archive for `src`, in path order: the contents of a directory, or a file on
+/// its own under its name.
+fn archive_entries(src: &Path) -> anyhow::Result<Vec<ArchiveEntry>> {
+    let metadata = fs::symlink_metadata(src)
+        .with_context(|| format!("symlink_metadata({})", src.display()))?;
+    if !metadata.is_dir() {
+        let name = src
+            .file_name()
+            .and_then(|name| name.to_str())
+            .with_context(|| format!("Invalid file name `{}`", src.display()))?;
+        return Ok(vec![ArchiveEntry {
+            name: name.to_owned(),
+            disk_path: src.to_owned(),
+            kind: entry_kind(&metadata),
+        }]);
+    }
+
+    let mut entries = Vec::new();
+    for entry in WalkDir::new(src).min_depth(1).sort_by_file_name() {
+        let entry = entry?;
+        let name = entry
+            .path()
+            .strip_prefix(src)?
+            .components()
+            .map(|c| c.as_os_str().to_str())
+            .collect::<Option<Vec<_>>>()
+            .with_context(|| format!("Invalid file name `{}`", entry.path().display()))?
+            .join("/");
+        entries.push(ArchiveEntry {
+            name,
+            disk_path: entry.path().to_owned(),
+            kind: entry_kind(&entry.metadata()?),
+        });
+    }
+    Ok(entries)
+}
+
+fn file_mode(is_executable: bool) -> u32 {
+    if is_executable {
+        0o755
+    } else {
+        0o644
+    }
+}
+
+fn create_file(path: &Path) -> anyhow::Result<File> {
+    File::create(path).with_context(|| format!("create_file({})", path.display()))
+}
+
+fn open_file(path: &Path) -> anyhow::Result<File> {
+    File::open(path).with_context(|| format!("open_file({})", path.display()))
+}
+
+fn create_tar_zst(entries: &[ArchiveEntry], dest: &Path) -> anyhow::Result<()> {
+    let mut builder = tar::Builder::new(zstd::Encoder::new(create_file(dest)?, 0)?);
+    for entry in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+        match entry.kind {
+            ArchiveEntryKind::Dir => {
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_mode(0o755);
+                header.set_size(0);
+                builder.append_data(&mut header, &entry.name, io::empty())?;
+            }
+            ArchiveEntryKind::File { is_executable } => {
+                let file = open_file(&entry.disk_path)?;
+                header.set_entry_type(tar::EntryType::Regular);
+                header.set_mode(file_mode(is_executable));
+                header.set_size(file.metadata()?.len());
+                builder.append_data(&mut header, &entry.name, file)?;
+            }
+            ArchiveEntryKind::Symlink => {
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_mode(0o777);
+                header.set_size(0);
+                let target = fs::read_link(&entry.disk_path)
+                    .with_context(|| format!("read_link({})", entry.disk_path.display()))?;
+                builder.append_link(&mut header, &entry.name, target)?;
+            }
+        }
+    }
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+fn create_zip(entries: &[ArchiveEntry], dest: &Path) -> anyhow::Result<()> {
+    let mut zip = zip::ZipWriter::new(create_file(dest)?);
+    let options = zip::write::FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .last_modified_time(zip::DateTime::default());
+    for entry in entries {
+        match entry.kind {
+            ArchiveEntryKind::Dir => {
+                zip.add_directory(entry.name.as_str(), options.unix_permissions(0o755))?;
+            }
+            ArchiveEntryKind::File { is_executable } => {
+                zip.start_file(
+                    entry.name.as_str(),
+                    options.unix_permissions(file_mode(is_executable)),
+                )?;
+                io::copy(&mut open_file(&entry.disk_path)?, &mut zip)?;
+            }
+            ArchiveEntryKind::Symlink => {
+                return Err(anyhow::anyhow!(
+                    "Cannot store symlink `{}` in a zip archive",
+                    entry.name
+                ));
+            }
+        }
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+fn extract_tar_zst(src: &Path, dest: &Path) -> anyhow::Result<()> {
+    let mut archive = tar::Archive::new(zstd::Decoder::new(open_file(src)?)?);
+    archive.set_preserve_mtime(false);
+    archive.unpack(dest)?;
+    Ok(())
+}
+
+fn extract_zip(src: &Path, dest: &Path) -> anyhow::Result<()> {
+    zip::ZipArchive::new(open_file(src)?)?.extract(dest)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use std::time::SystemTime;
+
+    use super::*;
+
+    fn write_file(path: &Path, contents: &str, mtime: SystemTime) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+    }
+
+    /// Writes the same tree under `root`, creating the files in the given order and with the
+    /// given modification time.
+    fn write_tree(root: &Path, names: &[&str], mtime: SystemTime) {
+        for name in names {
+            write_file(&root.join(name), &format!("contents of {}", name), mtime);
+        }
+    }
+
+    #[test]
+    fn test_create_is_deterministic() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let a = tempdir.path().join("a");
+        let b = tempdir.path().join("b");
+        write_tree(
+            &a,
+            &["foo", "dir/bar", "dir/sub/baz", "qux"],
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000),
+        );
+        write_tree(
+            &b,
+            &["qux", "dir/sub/baz", "dir/bar", "foo"],
+            SystemTime::now(),
+        );
+
+        for (format, ext) in [
+            (ArchiveFormat::TarZst, "tar.zst"),
+            (ArchiveFormat::Zip, "zip"),
+        ] {
+            let a_archive = tempdir.path().join(format!("a.{}", ext));
+            let b_archive = tempdir.path().join(format!("b.{}", ext));
+            create(format, &a, &a_archive).unwrap();
+            create(format, &b, &b_archive).unwrap();
+            assert_eq!(
+                fs::read(&a_archive).unwrap(),
+                fs::read(&b_archive).unwrap(),
+                "{} archives differ",
+                ext
+            );
+        }
+    }
+
+    #[test]
+    fn test_create_and_extract() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let src = tempdir.path().join("src");
+        write_tree(&src, &["foo", "dir/bar"], SystemTime::now());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            fs::set_permissions(src.join("foo"), fs::Permissions::from_mode(0o700)).unwrap();
+        }
+
+        for (format, ext) in [
+            (ArchiveFormat::TarZst, "tar.zst"),
+            (ArchiveFormat::Zip, "zip"),
+        ] {
+            let archive = tempdir.path().join(format!("out.{}", ext));
+            let out = tempdir.path().join(format!("out_{}", ext));
+            create(format, &src, &archive).unwrap();
+            extract(format, &archive, &out).unwrap();
+
+            assert_eq!(
+                "contents of foo",
+                fs::read_to_string(out.join("foo")).unwrap()
+            );
+            assert_eq!(
+                "contents of dir/bar",
+                fs::read_to_string(out.join("dir/bar")).unwrap()
+            );
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+
+                let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+                assert_eq!(0o755, mode(&out.join("foo")));
+                assert_eq!(0o644, mode(&out.join("dir/bar")));
+            }
+        }
+    }
+
+    #[test]
+    fn test_create_single_file() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let src = tempdir.path().join("file.txt");
+        write_file(&src, "hello", SystemTime::now());
+
+        let archive = tempdir.path().join("out.tar.zst");
+        let out = tempdir.path().join("out");
+        create(ArchiveFormat::TarZst, &src, &archive).unwrap();
+        extract(ArchiveFormat::TarZst, &archive, &out).unwrap();
+        assert_eq!("hello", fs::read_to_string(out.join("file.txt")).unwrap());
+    }
+}
@@ -0,0 +1,45 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! The tool run by the `archive` and `extract` actions. Archives are built the same way wherever
+//! this runs, locally or on RE, rather than depending on the `tar` or `zip` installed there.
+
+mod archive;
+
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::archive::ArchiveFormat;
+
+#[derive(Debug, Parser)]
+#[clap(about = "Create or extract tar.zst and zip archives deterministically")]
+enum Opt {
+    /// Pack `src` (the contents of a directory, or a single file) into the `dest` archive.
+    Create {
+        #[clap(long, value_enum)]
+        format: ArchiveFormat,
+        src: PathBuf,
+        dest: PathBuf,
+    },
+    /// Unpack the `src` archive into the `dest` directory.
+    Extract {
+        #[clap(long, value_enum)]
+        format: ArchiveFormat,
+        src: PathBuf,
+        dest: PathBuf,
+    },
+}
+
+fn main() -> anyhow::Result<()> {
+    match Opt::parse() {
+        Opt::Create { format, src, dest } => archive::create(format, &src, &dest),
+        Opt::Extract { format, src, dest } => archive::extract(format, &src, &dest),
+    }
+}
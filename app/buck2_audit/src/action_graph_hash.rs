@@ -0,0 +1,49 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_client_ctx::common::target_cfg::TargetCfgWithUniverseOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+
+use crate::AuditSubcommand;
+
+/// Print a digest of the actions across the configured graph of each of the specified target(s).
+///
+/// The digest covers the category, identifier, command line, environment and executor of every
+/// action, but not the contents of their inputs. Two machines (or configurations) which print the
+/// same digest for a target would run the same commands to build it, which makes this a quick
+/// check that a toolchain rollout didn't make a build less hermetic.
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(name = "audit-action-graph-hash")]
+pub struct AuditActionGraphHashCommand {
+    /// Patterns to analyze.
+    #[clap(name = "TARGET_PATTERNS", required = true)]
+    pub patterns: Vec<String>,
+
+    /// Also print the digest of every action, to find the actions which differ.
+    #[clap(long)]
+    pub per_action: bool,
+
+    /// Print the digests as JSON.
+    #[clap(long)]
+    pub json: bool,
+
+    #[clap(flatten)]
+    pub target_cfg: TargetCfgWithUniverseOptions,
+
+    #[clap(flatten)]
+    pub common_opts: CommonCommandOptions,
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditActionGraphHashCommand {
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
@@ -0,0 +1,45 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_client_ctx::common::target_cfg::TargetCfgWithUniverseOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+
+use crate::AuditSubcommand;
+
+/// Print the category and identifier of every action of the specified target(s), and the
+/// number of actions per category across their configured graph.
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(name = "audit-actions")]
+pub struct AuditActionsCommand {
+    /// Patterns to analyze.
+    #[clap(name = "TARGET_PATTERNS", required = true)]
+    pub patterns: Vec<String>,
+
+    /// Only print the per-category summary.
+    #[clap(long)]
+    pub summary: bool,
+
+    /// Print the actions and the summary as JSON.
+    #[clap(long)]
+    pub json: bool,
+
+    #[clap(flatten)]
+    pub target_cfg: TargetCfgWithUniverseOptions,
+
+    #[clap(flatten)]
+    pub common_opts: CommonCommandOptions,
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditActionsCommand {
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
@@ -0,0 +1,47 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_client_ctx::common::target_cfg::TargetCfgWithUniverseOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+
+use crate::AuditSubcommand;
+
+/// Report how many of the configured targets reachable from the specified target(s) would
+/// collapse if configurations were trimmed to the constraints each target actually reads, as
+/// with `buck2.trim_configurations`.
+///
+/// Two configured targets of the same target collapse when they only differ by constraints that
+/// neither they nor their dependencies read, so analyzing both is redundant work. Targets with
+/// transitions are never trimmed.
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(name = "audit-configuration-trimming")]
+pub struct AuditConfigurationTrimmingCommand {
+    /// Patterns to analyze.
+    #[clap(name = "TARGET_PATTERNS", required = true)]
+    pub patterns: Vec<String>,
+
+    /// Also list every target configured more than once, with the number of configurations it
+    /// would have after trimming.
+    #[clap(long)]
+    pub list: bool,
+
+    #[clap(flatten)]
+    pub target_cfg: TargetCfgWithUniverseOptions,
+
+    #[clap(flatten)]
+    pub common_opts: CommonCommandOptions,
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditConfigurationTrimmingCommand {
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
@@ -0,0 +1,53 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_client_ctx::common::target_cfg::TargetCfgWithUniverseOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+
+use crate::AuditSubcommand;
+
+/// Print the configured dependency tree of the specified target(s).
+///
+/// Each edge is annotated with the attribute it comes from. Targets that were already
+/// printed earlier in the tree are marked with `(*)` and not expanded again.
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(name = "audit-deps-tree")]
+pub struct AuditDepsTreeCommand {
+    /// Patterns to analyze.
+    #[clap(name = "TARGET_PATTERNS", required = true)]
+    pub patterns: Vec<String>,
+
+    /// Only print dependencies up to this depth. Direct dependencies are at depth 1.
+    #[clap(long)]
+    pub depth: Option<usize>,
+
+    /// Only print dependencies which provide a provider with this name (e.g.
+    /// `CxxLibraryInfo`), along with their own filtered dependencies. This analyzes
+    /// every dependency that is visited.
+    #[clap(long)]
+    pub provider: Option<String>,
+
+    /// Print the tree as JSON.
+    #[clap(long)]
+    pub json: bool,
+
+    #[clap(flatten)]
+    pub target_cfg: TargetCfgWithUniverseOptions,
+
+    #[clap(flatten)]
+    pub common_opts: CommonCommandOptions,
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditDepsTreeCommand {
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
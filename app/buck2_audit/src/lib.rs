@@ -24,12 +24,16 @@ use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::streaming::StreamingCommand;
 use classpath::AuditClasspathCommand;
 
+use crate::action_graph_hash::AuditActionGraphHashCommand;
+use crate::actions::AuditActionsCommand;
 use crate::analysis_queries::AuditAnalysisQueriesCommand;
 use crate::cell::AuditCellCommand;
 use crate::config::AuditConfigCommand;
+use crate::configuration_trimming::AuditConfigurationTrimmingCommand;
 use crate::configurations::AuditConfigurationsCommand;
 use crate::deferred_materializer::DeferredMaterializerCommand;
 use crate::dep_files::AuditDepFilesCommand;
+use crate::deps_tree::AuditDepsTreeCommand;
 use crate::execution_platform_resolution::AuditExecutionPlatformResolutionCommand;
 use crate::includes::AuditIncludesCommand;
 use crate::output::command::AuditOutputCommand;
@@ -41,13 +45,17 @@ use crate::starlark::StarlarkCommand;
 use crate::subtargets::AuditSubtargetsCommand;
 use crate::visibility::AuditVisibilityCommand;
 
+pub mod action_graph_hash;
+pub mod actions;
 pub mod analysis_queries;
 pub mod cell;
 pub mod classpath;
 pub mod config;
+pub mod configuration_trimming;
 pub mod configurations;
 pub mod deferred_materializer;
 pub mod dep_files;
+pub mod deps_tree;
 pub mod execution_platform_resolution;
 pub mod includes;
 pub mod output;
@@ -65,6 +73,7 @@ pub enum AuditCommand {
     Classpath(AuditClasspathCommand),
     Config(AuditConfigCommand),
     Configurations(AuditConfigurationsCommand),
+    ConfigurationTrimming(AuditConfigurationTrimmingCommand),
     Includes(AuditIncludesCommand),
     Prelude(AuditPreludeCommand),
     Providers(AuditProvidersCommand),
@@ -75,6 +84,9 @@ pub enum AuditCommand {
     #[clap(subcommand)]
     Starlark(StarlarkCommand),
     DepFiles(AuditDepFilesCommand),
+    DepsTree(AuditDepsTreeCommand),
+    Actions(AuditActionsCommand),
+    ActionGraphHash(AuditActionGraphHashCommand),
     DeferredMaterializer(DeferredMaterializerCommand),
     Output(AuditOutputCommand),
     Parse(AuditParseCommand),
@@ -101,6 +113,7 @@ impl AuditCommand {
             AuditCommand::Classpath(cmd) => cmd,
             AuditCommand::Config(cmd) => cmd,
             AuditCommand::Configurations(cmd) => cmd,
+            AuditCommand::ConfigurationTrimming(cmd) => cmd,
             AuditCommand::Includes(cmd) => cmd,
             AuditCommand::Prelude(cmd) => cmd,
             AuditCommand::Providers(cmd) => cmd,
@@ -109,6 +122,9 @@ impl AuditCommand {
             AuditCommand::ExecutionPlatformResolution(cmd) => cmd,
             AuditCommand::Starlark(cmd) => cmd,
             AuditCommand::DepFiles(cmd) => cmd,
+            AuditCommand::DepsTree(cmd) => cmd,
+            AuditCommand::Actions(cmd) => cmd,
+            AuditCommand::ActionGraphHash(cmd) => cmd,
             AuditCommand::DeferredMaterializer(cmd) => cmd,
             AuditCommand::Visibility(cmd) => cmd,
             AuditCommand::Output(cmd) => cmd,
@@ -17,7 +17,7 @@ use crate::AuditSubcommand;
 #[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
 #[clap(
     name = "audit-output",
-    about = "Query the action that produced the output artifact. Does not support BXL, test, scratch, or anon artifacts. If the configuration hash of the output path does not match the current platform configuration, the unconfigured target label will be returned."
+    about = "Query the action that produced the output artifact. Does not support BXL, test, scratch, or anon artifacts. Only analyzes the target, without building it. If the configuration hash of the output path matches neither the current platform configuration nor a configuration known to the daemon, the unconfigured target label will be returned."
 )]
 pub struct AuditOutputCommand {
     #[clap(
@@ -22,6 +22,11 @@ pub struct PackageValuesCommand {
     /// Package names to inspect (like `//foo/bar`, no trailing colon).
     pub packages: Vec<String>,
 
+    /// For each value, also print the package whose `PACKAGE` file set it. Packages don't
+    /// need to contain a build file in this mode.
+    #[clap(long)]
+    pub trace: bool,
+
     /// Command doesn't need these flags, but they are used in mode files, so we need to keep them.
     #[clap(flatten)]
     _target_cfg: TargetCfgUnusedOptions,
@@ -0,0 +1,242 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_audit::action_graph_hash::AuditActionGraphHashCommand;
+use buck2_build_api::actions::artifact::get_artifact_fs::GetArtifactFs;
+use buck2_build_api::actions::RegisteredAction;
+use buck2_cli_proto::ClientContext;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+use buck2_execute::artifact::fs::ExecutorFs;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_node::nodes::configured_frontend::ConfiguredTargetNodeCalculation;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use futures::FutureExt;
+
+use crate::actions::configured_graph;
+use crate::actions::registered_actions;
+use crate::common::configured_target_labels::audit_command_configured_target_labels;
+use crate::ServerAuditSubcommand;
+
+#[async_trait]
+impl ServerAuditSubcommand for AuditActionGraphHashCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(|server_ctx, mut ctx| async move {
+                let roots = audit_command_configured_target_labels(
+                    &mut ctx,
+                    &self.patterns,
+                    &self.target_cfg,
+                    server_ctx,
+                )
+                .await?;
+                let root_nodes: Vec<ConfiguredTargetNode> = ctx
+                    .try_compute_join(roots.iter(), |ctx, root| {
+                        async move {
+                            anyhow::Ok(
+                                ctx.get_configured_target_node(root)
+                                    .await?
+                                    .require_compatible()?,
+                            )
+                        }
+                        .boxed()
+                    })
+                    .await?;
+
+                let graph = configured_graph(&root_nodes);
+                let graph_actions = ctx
+                    .try_compute_join(graph.iter(), |ctx, label| {
+                        async move { registered_actions(ctx, label).await }.boxed()
+                    })
+                    .await?;
+                let artifact_fs = ctx.get_artifact_fs().await?;
+                let graph_digests: HashMap<&ConfiguredTargetLabel, Vec<ActionDigestEntry>> = graph
+                    .iter()
+                    .zip(&graph_actions)
+                    .map(|(label, actions)| {
+                        let digests = actions
+                            .iter()
+                            .flatten()
+                            .map(|action| ActionDigestEntry::new(action, &artifact_fs))
+                            .collect();
+                        (label, digests)
+                    })
+                    .collect();
+
+                let mut targets = BTreeMap::new();
+                for root in &root_nodes {
+                    let mut actions: Vec<&ActionDigestEntry> =
+                        configured_graph(std::slice::from_ref(root))
+                            .iter()
+                            .filter_map(|label| graph_digests.get(label))
+                            .flatten()
+                            .collect();
+                    targets.insert(
+                        root.label().to_string(),
+                        TargetDigest {
+                            digest: target_digest(&mut actions),
+                            actions: if self.per_action { actions } else { Vec::new() },
+                        },
+                    );
+                }
+
+                let mut stdout = stdout.as_writer();
+                if self.json {
+                    writeln!(stdout, "{}", serde_json::to_string_pretty(&targets)?)?;
+                    return Ok(());
+                }
+                for (label, target) in &targets {
+                    writeln!(stdout, "{} {}", label, target.digest)?;
+                    for action in &target.actions {
+                        write!(stdout, "  {} {}", action.digest, action.category)?;
+                        if let Some(identifier) = &action.identifier {
+                            write!(stdout, " {}", identifier)?;
+                        }
+                        writeln!(stdout, " ({})", action.owner)?;
+                    }
+                }
+
+                Ok(())
+            })
+            .await
+    }
+}
+
+/// The digest of the actions of a target, which are sorted first so that the digest doesn't depend
+/// on the order the graph is walked.
+fn target_digest(actions: &mut [&ActionDigestEntry]) -> String {
+    actions.sort_by(|a, b| a.digest.cmp(&b.digest));
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(actions.len().to_le_bytes().as_slice());
+    for action in actions.iter() {
+        hasher.update(action.digest.as_bytes());
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+#[derive(serde::Serialize)]
+struct TargetDigest<'a> {
+    digest: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    actions: Vec<&'a ActionDigestEntry>,
+}
+
+#[derive(serde::Serialize)]
+struct ActionDigestEntry {
+    digest: String,
+    category: String,
+    identifier: Option<String>,
+    owner: String,
+}
+
+impl ActionDigestEntry {
+    /// Hashes what the action would run, as rendered for `aquery`.
+    fn new(action: &RegisteredAction, artifact_fs: &ArtifactFs) -> Self {
+        let category = action.category().as_str().to_owned();
+        let identifier = action.identifier().map(|i| i.to_owned());
+        let mut attrs = action.action().aquery_attributes(&ExecutorFs::new(
+            artifact_fs,
+            action.execution_config().options.path_separator,
+        ));
+        attrs.insert(
+            "executor_configuration".to_owned(),
+            action.execution_config().executor.to_string(),
+        );
+
+        let digest = fields_digest(
+            [
+                action.action().kind().variant_name(),
+                &category,
+                identifier.as_deref().unwrap_or_default(),
+            ]
+            .into_iter()
+            .chain(attrs.iter().flat_map(|(k, v)| [k.as_str(), v.as_str()])),
+        );
+
+        ActionDigestEntry {
+            digest,
+            category,
+            identifier,
+            owner: action.owner().to_string(),
+        }
+    }
+}
+
+/// Conceptually this is as if we serialized the fields to a length-prefixed list then hashed it.
+fn fields_digest<'a>(fields: impl IntoIterator<Item = &'a str>) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for field in fields {
+        hasher.update(field.len().to_le_bytes().as_slice());
+        hasher.update(field.as_bytes());
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields_digest() {
+        let digest = fields_digest(["run", "cxx_compile", "foo.c", "cmd", "[cc, -c, foo.c]"]);
+        assert_eq!(
+            digest,
+            fields_digest(["run", "cxx_compile", "foo.c", "cmd", "[cc, -c, foo.c]"])
+        );
+        assert_ne!(
+            digest,
+            fields_digest(["run", "cxx_compile", "foo.c", "cmd", "[cc, -c, bar.c]"])
+        );
+        // Fields are delimited.
+        assert_ne!(fields_digest(["ab", "c"]), fields_digest(["a", "bc"]));
+        assert_ne!(fields_digest(["a"]), fields_digest(["a", ""]));
+    }
+
+    fn entry(digest: &str) -> ActionDigestEntry {
+        ActionDigestEntry {
+            digest: digest.to_owned(),
+            category: "run".to_owned(),
+            identifier: None,
+            owner: "root//:foo".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_target_digest() {
+        let (a, b, c) = (entry("a"), entry("b"), entry("c"));
+        let digest = target_digest(&mut [&a, &b]);
+        // The order the graph is walked in doesn't matter.
+        assert_eq!(digest, target_digest(&mut [&b, &a]));
+        assert_ne!(digest, target_digest(&mut [&a, &c]));
+        assert_ne!(digest, target_digest(&mut [&a, &b, &b]));
+        assert_ne!(digest, target_digest(&mut []));
+
+        let mut actions = [&c, &a, &b];
+        target_digest(&mut actions);
+        assert_eq!(
+            vec!["a", "b", "c"],
+            actions
+                .iter()
+                .map(|a| a.digest.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+}
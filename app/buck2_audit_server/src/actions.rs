@@ -0,0 +1,244 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::io::Write;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use buck2_artifact::artifact::provide_outputs::ProvideActionKey;
+use buck2_audit::actions::AuditActionsCommand;
+use buck2_build_api::actions::calculation::ActionCalculation;
+use buck2_build_api::actions::RegisteredAction;
+use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
+use buck2_cli_proto::ClientContext;
+use buck2_core::configuration::compatibility::MaybeCompatible;
+use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_node::nodes::configured_frontend::ConfiguredTargetNodeCalculation;
+use buck2_node::nodes::unconfigured::RuleKind;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use dice::DiceComputations;
+use dupe::Dupe;
+use futures::FutureExt;
+
+use crate::common::configured_target_labels::audit_command_configured_target_labels;
+use crate::ServerAuditSubcommand;
+
+#[async_trait]
+impl ServerAuditSubcommand for AuditActionsCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(|server_ctx, mut ctx| async move {
+                let roots = audit_command_configured_target_labels(
+                    &mut ctx,
+                    &self.patterns,
+                    &self.target_cfg,
+                    server_ctx,
+                )
+                .await?;
+                let root_nodes: Vec<ConfiguredTargetNode> = ctx
+                    .try_compute_join(roots.iter(), |ctx, root| {
+                        async move {
+                            anyhow::Ok(
+                                ctx.get_configured_target_node(root)
+                                    .await?
+                                    .require_compatible()?,
+                            )
+                        }
+                        .boxed()
+                    })
+                    .await?;
+
+                let graph = configured_graph(&root_nodes);
+                let graph_actions: Vec<Option<Vec<ActionEntry>>> = ctx
+                    .try_compute_join(graph.iter(), |ctx, label| {
+                        async move { target_actions(ctx, label).await }.boxed()
+                    })
+                    .await?;
+
+                let mut categories: BTreeMap<&str, usize> = BTreeMap::new();
+                for action in graph_actions.iter().flatten().flatten() {
+                    *categories.entry(action.category.as_str()).or_default() += 1;
+                }
+                let mut categories: Vec<(&str, usize)> = categories.into_iter().collect();
+                // Most actions first, then by name.
+                categories.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+                let roots: HashSet<&ConfiguredTargetLabel> = roots.iter().collect();
+                let root_actions: Vec<(String, &[ActionEntry])> = graph
+                    .iter()
+                    .zip(&graph_actions)
+                    .filter(|(label, _)| roots.contains(label))
+                    .filter_map(|(label, actions)| Some((label.to_string(), actions.as_deref()?)))
+                    .collect();
+
+                let mut stdout = stdout.as_writer();
+                if self.json {
+                    let targets: BTreeMap<_, _> = if self.summary {
+                        BTreeMap::new()
+                    } else {
+                        root_actions.into_iter().collect()
+                    };
+                    let categories: BTreeMap<_, _> = categories.into_iter().collect();
+                    let json = serde_json::json!({
+                        "targets": targets,
+                        "categories": categories,
+                    });
+                    writeln!(stdout, "{}", serde_json::to_string_pretty(&json)?)?;
+                    return Ok(());
+                }
+
+                if !self.summary {
+                    for (label, actions) in &root_actions {
+                        write_target_actions(&mut stdout, label, actions)?;
+                    }
+                }
+                writeln!(
+                    stdout,
+                    "Actions per category across {} configured targets:",
+                    graph.len()
+                )?;
+                for (category, count) in categories {
+                    writeln!(stdout, "  {:>8} {}", count, category)?;
+                }
+
+                Ok(())
+            })
+            .await
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ActionEntry {
+    category: String,
+    identifier: Option<String>,
+}
+
+/// Writes the label of a target, then its actions one per line.
+fn write_target_actions(
+    out: &mut impl Write,
+    label: &str,
+    actions: &[ActionEntry],
+) -> anyhow::Result<()> {
+    writeln!(out, "{}", label)?;
+    for action in actions {
+        write!(out, "  {}", action.category)?;
+        if let Some(identifier) = &action.identifier {
+            write!(out, " {}", identifier)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// The targets reachable from the roots, excluding configuration rules which have no actions.
+pub(crate) fn configured_graph(roots: &[ConfiguredTargetNode]) -> Vec<ConfiguredTargetLabel> {
+    let mut visited = HashSet::new();
+    let mut graph = Vec::new();
+    let mut stack: Vec<&ConfiguredTargetNode> = roots.iter().collect();
+    while let Some(node) = stack.pop() {
+        if node.rule_kind() == RuleKind::Configuration || !visited.insert(node.label()) {
+            continue;
+        }
+        graph.push(node.label().dupe());
+        stack.extend(node.deps());
+    }
+    graph
+}
+
+/// The actions registered by the analysis of the target, or `None` if it is incompatible.
+pub(crate) async fn registered_actions(
+    ctx: &mut DiceComputations<'_>,
+    label: &ConfiguredTargetLabel,
+) -> anyhow::Result<Option<Vec<Arc<RegisteredAction>>>> {
+    let analysis = match ctx.get_analysis_result(label).await? {
+        MaybeCompatible::Compatible(analysis) => analysis,
+        MaybeCompatible::Incompatible(_) => return Ok(None),
+    };
+    let keys: Vec<_> = analysis
+        .iter_deferreds()
+        .filter_map(|entry| provider::request_value::<ProvideActionKey>(entry.as_complex()))
+        .map(|key| key.0)
+        .collect();
+    Ok(Some(
+        ctx.try_compute_join(keys.iter(), |ctx, key| {
+            async move { ctx.get_action(key).await }.boxed()
+        })
+        .await?,
+    ))
+}
+
+async fn target_actions(
+    ctx: &mut DiceComputations<'_>,
+    label: &ConfiguredTargetLabel,
+) -> anyhow::Result<Option<Vec<ActionEntry>>> {
+    // Analysis fails if two actions of a target have the same category and identifier, and the
+    // error is returned as is.
+    let Some(actions) = registered_actions(ctx, label).await? else {
+        return Ok(None);
+    };
+
+    Ok(Some(
+        actions
+            .iter()
+            .map(|action| ActionEntry {
+                category: action.category().as_str().to_owned(),
+                identifier: action.identifier().map(|i| i.to_owned()),
+            })
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn actions() -> Vec<ActionEntry> {
+        vec![
+            ActionEntry {
+                category: "cxx_compile".to_owned(),
+                identifier: Some("foo.c".to_owned()),
+            },
+            ActionEntry {
+                category: "cxx_link".to_owned(),
+                identifier: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_write_target_actions() {
+        let mut out = Vec::new();
+        write_target_actions(&mut out, "root//:foo", &actions()).unwrap();
+        assert_eq!(
+            "root//:foo\n  cxx_compile foo.c\n  cxx_link\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_action_entry_json() {
+        assert_eq!(
+            serde_json::json!([
+                {"category": "cxx_compile", "identifier": "foo.c"},
+                {"category": "cxx_link", "identifier": null},
+            ]),
+            serde_json::to_value(actions()).unwrap()
+        );
+    }
+}
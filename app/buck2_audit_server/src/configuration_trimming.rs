@@ -0,0 +1,105 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_audit::configuration_trimming::AuditConfigurationTrimmingCommand;
+use buck2_cli_proto::ClientContext;
+use buck2_configured::nodes::trimming::trimmed_configuration;
+use buck2_core::configuration::data::ConfigurationData;
+use buck2_core::target::label::TargetLabel;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_node::nodes::configured_frontend::ConfiguredTargetNodeCalculation;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use dupe::Dupe;
+
+use crate::common::configured_target_labels::audit_command_configured_target_labels;
+use crate::ServerAuditSubcommand;
+
+#[async_trait]
+impl ServerAuditSubcommand for AuditConfigurationTrimmingCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(|server_ctx, mut ctx| async move {
+                let labels = audit_command_configured_target_labels(
+                    &mut ctx,
+                    &self.patterns,
+                    &self.target_cfg,
+                    server_ctx,
+                )
+                .await?;
+                let mut roots = Vec::with_capacity(labels.len());
+                for label in &labels {
+                    roots.push(
+                        ctx.get_configured_target_node(label)
+                            .await?
+                            .require_compatible()?,
+                    );
+                }
+
+                let nodes = reachable(&roots);
+                let mut by_target: BTreeMap<
+                    TargetLabel,
+                    (
+                        usize,
+                        HashSet<(ConfigurationData, Option<ConfigurationData>)>,
+                    ),
+                > = BTreeMap::new();
+                for node in &nodes {
+                    let label = node.label();
+                    let cfg = trimmed_configuration(&mut ctx, node)
+                        .await?
+                        .unwrap_or_else(|| label.cfg().dupe());
+                    let (configured, trimmed) =
+                        by_target.entry(label.unconfigured().dupe()).or_default();
+                    *configured += 1;
+                    trimmed.insert((cfg, label.exec_cfg().cloned()));
+                }
+                let trimmed: usize = by_target.values().map(|(_, keys)| keys.len()).sum();
+
+                let mut stdout = stdout.as_writer();
+                writeln!(stdout, "Configured targets: {}", nodes.len())?;
+                writeln!(stdout, "After trimming: {}", trimmed)?;
+                if self.list {
+                    for (target, (configured, trimmed)) in &by_target {
+                        if *configured > 1 {
+                            writeln!(stdout, "{}: {} -> {}", target, configured, trimmed.len())?;
+                        }
+                    }
+                }
+
+                Ok(())
+            })
+            .await
+    }
+}
+
+/// The nodes reachable from `roots`, including them.
+fn reachable(roots: &[ConfiguredTargetNode]) -> Vec<ConfiguredTargetNode> {
+    let mut seen = HashSet::new();
+    let mut nodes = Vec::new();
+    let mut stack: Vec<&ConfiguredTargetNode> = roots.iter().collect();
+    while let Some(node) = stack.pop() {
+        if seen.insert(node.label()) {
+            nodes.push(node.dupe());
+            stack.extend(node.deps());
+        }
+    }
+    nodes
+}
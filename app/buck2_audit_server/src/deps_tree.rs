@@ -0,0 +1,304 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_audit::deps_tree::AuditDepsTreeCommand;
+use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
+use buck2_cli_proto::ClientContext;
+use buck2_core::configuration::compatibility::MaybeCompatible;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+use buck2_node::attrs::configured_traversal::ConfiguredAttrTraversal;
+use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::nodes::configured_frontend::ConfiguredTargetNodeCalculation;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use dice::DiceComputations;
+use dupe::Dupe;
+
+use crate::common::configured_target_labels::audit_command_configured_target_labels;
+use crate::ServerAuditSubcommand;
+
+#[async_trait]
+impl ServerAuditSubcommand for AuditDepsTreeCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(|server_ctx, mut ctx| async move {
+                let roots = audit_command_configured_target_labels(
+                    &mut ctx,
+                    &self.patterns,
+                    &self.target_cfg,
+                    server_ctx,
+                )
+                .await?;
+
+                let graph = collect_deps_graph(&mut ctx, self, &roots).await?;
+
+                let mut visited = HashSet::new();
+                let trees = roots
+                    .iter()
+                    .map(|root| {
+                        graph.tree(&mut visited, root.to_string(), root, None, 0, self.depth)
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut stdout = stdout.as_writer();
+                if self.json {
+                    writeln!(stdout, "{}", serde_json::to_string_pretty(&trees)?)?;
+                } else {
+                    for tree in &trees {
+                        tree.write_text(&mut stdout, 0)?;
+                    }
+                }
+
+                Ok(())
+            })
+            .await
+    }
+}
+
+/// Collects the deps of a single attribute.
+struct AttrDepsCollector(Vec<ConfiguredProvidersLabel>);
+
+impl ConfiguredAttrTraversal for AttrDepsCollector {
+    fn dep(&mut self, dep: &ConfiguredProvidersLabel) -> anyhow::Result<()> {
+        self.0.push(dep.clone());
+        Ok(())
+    }
+}
+
+/// The (filtered) edges of every target reachable from the roots within the requested depth,
+/// labelled with the attribute they come from.
+struct DepsGraph(HashMap<ConfiguredTargetLabel, Vec<(String, ConfiguredProvidersLabel)>>);
+
+async fn collect_deps_graph(
+    ctx: &mut DiceComputations<'_>,
+    command: &AuditDepsTreeCommand,
+    roots: &[ConfiguredTargetLabel],
+) -> anyhow::Result<DepsGraph> {
+    let mut graph = HashMap::new();
+    // Breadth first, so each target is first reached at its minimum depth, which is the
+    // shallowest it can appear in the tree.
+    let mut queue: VecDeque<(ConfiguredTargetLabel, usize)> =
+        roots.iter().map(|root| (root.dupe(), 0)).collect();
+    while let Some((label, depth)) = queue.pop_front() {
+        if graph.contains_key(&label) {
+            continue;
+        }
+
+        let mut edges = Vec::new();
+        if command.depth.map_or(true, |max| depth < max) {
+            let node = ctx
+                .get_configured_target_node(&label)
+                .await?
+                .require_compatible()?;
+            for attr in node.attrs(AttrInspectOptions::All) {
+                let mut collector = AttrDepsCollector(Vec::new());
+                attr.traverse(node.label().pkg(), &mut collector)?;
+                for dep in collector.0 {
+                    if let Some(provider) = &command.provider {
+                        let matches = match ctx.get_providers(&dep).await? {
+                            MaybeCompatible::Compatible(providers) => providers
+                                .provider_collection()
+                                .provider_names()
+                                .contains(provider),
+                            MaybeCompatible::Incompatible(_) => false,
+                        };
+                        if !matches {
+                            continue;
+                        }
+                    }
+                    queue.push_back((dep.target().dupe(), depth + 1));
+                    edges.push((attr.name.to_owned(), dep));
+                }
+            }
+        }
+        graph.insert(label, edges);
+    }
+    Ok(DepsGraph(graph))
+}
+
+#[derive(serde::Serialize)]
+struct DepsTreeEntry {
+    label: String,
+    /// The attribute of the parent this dependency comes from, absent for the roots.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attr: Option<String>,
+    /// Whether the deps of this target were already printed elsewhere in the tree.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    deduplicated: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    deps: Vec<DepsTreeEntry>,
+}
+
+impl DepsGraph {
+    fn tree(
+        &self,
+        visited: &mut HashSet<ConfiguredTargetLabel>,
+        label: String,
+        target: &ConfiguredTargetLabel,
+        attr: Option<String>,
+        depth: usize,
+        max_depth: Option<usize>,
+    ) -> DepsTreeEntry {
+        let mut entry = DepsTreeEntry {
+            label,
+            attr,
+            deduplicated: false,
+            deps: Vec::new(),
+        };
+
+        let edges = match self.0.get(target) {
+            Some(edges) if !edges.is_empty() => edges,
+            _ => return entry,
+        };
+        if max_depth.map_or(false, |max| depth >= max) {
+            return entry;
+        }
+        if !visited.insert(target.dupe()) {
+            entry.deduplicated = true;
+            return entry;
+        }
+
+        entry.deps = edges
+            .iter()
+            .map(|(attr, dep)| {
+                self.tree(
+                    visited,
+                    dep.to_string(),
+                    dep.target(),
+                    Some(attr.clone()),
+                    depth + 1,
+                    max_depth,
+                )
+            })
+            .collect();
+        entry
+    }
+}
+
+impl DepsTreeEntry {
+    fn write_text(&self, w: &mut impl Write, indent: usize) -> anyhow::Result<()> {
+        write!(w, "{}", "  ".repeat(indent))?;
+        if let Some(attr) = &self.attr {
+            write!(w, "{}: ", attr)?;
+        }
+        write!(w, "{}", self.label)?;
+        if self.deduplicated {
+            write!(w, " (*)")?;
+        }
+        writeln!(w)?;
+        for dep in &self.deps {
+            dep.write_text(w, indent + 1)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::configuration::data::ConfigurationData;
+
+    use super::*;
+
+    fn label(name: &str) -> ConfiguredTargetLabel {
+        ConfiguredTargetLabel::testing_parse(
+            &format!("cell//pkg:{}", name),
+            ConfigurationData::testing_new(),
+        )
+    }
+
+    /// `a` -> `b` -> `d` -> `e`, and `a` -> `c` -> `d`.
+    fn graph() -> DepsGraph {
+        let edges = |deps: &[(&str, &str)]| {
+            deps.iter()
+                .map(|(attr, dep)| {
+                    (
+                        (*attr).to_owned(),
+                        ConfiguredProvidersLabel::default_for(label(dep)),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        DepsGraph(HashMap::from([
+            (label("a"), edges(&[("deps", "b"), ("deps", "c")])),
+            (label("b"), edges(&[("deps", "d")])),
+            (label("c"), edges(&[("exported_deps", "d")])),
+            (label("d"), edges(&[("deps", "e")])),
+            (label("e"), edges(&[])),
+        ]))
+    }
+
+    fn tree_text(max_depth: Option<usize>) -> String {
+        let root = label("a");
+        let tree = graph().tree(
+            &mut HashSet::new(),
+            root.to_string(),
+            &root,
+            None,
+            0,
+            max_depth,
+        );
+        let mut text = Vec::new();
+        tree.write_text(&mut text, 0).unwrap();
+        String::from_utf8(text)
+            .unwrap()
+            .replace(&format!(" ({})", ConfigurationData::testing_new()), "")
+    }
+
+    #[test]
+    fn test_tree() {
+        assert_eq!(
+            "cell//pkg:a\n\
+            \x20 deps: cell//pkg:b\n\
+            \x20   deps: cell//pkg:d\n\
+            \x20     deps: cell//pkg:e\n\
+            \x20 deps: cell//pkg:c\n\
+            \x20   exported_deps: cell//pkg:d (*)\n",
+            tree_text(None)
+        );
+    }
+
+    #[test]
+    fn test_tree_depth() {
+        assert_eq!(
+            "cell//pkg:a\n\
+            \x20 deps: cell//pkg:b\n\
+            \x20 deps: cell//pkg:c\n",
+            tree_text(Some(1))
+        );
+    }
+
+    #[test]
+    fn test_tree_json() {
+        let root = label("e");
+        let tree = graph().tree(&mut HashSet::new(), "e".to_owned(), &root, None, 0, None);
+        assert_eq!(
+            serde_json::json!({"label": "e"}),
+            serde_json::to_value(&tree).unwrap()
+        );
+
+        let root = label("c");
+        let tree = graph().tree(&mut HashSet::new(), "c".to_owned(), &root, None, 0, None);
+        let json = serde_json::to_value(&tree).unwrap();
+        assert_eq!("exported_deps", json["deps"][0]["attr"]);
+        assert_eq!(serde_json::Value::Null, json["deps"][0]["deduplicated"]);
+    }
+}
@@ -19,14 +19,18 @@ use buck2_cli_proto::ClientContext;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 
+mod action_graph_hash;
+mod actions;
 mod analysis_queries;
 mod cell;
 mod classpath;
 mod common;
 mod config;
+mod configuration_trimming;
 mod configurations;
 pub mod deferred_materializer;
 mod dep_files;
+mod deps_tree;
 mod execution_platform_resolution;
 mod includes;
 pub mod output;
@@ -85,6 +89,7 @@ impl AuditCommandExt for AuditCommand {
             AuditCommand::Classpath(cmd) => cmd,
             AuditCommand::Config(cmd) => cmd,
             AuditCommand::Configurations(cmd) => cmd,
+            AuditCommand::ConfigurationTrimming(cmd) => cmd,
             AuditCommand::Includes(cmd) => cmd,
             AuditCommand::Prelude(cmd) => cmd,
             AuditCommand::Providers(cmd) => cmd,
@@ -93,6 +98,9 @@ impl AuditCommandExt for AuditCommand {
             AuditCommand::ExecutionPlatformResolution(cmd) => cmd,
             AuditCommand::Starlark(cmd) => cmd,
             AuditCommand::DepFiles(cmd) => cmd,
+            AuditCommand::DepsTree(cmd) => cmd,
+            AuditCommand::Actions(cmd) => cmd,
+            AuditCommand::ActionGraphHash(cmd) => cmd,
             AuditCommand::DeferredMaterializer(cmd) => cmd,
             AuditCommand::Visibility(cmd) => cmd,
             AuditCommand::Output(cmd) => cmd,
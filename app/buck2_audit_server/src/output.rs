@@ -7,7 +7,6 @@
  * of this source tree.
  */
 
-pub(crate) mod buck_out_path_parser;
 pub(crate) mod buck_out_path_type_printer;
 pub mod command;
 pub mod parse;
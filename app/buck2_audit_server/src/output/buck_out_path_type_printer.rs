@@ -9,11 +9,10 @@
 
 use std::io::Write;
 
+use buck2_build_api::buck_out_path_parser::BuckOutPathType;
 use indexmap::IndexMap;
 use regex::RegexSet;
 
-use super::buck_out_path_parser::BuckOutPathType;
-
 pub(crate) struct BuckOutPathTypePrinter {
     json: bool,
     attributes: Option<RegexSet>,
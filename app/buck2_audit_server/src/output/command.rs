@@ -16,9 +16,13 @@ use buck2_build_api::actions::query::PRINT_ACTION_NODE;
 use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
 use buck2_build_api::audit_output::AuditOutputResult;
 use buck2_build_api::audit_output::AUDIT_OUTPUT;
+use buck2_build_api::buck_out_path_parser::lookup_configuration;
+use buck2_build_api::buck_out_path_parser::BuckOutPathParser;
+use buck2_build_api::buck_out_path_parser::BuckOutPathType;
 use buck2_cli_proto::ClientContext;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::global_cfg_options::GlobalCfgOptions;
+use buck2_core::base_deferred_key::BaseDeferredKey;
 use buck2_core::cells::CellResolver;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_node::target_calculation::ConfiguredTargetCalculation;
@@ -27,9 +31,8 @@ use buck2_server_ctx::ctx::ServerCommandDiceContext;
 use buck2_server_ctx::global_cfg_options::global_cfg_options_from_client_context;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 use dice::DiceComputations;
+use dupe::Dupe;
 
-use crate::output::buck_out_path_parser::BuckOutPathParser;
-use crate::output::buck_out_path_parser::BuckOutPathType;
 use crate::ServerAuditSubcommand;
 
 #[derive(Debug, buck2_error::Error)]
@@ -72,11 +75,17 @@ async fn audit_output<'v>(
         .get_configured_target(&target_label, global_cfg_options)
         .await?;
 
-    let command_config = configured_target_label.cfg();
-    let command_config_hash = command_config.output_hash();
-    if command_config_hash.as_str() != config_hash {
-        return Ok(Some(AuditOutputResult::MaybeRelevant(target_label)));
-    }
+    // The path may have been produced with another configuration than the one this command
+    // uses, e.g. by a build for another platform or through a transition.
+    let configured_target_label =
+        if configured_target_label.cfg().output_hash().as_str() == config_hash {
+            configured_target_label
+        } else {
+            match lookup_configuration(&config_hash) {
+                Some(cfg) => target_label.configure(cfg),
+                None => return Ok(Some(AuditOutputResult::MaybeRelevant(target_label))),
+            }
+        };
 
     let analysis = dice_ctx
         .get_analysis_result(&configured_target_label)
@@ -143,12 +152,29 @@ impl ServerAuditSubcommand for AuditOutputCommand {
                     Some(result) => {
                         match result {
                             AuditOutputResult::Match(action) => {
-                                (PRINT_ACTION_NODE.get()?)(&mut stdout, action, self.json, &self.query_attributes.get()?, &cell_resolver).await?
+                                let owner = match action.key().require_action()?.owner() {
+                                    BaseDeferredKey::TargetLabel(label) if !self.json => Some(label.dupe()),
+                                    _ => None,
+                                };
+                                (PRINT_ACTION_NODE.get()?)(&mut stdout, action, self.json, &self.query_attributes.get()?, &cell_resolver).await?;
+                                if let Some(owner) = owner {
+                                    writeln!(
+                                        stdout,
+                                        "To rebuild it, run: buck2 build '{} ({})'",
+                                        owner.unconfigured(),
+                                        owner.cfg()
+                                    )?;
+                                }
                             },
                             AuditOutputResult::MaybeRelevant(label) => {
                                 writeln!(
                                     stdout,
-                                    "Platform configuration of the buck-out path did not match the one used to invoke this command. Returning the most relevant unconfigured target label for the buck-out path: {}",
+                                    "Platform configuration of the buck-out path did not match the one used to invoke this command, and is not known to this daemon. Returning the most relevant unconfigured target label for the buck-out path: {}",
+                                    label
+                                )?;
+                                writeln!(
+                                    stdout,
+                                    "To rebuild it, run `buck2 build {}` with the `--target-platforms` that produced the path.",
                                     label
                                 )?;
                             }
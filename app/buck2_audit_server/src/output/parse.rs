@@ -9,13 +9,13 @@
 
 use async_trait::async_trait;
 use buck2_audit::output::parse::AuditParseCommand;
+use buck2_build_api::buck_out_path_parser::BuckOutPathParser;
 use buck2_cli_proto::ClientContext;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::ctx::ServerCommandDiceContext;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 
-use super::buck_out_path_parser::BuckOutPathParser;
 use super::buck_out_path_type_printer::BuckOutPathTypePrinter;
 use crate::ServerAuditSubcommand;
 
@@ -20,6 +20,7 @@ use buck2_node::package_values_calculation::PACKAGE_VALUES_CALCULATION;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::ctx::ServerCommandDiceContext;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use dice::DiceComputations;
 use dupe::Dupe;
 use futures::FutureExt;
 use gazebo::prelude::SliceExt;
@@ -49,6 +50,10 @@ impl ServerAuditSubcommand for PackageValuesCommand {
                     .packages
                     .try_map(|package| parse_package(package.dupe(), cell_alias_resolver))?;
 
+                if self.trace {
+                    return write_traced_package_values(&mut dice_ctx, packages, stdout).await;
+                }
+
                 let package_values_by_package = dice_ctx
                     .try_compute_join(packages, |ctx, package| {
                         async move {
@@ -75,3 +80,42 @@ impl ServerAuditSubcommand for PackageValuesCommand {
             .await
     }
 }
+
+#[derive(serde::Serialize)]
+struct TracedPackageValue {
+    value: serde_json::Value,
+    /// The package whose `PACKAGE` file set this value.
+    set_in: String,
+}
+
+async fn write_traced_package_values(
+    dice_ctx: &mut DiceComputations<'_>,
+    packages: Vec<PackageLabel>,
+    mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+) -> anyhow::Result<()> {
+    let traced_by_package = dice_ctx
+        .try_compute_join(packages, |ctx, package| {
+            async move {
+                let traced = PACKAGE_VALUES_CALCULATION
+                    .get()?
+                    .package_values_with_origins(ctx, package.dupe())
+                    .await?
+                    .into_iter()
+                    .map(|(key, (value, origin))| {
+                        let set_in = origin.to_string();
+                        (key, TracedPackageValue { value, set_in })
+                    })
+                    .collect::<SmallMap<_, _>>();
+                anyhow::Ok((package, traced))
+            }
+            .boxed()
+        })
+        .await?;
+    let traced_by_package: SmallMap<PackageLabel, SmallMap<MetadataKey, TracedPackageValue>> =
+        traced_by_package.into_iter().collect();
+
+    let mut stdout = stdout.as_writer();
+    serde_json::to_writer_pretty(&mut stdout, &traced_by_package)?;
+    writeln!(stdout)?;
+    Ok(())
+}
@@ -69,12 +69,14 @@ use crate::actions::execute::action_execution_target::ActionExecutionTarget;
 use crate::actions::execute::action_executor::ActionExecutionMetadata;
 use crate::actions::execute::action_executor::ActionOutputs;
 use crate::actions::execute::error::ExecuteError;
+use crate::actions::impls::action_retry_policy::ActionRetryPolicy;
 use crate::actions::impls::run_action_knobs::RunActionKnobs;
 use crate::artifact_groups::ArtifactGroup;
 use crate::artifact_groups::ArtifactGroupValues;
 use crate::deferred::types::AnyValue;
 use crate::deferred::types::DeferredOutput;
 use crate::deferred::types::TrivialDeferred;
+use crate::stamp::StampInfo;
 
 pub mod artifact;
 pub mod box_slice_set;
@@ -162,6 +164,12 @@ pub trait Action: Allocative + Debug + Send + Sync + 'static {
         None
     }
 
+    /// Whether the action reads `ActionExecutionCtx::stamp_info`. Such actions are re-executed
+    /// whenever the stamp info changes, i.e. on every command.
+    fn uses_stamp_info(&self) -> bool {
+        false
+    }
+
     // TODO this probably wants more data for execution, like printing a short_name and the target
 }
 
@@ -210,6 +218,9 @@ pub trait ActionExecutionCtx: Send + Sync {
 
     fn mergebase(&self) -> &Mergebase;
 
+    /// Only available to actions which return `true` from `Action::uses_stamp_info`.
+    fn stamp_info(&self) -> Option<&StampInfo>;
+
     fn prepare_action(
         &mut self,
         request: &CommandExecutionRequest,
@@ -229,13 +240,15 @@ pub trait ActionExecutionCtx: Send + Sync {
         dep_file_entry: Option<DepFileEntry>,
     ) -> anyhow::Result<CacheUploadResult>;
 
-    /// Executes a command
+    /// Executes a command, retrying failures according to `retry_policy` if provided, or the
+    /// build-wide retry policy otherwise.
     /// TODO(bobyf) this seems like it deserves critical sections?
     async fn exec_cmd(
         &mut self,
         manager: CommandExecutionManager,
         request: &CommandExecutionRequest,
         prepared_action: &PreparedAction,
+        retry_policy: Option<&ActionRetryPolicy>,
     ) -> CommandExecutionResult;
 
     fn unpack_command_execution_result(
@@ -261,6 +274,9 @@ pub trait ActionExecutionCtx: Send + Sync {
 
     fn re_platform(&self) -> &remote_execution::Platform;
 
+    /// Whether commands using a worker which allows it run on RE persistent workers.
+    fn uses_remote_persistent_workers(&self) -> bool;
+
     fn digest_config(&self) -> DigestConfig;
 
     /// Obtain per-command knobs for RunAction.
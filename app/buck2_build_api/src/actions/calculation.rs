@@ -58,6 +58,7 @@ use crate::actions::RegisteredAction;
 use crate::artifact_groups::calculation::ensure_artifact_group_staged;
 use crate::deferred::calculation::DeferredCalculation;
 use crate::keep_going::KeepGoing;
+use crate::stamp::HasStampInfo;
 use crate::starlark::values::type_repr::StarlarkTypeRepr;
 use crate::starlark::values::UnpackValue;
 
@@ -139,6 +140,13 @@ async fn build_action_no_redirect(
         .await
         .context(format!("for action `{}`", action))?;
 
+    // This makes the action depend on the stamp info, which changes on every command.
+    let stamp_info = if action.uses_stamp_info() {
+        Some(ctx.get_stamp_info().await?)
+    } else {
+        None
+    };
+
     let now = Instant::now();
     let action = &action;
 
@@ -162,7 +170,12 @@ async fn build_action_no_redirect(
     let ctx = &*ctx;
     let fut = async move {
         let (execute_result, command_reports) = executor
-            .execute(materialized_inputs, action, cancellation)
+            .execute(
+                materialized_inputs,
+                action,
+                stamp_info.as_deref(),
+                cancellation,
+            )
             .await;
 
         let allow_omit_details = execute_result.is_ok();
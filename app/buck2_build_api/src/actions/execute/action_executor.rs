@@ -71,6 +71,8 @@ use crate::actions::execute::dice_data::CommandExecutorResponse;
 use crate::actions::execute::dice_data::DiceHasCommandExecutor;
 use crate::actions::execute::dice_data::GetReClient;
 use crate::actions::execute::error::ExecuteError;
+use crate::actions::impls::action_retry_policy::ActionRetryPolicy;
+use crate::actions::impls::action_retry_policy::HasActionRetryPolicy;
 use crate::actions::impls::run_action_knobs::HasRunActionKnobs;
 use crate::actions::impls::run_action_knobs::RunActionKnobs;
 use crate::actions::ActionExecutable;
@@ -78,6 +80,7 @@ use crate::actions::ActionExecutionCtx;
 use crate::actions::RegisteredAction;
 use crate::artifact_groups::ArtifactGroup;
 use crate::artifact_groups::ArtifactGroupValues;
+use crate::stamp::StampInfo;
 
 /// This is the result of the action as exposed to other things in the dice computation.
 #[derive(Clone, Dupe, Debug, PartialEq, Eq, Allocative)]
@@ -238,6 +241,7 @@ impl HasActionExecutor for DiceComputations<'_> {
         let events = self.per_transaction_data().get_dispatcher().dupe();
         let re_client = self.per_transaction_data().get_re_client();
         let run_action_knobs = self.per_transaction_data().get_run_action_knobs();
+        let retry_policy = self.per_transaction_data().get_action_retry_policy();
         let io_provider = self.global_data().get_io_provider();
         let http_client = self.per_transaction_data().get_http_client();
         let mergebase = self.per_transaction_data().get_mergebase();
@@ -257,6 +261,7 @@ impl HasActionExecutor for DiceComputations<'_> {
             re_client,
             digest_config,
             run_action_knobs,
+            retry_policy,
             io_provider,
             http_client,
             mergebase,
@@ -272,6 +277,7 @@ pub struct BuckActionExecutor {
     re_client: ManagedRemoteExecutionClient,
     digest_config: DigestConfig,
     run_action_knobs: RunActionKnobs,
+    retry_policy: Arc<ActionRetryPolicy>,
     io_provider: Arc<dyn IoProvider>,
     http_client: HttpClient,
     mergebase: Mergebase,
@@ -286,6 +292,7 @@ impl BuckActionExecutor {
         re_client: ManagedRemoteExecutionClient,
         digest_config: DigestConfig,
         run_action_knobs: RunActionKnobs,
+        retry_policy: Arc<ActionRetryPolicy>,
         io_provider: Arc<dyn IoProvider>,
         http_client: HttpClient,
         mergebase: Mergebase,
@@ -298,6 +305,7 @@ impl BuckActionExecutor {
             re_client,
             digest_config,
             run_action_knobs,
+            retry_policy,
             io_provider,
             http_client,
             mergebase,
@@ -312,6 +320,7 @@ struct BuckActionExecutionContext<'a> {
     outputs: &'a [BuildArtifact],
     command_reports: &'a mut Vec<CommandExecutionReport>,
     cancellations: &'a CancellationContext<'a>,
+    stamp_info: Option<&'a StampInfo>,
 }
 
 #[async_trait]
@@ -360,12 +369,18 @@ impl ActionExecutionCtx for BuckActionExecutionContext<'_> {
         self.executor.command_executor.re_platform()
     }
 
+    fn uses_remote_persistent_workers(&self) -> bool {
+        self.executor
+            .command_executor
+            .uses_remote_persistent_workers()
+    }
+
     fn digest_config(&self) -> DigestConfig {
         self.executor.digest_config
     }
 
     fn run_action_knobs(&self) -> RunActionKnobs {
-        self.executor.run_action_knobs
+        self.executor.run_action_knobs.dupe()
     }
 
     fn cancellation_context(&self) -> &CancellationContext {
@@ -376,6 +391,10 @@ impl ActionExecutionCtx for BuckActionExecutionContext<'_> {
         &self.executor.mergebase
     }
 
+    fn stamp_info(&self) -> Option<&StampInfo> {
+        self.stamp_info
+    }
+
     fn prepare_action(
         &mut self,
         request: &CommandExecutionRequest,
@@ -466,21 +485,51 @@ impl ActionExecutionCtx for BuckActionExecutionContext<'_> {
         manager: CommandExecutionManager,
         request: &CommandExecutionRequest,
         prepared_action: &PreparedAction,
+        retry_policy: Option<&ActionRetryPolicy>,
     ) -> CommandExecutionResult {
-        let action = self.target();
-        self.executor
-            .command_executor
-            .exec_cmd(
-                manager,
-                &PreparedCommand {
-                    target: &action as _,
-                    request,
-                    prepared_action,
-                    digest_config: self.digest_config(),
-                },
-                self.cancellations,
-            )
-            .await
+        let build_retry_policy = self.executor.retry_policy.dupe();
+        let retry_policy = retry_policy.unwrap_or(&build_retry_policy);
+        let mut manager = manager;
+        let mut retries = 0;
+        loop {
+            let action = self.target();
+            let result = self
+                .executor
+                .command_executor
+                .exec_cmd(
+                    manager,
+                    &PreparedCommand {
+                        target: &action as _,
+                        request,
+                        prepared_action,
+                        digest_config: self.digest_config(),
+                    },
+                    self.cancellations,
+                )
+                .await;
+
+            if retries >= retry_policy.max_retries() {
+                return result;
+            }
+            let Some(reason) = retry_policy.retry_reason(&result.report).await else {
+                return result;
+            };
+
+            // Keep the failed attempt in the command reports, so that it shows up in the
+            // event log alongside the attempt that follows it.
+            retries += 1;
+            tracing::warn!(
+                "Retrying `{}` ({}/{}): {}",
+                self.action.owner(),
+                retries,
+                retry_policy.max_retries(),
+                reason,
+            );
+            self.command_reports.extend(result.rejected_execution);
+            self.command_reports.push(result.report);
+            tokio::time::sleep(retry_policy.backoff(retries)).await;
+            manager = self.command_execution_manager();
+        }
     }
 
     async fn cache_upload(
@@ -551,6 +600,7 @@ impl BuckActionExecutor {
         &self,
         inputs: IndexMap<ArtifactGroup, ArtifactGroupValues>,
         action: &RegisteredAction,
+        stamp_info: Option<&StampInfo>,
         cancellations: &CancellationContext<'_>,
     ) -> (
         Result<(ActionOutputs, ActionExecutionMetadata), ExecuteError>,
@@ -568,6 +618,7 @@ impl BuckActionExecutor {
                 outputs: outputs.as_ref(),
                 command_reports: &mut command_reports,
                 cancellations,
+                stamp_info,
             };
 
             let (result, metadata) = match action.as_executable() {
@@ -751,6 +802,7 @@ mod tests {
                 CommandGenerationOptions {
                     path_separator: PathSeparatorKind::Unix,
                     output_paths_behavior: Default::default(),
+                    use_remote_persistent_workers: false,
                 },
                 Default::default(),
             ),
@@ -762,6 +814,7 @@ mod tests {
             ManagedRemoteExecutionClient::testing_new_dummy(),
             DigestConfig::testing_default(),
             Default::default(),
+            Default::default(),
             Arc::new(FsIoProvider::new(
                 project_fs,
                 CasDigestConfig::testing_default(),
@@ -848,7 +901,7 @@ mod tests {
                 // on fake executor, this does nothing
                 let prepared_action = ctx.prepare_action(&req)?;
                 let manager = ctx.command_execution_manager();
-                let res = ctx.exec_cmd(manager, &req, &prepared_action).await;
+                let res = ctx.exec_cmd(manager, &req, &prepared_action, None).await;
 
                 // Must write out the things we promised to do
                 for x in &self.outputs {
@@ -911,7 +964,12 @@ mod tests {
         );
         let res = with_dispatcher_async(
             EventDispatcher::null(),
-            executor.execute(Default::default(), &action, CancellationContext::testing()),
+            executor.execute(
+                Default::default(),
+                &action,
+                None,
+                CancellationContext::testing(),
+            ),
         )
         .await
         .0
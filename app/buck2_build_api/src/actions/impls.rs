@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+pub mod action_retry_policy;
 pub mod expanded_command_line;
 pub mod json;
 pub mod run_action_knobs;
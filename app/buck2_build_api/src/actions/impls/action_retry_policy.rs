@@ -0,0 +1,325 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fmt;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use buck2_common::legacy_configs::configs::LegacyBuckConfig;
+use buck2_common::legacy_configs::key::BuckconfigKeyRef;
+use buck2_execute::execute::result::CommandExecutionReport;
+use buck2_execute::execute::result::CommandExecutionStatus;
+use dice::UserComputationData;
+use dupe::Dupe;
+use itertools::Itertools;
+use regex::Regex;
+
+/// The section listing the build-wide signatures, one per key: `name = regex`. Regexes are not
+/// split on commas, so they can contain any character.
+const SIGNATURES_SECTION: &str = "retry_failure_signatures";
+
+/// Which command failures are considered transient (a lost RE worker, a SIGBUS from a network
+/// filesystem, a docker daemon hiccup...) and how many times a command failing that way is
+/// re-executed before the failure is reported.
+#[derive(Default, Debug)]
+pub struct ActionRetryPolicy {
+    /// `(name, regex)`. Signatures from `retry_on` are named after their regex.
+    signatures: Vec<(String, Regex)>,
+    max_retries: u32,
+    /// Retry every failure, not only those matching `signatures`.
+    any_failure: bool,
+}
+
+impl ActionRetryPolicy {
+    /// Reads the `[retry_failure_signatures]` section, in name order, and
+    /// `build.retry_failure_max_retries`, which defaults to 1.
+    pub fn from_config(config: &LegacyBuckConfig) -> anyhow::Result<Self> {
+        let signatures = match config.get_section(SIGNATURES_SECTION) {
+            Some(section) => section
+                .iter()
+                .map(|(name, value)| {
+                    let regex = Regex::new(value.as_str()).with_context(|| {
+                        format!("Invalid regex for `{}.{}`", SIGNATURES_SECTION, name)
+                    })?;
+                    anyhow::Ok((name.to_owned(), regex))
+                })
+                .collect::<anyhow::Result<_>>()?,
+            None => Vec::new(),
+        };
+        let max_retries = config
+            .parse::<u32>(BuckconfigKeyRef {
+                section: "build",
+                property: "retry_failure_max_retries",
+            })?
+            .unwrap_or(1);
+        Ok(Self {
+            signatures,
+            max_retries,
+            any_failure: false,
+        })
+    }
+
+    /// The policy of an action passing `retries` (and optionally `retry_on`) to `run`: without
+    /// `retry_on`, any failure is retried.
+    pub fn for_action(retries: u32, retry_on: &[String]) -> anyhow::Result<Self> {
+        let signatures = retry_on
+            .iter()
+            .map(|s| anyhow::Ok((s.clone(), Regex::new(s)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self {
+            any_failure: signatures.is_empty(),
+            signatures,
+            max_retries: retries,
+        })
+    }
+
+    pub fn max_retries(&self) -> u32 {
+        if self.signatures.is_empty() && !self.any_failure {
+            0
+        } else {
+            self.max_retries
+        }
+    }
+
+    /// How long to wait before the given retry (starting at 1): half a second, doubling with
+    /// each attempt, up to 30 seconds.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let doublings = retry.saturating_sub(1).min(6);
+        Duration::from_millis(500 << doublings).min(Duration::from_secs(30))
+    }
+
+    /// Returns why this failed command should be retried, if it should. Signatures are matched
+    /// against the error for infra errors, and against the exit code (as `exit code: N`) and
+    /// stderr for commands that ran and failed. Successes, timeouts and cancellations are never
+    /// retried.
+    pub async fn retry_reason(&self, report: &CommandExecutionReport) -> Option<String> {
+        if self.signatures.is_empty() && !self.any_failure {
+            return None;
+        }
+        if self.any_failure {
+            return match &report.status {
+                CommandExecutionStatus::Error { .. } | CommandExecutionStatus::Failure { .. } => {
+                    Some("it failed".to_owned())
+                }
+                CommandExecutionStatus::Success { .. }
+                | CommandExecutionStatus::TimedOut { .. }
+                | CommandExecutionStatus::Cancelled => None,
+            };
+        }
+        let text = match &report.status {
+            CommandExecutionStatus::Error { error, .. } => format!("{:#}", error),
+            CommandExecutionStatus::Failure { .. } => {
+                let mut text = String::new();
+                if let Some(exit_code) = report.exit_code {
+                    text.push_str(&format!("exit code: {}\n", exit_code));
+                }
+                text.push_str(&report.std_streams.to_lossy_stderr().await);
+                text
+            }
+            CommandExecutionStatus::Success { .. }
+            | CommandExecutionStatus::TimedOut { .. }
+            | CommandExecutionStatus::Cancelled => return None,
+        };
+        self.signatures
+            .iter()
+            .find(|(_, re)| re.is_match(&text))
+            .map(|(name, _)| format!("its failure matched `{}`", name))
+    }
+}
+
+/// Printed as the `retry_policy` attribute of actions in aquery.
+impl Display for ActionRetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.max_retries() == 0 {
+            write!(f, "None")
+        } else if self.any_failure {
+            write!(f, "max_retries={}, any failure", self.max_retries)
+        } else {
+            write!(
+                f,
+                "max_retries={}, signatures=[{}]",
+                self.max_retries,
+                self.signatures.iter().format_with(", ", |(name, re), f| {
+                    if name == re.as_str() {
+                        f(re)
+                    } else {
+                        f(&format_args!("{}={}", name, re))
+                    }
+                })
+            )
+        }
+    }
+}
+
+pub trait HasActionRetryPolicy {
+    fn set_action_retry_policy(&mut self, policy: Arc<ActionRetryPolicy>);
+
+    fn get_action_retry_policy(&self) -> Arc<ActionRetryPolicy>;
+}
+
+impl HasActionRetryPolicy for UserComputationData {
+    fn set_action_retry_policy(&mut self, policy: Arc<ActionRetryPolicy>) {
+        self.data.set(policy);
+    }
+
+    fn get_action_retry_policy(&self) -> Arc<ActionRetryPolicy> {
+        // Not set in tests and tools that don't execute commands: never retry there.
+        self.data
+            .get::<Arc<ActionRetryPolicy>>()
+            .map_or_else(|_| Arc::new(ActionRetryPolicy::default()), |p| p.dupe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_common::legacy_configs::configs::testing::parse;
+    use buck2_execute::digest_config::DigestConfig;
+    use buck2_execute::execute::action_digest::ActionDigest;
+    use buck2_execute::execute::kind::CommandExecutionKind;
+    use buck2_execute::execute::output::CommandStdStreams;
+    use buck2_execute::execute::result::CommandExecutionErrorType;
+
+    use super::*;
+
+    fn policy(config: &str) -> ActionRetryPolicy {
+        ActionRetryPolicy::from_config(&parse(&[("/config", config)], "/config").unwrap()).unwrap()
+    }
+
+    fn report(status: CommandExecutionStatus, exit_code: Option<i32>) -> CommandExecutionReport {
+        CommandExecutionReport {
+            claim: None,
+            status,
+            timing: Default::default(),
+            std_streams: CommandStdStreams::Local {
+                stdout: Vec::new(),
+                stderr: b"Bus error (core dumped)\n".to_vec(),
+            },
+            exit_code,
+        }
+    }
+
+    fn failure(exit_code: i32) -> CommandExecutionReport {
+        let digest_config = DigestConfig::testing_default();
+        report(
+            CommandExecutionStatus::Failure {
+                execution_kind: CommandExecutionKind::Local {
+                    digest: ActionDigest::empty(digest_config.cas_digest_config()),
+                    command: Default::default(),
+                    env: Default::default(),
+                },
+            },
+            Some(exit_code),
+        )
+    }
+
+    fn error(message: &str) -> CommandExecutionReport {
+        report(
+            CommandExecutionStatus::Error {
+                stage: "remote_call",
+                error: anyhow::anyhow!("{}", message),
+                execution_kind: None,
+                typ: CommandExecutionErrorType::Other,
+            },
+            None,
+        )
+    }
+
+    #[test]
+    fn test_from_config() {
+        let p = policy(
+            "[retry_failure_signatures]\n\
+            sigbus = ^exit code: 135$\n\
+            lost_worker = (worker lost|Worker \\d+, of \\d+, exited)\n\
+            [build]\n\
+            retry_failure_max_retries = 3\n",
+        );
+        assert_eq!(3, p.max_retries());
+        // The comma in the regex is kept.
+        assert_eq!(
+            "max_retries=3, signatures=[lost_worker=(worker lost|Worker \\d+, of \\d+, exited), \
+            sigbus=^exit code: 135$]",
+            p.to_string()
+        );
+    }
+
+    #[test]
+    fn test_no_signatures() {
+        let p = policy("[build]\nretry_failure_max_retries = 3\n");
+        assert_eq!(0, p.max_retries());
+        assert_eq!("None", p.to_string());
+        assert_eq!(0, ActionRetryPolicy::default().max_retries());
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        let config = parse(
+            &[("/config", "[retry_failure_signatures]\nbad = (\n")],
+            "/config",
+        )
+        .unwrap();
+        let err = ActionRetryPolicy::from_config(&config).err().unwrap();
+        assert!(
+            err.to_string().contains("retry_failure_signatures.bad"),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_reason() {
+        let p = policy(
+            "[retry_failure_signatures]\n\
+            lost_worker = Worker \\d+, of \\d+, exited\n\
+            sigbus = Bus error\n",
+        );
+        assert_eq!(1, p.max_retries());
+        assert_eq!(
+            Some("its failure matched `sigbus`".to_owned()),
+            p.retry_reason(&failure(135)).await
+        );
+        assert_eq!(
+            Some("its failure matched `lost_worker`".to_owned()),
+            p.retry_reason(&error("Worker 3, of 10, exited")).await
+        );
+        assert_eq!(None, p.retry_reason(&error("Permission denied")).await);
+
+        let p = policy("[retry_failure_signatures]\nsigbus = (?m)^exit code: 135$\n");
+        assert!(p.retry_reason(&failure(135)).await.is_some());
+        assert_eq!(None, p.retry_reason(&failure(1)).await);
+    }
+
+    #[tokio::test]
+    async fn test_for_action() {
+        let p = ActionRetryPolicy::for_action(2, &[]).unwrap();
+        assert_eq!("max_retries=2, any failure", p.to_string());
+        assert_eq!(
+            Some("it failed".to_owned()),
+            p.retry_reason(&failure(1)).await
+        );
+
+        let p = ActionRetryPolicy::for_action(2, &["Bus error, again".to_owned()]).unwrap();
+        assert_eq!(
+            "max_retries=2, signatures=[Bus error, again]",
+            p.to_string()
+        );
+        assert_eq!(None, p.retry_reason(&failure(135)).await);
+
+        assert!(ActionRetryPolicy::for_action(2, &["(".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn test_backoff() {
+        let p = ActionRetryPolicy::default();
+        assert_eq!(Duration::from_millis(500), p.backoff(1));
+        assert_eq!(Duration::from_secs(1), p.backoff(2));
+        assert_eq!(Duration::from_secs(30), p.backoff(10));
+    }
+}
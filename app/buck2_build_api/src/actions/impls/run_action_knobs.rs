@@ -7,11 +7,14 @@
  * of this source tree.
  */
 
+use std::sync::Arc;
+use std::time::Duration;
+
 use dice::UserComputationData;
 use dupe::Dupe;
 
 /// Knobs controlling how RunAction works.
-#[derive(Copy, Clone, Dupe, Default)]
+#[derive(Clone, Dupe, Default)]
 pub struct RunActionKnobs {
     /// Process dep files as they are generated.
     pub eager_dep_files: bool,
@@ -24,6 +27,25 @@ pub struct RunActionKnobs {
     /// for network actions (download_file, cas_artifact). Used to support offline
     /// builds.
     pub use_network_action_output_cache: bool,
+
+    /// Never use the network. Network actions can only reuse outputs from the offline
+    /// cache or from earlier builds.
+    pub offline: bool,
+
+    /// Timeout for commands run by actions that don't set their own `timeout`.
+    pub default_timeout: Option<Duration>,
+
+    /// Run commands with an empty environment, except for the variables in
+    /// `env_allowlist`, unless an action sets `clean_env` itself.
+    pub clean_env: bool,
+
+    /// Variables passed through from the daemon's environment to commands run with a clean
+    /// environment, in addition to those allowed by the action itself.
+    pub env_allowlist: Arc<[String]>,
+
+    /// Reject commands whose command lines contain the absolute path of the project, or refer to
+    /// files in buck-out which are not inputs or outputs of their action.
+    pub validate_command_lines: bool,
 }
 
 pub trait HasRunActionKnobs {
@@ -38,9 +60,9 @@ impl HasRunActionKnobs for UserComputationData {
     }
 
     fn get_run_action_knobs(&self) -> RunActionKnobs {
-        *self
-            .data
+        self.data
             .get::<RunActionKnobs>()
             .expect("RunActionKnobs should be set")
+            .dupe()
     }
 }
@@ -25,7 +25,9 @@ use buck2_core::cells::CellResolver;
 use buck2_core::fs::artifact_path_resolver::ArtifactFs;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_execute::artifact::artifact_dyn::ArtifactDyn;
 use buck2_execute::artifact::fs::ExecutorFs;
 use buck2_query::query::environment::QueryTarget;
 use buck2_query::query::graph::node::LabeledNode;
@@ -35,14 +37,19 @@ use derivative::Derivative;
 use dice::DiceComputations;
 use dupe::Dupe;
 use either::Either;
+use futures::FutureExt;
 use gazebo::variants::VariantName;
 use indexmap::IndexMap;
 use internment::ArcIntern;
+use itertools::Itertools;
 use ref_cast::RefCast;
 use serde::Serialize;
 
+use crate::actions::impls::action_retry_policy::ActionRetryPolicy;
 use crate::actions::RegisteredAction;
 use crate::analysis::AnalysisResult;
+use crate::artifact_groups::calculation::ArtifactGroupCalculation;
+use crate::artifact_groups::ArtifactGroup;
 use crate::artifact_groups::TransitiveSetProjectionKey;
 use crate::interpreter::rule_defs::provider::collection::FrozenProviderCollectionValue;
 
@@ -132,21 +139,61 @@ pub enum ActionQueryNodeData {
 }
 
 impl ActionQueryNode {
+    /// `sources` are the source files among the direct inputs of the action.
     pub fn new_action(
         action: Arc<RegisteredAction>,
         deps: Vec<ActionInput>,
+        sources: Vec<CellPath>,
         fs: Arc<ArtifactFs>,
+        retry_policy: Arc<ActionRetryPolicy>,
     ) -> Self {
         Self {
             key: ActionQueryNodeRef::Action(action.key().dupe()),
             data: ActionQueryNodeData::Action(ActionData {
                 action,
                 deps: Arc::new(deps),
+                sources: Arc::new(sources),
                 fs,
+                retry_policy,
+                inputs_digests: None,
             }),
         }
     }
 
+    /// Builds the inputs of the action and records their digests, to be printed as the
+    /// `inputs_digests` attribute. Analysis nodes are returned unchanged.
+    pub async fn with_inputs_digests(self, ctx: &mut DiceComputations<'_>) -> anyhow::Result<Self> {
+        let data = match &self.data {
+            ActionQueryNodeData::Action(data) => data,
+            ActionQueryNodeData::Analysis(..) => return Ok(self),
+        };
+        let inputs = data.action.inputs()?.into_owned();
+        let values = ctx
+            .try_compute_join(inputs, |ctx, input| {
+                async move { ctx.ensure_artifact_group(&input).await }.boxed()
+            })
+            .await?;
+
+        let mut inputs_digests = Vec::new();
+        for (artifact, value) in values.iter().flat_map(|v| v.iter()) {
+            let path = artifact.resolve_path(&data.fs)?;
+            let digest = match value.digest() {
+                Some(digest) => digest.to_string(),
+                None => "symlink".to_owned(),
+            };
+            inputs_digests.push((path.to_string(), digest));
+        }
+        inputs_digests.sort();
+        inputs_digests.dedup();
+
+        let mut data = data.clone();
+        data.inputs_digests = Some(Arc::new(inputs_digests));
+        Ok(Self {
+            key: self.key,
+            data: ActionQueryNodeData::Action(data),
+        })
+    }
+
     pub fn new_analysis(target: ConfiguredProvidersLabel, analysis: AnalysisResult) -> Self {
         let target = Arc::new(target);
 
@@ -174,6 +221,20 @@ impl ActionQueryNode {
         &self.data
     }
 
+    /// The paths of the outputs of the action in buck-out. Analysis nodes have no outputs of their
+    /// own: use `all_outputs()` to get the actions producing the outputs of a target.
+    pub fn output_paths(&self) -> anyhow::Result<Vec<ProjectRelativePathBuf>> {
+        match &self.data {
+            ActionQueryNodeData::Analysis(..) => Ok(Vec::new()),
+            ActionQueryNodeData::Action(data) => Ok(data
+                .action
+                .outputs()?
+                .iter()
+                .map(|output| data.fs.resolve_build(output.get_path()))
+                .collect()),
+        }
+    }
+
     pub fn key(&self) -> &ActionQueryNodeRef {
         &self.key
     }
@@ -214,11 +275,44 @@ impl AnalysisData {
 pub struct ActionData {
     action: Arc<RegisteredAction>,
     deps: Arc<Vec<ActionInput>>,
+    sources: Arc<Vec<CellPath>>,
     #[derivative(Debug = "ignore")]
     fs: Arc<ArtifactFs>,
+    /// How the commands of the action are retried when they fail.
+    #[derivative(Debug = "ignore")]
+    retry_policy: Arc<ActionRetryPolicy>,
+    /// Sorted `(path, digest)` pairs, only set by `with_inputs_digests`.
+    inputs_digests: Option<Arc<Vec<(String, String)>>>,
 }
 
 impl ActionData {
+    /// The inputs the action declares, as paths. Transitive sets aren't expanded: use `deps()` to
+    /// get the actions producing their contents.
+    fn declared_inputs(&self) -> String {
+        let inputs = match self.action.inputs() {
+            Ok(inputs) => inputs,
+            Err(e) => return format!("<error: {:#}>", e),
+        };
+        format_list(inputs.iter().map(|input| match input {
+            ArtifactGroup::Artifact(artifact) => match artifact.resolve_path(&self.fs) {
+                Ok(path) => path.to_string(),
+                Err(_) => artifact.to_string(),
+            },
+            _ => input.to_string(),
+        }))
+    }
+
+    fn declared_outputs(&self) -> String {
+        match self.action.outputs() {
+            Ok(outputs) => format_list(
+                outputs
+                    .iter()
+                    .map(|output| self.fs.resolve_build(output.get_path()).to_string()),
+            ),
+            Err(e) => format!("<error: {:#}>", e),
+        }
+    }
+
     fn attrs(&self) -> IndexMap<String, String> {
         let mut attrs = self.action.action().aquery_attributes(&ExecutorFs::new(
             &self.fs,
@@ -228,10 +322,28 @@ impl ActionData {
             "executor_configuration".to_owned(),
             self.action.execution_config().executor.to_string(),
         );
+        // Actions with their own policy (`run(retries = ...)`) already set it.
+        attrs
+            .entry("retry_policy".to_owned())
+            .or_insert_with(|| self.retry_policy.to_string());
+        if let Some(inputs_digests) = &self.inputs_digests {
+            attrs.insert(
+                "inputs_digests".to_owned(),
+                format_list(
+                    inputs_digests
+                        .iter()
+                        .map(|(path, digest)| format!("{}={}", path, digest)),
+                ),
+            );
+        }
         attrs
     }
 }
 
+fn format_list(items: impl Iterator<Item = String>) -> String {
+    format!("[{}]", items.format(", "))
+}
+
 #[derive(
     Debug,
     Clone,
@@ -342,7 +454,8 @@ impl QueryTarget for ActionQueryNode {
             ActionAttr::new(action.action.identifier().unwrap_or("")),
         )?;
 
-        // inputs and outputs are not supported for aquery
+        func("inputs", ActionAttr::new(&action.declared_inputs()))?;
+        func("outputs", ActionAttr::new(&action.declared_outputs()))?;
 
         for (k, v) in action.attrs() {
             func(&k, ActionAttr::new(&v))?;
@@ -373,12 +486,21 @@ impl QueryTarget for ActionQueryNode {
         }
     }
 
+    /// Only the source files the action uses directly: generated inputs have no cell path, and
+    /// the inputs in transitive sets aren't included.
     fn inputs_for_each<E, F: FnMut(CellPath) -> Result<(), E>>(
         &self,
-        mut _func: F,
+        mut func: F,
     ) -> Result<(), E> {
-        // TODO(cjhopman): In addition to implementing this, we should be able to return an anyhow::Error here rather than panicking.
-        unimplemented!("inputs not yet implemented in aquery")
+        match &self.data {
+            ActionQueryNodeData::Action(data) => {
+                for source in data.sources.iter() {
+                    func(source.clone())?;
+                }
+                Ok(())
+            }
+            ActionQueryNodeData::Analysis(..) => Ok(()),
+        }
     }
 }
 
@@ -244,13 +244,13 @@ impl ActionsRegistry {
                 }
             }
 
+            let executor_config = self
+                .execution_platform
+                .executor_config()?
+                .for_category(action.category().as_str());
             registry.bind_trivial(
                 key,
-                RegisteredAction::new(
-                    action_key,
-                    action,
-                    (*self.execution_platform.executor_config()?).dupe(),
-                ),
+                RegisteredAction::new(action_key, action, executor_config),
             );
         }
 
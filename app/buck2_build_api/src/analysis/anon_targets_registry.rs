@@ -12,6 +12,7 @@ use std::marker::PhantomData;
 
 use allocative::Allocative;
 use buck2_core::execution_types::execution::ExecutionPlatformResolution;
+use buck2_core::package::PackageLabel;
 use buck2_util::late_binding::LateBinding;
 use starlark::any::AnyLifetime;
 use starlark::values::Trace;
@@ -31,6 +32,9 @@ pub trait AnonTargetsRegistryDyn<'v>:
     Debug + Allocative + Trace<'v> + AnyLifetime<'v> + 'v
 {
     fn as_any_mut(&mut self) -> &mut dyn AnyLifetime<'v>;
+    /// Sets the package relative labels in anon target attributes are resolved against, if
+    /// `relative_labels_allowed`.
+    fn set_base_package(&mut self, package: PackageLabel, relative_labels_allowed: bool);
     fn take_promises(&mut self) -> Option<Box<dyn AnonPromisesDyn<'v>>>;
     fn consumer_analysis_artifacts(&self) -> Vec<PromiseArtifact>;
     fn assert_no_promises(&self) -> anyhow::Result<()>;
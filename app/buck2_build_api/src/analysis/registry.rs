@@ -17,11 +17,13 @@ use buck2_artifact::artifact::artifact_type::Artifact;
 use buck2_artifact::artifact::artifact_type::DeclaredArtifact;
 use buck2_artifact::artifact::artifact_type::OutputArtifact;
 use buck2_artifact::deferred::id::DeferredId;
+use buck2_common::action_input_budgets::ActionInputBudgets;
 use buck2_core::base_deferred_key::BaseDeferredKey;
 use buck2_core::execution_types::execution::ExecutionPlatformResolution;
 use buck2_core::fs::buck_out_path::BuckOutPath;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_core::package::PackageLabel;
 use buck2_error::internal_error;
 use buck2_error::BuckErrorContext;
 use buck2_execute::execute::request::OutputType;
@@ -85,6 +87,8 @@ pub struct AnalysisRegistry<'v> {
     pub anon_targets: Box<dyn AnonTargetsRegistryDyn<'v>>,
     analysis_value_storage: AnalysisValueStorage<'v>,
     pub short_path_assertions: HashMap<PromiseArtifactId, ForwardRelativePathBuf>,
+    #[trace(unsafe_ignore)]
+    action_input_budgets: Option<Arc<ActionInputBudgets>>,
 }
 
 #[derive(buck2_error::Error, Debug)]
@@ -118,6 +122,7 @@ impl<'v> AnalysisRegistry<'v> {
             anon_targets: (ANON_TARGET_REGISTRY_NEW.get()?)(PhantomData, execution_platform),
             analysis_value_storage: AnalysisValueStorage::new(),
             short_path_assertions: HashMap::new(),
+            action_input_budgets: None,
         })
     }
 
@@ -276,6 +281,34 @@ impl<'v> AnalysisRegistry<'v> {
         Ok(())
     }
 
+    pub fn set_anon_target_base_package(
+        &mut self,
+        package: PackageLabel,
+        relative_labels_allowed: bool,
+    ) {
+        self.anon_targets
+            .set_base_package(package, relative_labels_allowed)
+    }
+
+    pub fn set_action_input_budgets(&mut self, budgets: Option<Arc<ActionInputBudgets>>) {
+        self.action_input_budgets = budgets;
+    }
+
+    /// The maximum number of inputs of a run action in `category`, if it has a budget.
+    pub fn action_input_budget(&self, category: &str) -> Option<u64> {
+        self.action_input_budgets
+            .as_ref()
+            .and_then(|budgets| budgets.max_inputs(category))
+    }
+
+    /// The maximum total size in bytes of the inputs of a run action in `category`, if it has a
+    /// budget.
+    pub fn action_input_size_budget(&self, category: &str) -> Option<u64> {
+        self.action_input_budgets
+            .as_ref()
+            .and_then(|budgets| budgets.max_input_bytes(category))
+    }
+
     pub(crate) fn take_promises(&mut self) -> Option<Box<dyn AnonPromisesDyn<'v>>> {
         self.anon_targets.take_promises()
     }
@@ -10,11 +10,11 @@
 use std::iter::Peekable;
 
 use anyhow::Context;
-use buck2_build_api::bxl::types::BxlFunctionLabel;
 use buck2_core::cells::cell_path::CellPath;
 use buck2_core::cells::name::CellName;
 use buck2_core::cells::paths::CellRelativePath;
 use buck2_core::cells::CellResolver;
+use buck2_core::configuration::data::ConfigurationData;
 use buck2_core::fs::paths::file_name::FileName;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
@@ -26,6 +26,8 @@ use buck2_interpreter::paths::bxl::BxlFilePath;
 use dupe::Dupe;
 use itertools::Itertools;
 
+use crate::bxl::types::BxlFunctionLabel;
+
 #[derive(Debug, buck2_error::Error)]
 enum BuckOutPathParserError {
     #[error(
@@ -39,16 +41,16 @@ enum BuckOutPathParserError {
 }
 
 /// The common attributes of each `buck-out` path type,
-pub(crate) struct BuckOutPathTypeCommon {
+pub struct BuckOutPathTypeCommon {
     /// Configuration hash within the `buck-out` path.
-    pub(crate) config_hash: String,
+    pub config_hash: String,
     /// The path starting from cell to the artifact, without the configuration hash. For example, in
     /// `buck-out/v2/gen/cell/<CONFIG_HASH>/path/to/__target_name__/target`, it would be `cell/path/to/__target_name__/target`.
-    pub(crate) raw_path_to_output: ForwardRelativePathBuf,
+    pub raw_path_to_output: ForwardRelativePathBuf,
 }
 
 /// The types of the `buck-out` path.
-pub(crate) enum BuckOutPathType {
+pub enum BuckOutPathType {
     BxlOutput {
         // `BxlFunctionLabel` contains the `CellPath` to the bxl function.
         bxl_function_label: BxlFunctionLabel,
@@ -79,7 +81,14 @@ pub(crate) enum BuckOutPathType {
     },
 }
 
-pub(crate) struct BuckOutPathParser<'v> {
+/// The configuration with the hash found in a buck-out path. Only the configurations this daemon
+/// has created are known, so this won't find configurations from a build by an earlier daemon
+/// which this daemon hasn't used yet.
+pub fn lookup_configuration(config_hash: &str) -> Option<ConfigurationData> {
+    ConfigurationData::iter_existing().find(|cfg| cfg.output_hash().as_str() == config_hash)
+}
+
+pub struct BuckOutPathParser<'v> {
     cell_resolver: &'v CellResolver,
 }
 
@@ -271,13 +280,13 @@ fn get_bxl_function_label<'v>(
 }
 
 impl<'v> BuckOutPathParser<'v> {
-    pub(crate) fn new(cell_resolver: &'v CellResolver) -> BuckOutPathParser {
+    pub fn new(cell_resolver: &'v CellResolver) -> BuckOutPathParser {
         BuckOutPathParser { cell_resolver }
     }
 
     // Validates and parses the buck-out path, returning the `BuckOutPathType`. Assumes
     // that the inputted path is not a symlink.
-    pub(crate) fn parse(&self, output_path: &str) -> anyhow::Result<BuckOutPathType> {
+    pub fn parse(&self, output_path: &str) -> anyhow::Result<BuckOutPathType> {
         match self.parse_inner(output_path) {
             Ok(res) => Ok(res),
             Err(e) => {
@@ -409,7 +418,6 @@ impl<'v> BuckOutPathParser<'v> {
 mod tests {
     use std::collections::BTreeMap;
 
-    use buck2_build_api::bxl::types::BxlFunctionLabel;
     use buck2_core::cells::cell_path::CellPath;
     use buck2_core::cells::cell_root_path::CellRootPath;
     use buck2_core::cells::name::CellName;
@@ -424,8 +432,10 @@ mod tests {
     use buck2_core::target::name::TargetNameRef;
     use buck2_interpreter::paths::bxl::BxlFilePath;
 
-    use crate::output::buck_out_path_parser::BuckOutPathParser;
-    use crate::output::buck_out_path_parser::BuckOutPathType;
+    use crate::buck_out_path_parser::lookup_configuration;
+    use crate::buck_out_path_parser::BuckOutPathParser;
+    use crate::buck_out_path_parser::BuckOutPathType;
+    use crate::bxl::types::BxlFunctionLabel;
 
     fn get_parse_test_cell_resolver() -> anyhow::Result<CellResolver> {
         let cell_path = CellRootPath::new(ProjectRelativePath::new("foo/bar")?);
@@ -718,4 +728,28 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_lookup_configuration() -> anyhow::Result<()> {
+        let configuration = ConfigurationData::from_platform(
+            "cfg_for//:lookup_configuration_testing".to_owned(),
+            ConfigurationDataData {
+                constraints: BTreeMap::new(),
+            },
+        )?;
+        let cell_resolver = get_parse_test_cell_resolver()?;
+        let path = format!(
+            "buck-out/v2/gen/bar/{}/path/to/target/__target_name__/output",
+            configuration.output_hash()
+        );
+        let config_hash = match BuckOutPathParser::new(&cell_resolver).parse(&path)? {
+            BuckOutPathType::RuleOutput { common_attrs, .. } => common_attrs.config_hash,
+            _ => panic!("Should have parsed buck-out path successfully"),
+        };
+
+        assert_eq!(Some(configuration), lookup_configuration(&config_hash));
+        // Configurations this daemon has not created are not found.
+        assert_eq!(None, lookup_configuration("0123456789abcdef"));
+        Ok(())
+    }
 }
@@ -25,6 +25,7 @@ use buck2_core::provider::label::ProvidersLabel;
 use buck2_error::BuckErrorContext;
 use buck2_events::dispatch::console_message;
 use buck2_execute::artifact::fs::ExecutorFs;
+use buck2_node::deprecation::DeprecatedUse;
 use buck2_node::nodes::configured_frontend::ConfiguredTargetNodeCalculation;
 use dashmap::DashMap;
 use dice::DiceComputations;
@@ -75,6 +76,9 @@ pub struct ConfiguredBuildTargetResultGen<T> {
     pub outputs: Vec<T>,
     pub run_args: Option<Vec<String>>,
     pub target_rule_type_name: Option<String>,
+    pub target_oncall: Option<String>,
+    /// The deprecated rule and attributes used by the target.
+    pub target_deprecated_uses: Vec<DeprecatedUse>,
     pub configured_graph_size: Option<buck2_error::Result<MaybeCompatible<u64>>>,
     pub errors: Vec<buck2_error::Error>,
 }
@@ -116,12 +120,16 @@ impl BuildTargetResult {
                 ConfiguredBuildEventVariant::Prepared {
                     run_args,
                     target_rule_type_name,
+                    target_oncall,
+                    target_deprecated_uses,
                 } => {
                     res.entry((*label).clone())
                         .or_insert(Some(ConfiguredBuildTargetResultGen {
                             outputs: Vec::new(),
                             run_args,
                             target_rule_type_name: Some(target_rule_type_name),
+                            target_oncall,
+                            target_deprecated_uses,
                             configured_graph_size: None,
                             errors: Vec::new(),
                         }));
@@ -155,6 +163,8 @@ impl BuildTargetResult {
                             outputs: Vec::new(),
                             run_args: None,
                             target_rule_type_name: None,
+                            target_oncall: None,
+                            target_deprecated_uses: Vec::new(),
                             configured_graph_size: None,
                             errors: Vec::new(),
                         }))
@@ -179,6 +189,8 @@ impl BuildTargetResult {
                         mut outputs,
                         run_args,
                         target_rule_type_name,
+                        target_oncall,
+                        target_deprecated_uses,
                         configured_graph_size,
                         errors,
                     } = result;
@@ -198,6 +210,8 @@ impl BuildTargetResult {
                             .collect(),
                         run_args,
                         target_rule_type_name,
+                        target_oncall,
+                        target_deprecated_uses,
                         configured_graph_size,
                         errors,
                     }
@@ -219,6 +233,8 @@ enum ConfiguredBuildEventVariant {
     Prepared {
         run_args: Option<Vec<String>>,
         target_rule_type_name: String,
+        target_oncall: Option<String>,
+        target_deprecated_uses: Vec<DeprecatedUse>,
     },
     Output {
         output: buck2_error::Result<ProviderArtifacts>,
@@ -253,6 +269,9 @@ pub enum BuildEvent {
 pub struct BuildConfiguredLabelOptions {
     pub skippable: bool,
     pub want_configured_graph_size: bool,
+    /// Only materialize the outputs from `DefaultInfo.default_outputs`, and leave the other
+    /// outputs of this target in the CAS.
+    pub materialize_default_outputs_only: bool,
 }
 
 pub async fn build_configured_label<'a>(
@@ -289,7 +308,7 @@ async fn build_configured_label_inner<'a>(
 ) -> anyhow::Result<BoxStream<'a, ConfiguredBuildEvent>> {
     let artifact_fs = ctx.get().get_artifact_fs().await?;
 
-    let (outputs, run_args, target_rule_type_name) = {
+    let (outputs, run_args, target_rule_type_name, target_oncall, target_deprecated_uses) = {
         // A couple of these objects aren't Send and so scope them here so async transform doesn't get concerned.
         let providers = match ctx.get().get_providers(providers_label.as_ref()).await? {
             MaybeCompatible::Incompatible(reason) => {
@@ -368,16 +387,22 @@ async fn build_configured_label_inner<'a>(
             }
         }
 
-        let target_rule_type_name: String = ctx
+        let target_node = ctx
             .get()
             .get_configured_target_node(providers_label.target())
             .await?
-            .require_compatible()?
-            .rule_type()
-            .name()
-            .to_owned();
+            .require_compatible()?;
+        let target_rule_type_name: String = target_node.rule_type().name().to_owned();
+        let target_oncall: Option<String> = target_node.oncall().map(str::to_owned);
+        let target_deprecated_uses = target_node.deprecated_uses();
 
-        (outputs, run_args, target_rule_type_name)
+        (
+            outputs,
+            run_args,
+            target_rule_type_name,
+            target_oncall,
+            target_deprecated_uses,
+        )
     };
 
     if let Some(signals) = ctx
@@ -427,7 +452,13 @@ async fn build_configured_label_inner<'a>(
         .enumerate()
         .map({
             |(index, (output, provider_type))| {
-                let materialization_context = materialization_context.dupe();
+                let materialization_context = if opts.materialize_default_outputs_only
+                    && !matches!(provider_type, BuildProviderType::Default)
+                {
+                    MaterializationContext::Skip
+                } else {
+                    materialization_context.dupe()
+                };
                 async move {
                     let res = match materialize_artifact_group_owned(
                         &mut ctx.get(),
@@ -460,6 +491,8 @@ async fn build_configured_label_inner<'a>(
         variant: ConfiguredBuildEventVariant::Prepared {
             run_args,
             target_rule_type_name,
+            target_oncall,
+            target_deprecated_uses,
         },
     }))
     .chain(outputs);
@@ -586,10 +619,12 @@ impl ConvertMaterializationContext for Materializations {
                 map: Arc::new(DashMap::new()),
                 force: false,
             },
-            Materializations::Materialize => MaterializationContext::Materialize {
-                map: Arc::new(DashMap::new()),
-                force: true,
-            },
+            Materializations::Materialize | Materializations::MaterializeRequested => {
+                MaterializationContext::Materialize {
+                    map: Arc::new(DashMap::new()),
+                    force: true,
+                }
+            }
         }
     }
 
@@ -600,10 +635,12 @@ impl ConvertMaterializationContext for Materializations {
                 map: map.dupe(),
                 force: false,
             },
-            Materializations::Materialize => MaterializationContext::Materialize {
-                map: map.dupe(),
-                force: true,
-            },
+            Materializations::Materialize | Materializations::MaterializeRequested => {
+                MaterializationContext::Materialize {
+                    map: map.dupe(),
+                    force: true,
+                }
+            }
         }
     }
 }
@@ -11,6 +11,7 @@
 
 use std::collections::hash_map::DefaultHasher;
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::hash::Hash;
@@ -36,6 +37,7 @@ use buck2_core::target::label::label::TargetLabel;
 use buck2_error::UniqueRootId;
 use buck2_events::errors::create_error_report;
 use buck2_execute::artifact::artifact_dyn::ArtifactDyn;
+use buck2_node::deprecation::DeprecatedUse;
 use buck2_wrapper_common::invocation_id::TraceId;
 use derivative::Derivative;
 use dupe::Dupe;
@@ -75,6 +77,9 @@ pub struct BuildReport {
     project_root: AbsNormPathBuf,
     truncated: bool,
     strings: BTreeMap<String, String>,
+    /// The deprecated rules and attributes used by the targets that were built, by package
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    deprecations: BTreeMap<String, BTreeSet<BuildReportDeprecation>>,
 }
 
 /// The fields that stored in the unconfigured `BuildReportEntry` for buck1 backcompat.
@@ -104,6 +109,9 @@ struct MaybeConfiguredBuildReportEntry {
 pub(crate) struct ConfiguredBuildReportEntry {
     /// A list of errors that occurred while building this target
     errors: Vec<BuildReportError>,
+    /// The oncall declared for this target in its `BUCK` or `PACKAGE` file, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    oncall: Option<String>,
     #[serde(flatten)]
     inner: MaybeConfiguredBuildReportEntry,
 }
@@ -146,6 +154,42 @@ struct BuildReportError {
     cause_index: usize,
 }
 
+/// DO NOT UPDATE WITHOUT UPDATING `docs/users/build_observability/build_report.md`!
+#[derive(Debug, Serialize, PartialOrd, Ord, PartialEq, Eq)]
+struct BuildReportDeprecation {
+    target: String,
+    rule: String,
+    /// The deprecated attribute, or `None` if the rule itself is deprecated
+    attr: Option<String>,
+    message: String,
+    replaced_by: Option<String>,
+}
+
+impl BuildReportDeprecation {
+    fn new(deprecated_use: &DeprecatedUse) -> Self {
+        Self {
+            target: deprecated_use.target.to_string(),
+            rule: deprecated_use.rule.clone(),
+            attr: deprecated_use.attr.clone(),
+            message: deprecated_use.deprecation.message.clone(),
+            replaced_by: deprecated_use.deprecation.replaced_by.clone(),
+        }
+    }
+}
+
+/// Groups deprecated uses by package. A target built in several configurations is listed once.
+fn add_deprecations(
+    deprecations: &mut BTreeMap<String, BTreeSet<BuildReportDeprecation>>,
+    deprecated_uses: &[DeprecatedUse],
+) {
+    for deprecated_use in deprecated_uses {
+        deprecations
+            .entry(deprecated_use.target.pkg().to_string())
+            .or_default()
+            .insert(BuildReportDeprecation::new(deprecated_use));
+    }
+}
+
 #[derive(Derivative, Serialize, Eq, PartialEq, Hash)]
 #[derivative(Debug)]
 #[serde(untagged)]
@@ -174,6 +218,7 @@ pub struct BuildReportCollector<'a> {
     failures: HashMap<EntryLabel, String>,
     include_failures: bool,
     include_package_project_relative_paths: bool,
+    deprecations: BTreeMap<String, BTreeSet<BuildReportDeprecation>>,
 }
 
 impl<'a> BuildReportCollector<'a> {
@@ -201,6 +246,7 @@ impl<'a> BuildReportCollector<'a> {
             failures: HashMap::default(),
             include_failures,
             include_package_project_relative_paths,
+            deprecations: BTreeMap::default(),
         };
         let mut entries = HashMap::new();
 
@@ -246,6 +292,7 @@ impl<'a> BuildReportCollector<'a> {
             // Setting this to false since we don't currently truncate buck2's build report.
             truncated: false,
             strings: this.strings,
+            deprecations: this.deprecations,
         }
     }
 
@@ -402,6 +449,12 @@ impl<'a> BuildReportCollector<'a> {
 
             errors.extend(result.errors.iter().cloned());
 
+            if let Some(oncall) = &result.target_oncall {
+                configured_report.oncall = Some(oncall.clone());
+            }
+
+            add_deprecations(&mut self.deprecations, &result.target_deprecated_uses);
+
             if let Some(Ok(MaybeCompatible::Compatible(configured_graph_size))) =
                 result.configured_graph_size
             {
@@ -575,3 +628,66 @@ pub fn generate_build_report(
 
     Ok(serialized_build_report)
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_node::deprecation::Deprecation;
+
+    use super::*;
+
+    fn deprecated_use(target: &str, attr: Option<&str>) -> DeprecatedUse {
+        DeprecatedUse {
+            target: TargetLabel::testing_parse(target),
+            rule: "old_library".to_owned(),
+            attr: attr.map(str::to_owned),
+            deprecation: Deprecation {
+                message: "Old libraries are slow".to_owned(),
+                replaced_by: Some("new_library".to_owned()),
+            },
+        }
+    }
+
+    #[test]
+    fn test_add_deprecations() {
+        let mut deprecations = BTreeMap::new();
+        let uses = [
+            deprecated_use("root//foo:a", None),
+            deprecated_use("root//foo:a", Some("legacy_flag")),
+            deprecated_use("root//bar:b", None),
+        ];
+        // The same targets, built in another configuration.
+        add_deprecations(&mut deprecations, &uses);
+        add_deprecations(&mut deprecations, &uses);
+
+        assert_eq!(
+            serde_json::json!({
+                "root//bar": [
+                    {
+                        "target": "root//bar:b",
+                        "rule": "old_library",
+                        "attr": null,
+                        "message": "Old libraries are slow",
+                        "replaced_by": "new_library",
+                    },
+                ],
+                "root//foo": [
+                    {
+                        "target": "root//foo:a",
+                        "rule": "old_library",
+                        "attr": null,
+                        "message": "Old libraries are slow",
+                        "replaced_by": "new_library",
+                    },
+                    {
+                        "target": "root//foo:a",
+                        "rule": "old_library",
+                        "attr": "legacy_flag",
+                        "message": "Old libraries are slow",
+                        "replaced_by": "new_library",
+                    },
+                ],
+            }),
+            serde_json::to_value(&deprecations).unwrap()
+        );
+    }
+}
@@ -18,7 +18,9 @@ pub mod command_executor_config;
 pub mod context;
 pub mod digest_config;
 pub mod label_relative_path;
+pub mod native_rules;
 pub mod plugins;
+pub mod pprint;
 pub mod provider;
 pub mod resolve_query_macro;
 pub mod resolved_macro;
@@ -121,6 +121,12 @@ pub(crate) fn artifact_methods(builder: &mut MethodsBuilder) {
     /// yields the file bar. It is possible for projected artifacts to hide the prefix in order to
     /// have the short name of the resulting artifact only contain the projected path, by passing
     /// `hide_prefix = True` to `project()`.
+    ///
+    /// Buck2 tracks the digest of every member of an output directory, so an action that only
+    /// uses a projection of a directory (rather than the whole directory) is keyed on the
+    /// projected member alone: changing other members of the directory won't rerun it or miss
+    /// its action cache entry. When the action runs locally, only the projected member is
+    /// downloaded, unless the rest of the directory is needed too.
     fn project<'v>(
         this: &'v dyn StarlarkArtifactLike,
         #[starlark(require = pos)] path: &str,
@@ -107,6 +107,12 @@ impl<'v> StarlarkValue<'v> for ArtifactTag {
 
 #[starlark_module]
 fn artifact_tag_methods(_: &mut MethodsBuilder) {
+    /// Wrap a value (e.g. an artifact, a list of artifacts, or `cmd_args`) so that the input and
+    /// output artifacts it contains carry this tag. If the value is usable as a command line,
+    /// the result is too.
+    ///
+    /// When passed to `run`, the output artifact tagged with a tag used in `dep_files` is the
+    /// dep file, and the input artifacts tagged with it are the inputs that dep file covers.
     fn tag_artifacts<'v>(
         this: &ArtifactTag,
         inner: Value<'v>,
@@ -120,6 +126,8 @@ fn artifact_tag_methods(_: &mut MethodsBuilder) {
         })
     }
 
+    /// Like `tag_artifacts`, but only input artifacts carry the tag: outputs in the value are
+    /// left untagged.
     fn tag_inputs<'v>(
         this: &ArtifactTag,
         inner: Value<'v>,
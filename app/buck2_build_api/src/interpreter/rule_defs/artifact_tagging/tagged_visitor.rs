@@ -50,4 +50,8 @@ impl<'a, 'b> CommandLineArtifactVisitor for TaggedVisitor<'a, 'b> {
         };
         self.inner.visit_output(artifact, tag)
     }
+
+    fn expand_transitive_sets(&self) -> bool {
+        self.inner.expand_transitive_sets()
+    }
 }
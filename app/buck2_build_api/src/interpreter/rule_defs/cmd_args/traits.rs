@@ -47,6 +47,12 @@ pub trait CommandLineArtifactVisitor {
     }
 
     fn pop_frame(&mut self) {}
+
+    /// Whether transitive set projections should be visited as the artifacts they contain rather
+    /// than as a single input. This is expensive and only used to count the inputs of an action.
+    fn expand_transitive_sets(&self) -> bool {
+        false
+    }
 }
 
 /// A CommandLineArtifactVisitor that gathers inputs and outputs.
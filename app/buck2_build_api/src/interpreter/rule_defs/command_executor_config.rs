@@ -81,12 +81,17 @@ pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
     /// * `allow_hybrid_fallbacks_on_failure`: Whether to allow fallbacks when the result is failure (i.e. the command failed on the primary, but the infra worked)
     /// * `use_windows_path_separators`: Whether to use Windows path separators in command line arguments
     /// * `use_persistent workers`: Whether to use persistent workers for local execution if they are available
+    /// * `use_remote_persistent_workers`: Whether to ask RE to run actions on persistent workers, for workers which
+    /// set `remote = True`. This requires an RE backend which supports them
     /// * `allow_cache_uploads`: Whether to upload local actions to the RE cache
     /// * `max_cache_upload_mebibytes`: Maximum size to upload in cache uploads
     /// * `experimental_low_pass_filter`: Whether to use the experimental low pass filter
     /// * `remote_output_paths`: How to express output paths to RE
     /// * `remote_execution_resource_units`: The resources (eg. GPUs) to use for remote execution
     /// * `remote_execution_dependencies`: Dependencies for remote execution for this platform
+    /// * `remote_execution_properties_by_category`: Properties for remote execution of actions of the
+    /// given categories, merged over `remote_execution_properties`. Use this to send e.g. `cuda_compile`
+    /// actions to a pool of GPU workers without a separate execution platform
     #[starlark(as_type = StarlarkCommandExecutorConfig)]
     fn CommandExecutorConfig<'v>(
         #[starlark(require = named)] local_enabled: bool,
@@ -105,6 +110,7 @@ pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
         #[starlark(default = false, require = named)] allow_hybrid_fallbacks_on_failure: bool,
         #[starlark(default = false, require = named)] use_windows_path_separators: bool,
         #[starlark(default = false, require = named)] use_persistent_workers: bool,
+        #[starlark(default = false, require = named)] use_remote_persistent_workers: bool,
         #[starlark(default = false, require = named)] allow_cache_uploads: bool,
         #[starlark(default = NoneOr::None, require = named)] max_cache_upload_mebibytes: NoneOr<
             i32,
@@ -115,6 +121,8 @@ pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
         remote_execution_resource_units: NoneOr<i64>,
         #[starlark(default=UnpackList::default(), require = named)]
         remote_execution_dependencies: UnpackList<SmallMap<&'v str, &'v str>>,
+        #[starlark(default = SmallMap::new(), require = named)]
+        remote_execution_properties_by_category: SmallMap<String, SmallMap<String, String>>,
     ) -> anyhow::Result<StarlarkCommandExecutorConfig> {
         let command_executor_config = {
             let remote_execution_max_input_files_mebibytes =
@@ -148,6 +156,20 @@ pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
                 })
             };
 
+            let re_properties_by_category = Arc::new(
+                remote_execution_properties_by_category
+                    .into_iter()
+                    .map(|(category, properties)| {
+                        (
+                            category,
+                            RePlatformFields {
+                                properties: Arc::new(properties.into_iter().collect()),
+                            },
+                        )
+                    })
+                    .collect(),
+            );
+
             let re_dependencies = remote_execution_dependencies
                 .into_iter()
                 .map(RemoteExecutorDependency::parse)
@@ -264,6 +286,7 @@ pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
                         remote_cache_enabled,
                         remote_dep_file_cache_enabled,
                         dependencies: re_dependencies,
+                        re_properties_by_category,
                     }
                 }
                 (Some(local), None, true) => {
@@ -279,6 +302,7 @@ pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
                         remote_cache_enabled: true,
                         remote_dep_file_cache_enabled,
                         dependencies: re_dependencies,
+                        re_properties_by_category,
                     }
                 }
                 // If remote cache is disabled, also disable the remote dep file cache as well
@@ -306,6 +330,7 @@ pub fn register_command_executor_config(builder: &mut GlobalsBuilder) {
                         PathSeparatorKind::Unix
                     },
                     output_paths_behavior,
+                    use_remote_persistent_workers,
                 },
             }
         };
@@ -8,10 +8,12 @@
  */
 
 use std::cell::RefCell;
+use std::cell::Cell;
 use std::cell::RefMut;
 use std::fmt;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::sync::Arc;
 
 use allocative::Allocative;
 use anyhow::Context;
@@ -30,8 +32,10 @@ use starlark::environment::MethodsBuilder;
 use starlark::environment::MethodsStatic;
 use starlark::eval::Evaluator;
 use starlark::typing::Ty;
+use starlark::values::dict::AllocDict;
 use starlark::values::starlark_value;
 use starlark::values::starlark_value_as_type::StarlarkValueAsType;
+use starlark::values::structs::AllocStruct;
 use starlark::values::structs::StructRef;
 use starlark::values::type_repr::StarlarkTypeRepr;
 use starlark::values::AllocValue;
@@ -49,6 +53,7 @@ use starlark::values::ValueTypedComplex;
 use crate::analysis::registry::AnalysisRegistry;
 use crate::deferred::calculation::GET_PROMISED_ARTIFACT;
 use crate::interpreter::rule_defs::plugins::AnalysisPlugins;
+use crate::invocation_info::InvocationInfo;
 
 /// Functions to allow users to interact with the Actions registry.
 ///
@@ -175,6 +180,13 @@ pub struct AnalysisContext<'v> {
     /// Only `None` when running a `dynamic_output` action from Bxl.
     label: Option<ValueTyped<'v, StarlarkConfiguredProvidersLabel>>,
     plugins: Option<ValueTypedComplex<'v, AnalysisPlugins<'v>>>,
+    /// Only available when analysing a target.
+    #[trace(unsafe_ignore)]
+    build_info: Option<Arc<InvocationInfo>>,
+    /// Whether `build_info` was read, so the analysis must depend on it.
+    #[trace(unsafe_ignore)]
+    #[allocative(skip)]
+    build_info_read: Cell<bool>,
 }
 
 impl<'v> Display for AnalysisContext<'v> {
@@ -199,6 +211,7 @@ impl<'v> AnalysisContext<'v> {
         plugins: Option<ValueTypedComplex<'v, AnalysisPlugins<'v>>>,
         registry: AnalysisRegistry<'v>,
         digest_config: DigestConfig,
+        build_info: Option<Arc<InvocationInfo>>,
     ) -> Self {
         Self {
             attrs,
@@ -210,6 +223,8 @@ impl<'v> AnalysisContext<'v> {
             }),
             label,
             plugins,
+            build_info,
+            build_info_read: Cell::new(false),
         }
     }
 
@@ -220,6 +235,7 @@ impl<'v> AnalysisContext<'v> {
         plugins: Option<ValueTypedComplex<'v, AnalysisPlugins<'v>>>,
         registry: AnalysisRegistry<'v>,
         digest_config: DigestConfig,
+        build_info: Option<Arc<InvocationInfo>>,
     ) -> ValueTyped<'v, AnalysisContext<'v>> {
         let label = label.map(|label| {
             heap.alloc_typed(StarlarkConfiguredProvidersLabel::new(
@@ -227,10 +243,33 @@ impl<'v> AnalysisContext<'v> {
             ))
         });
 
-        let analysis_context = Self::new(heap, attrs, label, plugins, registry, digest_config);
+        let analysis_context = Self::new(
+            heap,
+            attrs,
+            label,
+            plugins,
+            registry,
+            digest_config,
+            build_info,
+        );
         heap.alloc_typed(analysis_context)
     }
 
+    /// Whether the rule read `ctx.build_info`. The caller must then make the analysis depend on
+    /// the invocation info with `HasInvocationInfo::get_invocation_info`.
+    pub fn build_info_read(&self) -> bool {
+        self.build_info_read.get()
+    }
+
+    /// The attributes of the target, `None` when running a `dynamic_output` action from BXL.
+    pub(crate) fn attrs(&self) -> Option<ValueOfUnchecked<'v, StructRef<'v>>> {
+        self.attrs
+    }
+
+    pub(crate) fn label(&self) -> Option<ValueTyped<'v, StarlarkConfiguredProvidersLabel>> {
+        self.label
+    }
+
     pub fn assert_no_promises(&self) -> anyhow::Result<()> {
         self.actions.state().assert_no_promises()
     }
@@ -324,6 +363,38 @@ fn analysis_context_methods(builder: &mut MethodsBuilder) {
             .plugins
             .context("`plugins` is not available for `dynamic_output` or BXL")
     }
+
+    /// Information about the command which is running, for non-hermetic uses like stamping. A
+    /// struct with the fields:
+    ///
+    /// * `uuid`: the id of the command, as `str`. Empty unless stamping is enabled with the
+    ///   buckconfig `buck2.stamp`.
+    /// * `ci`: whether the client passed `--client-metadata ci=true`.
+    /// * `client_metadata`: all the `--client-metadata` values, as `dict[str, str]`.
+    ///
+    /// With stamping, the `uuid` changes with every command, so reading `build_info` makes the
+    /// analysis of the target run again on every command. Without it, the analysis only runs
+    /// again when the client metadata changes. Nothing from here is part of an action key unless
+    /// the rule passes it to the action.
+    #[starlark(attribute)]
+    fn build_info<'v>(this: RefAnalysisContext, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        let info = this
+            .0
+            .build_info
+            .as_ref()
+            .context("`build_info` is only available when analysing a target")?;
+        this.0.build_info_read.set(true);
+        let client_metadata = heap.alloc(AllocDict(
+            info.client_metadata
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str())),
+        ));
+        Ok(heap.alloc(AllocStruct([
+            ("uuid", heap.alloc(info.uuid.as_str())),
+            ("ci", Value::new_bool(info.is_ci())),
+            ("client_metadata", client_metadata),
+        ])))
+    }
 }
 
 #[starlark_module]
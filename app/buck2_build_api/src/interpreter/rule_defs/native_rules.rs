@@ -0,0 +1,161 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Analysis of the configuration rules implemented by buck2 itself (see `NativeRuleType`). They
+//! return the same providers as the prelude's implementations of these rules.
+
+use std::collections::BTreeMap;
+
+use buck2_common::legacy_configs::configs::parse_config_section_and_key;
+use buck2_core::configuration::config_setting::ConfigSettingData;
+use buck2_core::configuration::constraints::ConstraintKey;
+use buck2_core::configuration::constraints::ConstraintValue;
+use buck2_error::internal_error;
+use buck2_error::BuckErrorContext;
+use buck2_interpreter::error::BuckStarlarkError;
+use buck2_interpreter::types::target_label::StarlarkTargetLabel;
+use buck2_node::rule_type::NativeRuleType;
+use dupe::Dupe;
+use starlark::values::dict::DictRef;
+use starlark::values::list::AllocList;
+use starlark::values::list::ListRef;
+use starlark::values::Heap;
+use starlark::values::UnpackValue;
+use starlark::values::Value;
+use starlark::values::ValueOf;
+
+use crate::interpreter::rule_defs::context::AnalysisContext;
+use crate::interpreter::rule_defs::provider::builtin::configuration_info::ConfigurationInfo;
+use crate::interpreter::rule_defs::provider::builtin::constraint_setting_info::ConstraintSettingInfo;
+use crate::interpreter::rule_defs::provider::builtin::constraint_setting_info::FrozenConstraintSettingInfo;
+use crate::interpreter::rule_defs::provider::builtin::constraint_value_info::ConstraintValueInfo;
+use crate::interpreter::rule_defs::provider::builtin::constraint_value_info::FrozenConstraintValueInfo;
+use crate::interpreter::rule_defs::provider::builtin::default_info::DefaultInfo;
+use crate::interpreter::rule_defs::provider::dependency::Dependency;
+use crate::interpreter::rule_defs::provider::FrozenBuiltinProviderLike;
+
+#[derive(Debug, buck2_error::Error)]
+enum NativeRuleError {
+    #[error("Attribute `{0}` must be a target providing `{1}`, got `{2}`")]
+    MissingProvider(&'static str, String, String),
+}
+
+/// Returns the list of providers of a native rule target.
+pub fn native_rule_providers<'v>(
+    rule_type: NativeRuleType,
+    ctx: &AnalysisContext<'v>,
+    heap: &'v Heap,
+) -> anyhow::Result<Value<'v>> {
+    let (Some(label), Some(attrs)) = (ctx.label(), ctx.attrs()) else {
+        return Err(internal_error!("Native rules are only analyzed as targets"));
+    };
+    let label = label.label().target().unconfigured().dupe();
+    let attr = |name: &str| -> anyhow::Result<Value<'v>> {
+        attrs
+            .get()
+            .get_attr(name, heap)
+            .map_err(BuckStarlarkError::new)?
+            .with_internal_error(|| format!("Missing attribute `{}` of `{}`", name, rule_type))
+    };
+
+    let mut providers = vec![heap.alloc(DefaultInfo::empty(heap))];
+    match rule_type {
+        NativeRuleType::ConstraintSetting => {
+            let label = heap.alloc_value_of(StarlarkTargetLabel::new(label));
+            providers.push(heap.alloc(ConstraintSettingInfo::new(label)));
+        }
+        NativeRuleType::ConstraintValue => {
+            let setting = dep_provider::<FrozenConstraintSettingInfo>(
+                "constraint_setting",
+                attr("constraint_setting")?,
+            )?;
+            let setting = ValueOf::<&ConstraintSettingInfo>::unpack_value(setting)
+                .internal_error("Wrong type of `ConstraintSettingInfo`")?;
+            let constraints = BTreeMap::from([(
+                ConstraintKey(setting.typed.label().label().dupe()),
+                ConstraintValue(label.dupe()),
+            )]);
+            let label = heap.alloc_value_of(StarlarkTargetLabel::new(label));
+            providers.push(heap.alloc(ConstraintValueInfo::new(setting, label)));
+            // Like `config_setting`, so that constraint values can be used as `select` keys.
+            providers.push(heap.alloc(ConfigurationInfo::from_config_setting_data(
+                &ConfigSettingData {
+                    constraints,
+                    buckconfigs: BTreeMap::new(),
+                },
+                heap,
+            )));
+        }
+        NativeRuleType::ConfigSetting => {
+            let mut constraints = BTreeMap::new();
+            let constraint_values = attr("constraint_values")?;
+            for dep in ListRef::from_value(constraint_values)
+                .internal_error("`constraint_values` is not a list")?
+                .iter()
+            {
+                let value = dep_provider::<FrozenConstraintValueInfo>("constraint_values", dep)?;
+                let value = ConstraintValueInfo::from_value(value)
+                    .internal_error("Wrong type of `ConstraintValueInfo`")?;
+                // Later values of the same setting win, as in the prelude.
+                constraints.insert(
+                    ConstraintKey(value.setting().typed.label().label().dupe()),
+                    ConstraintValue(value.label().label().dupe()),
+                );
+            }
+
+            let mut buckconfigs = BTreeMap::new();
+            let values = attr("values")?;
+            for (k, v) in DictRef::from_value(values)
+                .internal_error("`values` is not a dict")?
+                .iter()
+            {
+                let k = k
+                    .unpack_str()
+                    .internal_error("`values` key is not a string")?;
+                let v = v
+                    .unpack_str()
+                    .internal_error("`values` value is not a string")?;
+                parse_config_section_and_key(k, None)?;
+                buckconfigs.insert(k.to_owned(), v.to_owned());
+            }
+
+            providers.push(heap.alloc(ConfigurationInfo::from_config_setting_data(
+                &ConfigSettingData {
+                    constraints,
+                    buckconfigs,
+                },
+                heap,
+            )));
+        }
+    }
+    Ok(heap.alloc(AllocList(providers)))
+}
+
+/// The provider `T` of the dependency in attribute `attr`.
+fn dep_provider<'v, T: FrozenBuiltinProviderLike>(
+    attr: &'static str,
+    dep: Value<'v>,
+) -> anyhow::Result<Value<'v>> {
+    let provider = match Dependency::from_value(dep) {
+        Some(dep) => dep
+            .provider_collection()?
+            .providers
+            .get(T::builtin_provider_id())
+            .copied(),
+        None => None,
+    };
+    provider.ok_or_else(|| {
+        NativeRuleError::MissingProvider(
+            attr,
+            T::builtin_provider_id().name.clone(),
+            dep.to_string(),
+        )
+        .into()
+    })
+}
@@ -0,0 +1,297 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fmt::Write;
+
+use starlark::environment::GlobalsBuilder;
+use starlark::eval::Evaluator;
+use starlark::starlark_module;
+use starlark::values::dict::DictRef;
+use starlark::values::list::ListRef;
+use starlark::values::none::NoneType;
+use starlark::values::tuple::TupleRef;
+use starlark::values::tuple::UnpackTuple;
+use starlark::values::Heap;
+use starlark::values::ProvidesStaticType;
+use starlark::values::UnpackValue;
+use starlark::values::Value;
+
+use crate::interpreter::rule_defs::artifact::starlark_artifact_like::ValueAsArtifactLike;
+
+#[derive(Debug, buck2_error::Error)]
+enum PprintError {
+    #[error("`{0}` must be non-negative, got {1}")]
+    Negative(&'static str, i32),
+}
+
+/// Set as the evaluator `extra` while analyzing a target, so that `pprint` output can be
+/// attributed to it. `print` output is left as is.
+#[derive(ProvidesStaticType)]
+pub struct PprintOwner(pub String);
+
+impl PprintOwner {
+    fn from_context<'a>(eval: &Evaluator<'_, 'a, '_>) -> Option<&'a PprintOwner> {
+        eval.extra?.downcast_ref::<PprintOwner>()
+    }
+
+    /// Prefixes `text` with the owner, so that output from evaluations running in parallel
+    /// can be told apart.
+    fn attribute(owner: Option<&PprintOwner>, text: String) -> String {
+        match owner {
+            Some(owner) => format!("[{}] {}", owner.0, text),
+            None => text,
+        }
+    }
+}
+
+struct PprintLimits {
+    max_depth: Option<usize>,
+    max_len: Option<usize>,
+}
+
+fn unpack_limit(name: &'static str, limit: Option<i32>) -> anyhow::Result<Option<usize>> {
+    match limit {
+        Some(x) if x < 0 => Err(PprintError::Negative(name, x).into()),
+        Some(x) => Ok(Some(x as usize)),
+        None => Ok(None),
+    }
+}
+
+/// Writes `items` one per line between `open` and `close`, eliding the items past
+/// `max_len` and replacing the whole container with `...` past `max_depth`.
+fn pprint_items<'v>(
+    out: &mut String,
+    open: &str,
+    close: &str,
+    items: impl ExactSizeIterator<Item = (Option<Value<'v>>, Value<'v>)>,
+    depth: usize,
+    limits: &PprintLimits,
+    heap: &'v Heap,
+) -> anyhow::Result<()> {
+    let len = items.len();
+    if len == 0 {
+        write!(out, "{}{}", open, close)?;
+        return Ok(());
+    }
+    if limits.max_depth.map_or(false, |max| depth >= max) {
+        write!(out, "{}...{}", open, close)?;
+        return Ok(());
+    }
+    let indent = "  ".repeat(depth + 1);
+    writeln!(out, "{}", open)?;
+    let shown = limits.max_len.map_or(len, |max| max.min(len));
+    for (key, value) in items.take(shown) {
+        out.push_str(&indent);
+        if let Some(key) = key {
+            pprint_value(out, key, depth + 1, limits, heap)?;
+            out.push_str(": ");
+        }
+        pprint_value(out, value, depth + 1, limits, heap)?;
+        out.push_str(",\n");
+    }
+    if shown < len {
+        writeln!(out, "{}... ({} more)", indent, len - shown)?;
+    }
+    write!(out, "{}{}", "  ".repeat(depth), close)?;
+    Ok(())
+}
+
+fn pprint_value<'v>(
+    out: &mut String,
+    value: Value<'v>,
+    depth: usize,
+    limits: &PprintLimits,
+    heap: &'v Heap,
+) -> anyhow::Result<()> {
+    if let Some(artifact) = ValueAsArtifactLike::unpack_value(value) {
+        // The default representation includes the full configuration and action key, which
+        // is mostly noise when debugging rules.
+        match artifact.0.short_path(heap) {
+            Ok(path) => write!(out, "<artifact {}>", path.as_str())?,
+            Err(_) => write!(out, "{}", value)?,
+        }
+    } else if let Some(list) = ListRef::from_value(value) {
+        pprint_items(
+            out,
+            "[",
+            "]",
+            list.iter().map(|v| (None, v)),
+            depth,
+            limits,
+            heap,
+        )?;
+    } else if let Some(tuple) = TupleRef::from_value(value) {
+        pprint_items(
+            out,
+            "(",
+            ")",
+            tuple.iter().map(|v| (None, v)),
+            depth,
+            limits,
+            heap,
+        )?;
+    } else if let Some(dict) = DictRef::from_value(value) {
+        pprint_items(
+            out,
+            "{",
+            "}",
+            dict.iter().map(|(k, v)| (Some(k), v)),
+            depth,
+            limits,
+            heap,
+        )?;
+    } else {
+        write!(out, "{:#}", value)?;
+    }
+    Ok(())
+}
+
+fn pprint_values<'v>(
+    values: &[Value<'v>],
+    limits: &PprintLimits,
+    heap: &'v Heap,
+) -> anyhow::Result<String> {
+    let mut out = String::new();
+    for (i, value) in values.iter().enumerate() {
+        if i != 0 {
+            out.push(' ');
+        }
+        pprint_value(&mut out, *value, 0, limits, heap)?;
+    }
+    Ok(out)
+}
+
+#[starlark_module]
+pub(crate) fn register_pprint(builder: &mut GlobalsBuilder) {
+    /// Pretty-print some values to the output, like `print` but with one list, tuple or dict
+    /// element per line.
+    ///
+    /// Nested lists, tuples and dicts deeper than `max_depth` are printed as `[...]`, and only
+    /// their first `max_len` elements are printed. Artifacts are printed as their path rather
+    /// than their full representation. When printed during analysis, the output is prefixed
+    /// with the target being analyzed.
+    fn pprint<'v>(
+        #[starlark(args)] args: UnpackTuple<Value<'v>>,
+        #[starlark(require = named)] max_depth: Option<i32>,
+        #[starlark(require = named)] max_len: Option<i32>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> anyhow::Result<NoneType> {
+        let limits = PprintLimits {
+            max_depth: unpack_limit("max_depth", max_depth)?,
+            max_len: unpack_limit("max_len", max_len)?,
+        };
+        let out = pprint_values(&args.items, &limits, eval.heap())?;
+        // Like `print`, so that the output goes wherever the evaluation sends it (e.g. BXL
+        // output).
+        eval.print_handler().println(&PprintOwner::attribute(
+            PprintOwner::from_context(eval),
+            out,
+        ))?;
+        Ok(NoneType)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use starlark::assert::Assert;
+    use starlark::values::dict::AllocDict;
+    use starlark::PrintHandler;
+
+    use super::*;
+
+    fn pprint<'v>(
+        heap: &'v Heap,
+        value: Value<'v>,
+        max_depth: Option<usize>,
+        max_len: Option<usize>,
+    ) -> String {
+        pprint_values(&[value], &PprintLimits { max_depth, max_len }, heap).unwrap()
+    }
+
+    #[test]
+    fn test_pprint_nested() {
+        let heap = Heap::new();
+        let value = heap.alloc(AllocDict([
+            ("a", heap.alloc(vec![1, 2])),
+            ("b", heap.alloc((3, "x"))),
+        ]));
+        assert_eq!(
+            "{\n  \"a\": [\n    1,\n    2,\n  ],\n  \"b\": (\n    3,\n    \"x\",\n  ),\n}",
+            pprint(&heap, value, None, None)
+        );
+        assert_eq!(
+            "[]",
+            pprint(&heap, heap.alloc(Vec::<i32>::new()), None, None)
+        );
+    }
+
+    #[test]
+    fn test_pprint_limits() {
+        let heap = Heap::new();
+        let value = heap.alloc(vec![vec![1, 2, 3], vec![4]]);
+        assert_eq!("[...]", pprint(&heap, value, Some(0), None));
+        assert_eq!(
+            "[\n  [...],\n  [...],\n]",
+            pprint(&heap, value, Some(1), None)
+        );
+        assert_eq!(
+            "[\n  [\n    1,\n    ... (2 more)\n  ],\n  ... (1 more)\n]",
+            pprint(&heap, value, None, Some(1))
+        );
+        assert!(unpack_limit("max_len", Some(-1)).is_err());
+    }
+
+    #[test]
+    fn test_pprint_multiple_values() {
+        let heap = Heap::new();
+        let out = pprint_values(
+            &[heap.alloc("a"), heap.alloc(1)],
+            &PprintLimits {
+                max_depth: None,
+                max_len: None,
+            },
+            &heap,
+        )
+        .unwrap();
+        assert_eq!("\"a\" 1", out);
+    }
+
+    #[test]
+    fn test_attribute() {
+        assert_eq!(
+            "[//foo:bar] text",
+            PprintOwner::attribute(
+                Some(&PprintOwner("//foo:bar".to_owned())),
+                "text".to_owned()
+            )
+        );
+        assert_eq!("text", PprintOwner::attribute(None, "text".to_owned()));
+    }
+
+    #[test]
+    fn test_pprint_uses_print_handler() {
+        struct Collect(RefCell<Vec<String>>);
+
+        impl PrintHandler for Collect {
+            fn println(&self, text: &str) -> anyhow::Result<()> {
+                self.0.borrow_mut().push(text.to_owned());
+                Ok(())
+            }
+        }
+
+        let handler = Collect(RefCell::new(Vec::new()));
+        let mut a = Assert::new();
+        a.globals_add(register_pprint);
+        a.set_print_handler(&handler);
+        a.pass("pprint([1], max_len = 0)");
+        assert_eq!(vec!["[\n  ... (1 more)\n]"], handler.0.into_inner());
+    }
+}
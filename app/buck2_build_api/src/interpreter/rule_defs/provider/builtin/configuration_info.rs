@@ -32,6 +32,7 @@ use starlark::values::type_repr::DictType;
 use starlark::values::Freeze;
 use starlark::values::Heap;
 use starlark::values::Trace;
+use starlark::values::Value;
 use starlark::values::ValueLifetimeless;
 use starlark::values::ValueLike;
 use starlark::values::ValueOf;
@@ -98,31 +99,48 @@ impl<'v, V: ValueLike<'v>> ConfigurationInfoGen<V> {
 impl<'v> ConfigurationInfo<'v> {
     /// Create a provider from configuration data.
     pub fn from_configuration_data(conf: &ConfigurationDataData, heap: &'v Heap) -> Self {
-        let mut constraints = SmallMap::new();
-        for (k, v) in &conf.constraints {
-            let constraint_setting_label =
-                heap.alloc_value_of(StarlarkTargetLabel::new(k.0.dupe()));
-            let constraint_value_label = heap.alloc_value_of(StarlarkTargetLabel::new(v.0.dupe()));
-            let constraint_setting =
-                heap.alloc_value_of(ConstraintSettingInfo::new(constraint_setting_label));
-            let constraint_value =
-                ConstraintValueInfo::new(constraint_setting, constraint_value_label);
-            let prev = constraints.insert_hashed(
-                constraint_setting_label
-                    .get_hashed()
-                    .expect("StarlarkTargetLabel is hashable"),
-                heap.alloc_complex(constraint_value),
-            );
-            assert!(prev.is_none());
+        ConfigurationInfoGen {
+            constraints: alloc_constraints(&conf.constraints, heap),
+            values: heap.alloc(AllocDict::EMPTY),
         }
+    }
 
+    /// Create a provider matching the given constraints and buckconfigs, as returned by a
+    /// `config_setting()`.
+    pub fn from_config_setting_data(data: &ConfigSettingData, heap: &'v Heap) -> Self {
         ConfigurationInfoGen {
-            constraints: heap.alloc(Dict::new(constraints)),
-            values: heap.alloc(AllocDict::EMPTY),
+            constraints: alloc_constraints(&data.constraints, heap),
+            values: heap.alloc(AllocDict(
+                data.buckconfigs
+                    .iter()
+                    .map(|(k, v)| (k.as_str(), v.as_str())),
+            )),
         }
     }
 }
 
+fn alloc_constraints<'v>(
+    constraints: &BTreeMap<ConstraintKey, ConstraintValue>,
+    heap: &'v Heap,
+) -> Value<'v> {
+    let mut dict = SmallMap::new();
+    for (k, v) in constraints {
+        let constraint_setting_label = heap.alloc_value_of(StarlarkTargetLabel::new(k.0.dupe()));
+        let constraint_value_label = heap.alloc_value_of(StarlarkTargetLabel::new(v.0.dupe()));
+        let constraint_setting =
+            heap.alloc_value_of(ConstraintSettingInfo::new(constraint_setting_label));
+        let constraint_value = ConstraintValueInfo::new(constraint_setting, constraint_value_label);
+        let prev = dict.insert_hashed(
+            constraint_setting_label
+                .get_hashed()
+                .expect("StarlarkTargetLabel is hashable"),
+            heap.alloc_complex(constraint_value),
+        );
+        assert!(prev.is_none());
+    }
+    heap.alloc(Dict::new(dict))
+}
+
 #[derive(Debug, buck2_error::Error)]
 enum ConfigurationInfoError {
     #[error("key `{0}` in constraints dict does not match constraint value `{1}`")]
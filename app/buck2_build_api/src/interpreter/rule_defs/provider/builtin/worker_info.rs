@@ -43,6 +43,10 @@ pub struct WorkerInfoGen<V: ValueLifetimeless> {
     // Maximum number of concurrent commands to execute on a worker instance without queuing
     #[provider(field_type = NoneOr<usize>)]
     pub concurrency: V,
+    // Whether RE may run commands on persistent workers spawned from `exe`, if the executor enables
+    // it. The RE backend spawns and talks to these itself, so `exe` has to support its protocol
+    #[provider(field_type = bool)]
+    pub remote: V,
 
     pub id: u64,
 }
@@ -58,6 +62,7 @@ fn worker_info_creator(globals: &mut GlobalsBuilder) {
     fn WorkerInfo<'v>(
         #[starlark(default = AllocList::EMPTY)] exe: Value<'v>,
         #[starlark(require = named, default = NoneOr::None)] concurrency: NoneOr<usize>,
+        #[starlark(require = named, default = false)] remote: bool,
         eval: &mut Evaluator<'v, '_, '_>,
     ) -> anyhow::Result<WorkerInfo<'v>> {
         let heap = eval.heap();
@@ -68,6 +73,7 @@ fn worker_info_creator(globals: &mut GlobalsBuilder) {
             exe,
             id,
             concurrency: heap.alloc(concurrency),
+            remote: heap.alloc(remote),
         })
     }
 }
@@ -84,6 +90,10 @@ impl<'v, V: ValueLike<'v>> WorkerInfoGen<V> {
             .expect("validated at construction")
             .into_option()
     }
+
+    pub fn remote(&self) -> bool {
+        bool::unpack_value(self.remote.to_value()).expect("validated at construction")
+    }
 }
 
 fn validate_worker_info<'v, V>(info: &WorkerInfoGen<V>) -> anyhow::Result<()>
@@ -96,6 +106,7 @@ where
             info.exe
         )
     })?;
+    bool::unpack_value(info.remote.to_value()).context("`remote` must be a bool")?;
     if exe.is_empty() {
         return Err(anyhow::anyhow!(
             "Value for `exe` field is an empty command line: `{}`",
@@ -116,7 +116,7 @@ impl<'v> Dependency<'v> {
         }
     }
 
-    fn provider_collection(&self) -> anyhow::Result<&ProviderCollection<'v>> {
+    pub(crate) fn provider_collection(&self) -> anyhow::Result<&ProviderCollection<'v>> {
         ProviderCollection::from_value(self.providers_collection)
             .ok_or_else(|| anyhow::anyhow!("internal error: not a ProviderCollection"))
     }
@@ -141,7 +141,7 @@ where
             .to_value()
             .at(index, heap)
             .map_err(BuckStarlarkError::new)
-            .with_context(|| format!("Error accessing dependencies of `{}`", self.label))
+            .with_context(|| format!("Error accessing providers of dependency `{}`", self.label))
             .map_err(Into::into)
     }
 
@@ -208,7 +208,7 @@ fn dependency_methods(builder: &mut MethodsBuilder) {
     ) -> anyhow::Result<NoneOr<ValueOfUnchecked<'v, AbstractProvider>>> {
         this.provider_collection()?
             .get(index)
-            .with_context(|| format!("Error accessing dependencies of `{}`", this.label))
+            .with_context(|| format!("Error accessing providers of dependency `{}`", this.label))
     }
 }
 
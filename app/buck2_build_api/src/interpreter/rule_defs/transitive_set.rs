@@ -115,6 +115,6 @@ pub(crate) enum TransitiveSetError {
         valid_reductions: Vec<String>,
     },
 
-    #[error("Expected ordering to be one of `preorder`, `postorder`, `topological`, or `bfs`,  but got `{0}`", .ordering)]
+    #[error("Expected ordering to be one of `preorder`, `postorder`, `topological`, or `bfs`, but got `{0}`", .ordering)]
     OrderingUnexpectedValue { ordering: String },
 }
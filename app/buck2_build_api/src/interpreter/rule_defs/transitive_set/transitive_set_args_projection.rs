@@ -240,6 +240,19 @@ impl<'v, V: ValueLike<'v>> CommandLineArgLike for TransitiveSetArgsProjectionGen
         let set = TransitiveSet::from_value(self.transitive_set.to_value())
             .context("Invalid transitive_set")?;
 
+        if visitor.expand_transitive_sets() {
+            for node in set.iter(self.ordering).values() {
+                let projection = node
+                    .projections
+                    .get(self.projection)
+                    .context("Invalid projection id")?;
+
+                TransitiveSetArgsProjection::as_command_line(*projection)?
+                    .visit_artifacts(visitor)?;
+            }
+            return Ok(());
+        }
+
         visitor.visit_input(
             ArtifactGroup::TransitiveSetProjection(TransitiveSetProjectionKey {
                 key: set.key().dupe(),
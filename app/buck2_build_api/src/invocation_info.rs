@@ -0,0 +1,141 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Injects the information about the running command exposed to analysis as `ctx.build_info`
+//! onto dice.
+//!
+//! Like the stamp info, it only changes with every command when stamping is enabled with
+//! `buck2.stamp`: otherwise the uuid is left empty, so that commands with the same client metadata
+//! don't invalidate anything.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use derive_more::Display;
+use dice::DiceComputations;
+use dice::DiceTransactionUpdater;
+use dice::InjectedKey;
+use dice::UserComputationData;
+use dupe::Dupe;
+
+/// Information about the command which is running, exposed to analysis as `ctx.build_info`.
+#[derive(Debug, Default, PartialEq, Eq, Allocative)]
+pub struct InvocationInfo {
+    /// The trace id of the command.
+    pub uuid: String,
+    /// The metadata provided by the client with `--client-metadata`.
+    pub client_metadata: BTreeMap<String, String>,
+}
+
+impl InvocationInfo {
+    /// Whether the client reported running under CI, with `--client-metadata ci=true`.
+    pub fn is_ci(&self) -> bool {
+        self.client_metadata.get("ci").map(|v| v.as_str()) == Some("true")
+    }
+
+    /// The information exposed when stamping is disabled: the same for every command with the
+    /// same client metadata.
+    pub fn unstamped(&self) -> Self {
+        InvocationInfo {
+            uuid: String::new(),
+            client_metadata: self.client_metadata.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Dupe, Display, Debug, Eq, Hash, PartialEq, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct InvocationInfoKey;
+
+impl InjectedKey for InvocationInfoKey {
+    type Value = Arc<InvocationInfo>;
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[async_trait]
+pub trait HasInvocationInfo {
+    /// Makes the computation depend on the invocation info.
+    async fn get_invocation_info(&mut self) -> anyhow::Result<Arc<InvocationInfo>>;
+}
+
+#[async_trait]
+impl HasInvocationInfo for DiceComputations<'_> {
+    async fn get_invocation_info(&mut self) -> anyhow::Result<Arc<InvocationInfo>> {
+        Ok(self.compute(&InvocationInfoKey).await?)
+    }
+}
+
+pub trait SetInvocationInfo {
+    fn set_invocation_info(&mut self, info: Arc<InvocationInfo>) -> anyhow::Result<()>;
+}
+
+impl SetInvocationInfo for DiceTransactionUpdater {
+    fn set_invocation_info(&mut self, info: Arc<InvocationInfo>) -> anyhow::Result<()> {
+        Ok(self.changed_to(vec![(InvocationInfoKey, info)])?)
+    }
+}
+
+#[derive(Clone, Dupe)]
+struct InvocationInfoHolder(Arc<InvocationInfo>);
+
+/// The same invocation info, in the per-transaction data, so that Starlark can read it
+/// synchronously. Whoever reads it must then call `get_invocation_info` to record the dependency.
+pub trait HasPerTransactionInvocationInfo {
+    fn set_per_transaction_invocation_info(&mut self, info: Arc<InvocationInfo>);
+
+    /// `None` when the command did not provide it, e.g. in tests.
+    fn get_per_transaction_invocation_info(&self) -> Option<Arc<InvocationInfo>>;
+}
+
+impl HasPerTransactionInvocationInfo for UserComputationData {
+    fn set_per_transaction_invocation_info(&mut self, info: Arc<InvocationInfo>) {
+        self.data.set(InvocationInfoHolder(info));
+    }
+
+    fn get_per_transaction_invocation_info(&self) -> Option<Arc<InvocationInfo>> {
+        self.data
+            .get::<InvocationInfoHolder>()
+            .ok()
+            .map(|h| h.0.dupe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_ci() {
+        let info = |value: &str| InvocationInfo {
+            uuid: "uuid".to_owned(),
+            client_metadata: BTreeMap::from([("ci".to_owned(), value.to_owned())]),
+        };
+        assert!(info("true").is_ci());
+        assert!(!info("1").is_ci());
+        assert!(!info("false").is_ci());
+        assert!(!InvocationInfo::default().is_ci());
+    }
+
+    #[test]
+    fn test_unstamped() {
+        let info = |uuid: &str| InvocationInfo {
+            uuid: uuid.to_owned(),
+            client_metadata: BTreeMap::from([("ci".to_owned(), "true".to_owned())]),
+        };
+        // Commands with the same client metadata are equal when not stamping.
+        assert_eq!(info("a").unstamped(), info("b").unstamped());
+        assert_eq!(info(""), info("a").unstamped());
+        assert!(info("a").unstamped().is_ci());
+    }
+}
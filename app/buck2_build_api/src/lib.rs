@@ -27,6 +27,7 @@ pub mod attrs;
 pub mod audit_cell;
 pub mod audit_dep_files;
 pub mod audit_output;
+pub mod buck_out_path_parser;
 pub mod build;
 pub mod build_signals;
 pub mod bxl;
@@ -36,9 +37,12 @@ pub mod context;
 pub mod deferred;
 pub mod dynamic;
 pub mod interpreter;
+pub mod invocation_info;
 pub mod keep_going;
+pub mod phase_budgets;
 pub mod query;
 pub mod spawner;
+pub mod stamp;
 pub mod transition;
 
 pub fn init_late_bindings() {
@@ -0,0 +1,127 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Limits on how many analyses and actions run at once.
+//!
+//! Analysis (evaluating Starlark) and action dispatch share the same runtime threads, so analysing
+//! a huge graph can starve the actions whose inputs are ready, or the reverse. Builds are demand
+//! driven, so the two phases already overlap: an action starts as soon as the analyses it depends
+//! on are done. These budgets only bound how much of each phase can run concurrently.
+//!
+//! The budgets are read when the daemon starts and shared by all its commands.
+
+use std::sync::Arc;
+
+use dice::UserComputationData;
+use dupe::Dupe;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+
+/// Set from `[buck2] analysis_concurrency` and `[buck2] action_dispatch_concurrency`. A phase
+/// without a budget is unlimited.
+#[derive(Clone, Dupe, Default)]
+pub struct PhaseBudgets {
+    analysis: Option<Arc<Semaphore>>,
+    action_dispatch: Option<Arc<Semaphore>>,
+}
+
+impl PhaseBudgets {
+    /// A limit of `0` means unlimited.
+    pub fn new(analysis: Option<usize>, action_dispatch: Option<usize>) -> Self {
+        let semaphore = |limit: Option<usize>| {
+            limit
+                .filter(|limit| *limit > 0)
+                .map(|limit| Arc::new(Semaphore::new(limit)))
+        };
+        Self {
+            analysis: semaphore(analysis),
+            action_dispatch: semaphore(action_dispatch),
+        }
+    }
+
+    /// Wait for a slot to evaluate a rule implementation, which must not wait on other analyses
+    /// while holding it.
+    pub async fn acquire_analysis(&self) -> Option<OwnedSemaphorePermit> {
+        acquire(&self.analysis).await
+    }
+
+    /// The slots to run commands locally, which the local executor holds while a command runs but
+    /// not while looking up caches or waiting on RE.
+    pub fn action_dispatch(&self) -> Option<Arc<Semaphore>> {
+        self.action_dispatch.dupe()
+    }
+}
+
+async fn acquire(semaphore: &Option<Arc<Semaphore>>) -> Option<OwnedSemaphorePermit> {
+    match semaphore {
+        Some(semaphore) => Some(
+            semaphore
+                .dupe()
+                .acquire_owned()
+                .await
+                .expect("Phase budget semaphores are never closed"),
+        ),
+        None => None,
+    }
+}
+
+pub trait HasPhaseBudgets {
+    fn set_phase_budgets(&mut self, budgets: PhaseBudgets);
+
+    /// Unlimited if not set.
+    fn get_phase_budgets(&self) -> PhaseBudgets;
+}
+
+impl HasPhaseBudgets for UserComputationData {
+    fn set_phase_budgets(&mut self, budgets: PhaseBudgets) {
+        self.data.set(budgets);
+    }
+
+    fn get_phase_budgets(&self) -> PhaseBudgets {
+        self.data
+            .get::<PhaseBudgets>()
+            .map(|budgets| budgets.dupe())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited() {
+        let budgets = PhaseBudgets::new(None, Some(0));
+        assert!(budgets.analysis.is_none());
+        assert!(budgets.action_dispatch().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_analysis_budget() {
+        let budgets = PhaseBudgets::new(Some(1), None);
+        let permit = budgets.acquire_analysis().await;
+        assert!(permit.is_some());
+        assert!(tokio::time::timeout(
+            std::time::Duration::from_millis(10),
+            budgets.acquire_analysis()
+        )
+        .await
+        .is_err());
+        drop(permit);
+        assert!(budgets.acquire_analysis().await.is_some());
+    }
+
+    #[test]
+    fn test_action_dispatch_is_shared() {
+        let budgets = PhaseBudgets::new(None, Some(2));
+        let semaphore = budgets.dupe().action_dispatch().unwrap();
+        let _permit = semaphore.try_acquire_owned().unwrap();
+        assert_eq!(1, budgets.action_dispatch().unwrap().available_permits());
+    }
+}
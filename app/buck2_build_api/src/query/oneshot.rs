@@ -12,6 +12,7 @@ use buck2_common::global_cfg_options::GlobalCfgOptions;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfile;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use buck2_util::late_binding::LateBinding;
 use dice::DiceComputations;
@@ -34,6 +35,7 @@ pub trait QueryFrontend: Send + Sync + 'static {
         working_dir: &ProjectRelativePath,
         query: &str,
         query_args: &[String],
+        profile: Option<&QueryProfile>,
     ) -> anyhow::Result<QueryEvaluationResult<TargetNode>>;
 
     async fn eval_cquery(
@@ -45,6 +47,7 @@ pub trait QueryFrontend: Send + Sync + 'static {
         query_args: &[String],
         global_cfg_options: GlobalCfgOptions,
         target_universe: Option<&[String]>,
+        profile: Option<&QueryProfile>,
     ) -> anyhow::Result<QueryEvaluationResult<ConfiguredTargetNode>>;
 
     async fn eval_aquery(
@@ -54,6 +57,7 @@ pub trait QueryFrontend: Send + Sync + 'static {
         query: &str,
         query_args: &[String],
         global_cfg_options: GlobalCfgOptions,
+        profile: Option<&QueryProfile>,
     ) -> anyhow::Result<QueryEvaluationResult<ActionQueryNode>>;
 }
 
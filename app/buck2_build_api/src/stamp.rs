@@ -0,0 +1,113 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Injects the volatile information written by `ctx.actions.stamp_file` onto dice.
+//!
+//! When stamping is enabled (`buck2.stamp = true`), it is updated by every command, so actions
+//! which read it are re-executed every time. Actions depending on their outputs are only
+//! re-executed if the contents of the outputs change. Otherwise it is constant, and doesn't
+//! invalidate anything.
+
+use std::sync::Arc;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use derive_more::Display;
+use dice::DiceComputations;
+use dice::DiceTransactionUpdater;
+use dice::InjectedKey;
+use dupe::Dupe;
+
+#[derive(Debug, PartialEq, Eq, Allocative)]
+pub struct StampInfo {
+    /// The revision of the repository, if the file watcher knows it.
+    pub revision: Option<String>,
+    /// When the command started, in seconds since the epoch.
+    pub timestamp: u64,
+}
+
+impl StampInfo {
+    /// The information written when stamping is disabled, which never changes.
+    pub fn unstamped() -> Self {
+        StampInfo {
+            revision: None,
+            timestamp: 0,
+        }
+    }
+
+    /// The names of the values which can be stamped, in the order they are written by default.
+    pub const KEYS: &'static [&'static str] = &["revision", "timestamp"];
+
+    /// Returns `None` if there is no such key.
+    pub fn get(&self, key: &str) -> Option<String> {
+        match key {
+            "revision" => Some(self.revision.clone().unwrap_or_default()),
+            "timestamp" => Some(self.timestamp.to_string()),
+            _ => None,
+        }
+    }
+}
+
+#[async_trait]
+pub trait HasStampInfo {
+    async fn get_stamp_info(&mut self) -> anyhow::Result<Arc<StampInfo>>;
+}
+
+pub trait SetStampInfo {
+    fn set_stamp_info(&mut self, info: StampInfo) -> anyhow::Result<()>;
+}
+
+#[derive(Clone, Dupe, Display, Debug, Eq, Hash, PartialEq, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct StampInfoKey;
+
+impl InjectedKey for StampInfoKey {
+    type Value = Arc<StampInfo>;
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        x == y
+    }
+}
+
+#[async_trait]
+impl HasStampInfo for DiceComputations<'_> {
+    async fn get_stamp_info(&mut self) -> anyhow::Result<Arc<StampInfo>> {
+        Ok(self.compute(&StampInfoKey).await?)
+    }
+}
+
+impl SetStampInfo for DiceTransactionUpdater {
+    fn set_stamp_info(&mut self, info: StampInfo) -> anyhow::Result<()> {
+        Ok(self.changed_to(vec![(StampInfoKey, Arc::new(info))])?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unstamped() {
+        let info = StampInfo::unstamped();
+        assert_eq!(info.get("revision").as_deref(), Some(""));
+        assert_eq!(info.get("timestamp").as_deref(), Some("0"));
+        assert_eq!(info, StampInfo::unstamped());
+    }
+
+    #[test]
+    fn test_get() {
+        let info = StampInfo {
+            revision: None,
+            timestamp: 1700000000,
+        };
+        assert_eq!(info.get("revision").as_deref(), Some(""));
+        assert_eq!(info.get("timestamp").as_deref(), Some("1700000000"));
+        assert_eq!(info.get("user"), None);
+    }
+}
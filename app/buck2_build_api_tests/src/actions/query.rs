@@ -0,0 +1,143 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use buck2_artifact::artifact::artifact_type::testing::BuildArtifactTestingExt;
+use buck2_artifact::artifact::artifact_type::Artifact;
+use buck2_artifact::artifact::build_artifact::BuildArtifact;
+use buck2_artifact::artifact::source_artifact::SourceArtifact;
+use buck2_artifact::deferred::id::DeferredId;
+use buck2_build_api::actions::impls::action_retry_policy::ActionRetryPolicy;
+use buck2_build_api::actions::query::ActionQueryNode;
+use buck2_build_api::actions::RegisteredAction;
+use buck2_build_api::artifact_groups::ArtifactGroup;
+use buck2_core::category::Category;
+use buck2_core::cells::cell_root_path::CellRootPathBuf;
+use buck2_core::cells::name::CellName;
+use buck2_core::cells::CellResolver;
+use buck2_core::configuration::data::ConfigurationData;
+use buck2_core::execution_types::executor_config::CommandExecutorConfig;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::fs::buck_out_path::BuckOutPathResolver;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_core::fs::project::ProjectRootTemp;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_core::package::package_relative_path::PackageRelativePathBuf;
+use buck2_core::package::source_path::SourcePath;
+use buck2_core::package::PackageLabel;
+use buck2_core::target::label::label::TargetLabel;
+use buck2_core::target::name::TargetNameRef;
+use buck2_query::query::environment::QueryTarget;
+use dupe::Dupe;
+use indexmap::indexset;
+
+use crate::actions::testings::SimpleAction;
+
+fn action_node(fs: &ProjectRootTemp) -> ActionQueryNode {
+    let pkg = PackageLabel::testing_new("cell", "pkg");
+    let label = TargetLabel::new(pkg.dupe(), TargetNameRef::unchecked_new("foo"))
+        .configure(ConfigurationData::testing_new());
+    let output = |path: &str, id| {
+        BuildArtifact::testing_new(
+            label.dupe(),
+            ForwardRelativePathBuf::unchecked_new(path.to_owned()),
+            DeferredId::testing_new(id),
+        )
+    };
+    let outputs = indexset![output("foo.o", 0), output("foo.d", 1)];
+    let inputs = indexset![ArtifactGroup::Artifact(Artifact::from(
+        SourceArtifact::new(SourcePath::testing_new(
+            pkg,
+            PackageRelativePathBuf::unchecked_new("foo.c".to_owned()),
+        ))
+    ))];
+
+    let action = RegisteredAction::new(
+        outputs[0].key().dupe(),
+        Box::new(SimpleAction::new(
+            inputs,
+            outputs,
+            vec!["cc".to_owned()],
+            Category::try_from("cxx_compile").unwrap(),
+            Some("foo.c".to_owned()),
+        )),
+        CommandExecutorConfig::testing_local(),
+    );
+    let artifact_fs = ArtifactFs::new(
+        CellResolver::testing_with_name_and_path(
+            CellName::testing_new("cell"),
+            CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("cell_path".into())),
+        ),
+        BuckOutPathResolver::new(ProjectRelativePathBuf::unchecked_new("buck-out/v2".into())),
+        fs.path().dupe(),
+    );
+    ActionQueryNode::new_action(
+        Arc::new(action),
+        Vec::new(),
+        Vec::new(),
+        Arc::new(artifact_fs),
+        Arc::new(ActionRetryPolicy::default()),
+    )
+}
+
+fn attrs(node: &ActionQueryNode) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    node.attrs_for_each(|name, attr| {
+        let value = RefCell::new(String::new());
+        ActionQueryNode::attr_any_matches(attr, &|v| {
+            *value.borrow_mut() = v.to_owned();
+            Ok(true)
+        })?;
+        attrs.insert(name.to_owned(), value.into_inner());
+        anyhow::Ok(())
+    })
+    .unwrap();
+    attrs
+}
+
+#[test]
+fn test_output_paths() -> anyhow::Result<()> {
+    let fs = ProjectRootTemp::new()?;
+    let paths = action_node(&fs).output_paths()?;
+    assert_eq!(2, paths.len());
+    assert!(paths[0].as_str().starts_with("buck-out/v2/gen/cell/"));
+    assert!(
+        paths[0].as_str().ends_with("/pkg/__foo__/foo.o"),
+        "{}",
+        paths[0]
+    );
+    assert!(
+        paths[1].as_str().ends_with("/pkg/__foo__/foo.d"),
+        "{}",
+        paths[1]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_inputs_and_outputs_attrs() -> anyhow::Result<()> {
+    let fs = ProjectRootTemp::new()?;
+    let node = action_node(&fs);
+    let attrs = attrs(&node);
+
+    assert_eq!("cxx_compile", attrs["category"]);
+    assert_eq!("foo.c", attrs["identifier"]);
+    assert_eq!("[cell_path/pkg/foo.c]", attrs["inputs"]);
+
+    let paths = node.output_paths()?;
+    assert_eq!(
+        format!("[{}, {}]", paths[0], paths[1]),
+        attrs["outputs"],
+        "The outputs attr lists the output paths in buck-out"
+    );
+    Ok(())
+}
@@ -7,14 +7,18 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::sync::Arc;
 
 use buck2_build_api::actions::execute::dice_data::set_fallback_executor_config;
 use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
 use buck2_build_api::deferred::types::testing::DeferredAnalysisResultExt;
+use buck2_build_api::interpreter::rule_defs::provider::builtin::configuration_info::FrozenConfigurationInfo;
+use buck2_build_api::interpreter::rule_defs::provider::builtin::constraint_value_info::FrozenConstraintValueInfo;
 use buck2_build_api::interpreter::rule_defs::provider::builtin::default_info::DefaultInfoCallable;
 use buck2_build_api::interpreter::rule_defs::provider::callable::register_provider;
+use buck2_build_api::interpreter::rule_defs::provider::collection::FrozenProviderCollectionValue;
 use buck2_build_api::interpreter::rule_defs::provider::registration::register_builtin_providers;
 use buck2_build_api::keep_going::HasKeepGoing;
 use buck2_build_api::spawner::BuckSpawner;
@@ -30,7 +34,11 @@ use buck2_core::cells::alias::NonEmptyCellAlias;
 use buck2_core::cells::cell_root_path::CellRootPathBuf;
 use buck2_core::cells::name::CellName;
 use buck2_core::cells::CellAliasResolver;
+use buck2_core::cells::CellResolver;
 use buck2_core::cells::CellsAggregator;
+use buck2_core::configuration::config_setting::ConfigSettingData;
+use buck2_core::configuration::constraints::ConstraintKey;
+use buck2_core::configuration::constraints::ConstraintValue;
 use buck2_core::configuration::data::ConfigurationData;
 use buck2_core::execution_types::executor_config::CommandExecutorConfig;
 use buck2_core::fs::project::ProjectRootTemp;
@@ -55,6 +63,7 @@ use buck2_interpreter_for_build::interpreter::interpreter_setup::setup_interpret
 use buck2_interpreter_for_build::interpreter::testing::Tester;
 use buck2_interpreter_for_build::rule::register_rule_function;
 use dice::testing::DiceBuilder;
+use dice::DiceTransaction;
 use dice::UserComputationData;
 use dupe::Dupe;
 use indoc::indoc;
@@ -62,9 +71,7 @@ use itertools::Itertools;
 use maplit::hashmap;
 use starlark_map::ordered_map::OrderedMap;
 
-#[tokio::test]
-async fn test_analysis_calculation() -> anyhow::Result<()> {
-    let bzlfile = ImportPath::testing_new("cell//pkg:foo.bzl");
+fn cells() -> anyhow::Result<(CellResolver, LegacyBuckConfigs)> {
     let resolver = {
         let mut cells = CellsAggregator::new();
         cells.add_cell_entry(
@@ -85,6 +92,53 @@ async fn test_analysis_calculation() -> anyhow::Result<()> {
         CellName::testing_new("cell") =>
         LegacyBuckConfig::empty(),
     ]);
+    Ok((resolver, configs))
+}
+
+/// Commits `dice`, with the mocks of the test, set up for analysis.
+async fn analysis_dice(
+    dice: DiceBuilder,
+    fs: &ProjectRootTemp,
+    resolver: CellResolver,
+    configs: LegacyBuckConfigs,
+) -> anyhow::Result<DiceTransaction> {
+    let mut dice = dice
+        .mock_and_return(ExecutionPlatformsKey, Ok(None))
+        .set_data(|data| {
+            data.set_testing_io_provider(fs);
+            data.set_digest_config(DigestConfig::testing_default());
+        })
+        .build({
+            let mut data = UserComputationData::new();
+            data.set_keep_going(true);
+            data.set_starlark_debugger_handle(None);
+            set_fallback_executor_config(&mut data.data, CommandExecutorConfig::testing_local());
+            data.data.set(EventDispatcher::null());
+            data.spawner = Arc::new(BuckSpawner::current_runtime().unwrap());
+            data
+        })?;
+    setup_interpreter_basic(
+        &mut dice,
+        resolver,
+        BuildInterpreterConfiguror::new(
+            None,
+            InterpreterHostPlatform::Linux,
+            InterpreterHostArchitecture::X86_64,
+            None,
+            false,
+            false,
+            None,
+            Arc::new(ConcurrentTargetLabelInterner::default()),
+        )?,
+        configs,
+    )?;
+    Ok(dice.commit().await)
+}
+
+#[tokio::test]
+async fn test_analysis_calculation() -> anyhow::Result<()> {
+    let bzlfile = ImportPath::testing_new("cell//pkg:foo.bzl");
+    let (resolver, configs) = cells()?;
     let mut interpreter = Tester::with_cells((
         CellAliasResolver::new(CellName::testing_new("cell"), HashMap::new())?,
         resolver.dupe(),
@@ -143,45 +197,21 @@ async fn test_analysis_calculation() -> anyhow::Result<()> {
     )?;
 
     let fs = ProjectRootTemp::new()?;
-    let mut dice = DiceBuilder::new()
-        .mock_and_return(
-            EvalImportKey(OwnedStarlarkModulePath::LoadFile(bzlfile.clone())),
-            Ok(module),
-        )
-        .mock_and_return(
-            InterpreterResultsKey(PackageLabel::testing_parse("cell//pkg")),
-            Ok(Arc::new(eval_res)),
-        )
-        .mock_and_return(ExecutionPlatformsKey, Ok(None))
-        .set_data(|data| {
-            data.set_testing_io_provider(&fs);
-            data.set_digest_config(DigestConfig::testing_default());
-        })
-        .build({
-            let mut data = UserComputationData::new();
-            data.set_keep_going(true);
-            data.set_starlark_debugger_handle(None);
-            set_fallback_executor_config(&mut data.data, CommandExecutorConfig::testing_local());
-            data.data.set(EventDispatcher::null());
-            data.spawner = Arc::new(BuckSpawner::current_runtime().unwrap());
-            data
-        })?;
-    setup_interpreter_basic(
-        &mut dice,
+    let mut dice = analysis_dice(
+        DiceBuilder::new()
+            .mock_and_return(
+                EvalImportKey(OwnedStarlarkModulePath::LoadFile(bzlfile.clone())),
+                Ok(module),
+            )
+            .mock_and_return(
+                InterpreterResultsKey(PackageLabel::testing_parse("cell//pkg")),
+                Ok(Arc::new(eval_res)),
+            ),
+        &fs,
         resolver,
-        BuildInterpreterConfiguror::new(
-            None,
-            InterpreterHostPlatform::Linux,
-            InterpreterHostArchitecture::X86_64,
-            None,
-            false,
-            false,
-            None,
-            Arc::new(ConcurrentTargetLabelInterner::default()),
-        )?,
         configs,
-    )?;
-    let mut dice = dice.commit().await;
+    )
+    .await?;
 
     let analysis = dice
         .get_analysis_result(
@@ -223,3 +253,117 @@ async fn test_analysis_calculation() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+async fn providers(
+    dice: &mut DiceTransaction,
+    target: &TargetLabel,
+) -> anyhow::Result<FrozenProviderCollectionValue> {
+    Ok(dice
+        .get_analysis_result(&target.configure(ConfigurationData::testing_new()))
+        .await?
+        .require_compatible()?
+        .providers()
+        .dupe())
+}
+
+#[tokio::test]
+async fn test_native_rules_analysis() -> anyhow::Result<()> {
+    let (resolver, configs) = cells()?;
+    let interpreter = Tester::with_cells((
+        CellAliasResolver::new(CellName::testing_new("cell"), HashMap::new())?,
+        resolver.dupe(),
+        configs.dupe(),
+    ))?;
+    // No prelude and no `load`: the rules are globals.
+    let eval_res = interpreter.eval_build_file_with_loaded_modules(
+        &BuildFilePath::testing_new("cell//pkg:BUCK"),
+        indoc!(
+            r#"
+                    constraint_setting(name = "os")
+                    constraint_value(name = "linux", constraint_setting = ":os")
+                    constraint_value(name = "macos", constraint_setting = ":os")
+                    config_setting(
+                        name = "linux_release",
+                        constraint_values = [":macos", ":linux"],
+                        values = {"build.mode": "release"},
+                    )
+                    config_setting(name = "not_a_value", constraint_values = [":os"])
+                "#
+        ),
+        LoadedModules::default(),
+        PackageListing::testing_new(&[], "BUCK"),
+    )?;
+
+    let fs = ProjectRootTemp::new()?;
+    let mut dice = analysis_dice(
+        DiceBuilder::new().mock_and_return(
+            InterpreterResultsKey(PackageLabel::testing_parse("cell//pkg")),
+            Ok(Arc::new(eval_res)),
+        ),
+        &fs,
+        resolver,
+        configs,
+    )
+    .await?;
+
+    let label = |name: &str| TargetLabel::testing_parse(&format!("cell//pkg:{}", name));
+
+    let os = providers(&mut dice, &label("os")).await?;
+    assert_eq!(
+        vec!["ConstraintSettingInfo", "DefaultInfo"],
+        os.provider_collection()
+            .provider_names()
+            .into_iter()
+            .sorted()
+            .collect::<Vec<_>>()
+    );
+
+    let linux = providers(&mut dice, &label("linux")).await?;
+    assert!(linux
+        .provider_collection()
+        .builtin_provider::<FrozenConstraintValueInfo>()
+        .is_some());
+    // A constraint value can be used as a `select` key.
+    assert_eq!(
+        ConfigSettingData {
+            constraints: BTreeMap::from([(
+                ConstraintKey(label("os")),
+                ConstraintValue(label("linux"))
+            )]),
+            buckconfigs: BTreeMap::new(),
+        },
+        linux
+            .provider_collection()
+            .builtin_provider::<FrozenConfigurationInfo>()
+            .unwrap()
+            .to_config_setting_data()
+    );
+
+    let linux_release = providers(&mut dice, &label("linux_release")).await?;
+    assert_eq!(
+        ConfigSettingData {
+            // The last value of a setting wins.
+            constraints: BTreeMap::from([(
+                ConstraintKey(label("os")),
+                ConstraintValue(label("linux"))
+            )]),
+            buckconfigs: BTreeMap::from([("build.mode".to_owned(), "release".to_owned())]),
+        },
+        linux_release
+            .provider_collection()
+            .builtin_provider::<FrozenConfigurationInfo>()
+            .unwrap()
+            .to_config_setting_data()
+    );
+
+    let err = providers(&mut dice, &label("not_a_value"))
+        .await
+        .unwrap_err();
+    assert!(
+        format!("{:#}", err).contains("must be a target providing `ConstraintValueInfo`"),
+        "{:#}",
+        err
+    );
+
+    Ok(())
+}
@@ -837,6 +837,37 @@ fn test_arg() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_location_requires_default_outputs() -> anyhow::Result<()> {
+    let env = Module::new();
+    let resolve = |value: &str| {
+        let attr = AttrType::arg(false);
+        let coerced = attr.coerce(
+            AttrIsConfigurable::Yes,
+            &coercion_ctx(),
+            env.heap().alloc(value),
+        )?;
+        let configured = coerced.configure(&attr, &configuration_ctx())?;
+        let resolution_ctx = resolution_ctx(&env);
+        configured
+            .resolve_single(PackageLabel::testing(), &resolution_ctx)
+            .map(|_| ())
+    };
+
+    resolve("$(location //sub/dir:foo)")?;
+    resolve("$(location //sub/dir:foo[multiple])")?;
+
+    let err = resolve("$(location //sub/dir:foo[zero])")
+        .expect_err("Expanding a target without default outputs should fail");
+    let message = format!("{:?}", err);
+    assert!(
+        message.contains("root//sub/dir:foo[zero]") && message.contains("no default outputs"),
+        "unexpected error: {}",
+        message
+    );
+    Ok(())
+}
+
 #[test]
 fn test_bool() -> anyhow::Result<()> {
     let globals = GlobalsBuilder::standard().with(register_select).build();
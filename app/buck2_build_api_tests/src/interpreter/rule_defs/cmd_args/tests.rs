@@ -525,6 +525,30 @@ fn test_inputs_outputs() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_path_modifiers_keep_inputs() -> anyhow::Result<()> {
+    let mut tester = tester()?;
+    tester.run_starlark_bzl_test(indoc!(
+        r#"
+        def test():
+            artifact = source_artifact("foo", "bar/baz/qux.h")
+            # The `relative_to` location only changes how paths are rendered, it is not an input.
+            relative = source_artifact("foo", "bar/foo")
+
+            cli = cmd_args(
+                artifact,
+                absolute_prefix="$ABSOLUTE/",
+                parent=1,
+                relative_to=relative,
+            )
+
+            assert_eq(get_args(cli), ["$ABSOLUTE/../baz"])
+            assert_eq(make_inputs([artifact]), cli.inputs)
+        "#
+    ))?;
+    Ok(())
+}
+
 #[test]
 fn test_ignore_artifacts() -> anyhow::Result<()> {
     let mut tester = tester()?;
@@ -13,6 +13,7 @@ use buck2_core::configuration::data::ConfigurationData;
 use buck2_core::pattern::pattern::ParsedPattern;
 use buck2_core::pattern::pattern_type::ProvidersPatternExtra;
 use buck2_interpreter_for_build::interpreter::build_context::BuildContext;
+use buck2_interpreter_for_build::interpreter::testing::expect_error;
 use buck2_interpreter_for_build::interpreter::testing::Tester;
 use indoc::indoc;
 use starlark::environment::GlobalsBuilder;
@@ -73,3 +74,44 @@ fn dependency_works() -> buck2_error::Result<()> {
     ))?;
     Ok(())
 }
+
+#[test]
+fn dependency_missing_provider() -> buck2_error::Result<()> {
+    let tester = || -> buck2_error::Result<Tester> {
+        let mut tester = Tester::new()?;
+        tester.additional_globals(buck2_build_api::interpreter::rule_defs::register_rule_defs);
+        tester.additional_globals(dependency_creator);
+        Ok(tester)
+    };
+
+    tester()?.run_starlark_bzl_test(indoc!(
+        r#"
+        def test():
+            dep = create_collection("root//foo:bar[baz]", [DefaultInfo()])
+            assert_eq(None, dep.get(RunInfo))
+            assert_false(RunInfo in dep)
+        "#
+    ))?;
+
+    for missing in [
+        indoc!(
+            r#"
+            def test():
+                create_collection("root//foo:bar[baz]", [DefaultInfo()])[RunInfo]
+            "#
+        ),
+        indoc!(
+            r#"
+            def test():
+                create_collection("root//foo:bar[baz]", [DefaultInfo()]).get("RunInfo")
+            "#
+        ),
+    ] {
+        expect_error(
+            tester()?.run_starlark_bzl_test(missing),
+            missing,
+            "Error accessing providers of dependency `root//foo:bar[baz] (<testing>#",
+        );
+    }
+    Ok(())
+}
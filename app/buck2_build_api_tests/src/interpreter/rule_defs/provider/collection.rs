@@ -67,6 +67,37 @@ fn provider_collection_constructs_properly() -> buck2_error::Result<()> {
     Ok(())
 }
 
+#[test]
+fn provider_collection_missing_provider() -> buck2_error::Result<()> {
+    let mut tester = provider_collection_tester()?;
+    tester.run_starlark_bzl_test(indoc!(
+        r#"
+            load("//provider:defs1.bzl", "FooInfo", "BazInfo")
+            load("//provider:defs2.bzl", "foo1")
+            def test():
+                col = create_collection([foo1, DefaultInfo()])
+                assert_true(FooInfo in col)
+                assert_false(BazInfo in col)
+            "#
+    ))?;
+
+    let mut tester = provider_collection_tester()?;
+    let missing = indoc!(
+        r#"
+            load("//provider:defs1.bzl", "BazInfo")
+            load("//provider:defs2.bzl", "foo1")
+            def test():
+                create_collection([foo1, DefaultInfo()])[BazInfo]
+            "#
+    );
+    expect_error(
+        tester.run_starlark_bzl_test(missing),
+        missing,
+        "provider collection does not have a key `BazInfo`, available keys are: ",
+    );
+    Ok(())
+}
+
 #[test]
 fn provider_collection_fails_to_construct_on_bad_data() -> buck2_error::Result<()> {
     let mut tester = provider_collection_tester()?;
@@ -220,6 +220,21 @@ fn test_transitive_sets_validation() -> anyhow::Result<()> {
         "not the output of transitive_set",
     );
 
+    let contents = indoc!(
+        r#"
+        FooSet = transitive_set()
+
+        def test():
+            make_tset(FooSet, value = 1).traverse(ordering = "dfs")
+        "#
+    );
+
+    expect_error(
+        tester.run_starlark_bzl_test(contents),
+        contents,
+        "Expected ordering to be one of `preorder`, `postorder`, `topological`, or `bfs`, but got `dfs`",
+    );
+
     Ok(())
 }
 
@@ -99,7 +99,8 @@ impl<'v> StarlarkAuditCtx<'v> {
 fn audit_methods(builder: &mut MethodsBuilder) {
     /// Returns either:
     ///  - The `action` which created the buck-out path, if exists.
-    ///  - The `unconfigured_target_label` constructed from the buck-out path, if the configuration hashes do not match.
+    ///  - The `unconfigured_target_label` constructed from the buck-out path, if the configuration hash of the buck-out path
+    /// matches neither the target platform nor a configuration known to the daemon.
     ///  - None, if the configuration hash of the buck-out path matches the one passed into this function, or the default target
     /// configuration, but no action could be found that generated the buck-out path.
     ///
@@ -30,6 +30,8 @@ use buck2_build_api::artifact_groups::ArtifactGroup;
 use buck2_build_api::deferred::types::DeferredCtx;
 use buck2_build_api::interpreter::rule_defs::context::AnalysisActions;
 use buck2_cli_proto::build_request::Materializations;
+use buck2_common::action_input_budgets::ActionInputBudgets;
+use buck2_common::action_input_budgets::HasActionInputBudgets;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::dice::data::HasIoProvider;
 use buck2_common::events::HasEvents;
@@ -533,6 +535,7 @@ pub(crate) async fn eval_bxl_for_dynamic_output<'v>(
     };
     let digest_config = dice_ctx.global_data().get_digest_config();
     let dispatcher = dice_ctx.per_transaction_data().get_dispatcher().dupe();
+    let action_input_budgets = dice_ctx.action_input_budgets().await?;
     let eval_ctx = BxlEvalContext {
         data: BxlContextCoreData::new(key, dice_ctx).await?,
         liveness,
@@ -540,6 +543,7 @@ pub(crate) async fn eval_bxl_for_dynamic_output<'v>(
         dynamic_data,
         digest_config,
         deferred_ctx,
+        action_input_budgets,
         print: EventDispatcherPrintHandler(dispatcher.dupe()),
     };
 
@@ -588,6 +592,7 @@ struct BxlEvalContext<'v> {
     dynamic_data: DynamicBxlContextData,
     digest_config: DigestConfig,
     deferred_ctx: &'v mut dyn DeferredCtx,
+    action_input_budgets: Option<Arc<ActionInputBudgets>>,
     print: EventDispatcherPrintHandler,
 }
 
@@ -605,8 +610,12 @@ impl BxlEvalContext<'_> {
             eval.set_soft_error_handler(&Buck2StarlarkSoftErrorHandler);
             eval.extra = Some(&BxlEvalExtraTag);
 
-            let dynamic_lambda_ctx_data =
-                dynamic_lambda_ctx_data(self.dynamic_lambda, self.deferred_ctx, &env)?;
+            let dynamic_lambda_ctx_data = dynamic_lambda_ctx_data(
+                self.dynamic_lambda,
+                self.deferred_ctx,
+                self.action_input_budgets,
+                &env,
+            )?;
 
             let async_ctx = Rc::new(RefCell::new(BxlSafeDiceComputations::new(
                 dice,
@@ -1093,7 +1102,12 @@ fn bxl_context_methods(builder: &mut MethodsBuilder) {
                             )
                             .await?;
 
-                            validate_action_instantiation(this, &execution_resolution)?;
+                            let action_input_budgets = ctx.action_input_budgets().await?;
+                            validate_action_instantiation(
+                                this,
+                                &execution_resolution,
+                                action_input_budgets,
+                            )?;
 
                             (
                                 execution_resolution.exec_deps_configured,
@@ -15,6 +15,7 @@ use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
 use buck2_build_api::analysis::registry::AnalysisRegistry;
 use buck2_build_api::interpreter::rule_defs::context::AnalysisActions;
 use buck2_build_api::interpreter::rule_defs::provider::dependency::Dependency;
+use buck2_common::action_input_budgets::ActionInputBudgets;
 use buck2_configured::configuration::calculation::ConfigurationCalculation;
 use buck2_configured::nodes::calculation::ExecutionPlatformConstraints;
 use buck2_configured::target::TargetConfiguredTargetLabel;
@@ -146,6 +147,7 @@ pub(crate) struct BxlExecutionResolution {
 pub(crate) fn validate_action_instantiation(
     this: &BxlContextNoDice<'_>,
     bxl_execution_resolution: &BxlExecutionResolution,
+    action_input_budgets: Option<Arc<ActionInputBudgets>>,
 ) -> anyhow::Result<()> {
     let mut registry = this.state.state.borrow_mut();
 
@@ -153,7 +155,7 @@ pub(crate) fn validate_action_instantiation(
         return Err(anyhow::anyhow!(BxlActionsError::RegistryAlreadyCreated));
     } else {
         let execution_platform = bxl_execution_resolution.resolved_execution.clone();
-        let analysis_registry = AnalysisRegistry::new_from_owner(
+        let mut analysis_registry = AnalysisRegistry::new_from_owner(
             BaseDeferredKey::BxlLabel(this.current_bxl().dupe().into_base_deferred_key_dyn_impl(
                 execution_platform.clone(),
                 bxl_execution_resolution.exec_deps_configured.clone(),
@@ -161,6 +163,7 @@ pub(crate) fn validate_action_instantiation(
             )),
             execution_platform,
         )?;
+        analysis_registry.set_action_input_budgets(action_input_budgets);
 
         *registry = Some(analysis_registry);
     }
@@ -235,6 +235,7 @@ pub(crate) fn build<'v>(
                                     BuildConfiguredLabelOptions {
                                         skippable: false,
                                         want_configured_graph_size: false,
+                                        materialize_default_outputs_only: false,
                                     },
                                 )
                                 .await
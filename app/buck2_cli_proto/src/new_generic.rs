@@ -17,6 +17,9 @@ pub enum NewGenericRequest {
     DebugEval(DebugEvalRequest),
     Explain(ExplainRequest),
     ExpandExternalCell(ExpandExternalCellRequest),
+    Warm(WarmRequest),
+    ActionInputDiff(ActionInputDiffRequest),
+    VerifyBuckOut(VerifyBuckOutRequest),
 }
 
 #[derive(Serialize, Deserialize)]
@@ -25,6 +28,9 @@ pub enum NewGenericResponse {
     DebugEval(DebugEvalResponse),
     Explain(ExplainResponse),
     ExpandExternalCell(ExpandExternalCellResponse),
+    Warm(WarmResponse),
+    ActionInputDiff(ActionInputDiffResponse),
+    VerifyBuckOut(VerifyBuckOutResponse),
 }
 
 #[derive(Serialize, Deserialize)]
@@ -65,3 +71,74 @@ pub struct ExpandExternalCellRequest {
 pub struct ExpandExternalCellResponse {
     pub path: String,
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct WarmRequest {
+    /// The actions whose outputs to fetch from the remote action cache.
+    pub actions: Vec<WarmAction>,
+    /// Whether to respond once the actions are warmed, rather than warming them in the
+    /// background.
+    pub wait: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WarmAction {
+    /// The action digest, as `hash:size`.
+    pub action_digest: String,
+    /// The RE use case the action ran with, if known.
+    pub use_case: Option<String>,
+}
+
+/// Only counted when the request waits for the actions to be warmed.
+#[derive(Default, Serialize, Deserialize)]
+pub struct WarmResponse {
+    pub stored: u64,
+    pub already_cached: u64,
+    pub not_found: u64,
+    pub failed: u64,
+    pub bytes_downloaded: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ActionInputDiffRequest {
+    /// The action digests to compare, as `hash:size`.
+    pub left: String,
+    pub right: String,
+    /// The RE use case to download the actions with, if not the default.
+    pub use_case: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ActionInputDiffResponse {
+    /// The inputs which differ between the two actions, sorted by path.
+    pub entries: Vec<ActionInputDiffEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ActionInputDiffEntry {
+    pub path: String,
+    /// A description of the input in each action, `None` if it is missing there.
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct VerifyBuckOutRequest {
+    /// Delete the corrupt artifacts, so that they are materialized again when next needed.
+    pub repair: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct VerifyBuckOutResponse {
+    /// The number of materialized artifacts that were checked.
+    pub checked: u64,
+    /// The corrupt artifacts, sorted by path.
+    pub corrupt: Vec<VerifyBuckOutEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct VerifyBuckOutEntry {
+    pub path: String,
+    pub reason: String,
+    pub repaired: bool,
+}
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+pub mod attach;
 pub mod build;
 pub mod bxl;
 pub mod clean;
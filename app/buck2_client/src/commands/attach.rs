@@ -0,0 +1,104 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_cli_proto::command_result;
+use buck2_cli_proto::AttachRequest;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::ui::CommonConsoleOptions;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_client_ctx::common::CommonEventLogOptions;
+use buck2_client_ctx::common::CommonStarlarkOptions;
+use buck2_client_ctx::daemon::client::AttachedCommandResult;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::daemon::client::StdoutPartialResultHandler;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::streaming::StreamingCommand;
+
+/// Follow a command that is running on the daemon, e.g. after its client lost the connection.
+///
+/// The command must have been started with `--reattachable`. Attaching takes the command over
+/// from the client currently following it, if any. The console shows the command's progress from
+/// the moment its previous client went away, and the exit code is that of the command. Output
+/// that its original client would print once it finishes, such as `--show-output`, is not
+/// reproduced.
+#[derive(Debug, clap::Parser)]
+#[clap(name = "attach")]
+pub struct AttachCommand {
+    /// The trace id of the command, which its console shows as `Build ID`.
+    #[clap(value_name = "TRACE_ID")]
+    trace_id: String,
+
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+}
+
+#[async_trait]
+impl StreamingCommand for AttachCommand {
+    const COMMAND_NAME: &'static str = "attach";
+
+    fn existing_only() -> bool {
+        true
+    }
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        _matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        let AttachedCommandResult(result) = buckd
+            .with_flushing()
+            .attach(
+                AttachRequest {
+                    trace_id: self.trace_id,
+                },
+                ctx.stdin()
+                    .console_interaction_stream(&self.common_opts.console_opts),
+                &mut StdoutPartialResultHandler,
+            )
+            .await??;
+        exit_result(&result)
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonEventLogOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn build_config_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+
+    fn starlark_opts(&self) -> &CommonStarlarkOptions {
+        &self.common_opts.starlark_opts
+    }
+}
+
+/// The exit code of the command, for the commands which report failures in their response rather
+/// than as errors, which are handled when receiving the result.
+fn exit_result(result: &command_result::Result) -> ExitResult {
+    let (errors, exit_code) = match result {
+        command_result::Result::BuildResponse(response) => (&response.errors, None),
+        command_result::Result::BxlResponse(response) => (&response.errors, None),
+        command_result::Result::TestResponse(response) => (&response.errors, response.exit_code),
+        _ => return ExitResult::success(),
+    };
+    if !errors.is_empty() {
+        ExitResult::from_errors(errors)
+    } else if let Some(exit_code) = exit_code {
+        ExitResult::status_extended(exit_code)
+    } else {
+        ExitResult::success()
+    }
+}
@@ -57,6 +57,14 @@ pub struct BuildCommand {
     )]
     materializations: Option<FinalArtifactMaterializations>,
 
+    /// Only materialize the default outputs of the targets named explicitly on the command line.
+    ///
+    /// Targets matched by a pattern such as `//foo/...` are still built, but their outputs, as
+    /// well as the other outputs of the named targets, are left in the CAS and materialized
+    /// later if something needs them.
+    #[clap(long, conflicts_with = "materializations")]
+    materialize_requested: bool,
+
     #[allow(unused)]
     #[clap(
         long,
@@ -183,7 +191,7 @@ pub fn print_build_result(
     errors: &[buck2_data::ErrorReport],
 ) -> anyhow::Result<()> {
     for error in errors {
-        console.print_error(&error.message)?;
+        console.print_error_report(error)?;
     }
     Ok(())
 }
@@ -219,7 +227,11 @@ impl StreamingCommand for BuildCommand {
                         return_default_other_outputs: show_default_other_outputs,
                     }),
                     build_opts: Some(self.build_opts.to_proto()),
-                    final_artifact_materializations: self.materializations.to_proto() as i32,
+                    final_artifact_materializations: if self.materialize_requested {
+                        buck2_cli_proto::build_request::Materializations::MaterializeRequested
+                    } else {
+                        self.materializations.to_proto()
+                    } as i32,
                     target_universe: self.target_cfg.target_universe,
                     output_hashes_file: self
                         .output_hashes_file
@@ -68,6 +68,11 @@ the specified duration, without killing the daemon",
     #[clap(long = "tracked-only", requires = "stale")]
     tracked_only: bool,
 
+    /// Also delete the least recently used artifacts until the remaining ones fit in this size
+    /// (e.g. `50GB`). Artifacts used by builds since the daemon started are kept.
+    #[clap(long = "max-size", requires = "stale", value_name = "SIZE")]
+    max_size: Option<bytesize::ByteSize>,
+
     /// Command doesn't need these flags, but they are used in mode files, so we need to keep them.
     #[clap(flatten)]
     _target_cfg: TargetCfgUnusedOptions,
@@ -82,6 +87,7 @@ impl CleanCommand {
             let cmd = CleanStaleCommand {
                 common_opts: self.common_opts,
                 keep_since_arg,
+                max_size: self.max_size.map(|size| size.as_u64()),
                 dry_run: self.dry_run,
                 tracked_only: self.tracked_only,
             };
@@ -35,6 +35,7 @@ use chrono::Utc;
 pub struct CleanStaleCommand {
     pub(crate) common_opts: CommonCommandOptions,
     pub keep_since_arg: KeepSinceArg,
+    pub max_size: Option<u64>,
     pub dry_run: bool,
     pub tracked_only: bool,
 }
@@ -128,6 +129,7 @@ impl StreamingCommand for CleanStaleCommand {
                 CleanStaleRequest {
                     context: Some(context),
                     keep_since_time: keep_since_time.timestamp(),
+                    max_size: self.max_size,
                     dry_run: self.dry_run,
                     tracked_only: self.tracked_only,
                 },
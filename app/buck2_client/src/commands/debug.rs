@@ -22,30 +22,39 @@ use heap_dump::HeapDumpCommand;
 use internal_version::InternalVersionCommand;
 use materialize::MaterializeCommand;
 
+use crate::commands::debug::action_input_diff::ActionInputDiffCommand;
 use crate::commands::debug::allocative::AllocativeCommand;
 use crate::commands::debug::daemon_dir::DaemonDirCommand;
+use crate::commands::debug::daemon_log::DaemonLogCommand;
 use crate::commands::debug::eval::EvalCommand;
 use crate::commands::debug::exe::ExeCommand;
+use crate::commands::debug::heap_diff::HeapDiffCommand;
 use crate::commands::debug::log_perf::LogPerfCommand;
 use crate::commands::debug::paranoid::ParanoidCommand;
 use crate::commands::debug::persist_event_logs::PersistEventLogsCommand;
 use crate::commands::debug::segfault::SegfaultCommand;
 use crate::commands::debug::set_log_filter::SetLogFilterCommand;
+use crate::commands::debug::snapshot_query::SnapshotQueryCommand;
 use crate::commands::debug::trace_io::TraceIoCommand;
 use crate::commands::debug::upload_re_logs::UploadReLogsCommand;
+use crate::commands::debug::verify_buck_out::VerifyBuckOutCommand;
+use crate::commands::debug::warm::WarmCommand;
 use crate::commands::log::debug_replay::DebugReplayCommand;
 use crate::commands::log::debug_what_ran::DebugWhatRanCommand;
 
+mod action_input_diff;
 mod allocative;
 mod allocator_stats;
 mod chrome_trace;
 mod crash;
 mod daemon_dir;
+mod daemon_log;
 mod dice_dump;
 mod eval;
 mod exe;
 mod file_status;
 mod flush_dep_files;
+mod heap_diff;
 mod heap_dump;
 mod internal_version;
 mod log_perf;
@@ -54,8 +63,11 @@ mod paranoid;
 mod persist_event_logs;
 mod segfault;
 mod set_log_filter;
+mod snapshot_query;
 mod trace_io;
 pub(crate) mod upload_re_logs;
+mod verify_buck_out;
+mod warm;
 
 #[derive(Debug, clap::Parser)]
 #[clap(about = "Hidden debug commands useful for testing buck2")]
@@ -90,6 +102,8 @@ pub enum DebugCommand {
     WhatRan(DebugWhatRanCommand),
     /// Prints buck2 daemon directory (`~/.buckd/xxx`).
     DaemonDir(DaemonDirCommand),
+    /// Prints the log of the buck2 daemon.
+    DaemonLog(DaemonLogCommand),
     /// Prints buck2 executable (this executable) path.
     Exe(ExeCommand),
     Allocative(AllocativeCommand),
@@ -103,6 +117,16 @@ pub enum DebugCommand {
     #[clap(subcommand)]
     Paranoid(ParanoidCommand),
     Eval(EvalCommand),
+    /// Queries a snapshot of the target graph written by `buck2 targets --snapshot`.
+    SnapshotQuery(SnapshotQueryCommand),
+    /// Downloads the outputs of the actions of a previous invocation into the local action cache.
+    Warm(WarmCommand),
+    /// Prints the inputs which differ between two actions, to diagnose cache misses.
+    ActionInputDiff(ActionInputDiffCommand),
+    /// Re-hashes materialized artifacts to find the ones that were corrupted or modified.
+    VerifyBuckOut(VerifyBuckOutCommand),
+    /// Compares two heap snapshots to find what is growing in the daemon.
+    HeapDiff(HeapDiffCommand),
 }
 
 impl DebugCommand {
@@ -122,6 +146,7 @@ impl DebugCommand {
             DebugCommand::Materialize(cmd) => cmd.exec(matches, ctx),
             DebugCommand::UploadReLogs(cmd) => cmd.exec(matches, ctx),
             DebugCommand::DaemonDir(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::DaemonLog(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Exe(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Allocative(cmd) => cmd.exec(matches, ctx),
             DebugCommand::SetLogFilter(cmd) => cmd.exec(matches, ctx),
@@ -131,6 +156,11 @@ impl DebugCommand {
             DebugCommand::PersistEventLogs(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Paranoid(cmd) => cmd.exec(matches, ctx),
             DebugCommand::Eval(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::SnapshotQuery(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::Warm(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::ActionInputDiff(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::VerifyBuckOut(cmd) => cmd.exec(matches, ctx),
+            DebugCommand::HeapDiff(cmd) => cmd.exec(matches, ctx),
         }
     }
 
@@ -0,0 +1,103 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_cli_proto::new_generic::ActionInputDiffRequest;
+use buck2_cli_proto::new_generic::NewGenericRequest;
+use buck2_cli_proto::new_generic::NewGenericResponse;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::ui::CommonConsoleOptions;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_client_ctx::common::CommonEventLogOptions;
+use buck2_client_ctx::common::CommonStarlarkOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::streaming::StreamingCommand;
+
+/// Downloads the input trees of two actions from the CAS and prints the inputs which differ.
+///
+/// Use this to find out why an action missed the cache: compare the action digest of the cache
+/// miss with the one of the build which populated the cache (e.g. from `buck2 log what-ran`).
+#[derive(Debug, clap::Parser)]
+pub struct ActionInputDiffCommand {
+    /// The first action digest, as `hash:size`.
+    left: String,
+
+    /// The second action digest, as `hash:size`.
+    right: String,
+
+    /// The RE use case to download the actions with.
+    #[clap(long)]
+    use_case: Option<String>,
+
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+}
+
+#[async_trait]
+impl StreamingCommand for ActionInputDiffCommand {
+    const COMMAND_NAME: &'static str = "action-input-diff";
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        let context = ctx.client_context(matches, &self)?;
+        let resp = buckd
+            .with_flushing()
+            .new_generic(
+                context,
+                NewGenericRequest::ActionInputDiff(ActionInputDiffRequest {
+                    left: self.left,
+                    right: self.right,
+                    use_case: self.use_case,
+                }),
+                ctx.stdin()
+                    .console_interaction_stream(&self.common_opts.console_opts),
+            )
+            .await??;
+        let NewGenericResponse::ActionInputDiff(resp) = resp else {
+            return ExitResult::bail("Unexpected response type from generic command");
+        };
+
+        if resp.entries.is_empty() {
+            buck2_client_ctx::eprintln!("The inputs of the two actions are identical")?;
+        }
+        for entry in resp.entries {
+            match (entry.left, entry.right) {
+                (Some(left), Some(right)) => {
+                    buck2_client_ctx::println!("~ {}: {} -> {}", entry.path, left, right)?
+                }
+                (Some(left), None) => buck2_client_ctx::println!("- {}: {}", entry.path, left)?,
+                (None, Some(right)) => buck2_client_ctx::println!("+ {}: {}", entry.path, right)?,
+                (None, None) => {}
+            }
+        }
+        ExitResult::success()
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonEventLogOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn build_config_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+
+    fn starlark_opts(&self) -> &CommonStarlarkOptions {
+        &self.common_opts.starlark_opts
+    }
+}
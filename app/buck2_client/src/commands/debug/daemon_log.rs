@@ -0,0 +1,137 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fs::File;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::time::Duration;
+
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_path::AbsPath;
+
+/// Print the log (stderr) of the buck2 daemon for the current isolation dir.
+#[derive(Debug, clap::Parser)]
+pub struct DaemonLogCommand {
+    /// Print the rotated log in buck-out instead: the log is rotated when the daemon starts, and
+    /// when it gets larger than `BUCK2_DAEMON_LOG_MAX_BYTES` (100 MiB by default).
+    #[clap(long)]
+    previous: bool,
+
+    /// Only print the last N lines of the log.
+    #[clap(long, short = 'n', value_name = "N")]
+    lines: Option<usize>,
+
+    /// Keep printing the log as the daemon writes it, until interrupted.
+    #[clap(long, short = 'f', conflicts_with = "previous")]
+    follow: bool,
+}
+
+impl DaemonLogCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let paths = ctx.paths?;
+        let path = if self.previous {
+            paths.daemon_log_prev()
+        } else {
+            paths.daemon_dir()?.buckd_stderr()
+        };
+        let Some(log) = fs_util::read_if_exists(&path)? else {
+            buck2_client_ctx::eprintln!("No daemon log at `{}`", path)?;
+            return ExitResult::success();
+        };
+        for line in last_lines(&String::from_utf8_lossy(&log), self.lines) {
+            buck2_client_ctx::println!("{}", line)?;
+        }
+        if self.follow {
+            let mut offset = log.len() as u64;
+            loop {
+                std::thread::sleep(Duration::from_millis(200));
+                let appended = read_appended(&path, &mut offset)?;
+                if !appended.is_empty() {
+                    buck2_client_ctx::print!("{}", String::from_utf8_lossy(&appended))?;
+                    buck2_client_ctx::stdio::flush()?;
+                }
+            }
+        }
+        ExitResult::success()
+    }
+}
+
+/// The last `n` lines of the log, or all of them.
+fn last_lines(log: &str, n: Option<usize>) -> impl Iterator<Item = &str> {
+    let skip = match n {
+        Some(n) => log.lines().count().saturating_sub(n),
+        None => 0,
+    };
+    log.lines().skip(skip)
+}
+
+/// Read what the daemon appended to the log since `offset`, and move `offset` past it. The daemon
+/// truncates the log when rotating it, in which case this starts again from the beginning.
+fn read_appended(path: &AbsPath, offset: &mut u64) -> anyhow::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    if file.metadata()?.len() < *offset {
+        *offset = 0;
+    }
+    file.seek(SeekFrom::Start(*offset))?;
+    let mut appended = Vec::new();
+    file.read_to_end(&mut appended)?;
+    *offset += appended.len() as u64;
+    Ok(appended)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_lines() {
+        let log = "a\nb\nc\n";
+        assert_eq!(vec!["b", "c"], last_lines(log, Some(2)).collect::<Vec<_>>());
+        assert_eq!(
+            vec!["a", "b", "c"],
+            last_lines(log, Some(5)).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["a", "b", "c"],
+            last_lines(log, None).collect::<Vec<_>>()
+        );
+        assert!(last_lines(log, Some(0)).next().is_none());
+    }
+
+    #[test]
+    fn test_read_appended() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let path = AbsPath::new(tempdir.path()).unwrap().join("buckd.stderr");
+        let mut offset = 0;
+
+        fs_util::write(&path, "first\n").unwrap();
+        assert_eq!(
+            b"first\n",
+            read_appended(&path, &mut offset).unwrap().as_slice()
+        );
+        assert!(read_appended(&path, &mut offset).unwrap().is_empty());
+
+        fs_util::write(&path, "first\nsecond\n").unwrap();
+        assert_eq!(
+            b"second\n",
+            read_appended(&path, &mut offset).unwrap().as_slice()
+        );
+
+        // Rotated by the daemon.
+        fs_util::write(&path, "third\n").unwrap();
+        assert_eq!(
+            b"third\n",
+            read_appended(&path, &mut offset).unwrap().as_slice()
+        );
+        assert_eq!(6, offset);
+    }
+}
@@ -0,0 +1,159 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_core::fs::fs_util;
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(input)]
+enum HeapDiffError {
+    #[error(
+        "Found {0} heap snapshots in `{1}`, but two are needed. Set `buck2.heap_snapshots` in the \
+        buckconfig and run `buck2 kill` to take a snapshot after each command"
+    )]
+    NotEnoughSnapshots(usize, String),
+    #[error("Malformed line {0} in heap snapshot `{1}`")]
+    MalformedLine(usize, String),
+}
+
+/// Compares two heap snapshots and prints the allocative keys (types and fields) whose retained
+/// size grew the most, to find out what is leaking memory in the daemon.
+///
+/// Snapshots are taken after each command if `buck2.heap_snapshots` is set to the number of
+/// snapshots to keep. They are written to `buck-out/v2/heap_snapshots`, named after the time they
+/// were taken and the trace id of the command.
+#[derive(Debug, clap::Parser)]
+pub struct HeapDiffCommand {
+    /// The older snapshot. Defaults to the second latest snapshot.
+    #[clap(requires = "after")]
+    before: Option<PathBuf>,
+
+    /// The newer snapshot. Defaults to the latest snapshot.
+    after: Option<PathBuf>,
+
+    /// How many keys to print.
+    #[clap(long, default_value = "30")]
+    limit: usize,
+}
+
+/// The retained size of each key in a snapshot, written as `<size>\t<key>` lines.
+fn parse_snapshot(path: &Path) -> anyhow::Result<BTreeMap<String, u64>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Error reading heap snapshot `{}`", path.display()))?;
+    let mut sizes = BTreeMap::new();
+    for (i, line) in content.lines().enumerate() {
+        let (size, key) = line
+            .split_once('\t')
+            .and_then(|(size, key)| Some((size.parse::<u64>().ok()?, key)))
+            .ok_or_else(|| HeapDiffError::MalformedLine(i + 1, path.display().to_string()))?;
+        sizes.insert(key.to_owned(), size);
+    }
+    Ok(sizes)
+}
+
+/// The keys whose size changed, with their size before and after, the largest growth first.
+fn diff<'a>(
+    before: &'a BTreeMap<String, u64>,
+    after: &'a BTreeMap<String, u64>,
+) -> Vec<(&'a str, u64, u64)> {
+    let mut changes: Vec<_> = before
+        .keys()
+        .chain(after.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|key| {
+            (
+                key.as_str(),
+                before.get(key).copied().unwrap_or_default(),
+                after.get(key).copied().unwrap_or_default(),
+            )
+        })
+        .filter(|(_, before, after)| before != after)
+        .collect();
+    changes.sort_by_key(|(key, before, after)| (*before as i128 - *after as i128, *key));
+    changes
+}
+
+impl HeapDiffCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let (before, after) = match (self.before, self.after) {
+            (Some(before), Some(after)) => (before, after),
+            _ => {
+                let dir = ctx.paths()?.heap_snapshots_dir();
+                let mut snapshots = Vec::new();
+                if let Some(entries) = fs_util::read_dir_if_exists(&dir)? {
+                    for entry in entries {
+                        snapshots.push(entry?.path().into_path_buf());
+                    }
+                }
+                snapshots.sort();
+                match snapshots.as_slice() {
+                    [.., before, after] => (before.clone(), after.clone()),
+                    _ => {
+                        return ExitResult::err(
+                            HeapDiffError::NotEnoughSnapshots(
+                                snapshots.len(),
+                                dir.display().to_string(),
+                            )
+                            .into(),
+                        );
+                    }
+                }
+            }
+        };
+
+        let before_sizes = parse_snapshot(&before)?;
+        let after_sizes = parse_snapshot(&after)?;
+        buck2_client_ctx::eprintln!("Comparing `{}` to `{}`", before.display(), after.display())?;
+        buck2_client_ctx::println!("{:>14} {:>14} {:>14}  key", "delta", "before", "after")?;
+        for (key, before, after) in diff(&before_sizes, &after_sizes)
+            .into_iter()
+            .take(self.limit)
+        {
+            buck2_client_ctx::println!(
+                "{:>+14} {:>14} {:>14}  {}",
+                after as i128 - before as i128,
+                before,
+                after,
+                key
+            )?;
+        }
+        ExitResult::success()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let before = BTreeMap::from([
+            ("Cache".to_owned(), 100),
+            ("Graph".to_owned(), 50),
+            ("Gone".to_owned(), 10),
+        ]);
+        let after = BTreeMap::from([
+            ("Cache".to_owned(), 300),
+            ("Graph".to_owned(), 50),
+            ("New".to_owned(), 20),
+        ]);
+        assert_eq!(
+            vec![("Cache", 100, 300), ("New", 0, 20), ("Gone", 10, 0)],
+            diff(&before, &after)
+        );
+    }
+}
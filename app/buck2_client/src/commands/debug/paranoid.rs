@@ -21,6 +21,13 @@ use prost::Message;
 /// interacts with. In particular, Buck2 will always race local and remote execution (including
 /// cache queries), eagerly download outputs, and not cancel local executions until it successfully
 /// downloads outputs from RE.
+///
+/// In addition, if `buck2.paranoid_double_execution_sample_rate` is set (to a fraction between 0
+/// and 1), that fraction of the actions is executed both locally and remotely on a cache miss,
+/// and a warning with the command line is printed for each action whose outputs differ. This
+/// detects toolchains which are not hermetic. The remote execution bypasses the cache, and the
+/// build waits for both executions, so sampled actions are slower and use local resources even
+/// when they would otherwise run remotely: keep the sample rate low.
 #[derive(Debug, clap::Parser)]
 pub enum ParanoidCommand {
     Enable(EnableParanoidCommand),
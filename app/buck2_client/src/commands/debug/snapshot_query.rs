@@ -0,0 +1,177 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+mod environment;
+
+use std::fs::File;
+use std::io::BufReader;
+
+use anyhow::Context;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::path_arg::PathArg;
+use buck2_query::query::graph::node::LabeledNode;
+use buck2_query::query::syntax::simple::eval::evaluator::QueryEvaluator;
+use buck2_query::query::syntax::simple::eval::values::QueryEvaluationValue;
+use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
+
+use crate::commands::debug::snapshot_query::environment::SnapshotQueryEnvironment;
+use crate::commands::targets::snapshot::Snapshot;
+
+/// Run a query against a snapshot of the target graph written by `buck2 targets --snapshot`,
+/// without a daemon.
+///
+/// The query language is the one of `buck2 uquery`, e.g. `rdeps(//..., //foo:bar, 1)` or
+/// `attrfilter(labels, foo, deps(//foo:bar))`, over the targets in the snapshot. The cell can be
+/// omitted from target patterns, e.g. `//foo:bar`, to match the targets of every cell. Deps on
+/// targets which are not in the snapshot are not traversed.
+#[derive(Debug, clap::Parser)]
+pub struct SnapshotQueryCommand {
+    /// The snapshot written by `buck2 targets --snapshot`.
+    #[clap(long, value_name = "PATH")]
+    snapshot: PathArg,
+
+    /// The query, e.g. `deps(//foo:bar, 1)`.
+    #[clap(value_name = "QUERY")]
+    query: String,
+}
+
+impl SnapshotQueryCommand {
+    pub fn exec(self, _matches: &clap::ArgMatches, ctx: ClientCommandContext<'_>) -> ExitResult {
+        let path = self.snapshot.resolve(&ctx.working_dir);
+        let file = File::open(&path).with_context(|| format!("Opening snapshot `{}`", path))?;
+        let snapshot = Snapshot::read(BufReader::new(file))
+            .with_context(|| format!("Reading snapshot `{}`", path))?;
+        let env = SnapshotQueryEnvironment::new(&snapshot)?;
+
+        let result = ctx.with_runtime(|_ctx| async move { eval_query(&env, &self.query).await })?;
+        for line in result {
+            buck2_client_ctx::println!("{}", line)?;
+        }
+        ExitResult::success()
+    }
+}
+
+/// Evaluates `query`, returning the labels of the resulting targets, or the resulting files.
+async fn eval_query(env: &SnapshotQueryEnvironment, query: &str) -> anyhow::Result<Vec<String>> {
+    let functions = DefaultQueryFunctionsModule::new();
+    let result = QueryEvaluator::new(env, &functions)
+        .eval_query(query)
+        .await?;
+    Ok(match result {
+        QueryEvaluationValue::TargetSet(targets) => {
+            targets.iter().map(|t| t.node_key().to_string()).collect()
+        }
+        QueryEvaluationValue::FileSet(files) => files.iter().map(|f| f.to_string()).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `lib` <- `util` <- `app`, and `test` on `util`.
+    const TARGETS_OUTPUT: &str = r#"{"buck.package": "root//lib", "buck.file": "root//lib/BUCK", "buck.imports": []}
+{"buck.type": "cxx_library", "buck.deps": [], "buck.inputs": ["root//lib/lib.c"], "buck.package": "root//lib", "name": "lib", "labels": []}
+{"buck.type": "cxx_library", "buck.deps": ["root//lib:lib"], "buck.inputs": ["root//lib/util.c"], "buck.package": "root//lib", "name": "util", "labels": ["public"]}
+{"buck.package": "root//app", "buck.file": "root//app/TARGETS", "buck.imports": []}
+{"buck.type": "cxx_binary", "buck.deps": ["root//lib:util"], "buck.inputs": ["root//app/main.c"], "buck.package": "root//app", "name": "app", "labels": ["public"]}
+{"buck.type": "cxx_test", "buck.deps": ["root//lib:util", "third_party//gtest:gtest"], "buck.inputs": ["root//app/test.c"], "buck.package": "root//app", "name": "test", "labels": []}
+"#;
+
+    async fn query(q: &str) -> anyhow::Result<Vec<String>> {
+        // Round trip through the file format, like `buck2 targets --snapshot` and
+        // `buck2 debug snapshot-query` do.
+        let mut bytes = Vec::new();
+        Snapshot::from_json_lines(TARGETS_OUTPUT)?.write(&mut bytes)?;
+        let env = SnapshotQueryEnvironment::new(&Snapshot::read(bytes.as_slice())?)?;
+        let mut result = eval_query(&env, q).await?;
+        result.sort();
+        Ok(result)
+    }
+
+    #[tokio::test]
+    async fn test_patterns() -> anyhow::Result<()> {
+        assert_eq!(vec!["root//app:app"], query("root//app:app").await?);
+        assert_eq!(vec!["root//app:app"], query("//app:app").await?);
+        assert_eq!(
+            vec!["root//lib:lib", "root//lib:util"],
+            query("//lib:").await?
+        );
+        assert_eq!(4, query("//...").await?.len());
+        assert!(query("//app:missing").await.is_err());
+        assert!(query("other//app:app").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_deps_and_rdeps() -> anyhow::Result<()> {
+        assert_eq!(
+            vec!["root//app:app", "root//lib:lib", "root//lib:util"],
+            query("deps(//app:app)").await?
+        );
+        assert_eq!(
+            vec!["root//app:app", "root//lib:util"],
+            query("deps(//app:app, 1)").await?
+        );
+        assert_eq!(
+            vec!["root//app:app", "root//app:test", "root//lib:util"],
+            query("rdeps(//..., //lib:util)").await?
+        );
+        assert_eq!(
+            vec![
+                "root//app:app",
+                "root//app:test",
+                "root//lib:lib",
+                "root//lib:util"
+            ],
+            query("allpaths(//app:, //lib:lib)").await?
+        );
+        // Deps outside of the snapshot are not traversed.
+        assert_eq!(
+            vec!["root//app:test", "root//lib:lib", "root//lib:util"],
+            query("deps(//app:test)").await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_filters() -> anyhow::Result<()> {
+        assert_eq!(
+            vec!["root//app:test"],
+            query("kind(cxx_test, //...)").await?
+        );
+        assert_eq!(
+            vec!["root//app:app", "root//lib:util"],
+            query("attrfilter(labels, public, //...)").await?
+        );
+        assert_eq!(
+            vec!["root//lib:lib", "root//lib:util"],
+            query("attrregexfilter(buck.type, '.*_library', //...)").await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_files() -> anyhow::Result<()> {
+        assert_eq!(
+            vec!["root//lib:util"],
+            query("owner(root//lib/util.c)").await?
+        );
+        assert_eq!(
+            vec!["root//app/TARGETS", "root//lib/BUCK"],
+            query("buildfile(//app:app + //lib:lib)").await?
+        );
+        assert_eq!(
+            vec!["root//app:app", "root//app:test"],
+            query("targets_in_buildfile(root//app/TARGETS)").await?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,433 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A query environment over a target graph snapshot, so that the query functions of
+//! `buck2 uquery` can run on it without a daemon.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_core::build_file_path::BuildFilePath;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::name::CellName;
+use buck2_core::cells::paths::CellRelativePathBuf;
+use buck2_core::configuration::compatibility::MaybeCompatible;
+use buck2_core::fs::paths::file_name::FileNameBuf;
+use buck2_core::package::PackageLabel;
+use buck2_query::query::environment::QueryEnvironment;
+use buck2_query::query::environment::QueryTarget;
+use buck2_query::query::graph::node::LabeledNode;
+use buck2_query::query::graph::node::NodeKey;
+use buck2_query::query::graph::successors::AsyncChildVisitor;
+use buck2_query::query::syntax::simple::eval::error::QueryError;
+use buck2_query::query::syntax::simple::eval::file_set::FileNode;
+use buck2_query::query::syntax::simple::eval::file_set::FileSet;
+use buck2_query::query::syntax::simple::eval::set::TargetSet;
+use buck2_query::query::traversal::async_depth_first_postorder_traversal;
+use buck2_query::query::traversal::async_depth_limited_traversal;
+use buck2_query::query::traversal::AsyncNodeLookup;
+use derive_more::Display;
+use dupe::Dupe;
+use dupe::IterDupedExt;
+use dupe::OptionDupedExt;
+use indexmap::IndexSet;
+
+use crate::commands::targets::snapshot::Snapshot;
+
+#[derive(Debug, buck2_error::Error)]
+enum SnapshotQueryError {
+    #[error(
+        "Invalid target pattern `{0}`, expected `cell//path:name`, `cell//path:` or \
+        `cell//path/...`"
+    )]
+    InvalidPattern(String),
+    #[error("Invalid file `{0}`, expected a cell path like `cell//path/to/file`")]
+    InvalidFile(String),
+    #[error("Target `{0}` is not in the snapshot")]
+    UnknownTarget(String),
+    #[error("Target `{0}` is ambiguous in the snapshot, specify its cell: {1}")]
+    AmbiguousTarget(String, String),
+}
+
+#[derive(Debug, Clone, Dupe, Eq, PartialEq, Hash, Ord, PartialOrd, Display)]
+pub(crate) struct SnapshotLabel(Arc<str>);
+
+impl NodeKey for SnapshotLabel {}
+
+#[derive(Debug)]
+struct SnapshotTargetData {
+    label: SnapshotLabel,
+    name: String,
+    rule_type: String,
+    buildfile_path: BuildFilePath,
+    deps: Vec<SnapshotLabel>,
+    inputs: Vec<CellPath>,
+    /// `buck.type`, `buck.deps`, `buck.package`...
+    special_attrs: Vec<(String, serde_json::Value)>,
+    attrs: Vec<(String, serde_json::Value)>,
+}
+
+/// A target of the snapshot.
+#[derive(Debug, Clone, Dupe)]
+pub(crate) struct SnapshotTarget(Arc<SnapshotTargetData>);
+
+impl LabeledNode for SnapshotTarget {
+    type Key = SnapshotLabel;
+
+    fn node_key(&self) -> &Self::Key {
+        &self.0.label
+    }
+}
+
+impl QueryTarget for SnapshotTarget {
+    type Attr<'a> = serde_json::Value;
+
+    fn inputs_for_each<E, F: FnMut(CellPath) -> Result<(), E>>(
+        &self,
+        mut func: F,
+    ) -> Result<(), E> {
+        for input in &self.0.inputs {
+            func(input.clone())?;
+        }
+        Ok(())
+    }
+
+    fn rule_type(&self) -> Cow<str> {
+        Cow::Borrowed(&self.0.rule_type)
+    }
+
+    fn name(&self) -> Cow<str> {
+        Cow::Borrowed(&self.0.name)
+    }
+
+    fn buildfile_path(&self) -> &BuildFilePath {
+        &self.0.buildfile_path
+    }
+
+    fn deps<'a>(&'a self) -> impl Iterator<Item = &'a Self::Key> + Send + 'a {
+        self.0.deps.iter()
+    }
+
+    // The snapshot only records `buck.deps`, not which kind of dep each one is.
+
+    fn exec_deps<'a>(&'a self) -> impl Iterator<Item = &'a Self::Key> + Send + 'a {
+        std::iter::empty()
+    }
+
+    fn target_deps<'a>(&'a self) -> impl Iterator<Item = &'a Self::Key> + Send + 'a {
+        self.0.deps.iter()
+    }
+
+    fn configuration_deps<'a>(&'a self) -> impl Iterator<Item = &'a Self::Key> + Send + 'a {
+        std::iter::empty()
+    }
+
+    fn toolchain_deps<'a>(&'a self) -> impl Iterator<Item = &'a Self::Key> + Send + 'a {
+        std::iter::empty()
+    }
+
+    fn attr_any_matches(
+        attr: &Self::Attr<'_>,
+        filter: &dyn Fn(&str) -> anyhow::Result<bool>,
+    ) -> anyhow::Result<bool> {
+        match attr {
+            serde_json::Value::String(s) => filter(s),
+            serde_json::Value::Array(values) => {
+                for v in values {
+                    if Self::attr_any_matches(v, filter)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            serde_json::Value::Object(map) => {
+                for (k, v) in map {
+                    if filter(k)? || Self::attr_any_matches(v, filter)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            v => filter(&v.to_string()),
+        }
+    }
+
+    fn special_attrs_for_each<E, F: FnMut(&str, &Self::Attr<'_>) -> Result<(), E>>(
+        &self,
+        mut func: F,
+    ) -> Result<(), E> {
+        for (name, value) in &self.0.special_attrs {
+            func(name, value)?;
+        }
+        Ok(())
+    }
+
+    fn attrs_for_each<E, F: FnMut(&str, &Self::Attr<'_>) -> Result<(), E>>(
+        &self,
+        mut func: F,
+    ) -> Result<(), E> {
+        for (name, value) in &self.0.attrs {
+            func(name, value)?;
+        }
+        Ok(())
+    }
+
+    fn defined_attrs_for_each<E, F: FnMut(&str, &Self::Attr<'_>) -> Result<(), E>>(
+        &self,
+        func: F,
+    ) -> Result<(), E> {
+        // The snapshot does not record which attributes were set explicitly.
+        self.attrs_for_each(func)
+    }
+
+    fn map_attr<R, F: FnMut(Option<&Self::Attr<'_>>) -> R>(&self, key: &str, mut func: F) -> R {
+        func(
+            self.0
+                .special_attrs
+                .iter()
+                .chain(&self.0.attrs)
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+        )
+    }
+}
+
+fn parse_cell_path(path: &str) -> anyhow::Result<CellPath> {
+    let (cell, path) = path
+        .split_once("//")
+        .with_context(|| format!("Invalid cell path `{}`", path))?;
+    Ok(CellPath::new(
+        CellName::unchecked_new(cell)?,
+        CellRelativePathBuf::try_from(path.to_owned())?,
+    ))
+}
+
+fn parse_buildfile_path(path: &str) -> anyhow::Result<BuildFilePath> {
+    let path = parse_cell_path(path)?;
+    let (package, file_name) = path
+        .parent()
+        .zip(path.path().file_name())
+        .with_context(|| format!("Invalid build file `{}`", path))?;
+    Ok(BuildFilePath::new(
+        PackageLabel::from_cell_path(package),
+        FileNameBuf::unchecked_new(file_name.as_str()),
+    ))
+}
+
+pub(crate) struct SnapshotQueryEnvironment {
+    targets: BTreeMap<SnapshotLabel, SnapshotTarget>,
+}
+
+impl SnapshotQueryEnvironment {
+    pub(crate) fn new(snapshot: &Snapshot) -> anyhow::Result<Self> {
+        let label = |index| SnapshotLabel(Arc::from(snapshot.string(index)));
+        let in_snapshot: HashSet<u32> = snapshot.nodes().iter().map(|n| n.label).collect();
+        let mut targets = BTreeMap::new();
+        for node in snapshot.nodes() {
+            // Deps outside of the patterns the snapshot was taken with have no node: they are
+            // still in `buck.deps`, but are not traversed.
+            let deps = node
+                .deps
+                .iter()
+                .filter(|d| in_snapshot.contains(*d))
+                .map(|d| label(*d))
+                .collect::<Vec<_>>();
+            let inputs = node
+                .inputs
+                .iter()
+                .map(|i| parse_cell_path(snapshot.string(*i)))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let mut special_attrs = vec![
+                (
+                    "buck.type".to_owned(),
+                    serde_json::Value::from(snapshot.string(node.rule_type)),
+                ),
+                (
+                    "buck.deps".to_owned(),
+                    node.deps.iter().map(|d| snapshot.string(*d)).collect(),
+                ),
+                (
+                    "buck.inputs".to_owned(),
+                    node.inputs.iter().map(|i| snapshot.string(*i)).collect(),
+                ),
+            ];
+            let mut attrs = Vec::new();
+            for (name, value) in &node.attrs {
+                let name = snapshot.string(*name);
+                let value = serde_json::from_str(value)
+                    .with_context(|| format!("Invalid value of attribute `{}`", name))?;
+                if name.starts_with("buck.") {
+                    special_attrs.push((name.to_owned(), value));
+                } else {
+                    attrs.push((name.to_owned(), value));
+                }
+            }
+            let target = SnapshotTarget(Arc::new(SnapshotTargetData {
+                label: label(node.label),
+                name: snapshot.string(node.name).to_owned(),
+                rule_type: snapshot.string(node.rule_type).to_owned(),
+                buildfile_path: parse_buildfile_path(snapshot.string(node.buildfile))?,
+                deps,
+                inputs,
+                special_attrs,
+                attrs,
+            }));
+            targets.insert(target.0.label.dupe(), target);
+        }
+        Ok(Self { targets })
+    }
+
+    /// The targets matching a target pattern. The cell can be omitted, e.g. `//foo:bar`, to
+    /// match the targets of every cell.
+    fn eval_literal(&self, literal: &str) -> anyhow::Result<Vec<SnapshotTarget>> {
+        let invalid = || SnapshotQueryError::InvalidPattern(literal.to_owned());
+        let (cell, pattern) = literal.split_once("//").ok_or_else(invalid)?;
+        let in_cell = |target: &SnapshotTarget| {
+            cell.is_empty() || target.0.buildfile_path.package().cell_name().as_str() == cell
+        };
+        let package_path = |target: &SnapshotTarget| {
+            target
+                .0
+                .buildfile_path
+                .package()
+                .cell_relative_path()
+                .as_str()
+        };
+
+        if let Some(dir) = pattern.strip_suffix("...") {
+            let dir = dir.strip_suffix('/').unwrap_or(dir);
+            return Ok(self
+                .targets
+                .values()
+                .filter(|t| {
+                    let path = package_path(t);
+                    in_cell(t)
+                        && (dir.is_empty()
+                            || path == dir
+                            || path.strip_prefix(dir).map_or(false, |p| p.starts_with('/')))
+                })
+                .duped()
+                .collect());
+        }
+
+        let (package, name) = pattern.split_once(':').ok_or_else(invalid)?;
+        let targets: Vec<_> = self
+            .targets
+            .values()
+            .filter(|t| {
+                in_cell(t) && package_path(t) == package && (name.is_empty() || t.0.name == name)
+            })
+            .duped()
+            .collect();
+        if !name.is_empty() {
+            match targets.as_slice() {
+                [] => return Err(SnapshotQueryError::UnknownTarget(literal.to_owned()).into()),
+                [_] => {}
+                [a, b, ..] => {
+                    return Err(SnapshotQueryError::AmbiguousTarget(
+                        literal.to_owned(),
+                        format!("{}, {}, ...", a.0.label, b.0.label),
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(targets)
+    }
+}
+
+#[async_trait]
+impl AsyncNodeLookup<SnapshotTarget> for SnapshotQueryEnvironment {
+    async fn get(&self, label: &SnapshotLabel) -> anyhow::Result<SnapshotTarget> {
+        self.targets
+            .get(label)
+            .duped()
+            .ok_or_else(|| SnapshotQueryError::UnknownTarget(label.to_string()).into())
+    }
+}
+
+#[async_trait]
+impl QueryEnvironment for SnapshotQueryEnvironment {
+    type Target = SnapshotTarget;
+
+    async fn get_node(&self, node_ref: &SnapshotLabel) -> anyhow::Result<SnapshotTarget> {
+        <Self as AsyncNodeLookup<SnapshotTarget>>::get(self, node_ref).await
+    }
+
+    async fn get_node_for_default_configured_target(
+        &self,
+        _node_ref: &SnapshotLabel,
+    ) -> anyhow::Result<MaybeCompatible<SnapshotTarget>> {
+        Err(QueryError::FunctionUnimplemented(
+            "get_node_for_default_configured_target() only for CqueryEnvironment",
+        )
+        .into())
+    }
+
+    async fn eval_literals(&self, literals: &[&str]) -> anyhow::Result<TargetSet<SnapshotTarget>> {
+        let mut result = TargetSet::new();
+        for literal in literals {
+            result.extend(self.eval_literal(literal)?);
+        }
+        Ok(result)
+    }
+
+    async fn eval_file_literal(&self, literal: &str) -> anyhow::Result<FileSet> {
+        let path = parse_cell_path(literal)
+            .map_err(|_| SnapshotQueryError::InvalidFile(literal.to_owned()))?;
+        Ok(FileSet::new(IndexSet::from([FileNode(path)])))
+    }
+
+    async fn dfs_postorder(
+        &self,
+        root: &TargetSet<SnapshotTarget>,
+        delegate: impl AsyncChildVisitor<SnapshotTarget>,
+        visit: impl FnMut(SnapshotTarget) -> anyhow::Result<()> + Send,
+    ) -> anyhow::Result<()> {
+        async_depth_first_postorder_traversal(self, root.iter_names(), delegate, visit).await
+    }
+
+    async fn depth_limited_traversal(
+        &self,
+        root: &TargetSet<SnapshotTarget>,
+        delegate: impl AsyncChildVisitor<SnapshotTarget>,
+        visit: impl FnMut(SnapshotTarget) -> anyhow::Result<()> + Send,
+        depth: u32,
+    ) -> anyhow::Result<()> {
+        async_depth_limited_traversal(self, root.iter_names(), delegate, visit, depth).await
+    }
+
+    async fn owner(&self, paths: &FileSet) -> anyhow::Result<TargetSet<SnapshotTarget>> {
+        Ok(self
+            .targets
+            .values()
+            .filter(|t| t.0.inputs.iter().any(|i| paths.iter().any(|p| p == i)))
+            .duped()
+            .collect())
+    }
+
+    async fn targets_in_buildfile(
+        &self,
+        paths: &FileSet,
+    ) -> anyhow::Result<TargetSet<SnapshotTarget>> {
+        Ok(self
+            .targets
+            .values()
+            .filter(|t| {
+                let buildfile = t.0.buildfile_path.path();
+                paths.iter().any(|p| *p == buildfile)
+            })
+            .duped()
+            .collect())
+    }
+}
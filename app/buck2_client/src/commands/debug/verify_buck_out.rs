@@ -0,0 +1,112 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_cli_proto::new_generic::NewGenericRequest;
+use buck2_cli_proto::new_generic::NewGenericResponse;
+use buck2_cli_proto::new_generic::VerifyBuckOutRequest;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::ui::CommonConsoleOptions;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_client_ctx::common::CommonEventLogOptions;
+use buck2_client_ctx::common::CommonStarlarkOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::streaming::StreamingCommand;
+
+/// Re-hashes the artifacts in buck-out and reports those which differ from what was materialized.
+///
+/// Only artifacts the deferred materializer knows about are checked. Each corrupt artifact is
+/// printed with the reason it is considered corrupt. Exits with an error if any corrupt artifact
+/// was left in place.
+#[derive(Debug, clap::Parser)]
+pub struct VerifyBuckOutCommand {
+    /// Delete and forget the corrupt artifacts, so that they are materialized again (e.g.
+    /// downloaded from the CAS) or rebuilt the next time they are declared. Artifacts used by
+    /// builds since the daemon started are only declared again once it restarts: run `buck2 kill`
+    /// after repairing them.
+    #[clap(long)]
+    repair: bool,
+
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+}
+
+#[async_trait]
+impl StreamingCommand for VerifyBuckOutCommand {
+    const COMMAND_NAME: &'static str = "verify-buck-out";
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        let context = ctx.client_context(matches, &self)?;
+        let resp = buckd
+            .with_flushing()
+            .new_generic(
+                context,
+                NewGenericRequest::VerifyBuckOut(VerifyBuckOutRequest {
+                    repair: self.repair,
+                }),
+                ctx.stdin()
+                    .console_interaction_stream(&self.common_opts.console_opts),
+            )
+            .await??;
+        let NewGenericResponse::VerifyBuckOut(resp) = resp else {
+            return ExitResult::bail("Unexpected response type from generic command");
+        };
+
+        let mut unrepaired = 0;
+        for entry in &resp.corrupt {
+            if entry.repaired {
+                buck2_client_ctx::println!("{}\t{} (repaired)", entry.path, entry.reason)?;
+            } else {
+                unrepaired += 1;
+                buck2_client_ctx::println!("{}\t{}", entry.path, entry.reason)?;
+            }
+        }
+        buck2_client_ctx::eprintln!(
+            "Checked {} artifacts, {} corrupt, {} repaired",
+            resp.checked,
+            resp.corrupt.len(),
+            resp.corrupt.len() - unrepaired
+        )?;
+
+        if unrepaired > 0 {
+            if self.repair {
+                ExitResult::bail(
+                    "Some corrupt artifacts are still used by this daemon: run `buck2 kill` so that the next build materializes them again",
+                )
+            } else {
+                ExitResult::bail("Found corrupt artifacts, use `--repair` to repair them")
+            }
+        } else {
+            ExitResult::success()
+        }
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonEventLogOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn build_config_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+
+    fn starlark_opts(&self) -> &CommonStarlarkOptions {
+        &self.common_opts.starlark_opts
+    }
+}
@@ -0,0 +1,246 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use buck2_cli_proto::new_generic::NewGenericRequest;
+use buck2_cli_proto::new_generic::NewGenericResponse;
+use buck2_cli_proto::new_generic::WarmAction;
+use buck2_cli_proto::new_generic::WarmRequest;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::ui::CommonConsoleOptions;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_client_ctx::common::CommonEventLogOptions;
+use buck2_client_ctx::common::CommonStarlarkOptions;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::streaming::StreamingCommand;
+use buck2_data::command_execution_kind::Command;
+use buck2_event_log::stream_value::StreamValue;
+use tokio_stream::StreamExt;
+
+use crate::commands::log::options::EventLogOptions;
+
+/// Downloads the outputs of the actions of a previous invocation from the remote action cache into
+/// the local action cache, so that building the same actions locally later is mostly cache hits.
+///
+/// This is meant to be run after a rebase, with the event log of a build of the new revision (e.g.
+/// from CI). The daemon downloads the outputs in the background, unless `--wait` is passed.
+/// Requires `buck2.local_action_cache_max_bytes` to be set.
+#[derive(Debug, clap::Parser)]
+pub struct WarmCommand {
+    #[clap(flatten)]
+    event_log: EventLogOptions,
+
+    /// Wait for the outputs to be downloaded, and print how many actions were warmed.
+    #[clap(long)]
+    wait: bool,
+
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+}
+
+/// The actions which succeeded in an event log, once each.
+async fn actions_from_event_log(
+    event_log: &EventLogOptions,
+    ctx: &ClientCommandContext<'_>,
+) -> anyhow::Result<Vec<WarmAction>> {
+    let log_path = event_log.get(ctx).await?;
+    let (invocation, mut events) = log_path.unpack_stream().await?;
+    buck2_client_ctx::eprintln!("Warming from: {}", invocation.display_command_line())?;
+
+    let mut seen = HashSet::new();
+    let mut actions = Vec::new();
+    while let Some(event) = events.try_next().await? {
+        let StreamValue::Event(event) = event else {
+            continue;
+        };
+        if let Some(action) = warm_action(event) {
+            if seen.insert(action.action_digest.clone()) {
+                actions.push(action);
+            }
+        }
+    }
+    Ok(actions)
+}
+
+/// The action to warm for an event, if it is the end of an action which succeeded.
+fn warm_action(event: buck2_data::BuckEvent) -> Option<WarmAction> {
+    let Some(buck2_data::buck_event::Data::SpanEnd(end)) = event.data else {
+        return None;
+    };
+    let Some(buck2_data::span_end_event::Data::ActionExecution(action)) = end.data else {
+        return None;
+    };
+    if action.failed {
+        return None;
+    }
+    // The last command is the one which produced the outputs.
+    let command = action
+        .commands
+        .into_iter()
+        .last()
+        .and_then(|c| c.details)
+        .and_then(|d| d.command_kind)
+        .and_then(|k| k.command);
+    let (action_digest, use_case) = match command? {
+        Command::RemoteCommand(c) => (
+            c.action_digest,
+            c.details.map(|d| d.use_case).filter(|u| !u.is_empty()),
+        ),
+        // Locally executed actions may have been uploaded to the cache.
+        Command::LocalCommand(c) => (c.action_digest, None),
+        Command::OmittedLocalCommand(c) => (c.action_digest, None),
+        Command::WorkerInitCommand(_) | Command::WorkerCommand(_) => return None,
+    };
+    if action_digest.is_empty() {
+        return None;
+    }
+    Some(WarmAction {
+        action_digest,
+        use_case,
+    })
+}
+
+#[async_trait]
+impl StreamingCommand for WarmCommand {
+    const COMMAND_NAME: &'static str = "warm";
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext<'_>,
+    ) -> ExitResult {
+        // Read the log before the daemon starts writing the log of this command, which would
+        // otherwise be the most recent one.
+        let actions = actions_from_event_log(&self.event_log, ctx).await?;
+        let action_count = actions.len();
+
+        let context = ctx.client_context(matches, &self)?;
+        let resp = buckd
+            .with_flushing()
+            .new_generic(
+                context,
+                NewGenericRequest::Warm(WarmRequest {
+                    actions,
+                    wait: self.wait,
+                }),
+                ctx.stdin()
+                    .console_interaction_stream(&self.common_opts.console_opts),
+            )
+            .await??;
+        let NewGenericResponse::Warm(resp) = resp else {
+            return ExitResult::bail("Unexpected response type from generic command");
+        };
+
+        if !self.wait {
+            buck2_client_ctx::eprintln!("Warming {} actions in the background", action_count)?;
+            return ExitResult::success();
+        }
+        buck2_client_ctx::eprintln!(
+            "Warmed {} of {} actions ({} bytes downloaded): {} already cached, {} not in the remote cache, {} failed",
+            resp.stored,
+            action_count,
+            resp.bytes_downloaded,
+            resp.already_cached,
+            resp.not_found,
+            resp.failed,
+        )?;
+        ExitResult::success()
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonEventLogOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn build_config_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+
+    fn starlark_opts(&self) -> &CommonStarlarkOptions {
+        &self.common_opts.starlark_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action_end(failed: bool, command: Option<Command>) -> buck2_data::BuckEvent {
+        buck2_data::BuckEvent {
+            data: Some(buck2_data::buck_event::Data::SpanEnd(
+                buck2_data::SpanEndEvent {
+                    data: Some(buck2_data::span_end_event::Data::ActionExecution(Box::new(
+                        buck2_data::ActionExecutionEnd {
+                            failed,
+                            commands: vec![buck2_data::CommandExecution {
+                                details: Some(buck2_data::CommandExecutionDetails {
+                                    command_kind: Some(buck2_data::CommandExecutionKind {
+                                        command,
+                                    }),
+                                    ..Default::default()
+                                }),
+                                ..Default::default()
+                            }],
+                            ..Default::default()
+                        },
+                    ))),
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        }
+    }
+
+    fn remote(action_digest: &str, use_case: &str) -> Option<Command> {
+        Some(Command::RemoteCommand(buck2_data::RemoteCommand {
+            action_digest: action_digest.to_owned(),
+            details: Some(buck2_data::RemoteCommandDetails {
+                use_case: use_case.to_owned(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }))
+    }
+
+    #[test]
+    fn test_warm_action() {
+        let action = warm_action(action_end(false, remote("abc:10", "buck2-build"))).unwrap();
+        assert_eq!("abc:10", action.action_digest);
+        assert_eq!(Some("buck2-build"), action.use_case.as_deref());
+
+        let action = warm_action(action_end(false, remote("abc:10", ""))).unwrap();
+        assert_eq!(None, action.use_case);
+
+        let local = Some(Command::LocalCommand(buck2_data::LocalCommand {
+            action_digest: "def:20".to_owned(),
+            ..Default::default()
+        }));
+        let action = warm_action(action_end(false, local)).unwrap();
+        assert_eq!("def:20", action.action_digest);
+        assert_eq!(None, action.use_case);
+    }
+
+    #[test]
+    fn test_warm_action_skipped() {
+        assert!(warm_action(action_end(true, remote("abc:10", ""))).is_none());
+        assert!(warm_action(action_end(false, remote("", ""))).is_none());
+        assert!(warm_action(action_end(false, None)).is_none());
+        let worker = Some(Command::WorkerCommand(Default::default()));
+        assert!(warm_action(action_end(false, worker)).is_none());
+        assert!(warm_action(buck2_data::BuckEvent::default()).is_none());
+    }
+}
@@ -20,6 +20,11 @@ use crate::commands::log::what_ran::WhatRanCommandCommon;
 pub struct WhatFailedCommand {
     #[clap(flatten)]
     pub common: WhatRanCommandCommon,
+
+    /// Show also the std_err of the commands that failed, which the console only shows the start
+    /// of when it is long.
+    #[clap(long)]
+    pub show_std_err: bool,
 }
 
 impl WhatFailedCommand {
@@ -28,7 +33,7 @@ impl WhatFailedCommand {
             common: self.common,
             failed: true,
             incomplete: false,
-            show_std_err: false,
+            show_std_err: self.show_std_err,
             omit_empty_std_err: false,
         }
         .exec(matches, ctx)
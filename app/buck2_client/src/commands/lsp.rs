@@ -12,6 +12,7 @@ use buck2_cli_proto::LspRequest;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
 use buck2_client_ctx::common::ui::CommonConsoleOptions;
 use buck2_client_ctx::common::ui::ConsoleType;
+use buck2_client_ctx::common::ui::ErrorFormat;
 use buck2_client_ctx::common::CommonBuildConfigurationOptions;
 use buck2_client_ctx::common::CommonEventLogOptions;
 use buck2_client_ctx::common::CommonStarlarkOptions;
@@ -86,6 +87,7 @@ impl StreamingCommand for LspCommand {
             console_type: ConsoleType::Simple,
             ui: vec![],
             no_interactive_console: true,
+            error_format: ErrorFormat::Text,
         });
         &SIMPLE_CONSOLE
     }
@@ -53,6 +53,10 @@ List all the commands for run actions for building a target
 
 `buck2 aquery 'kind(run, deps("//java/com/example/app:amazing+more"))' --output-attribute=cmd`
 
+Find which actions produce a file, and what they run and read
+
+`buck2 aquery 'outputs("Foo\.class$", deps("//java/com/example/app:amazing"))' --output-attribute=cmd --output-attribute=inputs`
+
 Dynamic outputs (`ctx.actions.dynamic_output`):
 
 Currently, aquery interacts poorly with dynamic outputs. It may
@@ -75,6 +79,11 @@ pub struct AqueryCommand {
     #[clap(flatten)]
     target_cfg: TargetCfgOptions,
 
+    /// Build the inputs of the matching actions and print their digests as the `inputs_digests`
+    /// attribute. Comparing them between two builds shows which input caused a cache miss.
+    #[clap(long)]
+    show_inputs_digests: bool,
+
     #[clap(flatten)]
     common_opts: CommonCommandOptions,
 }
@@ -91,10 +100,13 @@ impl StreamingCommand for AqueryCommand {
     ) -> ExitResult {
         let (query, query_args) = self.query_common.get_query();
         let unstable_output_format = self.query_common.output_format() as i32;
-        let output_attributes = self.query_common.attributes.get()?;
+        let mut output_attributes = self.query_common.attributes.get()?;
+        if self.show_inputs_digests {
+            output_attributes.push("^inputs_digests$".to_owned());
+        }
         let context = ctx.client_context(matches, &self)?;
 
-        let AqueryResponse {} = buckd
+        let AqueryResponse { profile } = buckd
             .with_flushing()
             .aquery(
                 AqueryRequest {
@@ -103,7 +115,10 @@ impl StreamingCommand for AqueryCommand {
                     target_cfg: Some(self.target_cfg.target_cfg()),
                     context: Some(context),
                     output_attributes,
+                    show_inputs_digests: self.show_inputs_digests,
                     unstable_output_format,
+                    dot_edges: self.query_common.dot_edges(),
+                    profile: self.query_common.profile,
                 },
                 ctx.stdin()
                     .console_interaction_stream(&self.common_opts.console_opts),
@@ -111,6 +126,9 @@ impl StreamingCommand for AqueryCommand {
             )
             .await??;
 
+        if let Some(profile) = profile {
+            buck2_client_ctx::eprint!("{}", profile)?;
+        }
         ExitResult::success()
     }
 
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use buck2_cli_proto::QueryDepKind;
 use buck2_cli_proto::QueryOutputFormat;
 use buck2_client_ctx::query_args::CommonAttributeArgs;
 use buck2_query_parser::placeholder::QUERY_PERCENT_SS_PLACEHOLDER;
@@ -26,6 +27,26 @@ enum QueryOutputFormatArg {
     Json,
     DotCompact,
     Starlark,
+    Files,
+    #[value(alias = "json-lines")]
+    JsonLines,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Dupe,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize
+)]
+#[clap(rename_all = "snake_case")]
+enum QueryDepKindArg {
+    Deps,
+    TargetDeps,
+    ExecDeps,
+    ConfigurationDeps,
+    ToolchainDeps,
 }
 
 /// Args common to all the query commands
@@ -55,13 +76,37 @@ pub(crate) struct CommonQueryOptions {
            dot -  dot graph format. \n
            dot_compact - compact alternative to dot format. \n
            json - JSON format. \n
-           starlark - targets are printed like starlark code that would produce them.
+           starlark - targets are printed like starlark code that would produce them. \n
+           files - the de-duplicated paths of the files in the result, relative to the project root: \
+           the sources of targets (and in cquery, their default outputs in buck-out), the outputs \
+           of actions (in buck-out) and file sets. \n
+           json_lines - JSON format with one target or file per line. The query is still evaluated \
+           in full before anything is printed, but each target is then printed as soon as its \
+           details are ready, rather than as one JSON document, so large results use less memory.
          ",
-        value_name = "dot|dot_compact|json|starlark",
+        value_name = "dot|dot_compact|json|json_lines|starlark|files",
         value_enum
     )]
     output_format: Option<QueryOutputFormatArg>,
 
+    #[clap(
+        long,
+        value_delimiter = ',',
+        help = "With dot output, only draw the edges for these kinds of dependencies \
+        (default: deps, i.e. all of them). For example, `--dot-edges=target_deps,exec_deps`.",
+        value_name = "deps|target_deps|exec_deps|configuration_deps|toolchain_deps",
+        value_enum
+    )]
+    dot_edges: Vec<QueryDepKindArg>,
+
+    #[clap(
+        long,
+        help = "Print to stderr how long each query function took to evaluate, not counting its \
+        arguments, how many graph nodes it traversed, and how long resolving the target literals \
+        took"
+    )]
+    pub profile: bool,
+
     #[clap(
         name = "QUERY_ARGS",
         help = "list of literals for a multi-query (one containing `%s` or `%Ss`)"
@@ -90,6 +135,8 @@ impl CommonQueryOptions {
             Some(QueryOutputFormatArg::Dot) => QueryOutputFormat::Dot,
             Some(QueryOutputFormatArg::DotCompact) => QueryOutputFormat::DotCompact,
             Some(QueryOutputFormatArg::Starlark) => QueryOutputFormat::Starlark,
+            Some(QueryOutputFormatArg::Files) => QueryOutputFormat::Files,
+            Some(QueryOutputFormatArg::JsonLines) => QueryOutputFormat::JsonLines,
             None => {
                 if self.json {
                     QueryOutputFormat::Json
@@ -104,6 +151,21 @@ impl CommonQueryOptions {
         }
     }
 
+    pub fn dot_edges(&self) -> Vec<i32> {
+        self.dot_edges
+            .iter()
+            .map(|kind| {
+                (match kind {
+                    QueryDepKindArg::Deps => QueryDepKind::AllDeps,
+                    QueryDepKindArg::TargetDeps => QueryDepKind::TargetDeps,
+                    QueryDepKindArg::ExecDeps => QueryDepKind::ExecDeps,
+                    QueryDepKindArg::ConfigurationDeps => QueryDepKind::ConfigurationDeps,
+                    QueryDepKindArg::ToolchainDeps => QueryDepKind::ToolchainDeps,
+                }) as i32
+            })
+            .collect()
+    }
+
     pub fn get_query(&self) -> (String, Vec<String>) {
         if self.query.contains(QUERY_PERCENT_SS_PLACEHOLDER) {
             let replacement = Self::args_as_set(&self.query_args);
@@ -62,6 +62,10 @@ List the deps of a target (special characters in a target will
 require quotes):
 
 `buck2 cquery 'deps("//java/com/example/app:amazing+more")'`
+
+List the configurations of a library that are reachable from a binary
+
+`buck2 cquery //java/com/example/lib:util --target-universe //java/com/example/app:amazing`
 "#
     )
 }
@@ -130,7 +134,7 @@ impl StreamingCommand for CqueryCommand {
             }
         };
 
-        let CqueryResponse {} = buckd
+        let CqueryResponse { profile } = buckd
             .with_flushing()
             .cquery(
                 CqueryRequest {
@@ -142,6 +146,8 @@ impl StreamingCommand for CqueryCommand {
                     target_cfg: Some(self.target_cfg.target_cfg.target_cfg()),
                     show_providers: self.show_providers,
                     unstable_output_format,
+                    dot_edges: self.query_common.dot_edges(),
+                    profile: self.query_common.profile,
                     correct_owner,
                 },
                 ctx.stdin()
@@ -150,6 +156,9 @@ impl StreamingCommand for CqueryCommand {
             )
             .await??;
 
+        if let Some(profile) = profile {
+            buck2_client_ctx::eprint!("{}", profile)?;
+        }
         ExitResult::success()
     }
 
@@ -103,7 +103,7 @@ impl StreamingCommand for UqueryCommand {
         let output_attributes = self.query_common.attributes.get()?;
         let context = ctx.client_context(matches, &self)?;
 
-        let UqueryResponse {} = buckd
+        let UqueryResponse { profile } = buckd
             .with_flushing()
             .uquery(
                 UqueryRequest {
@@ -112,6 +112,8 @@ impl StreamingCommand for UqueryCommand {
                     context: Some(context),
                     output_attributes,
                     unstable_output_format,
+                    dot_edges: self.query_common.dot_edges(),
+                    profile: self.query_common.profile,
                 },
                 ctx.stdin()
                     .console_interaction_stream(&self.common_opts.console_opts),
@@ -119,6 +121,9 @@ impl StreamingCommand for UqueryCommand {
             )
             .await??;
 
+        if let Some(profile) = profile {
+            buck2_client_ctx::eprint!("{}", profile)?;
+        }
         ExitResult::success()
     }
 
@@ -10,6 +10,7 @@
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write;
+use std::process::Stdio;
 
 use anyhow::Context;
 use async_trait::async_trait;
@@ -33,9 +34,15 @@ use buck2_client_ctx::path_arg::PathArg;
 use buck2_client_ctx::streaming::StreamingCommand;
 use buck2_common::argv::Argv;
 use buck2_common::argv::SanitizedArgv;
+use buck2_core::fs::paths::abs_path::AbsPathBuf;
 use buck2_wrapper_common::BUCK2_WRAPPER_ENV_VAR;
 use buck2_wrapper_common::BUCK_WRAPPER_UUID_ENV_VAR;
+use futures::future;
 use serde::Serialize;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncRead;
+use tokio::io::BufReader;
+use tokio::process::Command;
 
 use crate::commands::build::print_build_failed;
 use crate::commands::build::print_build_result;
@@ -45,6 +52,9 @@ use crate::commands::build::print_build_succeeded;
 ///
 /// The Build ID for the underlying build execution is made available to the target in
 /// the `BUCK_RUN_BUILD_ID` environment variable.
+///
+/// With `--parallel`, several targets are built together and then run concurrently (e.g. a dev
+/// server and its backends), each line of their output prefixed with the target it comes from.
 #[derive(Debug, clap::Parser)]
 #[clap(name = "run", trailing_var_arg = true)]
 pub struct RunCommand {
@@ -67,6 +77,12 @@ pub struct RunCommand {
     #[clap(long, group = "exec_options")]
     emit_shell: bool,
 
+    /// Build all the given targets, then run them concurrently and wait for all of them to
+    /// exit. All positional arguments are targets, which can't be passed additional arguments.
+    /// Exits with the exit code of the first target (in the order given) that failed.
+    #[clap(long, conflicts_with_all = &["command_args_file", "emit_shell"])]
+    parallel: bool,
+
     #[clap(name = "TARGET", help = "Target to build and run")]
     target: String,
 
@@ -97,6 +113,13 @@ impl StreamingCommand for RunCommand {
         ctx: &mut ClientCommandContext<'_>,
     ) -> ExitResult {
         let context = ctx.client_context(matches, &self)?;
+        let target_patterns = if self.parallel {
+            std::iter::once(self.target.clone())
+                .chain(self.extra_run_args.iter().cloned())
+                .collect()
+        } else {
+            vec![self.target.clone()]
+        };
         // TODO(rafaelc): fail fast on the daemon if the target doesn't have RunInfo
         let response = buckd
             .with_flushing()
@@ -104,7 +127,7 @@ impl StreamingCommand for RunCommand {
                 BuildRequest {
                     context: Some(context),
                     // TODO(wendyy): glob patterns should be prohibited, and command should fail before the build event happens.
-                    target_patterns: vec![self.target.clone()],
+                    target_patterns,
                     target_cfg: Some(self.target_cfg.target_cfg()),
                     build_providers: Some(BuildProviders {
                         default_info: build_providers::Action::Skip as i32,
@@ -139,6 +162,21 @@ impl StreamingCommand for RunCommand {
             return ExitResult::from_errors(&response.errors);
         }
 
+        if self.parallel {
+            let mut targets = Vec::with_capacity(response.build_targets.len());
+            for target in response.build_targets {
+                if target.run_args.is_empty() {
+                    return ExitResult::err(RunCommandError::NonBinaryRule(target.target).into());
+                }
+                targets.push((target.target, target.run_args));
+            }
+            print_build_succeeded(&console, ctx)?;
+            std::env::remove_var(BUCK2_WRAPPER_ENV_VAR);
+            std::env::remove_var(BUCK_WRAPPER_UUID_ENV_VAR);
+            let chdir = self.chdir.map(|chdir| chdir.resolve(&ctx.working_dir));
+            return run_parallel(targets, chdir, ctx.trace_id.to_string()).await;
+        }
+
         if response.build_targets.len() > 1 {
             return ExitResult::err(RunCommandError::MultipleTargets.into());
         }
@@ -236,6 +274,99 @@ impl StreamingCommand for RunCommand {
     }
 }
 
+/// Runs the targets concurrently, prefixing their output with their label, and waits for all of
+/// them to exit.
+async fn run_parallel(
+    targets: Vec<(String, Vec<String>)>,
+    chdir: Option<AbsPathBuf>,
+    build_id: String,
+) -> ExitResult {
+    let runs = targets.into_iter().map(|(target, run_args)| {
+        let chdir = chdir.clone();
+        let build_id = build_id.clone();
+        async move {
+            // Drop the configuration, which is the same for all the targets.
+            let prefix = match target.split_once(" (") {
+                Some((label, _)) => label.to_owned(),
+                None => target,
+            };
+            let mut command = Command::new(&run_args[0]);
+            command
+                .args(&run_args[1..])
+                .env("BUCK_RUN_BUILD_ID", build_id)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true);
+            if let Some(chdir) = chdir {
+                command.current_dir(chdir);
+            }
+            let mut child = command
+                .spawn()
+                .with_context(|| format!("Failed to run `{}` for `{}`", run_args[0], prefix))?;
+            let stdout = child.stdout.take().context("Child stdout is piped")?;
+            let stderr = child.stderr.take().context("Child stderr is piped")?;
+            let (status, (), ()) = futures::try_join!(
+                async { anyhow::Ok(child.wait().await?) },
+                forward_lines(stdout, &prefix, false),
+                forward_lines(stderr, &prefix, true),
+            )?;
+            anyhow::Ok((prefix, status))
+        }
+    });
+
+    let mut failure = None;
+    for run in future::join_all(runs).await {
+        let (target, status) = run?;
+        if status.success() {
+            continue;
+        }
+        buck2_client_ctx::eprintln!("`{}` failed: {}", target, status)?;
+        if failure.is_none() {
+            failure = Some(match status.code() {
+                Some(code) => ExitResult::status_extended(code),
+                None => ExitResult::bail(format!("`{}` was killed", target)),
+            });
+        }
+    }
+    failure.unwrap_or_else(ExitResult::success)
+}
+
+async fn forward_lines(
+    output: impl AsyncRead + Unpin,
+    prefix: &str,
+    stderr: bool,
+) -> anyhow::Result<()> {
+    for_each_line(output, |line| {
+        if stderr {
+            buck2_client_ctx::eprintln!("[{}] {}", prefix, line)?;
+        } else {
+            buck2_client_ctx::println!("[{}] {}", prefix, line)?;
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Calls `f` on each line of `output`, without the line terminator. Output that isn't UTF-8 is
+/// still forwarded, with the invalid bytes replaced.
+async fn for_each_line(
+    output: impl AsyncRead + Unpin,
+    mut f: impl FnMut(&str) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut output = BufReader::new(output);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if output.read_until(b'\n', &mut line).await? == 0 {
+            return Ok(());
+        }
+        let line = line.strip_suffix(b"\n").unwrap_or(&line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        f(&String::from_utf8_lossy(line))?;
+    }
+}
+
 #[derive(Serialize)]
 struct CommandArgsFile {
     path: String,
@@ -258,3 +389,20 @@ pub enum RunCommandError {
     )]
     MultipleTargets,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_for_each_line() {
+        let mut lines = Vec::new();
+        for_each_line(&b"a\n\xffb\r\n\nc"[..], |line| {
+            lines.push(line.to_owned());
+            Ok(())
+        })
+        .await
+        .unwrap();
+        assert_eq!(lines, vec!["a", "\u{FFFD}b", "", "c"]);
+    }
+}
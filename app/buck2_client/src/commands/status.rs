@@ -7,6 +7,9 @@
  * of this source tree.
  */
 
+#[cfg(unix)]
+mod passwd;
+
 use std::time::Duration;
 
 use anyhow::Context;
@@ -19,6 +22,8 @@ use buck2_client_ctx::subscribers::subscribers::EventSubscribers;
 use buck2_common::argv::Argv;
 use buck2_common::argv::SanitizedArgv;
 use buck2_common::daemon_dir::DaemonDir;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use chrono::NaiveDateTime;
 use humantime::format_duration;
 use walkdir::WalkDir;
@@ -27,6 +32,12 @@ use walkdir::WalkDir;
 enum StatusError {
     #[error("Incorrect seconds/nanos argument")]
     NativeDateTime,
+    #[error("`--all-users` can only be used by root")]
+    #[buck2(input)]
+    AllUsersRequiresRoot,
+    #[error("`--all-users` is not supported on this platform")]
+    #[buck2(input)]
+    AllUsersUnsupported,
 }
 
 #[derive(Debug, clap::Parser)]
@@ -36,6 +47,18 @@ pub struct StatusCommand {
     snapshot: bool,
     #[clap(long, help = "Enable printing status for all running buckd")]
     all: bool,
+    #[clap(
+        long,
+        help = "Like `--all`, but for the daemons of every user on this machine, with the user \
+        which owns each one. Must be run as root."
+    )]
+    all_users: bool,
+    #[clap(
+        long,
+        help = "Whether to include the file and buckconfig changes which the next command will \
+        invalidate, so scripts can tell whether it will be incremental."
+    )]
+    dirty: bool,
 }
 
 impl StatusCommand {
@@ -45,35 +68,31 @@ impl StatusCommand {
         ctx: ClientCommandContext<'_>,
     ) -> anyhow::Result<()> {
         ctx.with_runtime(|ctx| async move {
-            if self.all {
-                let mut daemon_dirs = Vec::new();
-                let root = ctx.paths()?.roots.common_buckd_dir()?;
-                let walker = WalkDir::new(&root).follow_links(false).into_iter();
-                for entry in walker {
-                    let entry = entry?;
-                    if entry.file_type().is_dir() {
-                        let dir = DaemonDir {
-                            path: entry.into_path().try_into()?,
-                        };
-
-                        if dir.buckd_info().exists() {
-                            daemon_dirs.push(dir);
-                        }
-                    }
-                }
+            if self.all || self.all_users {
+                let buckd_dirs = if self.all_users {
+                    users_buckd_dirs()?
+                } else {
+                    vec![(None, ctx.paths()?.roots.common_buckd_dir()?)]
+                };
 
                 let mut statuses = Vec::new();
-                for dir in daemon_dirs {
-                    if let Ok(bootstrap_client) = establish_connection_existing(&dir).await {
-                        statuses.push(process_status(
-                            bootstrap_client
-                                .with_subscribers(EventSubscribers::new(vec![Box::new(
-                                    StdoutStderrForwarder,
-                                )]))
-                                .with_flushing()
-                                .status(self.snapshot)
-                                .await?,
-                        )?);
+                for (user, root) in buckd_dirs {
+                    for dir in find_daemon_dirs(&root)? {
+                        if let Ok(bootstrap_client) = establish_connection_existing(&dir).await {
+                            let mut status = process_status(
+                                bootstrap_client
+                                    .with_subscribers(EventSubscribers::new(vec![Box::new(
+                                        StdoutStderrForwarder,
+                                    )]))
+                                    .with_flushing()
+                                    .status(self.snapshot, self.dirty)
+                                    .await?,
+                            )?;
+                            if let Some(user) = &user {
+                                status["user"] = serde_json::to_value(user)?;
+                            }
+                            statuses.push(status);
+                        }
                     }
                 }
 
@@ -88,8 +107,12 @@ impl StatusCommand {
                         // Should this be an error?
                     }
                     Ok(mut client) => {
-                        let json_status =
-                            process_status(client.with_flushing().status(self.snapshot).await?)?;
+                        let json_status = process_status(
+                            client
+                                .with_flushing()
+                                .status(self.snapshot, self.dirty)
+                                .await?,
+                        )?;
                         buck2_client_ctx::println!(
                             "{}",
                             serde_json::to_string_pretty(&json_status)?
@@ -107,6 +130,69 @@ impl StatusCommand {
     }
 }
 
+/// The daemon dirs under `root` which have a `buckd.info`, i.e. of daemons which may be running.
+fn find_daemon_dirs(root: &AbsNormPath) -> anyhow::Result<Vec<DaemonDir>> {
+    let mut daemon_dirs = Vec::new();
+    if !root.as_path().exists() {
+        return Ok(daemon_dirs);
+    }
+    let walker = WalkDir::new(root).follow_links(false).into_iter();
+    for entry in walker {
+        // E.g. a directory of another user, or one deleted while walking: skip it rather than
+        // not listing any daemon.
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::warn!("Error listing daemon dirs: {:#}", e);
+                continue;
+            }
+        };
+        if entry.file_type().is_dir() {
+            let dir = DaemonDir {
+                path: entry.into_path().try_into()?,
+            };
+
+            if dir.buckd_info().exists() {
+                daemon_dirs.push(dir);
+            }
+        }
+    }
+    Ok(daemon_dirs)
+}
+
+/// The `common_buckd_dir` of every user, with their name.
+#[cfg(unix)]
+fn users_buckd_dirs() -> anyhow::Result<Vec<(Option<String>, AbsNormPathBuf)>> {
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+
+    use buck2_common::invocation_roots::buckd_dir_in_home;
+
+    // The daemon dirs of other users are only readable by them.
+    if !passwd::is_root() {
+        return Err(StatusError::AllUsersRequiresRoot.into());
+    }
+
+    let mut seen = HashSet::new();
+    let mut dirs = Vec::new();
+    for user in passwd::passwd_entries() {
+        // System users often have no home directory, or share one.
+        let Ok(home) = AbsNormPathBuf::new(PathBuf::from(user.home)) else {
+            continue;
+        };
+        let dir = buckd_dir_in_home(&home);
+        if seen.insert(dir.clone()) {
+            dirs.push((Some(user.name), dir));
+        }
+    }
+    Ok(dirs)
+}
+
+#[cfg(not(unix))]
+fn users_buckd_dirs() -> anyhow::Result<Vec<(Option<String>, AbsNormPathBuf)>> {
+    Err(StatusError::AllUsersUnsupported.into())
+}
+
 fn timestamp_to_string(seconds: u64, nanos: u32) -> anyhow::Result<String> {
     Ok(NaiveDateTime::from_timestamp_opt(seconds as i64, nanos)
         .context(StatusError::NativeDateTime)?
@@ -153,6 +239,10 @@ fn process_status(status: StatusResponse) -> anyhow::Result<serde_json::Value> {
         value["valid_buck_out_mount"] = serde_json::to_value(valid_buck_out_mount)?;
     }
 
+    if let Some(pending_invalidations) = status.pending_invalidations {
+        value["pending_invalidations"] = serde_json::to_value(pending_invalidations)?;
+    }
+
     Ok(value)
 }
 
@@ -160,7 +250,11 @@ fn process_status(status: StatusResponse) -> anyhow::Result<serde_json::Value> {
 mod tests {
     use std::time::Duration;
 
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+
     use crate::commands::status::duration_to_string;
+    use crate::commands::status::find_daemon_dirs;
     use crate::commands::status::timestamp_to_string;
 
     #[test]
@@ -179,4 +273,43 @@ mod tests {
             duration_to_string(Duration::new(3600 + 120 + 3, 123456789))
         );
     }
+
+    #[test]
+    fn test_find_daemon_dirs() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let root = AbsNormPath::new(tempdir.path()).unwrap();
+        let buckd_info = |dir: &str| {
+            let dir = tempdir.path().join(dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("buckd.info"), "{}").unwrap();
+        };
+        buckd_info("repo1/v2");
+        buckd_info("repo2/v2-alice");
+        std::fs::create_dir_all(tempdir.path().join("repo3/v2")).unwrap();
+
+        let found = |root: &AbsNormPath| {
+            let mut dirs = find_daemon_dirs(root)
+                .unwrap()
+                .into_iter()
+                .map(|d| d.path.strip_prefix(root).unwrap().as_str().to_owned())
+                .collect::<Vec<_>>();
+            dirs.sort();
+            dirs
+        };
+        assert_eq!(vec!["repo1/v2", "repo2/v2-alice"], found(root));
+        let missing = AbsNormPathBuf::new(tempdir.path().join("missing")).unwrap();
+        assert!(find_daemon_dirs(&missing).unwrap().is_empty());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            // A directory which can't be read is skipped, the others are still listed.
+            let unreadable = tempdir.path().join("repo4");
+            std::fs::create_dir(&unreadable).unwrap();
+            std::fs::set_permissions(&unreadable, std::fs::Permissions::from_mode(0o000)).unwrap();
+            assert_eq!(vec!["repo1/v2", "repo2/v2-alice"], found(root));
+            std::fs::set_permissions(&unreadable, std::fs::Permissions::from_mode(0o700)).unwrap();
+        }
+    }
 }
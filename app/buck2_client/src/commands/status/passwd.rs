@@ -0,0 +1,69 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Safe wrappers around the user database functions of libc.
+
+use std::ffi::CStr;
+use std::sync::Mutex;
+
+/// An entry of the user database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PasswdEntry {
+    pub(crate) name: String,
+    pub(crate) home: String,
+}
+
+/// `setpwent`, `getpwent` and `endpwent` share a cursor, and the entry returned by `getpwent` is
+/// only valid until the next call, so iterations must not overlap.
+static PASSWD_LOCK: Mutex<()> = Mutex::new(());
+
+pub(crate) fn is_root() -> bool {
+    // SAFETY: `geteuid` always succeeds and has no side effects.
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// Every entry of the user database: `/etc/passwd`, and any other source configured in NSS.
+pub(crate) fn passwd_entries() -> Vec<PasswdEntry> {
+    let _guard = PASSWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut entries = Vec::new();
+    // SAFETY: the lock makes sure that nothing else moves the cursor while iterating, and each
+    // entry is copied before the next call to `getpwent` overwrites it.
+    unsafe {
+        libc::setpwent();
+        loop {
+            let entry = libc::getpwent();
+            if entry.is_null() {
+                break;
+            }
+            let entry = &*entry;
+            if entry.pw_name.is_null() || entry.pw_dir.is_null() {
+                continue;
+            }
+            entries.push(PasswdEntry {
+                name: CStr::from_ptr(entry.pw_name).to_string_lossy().into_owned(),
+                home: CStr::from_ptr(entry.pw_dir).to_string_lossy().into_owned(),
+            });
+        }
+        libc::endpwent();
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passwd_entries() {
+        let entries = passwd_entries();
+        assert!(entries.iter().any(|e| e.name == "root"), "{:?}", entries);
+        // Each call iterates from the start.
+        assert_eq!(entries, passwd_entries());
+    }
+}
@@ -7,6 +7,10 @@
  * of this source tree.
  */
 
+use std::fs::File;
+use std::io::BufWriter;
+
+use anyhow::Context;
 use async_trait::async_trait;
 use buck2_cli_proto::targets_request;
 use buck2_cli_proto::targets_request::OutputFormat;
@@ -29,11 +33,15 @@ use buck2_client_ctx::query_args::CommonAttributeArgs;
 use buck2_client_ctx::stdin::Stdin;
 use buck2_client_ctx::streaming::StreamingCommand;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_path::AbsPath;
 use dupe::Dupe;
 use gazebo::prelude::*;
 
+use crate::commands::targets::snapshot::Snapshot;
 use crate::print::PrintOutputs;
 
+pub(crate) mod snapshot;
+
 #[derive(buck2_error::Error, Debug)]
 enum TargetsError {
     /// Clap should report it, but if we missed something, this is a fallback.
@@ -220,6 +228,16 @@ pub struct TargetsCommand {
     )]
     compression: Compression,
 
+    /// Write a snapshot of the unconfigured target graph (every attribute of every target,
+    /// including its deps) to this file, in a compact binary format. The snapshot can be
+    /// queried later, without a daemon, with `buck2 debug snapshot-query`.
+    #[clap(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = &["json", "output", "stats", "streaming", "resolve_alias"]
+    )]
+    snapshot: Option<PathArg>,
+
     /// Patterns to interpret
     #[clap(name = "TARGET_PATTERNS")]
     patterns: Vec<String>,
@@ -304,7 +322,20 @@ impl StreamingCommand for TargetsCommand {
             TargetHashFunction::Strong => false,
         };
 
-        let output_attributes = self.attributes.get()?;
+        let snapshot = self
+            .snapshot
+            .take()
+            .map(|path| path.resolve(&ctx.working_dir));
+        let output_attributes = if snapshot.is_some() {
+            // Every attribute with its default, and the build file of each package, so that the
+            // snapshot can answer any query.
+            self.json_lines = true;
+            self.include_defaults = true;
+            self.imports = true;
+            vec![String::new()]
+        } else {
+            self.attributes.get()?
+        };
         let package_values = self.package_values_as_regexes()?;
         let target_hash_graph_type =
             match (self.show_target_hash, self.show_unconfigured_target_hash) {
@@ -369,6 +400,15 @@ impl StreamingCommand for TargetsCommand {
                 &self.common_opts.console_opts,
             )
             .await
+        } else if let Some(snapshot) = snapshot {
+            targets_snapshot(
+                ctx.stdin(),
+                buckd,
+                target_request,
+                &snapshot,
+                &self.common_opts.console_opts,
+            )
+            .await
         } else {
             targets(
                 ctx.stdin(),
@@ -447,3 +487,24 @@ async fn targets(
     }
     ExitResult::success()
 }
+
+async fn targets_snapshot(
+    stdin: &mut Stdin,
+    buckd: &mut BuckdClientConnector<'_>,
+    target_request: TargetsRequest,
+    snapshot: &AbsPath,
+    console_opts: &CommonConsoleOptions,
+) -> ExitResult {
+    let response = buckd
+        .with_flushing()
+        .targets(
+            target_request,
+            stdin.console_interaction_stream(console_opts),
+            &mut NoPartialResultHandler,
+        )
+        .await??;
+    let file = File::create(snapshot)
+        .with_context(|| format!("Creating snapshot `{}`", snapshot.display()))?;
+    Snapshot::from_json_lines(&response.serialized_targets_output)?.write(BufWriter::new(file))?;
+    ExitResult::success()
+}
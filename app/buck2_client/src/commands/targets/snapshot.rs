@@ -0,0 +1,288 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! The file format of the target graph snapshots written by `buck2 targets --snapshot` and
+//! queried by `buck2 debug snapshot-query`.
+//!
+//! A snapshot is a header (`MAGIC` and the format version) followed by the zstd-compressed
+//! bincode encoding of [`Snapshot`]. Every label, path and attribute name is stored once in
+//! the string table, and nodes refer to strings by their index in it.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::io::Write;
+
+use anyhow::Context;
+
+const MAGIC: &[u8; 8] = b"BUCK2TGS";
+const VERSION: u32 = 1;
+
+#[derive(Debug, buck2_error::Error)]
+enum SnapshotError {
+    #[error("Not a target graph snapshot")]
+    NotASnapshot,
+    #[error("Snapshot format version {0} is not supported, expected version {1}")]
+    UnsupportedVersion(u32, u32),
+    #[error("Target `{0}` has no `{1}` attribute")]
+    MissingAttribute(String, &'static str),
+    #[error("Build file of package `{0}` is not in the `buck2 targets` output")]
+    MissingBuildFile(String),
+}
+
+#[derive(Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct Snapshot {
+    strings: Vec<String>,
+    nodes: Vec<SnapshotNode>,
+}
+
+/// An unconfigured target. All the `u32` are indexes in the string table.
+#[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct SnapshotNode {
+    pub(crate) label: u32,
+    pub(crate) name: u32,
+    pub(crate) rule_type: u32,
+    /// The cell path of the build file which defines the target.
+    pub(crate) buildfile: u32,
+    pub(crate) deps: Vec<u32>,
+    pub(crate) inputs: Vec<u32>,
+    /// Every other attribute, with its value encoded as JSON (bincode cannot encode arbitrary
+    /// JSON values).
+    pub(crate) attrs: Vec<(u32, String)>,
+}
+
+#[derive(Default)]
+struct StringTable {
+    strings: Vec<String>,
+    indexes: HashMap<String, u32>,
+}
+
+impl StringTable {
+    fn intern(&mut self, s: &str) -> u32 {
+        if let Some(index) = self.indexes.get(s) {
+            return *index;
+        }
+        let index = self.strings.len() as u32;
+        self.strings.push(s.to_owned());
+        self.indexes.insert(s.to_owned(), index);
+        index
+    }
+}
+
+impl Snapshot {
+    /// Builds a snapshot from the JSON lines output of `buck2 targets` with every attribute
+    /// and `--imports` (which gives the build file of each package).
+    pub(crate) fn from_json_lines(json_lines: &str) -> anyhow::Result<Snapshot> {
+        let mut buildfiles = HashMap::new();
+        let mut targets = Vec::new();
+        for line in json_lines.lines() {
+            let entry: serde_json::Map<String, serde_json::Value> = serde_json::from_str(line)
+                .with_context(|| format!("Parsing `buck2 targets` output line `{}`", line))?;
+            let package = entry.get("buck.package").and_then(|p| p.as_str());
+            if let (Some(package), Some(file)) =
+                (package, entry.get("buck.file").and_then(|f| f.as_str()))
+            {
+                buildfiles.insert(package.to_owned(), file.to_owned());
+            } else if entry.contains_key("name") {
+                targets.push(entry);
+            }
+            // Otherwise this is a package which failed to load (with `--keep-going`), or
+            // the imports of a `.bzl` file.
+        }
+
+        let mut strings = StringTable::default();
+        let mut nodes = Vec::with_capacity(targets.len());
+        for mut target in targets {
+            let package = str_attr(&target, "<unknown>", "buck.package")?.to_owned();
+            let name = str_attr(&target, &package, "name")?.to_owned();
+            let label = format!("{}:{}", package, name);
+            let rule_type = str_attr(&target, &label, "buck.type")?.to_owned();
+            let buildfile = buildfiles
+                .get(&package)
+                .ok_or_else(|| SnapshotError::MissingBuildFile(package.clone()))?;
+            let deps = str_list_attr(&mut target, &label, "buck.deps")?;
+            let inputs = str_list_attr(&mut target, &label, "buck.inputs")?;
+            target.remove("buck.type");
+            nodes.push(SnapshotNode {
+                label: strings.intern(&label),
+                name: strings.intern(&name),
+                rule_type: strings.intern(&rule_type),
+                buildfile: strings.intern(buildfile),
+                deps: deps.iter().map(|d| strings.intern(d)).collect(),
+                inputs: inputs.iter().map(|i| strings.intern(i)).collect(),
+                attrs: target
+                    .iter()
+                    .map(|(k, v)| (strings.intern(k), v.to_string()))
+                    .collect(),
+            });
+        }
+        Ok(Snapshot {
+            strings: strings.strings,
+            nodes,
+        })
+    }
+
+    pub(crate) fn nodes(&self) -> &[SnapshotNode] {
+        &self.nodes
+    }
+
+    pub(crate) fn string(&self, index: u32) -> &str {
+        &self.strings[index as usize]
+    }
+
+    pub(crate) fn write(&self, mut writer: impl Write) -> anyhow::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        let mut encoder = zstd::Encoder::new(writer, 0)?;
+        bincode::serialize_into(&mut encoder, self)?;
+        encoder.finish()?.flush()?;
+        Ok(())
+    }
+
+    pub(crate) fn read(mut reader: impl Read) -> anyhow::Result<Snapshot> {
+        let mut magic = [0; MAGIC.len()];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|_| SnapshotError::NotASnapshot)?;
+        if &magic != MAGIC {
+            return Err(SnapshotError::NotASnapshot.into());
+        }
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version, VERSION).into());
+        }
+        let snapshot: Snapshot = bincode::deserialize_from(zstd::Decoder::new(reader)?)?;
+        // Check the string indexes once here, rather than on every access.
+        let strings = snapshot.strings.len() as u32;
+        for node in &snapshot.nodes {
+            let indexes = [node.label, node.name, node.rule_type, node.buildfile];
+            if indexes
+                .iter()
+                .chain(&node.deps)
+                .chain(&node.inputs)
+                .chain(node.attrs.iter().map(|(k, _)| k))
+                .any(|i| *i >= strings)
+            {
+                return Err(SnapshotError::NotASnapshot.into());
+            }
+        }
+        Ok(snapshot)
+    }
+}
+
+fn str_attr<'a>(
+    target: &'a serde_json::Map<String, serde_json::Value>,
+    label: &str,
+    attr: &'static str,
+) -> anyhow::Result<&'a str> {
+    target
+        .get(attr)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| SnapshotError::MissingAttribute(label.to_owned(), attr).into())
+}
+
+/// Removes a list of strings attribute from `target`: it is stored in its own field.
+fn str_list_attr(
+    target: &mut serde_json::Map<String, serde_json::Value>,
+    label: &str,
+    attr: &'static str,
+) -> anyhow::Result<Vec<String>> {
+    match target.remove(attr) {
+        Some(serde_json::Value::Array(values)) => values
+            .into_iter()
+            .map(|v| match v {
+                serde_json::Value::String(s) => Ok(s),
+                _ => Err(SnapshotError::MissingAttribute(label.to_owned(), attr).into()),
+            })
+            .collect(),
+        _ => Err(SnapshotError::MissingAttribute(label.to_owned(), attr).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGETS_OUTPUT: &str = r#"{"buck.package": "root//lib", "buck.file": "root//lib/BUCK", "buck.imports": []}
+{"buck.type": "cxx_library", "buck.deps": [], "buck.inputs": ["root//lib/lib.c"], "buck.package": "root//lib", "name": "lib", "srcs": ["root//lib/lib.c"]}
+{"buck.package": "root//app", "buck.file": "root//app/BUCK", "buck.imports": []}
+{"buck.type": "cxx_binary", "buck.deps": ["root//lib:lib"], "buck.inputs": ["root//app/main.c"], "buck.package": "root//app", "name": "app", "srcs": ["root//app/main.c"], "labels": ["app"]}
+{"buck.package": "root//broken", "buck.error": "Error parsing root//broken"}
+"#;
+
+    #[test]
+    fn test_from_json_lines() {
+        let snapshot = Snapshot::from_json_lines(TARGETS_OUTPUT).unwrap();
+        assert_eq!(2, snapshot.nodes().len());
+
+        let app = &snapshot.nodes()[1];
+        assert_eq!("root//app:app", snapshot.string(app.label));
+        assert_eq!("cxx_binary", snapshot.string(app.rule_type));
+        assert_eq!("root//app/BUCK", snapshot.string(app.buildfile));
+        assert_eq!(
+            vec!["root//lib:lib"],
+            app.deps
+                .iter()
+                .map(|d| snapshot.string(*d))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                ("buck.package", "\"root//app\""),
+                ("labels", "[\"app\"]"),
+                ("name", "\"app\""),
+                ("srcs", "[\"root//app/main.c\"]"),
+            ],
+            app.attrs
+                .iter()
+                .map(|(k, v)| (snapshot.string(*k), v.as_str()))
+                .collect::<Vec<_>>()
+        );
+        // Labels, paths and names are only stored once.
+        assert_eq!(
+            1,
+            snapshot
+                .strings
+                .iter()
+                .filter(|s| *s == "root//lib:lib")
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_missing_build_file() {
+        let output = TARGETS_OUTPUT
+            .lines()
+            .filter(|line| !line.contains("buck.file"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(Snapshot::from_json_lines(&output).is_err());
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let snapshot = Snapshot::from_json_lines(TARGETS_OUTPUT).unwrap();
+        let mut bytes = Vec::new();
+        snapshot.write(&mut bytes).unwrap();
+        assert!(bytes.starts_with(MAGIC));
+        assert_eq!(snapshot, Snapshot::read(bytes.as_slice()).unwrap());
+    }
+
+    #[test]
+    fn test_read_invalid() {
+        assert!(Snapshot::read(&b"{\"buck.package\": \"root//lib\"}"[..]).is_err());
+
+        let mut bytes = Vec::new();
+        Snapshot::default().write(&mut bytes).unwrap();
+        bytes[MAGIC.len()] = 2;
+        let err = Snapshot::read(bytes.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("version 2"), "{}", err);
+    }
+}
@@ -9,6 +9,8 @@
 
 use anyhow::Context;
 use async_trait::async_trait;
+use buck2_cli_proto::test_response::QuarantineRecommendation;
+use buck2_cli_proto::test_response::TestStatuses;
 use buck2_cli_proto::CounterWithExamples;
 use buck2_cli_proto::TestRequest;
 use buck2_cli_proto::TestSessionOptions;
@@ -66,6 +68,79 @@ fn print_error_counter(
     }
     Ok(())
 }
+
+fn print_quarantine_recommendations(
+    console: &FinalConsole,
+    recommendations: &[QuarantineRecommendation],
+) -> anyhow::Result<()> {
+    if recommendations.is_empty() {
+        return Ok(());
+    }
+    console.print_warning(&format!("{} TESTS LOOK FLAKY", recommendations.len()))?;
+    for recommendation in recommendations {
+        console.print_warning(&format!(
+            "  ~ {} {} ({}/{} recent runs failed)",
+            recommendation.target,
+            recommendation.name,
+            recommendation.recent_failures,
+            recommendation.recent_runs,
+        ))?;
+    }
+    Ok(())
+}
+
+/// The machine-readable summary written by `--test-report`.
+#[derive(serde::Serialize)]
+struct TestReport<'a> {
+    passed: u64,
+    failed: u64,
+    fatals: u64,
+    skipped: u64,
+    listing_failed: u64,
+    quarantine: Vec<QuarantineEntry<'a>>,
+}
+
+#[derive(serde::Serialize)]
+struct QuarantineEntry<'a> {
+    target: &'a str,
+    name: &'a str,
+    /// `passed_on_rerun` or `flipping`.
+    reason: String,
+    recent_runs: u64,
+    recent_failures: u64,
+}
+
+fn write_test_report(
+    path: &PathArg,
+    working_dir: &WorkingDir,
+    statuses: &TestStatuses,
+    recommendations: &[QuarantineRecommendation],
+) -> anyhow::Result<()> {
+    let count = |counter: &Option<CounterWithExamples>| counter.as_ref().map_or(0, |c| c.count);
+    let report = TestReport {
+        passed: count(&statuses.passed),
+        failed: count(&statuses.failed),
+        fatals: count(&statuses.fatals),
+        skipped: count(&statuses.skipped),
+        listing_failed: count(&statuses.listing_failed),
+        quarantine: recommendations
+            .iter()
+            .map(|r| QuarantineEntry {
+                target: &r.target,
+                name: &r.name,
+                reason: r.reason().as_str_name().to_lowercase(),
+                recent_runs: r.recent_runs,
+                recent_failures: r.recent_failures,
+            })
+            .collect(),
+    };
+    fs_util::write(
+        path.resolve(working_dir),
+        serde_json::to_string_pretty(&report)?,
+    )
+    .context("Failed to write test report")
+}
+
 #[derive(Debug, clap::Parser)]
 #[clap(name = "test", about = "Build and test the specified targets")]
 pub struct TestCommand {
@@ -157,6 +232,11 @@ If include patterns are present, regardless of whether exclude patterns are pres
     #[clap(long)]
     test_executor_stderr: Option<OutputDestinationArg>,
 
+    /// Writes a JSON summary of the test run to the provided path, including the tests which look
+    /// flaky and which CI may want to quarantine.
+    #[clap(long, value_name = "PATH")]
+    test_report: Option<PathArg>,
+
     /// Additional arguments passed to the test executor.
     ///
     /// Test executor is expected to have `--env` flag to pass environment variables.
@@ -292,6 +372,16 @@ impl StreamingCommand for TestCommand {
         if passed.count + failed.count + fatals.count + skipped.count == 0 {
             console.print_warning("NO TESTS RAN")?;
         }
+        print_quarantine_recommendations(&console, &response.quarantine_recommendations)?;
+
+        if let Some(path) = &self.test_report {
+            write_test_report(
+                path,
+                &ctx.working_dir,
+                statuses,
+                &response.quarantine_recommendations,
+            )?;
+        }
 
         let info_messages = response.executor_info_messages;
         for message in info_messages {
@@ -157,6 +157,7 @@ impl<'a> ClientCommandContext<'a> {
                 Some(PreemptibleWhen::OnDifferentState) => GrpcPreemptibleWhen::OnDifferentState,
             }
             .into(),
+            reattachable: config_opts.reattachable,
             argfiles: self
                 .immediate_config
                 .trace()
@@ -204,6 +205,7 @@ impl<'a> ClientCommandContext<'a> {
                 .map(ClientMetadata::to_proto)
                 .collect(),
             preemptible: Default::default(),
+            reattachable: false,
         })
     }
 
@@ -177,6 +177,14 @@ pub struct CommonBuildConfigurationOptions {
     /// Used to configure when this command could be preempted by another command.
     #[clap(long, ignore_case = true, value_enum)]
     pub preemptible: Option<PreemptibleWhen>,
+
+    /// Keep the command running if the connection to the daemon is lost, and reconnect to it.
+    ///
+    /// The daemon cancels the command once no client has been attached to it for a minute, so
+    /// interrupting the client does not cancel the command right away. Use `buck2 attach` to
+    /// follow the command from another client.
+    #[clap(long)]
+    pub reattachable: bool,
 }
 
 impl CommonBuildConfigurationOptions {
@@ -279,6 +287,7 @@ impl CommonBuildConfigurationOptions {
             reuse_current_config: false,
             exit_when_different_state: false,
             preemptible: Some(PreemptibleWhen::Never),
+            reattachable: false,
         };
         &DEFAULT
     }
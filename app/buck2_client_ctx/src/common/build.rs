@@ -110,6 +110,12 @@ pub struct CommonBuildOptions {
     #[clap(long, requires = "no_remote_cache")]
     write_to_cache_anyway: bool,
 
+    /// Read from the remote cache, but never write to it. Use this when building from a
+    /// workspace whose outputs should not be shared, e.g. with uncommitted changes. The local
+    /// action cache is still written to.
+    #[clap(long, conflicts_with = "no_remote_cache")]
+    remote_cache_read_only: bool,
+
     /// Process dep files when they are generated (i.e. after running a command that produces dep
     /// files), rather than when they are used (i.e. before re-running a command that previously
     /// produced dep files). Use this when debugging commands that produce dep files. Note that
@@ -163,6 +169,18 @@ pub struct CommonBuildOptions {
     /// Materializes inputs for failed actions which ran on RE
     #[clap(long)]
     materialize_failed_inputs: bool,
+
+    /// Execute every action twice, without reading from or writing to the caches, and warn about
+    /// the actions whose outputs differ between the two executions. Only the actions which run in
+    /// this command are checked.
+    #[clap(long)]
+    unstable_check_determinism: bool,
+
+    /// Do not use the network. Actions run locally, remote caches are not used, and
+    /// `download_file` or CAS artifacts are only available if an earlier build downloaded them.
+    /// Can also be enabled with `[buck2] offline = true`.
+    #[clap(long, conflicts_with_all = &["remote_only", "prefer_remote"])]
+    offline: bool,
 }
 
 impl CommonBuildOptions {
@@ -209,11 +227,14 @@ impl CommonBuildOptions {
             upload_all_actions: self.upload_all_actions,
             skip_cache_read: self.no_remote_cache,
             skip_cache_write: self.no_remote_cache && !self.write_to_cache_anyway,
+            skip_remote_cache_write: self.remote_cache_read_only,
             fail_fast: self.fail_fast,
             keep_going: self.keep_going,
             skip_missing_targets: self.skip_missing_targets,
             skip_incompatible_targets: self.skip_incompatible_targets,
             materialize_failed_inputs: self.materialize_failed_inputs,
+            unstable_check_determinism: self.unstable_check_determinism,
+            offline: self.offline,
             unstable_include_failures_build_report,
             unstable_include_package_project_relative_paths,
         }
@@ -277,3 +298,41 @@ impl CommonOutputOptions {
         self.show_full_output || self.show_full_simple_output || self.show_full_json_output
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> anyhow::Result<buck2_cli_proto::CommonBuildOptions> {
+        Ok(CommonBuildOptions::try_parse_from(
+            std::iter::once("program").chain(args.iter().copied()),
+        )?
+        .to_proto())
+    }
+
+    #[test]
+    fn test_remote_cache_read_only() -> anyhow::Result<()> {
+        let opts = parse(&["--remote-cache-read-only"])?;
+        assert!(!opts.skip_cache_read);
+        assert!(opts.skip_remote_cache_write);
+        // The local action cache is still written to.
+        assert!(!opts.skip_cache_write);
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_remote_cache() -> anyhow::Result<()> {
+        let opts = parse(&["--no-remote-cache"])?;
+        assert!(opts.skip_cache_read);
+        assert!(opts.skip_cache_write);
+        assert!(!opts.skip_remote_cache_write);
+
+        let opts = parse(&["--no-remote-cache", "--write-to-cache-anyway"])?;
+        assert!(!opts.skip_cache_write);
+
+        assert!(parse(&["--no-remote-cache", "--remote-cache-read-only"]).is_err());
+        Ok(())
+    }
+}
@@ -51,6 +51,26 @@ pub enum UiOptions {
     Io,
     /// RE panel.
     Re,
+    /// Only show the first lines of the stderr of failed actions.
+    CollapseStderr,
+}
+
+/// How the errors a command fails with are printed.
+#[derive(
+    Debug,
+    serde::Serialize,
+    serde::Deserialize,
+    Clone,
+    Dupe,
+    Copy,
+    PartialEq,
+    clap::ValueEnum
+)]
+#[clap(rename_all = "lower")]
+pub enum ErrorFormat {
+    Text,
+    /// One JSON object per error on stderr, with the location of errors in Starlark files.
+    Json,
 }
 
 /// Defines common console options for commands.
@@ -74,6 +94,7 @@ pub struct CommonConsoleOptions {
     ///
     ///   dice - shows information about evaluated dice nodes
     ///   debugevents - shows information about the flow of events from buckd
+    ///   collapsestderr - only shows the first lines of the stderr of failed actions
     ///
     /// These components can be turned on/off interactively.
     /// Press 'h' for help when superconsole is active.
@@ -92,6 +113,17 @@ pub struct CommonConsoleOptions {
         value_parser = FalseyValueParser::new(),
     )]
     pub no_interactive_console: bool,
+
+    #[clap(
+        long,
+        help = "How to print the errors the command fails with. With `json`, each error is \
+        printed on stderr as a JSON object on its own line, including where the error is in \
+        BUCK and .bzl files, for editors and other tools to annotate those files",
+        default_value = "text",
+        ignore_case = true,
+        value_enum
+    )]
+    pub error_format: ErrorFormat,
 }
 
 impl Default for CommonConsoleOptions {
@@ -100,6 +132,7 @@ impl Default for CommonConsoleOptions {
             console_type: ConsoleType::Auto,
             ui: Vec::new(),
             no_interactive_console: false,
+            error_format: ErrorFormat::Text,
         }
     }
 }
@@ -110,6 +143,7 @@ impl CommonConsoleOptions {
             console_type: ConsoleType::Auto,
             ui: vec![],
             no_interactive_console: false,
+            error_format: ErrorFormat::Text,
         };
         &OPTS
     }
@@ -119,6 +153,7 @@ impl CommonConsoleOptions {
             console_type: ConsoleType::Simple,
             ui: vec![],
             no_interactive_console: false,
+            error_format: ErrorFormat::Text,
         };
         &OPTS
     }
@@ -128,6 +163,7 @@ impl CommonConsoleOptions {
             console_type: ConsoleType::None,
             ui: vec![],
             no_interactive_console: false,
+            error_format: ErrorFormat::Text,
         };
         &OPTS
     }
@@ -140,11 +176,12 @@ impl CommonConsoleOptions {
             ConsoleType::SimpleTty => true,
             ConsoleType::None => false,
         };
-        if is_tty {
+        let console = if is_tty {
             FinalConsole::new_with_tty()
         } else {
             FinalConsole::new_without_tty()
-        }
+        };
+        console.with_error_format(self.error_format)
     }
 
     pub fn superconsole_config(
@@ -161,6 +198,7 @@ impl CommonConsoleOptions {
                 UiOptions::DebugEvents => config.enable_debug_events = true,
                 UiOptions::Io => config.enable_io = true,
                 UiOptions::Re => config.enable_detailed_re = true,
+                UiOptions::CollapseStderr => config.collapse_failure_stderr = true,
             }
         }
         config
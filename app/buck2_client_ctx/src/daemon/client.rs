@@ -190,12 +190,18 @@ fn grpc_to_stream(
         Err(e) => return futures::stream::once(futures::future::ready(Err(e))).left_stream(),
     };
 
+    progress_to_stream_values(stream.map_err(tonic_status_to_error)).right_stream()
+}
+
+fn progress_to_stream_values(
+    stream: impl Stream<Item = anyhow::Result<MultiCommandProgress>>,
+) -> impl Stream<Item = anyhow::Result<StreamValue>> {
+    // Empty batches are heartbeats, which flatten to nothing here.
     let stream = stream
-        .map_err(tonic_status_to_error)
         .map_ok(|e| stream::iter(e.messages.into_iter().map(anyhow::Ok)))
         .try_flatten();
 
-    stream::unfold(stream, |mut stream| async {
+    stream::unfold(Box::pin(stream), |mut stream| async {
         let msg = match stream.try_next().await {
             Ok(Some(msg)) => msg,
             Ok(None) => return None,
@@ -212,7 +218,117 @@ fn grpc_to_stream(
 
         value.map(|v| (v, stream))
     })
-    .right_stream()
+}
+
+/// The daemon sends a heartbeat every few seconds, so a stream that has been silent for longer
+/// than this has lost its connection.
+const STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many times to try attaching to a command again after losing the connection to it.
+const REATTACH_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, buck2_error::Error)]
+enum ReattachError {
+    #[error("buck daemon sent nothing for {}s", STREAM_IDLE_TIMEOUT.as_secs())]
+    Idle,
+}
+
+/// Whether `status` looks like a lost connection rather than an error reported by the daemon.
+fn is_disconnect(status: &Status) -> bool {
+    matches!(
+        status.code(),
+        tonic::Code::Unavailable | tonic::Code::Cancelled
+    )
+}
+
+/// Attach to the command `trace_id` again after losing the connection to it with `error`.
+async fn reattach(
+    client: &mut DaemonApiClient<InterceptedService<Channel, BuckAddAuthTokenInterceptor>>,
+    trace_id: &str,
+    error: anyhow::Error,
+) -> anyhow::Result<tonic::Streaming<MultiCommandProgress>> {
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        tracing::warn!(
+            "Lost connection to buck daemon ({:#}), reattaching to command `{}` (attempt {}/{})",
+            error,
+            trace_id,
+            attempt,
+            REATTACH_ATTEMPTS,
+        );
+        let request = Request::new(AttachRequest {
+            trace_id: trace_id.to_owned(),
+        });
+        match client.attach(request).await {
+            Ok(response) => return Ok(response.into_inner()),
+            Err(status) if attempt < REATTACH_ATTEMPTS && is_disconnect(&status) => {
+                tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+            }
+            Err(status) => {
+                return Err(error.context(format!(
+                    "Error reattaching to command `{}`: {}",
+                    trace_id,
+                    status.message()
+                )));
+            }
+        }
+    }
+}
+
+type ProgressStream = stream::BoxStream<'static, Result<MultiCommandProgress, Status>>;
+
+/// Like `grpc_to_stream`, but for a reattachable command: when the connection to the daemon is
+/// lost or goes silent, attach to the command again and carry on with its events.
+fn reattaching_grpc_to_stream(
+    client: DaemonApiClient<InterceptedService<Channel, BuckAddAuthTokenInterceptor>>,
+    trace_id: String,
+    response: anyhow::Result<tonic::Response<tonic::Streaming<MultiCommandProgress>>>,
+) -> impl Stream<Item = anyhow::Result<StreamValue>> {
+    let stream = match response {
+        Ok(response) => response.into_inner().boxed(),
+        Err(e) => return futures::stream::once(futures::future::ready(Err(e))).left_stream(),
+    };
+
+    let stream = reattaching_stream(stream, STREAM_IDLE_TIMEOUT, move |error| {
+        let mut client = client.clone();
+        let trace_id = trace_id.clone();
+        async move { Ok(reattach(&mut client, &trace_id, error).await?.boxed()) }
+    });
+
+    progress_to_stream_values(stream).right_stream()
+}
+
+/// Follows `stream`, replacing it with the one `reattach` returns whenever it is disconnected or
+/// sends nothing for `idle_timeout`.
+fn reattaching_stream<F, Fut>(
+    stream: ProgressStream,
+    idle_timeout: Duration,
+    reattach: F,
+) -> impl Stream<Item = anyhow::Result<MultiCommandProgress>>
+where
+    F: Fn(anyhow::Error) -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<ProgressStream>>,
+{
+    stream::unfold(Some((stream, reattach)), move |state| async move {
+        let (mut stream, reattach) = state?;
+        loop {
+            let message = tokio::time::timeout(idle_timeout, stream.next()).await;
+            let error = match message {
+                Ok(Some(Ok(msg))) => return Some((Ok(msg), Some((stream, reattach)))),
+                Ok(None) => return None,
+                Ok(Some(Err(status))) if !is_disconnect(&status) => {
+                    return Some((Err(tonic_status_to_error(status)), None));
+                }
+                Ok(Some(Err(status))) => tonic_status_to_error(status),
+                Err(_) => ReattachError::Idle.into(),
+            };
+            match reattach(error).await {
+                Ok(reattached) => stream = reattached,
+                Err(e) => return Some((Err(e), None)),
+            }
+        }
+    })
 }
 
 impl<'a> BuckdClient<'a> {
@@ -225,10 +341,14 @@ impl<'a> BuckdClient<'a> {
 
     /// Some commands stream events back from the server.
     /// For these commands, we want to be able to manipulate CLI state.
+    ///
+    /// If `reattach` is set to the trace id of a reattachable command, reconnect to that command
+    /// when the stream is interrupted.
     async fn stream<'i, T, Res, Handler, Command>(
         &mut self,
         command: Command,
         request: T,
+        reattach: Option<String>,
         partial_result_handler: &mut Handler,
         console_interaction: Option<ConsoleInteractionStream<'i>>,
     ) -> anyhow::Result<CommandOutcome<Res>>
@@ -251,7 +371,12 @@ impl<'a> BuckdClient<'a> {
             .await
             .map_err(tonic_status_to_error)
             .context("Error dispatching request");
-        let stream = grpc_to_stream(response);
+        let stream = match reattach {
+            Some(trace_id) => {
+                reattaching_grpc_to_stream(client.clone(), trace_id, response).left_stream()
+            }
+            None => grpc_to_stream(response).right_stream(),
+        };
         pin_mut!(stream);
         events_ctx
             .unpack_stream(
@@ -263,12 +388,13 @@ impl<'a> BuckdClient<'a> {
             .await
     }
 
-    pub async fn status(&mut self, snapshot: bool) -> anyhow::Result<StatusResponse> {
+    pub async fn status(&mut self, snapshot: bool, dirty: bool) -> anyhow::Result<StatusResponse> {
         let outcome = self
             .events_ctx
             // Safe to unwrap tailers here because they are instantiated prior to a command being called.
             .unpack_oneshot(mem::take(&mut self.tailers), {
-                self.client.status(Request::new(StatusRequest { snapshot }))
+                self.client
+                    .status(Request::new(StatusRequest { snapshot, dirty }))
             })
             .await;
         // TODO(nmj): We have a number of things that wish to use status() and return an anyhow::Result,
@@ -350,6 +476,17 @@ impl PartialResultHandler for StdoutPartialResultHandler {
     }
 }
 
+/// The result of whichever command `attach` followed.
+pub struct AttachedCommandResult(pub command_result::Result);
+
+impl TryFrom<command_result::Result> for AttachedCommandResult {
+    type Error = command_result::Result;
+
+    fn try_from(v: command_result::Result) -> Result<Self, Self::Error> {
+        Ok(Self(v))
+    }
+}
+
 /// Implement a streaming method with full event reporting.
 macro_rules! stream_method {
     ($method: ident, $req: ty, $res: ty, $message: ty) => {
@@ -364,11 +501,17 @@ macro_rules! stream_method {
             handler: &mut impl PartialResultHandler<PartialResult = $message>,
         ) -> anyhow::Result<CommandOutcome<$res>> {
             self.enter()?;
+            let reattach = req
+                .client_context()
+                .ok()
+                .filter(|context| context.reattachable)
+                .map(|context| context.trace_id.clone());
             let res = self
                 .inner
                 .stream(
                     |d, r| Box::pin(DaemonApiClient::$grpc_method(d, r)),
                     req,
+                    reattach,
                     // For now we only support handlers that can be constructed like so, and we
                     // don't let anything go out. Eventually if we wanted to stream structured
                     // data, that could change.
@@ -402,6 +545,7 @@ macro_rules! bidirectional_stream_method {
                 .stream(
                     |d, r| Box::pin(DaemonApiClient::$method(d, r)),
                     req,
+                    None,
                     handler,
                     None,
                 )
@@ -589,6 +733,29 @@ impl<'a, 'b> FlushingBuckdClient<'a, 'b> {
     wrap_method!(set_log_filter(log_filter: SetLogFilterRequest), ());
     stream_method!(trace_io, TraceIoRequest, TraceIoResponse, NoPartialResult);
 
+    /// Follow a running reattachable command until it finishes.
+    pub async fn attach(
+        &mut self,
+        req: AttachRequest,
+        console_interaction: Option<ConsoleInteractionStream<'_>>,
+        handler: &mut impl PartialResultHandler<PartialResult = buck2_cli_proto::StdoutBytes>,
+    ) -> anyhow::Result<CommandOutcome<AttachedCommandResult>> {
+        self.enter()?;
+        let reattach = Some(req.trace_id.clone());
+        let res = self
+            .inner
+            .stream(
+                |d, r| Box::pin(DaemonApiClient::attach(d, r)),
+                req,
+                reattach,
+                handler,
+                console_interaction,
+            )
+            .await;
+        self.exit().await?;
+        res
+    }
+
     pub async fn new_generic(
         &mut self,
         context: buck2_cli_proto::ClientContext,
@@ -630,3 +797,121 @@ fn create_client_stream<
     };
     stream::once(async move { init_req }).chain(requests.map(|request| request.into()))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    use dupe::Dupe;
+
+    use super::*;
+
+    fn progress(span_id: u64) -> MultiCommandProgress {
+        MultiCommandProgress {
+            messages: vec![CommandProgress {
+                progress: Some(command_progress::Progress::Event(Box::new(
+                    buck2_data::BuckEvent {
+                        span_id,
+                        ..Default::default()
+                    },
+                ))),
+            }],
+        }
+    }
+
+    fn span_ids(results: Vec<anyhow::Result<MultiCommandProgress>>) -> Vec<anyhow::Result<u64>> {
+        results
+            .into_iter()
+            .map(|res| {
+                res.map(|p| match &p.messages[0].progress {
+                    Some(command_progress::Progress::Event(e)) => e.span_id,
+                    _ => panic!("unexpected progress"),
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_is_disconnect() {
+        assert!(is_disconnect(&Status::unavailable("connection reset")));
+        assert!(is_disconnect(&Status::cancelled("cancelled")));
+        assert!(!is_disconnect(&Status::unknown("error in the daemon")));
+        assert!(!is_disconnect(&Status::internal("error in the daemon")));
+    }
+
+    #[tokio::test]
+    async fn test_reattaching_stream_reattaches_on_disconnect() {
+        let reattached = Arc::new(AtomicUsize::new(0));
+        let stream = stream::iter(vec![
+            Ok(progress(1)),
+            Err(Status::unavailable("connection reset")),
+        ])
+        .boxed();
+        let results = reattaching_stream(stream, Duration::from_secs(60), {
+            let reattached = reattached.dupe();
+            move |_error| {
+                reattached.fetch_add(1, Ordering::SeqCst);
+                futures::future::ready(Ok(stream::iter(vec![Ok(progress(2))]).boxed()))
+            }
+        })
+        .collect::<Vec<_>>()
+        .await;
+
+        let span_ids: Vec<u64> = span_ids(results)
+            .into_iter()
+            .collect::<anyhow::Result<_>>()
+            .unwrap();
+        assert_eq!(span_ids, vec![1, 2]);
+        assert_eq!(reattached.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reattaching_stream_reattaches_when_idle() {
+        let stream = stream::pending().boxed();
+        let results = reattaching_stream(stream, Duration::from_millis(10), |error| {
+            assert!(format!("{:#}", error).contains("sent nothing"));
+            futures::future::ready(Ok(stream::iter(vec![Ok(progress(1))]).boxed()))
+        })
+        .collect::<Vec<_>>()
+        .await;
+
+        let span_ids: Vec<u64> = span_ids(results)
+            .into_iter()
+            .collect::<anyhow::Result<_>>()
+            .unwrap();
+        assert_eq!(span_ids, vec![1]);
+    }
+
+    #[tokio::test]
+    async fn test_reattaching_stream_forwards_daemon_errors() {
+        let stream = stream::iter(vec![Err(Status::internal("error in the daemon"))]).boxed();
+        let results = reattaching_stream(
+            stream,
+            Duration::from_secs(60),
+            |_error| -> futures::future::Ready<anyhow::Result<ProgressStream>> {
+                panic!("must not reattach on errors reported by the daemon")
+            },
+        )
+        .collect::<Vec<_>>()
+        .await;
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reattaching_stream_stops_when_reattach_fails() {
+        let stream = stream::iter(vec![Err(Status::unavailable("connection reset"))]).boxed();
+        let results = reattaching_stream(stream, Duration::from_secs(60), |error| {
+            futures::future::ready(Err(error.context("Error reattaching")))
+        })
+        .collect::<Vec<_>>()
+        .await;
+
+        assert_eq!(results.len(), 1);
+        let error = format!("{:#}", results[0].as_ref().unwrap_err());
+        assert!(error.contains("Error reattaching"), "{}", error);
+    }
+}
@@ -545,7 +545,8 @@ impl BootstrapBuckdClient {
     ) -> anyhow::Result<Self> {
         let daemon_dir = paths.daemon_dir()?;
 
-        buck2_core::fs::fs_util::create_dir_all(&daemon_dir.path)
+        daemon_dir
+            .create()
             .with_context(|| format!("Error creating daemon dir: {}", daemon_dir))?;
 
         let delete_commad = if cfg!(windows) {
@@ -1026,6 +1027,7 @@ async fn get_constraints(
         .unpack_oneshot(None, {
             client.status(tonic::Request::new(buck2_cli_proto::StatusRequest {
                 snapshot: false,
+                dirty: false,
             }))
         })
         .await?;
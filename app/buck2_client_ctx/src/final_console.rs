@@ -11,20 +11,35 @@ use superconsole::style::Color;
 use superconsole::style::ContentStyle;
 use superconsole::style::StyledContent;
 
+use crate::common::ui::ErrorFormat;
+use crate::subscribers::json_errors::error_json;
+
 /// A way to uniformly print to the console after a command has finished. This should
 /// only be used at the end of a command, after the event context from the buckd client
 /// is not available.
 pub struct FinalConsole {
     is_tty: bool,
+    error_format: ErrorFormat,
 }
 
 impl FinalConsole {
     pub fn new_with_tty() -> Self {
-        Self { is_tty: true }
+        Self {
+            is_tty: true,
+            error_format: ErrorFormat::Text,
+        }
     }
 
     pub fn new_without_tty() -> Self {
-        Self { is_tty: false }
+        Self {
+            is_tty: false,
+            error_format: ErrorFormat::Text,
+        }
+    }
+
+    pub fn with_error_format(mut self, error_format: ErrorFormat) -> Self {
+        self.error_format = error_format;
+        self
     }
 
     fn stderr_colored(&self, message: &str, color: Color) -> anyhow::Result<()> {
@@ -50,6 +65,14 @@ impl FinalConsole {
         self.stderr_colored(message, Color::DarkRed)
     }
 
+    /// Print an error the command failed with, as JSON with `--error-format json`.
+    pub fn print_error_report(&self, error: &buck2_data::ErrorReport) -> anyhow::Result<()> {
+        match self.error_format {
+            ErrorFormat::Text => self.print_error(&error.message),
+            ErrorFormat::Json => crate::eprintln!("{}", error_json(error)),
+        }
+    }
+
     /// Print the given message to stderr, in yellow if possible
     pub fn print_warning(&self, message: &str) -> anyhow::Result<()> {
         self.stderr_colored(message, Color::Yellow)
@@ -15,6 +15,7 @@ use anyhow::Context as _;
 use buck2_common::init::DaemonStartupConfig;
 use buck2_common::invocation_roots::find_invocation_roots;
 use buck2_common::legacy_configs::cells::BuckConfigBasedCells;
+use buck2_common::legacy_configs::key::BuckconfigKeyRef;
 use buck2_core::buck2_env;
 use buck2_core::cells::CellResolver;
 use buck2_core::fs::fs_util;
@@ -29,6 +30,7 @@ use prost::Message;
 struct ImmediateConfig {
     cell_resolver: CellResolver,
     daemon_startup_config: DaemonStartupConfig,
+    per_user_isolation_dir: bool,
 }
 
 impl ImmediateConfig {
@@ -47,6 +49,12 @@ impl ImmediateConfig {
             cell_resolver: cells.cell_resolver,
             daemon_startup_config: DaemonStartupConfig::new(root_config)
                 .context("Error loading daemon startup config")?,
+            per_user_isolation_dir: root_config
+                .parse(BuckconfigKeyRef {
+                    section: "buck2",
+                    property: "per_user_isolation_dir",
+                })?
+                .unwrap_or(false),
         })
     }
 }
@@ -56,6 +64,7 @@ impl ImmediateConfig {
 struct ImmediateConfigContextData {
     cell_resolver: CellResolver,
     daemon_startup_config: DaemonStartupConfig,
+    per_user_isolation_dir: bool,
     project_filesystem: ProjectRoot,
 }
 
@@ -91,6 +100,12 @@ impl<'a> ImmediateConfigContext<'a> {
         Ok(&self.data()?.daemon_startup_config)
     }
 
+    /// Whether the default isolation dir should include the name of the user, so that users
+    /// sharing a checkout don't share (and fight over) a daemon and buck-out. On by default.
+    pub fn per_user_isolation_dir(&self) -> anyhow::Result<bool> {
+        Ok(self.data()?.per_user_isolation_dir)
+    }
+
     /// Resolves an argument which can possibly be a cell-relative path.
     /// If the argument is not a cell-relative path, it returns `None`.
     /// Otherwise, it tries to resolve the cell and returns a `Result`.
@@ -155,6 +170,7 @@ impl<'a> ImmediateConfigContext<'a> {
                 anyhow::Ok(ImmediateConfigContextData {
                     cell_resolver: cfg.cell_resolver,
                     daemon_startup_config,
+                    per_user_isolation_dir: cfg.per_user_isolation_dir,
                     project_filesystem,
                 })
             })
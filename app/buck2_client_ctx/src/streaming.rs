@@ -17,6 +17,7 @@ use dupe::Dupe;
 
 use crate::client_ctx::ClientCommandContext;
 use crate::common::ui::CommonConsoleOptions;
+use crate::common::ui::ErrorFormat;
 use crate::common::CommonBuildConfigurationOptions;
 use crate::common::CommonEventLogOptions;
 use crate::common::CommonStarlarkOptions;
@@ -34,6 +35,7 @@ use crate::subscribers::get::try_get_build_graph_stats;
 use crate::subscribers::get::try_get_build_id_writer;
 use crate::subscribers::get::try_get_event_log_subscriber;
 use crate::subscribers::get::try_get_re_log_subscriber;
+use crate::subscribers::json_errors::JsonErrorConsole;
 use crate::subscribers::recorder::try_get_invocation_recorder;
 use crate::subscribers::subscriber::EventSubscriber;
 use crate::subscribers::subscribers::EventSubscribers;
@@ -55,7 +57,7 @@ fn default_subscribers<'a, T: StreamingCommand>(
         .daemon_startup_config()?
         .system_warning_config;
 
-    subscribers.push(get_console_with_root(
+    let console = get_console_with_root(
         ctx.trace_id.dupe(),
         console_opts.console_type,
         ctx.verbosity,
@@ -63,7 +65,11 @@ fn default_subscribers<'a, T: StreamingCommand>(
         None,
         T::COMMAND_NAME,
         console_opts.superconsole_config(system_warning_config.clone()),
-    )?);
+    )?;
+    subscribers.push(match console_opts.error_format {
+        ErrorFormat::Text => console,
+        ErrorFormat::Json => Box::new(JsonErrorConsole::new(console)),
+    });
 
     if let Some(event_log) = try_get_event_log_subscriber(cmd, ctx, log_size_counter_bytes.clone())?
     {
@@ -13,6 +13,7 @@ pub(crate) mod classify_server_stderr;
 pub(crate) mod errorconsole;
 pub mod event_log;
 pub mod get;
+pub(crate) mod json_errors;
 pub(crate) mod observer;
 pub mod re_log;
 pub mod recorder;
@@ -0,0 +1,175 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use buck2_cli_proto::command_result;
+use buck2_events::BuckEvent;
+
+use crate::subscribers::observer::ErrorObserver;
+use crate::subscribers::subscriber::EventSubscriber;
+use crate::subscribers::subscriber::Tick;
+
+/// Wraps a console to print the errors the command failed with as JSON for `--error-format json`,
+/// instead of letting the console print them as text.
+pub(crate) struct JsonErrorConsole {
+    console: Box<dyn EventSubscriber>,
+}
+
+impl JsonErrorConsole {
+    pub(crate) fn new(console: Box<dyn EventSubscriber>) -> Self {
+        Self { console }
+    }
+}
+
+/// The JSON object printed for an error with `--error-format json`.
+pub(crate) fn error_json(error: &buck2_data::ErrorReport) -> serde_json::Value {
+    serde_json::json!({
+        "message": error.message,
+        "tier": error
+            .tier
+            .and_then(buck2_data::error::ErrorTier::from_i32)
+            .map(|tier| tier.as_str_name()),
+        "tags": error
+            .tags
+            .iter()
+            .filter_map(|tag| buck2_data::error::ErrorTag::from_i32(*tag))
+            .map(|tag| tag.as_str_name())
+            .collect::<Vec<_>>(),
+        "diagnostic": error.diagnostic.as_ref().map(|d| serde_json::json!({
+            "path": d.path,
+            "begin_line": d.begin_line,
+            "begin_column": d.begin_column,
+            "end_line": d.end_line,
+            "end_column": d.end_column,
+            "code": d.code,
+            "suggestion": d.suggestion,
+        })),
+    })
+}
+
+#[async_trait]
+impl EventSubscriber for JsonErrorConsole {
+    async fn handle_output(&mut self, raw_output: &[u8]) -> anyhow::Result<()> {
+        self.console.handle_output(raw_output).await
+    }
+
+    async fn handle_tailer_stderr(&mut self, stderr: &str) -> anyhow::Result<()> {
+        self.console.handle_tailer_stderr(stderr).await
+    }
+
+    async fn handle_console_interaction(&mut self, c: char) -> anyhow::Result<()> {
+        self.console.handle_console_interaction(c).await
+    }
+
+    async fn handle_events(&mut self, events: &[Arc<BuckEvent>]) -> anyhow::Result<()> {
+        self.console.handle_events(events).await
+    }
+
+    async fn handle_command_result(
+        &mut self,
+        result: &buck2_cli_proto::CommandResult,
+    ) -> anyhow::Result<()> {
+        let Some(command_result::Result::Error(e)) = &result.result else {
+            return self.console.handle_command_result(result).await;
+        };
+        // The console still needs the result to finish rendering.
+        self.console
+            .handle_command_result(&buck2_cli_proto::CommandResult {
+                result: Some(command_result::Result::Error(
+                    buck2_cli_proto::CommandError { errors: Vec::new() },
+                )),
+            })
+            .await?;
+        for error in &e.errors {
+            crate::eprintln!("{}", error_json(error))?;
+        }
+        Ok(())
+    }
+
+    async fn handle_error(&mut self, error: &buck2_error::Error) -> anyhow::Result<()> {
+        self.console.handle_error(error).await
+    }
+
+    async fn tick(&mut self, tick: &Tick) -> anyhow::Result<()> {
+        self.console.tick(tick).await
+    }
+
+    async fn exit(&mut self) -> anyhow::Result<()> {
+        self.console.exit().await
+    }
+
+    fn as_error_observer(&self) -> Option<&dyn ErrorObserver> {
+        self.console.as_error_observer()
+    }
+
+    fn handle_daemon_connection_failure(&mut self, error: &buck2_error::Error) {
+        self.console.handle_daemon_connection_failure(error)
+    }
+
+    fn handle_daemon_started(&mut self, reason: buck2_data::DaemonWasStartedReason) {
+        self.console.handle_daemon_started(reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_json() {
+        let error = buck2_data::ErrorReport {
+            message: "Variable `srcs` not found".to_owned(),
+            tier: Some(buck2_data::error::ErrorTier::Input as i32),
+            tags: vec![buck2_data::error::ErrorTag::StarlarkFail as i32],
+            diagnostic: Some(buck2_data::ErrorDiagnostic {
+                path: "foo/BUCK".to_owned(),
+                begin_line: 3,
+                begin_column: 4,
+                end_line: 3,
+                end_column: 8,
+                code: "Variable".to_owned(),
+                suggestion: Some("srcs_".to_owned()),
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            error_json(&error),
+            serde_json::json!({
+                "message": "Variable `srcs` not found",
+                "tier": "INPUT",
+                "tags": ["STARLARK_FAIL"],
+                "diagnostic": {
+                    "path": "foo/BUCK",
+                    "begin_line": 3,
+                    "begin_column": 4,
+                    "end_line": 3,
+                    "end_column": 8,
+                    "code": "Variable",
+                    "suggestion": "srcs_",
+                },
+            })
+        );
+
+        let error = buck2_data::ErrorReport {
+            message: "Build failed".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(
+            error_json(&error),
+            serde_json::json!({
+                "message": "Build failed",
+                "tier": null,
+                "tags": [],
+                "diagnostic": null,
+            })
+        );
+    }
+}
@@ -21,11 +21,14 @@ use buck2_event_observer::display;
 use buck2_event_observer::display::display_file_watcher_end;
 use buck2_event_observer::display::TargetDisplayOptions;
 use buck2_event_observer::event_observer::DebugEventObserverExtra;
+use buck2_event_observer::fmt_duration;
 use buck2_event_observer::session_info::SessionInfo;
+use buck2_event_observer::span_tracker::BuckEventSpanHandle;
 use buck2_event_observer::verbosity::Verbosity;
 use buck2_event_observer::what_ran;
 use buck2_event_observer::what_ran::command_to_string;
 use buck2_event_observer::what_ran::worker_command_as_fallback_to_string;
+use buck2_event_observer::what_ran::CommandReproducer;
 use buck2_event_observer::what_ran::WhatRanOptions;
 use buck2_event_observer::what_ran::WhatRanOptionsRegex;
 use buck2_events::BuckEvent;
@@ -78,6 +81,14 @@ pub mod timed_list;
 
 const SUPERCONSOLE_WIDTH: usize = 300;
 
+/// How many lines of the stderr of a failed action are shown with `--ui collapsestderr`. The full
+/// stderr of the last failure can be shown with `f`, and the full stderr of every failure with
+/// `buck2 log what-failed`.
+const FAILURE_STDERR_LINES: usize = 50;
+
+/// How many actions `l` shows.
+const LONGEST_RUNNING_ACTIONS: usize = 5;
+
 pub const CUTOFFS: Cutoffs = Cutoffs {
     inform: Duration::from_secs(4),
     warn: Duration::from_secs(8),
@@ -89,6 +100,10 @@ pub struct StatefulSuperConsole {
     state: SuperConsoleState,
     super_console: Option<SuperConsole>,
     verbosity: Verbosity,
+    /// When set, events are still processed, but the console is not redrawn.
+    paused: bool,
+    /// The identity and stderr of the last action which failed.
+    last_failure: Option<(String, String)>,
 }
 
 #[derive(Copy, Clone, Dupe, Debug)]
@@ -139,6 +154,8 @@ pub struct SuperConsoleConfig {
     /// Two lines for root events with single child event.
     pub two_lines: bool,
     pub max_lines: usize,
+    /// Only show the first lines of the stderr of failed actions.
+    pub collapse_failure_stderr: bool,
     pub system_warning_config: SystemWarningConfig,
 }
 
@@ -154,6 +171,7 @@ impl Default for SuperConsoleConfig {
             display_platform: false,
             two_lines: false,
             max_lines: 10,
+            collapse_failure_stderr: false,
             system_warning_config: SystemWarningConfig::default(),
         }
     }
@@ -318,6 +336,8 @@ impl StatefulSuperConsole {
             state: SuperConsoleState::new(replay_speed, trace_id, verbosity, expect_spans, config)?,
             super_console: Some(super_console),
             verbosity,
+            paused: false,
+            last_failure: None,
         })
     }
 
@@ -402,6 +422,94 @@ impl StatefulSuperConsole {
         self.handle_stderr(&format!("{what}: {on_off}, press `{key}` to revert"))
             .await
     }
+
+    fn render(&mut self) -> anyhow::Result<()> {
+        match &mut self.super_console {
+            Some(super_console) => super_console.render(&BuckRootComponent {
+                header: &self.header,
+                state: &self.state,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// The full stderr of the last failed action, which may be collapsed when it is first shown.
+    fn last_failure_lines(&self) -> Lines {
+        let Some((action_id, stderr)) = &self.last_failure else {
+            return Lines(vec![Line::sanitized("No action failed yet")]);
+        };
+        let mut lines = vec![Line::from_iter([Span::new_styled_lossy(
+            format!("Full stderr for {}:", action_id)
+                .with(Color::White)
+                .attribute(Attribute::Bold),
+        )])];
+        lines.extend(Lines::from_colored_multiline_string(stderr));
+        Lines(lines)
+    }
+
+    /// The actions which have been running for the longest, with the command they are running.
+    fn longest_running_actions_lines(&self) -> anyhow::Result<Lines> {
+        let mut actions: Vec<BuckEventSpanHandle> = self
+            .state
+            .simple_console
+            .observer()
+            .spans()
+            .iter_roots()
+            .filter(|root| {
+                matches!(
+                    root.info()
+                        .event
+                        .span_start_event()
+                        .and_then(|span| span.data.as_ref()),
+                    Some(buck2_data::span_start_event::Data::ActionExecution(..))
+                )
+            })
+            .collect();
+        if actions.is_empty() {
+            return Ok(Lines(vec![Line::sanitized("No action is running")]));
+        }
+        actions.sort_by_key(|action| action.info().start);
+
+        let display_platform = self.state.config.display_platform;
+        let mut lines = vec![Line::from_iter([Span::new_styled_lossy(
+            "Longest running actions:"
+                .to_owned()
+                .with(Color::White)
+                .attribute(Attribute::Bold),
+        )])];
+        for action in actions.iter().take(LONGEST_RUNNING_ACTIONS) {
+            let info = action.info();
+            lines.push(Line::sanitized(&format!(
+                "{} {}",
+                fmt_duration::fmt_duration(info.start.elapsed(), self.state.time_speed.speed()),
+                display::display_event(
+                    &info.event,
+                    TargetDisplayOptions::for_console(display_platform)
+                )?,
+            )));
+            if let Some(command) = running_command(action) {
+                lines.push(Line::sanitized(&format!("  {}", command)));
+            }
+        }
+        Ok(Lines(lines))
+    }
+}
+
+/// The command run by a span or any of its descendants, if it is known yet.
+fn running_command(span: &BuckEventSpanHandle) -> Option<String> {
+    for child in span.children() {
+        if let Some(command) =
+            CommandReproducer::from_buck_data(child.info().event.data(), &WhatRanOptions::default())
+        {
+            let human_readable = command.as_human_readable().to_string();
+            let human_readable = truncate(&human_readable).unwrap_or(human_readable);
+            return Some(format!("{}: {}", command.executor(), human_readable));
+        }
+        if let Some(command) = running_command(&child) {
+            return Some(command);
+        }
+    }
+    None
 }
 
 // TODO(brasselsprouts): after deprecating filetailers, simplify these code paths
@@ -546,6 +654,26 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
         } else if c == 'c' {
             self.toggle("Commands", 'c', |s| &mut s.state.config.enable_commands)
                 .await?;
+        } else if c == 'f' {
+            let lines = self.last_failure_lines();
+            if let Some(super_console) = &mut self.super_console {
+                super_console.emit(lines);
+            }
+        } else if c == 'l' {
+            let lines = self.longest_running_actions_lines()?;
+            if let Some(super_console) = &mut self.super_console {
+                super_console.emit(lines);
+            }
+        } else if c == ' ' {
+            self.paused = !self.paused;
+            if self.paused {
+                self.handle_stderr("Rendering paused, press `space` to resume")
+                    .await?;
+                // Later ticks don't draw, so draw the message now.
+                self.render()?;
+            } else {
+                self.handle_stderr("Rendering resumed").await?;
+            }
         } else if c == '+' {
             self.state.config.max_lines = self.state.config.max_lines.saturating_add(1);
         } else if c == '-' {
@@ -560,6 +688,9 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
                 `r` = toggle detailed RE\n\
                 `i` = toggle I/O counters\n\
                 `p` = display target configurations\n\
+                `f` = show the full stderr of the last failed action\n\
+                `l` = show the longest running actions and their commands\n\
+                `space` = pause or resume rendering\n\
                 `+` = show more lines\n\
                 `-` = show fewer lines\n\
                 `h` = show this help",
@@ -593,16 +724,11 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
     }
 
     async fn tick(&mut self, tick: &Tick) -> anyhow::Result<()> {
-        match &mut self.super_console {
-            Some(super_console) => {
-                self.state.current_tick = tick.dupe();
-                super_console.render(&BuckRootComponent {
-                    header: &self.header,
-                    state: &self.state,
-                })
-            }
-            None => Ok(()),
+        self.state.current_tick = tick.dupe();
+        if self.paused {
+            return Ok(());
         }
+        self.render()
     }
 
     async fn handle_error(&mut self, _error: &buck2_error::Error) -> anyhow::Result<()> {
@@ -739,7 +865,7 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
                     attributes: Attribute::Bold.into(),
                     ..Default::default()
                 },
-                format!("Action failed: {}", action_id,),
+                format!("Action failed: {}", action_id),
             ),
         )]));
 
@@ -748,7 +874,14 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
         )]));
 
         if let Some(command) = command {
-            lines_for_command_details(&command, self.verbosity, &mut lines);
+            lines_for_command_details(
+                &command,
+                self.verbosity,
+                self.state.config.collapse_failure_stderr,
+                &self.state.session_info().trace_id,
+                &mut lines,
+            );
+            self.last_failure = Some((action_id, command.stderr.clone()));
         }
 
         super_console.emit(Lines(lines));
@@ -810,6 +943,8 @@ impl UnpackingEventSubscriber for StatefulSuperConsole {
 fn lines_for_command_details(
     command_failed: &CommandExecutionDetails,
     verbosity: Verbosity,
+    collapse_stderr: bool,
+    trace_id: &TraceId,
     lines: &mut Vec<Line>,
 ) {
     if let Some(command_kind) = command_failed.command_kind.as_ref() {
@@ -903,7 +1038,23 @@ fn lines_for_command_details(
             .with(Color::DarkRed)
             .attribute(Attribute::Bold),
     )]));
-    lines.extend(Lines::from_colored_multiline_string(&command_failed.stderr));
+    let stderr = Lines::from_colored_multiline_string(&command_failed.stderr);
+    let omitted = stderr.len().saturating_sub(FAILURE_STDERR_LINES);
+    if !collapse_stderr || omitted == 0 || verbosity.print_failure_full_command() {
+        lines.extend(stderr);
+    } else {
+        // Compilers tend to print the most relevant error first.
+        lines.extend(stderr.into_iter().take(FAILURE_STDERR_LINES));
+        // The event log keeps the full stderr, so it can be shown after the build too.
+        lines.push(Line::from_iter([Span::new_styled_lossy(
+            format!(
+                "... {} more lines, press `f` to show the full stderr, or run \
+                `buck2 log what-failed --show-std-err --trace-id {}`",
+                omitted, trace_id
+            )
+            .italic(),
+        )]));
+    }
 }
 
 // Truncates a string to a reasonable number characters, or returns None if it doesn't need truncating.
@@ -1103,6 +1254,47 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_failure_stderr_is_collapsed() {
+        let details = CommandExecutionDetails {
+            stderr: (0..FAILURE_STDERR_LINES + 10)
+                .map(|i| format!("error {}\n", i))
+                .collect(),
+            ..Default::default()
+        };
+
+        let trace_id = TraceId::new();
+
+        // Collapsing is opt-in.
+        let mut lines = Vec::new();
+        lines_for_command_details(&details, Verbosity::default(), false, &trace_id, &mut lines);
+        assert_eq!(lines.last().unwrap().to_unstyled(), "error 59");
+
+        let mut lines = Vec::new();
+        lines_for_command_details(&details, Verbosity::default(), true, &trace_id, &mut lines);
+        let last = lines.last().unwrap().to_unstyled();
+        assert!(last.contains("10 more lines"), "{}", last);
+        assert!(
+            last.contains(&format!(
+                "buck2 log what-failed --show-std-err --trace-id {}",
+                trace_id
+            )),
+            "{}",
+            last
+        );
+        assert!(!lines.iter().any(|l| l.to_unstyled() == "error 50"));
+
+        let mut lines = Vec::new();
+        lines_for_command_details(
+            &details,
+            Verbosity::try_from_cli("full_failed_command").unwrap(),
+            true,
+            &trace_id,
+            &mut lines,
+        );
+        assert_eq!(lines.last().unwrap().to_unstyled(), "error 59");
+    }
+
     #[test]
     fn test_session_info() -> anyhow::Result<()> {
         let info = SessionInfo {
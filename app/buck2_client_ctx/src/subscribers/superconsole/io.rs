@@ -124,6 +124,13 @@ fn do_render(
             snapshot.deferred_materializer_queue_size
         ));
     }
+    if snapshot.deferred_materializer_materializations_waiting > 0 {
+        parts.push(format!(
+            "Materializing = {} ({} waiting)",
+            snapshot.deferred_materializer_materializations_in_flight,
+            snapshot.deferred_materializer_materializations_waiting
+        ));
+    }
     if snapshot.blocking_executor_io_queue_size > 0 {
         parts.push(format!(
             "IO Queue = {}",
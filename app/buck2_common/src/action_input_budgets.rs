@@ -0,0 +1,196 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Limits on the number and total size of the inputs of run actions, per action category. An
+//! action over its budget is almost certainly a rule bug (e.g. a link action with hundreds of
+//! thousands of inputs), and fails rather than building a huge merkle tree for remote execution.
+//! The root buckconfig `build.action_input_budgets` lists the limits on the number of inputs
+//! comma-separated as `category=max_inputs`, and `build.action_input_mebibyte_budgets` the limits
+//! on their total size as `category=max_mebibytes`.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use buck2_futures::cancellation::CancellationContext;
+use derive_more::Display;
+use dice::DiceComputations;
+use dice::Key;
+use dupe::Dupe;
+
+use crate::dice::cells::HasCellResolver;
+use crate::legacy_configs::dice::HasLegacyConfigs;
+use crate::legacy_configs::key::BuckconfigKeyRef;
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(input)]
+enum ActionInputBudgetsError {
+    #[error("Expected `category={0}` in `build.{1}`, got `{2}`")]
+    InvalidBudget(&'static str, &'static str, String),
+}
+
+const COUNT_PROPERTY: &str = "action_input_budgets";
+const SIZE_PROPERTY: &str = "action_input_mebibyte_budgets";
+
+#[derive(PartialEq, Debug, Default, Allocative)]
+pub struct ActionInputBudgets {
+    max_inputs: BTreeMap<String, u64>,
+    max_input_bytes: BTreeMap<String, u64>,
+}
+
+fn parse_budgets(
+    s: &str,
+    property: &'static str,
+    unit: &'static str,
+) -> anyhow::Result<BTreeMap<String, u64>> {
+    let mut budgets = BTreeMap::new();
+    for budget in s.split(',') {
+        let budget = budget.trim();
+        if budget.is_empty() {
+            continue;
+        }
+        let (category, max) = budget
+            .split_once('=')
+            .and_then(|(category, max)| Some((category.trim(), max.trim().parse::<u64>().ok()?)))
+            .ok_or_else(|| {
+                ActionInputBudgetsError::InvalidBudget(unit, property, budget.to_owned())
+            })?;
+        budgets.insert(category.to_owned(), max);
+    }
+    Ok(budgets)
+}
+
+impl ActionInputBudgets {
+    /// Parses the values of `build.action_input_budgets` and
+    /// `build.action_input_mebibyte_budgets`.
+    pub fn new(counts: Option<&str>, sizes: Option<&str>) -> anyhow::Result<Self> {
+        let mut budgets = Self::default();
+        if let Some(counts) = counts {
+            budgets.max_inputs = parse_budgets(counts, COUNT_PROPERTY, "max_inputs")?;
+        }
+        if let Some(sizes) = sizes {
+            budgets.max_input_bytes = parse_budgets(sizes, SIZE_PROPERTY, "max_mebibytes")?
+                .into_iter()
+                .map(|(category, mebibytes)| (category, mebibytes.saturating_mul(1024 * 1024)))
+                .collect();
+        }
+        Ok(budgets)
+    }
+
+    /// The maximum number of inputs of an action in `category`, if it has a budget.
+    pub fn max_inputs(&self, category: &str) -> Option<u64> {
+        self.max_inputs.get(category).copied()
+    }
+
+    /// The maximum total size in bytes of the inputs of an action in `category`, if it has a
+    /// budget.
+    pub fn max_input_bytes(&self, category: &str) -> Option<u64> {
+        self.max_input_bytes.get(category).copied()
+    }
+}
+
+#[derive(Hash, Eq, PartialEq, Clone, Dupe, Display, Debug, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct ActionInputBudgetsKey;
+
+#[async_trait]
+impl Key for ActionInputBudgetsKey {
+    type Value = buck2_error::Result<Option<Arc<ActionInputBudgets>>>;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        let root_cell = ctx.get_cell_resolver().await?.root_cell();
+        let counts = ctx
+            .get_legacy_config_property(
+                root_cell,
+                BuckconfigKeyRef {
+                    section: "build",
+                    property: COUNT_PROPERTY,
+                },
+            )
+            .await?;
+        let sizes = ctx
+            .get_legacy_config_property(
+                root_cell,
+                BuckconfigKeyRef {
+                    section: "build",
+                    property: SIZE_PROPERTY,
+                },
+            )
+            .await?;
+        if counts.is_none() && sizes.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Arc::new(ActionInputBudgets::new(
+            counts.as_deref(),
+            sizes.as_deref(),
+        )?)))
+    }
+
+    fn validity(x: &Self::Value) -> bool {
+        x.is_ok()
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        match (x, y) {
+            (Ok(x), Ok(y)) => x == y,
+            _ => false,
+        }
+    }
+}
+
+#[async_trait]
+pub trait HasActionInputBudgets {
+    /// The input budgets of run actions, if any are configured.
+    async fn action_input_budgets(
+        &mut self,
+    ) -> buck2_error::Result<Option<Arc<ActionInputBudgets>>>;
+}
+
+#[async_trait]
+impl HasActionInputBudgets for DiceComputations<'_> {
+    async fn action_input_budgets(
+        &mut self,
+    ) -> buck2_error::Result<Option<Arc<ActionInputBudgets>>> {
+        self.compute(&ActionInputBudgetsKey).await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_input_budgets() {
+        let budgets = ActionInputBudgets::new(
+            Some("cxx_link=200000, rust_link = 100000,"),
+            Some("cxx_link=2048"),
+        )
+        .unwrap();
+        assert_eq!(Some(200000), budgets.max_inputs("cxx_link"));
+        assert_eq!(Some(100000), budgets.max_inputs("rust_link"));
+        assert_eq!(None, budgets.max_inputs("cxx_compile"));
+        assert_eq!(
+            Some(2048 * 1024 * 1024),
+            budgets.max_input_bytes("cxx_link")
+        );
+        assert_eq!(None, budgets.max_input_bytes("rust_link"));
+    }
+
+    #[test]
+    fn test_action_input_budgets_invalid() {
+        assert!(ActionInputBudgets::new(Some("cxx_link"), None).is_err());
+        assert!(ActionInputBudgets::new(Some("cxx_link=lots"), None).is_err());
+        assert!(ActionInputBudgets::new(None, Some("cxx_link=2GB")).is_err());
+    }
+}
@@ -171,7 +171,7 @@ pub enum DigestAlgorithm {
 }
 
 impl DigestAlgorithm {
-    fn kind(self) -> DigestAlgorithmKind {
+    pub fn kind(self) -> DigestAlgorithmKind {
         match self {
             Self::Sha1 => DigestAlgorithmKind::Sha1,
             Self::Sha256 => DigestAlgorithmKind::Sha256,
@@ -391,6 +391,17 @@ impl DataDigester {
         self.size += data.len() as u64;
     }
 
+    /// Like `update`, but BLAKE3 hashes `data` on the threads of the current rayon thread pool.
+    /// This is only worth it for large inputs.
+    pub fn update_parallel(&mut self, data: &[u8]) {
+        if let DigesterVariant::Blake3(h) | DigesterVariant::Blake3Keyed(h) = &mut self.variant {
+            h.update_rayon(data);
+            self.size += data.len() as u64;
+        } else {
+            self.update(data);
+        }
+    }
+
     pub fn finalize(self) -> CasDigestData {
         match self.variant {
             DigesterVariant::Sha1(h) => CasDigestData::new_sha1(h.finalize().into(), self.size),
@@ -421,6 +432,10 @@ impl<Kind: CasDigestKind> Digester<Kind> {
         self.data.update(data);
     }
 
+    pub fn update_parallel(&mut self, data: &[u8]) {
+        self.data.update_parallel(data);
+    }
+
     pub fn finalize(self) -> CasDigest<Kind> {
         CasDigest {
             data: self.data.finalize(),
@@ -7,9 +7,20 @@
  * of this source tree.
  */
 
+use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::paths::file_name::FileName;
 
+#[cfg(unix)]
+#[derive(Debug, buck2_error::Error)]
+enum DaemonDirError {
+    #[error(
+        "Daemon dir `{0}` is owned by uid {1}, not by the current user (uid {2}). If `HOME` is \
+        shared with another user, set it to a directory of your own"
+    )]
+    NotOwned(AbsNormPathBuf, u32, u32),
+}
+
 /// `~/.buck/buckd/repo-path` directory.
 #[derive(Debug, Clone, derive_more::Display)]
 #[display(fmt = "{}", path.display())]
@@ -18,6 +29,37 @@ pub struct DaemonDir {
 }
 
 impl DaemonDir {
+    /// Creates the daemon dir if needed, and makes sure that no other user can read it: it holds
+    /// the token which authenticates clients to the daemon.
+    pub fn create(&self) -> anyhow::Result<()> {
+        #[cfg(unix)]
+        {
+            self.create_for_uid(nix::unistd::geteuid().as_raw())
+        }
+        #[cfg(not(unix))]
+        {
+            fs_util::create_dir_all(&self.path)?;
+            Ok(())
+        }
+    }
+
+    /// Creates the daemon dir of the user `uid`, which must own it.
+    #[cfg(unix)]
+    fn create_for_uid(&self, uid: u32) -> anyhow::Result<()> {
+        use std::os::unix::fs::MetadataExt;
+        use std::os::unix::fs::PermissionsExt;
+
+        fs_util::create_dir_all(&self.path)?;
+        let metadata = fs_util::metadata(&self.path)?;
+        if metadata.uid() != uid {
+            return Err(DaemonDirError::NotOwned(self.path.clone(), metadata.uid(), uid).into());
+        }
+        if metadata.mode() & 0o077 != 0 {
+            fs_util::set_permissions(&self.path, std::fs::Permissions::from_mode(0o700))?;
+        }
+        Ok(())
+    }
+
     /// Path to `buckd.info` file.
     pub fn buckd_info(&self) -> AbsNormPathBuf {
         self.path.join(FileName::new("buckd.info").unwrap())
@@ -38,3 +80,55 @@ impl DaemonDir {
         self.path.join(FileName::new("buckd.pid").unwrap())
     }
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    fn daemon_dir(tempdir: &tempfile::TempDir) -> DaemonDir {
+        DaemonDir {
+            path: AbsNormPathBuf::new(tempdir.path().join("buckd").join("repo")).unwrap(),
+        }
+    }
+
+    fn mode(dir: &DaemonDir) -> u32 {
+        fs_util::metadata(&dir.path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn test_create() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = daemon_dir(&tempdir);
+        dir.create().unwrap();
+        assert_eq!(0o700, mode(&dir));
+        // Creating it again is fine.
+        dir.create().unwrap();
+        assert_eq!(0o700, mode(&dir));
+    }
+
+    #[test]
+    fn test_create_restricts_permissions() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = daemon_dir(&tempdir);
+        fs_util::create_dir_all(&dir.path).unwrap();
+        fs_util::set_permissions(&dir.path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        dir.create().unwrap();
+        assert_eq!(0o700, mode(&dir));
+    }
+
+    #[test]
+    fn test_create_not_owned() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let dir = daemon_dir(&tempdir);
+        fs_util::create_dir_all(&dir.path).unwrap();
+        fs_util::set_permissions(&dir.path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let other_uid = nix::unistd::geteuid().as_raw() + 1;
+        let err = dir.create_for_uid(other_uid).unwrap_err();
+        assert!(err.to_string().contains("is owned by uid"), "{}", err);
+        // The permissions of a directory owned by someone else are left alone.
+        assert_eq!(0o755, mode(&dir));
+    }
+}
@@ -102,15 +102,26 @@ impl Key for CellAliasResolverKey {
         let root_aliases = resolver
             .root_cell_instance()
             .non_external_cell_alias_resolver();
+        let self_alias = root_aliases
+            .mappings()
+            .find(|(_, name)| *name == self.0)
+            .map(|(alias, _)| alias.clone());
         let config = ctx.get_legacy_config_for_cell(self.0).await?;
+        let root_config = ctx.get_legacy_config_for_cell(resolver.root_cell()).await?;
         // Cell alias resolvers that are parsed within dice differ from those outside of dice in
-        // that they cannot create new cells, and so respect only their `cell_aliases` section, not
-        // their `cells` section. This is the expected behavior for external cells, moving other
-        // cell resolver parsing into dice would require this code to be adjusted.
+        // that they cannot create new cells, and so respect only their `cell_aliases` section, and
+        // the entries of their `cells` section pointing at themselves. This is the expected
+        // behavior for external cells, moving other cell resolver parsing into dice would require
+        // this code to be adjusted.
         CellAliasResolver::new_for_dice_parsed_cell(
             self.0,
             root_aliases,
-            BuckConfigBasedCells::get_cell_aliases_from_config(&config)?,
+            BuckConfigBasedCells::get_external_cell_aliases_from_config(
+                self.0,
+                self_alias.as_ref(),
+                &config,
+                &root_config,
+            )?,
         )
         .map_err(Into::into)
     }
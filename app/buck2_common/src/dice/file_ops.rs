@@ -17,6 +17,7 @@ use async_trait::async_trait;
 use buck2_core::cells::cell_path::CellPath;
 use buck2_core::cells::cell_path::CellPathRef;
 use buck2_core::cells::name::CellName;
+use buck2_core::cells::paths::CellRelativePathBuf;
 use buck2_core::fs::paths::file_name::FileNameBuf;
 use buck2_futures::cancellation::CancellationContext;
 use cmp_any::PartialEqAny;
@@ -37,6 +38,7 @@ use crate::file_ops::RawPathMetadata;
 use crate::file_ops::ReadDirOutput;
 use crate::ignores::file_ignores::FileIgnoreResult;
 use crate::io::ReadDirError;
+use crate::pattern::recursive_excludes::HasRecursivePatternExcludes;
 
 pub mod delegate;
 
@@ -143,6 +145,13 @@ impl DiceFileComputations {
     ) -> anyhow::Result<Arc<[FileNameBuf]>> {
         ctx.get_buildfiles(cell).await
     }
+
+    pub async fn recursive_pattern_excludes(
+        ctx: &mut DiceComputations<'_>,
+        cell: CellName,
+    ) -> anyhow::Result<Arc<[CellRelativePathBuf]>> {
+        ctx.get_recursive_pattern_excludes(cell).await
+    }
 }
 
 #[derive(Debug, Display, Clone, Dupe, Copy, PartialEq, Eq, Hash, Allocative)]
@@ -372,6 +381,13 @@ impl FileOps for DiceFileOps<'_, '_> {
     async fn buildfiles<'a>(&self, cell: CellName) -> anyhow::Result<Arc<[FileNameBuf]>> {
         DiceFileComputations::buildfiles(&mut self.0.get(), cell).await
     }
+
+    async fn recursive_pattern_excludes(
+        &self,
+        cell: CellName,
+    ) -> anyhow::Result<Arc<[CellRelativePathBuf]>> {
+        DiceFileComputations::recursive_pattern_excludes(&mut self.0.get(), cell).await
+    }
 }
 
 fn extended_ignore_error<'a>(
@@ -0,0 +1,105 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Hashing of large files, which otherwise dominates the time spent hashing multi-GB outputs:
+//! they are memory-mapped rather than read through a buffer, and BLAKE3 hashes them on several
+//! threads. This is only used for the outputs Buck2 wrote under buck-out: user files are always
+//! read through a buffer.
+
+use std::fs::File;
+use std::sync::OnceLock;
+
+use crate::cas_digest::CasDigestConfig;
+use crate::file_ops::FileDigest;
+
+/// Files smaller than this are read through a buffer: mapping them costs more than it saves, and
+/// BLAKE3 can't split them across many threads.
+const LARGE_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+/// `None` if the thread pool couldn't be created, in which case large files are hashed on the
+/// calling thread.
+static THREAD_POOL: OnceLock<Option<rayon::ThreadPool>> = OnceLock::new();
+
+fn build_thread_pool(threads: usize) -> Option<rayon::ThreadPool> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("buck2-hash-{}", i))
+        .build()
+        .map_err(|e| tracing::warn!("Error creating hashing thread pool: {:#}", e))
+        .ok()
+}
+
+/// Sets how many threads hash large files at once, across all files. This must be called before
+/// the first large file is hashed, which is otherwise done with one thread per CPU.
+pub fn init_hashing_threads(threads: usize) {
+    if THREAD_POOL.set(build_thread_pool(threads)).is_err() {
+        tracing::warn!("Hashing threads were already initialized");
+    }
+}
+
+/// The digest of `file` if it is large, or `None` if it should be read through a buffer instead.
+/// `file` must be an output under buck-out.
+pub(crate) fn digest_large_file(
+    file: &File,
+    config: CasDigestConfig,
+) -> anyhow::Result<Option<FileDigest>> {
+    if file.metadata()?.len() < LARGE_FILE_SIZE {
+        return Ok(None);
+    }
+
+    // SAFETY: truncating the file while it is mapped kills the process with SIGBUS (rather than
+    // giving a meaningless digest, as it does when reading it). The callers only pass outputs
+    // under buck-out, which Buck2 doesn't modify while it hashes them, never user files that an
+    // editor could truncate at any time.
+    let mmap = unsafe { memmap2::Mmap::map(file)? };
+
+    let mut digester = FileDigest::digester(config);
+    match THREAD_POOL.get_or_init(|| build_thread_pool(0)) {
+        Some(pool) => pool.install(|| digester.update_parallel(&mmap)),
+        None => digester.update(&mmap),
+    }
+    Ok(Some(digester.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::cas_digest::DigestAlgorithm;
+
+    #[test]
+    fn test_digest_large_file() -> anyhow::Result<()> {
+        let content: Vec<u8> = (0..LARGE_FILE_SIZE + 1).map(|i| (i % 251) as u8).collect();
+        let mut file = tempfile::tempfile()?;
+        file.write_all(&content)?;
+
+        for config in [
+            CasDigestConfig::testing_default(),
+            CasDigestConfig::leak_new(vec![DigestAlgorithm::Blake3], None)?,
+        ] {
+            assert_eq!(
+                digest_large_file(&file, config)?,
+                Some(FileDigest::from_content(&content, config))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_small_file_is_not_mapped() -> anyhow::Result<()> {
+        let mut file = tempfile::tempfile()?;
+        file.write_all(b"small")?;
+        assert_eq!(
+            digest_large_file(&file, CasDigestConfig::testing_default())?,
+            None
+        );
+        Ok(())
+    }
+}
@@ -17,6 +17,7 @@ use buck2_core::buck2_env;
 use buck2_core::cells::cell_path::CellPath;
 use buck2_core::cells::cell_path::CellPathRef;
 use buck2_core::cells::name::CellName;
+use buck2_core::cells::paths::CellRelativePathBuf;
 use buck2_core::fs::paths::abs_path::AbsPath;
 use buck2_core::fs::paths::file_name::FileName;
 use buck2_core::fs::paths::file_name::FileNameBuf;
@@ -31,6 +32,7 @@ use crate::cas_digest::CasDigestConfig;
 use crate::cas_digest::CasDigestKind;
 use crate::cas_digest::TrackedCasDigest;
 use crate::external_symlink::ExternalSymlink;
+use crate::file_hashing::digest_large_file;
 use crate::ignores::file_ignores::FileIgnoreResult;
 
 #[derive(Debug, buck2_error::Error)]
@@ -218,6 +220,23 @@ impl FileDigest {
         let f = File::open(file.as_maybe_relativized())?;
         FileDigest::from_reader(f, config.as_cas_digest_config())
     }
+
+    /// Obtain the digest of an output Buck2 wrote under buck-out, like `from_file`. Large files
+    /// are hashed from memory on several threads, which isn't safe for user files (see
+    /// `digest_large_file`).
+    pub fn from_output_file(file: &AbsPath, config: FileDigestConfig) -> anyhow::Result<Self> {
+        if !buck2_env!("BUCK2_DISABLE_FILE_ATTR", bool)? {
+            if let Some(digest) = Self::from_file_attr(file, config) {
+                return Ok(digest);
+            }
+        }
+
+        let f = File::open(file.as_maybe_relativized())?;
+        if let Some(digest) = digest_large_file(&f, config.as_cas_digest_config())? {
+            return Ok(digest);
+        }
+        FileDigest::from_reader(f, config.as_cas_digest_config())
+    }
 }
 
 /// Stores the relevant metadata for a file.
@@ -354,6 +373,12 @@ pub trait FileOps: Send + Sync {
     fn eq_token(&self) -> PartialEqAny;
 
     async fn buildfiles<'a>(&self, cell: CellName) -> anyhow::Result<Arc<[FileNameBuf]>>;
+
+    /// Directories of `cell` that recursive patterns don't descend into.
+    async fn recursive_pattern_excludes(
+        &self,
+        cell: CellName,
+    ) -> anyhow::Result<Arc<[CellRelativePathBuf]>>;
 }
 
 impl dyn FileOps + '_ {
@@ -390,6 +415,7 @@ pub mod testing {
     use buck2_core::cells::cell_path::CellPathRef;
     use buck2_core::cells::name::CellName;
     use buck2_core::cells::paths::CellRelativePath;
+    use buck2_core::cells::paths::CellRelativePathBuf;
     use buck2_core::fs::paths::file_name::FileNameBuf;
     use cmp_any::PartialEqAny;
     use dice::testing::DiceBuilder;
@@ -424,6 +450,7 @@ pub mod testing {
     pub struct TestFileOps {
         #[allocative(skip)]
         entries: Arc<BTreeMap<CellPath, TestFileOpsEntry>>,
+        recursive_pattern_excludes: Arc<[CellRelativePathBuf]>,
     }
 
     impl TestFileOps {
@@ -464,6 +491,7 @@ pub mod testing {
             }
             TestFileOps {
                 entries: Arc::new(entries),
+                recursive_pattern_excludes: Arc::from_iter([]),
             }
         }
 
@@ -510,6 +538,15 @@ pub mod testing {
             )
         }
 
+        /// Sets the directories recursive patterns don't descend into, in every cell.
+        pub fn with_recursive_pattern_excludes(mut self, excludes: &[&str]) -> Self {
+            self.recursive_pattern_excludes = excludes
+                .iter()
+                .map(|e| CellRelativePathBuf::testing_new(e))
+                .collect();
+            self
+        }
+
         pub fn mock_in_cell(&self, cell: CellName, builder: DiceBuilder) -> DiceBuilder {
             let data = Ok(FileOpsValue(FileOpsDelegateWithIgnores::new(
                 None,
@@ -517,6 +554,7 @@ pub mod testing {
                     cell,
                     Self {
                         entries: Arc::clone(&self.entries),
+                        recursive_pattern_excludes: self.recursive_pattern_excludes.dupe(),
                     },
                 )),
             )));
@@ -597,6 +635,13 @@ pub mod testing {
         async fn buildfiles<'a>(&self, _cell: CellName) -> anyhow::Result<Arc<[FileNameBuf]>> {
             Ok(Arc::from_iter([FileNameBuf::unchecked_new("BUCK")]))
         }
+
+        async fn recursive_pattern_excludes(
+            &self,
+            _cell: CellName,
+        ) -> anyhow::Result<Arc<[CellRelativePathBuf]>> {
+            Ok(self.recursive_pattern_excludes.dupe())
+        }
     }
 
     pub struct TestCellFileOps(CellName, TestFileOps);
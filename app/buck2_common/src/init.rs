@@ -255,6 +255,7 @@ impl ResourceControlConfig {
 pub struct DaemonStartupConfig {
     pub daemon_buster: Option<String>,
     pub digest_algorithms: Option<String>,
+    pub digest_algorithm_candidates: Option<String>,
     pub source_digest_algorithm: Option<String>,
     pub allow_vpnless: bool,
     pub paranoid: bool,
@@ -287,6 +288,12 @@ impl DaemonStartupConfig {
                     property: "digest_algorithms",
                 })
                 .map(ToOwned::to_owned),
+            digest_algorithm_candidates: config
+                .get(BuckconfigKeyRef {
+                    section: "buck2",
+                    property: "digest_algorithm_candidates",
+                })
+                .map(ToOwned::to_owned),
             source_digest_algorithm: config
                 .get(BuckconfigKeyRef {
                     section: "buck2",
@@ -319,6 +326,7 @@ impl DaemonStartupConfig {
         Self {
             daemon_buster: None,
             digest_algorithms: None,
+            digest_algorithm_candidates: None,
             source_digest_algorithm: None,
             allow_vpnless: false,
             paranoid: false,
@@ -114,6 +114,20 @@ impl InvocationPaths {
             .join(ForwardRelativePath::unchecked_new("dice_dump"))
     }
 
+    /// Retained memory summaries written after each command if `buck2.heap_snapshots` is set.
+    pub fn heap_snapshots_dir(&self) -> AbsNormPathBuf {
+        self.buck_out_path()
+            .join(ForwardRelativePath::unchecked_new("heap_snapshots"))
+    }
+
+    /// The daemon log of the previous daemon for this isolation dir, or of the current daemon
+    /// once its log in the daemon dir got too large.
+    pub fn daemon_log_prev(&self) -> AbsNormPathBuf {
+        self.buck_out_path()
+            .join(ForwardRelativePath::unchecked_new("daemon_log"))
+            .join(ForwardRelativePath::unchecked_new("buckd.stderr.prev"))
+    }
+
     pub fn buck_out_dir_prefix() -> &'static ProjectRelativePath {
         ProjectRelativePath::unchecked_new("buck-out")
     }
@@ -148,6 +162,29 @@ impl InvocationPaths {
             .join(self.materializer_state_dir_name())
     }
 
+    /// Subdirectory of `cache_dir` storing the digests of source files.
+    pub fn source_digest_cache_path(&self) -> AbsNormPathBuf {
+        self.cache_dir_path()
+            .join(self.source_digest_cache_dir_name())
+    }
+
+    /// File in `cache_dir` storing the digest algorithm last negotiated with the RE server.
+    pub fn digest_negotiation_path(&self) -> AbsNormPathBuf {
+        self.cache_dir_path()
+            .join(self.digest_negotiation_dir_name())
+            .join(ForwardRelativePath::unchecked_new("algorithm"))
+    }
+
+    /// Directory of the local action cache. It is shared by all isolation dirs, and is outside
+    /// of `buck_out_path` so that `buck2 clean` keeps it.
+    pub fn local_action_cache_dir(&self) -> AbsNormPathBuf {
+        self.roots
+            .project_root
+            .root()
+            .join(Self::buck_out_dir_prefix())
+            .join(ForwardRelativePath::unchecked_new("local_action_cache"))
+    }
+
     /// This is used by the forkserver to write the miniperf wrapper binary (if used), as well as
     /// temporary files used by miniperf. We put this in buck-out because that directory gets
     /// allowlisted for execution (because we write lots of tools there).
@@ -160,8 +197,20 @@ impl InvocationPaths {
         FileName::unchecked_new("materializer_state")
     }
 
+    pub fn source_digest_cache_dir_name(&self) -> &FileName {
+        FileName::unchecked_new("source_digests")
+    }
+
+    pub fn digest_negotiation_dir_name(&self) -> &FileName {
+        FileName::unchecked_new("digest_negotiation")
+    }
+
     pub fn valid_cache_dirs(&self) -> Vec<&FileName> {
-        vec![self.materializer_state_dir_name()]
+        vec![
+            self.materializer_state_dir_name(),
+            self.source_digest_cache_dir_name(),
+            self.digest_negotiation_dir_name(),
+        ]
     }
 }
 
@@ -256,6 +305,15 @@ mod tests {
             "/my/project/buck-out/isolation/dice_dump"
         };
         assert_eq!(paths.dice_dump_dir().as_os_str(), OsStr::new(expected_path));
+        let expected_path = if cfg!(windows) {
+            "C:\\my\\project\\buck-out\\isolation\\heap_snapshots"
+        } else {
+            "/my/project/buck-out/isolation/heap_snapshots"
+        };
+        assert_eq!(
+            paths.heap_snapshots_dir().as_os_str(),
+            OsStr::new(expected_path)
+        );
 
         assert_eq!(
             paths.cache_dir(),
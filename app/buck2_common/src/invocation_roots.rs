@@ -132,3 +132,10 @@ pub(crate) fn home_buck_dir() -> anyhow::Result<&'static AbsNormPath> {
 
     Ok(&Lazy::force(&DIR).as_ref().map_err(dupe::Dupe::dupe)?)
 }
+
+/// The `common_buckd_dir` of the user whose home directory is `home`. Used by administrators to
+/// find the daemons of all users.
+pub fn buckd_dir_in_home(home: &AbsNormPath) -> AbsNormPathBuf {
+    home.join(FileName::unchecked_new(".buck"))
+        .join(FileName::unchecked_new("buckd"))
+}
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+pub mod digest_cache;
 pub mod fs;
 pub mod trace;
 
@@ -0,0 +1,489 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A persistent cache of the digests of source files, keyed by their inode, size and
+//! modification time, so that a new daemon doesn't need to hash the files which haven't changed
+//! since an earlier daemon hashed them.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::file_name::FileName;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use dupe::Dupe;
+use parking_lot::Mutex;
+use rusqlite::Connection;
+use rusqlite::OptionalExtension;
+
+use crate::cas_digest::CasDigestConfig;
+use crate::file_ops::FileDigest;
+use crate::sqlite::KeyValueSqliteTable;
+
+/// Bump this when changing the schema of the digests table, so that existing caches are
+/// discarded.
+const SCHEMA_VERSION: u64 = 2;
+
+/// Entries which no daemon used for this many days are deleted when the cache is opened.
+const MAX_UNUSED_DAYS: i64 = 30;
+
+/// Writes are buffered and written this many at a time, in a single transaction.
+const WRITE_BATCH_SIZE: usize = 1000;
+
+/// Smaller batches are written after this long, since the daemon may be killed before the cache
+/// is dropped.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Identifies the contents of a file on disk, as long as nothing deliberately preserves the
+/// modification time of a file it rewrites in place.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+#[cfg_attr(not(unix), allow(dead_code))]
+pub(crate) struct FileStat {
+    inode: u64,
+    size: u64,
+    mtime_nanos: i64,
+}
+
+impl FileStat {
+    /// Returns `None` if the digest of this file can't be cached.
+    #[cfg(unix)]
+    pub(crate) fn from_metadata(meta: &std::fs::Metadata) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+        use std::time::Duration;
+        use std::time::SystemTime;
+
+        // A file modified this recently might be modified again without its modification time
+        // changing, on filesystems with coarse timestamps, so its digest isn't cached.
+        const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+        let age = SystemTime::now()
+            .duration_since(meta.modified().ok()?)
+            .ok()?;
+        if age < RACY_WINDOW {
+            return None;
+        }
+
+        Some(Self {
+            inode: meta.ino(),
+            size: meta.len(),
+            mtime_nanos: meta
+                .mtime()
+                .checked_mul(1_000_000_000)?
+                .checked_add(meta.mtime_nsec())?,
+        })
+    }
+
+    /// The standard library doesn't expose file IDs on Windows.
+    #[cfg(not(unix))]
+    pub(crate) fn from_metadata(_meta: &std::fs::Metadata) -> Option<Self> {
+        None
+    }
+}
+
+/// An entry waiting to be written to the DB.
+struct PendingEntry {
+    stat: FileStat,
+    digest: FileDigest,
+}
+
+pub struct SourceDigestCache {
+    connection: Arc<Mutex<Connection>>,
+    /// Hashing threads only contend on this buffer: it is written to the DB in batches.
+    pending: Mutex<HashMap<ForwardRelativePathBuf, PendingEntry>>,
+    config: CasDigestConfig,
+    /// Days since the epoch, recorded as the last use of the entries this daemon reads or
+    /// writes.
+    today: i64,
+}
+
+impl SourceDigestCache {
+    const DB_FILENAME: &'static str = "db.sqlite";
+
+    /// Opens the cache in `dir`, or creates a new one if there is none or it was created for
+    /// different digests. Entries which haven't been used for a while are deleted.
+    pub fn open(dir: &AbsNormPath, config: CasDigestConfig) -> anyhow::Result<Self> {
+        let config = config.source_files_config();
+        let versions = HashMap::from([
+            ("schema_version".to_owned(), SCHEMA_VERSION.to_string()),
+            (
+                "digest_algorithm".to_owned(),
+                config.preferred_algorithm().kind().to_string(),
+            ),
+            // Keyed algorithms produce different digests for different keys. The digest of the
+            // empty file identifies the key without storing it.
+            (
+                "empty_digest".to_owned(),
+                config.empty_file_digest().raw_digest().to_string(),
+            ),
+        ]);
+        let db_path = dir.join(FileName::unchecked_new(Self::DB_FILENAME));
+
+        let existing = || -> anyhow::Result<Self> {
+            if !fs_util::try_exists(&db_path)? {
+                return Err(anyhow::anyhow!("`{}` does not exist", db_path));
+            }
+            let cache = Self::connect(&db_path, config)?;
+            let found = cache.versions_table().read_all()?;
+            if found != versions {
+                return Err(anyhow::anyhow!(
+                    "Expected versions {:?}, found {:?}",
+                    versions,
+                    found
+                ));
+            }
+            cache.evict_unused()?;
+            Ok(cache)
+        };
+
+        let res = match existing() {
+            Ok(cache) => Ok(cache),
+            Err(e) => {
+                tracing::debug!("Creating a new source digest cache: {:#}", e);
+                // Sqlite can leave other files next to the DB, so start from an empty directory.
+                fs_util::remove_all(dir)?;
+                fs_util::create_dir_all(dir)?;
+
+                let cache = Self::connect(&db_path, config)?;
+                cache.create_digests_table()?;
+                let versions_table = cache.versions_table();
+                versions_table.create_table()?;
+                versions_table.insert_all(versions)?;
+                Ok(cache)
+            }
+        };
+        res.with_context(|| format!("Error opening source digest cache in `{}`", dir))
+    }
+
+    fn connect(db_path: &AbsNormPath, config: CasDigestConfig) -> anyhow::Result<Self> {
+        let connection = Connection::open(db_path)?;
+        if cfg!(unix) {
+            connection.pragma_update(None, "journal_mode", "WAL")?;
+        }
+        // Like the materializer state, losing the cache on power loss is much cheaper than
+        // syncing it while building: it is only ever a cache.
+        connection.pragma_update(None, "synchronous", "OFF")?;
+        let today = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs() as i64
+            / (24 * 60 * 60);
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+            pending: Mutex::new(HashMap::new()),
+            config,
+            today,
+        })
+    }
+
+    fn versions_table(&self) -> KeyValueSqliteTable {
+        KeyValueSqliteTable::new("versions".to_owned(), self.connection.dupe())
+    }
+
+    fn create_digests_table(&self) -> anyhow::Result<()> {
+        self.connection.lock().execute(
+            "CREATE TABLE digests (
+                path        TEXT PRIMARY KEY NOT NULL,
+                inode       INTEGER NOT NULL,
+                size        INTEGER NOT NULL,
+                mtime_nanos INTEGER NOT NULL,
+                digest      TEXT NOT NULL,
+                last_used   INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn evict_unused(&self) -> anyhow::Result<()> {
+        let evicted = self.connection.lock().execute(
+            "DELETE FROM digests WHERE last_used < ?1",
+            [self.today - MAX_UNUSED_DAYS],
+        )?;
+        if evicted > 0 {
+            tracing::debug!("Evicted {} unused entries from the digest cache", evicted);
+        }
+        Ok(())
+    }
+
+    /// The digest of the file at `path`, if it was cached for the same version of the file.
+    /// Failing to read the cache is a cache miss.
+    pub(crate) fn get(&self, path: &ForwardRelativePath, stat: FileStat) -> Option<FileDigest> {
+        let res = self.get_impl(path, stat);
+        res.unwrap_or_else(|e| {
+            tracing::debug!("Error reading digest of `{}` from cache: {:#}", path, e);
+            None
+        })
+    }
+
+    fn get_impl(
+        &self,
+        path: &ForwardRelativePath,
+        stat: FileStat,
+    ) -> anyhow::Result<Option<FileDigest>> {
+        if let Some(entry) = self.pending.lock().get(path) {
+            return Ok((entry.stat == stat).then(|| entry.digest.dupe()));
+        }
+
+        let connection = self.connection.lock();
+        let row: Option<(i64, i64, i64, String, i64)> = connection
+            .prepare_cached(
+                "SELECT inode, size, mtime_nanos, digest, last_used FROM digests WHERE path = ?1",
+            )?
+            .query_row([path.as_str()], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })
+            .optional()?;
+        drop(connection);
+
+        match row {
+            Some((inode, size, mtime_nanos, digest, last_used))
+                if (inode as u64, size as u64, mtime_nanos)
+                    == (stat.inode, stat.size, stat.mtime_nanos) =>
+            {
+                let (raw, _) = FileDigest::parse_digest_without_size(&digest, self.config)?;
+                let digest = FileDigest::new(raw, stat.size);
+                // Record the use at most once a day, so that hits don't turn into writes.
+                if last_used < self.today {
+                    self.insert(path, stat, &digest);
+                }
+                Ok(Some(digest))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Failing to write to the cache only means the file will be hashed again next time.
+    pub(crate) fn insert(&self, path: &ForwardRelativePath, stat: FileStat, digest: &FileDigest) {
+        let mut pending = self.pending.lock();
+        pending.insert(
+            path.to_buf(),
+            PendingEntry {
+                stat,
+                digest: digest.dupe(),
+            },
+        );
+        if pending.len() < WRITE_BATCH_SIZE {
+            return;
+        }
+        let batch = std::mem::take(&mut *pending);
+        drop(pending);
+        self.write(batch);
+    }
+
+    /// Writes the buffered entries every `FLUSH_INTERVAL` until the cache is dropped.
+    pub fn spawn_flush_task(cache: &Arc<Self>) {
+        Self::spawn_flush_task_impl(cache, FLUSH_INTERVAL)
+    }
+
+    fn spawn_flush_task_impl(cache: &Arc<Self>, interval: Duration) {
+        let cache = Arc::downgrade(cache);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let Some(cache) = cache.upgrade() else {
+                    break;
+                };
+                if tokio::task::spawn_blocking(move || cache.flush())
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Writes the buffered entries to the DB.
+    pub(crate) fn flush(&self) {
+        let batch = std::mem::take(&mut *self.pending.lock());
+        if !batch.is_empty() {
+            self.write(batch);
+        }
+    }
+
+    fn write(&self, batch: HashMap<ForwardRelativePathBuf, PendingEntry>) {
+        let len = batch.len();
+        if let Err(e) = self.write_impl(batch) {
+            tracing::debug!("Error writing {} digests to cache: {:#}", len, e);
+        }
+    }
+
+    fn write_impl(
+        &self,
+        batch: HashMap<ForwardRelativePathBuf, PendingEntry>,
+    ) -> anyhow::Result<()> {
+        let mut connection = self.connection.lock();
+        let transaction = connection.transaction()?;
+        {
+            let mut stmt = transaction.prepare_cached(
+                "INSERT OR REPLACE INTO digests (path, inode, size, mtime_nanos, digest, last_used)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for (path, entry) in batch {
+                stmt.execute(rusqlite::params![
+                    path.as_str(),
+                    entry.stat.inode as i64,
+                    entry.stat.size as i64,
+                    entry.stat.mtime_nanos,
+                    entry.digest.raw_digest().to_string(),
+                    self.today,
+                ])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+impl Drop for SourceDigestCache {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_core::fs::project_rel_path::ProjectRelativePath;
+
+    use super::*;
+    use crate::cas_digest::DigestAlgorithm;
+
+    #[test]
+    fn test_digest_cache() {
+        let fs = ProjectRootTemp::new().unwrap();
+        let dir = fs
+            .path()
+            .resolve(ProjectRelativePath::unchecked_new("cache"));
+        let config = CasDigestConfig::testing_default();
+        let path = ForwardRelativePath::unchecked_new("foo/bar.txt");
+        let stat = FileStat {
+            inode: 1,
+            size: 3,
+            mtime_nanos: 1_000,
+        };
+        let digest = FileDigest::from_content(b"bar", config);
+
+        let cache = SourceDigestCache::open(&dir, config).unwrap();
+        assert_eq!(cache.get(path, stat), None);
+        cache.insert(path, stat, &digest);
+        assert_eq!(cache.get(path, stat), Some(digest.dupe()));
+        assert_eq!(
+            cache.get(
+                path,
+                FileStat {
+                    mtime_nanos: 2_000,
+                    ..stat
+                }
+            ),
+            None
+        );
+        drop(cache);
+
+        // The cache persists.
+        let cache = SourceDigestCache::open(&dir, config).unwrap();
+        assert_eq!(cache.get(path, stat), Some(digest));
+    }
+
+    #[tokio::test]
+    async fn test_digest_cache_flush_task() {
+        let fs = ProjectRootTemp::new().unwrap();
+        let dir = fs
+            .path()
+            .resolve(ProjectRelativePath::unchecked_new("cache"));
+        let config = CasDigestConfig::testing_default();
+        let path = ForwardRelativePath::unchecked_new("foo");
+        let stat = FileStat {
+            inode: 1,
+            size: 3,
+            mtime_nanos: 1_000,
+        };
+
+        let cache = Arc::new(SourceDigestCache::open(&dir, config).unwrap());
+        SourceDigestCache::spawn_flush_task_impl(&cache, Duration::from_millis(10));
+        cache.insert(path, stat, &FileDigest::from_content(b"foo", config));
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !cache.pending.lock().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn test_digest_cache_discarded_for_different_key() {
+        static KEY_A: [u8; 32] = [1; 32];
+        static KEY_B: [u8; 32] = [2; 32];
+
+        let fs = ProjectRootTemp::new().unwrap();
+        let dir = fs
+            .path()
+            .resolve(ProjectRelativePath::unchecked_new("cache"));
+        let config_a =
+            CasDigestConfig::leak_new(vec![DigestAlgorithm::Blake3Keyed { key: &KEY_A }], None)
+                .unwrap();
+        let config_b =
+            CasDigestConfig::leak_new(vec![DigestAlgorithm::Blake3Keyed { key: &KEY_B }], None)
+                .unwrap();
+        let path = ForwardRelativePath::unchecked_new("foo");
+        let stat = FileStat {
+            inode: 1,
+            size: 3,
+            mtime_nanos: 1_000,
+        };
+
+        let cache = SourceDigestCache::open(&dir, config_a).unwrap();
+        cache.insert(path, stat, &FileDigest::from_content(b"foo", config_a));
+        drop(cache);
+
+        let cache = SourceDigestCache::open(&dir, config_b).unwrap();
+        assert_eq!(cache.get(path, stat), None);
+    }
+
+    #[test]
+    fn test_digest_cache_evicts_unused() {
+        let fs = ProjectRootTemp::new().unwrap();
+        let dir = fs
+            .path()
+            .resolve(ProjectRelativePath::unchecked_new("cache"));
+        let config = CasDigestConfig::testing_default();
+        let old = ForwardRelativePath::unchecked_new("old");
+        let new = ForwardRelativePath::unchecked_new("new");
+        let stat = FileStat {
+            inode: 1,
+            size: 3,
+            mtime_nanos: 1_000,
+        };
+        let digest = FileDigest::from_content(b"foo", config);
+
+        let mut cache = SourceDigestCache::open(&dir, config).unwrap();
+        let today = cache.today;
+        cache.today = today - MAX_UNUSED_DAYS - 1;
+        cache.insert(old, stat, &digest);
+        cache.flush();
+        cache.today = today;
+        cache.insert(new, stat, &digest);
+        drop(cache);
+
+        let cache = SourceDigestCache::open(&dir, config).unwrap();
+        assert_eq!(cache.get(old, stat), None);
+        assert_eq!(cache.get(new, stat), Some(digest));
+    }
+}
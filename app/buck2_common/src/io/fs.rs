@@ -37,12 +37,16 @@ use crate::file_ops::RawDirEntry;
 use crate::file_ops::RawPathMetadata;
 use crate::file_ops::RawSymlink;
 use crate::file_ops::TrackedFileDigest;
+use crate::io::digest_cache::FileStat;
+use crate::io::digest_cache::SourceDigestCache;
 use crate::io::IoProvider;
 
 #[derive(Clone, Dupe, Allocative)]
 pub struct FsIoProvider {
     fs: ProjectRoot,
     cas_digest_config: CasDigestConfig,
+    #[allocative(skip)]
+    digest_cache: Option<Arc<SourceDigestCache>>,
 }
 
 impl FsIoProvider {
@@ -50,6 +54,16 @@ impl FsIoProvider {
         Self {
             fs,
             cas_digest_config,
+            digest_cache: None,
+        }
+    }
+
+    /// Reuse the digests of source files from `digest_cache` instead of hashing them, for files
+    /// which haven't changed since they were cached.
+    pub fn with_digest_cache(self, digest_cache: Arc<SourceDigestCache>) -> Self {
+        Self {
+            digest_cache: Some(digest_cache),
+            ..self
         }
     }
 
@@ -78,11 +92,16 @@ impl FsIoProvider {
         let fs = self.fs.dupe();
         let path = path.into_forward_relative_path_buf();
         let file_digest_config = FileDigestConfig::source(self.cas_digest_config);
+        let digest_cache = self.digest_cache.dupe();
         tokio::task::spawn_blocking(move || {
-            Ok(
-                read_unchecked(fs.root(), path, file_digest_config, options)?
-                    .map(ProjectRelativePathBuf::from),
-            )
+            Ok(read_unchecked(
+                fs.root(),
+                path,
+                file_digest_config,
+                digest_cache.as_deref(),
+                options,
+            )?
+            .map(ProjectRelativePathBuf::from))
         })
         .await?
     }
@@ -159,11 +178,18 @@ impl IoProvider for FsIoProvider {
         let fs = self.fs.dupe();
         let path = path.into_forward_relative_path_buf();
         let file_digest_config = FileDigestConfig::source(self.cas_digest_config);
+        let digest_cache = self.digest_cache.dupe();
 
         tokio::task::spawn_blocking(move || {
-            let meta = read_path_metadata(fs.root(), &path, file_digest_config)?.map(
-                |raw_meta_or_redirection| raw_meta_or_redirection.map(ProjectRelativePathBuf::from),
-            );
+            let meta = read_path_metadata(
+                fs.root(),
+                &path,
+                file_digest_config,
+                digest_cache.as_deref(),
+            )?
+            .map(|raw_meta_or_redirection| {
+                raw_meta_or_redirection.map(ProjectRelativePathBuf::from)
+            });
 
             Ok(meta)
         })
@@ -208,6 +234,7 @@ fn read_path_metadata<P: AsRef<AbsPath>>(
     root: P,
     relpath: &ForwardRelativePath,
     file_digest_config: FileDigestConfig,
+    digest_cache: Option<&SourceDigestCache>,
 ) -> anyhow::Result<Option<RawPathMetadata<ForwardRelativePathBuf>>> {
     let root = root.as_ref();
 
@@ -248,7 +275,7 @@ fn read_path_metadata<P: AsRef<AbsPath>>(
 
     // If we get here that means we never hit a symlink. So, the metadata we have
     let meta = meta.context("Attempted to access empty path")?;
-    let meta = convert_metadata(&curr, meta, file_digest_config)?;
+    let meta = convert_metadata(&curr, meta, file_digest_config, digest_cache)?;
 
     if cfg!(test) {
         assert!(curr.abspath.as_os_str().len() <= curr_abspath_capacity);
@@ -262,11 +289,12 @@ fn convert_metadata(
     path: &PathAndAbsPath,
     meta: std::fs::Metadata,
     file_digest_config: FileDigestConfig,
+    digest_cache: Option<&SourceDigestCache>,
 ) -> anyhow::Result<RawPathMetadata<ForwardRelativePathBuf>> {
     let meta = if meta.is_dir() {
         RawPathMetadata::Directory
     } else {
-        let digest = FileDigest::from_file(&path.abspath, file_digest_config)
+        let digest = file_digest(path, &meta, file_digest_config, digest_cache)
             .with_context(|| format!("Error collecting file digest for `{}`", path.path))?;
         let digest = TrackedFileDigest::new(digest, file_digest_config.as_cas_digest_config());
         RawPathMetadata::File(FileMetadata {
@@ -278,6 +306,26 @@ fn convert_metadata(
     Ok(meta)
 }
 
+fn file_digest(
+    path: &PathAndAbsPath,
+    meta: &std::fs::Metadata,
+    file_digest_config: FileDigestConfig,
+    digest_cache: Option<&SourceDigestCache>,
+) -> anyhow::Result<FileDigest> {
+    let cached = digest_cache.and_then(|cache| Some((cache, FileStat::from_metadata(meta)?)));
+    if let Some((cache, stat)) = cached {
+        if let Some(digest) = cache.get(&path.path, stat) {
+            return Ok(digest);
+        }
+    }
+
+    let digest = FileDigest::from_file(&path.abspath, file_digest_config)?;
+    if let Some((cache, stat)) = cached {
+        cache.insert(&path.path, stat, &digest);
+    }
+    Ok(digest)
+}
+
 enum ExactPathMetadata {
     DoesNotExist,
     Symlink(ExactPathSymlinkMetadata),
@@ -360,6 +408,7 @@ fn read_unchecked<P: AsRef<AbsPath>>(
     root: P,
     relpath: ForwardRelativePathBuf,
     file_digest_config: FileDigestConfig,
+    digest_cache: Option<&SourceDigestCache>,
     options: ReadUncheckedOptions,
 ) -> anyhow::Result<RawPathMetadata<ForwardRelativePathBuf>> {
     let abspath = root.as_ref().join(relpath.as_path());
@@ -373,7 +422,9 @@ fn read_unchecked<P: AsRef<AbsPath>>(
         ExactPathMetadata::DoesNotExist => Err(ReadSymlinkAtExactPathError::DoesNotExist.into()),
         ExactPathMetadata::FileOrDirectory(meta) => match options {
             ReadUncheckedOptions::Symlink => Err(ReadSymlinkAtExactPathError::NotASymlink.into()),
-            ReadUncheckedOptions::Anything => convert_metadata(&curr, meta, file_digest_config),
+            ReadUncheckedOptions::Anything => {
+                convert_metadata(&curr, meta, file_digest_config, digest_cache)
+            }
         },
         ExactPathMetadata::Symlink(link) => link.to_raw_path_metadata(curr, None),
     }
@@ -397,6 +448,7 @@ mod tests {
     use std::os::unix;
 
     use assert_matches::assert_matches;
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
     use tempfile::TempDir;
 
     use super::*;
@@ -412,7 +464,8 @@ mod tests {
             read_path_metadata(
                 AbsPath::new(t.path())?,
                 ForwardRelativePath::new("x")?,
-                FileDigestConfig::source(CasDigestConfig::testing_default())
+                FileDigestConfig::source(CasDigestConfig::testing_default()),
+                None
             ),
             Ok(Some(RawPathMetadata::File(..)))
         );
@@ -427,7 +480,7 @@ mod tests {
         unix::fs::symlink("y/z", t.path().join("x"))?;
 
         assert_matches!(
-            read_path_metadata(AbsPath::new(t.path())?, ForwardRelativePath::new("x")?, FileDigestConfig::source(CasDigestConfig::testing_default())),
+            read_path_metadata(AbsPath::new(t.path())?, ForwardRelativePath::new("x")?, FileDigestConfig::source(CasDigestConfig::testing_default()), None),
             Ok(Some(RawPathMetadata::Symlink{at:_, to: RawSymlink::Relative(r)})) => {
                 assert_eq!(r, "y/z");
             }
@@ -445,7 +498,7 @@ mod tests {
         unix::fs::symlink("../y", t.join("x/xx/xxx"))?;
 
         assert_matches!(
-            read_path_metadata(AbsPath::new(t)?, ForwardRelativePath::new("x/xx/xxx")?, FileDigestConfig::source(CasDigestConfig::testing_default())),
+            read_path_metadata(AbsPath::new(t)?, ForwardRelativePath::new("x/xx/xxx")?, FileDigestConfig::source(CasDigestConfig::testing_default()), None),
             Ok(Some(RawPathMetadata::Symlink{at:_, to: RawSymlink::Relative(r)})) => {
                 assert_eq!(r, "x/y");
             }
@@ -461,7 +514,7 @@ mod tests {
         unix::fs::symlink("y", t.path().join("x"))?;
 
         assert_matches!(
-            read_path_metadata(AbsPath::new(t.path())?, ForwardRelativePath::new("x/z/zz")?, FileDigestConfig::source(CasDigestConfig::testing_default())),
+            read_path_metadata(AbsPath::new(t.path())?, ForwardRelativePath::new("x/z/zz")?, FileDigestConfig::source(CasDigestConfig::testing_default()), None),
             Ok(Some(RawPathMetadata::Symlink{at:_, to: RawSymlink::Relative(r)})) => {
                 assert_eq!(r, "y/z/zz");
             }
@@ -477,7 +530,7 @@ mod tests {
         unix::fs::symlink("../y", t.path().join("x"))?;
 
         assert_matches!(
-            read_path_metadata(AbsPath::new(t.path())?, ForwardRelativePath::new("x/xx/xxx")?, FileDigestConfig::source(CasDigestConfig::testing_default())),
+            read_path_metadata(AbsPath::new(t.path())?, ForwardRelativePath::new("x/xx/xxx")?, FileDigestConfig::source(CasDigestConfig::testing_default()), None),
             Err(e) if format!("{:#}", e).contains("Invalid symlink")
         );
 
@@ -499,6 +552,7 @@ mod tests {
                 root,
                 ForwardRelativePathBuf::new("link".to_owned())?,
                 digest_config,
+                None,
                 ReadUncheckedOptions::Symlink
             ),
             Ok(RawPathMetadata::Symlink {
@@ -512,6 +566,7 @@ mod tests {
                 root,
                 ForwardRelativePathBuf::new("abs_link".to_owned())?,
                 digest_config,
+                None,
                 ReadUncheckedOptions::Symlink
             ),
             Ok(RawPathMetadata::Symlink {
@@ -525,6 +580,7 @@ mod tests {
                 root,
                 ForwardRelativePathBuf::new("file".to_owned())?,
                 digest_config,
+                None,
                 ReadUncheckedOptions::Symlink
             ),
             Err(..)
@@ -535,6 +591,7 @@ mod tests {
                 root,
                 ForwardRelativePathBuf::new("file".to_owned())?,
                 digest_config,
+                None,
                 ReadUncheckedOptions::Anything
             ),
             Ok(..)
@@ -545,6 +602,7 @@ mod tests {
                 root,
                 ForwardRelativePathBuf::new("does_not_exist".to_owned())?,
                 digest_config,
+                None,
                 ReadUncheckedOptions::Symlink,
             ),
             Err(..)
@@ -552,4 +610,40 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_file_digest_uses_cache() -> anyhow::Result<()> {
+        let t = TempDir::new()?;
+        let root = AbsPath::new(t.path())?;
+        let config = CasDigestConfig::testing_default();
+        let digest_config = FileDigestConfig::source(config);
+        let path = PathAndAbsPath {
+            path: ForwardRelativePathBuf::new("x".to_owned())?,
+            abspath: root.join("x"),
+        };
+
+        fs_util::write(&path.abspath, "xx")?;
+        // The digests of files modified in the last couple of seconds aren't cached.
+        std::fs::File::options()
+            .write(true)
+            .open(&path.abspath)?
+            .set_modified(std::time::SystemTime::now() - std::time::Duration::from_secs(60))?;
+        let meta = fs_util::metadata(&path.abspath)?;
+
+        let cache = SourceDigestCache::open(AbsNormPath::new(&root.join("cache"))?, config)?;
+        // Cache a digest the file can't have, so that hits can be told from hashing the file.
+        let cached = FileDigest::from_content(b"yy", config);
+        cache.insert(&path.path, FileStat::from_metadata(&meta).unwrap(), &cached);
+
+        assert_eq!(
+            file_digest(&path, &meta, digest_config, None)?,
+            FileDigest::from_content(b"xx", config)
+        );
+        assert_eq!(
+            file_digest(&path, &meta, digest_config, Some(&cache))?,
+            cached
+        );
+
+        Ok(())
+    }
 }
@@ -16,5 +16,5 @@ pub mod configs;
 pub mod dice;
 pub mod key;
 mod parser;
-pub(crate) mod path;
+pub mod path;
 pub mod view;
@@ -64,6 +64,10 @@ enum CellsError {
     MissingRootCellName,
     #[error("Unknown cell name `{}` when parsing external cell declarations", _0)]
     UnknownCellName(NonEmptyCellAlias),
+    #[error(
+        "Invalid entry `{0}` in `{1}.cell_aliases`, expected a comma-separated list of `alias=cell`"
+    )]
+    InvalidExternalCellAlias(String, String),
 }
 
 /// Used for creating a CellResolver in a buckv1-compatible way based on values
@@ -350,6 +354,65 @@ impl BuckConfigBasedCells {
         Ok(aliases.into_iter())
     }
 
+    /// The aliases of an external cell, given its own `config` and the `root_config` of the
+    /// project, in increasing order of precedence:
+    ///  - the `cell_aliases` of the external cell
+    ///  - the entries of its `cells` section which point at itself, mapped to `self_alias`, so
+    ///    that another buck2 project can refer to itself by the name it uses for its root cell
+    ///  - `external_cell_<name>.cell_aliases` in the root config, which maps the cells the external
+    ///    cell refers to onto the cells of this project
+    ///
+    /// Other entries of the `cells` section are ignored, as external cells cannot create new cells.
+    pub(crate) fn get_external_cell_aliases_from_config(
+        cell: CellName,
+        self_alias: Option<&NonEmptyCellAlias>,
+        config: &LegacyBuckConfig,
+        root_config: &LegacyBuckConfig,
+    ) -> anyhow::Result<Vec<(NonEmptyCellAlias, NonEmptyCellAlias)>> {
+        let mut aliases: Vec<_> = Self::get_cell_aliases_from_config(config)?.collect();
+
+        if let Some(self_alias) = self_alias {
+            if let Some(repositories) = config
+                .get_section("repositories")
+                .or_else(|| config.get_section("cells"))
+            {
+                for (alias, alias_path) in repositories.iter() {
+                    if RelativePath::new(alias_path.as_str())
+                        .normalize()
+                        .as_str()
+                        .is_empty()
+                    {
+                        aliases.push((
+                            NonEmptyCellAlias::new(alias.to_owned())?,
+                            self_alias.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        let section = format!("external_cell_{}", cell.as_str());
+        if let Some(value) = root_config.get(crate::legacy_configs::key::BuckconfigKeyRef {
+            section: &section,
+            property: "cell_aliases",
+        }) {
+            for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                let (alias, destination) = entry.split_once('=').ok_or_else(|| {
+                    CellsError::InvalidExternalCellAlias(entry.to_owned(), section.clone())
+                })?;
+                let alias = NonEmptyCellAlias::new(alias.trim().to_owned())?;
+                // The external cell's own mapping may not even be valid in this project.
+                aliases.retain(|(a, _)| a != &alias);
+                aliases.push((
+                    alias,
+                    NonEmptyCellAlias::new(destination.trim().to_owned())?,
+                ));
+            }
+        }
+
+        Ok(aliases)
+    }
+
     pub(crate) async fn parse_single_cell_with_dice(
         ctx: &mut DiceComputations<'_>,
         cell_path: &CellRootPath,
@@ -602,6 +665,7 @@ pub(crate) fn create_project_filesystem() -> ProjectRoot {
 mod tests {
     use std::sync::Arc;
 
+    use buck2_core::cells::alias::NonEmptyCellAlias;
     use buck2_core::cells::cell_root_path::CellRootPath;
     use buck2_core::cells::external::ExternalCellOrigin;
     use buck2_core::cells::external::GitCellSetup;
@@ -1364,4 +1428,56 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_external_cell_aliases() -> anyhow::Result<()> {
+        let config = crate::legacy_configs::configs::testing::parse(
+            &[(
+                "/libfoo/.buckconfig",
+                indoc!(
+                    r#"
+                        [cells]
+                            foo_root = .
+                            prelude = prelude/
+                        [cell_aliases]
+                            config = prelude
+                    "#
+                ),
+            )],
+            "/libfoo/.buckconfig",
+        )?;
+        let root_config = crate::legacy_configs::configs::testing::parse(
+            &[(
+                "/.buckconfig",
+                indoc!(
+                    r#"
+                        [external_cell_libfoo]
+                            cell_aliases = tc = toolchains, config=root
+                    "#
+                ),
+            )],
+            "/.buckconfig",
+        )?;
+
+        let aliases = BuckConfigBasedCells::get_external_cell_aliases_from_config(
+            CellName::testing_new("libfoo"),
+            Some(&NonEmptyCellAlias::testing_new("libfoo")),
+            &config,
+            &root_config,
+        )?
+        .into_iter()
+        .map(|(a, d)| (a.as_str().to_owned(), d.as_str().to_owned()))
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            aliases,
+            vec![
+                ("foo_root".to_owned(), "libfoo".to_owned()),
+                ("tc".to_owned(), "toolchains".to_owned()),
+                ("config".to_owned(), "root".to_owned()),
+            ]
+        );
+
+        Ok(())
+    }
 }
@@ -7,6 +7,8 @@
  * of this source tree.
  */
 
+use buck2_core::cells::paths::CellRelativePath;
+
 pub(crate) enum BuckConfigFile {
     // Buckconfig file in the cell relative to project root, such as .buckconfig or .buckconfig.local
     CellRelativeFile(&'static str),
@@ -64,3 +66,35 @@ pub(crate) static DEFAULT_BUCK_CONFIG_FILES: &[BuckConfigFile] = &[
     BuckConfigFile::CellRelativeFile(".buckconfig"),
     BuckConfigFile::CellRelativeFile(".buckconfig.local"),
 ];
+
+/// Whether a path in a cell is one of the buckconfig files which are read for that cell. Files
+/// which are only included from those are not detected.
+pub fn is_cell_buckconfig_path(path: &CellRelativePath) -> bool {
+    DEFAULT_BUCK_CONFIG_FILES.iter().any(|file| match file {
+        BuckConfigFile::CellRelativeFile(name) => path.as_str() == *name,
+        BuckConfigFile::CellRelativeFolder(name) => path
+            .as_str()
+            .strip_prefix(name)
+            .map_or(false, |rest| rest.starts_with('/')),
+        BuckConfigFile::UserFile(..)
+        | BuckConfigFile::UserFolder(..)
+        | BuckConfigFile::GlobalFile(..)
+        | BuckConfigFile::GlobalFolder(..) => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_cell_buckconfig_path() {
+        let check = |path: &str| is_cell_buckconfig_path(CellRelativePath::unchecked_new(path));
+        assert!(check(".buckconfig"));
+        assert!(check(".buckconfig.local"));
+        assert!(check(".buckconfig.d/experiments"));
+        assert!(!check(".buckconfig.dx"));
+        assert!(!check("foo/.buckconfig"));
+        assert!(!check("BUCK"));
+    }
+}
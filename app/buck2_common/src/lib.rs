@@ -21,6 +21,7 @@
 #[macro_use]
 extern crate maplit;
 
+pub mod action_input_budgets;
 pub mod argv;
 pub mod buckd_connection;
 pub mod buildfiles;
@@ -33,6 +34,7 @@ pub mod dice;
 pub mod events;
 pub mod external_cells;
 pub mod external_symlink;
+pub mod file_hashing;
 pub mod file_ops;
 pub mod find_buildfile;
 pub mod global_cfg_options;
@@ -52,6 +54,7 @@ pub mod memory;
 pub mod package_boundary;
 pub mod package_listing;
 pub mod pattern;
+pub mod relative_label_policy;
 pub mod scope;
 pub mod sqlite;
 pub mod starlark_profiler;
@@ -114,10 +114,11 @@ pub async fn collect_package_roots<E>(
     }
 
     while let Some((path, listing)) = queue.next().await {
-        let (buildfile_candidates, listing) = {
+        let (buildfile_candidates, excludes, listing) = {
             let r = async {
                 let buildfiles = file_ops.buildfiles(path.cell()).await?;
-                anyhow::Ok((buildfiles, listing?.included))
+                let excludes = file_ops.recursive_pattern_excludes(path.cell()).await?;
+                anyhow::Ok((buildfiles, excludes, listing?.included))
             }
             .await;
 
@@ -143,6 +144,10 @@ pub async fn collect_package_roots<E>(
         for entry in listing.iter().rev() {
             if entry.file_type.is_dir() {
                 let child = path.join(&entry.file_name);
+                // Only directories found while crawling are excluded, not the requested paths.
+                if excludes.iter().any(|e| **e == *child.path()) {
+                    continue;
+                }
                 if seen.insert(child.clone()) {
                     queue.push(list_dir(child));
                 }
@@ -170,3 +175,65 @@ pub(crate) async fn find_package_roots(
     results.sort();
     Ok(results)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::paths::CellRelativePath;
+
+    use super::*;
+    use crate::file_ops::testing::TestFileOps;
+
+    fn file_ops(files: &[&str]) -> TestFileOps {
+        TestFileOps::new_with_files(
+            files
+                .iter()
+                .map(|f| {
+                    (
+                        CellPath::new(
+                            CellName::testing_new("root"),
+                            CellRelativePath::testing_new(f).to_buf(),
+                        ),
+                        String::new(),
+                    )
+                })
+                .collect::<BTreeMap<_, _>>(),
+        )
+    }
+
+    async fn package_roots(fs: &TestFileOps, path: &str) -> anyhow::Result<Vec<String>> {
+        let mut roots: Vec<String> = find_package_roots(CellPath::testing_new(path), fs)
+            .await?
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        roots.sort();
+        Ok(roots)
+    }
+
+    #[tokio::test]
+    async fn test_recursive_pattern_excludes() -> anyhow::Result<()> {
+        let fs = file_ops(&[
+            "BUCK",
+            "app/BUCK",
+            "third-party/BUCK",
+            "third-party/zlib/BUCK",
+            "tools/generated/BUCK",
+            "tools/generated_not/BUCK",
+        ])
+        .with_recursive_pattern_excludes(&["third-party", "tools/generated"]);
+
+        assert_eq!(
+            vec!["root//", "root//app", "root//tools/generated_not"],
+            package_roots(&fs, "root//").await?
+        );
+        // Patterns rooted in an excluded directory still match its packages.
+        assert_eq!(
+            vec!["root//third-party", "root//third-party/zlib"],
+            package_roots(&fs, "root//third-party").await?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Directories that recursive patterns (`//...`) don't descend into, e.g. large vendored trees
+//! which are only ever depended on. Unlike `project.ignore`, the targets there can still be
+//! built, and patterns rooted in an excluded directory (`//third-party/...`) still match them.
+//! The buckconfig `project.recursive_pattern_excludes` lists the directories of a cell,
+//! comma-separated.
+
+use std::future::Future;
+use std::sync::Arc;
+
+use buck2_core::cells::name::CellName;
+use buck2_core::cells::paths::CellRelativePath;
+use buck2_core::cells::paths::CellRelativePathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use dice::CancellationContext;
+use dice::DiceComputations;
+use dice::Key;
+
+use crate::legacy_configs::dice::HasLegacyConfigs;
+use crate::legacy_configs::key::BuckconfigKeyRef;
+
+fn parse_recursive_pattern_excludes(s: &str) -> anyhow::Result<Vec<CellRelativePathBuf>> {
+    let mut excludes = Vec::new();
+    for path in s.split(',') {
+        let path = path.trim();
+        if path.is_empty() {
+            continue;
+        }
+        excludes.push(CellRelativePath::new(ForwardRelativePath::new(path)?).to_buf());
+    }
+    Ok(excludes)
+}
+
+pub trait HasRecursivePatternExcludes {
+    fn get_recursive_pattern_excludes(
+        &mut self,
+        cell: CellName,
+    ) -> impl Future<Output = anyhow::Result<Arc<[CellRelativePathBuf]>>>;
+}
+
+#[derive(
+    Clone,
+    derive_more::Display,
+    Debug,
+    Hash,
+    Eq,
+    PartialEq,
+    allocative::Allocative
+)]
+#[display(fmt = "RecursivePatternExcludesKey({})", "self.0")]
+struct RecursivePatternExcludesKey(CellName);
+
+#[async_trait::async_trait]
+impl Key for RecursivePatternExcludesKey {
+    type Value = buck2_error::Result<Arc<[CellRelativePathBuf]>>;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        let excludes = ctx
+            .get_legacy_config_property(
+                self.0,
+                BuckconfigKeyRef {
+                    section: "project",
+                    property: "recursive_pattern_excludes",
+                },
+            )
+            .await?;
+        let excludes = match excludes {
+            Some(excludes) => parse_recursive_pattern_excludes(&excludes)?,
+            None => Vec::new(),
+        };
+        Ok(excludes.into())
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        match (x, y) {
+            (Ok(x), Ok(y)) => x == y,
+            _ => false,
+        }
+    }
+}
+
+impl HasRecursivePatternExcludes for DiceComputations<'_> {
+    async fn get_recursive_pattern_excludes(
+        &mut self,
+        cell: CellName,
+    ) -> anyhow::Result<Arc<[CellRelativePathBuf]>> {
+        Ok(self.compute(&RecursivePatternExcludesKey(cell)).await??)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recursive_pattern_excludes() {
+        assert_eq!(
+            vec![
+                CellRelativePathBuf::testing_new("third-party"),
+                CellRelativePathBuf::testing_new("foo/bar"),
+            ],
+            parse_recursive_pattern_excludes("third-party, foo/bar,").unwrap()
+        );
+        assert!(parse_recursive_pattern_excludes("../foo").is_err());
+    }
+}
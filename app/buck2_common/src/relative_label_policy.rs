@@ -0,0 +1,151 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Cells can forbid relative labels (`:foo`) in some of their packages, so that labels there can
+//! be moved around or searched for verbatim. The buckconfig `project.forbid_relative_labels`
+//! lists the forbidden directories, comma-separated, with `.` standing for the whole cell.
+
+use std::sync::Arc;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use buck2_core::cells::cell_path::CellPathRef;
+use buck2_core::cells::name::CellName;
+use buck2_core::cells::paths::CellRelativePath;
+use buck2_core::cells::paths::CellRelativePathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_futures::cancellation::CancellationContext;
+use derive_more::Display;
+use dice::DiceComputations;
+use dice::Key;
+use dupe::Dupe;
+
+use crate::legacy_configs::dice::HasLegacyConfigs;
+use crate::legacy_configs::key::BuckconfigKeyRef;
+
+#[derive(PartialEq, Debug, Allocative)]
+struct CellRelativeLabelPolicy {
+    forbidden: Vec<CellRelativePathBuf>,
+}
+
+impl CellRelativeLabelPolicy {
+    fn new(s: &str) -> anyhow::Result<Self> {
+        let mut forbidden = Vec::new();
+        for path_str in s.split(',') {
+            let path_str = path_str.trim();
+            if path_str.is_empty() {
+                continue;
+            }
+            let path = if path_str == "." {
+                CellRelativePath::empty()
+            } else {
+                CellRelativePath::new(ForwardRelativePath::new(path_str)?)
+            };
+            forbidden.push(path.to_buf());
+        }
+        Ok(Self { forbidden })
+    }
+
+    fn allows(&self, package: &CellRelativePath) -> bool {
+        !self.forbidden.iter().any(|p| package.starts_with(p))
+    }
+}
+
+#[derive(Hash, Eq, PartialEq, Clone, Dupe, Display, Debug, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct CellRelativeLabelPolicyKey(CellName);
+
+#[async_trait]
+impl Key for CellRelativeLabelPolicyKey {
+    type Value = buck2_error::Result<Option<Arc<CellRelativeLabelPolicy>>>;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        let s = ctx
+            .get_legacy_config_property(
+                self.0,
+                BuckconfigKeyRef {
+                    section: "project",
+                    property: "forbid_relative_labels",
+                },
+            )
+            .await?;
+        if let Some(s) = s {
+            Ok(Some(Arc::new(CellRelativeLabelPolicy::new(&s)?)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn validity(x: &Self::Value) -> bool {
+        x.is_ok()
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        match (x, y) {
+            (Ok(x), Ok(y)) => x == y,
+            _ => false,
+        }
+    }
+}
+
+#[async_trait]
+pub trait HasRelativeLabelPolicy {
+    /// Whether labels written in `package` may be relative to it.
+    async fn relative_labels_allowed(
+        &mut self,
+        package: CellPathRef<'async_trait>,
+    ) -> buck2_error::Result<bool>;
+}
+
+#[async_trait]
+impl HasRelativeLabelPolicy for DiceComputations<'_> {
+    async fn relative_labels_allowed(
+        &mut self,
+        package: CellPathRef<'async_trait>,
+    ) -> buck2_error::Result<bool> {
+        let policy = self
+            .compute(&CellRelativeLabelPolicyKey(package.cell()))
+            .await??;
+        Ok(policy.map_or(true, |policy| policy.allows(package.path())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allows(policy: &CellRelativeLabelPolicy, package: &str) -> bool {
+        policy.allows(CellRelativePath::unchecked_new(package))
+    }
+
+    #[test]
+    fn test_relative_label_policy() {
+        let policy = CellRelativeLabelPolicy::new("foo/bar, baz,").unwrap();
+        assert!(!allows(&policy, "foo/bar"));
+        assert!(!allows(&policy, "foo/bar/qux"));
+        assert!(!allows(&policy, "baz"));
+        assert!(allows(&policy, "foo"));
+        assert!(allows(&policy, "foo/barqux"));
+        assert!(allows(&policy, ""));
+    }
+
+    #[test]
+    fn test_relative_label_policy_whole_cell() {
+        let policy = CellRelativeLabelPolicy::new(".").unwrap();
+        assert!(!allows(&policy, ""));
+        assert!(!allows(&policy, "foo/bar"));
+
+        let policy = CellRelativeLabelPolicy::new("").unwrap();
+        assert!(allows(&policy, "foo/bar"));
+    }
+}
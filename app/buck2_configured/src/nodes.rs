@@ -8,3 +8,4 @@
  */
 
 pub mod calculation;
+pub mod trimming;
@@ -76,6 +76,8 @@ use starlark_map::small_set::SmallSet;
 
 use crate::calculation::ConfiguredGraphCycleDescriptor;
 use crate::configuration::calculation::ConfigurationCalculation;
+use crate::nodes::trimming::is_trimming_enabled;
+use crate::nodes::trimming::trimmed_configuration;
 use crate::target::TargetConfiguredTargetLabel;
 
 #[derive(Debug, buck2_error::Error)]
@@ -1192,7 +1194,25 @@ async fn compute_configured_target_node(
     } else {
         // We are not caching `ConfiguredTransitionedNodeKey` because this is cheap,
         // and no need to fetch `target_node` again.
-        compute_configured_target_node_no_transition(&key.0.dupe(), target_node, ctx).await
+        let node =
+            compute_configured_target_node_no_transition(&key.0.dupe(), target_node, ctx).await?;
+        if let MaybeCompatible::Compatible(node) = &node {
+            if is_trimming_enabled(ctx).await? {
+                if let Some(cfg) = trimmed_configuration(ctx, node).await? {
+                    if &cfg != key.0.cfg() {
+                        // Forward to the target in the trimmed configuration, which is shared
+                        // by all the configurations which only differ by unread constraints.
+                        return Ok(ctx
+                            .compute(&ConfiguredTransitionedNodeKey {
+                                forward: key.0.dupe(),
+                                transitioned: key.0.unconfigured().configure(cfg),
+                            })
+                            .await??);
+                    }
+                }
+            }
+        }
+        Ok(node)
     }
 }
 
@@ -0,0 +1,232 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Configuration trimming, enabled with the root buckconfig `buck2.trim_configurations`.
+//!
+//! A target is configured in a configuration with only the constraints that it and its deps
+//! read (to resolve their `select`s and check their compatibility), so that the configured
+//! targets which only differ by constraints they don't read are analyzed and built once. The
+//! target in the full configuration becomes a forward node to the target in the trimmed one,
+//! like for a rule transition.
+//!
+//! Transitions can read any constraint, so the targets with transitions (and the targets
+//! depending on them) are not trimmed.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::legacy_configs::dice::HasLegacyConfigs;
+use buck2_common::legacy_configs::key::BuckconfigKeyRef;
+use buck2_core::configuration::compatibility::MaybeCompatible;
+use buck2_core::configuration::constraints::ConstraintKey;
+use buck2_core::configuration::data::ConfigurationData;
+use buck2_core::configuration::data::ConfigurationDataData;
+use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+use buck2_futures::cancellation::CancellationContext;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_node::nodes::configured_frontend::ConfiguredTargetNodeCalculation;
+use buck2_node::nodes::frontend::TargetGraphCalculation;
+use derive_more::Display;
+use dice::DiceComputations;
+use dice::Key;
+use dupe::Dupe;
+use dupe::IterDupedExt;
+use futures::FutureExt;
+
+/// The label of trimmed configurations, so that the trimmed configurations with the same
+/// constraints are equal, whichever platform they come from.
+const TRIMMED_CONFIGURATION_LABEL: &str = "trimmed";
+
+/// The constraints read by a configured target and its deps, or `None` if they may read any
+/// constraint.
+type ConfigurationReads = Option<Arc<BTreeSet<ConstraintKey>>>;
+
+#[derive(Clone, Dupe, Display, Debug, Eq, Hash, PartialEq, Allocative)]
+#[display(fmt = "ConfigurationReadsKey({})", _0)]
+struct ConfigurationReadsKey(ConfiguredTargetLabel);
+
+#[async_trait]
+impl Key for ConfigurationReadsKey {
+    type Value = buck2_error::Result<ConfigurationReads>;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellation: &CancellationContext,
+    ) -> Self::Value {
+        match ctx.get_configured_target_node(&self.0).await? {
+            MaybeCompatible::Compatible(node) => Ok(configuration_reads(ctx, &node).await?),
+            MaybeCompatible::Incompatible(_) => Ok(None),
+        }
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        match (x, y) {
+            (Ok(x), Ok(y)) => x == y,
+            _ => false,
+        }
+    }
+}
+
+async fn configuration_reads(
+    ctx: &mut DiceComputations<'_>,
+    node: &ConfiguredTargetNode,
+) -> anyhow::Result<ConfigurationReads> {
+    let target_node = ctx.get_target_node(node.label().unconfigured()).await?;
+    if target_node.rule.cfg.is_some() || target_node.transition_deps().next().is_some() {
+        return Ok(None);
+    }
+    if let Some(trimmed) = node.forward_target() {
+        return Ok(ctx
+            .compute(&ConfigurationReadsKey(trimmed.label().dupe()))
+            .await??);
+    }
+    // Without execution platforms, the execution deps are configured in the target
+    // configuration.
+    if node.execution_platform_resolution().cfg().cfg() == node.label().cfg() {
+        return Ok(None);
+    }
+
+    let mut reads: BTreeSet<ConstraintKey> = node.inspected_constraints().duped().collect();
+    let deps: Vec<ConfigurationReadsKey> = node
+        .target_deps()
+        .chain(node.toolchain_deps())
+        .map(|dep| ConfigurationReadsKey(dep.label().dupe()))
+        .collect();
+    let dep_reads = ctx
+        .compute_join(deps.iter(), |ctx, key| {
+            async move { ctx.compute(key).await }.boxed()
+        })
+        .await;
+    for dep_reads in dep_reads {
+        match dep_reads?? {
+            Some(dep_reads) => reads.extend(dep_reads.iter().duped()),
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(Arc::new(reads)))
+}
+
+pub(crate) async fn is_trimming_enabled(ctx: &mut DiceComputations<'_>) -> anyhow::Result<bool> {
+    let root_cell = ctx.get_cell_resolver().await?.root_cell();
+    Ok(ctx
+        .parse_legacy_config_property(
+            root_cell,
+            BuckconfigKeyRef {
+                section: "buck2",
+                property: "trim_configurations",
+            },
+        )
+        .await?
+        .unwrap_or(false))
+}
+
+/// The configuration of `node` with only the constraints it and its deps read, or `None` if it
+/// can't be trimmed.
+pub async fn trimmed_configuration(
+    ctx: &mut DiceComputations<'_>,
+    node: &ConfiguredTargetNode,
+) -> anyhow::Result<Option<ConfigurationData>> {
+    let cfg = node.label().cfg();
+    if !cfg.is_bound() || node.label().exec_cfg().is_some() {
+        return Ok(None);
+    }
+    let Some(reads) = configuration_reads(ctx, node).await? else {
+        return Ok(None);
+    };
+    Ok(Some(trim(cfg, &reads)?))
+}
+
+fn trim(
+    cfg: &ConfigurationData,
+    reads: &BTreeSet<ConstraintKey>,
+) -> anyhow::Result<ConfigurationData> {
+    let constraints = cfg
+        .data()?
+        .constraints
+        .iter()
+        .filter(|(key, _)| reads.contains(*key))
+        .map(|(key, value)| (key.dupe(), value.dupe()))
+        .collect();
+    ConfigurationData::from_platform(
+        TRIMMED_CONFIGURATION_LABEL.to_owned(),
+        ConfigurationDataData::new(constraints),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use buck2_core::configuration::constraints::ConstraintValue;
+
+    use super::*;
+
+    fn configuration(label: &str, constraints: &[(&str, &str)]) -> ConfigurationData {
+        ConfigurationData::from_platform(
+            label.to_owned(),
+            ConfigurationDataData::new(
+                constraints
+                    .iter()
+                    .map(|(key, value)| {
+                        (
+                            ConstraintKey::testing_new(key),
+                            ConstraintValue::testing_new(value),
+                        )
+                    })
+                    .collect::<BTreeMap<_, _>>(),
+            ),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_trim_keeps_read_constraints() {
+        let cfg = configuration(
+            "root//:linux",
+            &[("root//:os", "root//:linux"), ("root//:cpu", "root//:x86")],
+        );
+        let reads = BTreeSet::from([ConstraintKey::testing_new("root//:os")]);
+        assert_eq!(
+            configuration("trimmed", &[("root//:os", "root//:linux")]),
+            trim(&cfg, &reads).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_trim_merges_configurations_differing_by_unread_constraints() {
+        let reads = BTreeSet::from([ConstraintKey::testing_new("root//:os")]);
+        let x86 = configuration(
+            "root//:linux-x86",
+            &[("root//:os", "root//:linux"), ("root//:cpu", "root//:x86")],
+        );
+        let arm = configuration(
+            "root//:linux-arm",
+            &[("root//:os", "root//:linux"), ("root//:cpu", "root//:arm")],
+        );
+        let macos = configuration(
+            "root//:macos-arm",
+            &[("root//:os", "root//:macos"), ("root//:cpu", "root//:arm")],
+        );
+        assert_eq!(trim(&x86, &reads).unwrap(), trim(&arm, &reads).unwrap());
+        assert_ne!(trim(&arm, &reads).unwrap(), trim(&macos, &reads).unwrap());
+    }
+
+    #[test]
+    fn test_trim_without_reads() {
+        let cfg = configuration("root//:linux", &[("root//:os", "root//:linux")]);
+        assert_eq!(
+            configuration("trimmed", &[]),
+            trim(&cfg, &BTreeSet::new()).unwrap()
+        );
+    }
+}
@@ -61,6 +61,15 @@ enum ConfigurationLookupError {
     ConfigFoundByHashLabelMismatch(ConfigurationData, BoundConfigurationId),
 }
 
+#[derive(Debug, buck2_error::Error)]
+enum ConfigurationHashCollisionError {
+    #[error(
+        "Configurations `{0}` and `{1}` have the same output hash and would write to the same \
+        output paths. Rename one of the platforms or change its constraints."
+    )]
+    OutputHashCollision(String, String),
+}
+
 /// The inner PlatformConfigurationData is interned as the same configuration could be formed through
 /// paths (as many transitions are associative).
 #[derive(
@@ -92,9 +101,13 @@ impl ConfigurationData {
     /// Produces a "bound" configuration for a platform. The label should be a unique identifier for the data.
     pub fn from_platform(label: String, data: ConfigurationDataData) -> anyhow::Result<Self> {
         let label = BoundConfigurationLabel::new(label)?;
-        Ok(Self::from_data(HashedConfigurationPlatform::new(
-            ConfigurationPlatform::Bound(label, data),
-        )))
+        let data = HashedConfigurationPlatform::new(ConfigurationPlatform::Bound(label, data));
+        // Every bound configuration is created here, whether it comes from a platform, an
+        // execution platform or a transition, so this catches collisions between all of them.
+        if let Some(existing) = INTERNER.get(ConfigurationHashRef(data.output_hash.as_str())) {
+            check_output_hash_collision(&existing, &data)?;
+        }
+        Ok(Self::from_data(data))
     }
 
     pub fn unspecified() -> Self {
@@ -369,6 +382,24 @@ impl std::hash::Hash for HashedConfigurationPlatform {
     }
 }
 
+/// The output hash is all that distinguishes the output paths of two configurations, so two
+/// different configurations with the same hash would race writing the same files in buck-out.
+fn check_output_hash_collision(
+    existing: &HashedConfigurationPlatform,
+    new: &HashedConfigurationPlatform,
+) -> anyhow::Result<()> {
+    if existing.output_hash == new.output_hash
+        && existing.configuration_platform != new.configuration_platform
+    {
+        return Err(ConfigurationHashCollisionError::OutputHashCollision(
+            existing.full_name.clone(),
+            new.full_name.clone(),
+        )
+        .into());
+    }
+    Ok(())
+}
+
 impl HashedConfigurationPlatform {
     fn new(configuration_platform: ConfigurationPlatform) -> Self {
         // TODO(cjhopman): Should this be a crypto hasher?
@@ -396,10 +427,14 @@ mod tests {
     use std::collections::BTreeMap;
 
     use crate::configuration::bound_id::BoundConfigurationId;
+    use crate::configuration::bound_label::BoundConfigurationLabel;
     use crate::configuration::constraints::ConstraintKey;
     use crate::configuration::constraints::ConstraintValue;
+    use crate::configuration::data::check_output_hash_collision;
     use crate::configuration::data::ConfigurationData;
     use crate::configuration::data::ConfigurationDataData;
+    use crate::configuration::data::ConfigurationPlatform;
+    use crate::configuration::data::HashedConfigurationPlatform;
     use crate::target::label::label::TargetLabel;
 
     /// We don't want the output hash to change by accident. This test is here to assert that it
@@ -463,4 +498,52 @@ mod tests {
         .unwrap();
         assert_eq!(configuration, looked_up);
     }
+
+    fn bound_platform(label: &str, value: &str) -> HashedConfigurationPlatform {
+        HashedConfigurationPlatform::new(ConfigurationPlatform::Bound(
+            BoundConfigurationLabel::new(label.to_owned()).unwrap(),
+            ConfigurationDataData {
+                constraints: BTreeMap::from_iter([(
+                    ConstraintKey(TargetLabel::testing_parse("foo//bar:c")),
+                    ConstraintValue(TargetLabel::testing_parse(value)),
+                )]),
+            },
+        ))
+    }
+
+    #[test]
+    fn test_output_hash_collision() {
+        let x = bound_platform("cfg//:x", "foo//bar:v");
+        let mut y = bound_platform("cfg//:y", "foo//bar:w");
+        assert!(check_output_hash_collision(&x, &y).is_ok());
+
+        // Real collisions are too unlikely to construct, so force one.
+        y.output_hash = x.output_hash.clone();
+        let err = check_output_hash_collision(&x, &y).unwrap_err().to_string();
+        assert!(err.contains(&x.full_name), "{}", err);
+        assert!(err.contains(&y.full_name), "{}", err);
+    }
+
+    #[test]
+    fn test_same_configuration_is_not_a_collision() {
+        // Transitions produce the same configurations over and over again.
+        let x = bound_platform("cfg//:transitioned", "foo//bar:v");
+        assert!(check_output_hash_collision(
+            &x,
+            &bound_platform("cfg//:transitioned", "foo//bar:v")
+        )
+        .is_ok());
+
+        let data = || ConfigurationDataData {
+            constraints: BTreeMap::from_iter([(
+                ConstraintKey(TargetLabel::testing_parse("foo//bar:c")),
+                ConstraintValue(TargetLabel::testing_parse("foo//bar:transitioned")),
+            )]),
+        };
+        let first =
+            ConfigurationData::from_platform("cfg//:transitioned".to_owned(), data()).unwrap();
+        let second =
+            ConfigurationData::from_platform("cfg//:transitioned".to_owned(), data()).unwrap();
+        assert_eq!(first, second);
+    }
 }
@@ -153,6 +153,8 @@ pub enum Executor {
         remote_cache_enabled: bool,
         remote_dep_file_cache_enabled: bool,
         dependencies: Vec<RemoteExecutorDependency>,
+        /// Properties merged over `re_properties` for actions of the given categories.
+        re_properties_by_category: Arc<SortedMap<String, RePlatformFields>>,
     },
 }
 
@@ -175,6 +177,7 @@ impl Display for Executor {
                 remote_cache_enabled,
                 remote_dep_file_cache_enabled,
                 dependencies: _,
+                re_properties_by_category: _,
             } => {
                 let cache = match remote_cache_enabled {
                     true => "enabled",
@@ -263,6 +266,8 @@ impl Default for CacheUploadBehavior {
 pub struct CommandGenerationOptions {
     pub path_separator: PathSeparatorKind,
     pub output_paths_behavior: OutputPathsBehavior,
+    /// Whether to ask RE to run commands on persistent workers, for workers which support it.
+    pub use_remote_persistent_workers: bool,
 }
 
 #[derive(Debug, Eq, PartialEq, Hash, Allocative)]
@@ -288,13 +293,138 @@ pub enum HybridExecutionLevel {
 }
 
 impl CommandExecutorConfig {
+    /// The config to execute actions of the given category with: the same config, with the
+    /// properties for the category merged over the RE properties, if there are any.
+    pub fn for_category(self: &Arc<Self>, category: &str) -> Arc<CommandExecutorConfig> {
+        let Executor::RemoteEnabled {
+            executor,
+            re_properties,
+            re_use_case,
+            re_action_key,
+            cache_upload_behavior,
+            remote_cache_enabled,
+            remote_dep_file_cache_enabled,
+            dependencies,
+            re_properties_by_category,
+        } = &self.executor
+        else {
+            return self.dupe();
+        };
+        let Some(overrides) = re_properties_by_category.get(category) else {
+            return self.dupe();
+        };
+
+        let mut properties: SmallMap<String, String> = re_properties
+            .properties
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        for (k, v) in overrides.properties.iter() {
+            properties.insert(k.clone(), v.clone());
+        }
+        Arc::new(CommandExecutorConfig {
+            executor: Executor::RemoteEnabled {
+                executor: executor.clone(),
+                re_properties: RePlatformFields {
+                    properties: Arc::new(properties.into_iter().collect()),
+                },
+                re_use_case: *re_use_case,
+                re_action_key: re_action_key.clone(),
+                cache_upload_behavior: *cache_upload_behavior,
+                remote_cache_enabled: *remote_cache_enabled,
+                remote_dep_file_cache_enabled: *remote_dep_file_cache_enabled,
+                dependencies: dependencies.clone(),
+                re_properties_by_category: re_properties_by_category.dupe(),
+            },
+            options: self.options,
+        })
+    }
+
     pub fn testing_local() -> Arc<CommandExecutorConfig> {
         Arc::new(CommandExecutorConfig {
             executor: Executor::Local(LocalExecutorOptions::default()),
             options: CommandGenerationOptions {
                 path_separator: PathSeparatorKind::system_default(),
                 output_paths_behavior: Default::default(),
+                use_remote_persistent_workers: false,
             },
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn platform(properties: &[(&str, &str)]) -> RePlatformFields {
+        RePlatformFields {
+            properties: Arc::new(
+                properties
+                    .iter()
+                    .map(|(k, v)| ((*k).to_owned(), (*v).to_owned()))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn remote_config(
+        re_properties: RePlatformFields,
+        re_properties_by_category: &[(&str, RePlatformFields)],
+    ) -> Arc<CommandExecutorConfig> {
+        Arc::new(CommandExecutorConfig {
+            executor: Executor::RemoteEnabled {
+                executor: RemoteEnabledExecutor::Remote(RemoteExecutorOptions::default()),
+                re_properties,
+                re_use_case: RemoteExecutorUseCase::buck2_default(),
+                re_action_key: None,
+                cache_upload_behavior: CacheUploadBehavior::Disabled,
+                remote_cache_enabled: true,
+                remote_dep_file_cache_enabled: false,
+                dependencies: Vec::new(),
+                re_properties_by_category: Arc::new(
+                    re_properties_by_category
+                        .iter()
+                        .map(|(c, p)| ((*c).to_owned(), p.clone()))
+                        .collect(),
+                ),
+            },
+            options: CommandGenerationOptions {
+                path_separator: PathSeparatorKind::Unix,
+                output_paths_behavior: OutputPathsBehavior::Strict,
+                use_remote_persistent_workers: false,
+            },
+        })
+    }
+
+    fn re_properties(config: &CommandExecutorConfig) -> &RePlatformFields {
+        match &config.executor {
+            Executor::RemoteEnabled { re_properties, .. } => re_properties,
+            Executor::Local(_) => panic!("expected a remote enabled executor"),
+        }
+    }
+
+    #[test]
+    fn test_for_category() {
+        let config = remote_config(
+            platform(&[("platform", "linux"), ("pool", "default")]),
+            &[("cuda_compile", platform(&[("pool", "gpu"), ("gpu", "1")]))],
+        );
+
+        let cuda = config.for_category("cuda_compile");
+        assert_eq!(
+            &platform(&[("gpu", "1"), ("platform", "linux"), ("pool", "gpu")]),
+            re_properties(&cuda)
+        );
+        // The category properties are kept, so the config can be specialized again.
+        assert_eq!(cuda, cuda.for_category("cuda_compile"));
+
+        // Other categories use the config as is.
+        assert!(Arc::ptr_eq(&config, &config.for_category("cxx_compile")));
+    }
+
+    #[test]
+    fn test_for_category_local() {
+        let config = CommandExecutorConfig::testing_local();
+        assert!(Arc::ptr_eq(&config, &config.for_category("cuda_compile")));
+    }
+}
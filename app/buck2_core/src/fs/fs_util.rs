@@ -357,6 +357,127 @@ pub fn copy<P: AsRef<AbsPath>, Q: AsRef<AbsPath>>(from: P, to: Q) -> Result<u64,
     )
 }
 
+/// Copy a file by cloning its extents (`FICLONE` on Linux, `clonefile` on macOS), which is only
+/// supported by copy-on-write filesystems such as btrfs, XFS or APFS. `to` must not exist.
+pub fn reflink<P: AsRef<AbsPath>, Q: AsRef<AbsPath>>(from: P, to: Q) -> Result<(), IoError> {
+    let _guard = IoCounterKey::Copy.guard();
+    make_error!(
+        reflink_impl(
+            from.as_ref().as_maybe_relativized(),
+            to.as_ref().as_maybe_relativized(),
+        ),
+        format!(
+            "reflink(from={}, to={})",
+            P::as_ref(&from).display(),
+            Q::as_ref(&to).display()
+        ),
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn reflink_impl(from: &Path, to: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // `_IOW(0x94, 9, int)` from `linux/fs.h`.
+    const FICLONE: libc::c_ulong = 0x40049409;
+
+    let src = File::open(from)?;
+    let dest = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(to)?;
+    // SAFETY: both file descriptors are open for the duration of the call.
+    if unsafe { libc::ioctl(dest.as_raw_fd(), FICLONE as _, src.as_raw_fd()) } == -1 {
+        let e = io::Error::last_os_error();
+        drop(dest);
+        let _ignored = fs::remove_file(to);
+        return Err(e);
+    }
+    dest.set_permissions(src.metadata()?.permissions())
+}
+
+#[cfg(target_os = "macos")]
+fn reflink_impl(from: &Path, to: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let from = CString::new(from.as_os_str().as_bytes())?;
+    let to = CString::new(to.as_os_str().as_bytes())?;
+    // SAFETY: both paths are valid NUL-terminated strings.
+    if unsafe { libc::clonefile(from.as_ptr(), to.as_ptr(), 0) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn reflink_impl(_from: &Path, _to: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reflinks are not supported on this platform",
+    ))
+}
+
+/// Whether `path` is on a network filesystem (NFS, SMB, ...) rather than a local disk. Always
+/// `false` on platforms where this isn't detected.
+pub fn is_network_filesystem<P: AsRef<AbsPath>>(path: P) -> Result<bool, IoError> {
+    let _guard = IoCounterKey::Stat.guard();
+    make_error!(
+        is_network_filesystem_impl(path.as_ref().as_maybe_relativized()),
+        format!("statfs({})", P::as_ref(&path).display()),
+    )
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn statfs(path: &Path) -> io::Result<libc::statfs> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: `statfs` is plain old data, and is only read if the call succeeds.
+    let mut buf: libc::statfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is a valid NUL-terminated string, and `buf` is valid for writes.
+    if unsafe { libc::statfs(path.as_ptr(), &mut buf) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(buf)
+}
+
+#[cfg(target_os = "linux")]
+fn is_network_filesystem_impl(path: &Path) -> io::Result<bool> {
+    // Magic numbers from `linux/magic.h` and `statfs(2)`.
+    const NETWORK_FILESYSTEMS: &[u32] = &[
+        0x6969,     // NFS
+        0x517b,     // SMB
+        0xfe534d42, // SMB2
+        0xff534d42, // CIFS
+        0x5346414f, // AFS
+        0x00c36400, // Ceph
+        0x01021997, // 9P
+    ];
+    // `f_type` is signed on some architectures, the magic numbers are the low 32 bits.
+    let f_type = statfs(path)?.f_type as u32;
+    Ok(NETWORK_FILESYSTEMS.contains(&f_type))
+}
+
+#[cfg(target_os = "macos")]
+fn is_network_filesystem_impl(path: &Path) -> io::Result<bool> {
+    use std::ffi::CStr;
+
+    const NETWORK_FILESYSTEMS: &[&str] = &["nfs", "smbfs", "afpfs", "webdav", "cifs"];
+    let buf = statfs(path)?;
+    // SAFETY: the kernel NUL-terminates `f_fstypename`.
+    let name = unsafe { CStr::from_ptr(buf.f_fstypename.as_ptr()) };
+    Ok(NETWORK_FILESYSTEMS
+        .iter()
+        .any(|fs| fs.as_bytes() == name.to_bytes()))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn is_network_filesystem_impl(_path: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
 pub fn read_link<P: AsRef<AbsPath>>(path: P) -> Result<PathBuf, IoError> {
     let _guard = IoCounterKey::ReadLink.guard();
     make_error!(
@@ -714,6 +835,27 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn reflink_copies_or_leaves_nothing() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let root = AbsPath::new(tempdir.path())?;
+        let src = root.join("src");
+        let dest = root.join("dest");
+        write(&src, "contents")?;
+
+        // Whether this works depends on the filesystem of the temporary directory.
+        match fs_util::reflink(&src, &dest) {
+            Ok(()) => {
+                assert_eq!("contents", read_to_string(&dest)?);
+                // The clone is independent of its source.
+                write(&dest, "changed")?;
+                assert_eq!("contents", read_to_string(&src)?);
+            }
+            Err(..) => assert_matches!(symlink_metadata(&dest), Err(..)),
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_symlink_with_target_length_over_max_path() -> anyhow::Result<()> {
         // In Windows, the maximum length of a path is 260.
@@ -79,6 +79,18 @@ enum TargetPatternParseError {
     ConfigurationPartMustBeEnclosedInParentheses,
     #[error("Pattern `{0}` is parsed as `{1}` which crosses cell boundaries. Try `{2}` instead")]
     PatternCrossesCellBoundaries(String, String, String),
+    #[error(
+        "Unclosed `[` in providers. Sub-targets are written as `//foo:bar[name]`, and nested \
+        sub-targets as `//foo:bar[name][inner]`"
+    )]
+    UnclosedProviders,
+    #[error("Unexpected `{0}` after providers, expected `[` to start a nested sub-target")]
+    UnexpectedAfterProviders(String),
+    #[error(
+        "Providers can only be given for a single target, not for all the targets of a package \
+        or a recursive pattern"
+    )]
+    ProvidersOnMultipleTargets,
 }
 
 pub fn display_precise_pattern<'a, T: PatternType>(
@@ -112,22 +124,21 @@ pub(crate) fn split_providers_name(s: &str) -> anyhow::Result<(&str, ProvidersNa
             names.push(ProviderName::new(p.to_owned())?);
             r
         } else {
-            return Err(anyhow::anyhow!(
-                "target pattern with `[` must end with `]` to mark end of providers set label"
-            ));
+            return Err(TargetPatternParseError::UnclosedProviders.into());
         };
 
         while !remaining.is_empty() {
-            if let Some(("", r)) = split1_opt_ascii(remaining, AsciiChar::new('[')) {
-                if let Some((p, r)) = split1_opt_ascii(r, AsciiChar::new(']')) {
-                    names.push(ProviderName::new(p.to_owned())?);
-                    remaining = r;
-                    continue;
-                }
-            }
-            return Err(anyhow::anyhow!(
-                "target pattern with `[` must end with `]` to mark end of providers set label"
-            ));
+            let Some(("", r)) = split1_opt_ascii(remaining, AsciiChar::new('[')) else {
+                return Err(TargetPatternParseError::UnexpectedAfterProviders(
+                    remaining.to_owned(),
+                )
+                .into());
+            };
+            let Some((p, r)) = split1_opt_ascii(r, AsciiChar::new(']')) else {
+                return Err(TargetPatternParseError::UnclosedProviders.into());
+            };
+            names.push(ProviderName::new(p.to_owned())?);
+            remaining = r;
         }
 
         Ok((
@@ -631,6 +642,9 @@ fn lex_provider_pattern<'a>(
         .into(),
         Some((package, target)) => {
             let (target, providers) = split_providers_name(target)?;
+            if target.is_empty() && providers != ProvidersName::Default {
+                return Err(TargetPatternParseError::ProvidersOnMultipleTargets.into());
+            }
             let target_name = TargetName::new(target)?;
             let extra = ProvidersPatternExtra { providers };
             PatternData::TargetInPackage {
@@ -653,6 +667,9 @@ fn lex_provider_pattern<'a>(
                 .into()
             } else if !pattern.is_empty() {
                 let (pattern, providers) = split_providers_name(pattern)?;
+                if pattern == "..." || pattern.ends_with("/...") {
+                    return Err(TargetPatternParseError::ProvidersOnMultipleTargets.into());
+                }
                 PatternDataOrAmbiguous::Ambiguous {
                     pattern,
                     strip_package_trailing_slash,
@@ -1682,6 +1699,20 @@ mod tests {
                 &alias_resolver(),
             )?
         );
+        assert_eq!(
+            mk_providers(
+                "root",
+                "package/path",
+                "target",
+                Some(&["shared", "headers"]),
+            ),
+            ParsedPattern::parse_precise(
+                "//package/path:target[shared][headers]",
+                CellName::testing_new("root"),
+                &resolver(),
+                &alias_resolver(),
+            )?
+        );
 
         let (target_label, providers) = ParsedPattern::parse_precise(
             "//package/path:target#flavor",
@@ -1892,7 +1923,7 @@ mod tests {
             ),
             &[
                 "//package/path:target[unclosed",
-                "target pattern with `[` must end with `]` to mark end of providers set label",
+                "Unclosed `[` in providers",
             ],
         );
         fails(
@@ -1904,9 +1935,36 @@ mod tests {
             ),
             &[
                 "//package/path:target[out]wrong",
-                "target pattern with `[` must end with `]` to mark end of providers set label",
+                "Unexpected `wrong` after providers",
             ],
         );
+        fails(
+            ParsedPattern::<ProvidersPatternExtra>::parse_precise(
+                "//package/path:target[out][unclosed",
+                CellName::testing_new("root"),
+                &resolver(),
+                &alias_resolver(),
+            ),
+            &["Unclosed `[` in providers"],
+        );
+        fails(
+            ParsedPattern::<ProvidersPatternExtra>::parse_precise(
+                "//package/path/...[out]",
+                CellName::testing_new("root"),
+                &resolver(),
+                &alias_resolver(),
+            ),
+            &["Providers can only be given for a single target"],
+        );
+        fails(
+            ParsedPattern::<ProvidersPatternExtra>::parse_precise(
+                "//package/path:[out]",
+                CellName::testing_new("root"),
+                &resolver(),
+                &alias_resolver(),
+            ),
+            &["Providers can only be given for a single target"],
+        );
         fails(
             ParsedPattern::<ProvidersPatternExtra>::parse_precise(
                 "$(exe my macro)",
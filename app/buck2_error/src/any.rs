@@ -47,10 +47,11 @@ fn maybe_add_context_from_metadata(mut e: crate::Error, context: &dyn StdError)
         if !metadata.tags.is_empty() {
             e = e.tag(metadata.tags.iter().copied());
         }
-        e
-    } else {
-        e
     }
+    if let Some(diagnostic) = request_value::<buck2_data::ErrorDiagnostic>(context) {
+        e = e.context(diagnostic);
+    }
+    e
 }
 
 pub(crate) fn recover_crate_error(
@@ -313,4 +314,30 @@ mod tests {
         let e: crate::Error = e.into();
         assert_eq!(e.get_tier(), Some(crate::Tier::Tier0));
     }
+
+    #[derive(Debug, derive_more::Display)]
+    struct DiagnosticError;
+
+    impl StdError for DiagnosticError {
+        fn provide<'a>(&'a self, request: &mut Request<'a>) {
+            request.provide_value(buck2_data::ErrorDiagnostic {
+                path: "foo/BUCK".to_owned(),
+                begin_line: 1,
+                begin_column: 1,
+                end_line: 1,
+                end_column: 4,
+                code: "DiagnosticError".to_owned(),
+                suggestion: None,
+            });
+        }
+    }
+
+    #[test]
+    fn test_diagnostic() {
+        let e: anyhow::Error = DiagnosticError.into();
+        let e: crate::Error = e.context("wrapper").into();
+        assert_eq!(e.diagnostic().map(|d| d.path.as_str()), Some("foo/BUCK"));
+        // The diagnostic isn't part of the message.
+        assert!(!format!("{:?}", e).contains("foo/BUCK"));
+    }
 }
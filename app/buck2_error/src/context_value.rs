@@ -18,6 +18,8 @@ pub enum ContextValue {
     Tier(Tier),
     Tags(SmallVec<[crate::ErrorTag; 1]>),
     Typed(Arc<dyn TypedContext>),
+    /// Where in a Starlark file the error is.
+    Diagnostic(Arc<buck2_data::ErrorDiagnostic>),
 }
 
 impl ContextValue {
@@ -28,6 +30,8 @@ impl ContextValue {
             // Displaying the category in the middle of an error message doesn't seem useful
             Self::Tier(_) => false,
             Self::Tags(_) => false,
+            // This is already part of the message of the Starlark error.
+            Self::Diagnostic(_) => false,
         }
     }
 
@@ -46,6 +50,9 @@ impl ContextValue {
             (ContextValue::Typed(left), ContextValue::Typed(right)) => {
                 assert!(left.eq(&**right))
             }
+            (ContextValue::Diagnostic(a), ContextValue::Diagnostic(b)) => {
+                assert_eq!(a, b);
+            }
             (_, _) => panic!("context variants don't match!"),
         }
     }
@@ -58,6 +65,7 @@ impl std::fmt::Display for ContextValue {
             Self::Tier(category) => write!(f, "{:?}", category),
             Self::Tags(tags) => write!(f, "{:?}", tags),
             Self::Typed(v) => std::fmt::Display::fmt(v, f),
+            Self::Diagnostic(d) => write!(f, "{:?}", d),
         }
     }
 }
@@ -96,6 +104,12 @@ impl Tier {
     }
 }
 
+impl From<buck2_data::ErrorDiagnostic> for ContextValue {
+    fn from(value: buck2_data::ErrorDiagnostic) -> Self {
+        ContextValue::Diagnostic(Arc::new(value))
+    }
+}
+
 impl From<Tier> for ContextValue {
     fn from(value: Tier) -> Self {
         ContextValue::Tier(value)
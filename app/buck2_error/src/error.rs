@@ -88,6 +88,17 @@ impl Error {
         self.root().action_error()
     }
 
+    /// Where in a Starlark file the error is. If several errors in the chain have a location, this
+    /// is the innermost one.
+    pub fn diagnostic(&self) -> Option<&buck2_data::ErrorDiagnostic> {
+        self.iter_context()
+            .filter_map(|context| match context {
+                ContextValue::Diagnostic(d) => Some(&**d),
+                _ => None,
+            })
+            .last()
+    }
+
     pub(crate) fn iter_context<'a>(&'a self) -> impl Iterator<Item = &'a ContextValue> {
         self.iter_kinds().filter_map(|kind| match kind {
             ErrorKind::WithContext(ctx, _) => Some(ctx),
@@ -87,6 +87,9 @@ where
                         ReSession(re_session) => {
                             self.re_state.add_re_session(re_session);
                         }
+                        ReLargeUploadProgress(progress) => {
+                            self.re_state.update_large_upload(progress);
+                        }
                         Snapshot(snapshot) => {
                             self.re_state.update(snapshot);
                             self.two_snapshots.update(event.timestamp(), snapshot);
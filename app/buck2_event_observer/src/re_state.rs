@@ -7,6 +7,8 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
+
 use superconsole::DrawMode;
 use superconsole::Line;
 use superconsole::Lines;
@@ -18,6 +20,8 @@ use crate::two_snapshots::TwoSnapshots;
 pub struct ReState {
     session_id: Option<String>,
     first_snapshot: Option<buck2_data::Snapshot>,
+    /// Bytes sent and total bytes of the large blobs being uploaded, by digest.
+    large_uploads: HashMap<String, (u64, u64)>,
 }
 
 impl ReState {
@@ -25,6 +29,7 @@ impl ReState {
         Self {
             session_id: None,
             first_snapshot: None,
+            large_uploads: HashMap::new(),
         }
     }
 
@@ -38,6 +43,33 @@ impl ReState {
         }
     }
 
+    pub fn update_large_upload(&mut self, progress: &buck2_data::ReLargeUploadProgress) {
+        if progress.bytes_sent >= progress.total_bytes {
+            self.large_uploads.remove(&progress.digest);
+        } else {
+            self.large_uploads.insert(
+                progress.digest.clone(),
+                (progress.bytes_sent, progress.total_bytes),
+            );
+        }
+    }
+
+    fn render_large_uploads(&self) -> Option<String> {
+        if self.large_uploads.is_empty() {
+            return None;
+        }
+        let (sent, total) = self
+            .large_uploads
+            .values()
+            .fold((0, 0), |(sent, total), (s, t)| (sent + s, total + t));
+        Some(format!(
+            "Uploading {} large blob(s): {} / {}",
+            self.large_uploads.len(),
+            HumanizedBytes::new(sent),
+            HumanizedBytes::new(total),
+        ))
+    }
+
     pub fn render_header(
         &self,
         two_snapshots: &TwoSnapshots,
@@ -204,13 +236,15 @@ impl ReState {
         detailed: bool,
         draw_mode: DrawMode,
     ) -> anyhow::Result<Lines> {
-        let header = match self.render_header(two_snapshots, draw_mode) {
-            Some(header) => header,
-            None => return Ok(Lines::new()),
-        };
-        let mut lines = vec![Line::unstyled(&header)?];
-        if detailed {
-            lines.extend(self.render_detailed(two_snapshots)?);
+        let mut lines = Vec::new();
+        if let Some(header) = self.render_header(two_snapshots, draw_mode) {
+            lines.push(Line::unstyled(&header)?);
+            if detailed {
+                lines.extend(self.render_detailed(two_snapshots)?);
+            }
+        }
+        if let (DrawMode::Normal, Some(uploads)) = (draw_mode, self.render_large_uploads()) {
+            lines.push(Line::unstyled(&uploads)?);
         }
         Ok(Lines(lines))
     }
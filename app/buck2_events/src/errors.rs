@@ -52,5 +52,6 @@ pub fn create_error_report(err: &buck2_error::Error) -> buck2_data::ErrorReport
         source_location,
         tags: err.tags().map(|t| *t as i32),
         sub_error_categories,
+        diagnostic: err.diagnostic().cloned(),
     }
 }
@@ -71,11 +71,12 @@ impl<'a> ArtifactValueBuilder<'a> {
         src_value: &ArtifactValue,
         src: &ProjectRelativePath,
         dest: &ProjectRelativePath,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<ActionDirectoryEntry<ActionSharedDirectory>> {
         insert_artifact(&mut self.builder, src, src_value)?;
-        let entry = DirectoryEntry::Leaf(new_symlink(self.project_fs.relative_path(src, dest))?);
-        self.builder.insert(dest, entry)?;
-        Ok(())
+        let member = new_symlink(self.project_fs.relative_path(src, dest))?;
+        self.builder
+            .insert(dest, DirectoryEntry::Leaf(member.dupe()))?;
+        Ok(DirectoryEntry::Leaf(member))
     }
 
     /// Takes an input `src_value`, adds it to the builder at `src`. Then
@@ -55,6 +55,7 @@ use crate::artifact_value::ArtifactValue;
 use crate::digest::CasDigestFromReExt;
 use crate::digest::CasDigestToReExt;
 use crate::digest_config::DigestConfig;
+use crate::execute::action_digest::ActionDigest;
 use crate::re::manager::ManagedRemoteExecutionClient;
 
 #[allocative::root]
@@ -277,6 +278,33 @@ pub async fn re_directory_to_re_tree(
     })
 }
 
+/// Downloads the input root of an action from the CAS, e.g. to compare the inputs of two actions.
+pub async fn download_action_input_directory(
+    action_digest: &ActionDigest,
+    client: &ManagedRemoteExecutionClient,
+    use_case: RemoteExecutorUseCase,
+    digest_config: DigestConfig,
+) -> anyhow::Result<ActionDirectoryBuilder> {
+    let action = client
+        .download_typed_blobs::<RE::Action>(None, vec![action_digest.to_re()], use_case)
+        .await?
+        .into_iter()
+        .next()
+        .with_context(|| format!("Action `{}` was not downloaded", action_digest))?;
+    let input_root = action
+        .input_root_digest
+        .with_context(|| format!("Action `{}` has no input root", action_digest))?;
+    let input_root = FileDigest::from_grpc(&input_root, digest_config)?;
+    let root = client
+        .download_typed_blobs::<RE::Directory>(None, vec![input_root.to_re()], use_case)
+        .await?
+        .into_iter()
+        .next()
+        .with_context(|| format!("Input root `{}` was not downloaded", input_root))?;
+    let tree = re_directory_to_re_tree(root, client, use_case).await?;
+    re_tree_to_directory(&tree, &Utc::now(), digest_config)
+}
+
 /// Constructs a `Directory` from an `RE::Tree`. As long as the
 /// `RE::Tree` is valid (i.e. nothing is broken in the RE side), this
 /// should always succeed.
@@ -186,13 +186,16 @@ fn build_file_metadata(
     static SEMAPHORE: Lazy<Semaphore> = Lazy::new(|| Semaphore::new(100));
     let exec_path = disk_path.clone();
     let executable = blocking_executor.execute_io_inline(move || Ok(exec_path.executable()));
-    let file_digest =
-        tokio::task::spawn_blocking(move || FileDigest::from_file(&disk_path, digest_config));
 
     async move {
         let _permit = SEMAPHORE.acquire().await.unwrap();
         let hashing_start = Instant::now();
-        let file_digest = file_digest.await??;
+        // Only start hashing once we have a permit: the files of a directory are all hashed at
+        // once otherwise, each on its own blocking thread.
+        let file_digest = tokio::task::spawn_blocking(move || {
+            FileDigest::from_output_file(&disk_path, digest_config)
+        })
+        .await??;
         let hashing_duration = HashingInfo::new(hashing_start.elapsed(), 1);
         let file_metadata = FileMetadata {
             digest: TrackedFileDigest::new(file_digest, digest_config.as_cas_digest_config()),
@@ -23,6 +23,7 @@ use buck2_futures::cancellation::CancellationContext;
 use dupe::Dupe;
 use remote_execution as RE;
 use sorted_vector_map::SortedVectorMap;
+use starlark_map::sorted_map::SortedMap;
 
 use super::cache_uploader::CacheUploadResult;
 use crate::artifact::fs::ExecutorFs;
@@ -44,6 +45,7 @@ use crate::execute::request::CommandExecutionInput;
 use crate::execute::request::CommandExecutionRequest;
 use crate::execute::request::ExecutorPreference;
 use crate::execute::request::OutputType;
+use crate::execute::request::WorkerSpec;
 use crate::execute::result::CommandExecutionMetadata;
 use crate::execute::result::CommandExecutionResult;
 
@@ -111,6 +113,10 @@ impl CommandExecutor {
         &self.0.re_platform
     }
 
+    pub fn uses_remote_persistent_workers(&self) -> bool {
+        self.0.options.use_remote_persistent_workers
+    }
+
     /// Check if the action can be served by the action cache.
     pub async fn action_cache(
         &self,
@@ -180,15 +186,35 @@ impl CommandExecutor {
                 }
                 CommandExecutionInput::ScratchPath(_) => None,
             });
+            let mut platform = merge_platform_properties(
+                &self.0.re_platform,
+                request.remote_execution_properties(),
+            );
+            let args = match request.worker() {
+                Some(WorkerSpec {
+                    exe,
+                    remote_key: Some(key),
+                    ..
+                }) if self.0.options.use_remote_persistent_workers => {
+                    // The RE backend spawns a worker from the worker's own command line (and reuses
+                    // it for commands with the same key), then sends it the arguments of the
+                    // command, so those go last.
+                    platform =
+                        merge_platform_properties(&platform, &persistent_worker_properties(key));
+                    exe.iter().chain(request.args()).cloned().collect()
+                }
+                _ => request.all_args_vec(),
+            };
+
             let action = re_create_action(
-                request.all_args_vec(),
+                args,
                 request.paths().output_paths(),
                 request.working_directory().map(|p| p.as_str().to_owned()),
                 request.env(),
                 input_digest,
                 action_metadata_blobs,
                 request.timeout(),
-                self.0.re_platform.clone(),
+                platform,
                 false,
                 digest_config,
                 self.0.options.output_paths_behavior,
@@ -201,6 +227,41 @@ impl CommandExecutor {
     }
 }
 
+/// The platform properties RE backends supporting persistent workers use to pick a worker for a
+/// command, and to know how to send it the arguments of the command: Bazel's worker protocol, in
+/// protobuf.
+fn persistent_worker_properties(key: &str) -> SortedMap<String, String> {
+    SortedMap::from_iter([
+        ("persistentWorkerKey".to_owned(), key.to_owned()),
+        ("persistentWorkerProtocol".to_owned(), "proto".to_owned()),
+    ])
+}
+
+/// Properties of the command override those of the executor with the same name. The RE spec
+/// requires the properties to be sorted by name.
+fn merge_platform_properties(
+    platform: &RE::Platform,
+    overrides: &SortedMap<String, String>,
+) -> RE::Platform {
+    if overrides.is_empty() {
+        return platform.clone();
+    }
+
+    let mut properties: Vec<RE::Property> = platform
+        .properties
+        .iter()
+        .filter(|p| !overrides.contains_key(&p.name))
+        .cloned()
+        .collect();
+    properties.extend(overrides.iter().map(|(name, value)| RE::Property {
+        name: name.clone(),
+        value: value.clone(),
+    }));
+    properties.sort_by(|a, b| a.name.cmp(&b.name));
+
+    RE::Platform { properties }
+}
+
 fn re_create_action(
     args: Vec<String>,
     outputs: &[(ProjectRelativePathBuf, OutputType)],
@@ -332,3 +393,72 @@ fn re_create_action(
         remote_execution_dependencies: remote_execution_dependencies.to_owned(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn property(name: &str, value: &str) -> RE::Property {
+        RE::Property {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_merge_platform_properties() {
+        let platform = RE::Platform {
+            properties: vec![property("OSFamily", "Linux"), property("zone", "a")],
+        };
+        assert_eq!(
+            platform.properties,
+            merge_platform_properties(&platform, &SortedMap::new()).properties
+        );
+        // Overrides replace the executor property with the same name, and the result is sorted.
+        assert_eq!(
+            vec![
+                property("OSFamily", "Linux"),
+                property("gpu", "1"),
+                property("zone", "b"),
+            ],
+            merge_platform_properties(
+                &platform,
+                &SortedMap::from_iter([
+                    ("zone".to_owned(), "b".to_owned()),
+                    ("gpu".to_owned(), "1".to_owned()),
+                ])
+            )
+            .properties
+        );
+        assert_eq!(
+            vec![property("gpu", "1")],
+            merge_platform_properties(
+                &RE::Platform {
+                    properties: Vec::new()
+                },
+                &SortedMap::from_iter([("gpu".to_owned(), "1".to_owned())])
+            )
+            .properties
+        );
+    }
+
+    #[test]
+    fn test_persistent_worker_properties() {
+        let platform = RE::Platform {
+            properties: vec![
+                property("OSFamily", "Linux"),
+                property("persistentWorkerKey", "stale"),
+                property("zone", "a"),
+            ],
+        };
+        assert_eq!(
+            vec![
+                property("OSFamily", "Linux"),
+                property("persistentWorkerKey", "abc"),
+                property("persistentWorkerProtocol", "proto"),
+                property("zone", "a"),
+            ],
+            merge_platform_properties(&platform, &persistent_worker_properties("abc")).properties
+        );
+    }
+}
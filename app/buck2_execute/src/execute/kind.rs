@@ -31,6 +31,20 @@ pub enum CommandExecutionKind {
         command: Vec<String>,
         env: SortedVectorMap<String, String>,
     },
+    /// This action was served by the local action cache and not executed.
+    #[display(fmt = "local_action_cache")]
+    LocalActionCache {
+        digest: ActionDigest,
+        command: Vec<String>,
+        env: SortedVectorMap<String, String>,
+    },
+    /// This action was served by the HTTP cache and not executed.
+    #[display(fmt = "http_cache")]
+    HttpCache {
+        digest: ActionDigest,
+        command: Vec<String>,
+        env: SortedVectorMap<String, String>,
+    },
     /// This action was executed via a remote executor.
     #[display(fmt = "remote")]
     Remote {
@@ -73,6 +87,8 @@ impl CommandExecutionKind {
     pub fn as_enum(&self) -> buck2_data::ActionExecutionKind {
         match self {
             Self::Local { .. } => buck2_data::ActionExecutionKind::Local,
+            Self::LocalActionCache { .. } => buck2_data::ActionExecutionKind::LocalActionCache,
+            Self::HttpCache { .. } => buck2_data::ActionExecutionKind::HttpCache,
             Self::LocalWorker { .. } | Self::LocalWorkerInit { .. } => {
                 buck2_data::ActionExecutionKind::LocalWorker
             }
@@ -90,10 +106,23 @@ impl CommandExecutionKind {
                 command,
                 env,
                 digest,
+            }
+            | Self::LocalActionCache {
+                command,
+                env,
+                digest,
+            }
+            | Self::HttpCache {
+                command,
+                env,
+                digest,
             } => {
+                let cache_hit =
+                    matches!(self, Self::LocalActionCache { .. } | Self::HttpCache { .. });
                 if omit_details {
                     Command::OmittedLocalCommand(buck2_data::OmittedLocalCommand {
                         action_digest: digest.to_string(),
+                        cache_hit,
                     })
                 } else {
                     Command::LocalCommand(buck2_data::LocalCommand {
@@ -106,6 +135,7 @@ impl CommandExecutionKind {
                                 value: value.clone(),
                             })
                             .collect(),
+                        cache_hit,
                     })
                 }
             }
@@ -15,6 +15,7 @@ use serde::Serialize;
 use serde::Serializer;
 
 use crate::execute::request::ActionMetadataBlobData;
+use crate::execute::request::OutputType;
 
 #[derive(Clone)]
 pub struct PathsWithDigestBlobData(pub ActionMetadataBlobData);
@@ -34,15 +35,35 @@ struct PathWithDigest<'a> {
     digest: &'a FileDigest,
 }
 
+fn output_type_name<S>(value: &OutputType, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(match value {
+        OutputType::File => "file",
+        OutputType::Directory => "directory",
+        OutputType::FileOrDirectory => "file_or_directory",
+    })
+}
+
+#[derive(Serialize)]
+struct PathWithOutputType {
+    path: ForwardRelativePathBuf,
+    #[serde(rename = "type", serialize_with = "output_type_name")]
+    output_type: OutputType,
+}
+
 #[derive(Serialize)]
 struct MetadataJson<'a> {
     version: i32,
     digests: Vec<PathWithDigest<'a>>,
+    outputs: Vec<PathWithOutputType>,
 }
 
 #[derive(Default)]
 pub struct PathsWithDigestBuilder<'a> {
     paths: Vec<PathWithDigest<'a>>,
+    outputs: Vec<PathWithOutputType>,
 }
 
 impl<'a> PathsWithDigestBuilder<'a> {
@@ -50,9 +71,15 @@ impl<'a> PathsWithDigestBuilder<'a> {
         self.paths.push(PathWithDigest { path, digest });
     }
 
+    /// Outputs are declared before the action runs, so they have no digest.
+    pub fn add_output(&mut self, path: ForwardRelativePathBuf, output_type: OutputType) {
+        self.outputs.push(PathWithOutputType { path, output_type });
+    }
+
     pub fn build(self) -> anyhow::Result<PathsWithDigestBlobData> {
         let json = MetadataJson {
             digests: self.paths,
+            outputs: self.outputs,
             // Increment this version if format changes
             version: 1,
         };
@@ -62,3 +89,35 @@ impl<'a> PathsWithDigestBuilder<'a> {
         )))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_common::cas_digest::CasDigestConfig;
+
+    use super::*;
+
+    #[test]
+    fn test_build() -> anyhow::Result<()> {
+        let digest = FileDigest::from_content(b"foo", CasDigestConfig::testing_default());
+        let mut builder = PathsWithDigestBuilder::default();
+        builder.add(
+            ForwardRelativePathBuf::unchecked_new("src/foo".to_owned()),
+            &digest,
+        );
+        builder.add_output(
+            ForwardRelativePathBuf::unchecked_new("buck-out/v2/gen/out".to_owned()),
+            OutputType::Directory,
+        );
+
+        let json: serde_json::Value = serde_json::from_slice(&builder.build()?.0 .0)?;
+        assert_eq!(
+            serde_json::json!({
+                "version": 1,
+                "digests": [{"path": "src/foo", "digest": digest.to_string()}],
+                "outputs": [{"path": "buck-out/v2/gen/out", "type": "directory"}],
+            }),
+            json
+        );
+        Ok(())
+    }
+}
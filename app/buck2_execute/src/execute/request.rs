@@ -34,6 +34,7 @@ use itertools::Itertools;
 use prost::Message;
 use remote_execution as RE;
 use sorted_vector_map::SortedVectorMap;
+use starlark_map::sorted_map::SortedMap;
 use starlark_map::sorted_set::SortedSet;
 
 use super::dep_file_digest::DepFileDigest;
@@ -283,6 +284,9 @@ pub struct WorkerSpec {
     pub id: WorkerId,
     pub exe: Vec<String>,
     pub concurrency: Option<usize>,
+    /// Set if the worker can also run on RE: identifies the worker's command line and the
+    /// contents of its inputs, so that RE only reuses workers spawned from the same tool.
+    pub remote_key: Option<String>,
 }
 
 /// The data contains the information about the command to be executed.
@@ -296,6 +300,8 @@ pub struct CommandExecutionRequest {
     timeout: Option<Duration>,
     executor_preference: ExecutorPreference,
     host_sharing_requirements: HostSharingRequirements,
+    /// How much memory the command is expected to use, in megabytes, when running locally.
+    memory_mb: Option<usize>,
     // Used to disable the low pass filter for concurrent local actions. Enabled by default
     low_pass_filter: bool,
     /// Working directory, relative to the project root.
@@ -311,6 +317,10 @@ pub struct CommandExecutionRequest {
     /// Whether this command should override the fallback-only behavior on an hybrid executor and
     /// thus always run as if the executor was full-hybrid, assuming it is capable.
     force_full_hybrid_if_capable: bool,
+    /// Whether the result of this command must not be written to remote caches.
+    remote_cache_read_only: bool,
+    /// Whether the action opted into having its outputs uploaded to caches.
+    allow_cache_upload: bool,
     /// Whether to disable capturing performance counters for this execution.
     disable_miniperf: bool,
     required_local_resources: SortedSet<LocalResourceState>,
@@ -324,6 +334,8 @@ pub struct CommandExecutionRequest {
     pub remote_dep_file_key: Option<DepFileDigest>,
     /// RE dependencies to pass in action metadata.
     remote_execution_dependencies: Vec<RemoteExecutorDependency>,
+    /// RE platform properties overriding or extending those of the executor for this command.
+    remote_execution_properties: SortedMap<String, String>,
 }
 
 impl CommandExecutionRequest {
@@ -341,18 +353,22 @@ impl CommandExecutionRequest {
             timeout: None,
             executor_preference: ExecutorPreference::Default,
             host_sharing_requirements: HostSharingRequirements::default(),
+            memory_mb: None,
             low_pass_filter: true,
             working_directory: None,
             prefetch_lossy_stderr: false,
             outputs_cleanup: true,
             local_environment_inheritance: None,
             force_full_hybrid_if_capable: false,
+            remote_cache_read_only: false,
+            allow_cache_upload: false,
             disable_miniperf: false,
             required_local_resources: SortedSet::new(),
             worker: None,
             unique_input_inodes: false,
             remote_dep_file_key: None,
             remote_execution_dependencies: Vec::new(),
+            remote_execution_properties: SortedMap::new(),
         }
     }
 
@@ -378,6 +394,11 @@ impl CommandExecutionRequest {
         self
     }
 
+    pub fn with_memory_mb(mut self, memory_mb: Option<usize>) -> Self {
+        self.memory_mb = memory_mb;
+        self
+    }
+
     pub fn with_low_pass_filter(mut self, low_pass_filter: bool) -> Self {
         self.low_pass_filter = low_pass_filter;
         self
@@ -468,6 +489,10 @@ impl CommandExecutionRequest {
         &self.host_sharing_requirements
     }
 
+    pub fn memory_mb(&self) -> Option<usize> {
+        self.memory_mb
+    }
+
     pub fn low_pass_filter(&self) -> bool {
         self.low_pass_filter
     }
@@ -497,6 +522,24 @@ impl CommandExecutionRequest {
         self.force_full_hybrid_if_capable
     }
 
+    pub fn with_remote_cache_read_only(mut self, remote_cache_read_only: bool) -> Self {
+        self.remote_cache_read_only = remote_cache_read_only;
+        self
+    }
+
+    pub fn remote_cache_read_only(&self) -> bool {
+        self.remote_cache_read_only
+    }
+
+    pub fn with_allow_cache_upload(mut self, allow_cache_upload: bool) -> Self {
+        self.allow_cache_upload = allow_cache_upload;
+        self
+    }
+
+    pub fn allow_cache_upload(&self) -> bool {
+        self.allow_cache_upload
+    }
+
     pub fn with_disable_miniperf(mut self, disable_miniperf: bool) -> Self {
         self.disable_miniperf = disable_miniperf;
         self
@@ -544,6 +587,18 @@ impl CommandExecutionRequest {
     pub fn remote_execution_dependencies(&self) -> &Vec<RemoteExecutorDependency> {
         &self.remote_execution_dependencies
     }
+
+    pub fn with_remote_execution_properties(
+        mut self,
+        remote_execution_properties: SortedMap<String, String>,
+    ) -> Self {
+        self.remote_execution_properties = remote_execution_properties;
+        self
+    }
+
+    pub fn remote_execution_properties(&self) -> &SortedMap<String, String> {
+        &self.remote_execution_properties
+    }
 }
 
 /// Is an output a file or a directory
@@ -7,6 +7,8 @@
  * of this source tree.
  */
 
+use std::time::Duration;
+
 use dupe::Dupe;
 
 /// Command-level config that can tweak how the executors work.
@@ -17,4 +19,8 @@ pub struct ExecutorGlobalKnobs {
     /// Whether to emit action keys to execution logs (thos are pretty verbose and omitted by
     /// default).
     pub log_action_keys: bool,
+
+    /// Print the output of remote actions which have been executing for longer than this, as
+    /// they produce it, rather than only once they finish.
+    pub re_stream_output_after: Option<Duration>,
 }
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::io::Read;
 use std::io::Write;
 use std::sync::Arc;
 use std::time::Duration;
@@ -15,9 +16,11 @@ use allocative::Allocative;
 use anyhow::Context as _;
 use buck2_common::cas_digest::CasDigestConfig;
 use buck2_common::cas_digest::DigestAlgorithmKind;
+use buck2_common::cas_digest::Digester;
 use buck2_common::cas_digest::SHA1_SIZE;
 use buck2_common::cas_digest::SHA256_SIZE;
 use buck2_common::file_ops::FileDigest;
+use buck2_common::file_ops::FileDigestKind;
 use buck2_common::file_ops::TrackedFileDigest;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::project::ProjectRoot;
@@ -258,51 +261,132 @@ pub async fn http_download(
     .await?)
 }
 
-/// Copy a stream into a writer while producing its digest and checksumming it.
-async fn copy_and_hash(
-    url: &str,
-    abs_path: &(impl std::fmt::Display + ?Sized),
-    mut stream: impl Stream<Item = Result<Bytes, hyper::Error>> + Unpin,
-    mut writer: impl Write,
-    digest_config: CasDigestConfig,
+/// Checks whether a file left at `path` by an earlier download matches `checksum`, without using
+/// the network. Returns its digest if it does, and `None` if it doesn't or isn't there.
+pub fn verify_existing_download(
+    fs: &ProjectRoot,
+    digest_config: DigestConfig,
+    path: &ProjectRelativePath,
     checksum: &Checksum,
-    is_vpnless: bool,
-) -> Result<FileDigest, HttpDownloadError> {
-    let mut digester = FileDigest::digester(digest_config);
+) -> anyhow::Result<Option<TrackedFileDigest>> {
+    let abs_path = fs.resolve(path);
+    if !fs_util::try_exists(&abs_path)? {
+        return Ok(None);
+    }
+    let mut file = fs_util::open_file(&abs_path)?;
+
+    let mut hasher = ChecksumHasher::new(digest_config.cas_digest_config(), checksum);
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = file
+            .read(&mut buf)
+            .with_context(|| format!("read({})", abs_path))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    match hasher.finalize() {
+        (digest, None) => Ok(Some(TrackedFileDigest::new(
+            digest,
+            digest_config.cas_digest_config(),
+        ))),
+        (_, Some(_mismatch)) => Ok(None),
+    }
+}
 
+/// Produces the digest of some contents while checksumming them.
+struct ChecksumHasher<'a> {
+    digester: Digester<FileDigestKind>,
     // For each checksum entry we have, we're going to add a validator. We might have to create
     // a new hasher, or reuse the `FileDigest::digester` if it matches.
+    validators: SmallVec<[(Validator, &'a str, &'static str); 2]>,
+}
 
-    enum Validator {
-        PrimaryDigest,
-        ExtraDigest(Box<dyn DynDigest + Send>),
-    }
+enum Validator {
+    PrimaryDigest,
+    ExtraDigest(Box<dyn DynDigest + Send>),
+}
 
-    let mut validators = SmallVec::<[_; 2]>::new();
+impl<'a> ChecksumHasher<'a> {
+    fn new(digest_config: CasDigestConfig, checksum: &'a Checksum) -> Self {
+        let digester = FileDigest::digester(digest_config);
+        let mut validators = SmallVec::new();
 
-    if let Some(sha1) = checksum.sha1() {
-        let validator = if digester.algorithm() == DigestAlgorithmKind::Sha1 {
-            Validator::PrimaryDigest
-        } else {
-            Validator::ExtraDigest(Box::new(Sha1::new()) as _)
-        };
+        if let Some(sha1) = checksum.sha1() {
+            let validator = if digester.algorithm() == DigestAlgorithmKind::Sha1 {
+                Validator::PrimaryDigest
+            } else {
+                Validator::ExtraDigest(Box::new(Sha1::new()) as _)
+            };
 
-        validators.push((validator, sha1, "sha1"));
+            validators.push((validator, sha1, "sha1"));
+        }
+
+        if let Some(sha256) = checksum.sha256() {
+            let validator = if digester.algorithm() == DigestAlgorithmKind::Sha256 {
+                Validator::PrimaryDigest
+            } else {
+                Validator::ExtraDigest(Box::new(Sha256::new()) as _)
+            };
+
+            validators.push((validator, sha256, "sha256"));
+        }
+
+        Self {
+            digester,
+            validators,
+        }
+    }
+
+    fn bytes_read(&self) -> u64 {
+        self.digester.bytes_read()
+    }
+
+    fn update(&mut self, chunk: &[u8]) {
+        self.digester.update(chunk);
+        for (validator, _expected, _kind) in self.validators.iter_mut() {
+            if let Validator::ExtraDigest(hasher) = validator {
+                hasher.update(chunk);
+            }
+        }
     }
 
-    if let Some(sha256) = checksum.sha256() {
-        let validator = if digester.algorithm() == DigestAlgorithmKind::Sha256 {
-            Validator::PrimaryDigest
-        } else {
-            Validator::ExtraDigest(Box::new(Sha256::new()) as _)
-        };
+    /// The digest, and the first checksum that the contents don't match, as `(kind, expected,
+    /// obtained)`.
+    fn finalize(self) -> (FileDigest, Option<(&'static str, &'a str, String)>) {
+        let digest = self.digester.finalize();
+
+        for (validator, expected, kind) in self.validators {
+            let obtained = match validator {
+                Validator::PrimaryDigest => digest.raw_digest().to_string(),
+                Validator::ExtraDigest(hasher) => hex::encode(hasher.finalize()),
+            };
 
-        validators.push((validator, sha256, "sha256"));
+            if expected != obtained {
+                return (digest, Some((kind, expected, obtained)));
+            }
+        }
+        (digest, None)
     }
+}
+
+/// Copy a stream into a writer while producing its digest and checksumming it.
+async fn copy_and_hash(
+    url: &str,
+    abs_path: &(impl std::fmt::Display + ?Sized),
+    mut stream: impl Stream<Item = Result<Bytes, hyper::Error>> + Unpin,
+    mut writer: impl Write,
+    digest_config: CasDigestConfig,
+    checksum: &Checksum,
+    is_vpnless: bool,
+) -> Result<FileDigest, HttpDownloadError> {
+    let mut hasher = ChecksumHasher::new(digest_config, checksum);
 
     while let Some(chunk) = stream.next().await {
         let chunk = chunk.map_err(|source| HttpError::Transfer {
-            received: digester.bytes_read(),
+            received: hasher.bytes_read(),
             url: url.to_owned(),
             source,
         })?;
@@ -311,44 +395,31 @@ async fn copy_and_hash(
             .with_context(|| format!("write({})", abs_path))
             .map_err(HttpDownloadError::IoError)?;
 
-        digester.update(&chunk);
-        for (validator, _expected, _kind) in validators.iter_mut() {
-            if let Validator::ExtraDigest(hasher) = validator {
-                hasher.update(&chunk);
-            }
-        }
+        hasher.update(&chunk);
     }
     writer
         .flush()
         .with_context(|| format!("flush({})", abs_path))
         .map_err(HttpDownloadError::IoError)?;
 
-    let digest = digester.finalize();
-
     // Validate
-    for (validator, expected, kind) in validators {
-        let obtained = match validator {
-            Validator::PrimaryDigest => digest.raw_digest().to_string(),
-            Validator::ExtraDigest(hasher) => hex::encode(hasher.finalize()),
-        };
-
-        if expected != obtained {
-            if is_vpnless {
-                return Err(HttpDownloadError::MaybeNotAllowedOnVpnless {
-                    kind,
-                    want: expected.to_owned(),
-                    got: obtained,
-                    url: url.to_owned(),
-                    path: abs_path.to_string(),
-                });
-            }
-            return Err(HttpDownloadError::InvalidChecksum(
+    let (digest, mismatch) = hasher.finalize();
+    if let Some((kind, expected, obtained)) = mismatch {
+        if is_vpnless {
+            return Err(HttpDownloadError::MaybeNotAllowedOnVpnless {
                 kind,
-                expected.to_owned(),
-                obtained,
-                url.to_owned(),
-            ));
+                want: expected.to_owned(),
+                got: obtained,
+                url: url.to_owned(),
+                path: abs_path.to_string(),
+            });
         }
+        return Err(HttpDownloadError::InvalidChecksum(
+            kind,
+            expected.to_owned(),
+            obtained,
+            url.to_owned(),
+        ));
     }
 
     Ok(digest)
@@ -358,6 +429,7 @@ async fn copy_and_hash(
 mod tests {
     use assert_matches::assert_matches;
     use buck2_common::cas_digest::testing;
+    use buck2_core::fs::project::ProjectRootTemp;
     use futures::stream;
 
     use super::*;
@@ -434,4 +506,38 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_verify_existing_download() -> anyhow::Result<()> {
+        let temp = ProjectRootTemp::new()?;
+        let digest_config = DigestConfig::testing_default();
+        let path = ProjectRelativePath::new("buck-out/v2/gen/foo/download")?;
+        let foobar = Checksum::Sha256(Arc::from(
+            "c3ab8ff13720e8ad9047dd39466b3c8974e592c2fa383d4a3960714caef0c4f2",
+        ));
+
+        // Not downloaded yet.
+        assert_eq!(
+            None,
+            verify_existing_download(temp.path(), digest_config, path, &foobar)?
+        );
+
+        temp.path().write_file(path, "foobar", false)?;
+        assert_eq!(
+            Some(TrackedFileDigest::from_content(
+                b"foobar",
+                digest_config.cas_digest_config()
+            )),
+            verify_existing_download(temp.path(), digest_config, path, &foobar)?
+        );
+
+        // Left by a download of another version of the file.
+        temp.path().write_file(path, "foobaz", false)?;
+        assert_eq!(
+            None,
+            verify_existing_download(temp.path(), digest_config, path, &foobar)?
+        );
+
+        Ok(())
+    }
 }
@@ -577,14 +577,21 @@ pub enum MaterializationMethod {
     Deferred,
     /// Materialize only when needed, do not materialize final artifacts
     DeferredSkipFinalArtifacts,
+    /// Let Eden fetch artifacts from the CAS when they are first read. Requires buck-out to be an
+    /// Eden mount.
+    Eden,
 }
 
 #[derive(Debug, buck2_error::Error)]
 pub enum MaterializationMethodError {
     #[error(
-        "Invalid value for buckconfig `[buck2] materializations`. Got `{0}`. Expected one of `all`, `deferred`, or `deferred_skip_final_artifacts`."
+        "Invalid value for buckconfig `[buck2] materializations`. Got `{0}`. Expected one of `all`, `deferred`, `deferred_skip_final_artifacts`, or `eden`."
     )]
     InvalidValueForConfig(String),
+    #[error(
+        "`[buck2] materializations = eden` is not supported in the open source build of Buck2"
+    )]
+    EdenNotSupported,
 }
 
 impl MaterializationMethod {
@@ -595,6 +602,9 @@ impl MaterializationMethod {
             Some("deferred_skip_final_artifacts") => {
                 Ok(MaterializationMethod::DeferredSkipFinalArtifacts)
             }
+            // The Eden materializer needs the EdenFS Thrift bindings.
+            Some("eden") if cfg!(fbcode_build) => Ok(MaterializationMethod::Eden),
+            Some("eden") => Err(MaterializationMethodError::EdenNotSupported.into()),
             Some(v) => Err(MaterializationMethodError::InvalidValueForConfig(v.to_owned()).into()),
         }
     }
@@ -604,6 +614,24 @@ impl MaterializationMethod {
 /// `DeferredMaterializerEntry` lives in a crate that depends on this one.
 pub trait DeferredMaterializerEntry: Send + Sync + std::fmt::Display {}
 
+/// A materialized artifact whose contents on disk no longer match the digest it was declared with.
+#[derive(Debug)]
+pub struct CorruptArtifact {
+    pub path: ProjectRelativePathBuf,
+    /// What is wrong with the artifact, e.g. `missing`.
+    pub reason: String,
+    /// Whether the artifact was deleted and forgotten, so that it is materialized again (or
+    /// rebuilt) the next time it is needed.
+    pub repaired: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct VerifyBuckOutReport {
+    /// The number of materialized artifacts that were re-hashed.
+    pub checked: u64,
+    pub corrupt: Vec<CorruptArtifact>,
+}
+
 /// Obtain notifications for entries as they are materialized, and request eager materialization of
 /// those paths.
 #[async_trait]
@@ -642,10 +670,17 @@ pub trait DeferredMaterializerExtensions: Send + Sync {
     async fn clean_stale_artifacts(
         &self,
         keep_since_time: DateTime<Utc>,
+        max_size: Option<u64>,
         dry_run: bool,
         tracked_only: bool,
     ) -> anyhow::Result<buck2_cli_proto::CleanStaleResponse>;
 
+    /// Re-hash the materialized artifacts and compare them to the digests they were declared
+    /// with. Unlike `fsck`, this reads the contents of every artifact. If `repair` is set, the
+    /// corrupt artifacts are deleted and forgotten, except for those declared by this daemon,
+    /// which its build graph still depends on.
+    async fn verify_buck_out(&self, repair: bool) -> anyhow::Result<VerifyBuckOutReport>;
+
     async fn test_iter(&self, count: usize) -> anyhow::Result<String>;
     async fn flush_all_access_times(&self) -> anyhow::Result<String>;
 
@@ -654,3 +689,48 @@ pub trait DeferredMaterializerExtensions: Send + Sync {
         &self,
     ) -> anyhow::Result<Box<dyn DeferredMaterializerSubscription>>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_materialization_method_from_config() {
+        let parse = MaterializationMethod::try_new_from_config_value;
+        assert!(matches!(parse(None), Ok(MaterializationMethod::Deferred)));
+        assert!(matches!(
+            parse(Some("")),
+            Ok(MaterializationMethod::Deferred)
+        ));
+        assert!(matches!(
+            parse(Some("all")),
+            Ok(MaterializationMethod::Immediate)
+        ));
+        assert!(matches!(
+            parse(Some("deferred_skip_final_artifacts")),
+            Ok(MaterializationMethod::DeferredSkipFinalArtifacts)
+        ));
+        assert!(parse(Some("lazy")).is_err());
+    }
+
+    #[cfg(fbcode_build)]
+    #[test]
+    fn test_eden_materialization_method() {
+        assert!(matches!(
+            MaterializationMethod::try_new_from_config_value(Some("eden")),
+            Ok(MaterializationMethod::Eden)
+        ));
+    }
+
+    #[cfg(not(fbcode_build))]
+    #[test]
+    fn test_eden_materialization_method_rejected() {
+        let err = MaterializationMethod::try_new_from_config_value(Some("eden")).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("not supported in the open source build"),
+            "{}",
+            err
+        );
+    }
+}
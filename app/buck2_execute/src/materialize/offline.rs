@@ -0,0 +1,261 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::sync::Arc;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_events::dispatch::EventDispatcher;
+use buck2_futures::cancellation::CancellationContext;
+use futures::stream;
+use futures::stream::BoxStream;
+use futures::stream::StreamExt;
+
+use crate::artifact_value::ArtifactValue;
+use crate::materialize::materializer::ArtifactNotMaterializedReason;
+use crate::materialize::materializer::CasDownloadInfo;
+use crate::materialize::materializer::CopiedArtifact;
+use crate::materialize::materializer::DeclareMatchOutcome;
+use crate::materialize::materializer::DeferredMaterializerExtensions;
+use crate::materialize::materializer::HttpDownloadInfo;
+use crate::materialize::materializer::MaterializationError;
+use crate::materialize::materializer::Materializer;
+use crate::materialize::materializer::WriteRequest;
+
+#[derive(Debug, buck2_error::Error)]
+enum OfflineMaterializationError {
+    #[error(
+        "Cannot fetch `{path}` from the CAS while offline: it was produced remotely ({origin}) and was never materialized"
+    )]
+    #[buck2(input, tag = Offline)]
+    CasDownload {
+        path: ProjectRelativePathBuf,
+        origin: String,
+    },
+}
+
+/// Wraps the materializer of a command running in offline mode, so that artifacts which would
+/// have to be fetched from the CAS fail with an error tagged `OFFLINE` instead of trying to reach
+/// the network. Everything else is left to the inner materializer.
+#[derive(Allocative)]
+pub struct OfflineMaterializer {
+    inner: Arc<dyn Materializer>,
+}
+
+impl OfflineMaterializer {
+    pub fn new(inner: Arc<dyn Materializer>) -> Self {
+        Self { inner }
+    }
+
+    /// For each path, the error to raise if materializing it requires a CAS download.
+    async fn cas_downloads(
+        &self,
+        paths: Vec<ProjectRelativePathBuf>,
+    ) -> anyhow::Result<Vec<Option<MaterializationError>>> {
+        Ok(self
+            .inner
+            .get_materialized_file_paths(paths.clone())
+            .await?
+            .into_iter()
+            .zip(paths)
+            .map(|(res, path)| match res {
+                Err(ArtifactNotMaterializedReason::RequiresCasDownload { info, .. }) => {
+                    Some(offline_error(path, &info))
+                }
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+fn offline_error(path: ProjectRelativePathBuf, info: &CasDownloadInfo) -> MaterializationError {
+    MaterializationError::Error {
+        path: path.clone(),
+        source: OfflineMaterializationError::CasDownload {
+            path,
+            origin: info.origin.to_string(),
+        }
+        .into(),
+    }
+}
+
+/// Fills the paths without an error with the results of materializing them, in order.
+fn merge_results(
+    errors: Vec<Option<MaterializationError>>,
+    others: Vec<Result<(), MaterializationError>>,
+) -> Vec<Result<(), MaterializationError>> {
+    let mut others = others.into_iter();
+    errors
+        .into_iter()
+        .map(|e| match e {
+            Some(e) => Err(e),
+            None => others.next().unwrap_or(Ok(())),
+        })
+        .collect()
+}
+
+#[async_trait]
+impl Materializer for OfflineMaterializer {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn declare_existing(
+        &self,
+        artifacts: Vec<(ProjectRelativePathBuf, ArtifactValue)>,
+    ) -> anyhow::Result<()> {
+        self.inner.declare_existing(artifacts).await
+    }
+
+    async fn declare_copy_impl(
+        &self,
+        path: ProjectRelativePathBuf,
+        value: ArtifactValue,
+        srcs: Vec<CopiedArtifact>,
+        cancellations: &CancellationContext,
+    ) -> anyhow::Result<()> {
+        self.inner
+            .declare_copy_impl(path, value, srcs, cancellations)
+            .await
+    }
+
+    async fn declare_cas_many_impl<'a, 'b>(
+        &self,
+        info: Arc<CasDownloadInfo>,
+        artifacts: Vec<(ProjectRelativePathBuf, ArtifactValue)>,
+        cancellations: &CancellationContext,
+    ) -> anyhow::Result<()> {
+        self.inner
+            .declare_cas_many_impl(info, artifacts, cancellations)
+            .await
+    }
+
+    async fn declare_http(
+        &self,
+        path: ProjectRelativePathBuf,
+        info: HttpDownloadInfo,
+        cancellations: &CancellationContext,
+    ) -> anyhow::Result<()> {
+        self.inner.declare_http(path, info, cancellations).await
+    }
+
+    async fn declare_write<'a>(
+        &self,
+        gen: Box<dyn FnOnce() -> anyhow::Result<Vec<WriteRequest>> + Send + 'a>,
+    ) -> anyhow::Result<Vec<ArtifactValue>> {
+        self.inner.declare_write(gen).await
+    }
+
+    async fn declare_match(
+        &self,
+        artifacts: Vec<(ProjectRelativePathBuf, ArtifactValue)>,
+    ) -> anyhow::Result<DeclareMatchOutcome> {
+        self.inner.declare_match(artifacts).await
+    }
+
+    async fn has_artifact_at(&self, path: ProjectRelativePathBuf) -> anyhow::Result<bool> {
+        self.inner.has_artifact_at(path).await
+    }
+
+    async fn invalidate_many(&self, paths: Vec<ProjectRelativePathBuf>) -> anyhow::Result<()> {
+        self.inner.invalidate_many(paths).await
+    }
+
+    async fn materialize_many(
+        &self,
+        artifact_paths: Vec<ProjectRelativePathBuf>,
+    ) -> anyhow::Result<BoxStream<'static, Result<(), MaterializationError>>> {
+        let errors = self.cas_downloads(artifact_paths.clone()).await?;
+        if errors.iter().all(Option::is_none) {
+            return self.inner.materialize_many(artifact_paths).await;
+        }
+
+        // The other artifacts are still materialized, and the results are returned in the order
+        // of the paths.
+        let others = artifact_paths
+            .into_iter()
+            .zip(&errors)
+            .filter(|(_, e)| e.is_none())
+            .map(|(path, _)| path)
+            .collect();
+        let others = self.inner.materialize_many(others).await?.collect().await;
+        Ok(stream::iter(merge_results(errors, others)).boxed())
+    }
+
+    async fn try_materialize_final_artifact(
+        &self,
+        artifact_path: ProjectRelativePathBuf,
+    ) -> anyhow::Result<bool> {
+        if let Some(Some(e)) = self.cas_downloads(vec![artifact_path.clone()]).await?.pop() {
+            return Err(e.into());
+        }
+        self.inner
+            .try_materialize_final_artifact(artifact_path)
+            .await
+    }
+
+    async fn get_materialized_file_paths(
+        &self,
+        paths: Vec<ProjectRelativePathBuf>,
+    ) -> anyhow::Result<Vec<Result<ProjectRelativePathBuf, ArtifactNotMaterializedReason>>> {
+        self.inner.get_materialized_file_paths(paths).await
+    }
+
+    fn as_deferred_materializer_extension(&self) -> Option<&dyn DeferredMaterializerExtensions> {
+        self.inner.as_deferred_materializer_extension()
+    }
+
+    fn log_materializer_state(&self, events: &EventDispatcher) {
+        self.inner.log_materializer_state(events)
+    }
+
+    fn add_snapshot_stats(&self, snapshot: &mut buck2_data::Snapshot) {
+        self.inner.add_snapshot_stats(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::execution_types::executor_config::RemoteExecutorUseCase;
+
+    use super::*;
+
+    #[test]
+    fn test_offline_error() {
+        let path = ProjectRelativePathBuf::testing_new("buck-out/v2/gen/foo");
+        let info = CasDownloadInfo::new_declared(RemoteExecutorUseCase::buck2_default());
+        let err = buck2_error::Error::from(anyhow::Error::from(offline_error(path, &info)));
+        assert!(err.tags().contains(&buck2_error::ErrorTag::Offline));
+    }
+
+    #[test]
+    fn test_merge_results() {
+        let info = CasDownloadInfo::new_declared(RemoteExecutorUseCase::buck2_default());
+        let path = ProjectRelativePathBuf::testing_new;
+        let results = merge_results(
+            vec![None, Some(offline_error(path("b"), &info)), None],
+            vec![
+                Ok(()),
+                Err(MaterializationError::Error {
+                    path: path("c"),
+                    source: anyhow::anyhow!("failed"),
+                }),
+            ],
+        );
+        assert_eq!(3, results.len());
+        assert!(results[0].is_ok());
+        assert!(
+            matches!(&results[1], Err(MaterializationError::Error { path: p, .. }) if p.as_str() == "b")
+        );
+        assert!(
+            matches!(&results[2], Err(MaterializationError::Error { path: p, .. }) if p.as_str() == "c")
+        );
+    }
+}
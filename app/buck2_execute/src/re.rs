@@ -14,6 +14,7 @@ pub mod manager;
 pub mod metadata;
 pub mod re_get_session_id;
 pub mod remote_action_result;
+pub mod retry;
 mod stats;
 pub mod streams;
 pub mod uploader;
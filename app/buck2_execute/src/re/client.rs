@@ -7,17 +7,20 @@
  * of this source tree.
  */
 
+use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
 
 use allocative::Allocative;
 use anyhow::Context;
+use buck2_common::cas_digest::DigestAlgorithmKind;
 use buck2_core::buck2_env;
 use buck2_core::execution_types::executor_config::RemoteExecutorDependency;
 use buck2_core::execution_types::executor_config::RemoteExecutorUseCase;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_events::dispatch::EventDispatcher;
 use buck2_re_configuration::RemoteExecutionStaticMetadata;
 use buck2_re_configuration::RemoteExecutionStaticMetadataImpl;
 use chrono::DateTime;
@@ -45,6 +48,7 @@ use remote_execution::GetDigestsTtlRequest;
 use remote_execution::InlinedBlobWithDigest;
 use remote_execution::NamedDigest;
 use remote_execution::NamedDigestWithPermissions;
+use remote_execution::OperationMetadata;
 use remote_execution::REClient;
 use remote_execution::REClientBuilder;
 use remote_execution::REClientError;
@@ -74,9 +78,14 @@ use crate::materialize::materializer::Materializer;
 use crate::re::action_identity::ReActionIdentity;
 use crate::re::convert::platform_to_proto;
 use crate::re::metadata::RemoteExecutionMetadataExt;
+use crate::re::retry::ReCircuitBreaker;
+use crate::re::retry::ReCircuitBreakerConfig;
+use crate::re::retry::ReRetryConfig;
+use crate::re::retry::ReRetryPolicy;
 use crate::re::stats::OpStats;
 use crate::re::stats::RemoteExecutionClientOpStats;
 use crate::re::stats::RemoteExecutionClientStats;
+use crate::re::uploader::FindMissingBatcher;
 use crate::re::uploader::UploadStats;
 use crate::re::uploader::Uploader;
 
@@ -103,6 +112,8 @@ struct RemoteExecutionClientData {
     write_action_results: OpStats,
     get_digest_expirations: OpStats,
     extend_digest_ttl: OpStats,
+    retries: ReRetryConfig,
+    circuit_breaker: ReCircuitBreaker,
 }
 
 impl RemoteExecutionClient {
@@ -113,6 +124,8 @@ impl RemoteExecutionClient {
         logs_dir_path: Option<&AbsNormPath>,
         buck_out_path: &AbsNormPath,
         is_paranoid_mode: bool,
+        retries: ReRetryConfig,
+        circuit_breaker: Option<ReCircuitBreakerConfig>,
     ) -> anyhow::Result<Self> {
         let client = RemoteExecutionClientImpl::new(
             fb,
@@ -135,6 +148,8 @@ impl RemoteExecutionClient {
                 write_action_results: OpStats::default(),
                 get_digest_expirations: OpStats::default(),
                 extend_digest_ttl: OpStats::default(),
+                retries,
+                circuit_breaker: ReCircuitBreaker::new(circuit_breaker),
             }),
         })
     }
@@ -147,6 +162,8 @@ impl RemoteExecutionClient {
         logs_dir_path: Option<&AbsNormPath>,
         buck_out_path: &AbsNormPath,
         is_paranoid_mode: bool,
+        retries: &ReRetryConfig,
+        circuit_breaker: Option<ReCircuitBreakerConfig>,
     ) -> anyhow::Result<Self> {
         // Loop happens times-1 times at most
         for i in 1..times {
@@ -157,6 +174,8 @@ impl RemoteExecutionClient {
                 logs_dir_path,
                 buck_out_path,
                 is_paranoid_mode,
+                retries.clone(),
+                circuit_breaker,
             )
             .await
             {
@@ -178,10 +197,31 @@ impl RemoteExecutionClient {
             logs_dir_path,
             buck_out_path,
             is_paranoid_mode,
+            retries.clone(),
+            circuit_breaker,
         )
         .await
     }
 
+    /// Run an operation, retrying it on transient errors, and record its outcome for the circuit
+    /// breaker.
+    async fn op_with_retries<A, R, F, Fut>(
+        &self,
+        stats: &OpStats,
+        policy: &ReRetryPolicy,
+        args: A,
+        f: F,
+    ) -> anyhow::Result<R>
+    where
+        A: Clone,
+        F: FnMut(A) -> Fut,
+        Fut: Future<Output = anyhow::Result<R>>,
+    {
+        let res = stats.op_with_retries(policy, args, f).await;
+        self.data.circuit_breaker.record(&res);
+        res
+    }
+
     fn decorate_error(&self, op: &str, source: anyhow::Error) -> anyhow::Error {
         source.context(format!(
             "Remote Execution Error on {} ({})",
@@ -195,10 +235,13 @@ impl RemoteExecutionClient {
         action_digest: ActionDigest,
         use_case: RemoteExecutorUseCase,
     ) -> anyhow::Result<Option<ActionResultResponse>> {
-        self.data
-            .action_cache
-            .op(self.data.client.action_cache(action_digest, use_case))
-            .await
+        self.op_with_retries(
+            &self.data.action_cache,
+            &self.data.retries.action_cache,
+            (),
+            |()| self.data.client.action_cache(action_digest, use_case),
+        )
+        .await
     }
 
     pub async fn upload(
@@ -212,23 +255,20 @@ impl RemoteExecutionClient {
         identity: Option<&ReActionIdentity<'_>>,
         digest_config: DigestConfig,
     ) -> anyhow::Result<UploadStats> {
-        self.data
-            .uploads
-            .op(self
-                .data
-                .client
-                .upload(
-                    fs,
-                    materializer,
-                    blobs,
-                    dir_path,
-                    input_dir,
-                    use_case,
-                    identity,
-                    digest_config,
-                )
-                .map_err(|e| self.decorate_error("upload", e)))
-            .await
+        self.op_with_retries(&self.data.uploads, &self.data.retries.upload, (), |()| {
+            self.data.client.upload(
+                fs,
+                materializer,
+                blobs,
+                dir_path,
+                input_dir,
+                use_case,
+                identity,
+                digest_config,
+            )
+        })
+        .await
+        .map_err(|e| self.decorate_error("upload", e))
     }
 
     pub async fn upload_files_and_directories(
@@ -267,7 +307,8 @@ impl RemoteExecutionClient {
         re_resource_units: Option<i64>,
         knobs: &ExecutorGlobalKnobs,
     ) -> anyhow::Result<ExecuteResponseOrCancelled> {
-        self.data
+        let res = self
+            .data
             .executes
             .op(self
                 .data
@@ -286,7 +327,9 @@ impl RemoteExecutionClient {
                     knobs,
                 )
                 .map_err(|e| self.decorate_error("execute", e)))
-            .await
+            .await;
+        self.data.circuit_breaker.record(&res);
+        res
     }
 
     pub async fn materialize_files(
@@ -306,14 +349,18 @@ impl RemoteExecutionClient {
         digests: Vec<TDigest>,
         use_case: RemoteExecutorUseCase,
     ) -> anyhow::Result<Vec<T>> {
-        self.data
-            .downloads
-            .op(self
-                .data
-                .client
-                .download_typed_blobs(identity, digests, use_case)
-                .map_err(|e| self.decorate_error("download_typed_blob", e)))
-            .await
+        self.op_with_retries(
+            &self.data.downloads,
+            &self.data.retries.download,
+            digests,
+            |digests| {
+                self.data
+                    .client
+                    .download_typed_blobs(identity, digests, use_case)
+            },
+        )
+        .await
+        .map_err(|e| self.decorate_error("download_typed_blob", e))
     }
 
     pub async fn download_blob(
@@ -321,14 +368,14 @@ impl RemoteExecutionClient {
         digest: &TDigest,
         use_case: RemoteExecutorUseCase,
     ) -> anyhow::Result<Vec<u8>> {
-        self.data
-            .downloads
-            .op(self
-                .data
-                .client
-                .download_blob(digest, use_case)
-                .map_err(|e| self.decorate_error("download_blob", e)))
-            .await
+        self.op_with_retries(
+            &self.data.downloads,
+            &self.data.retries.download,
+            (),
+            |()| self.data.client.download_blob(digest, use_case),
+        )
+        .await
+        .map_err(|e| self.decorate_error("download_blob", e))
     }
 
     pub async fn upload_blob(
@@ -336,14 +383,14 @@ impl RemoteExecutionClient {
         blob: Vec<u8>,
         use_case: RemoteExecutorUseCase,
     ) -> anyhow::Result<TDigest> {
-        self.data
-            .uploads
-            .op(self
-                .data
-                .client
-                .upload_blob(blob, use_case)
-                .map_err(|e| self.decorate_error("upload_blob", e)))
-            .await
+        self.op_with_retries(
+            &self.data.uploads,
+            &self.data.retries.upload,
+            blob,
+            |blob| self.data.client.upload_blob(blob, use_case),
+        )
+        .await
+        .map_err(|e| self.decorate_error("upload_blob", e))
     }
 
     pub async fn get_digest_expirations(
@@ -351,14 +398,14 @@ impl RemoteExecutionClient {
         digests: Vec<TDigest>,
         use_case: RemoteExecutorUseCase,
     ) -> anyhow::Result<Vec<(TDigest, DateTime<Utc>)>> {
-        self.data
-            .get_digest_expirations
-            .op(self
-                .data
-                .client
-                .get_digest_expirations(digests, use_case)
-                .map_err(|e| self.decorate_error("get_digest_expirations", e)))
-            .await
+        self.op_with_retries(
+            &self.data.get_digest_expirations,
+            &self.data.retries.get_digest_expirations,
+            digests,
+            |digests| self.data.client.get_digest_expirations(digests, use_case),
+        )
+        .await
+        .map_err(|e| self.decorate_error("get_digest_expirations", e))
     }
 
     pub async fn extend_digest_ttl(
@@ -367,14 +414,14 @@ impl RemoteExecutionClient {
         ttl: Duration,
         use_case: RemoteExecutorUseCase,
     ) -> anyhow::Result<()> {
-        self.data
-            .extend_digest_ttl
-            .op(self
-                .data
-                .client
-                .extend_digest_ttl(digests, ttl, use_case)
-                .map_err(|e| self.decorate_error("extend_digest_ttl", e)))
-            .await
+        self.op_with_retries(
+            &self.data.extend_digest_ttl,
+            &self.data.retries.extend_digest_ttl,
+            digests,
+            |digests| self.data.client.extend_digest_ttl(digests, ttl, use_case),
+        )
+        .await
+        .map_err(|e| self.decorate_error("extend_digest_ttl", e))
     }
 
     pub async fn write_action_result(
@@ -384,20 +431,30 @@ impl RemoteExecutionClient {
         use_case: RemoteExecutorUseCase,
         platform: &RE::Platform,
     ) -> anyhow::Result<WriteActionResultResponse> {
-        self.data
-            .write_action_results
-            .op(self
-                .data
-                .client
-                .write_action_result(digest, result, use_case, platform)
-                .map_err(|e| self.decorate_error("write_action_result", e)))
-            .await
+        self.op_with_retries(
+            &self.data.write_action_results,
+            &self.data.retries.write_action_result,
+            result,
+            |result| {
+                self.data
+                    .client
+                    .write_action_result(digest, result, use_case, platform)
+            },
+        )
+        .await
+        .map_err(|e| self.decorate_error("write_action_result", e))
     }
 
     pub fn get_session_id(&self) -> &str {
         self.data.client.client().get_session_id()
     }
 
+    /// Whether too many requests failed with transient errors recently, in which case executors
+    /// should prefer local execution.
+    pub fn is_circuit_open(&self) -> bool {
+        self.data.circuit_breaker.is_open()
+    }
+
     pub fn get_experiment_name(&self) -> anyhow::Result<Option<String>> {
         self.data.client.client().get_experiment_name()
     }
@@ -412,6 +469,94 @@ impl RemoteExecutionClient {
         stats.materializes = RemoteExecutionClientOpStats::from(&self.data.materializes);
         stats.get_digest_expirations =
             RemoteExecutionClientOpStats::from(&self.data.get_digest_expirations);
+        stats.circuit_open = self.is_circuit_open();
+    }
+}
+
+/// Prints the stdout and stderr of an executing action on the console as the RE workers produce
+/// them, starting once the action has been executing for a while. Stops when dropped.
+struct OutputTail(tokio::task::JoinHandle<()>);
+
+impl OutputTail {
+    fn spawn(
+        client: &REClient,
+        metadata: &RemoteExecutionMetadata,
+        operation: &OperationMetadata,
+        after: Duration,
+        action_name: &str,
+        events: EventDispatcher,
+    ) -> Option<Self> {
+        // Not all RE implementations expose the logs of executing actions.
+        let logs: Vec<_> = [&operation.stdout_stream_name, &operation.stderr_stream_name]
+            .into_iter()
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                client
+                    .get_execution_client()
+                    .read_log_stream(metadata.clone(), name.clone(), 0)
+            })
+            .collect();
+        if logs.is_empty() {
+            return None;
+        }
+
+        let action_name = action_name.to_owned();
+        Some(Self(tokio::spawn(async move {
+            tokio::time::sleep(after).await;
+            futures::future::join_all(
+                logs.into_iter()
+                    .map(|log| Self::print_lines(log, &action_name, &events)),
+            )
+            .await;
+        })))
+    }
+
+    async fn print_lines(
+        log: BoxStream<'static, anyhow::Result<Vec<u8>>>,
+        action_name: &str,
+        events: &EventDispatcher,
+    ) {
+        Self::for_each_line(log, action_name, |line| {
+            events.console_message(format!(
+                "[{}] {}",
+                action_name,
+                String::from_utf8_lossy(line).trim_end()
+            ))
+        })
+        .await
+    }
+
+    /// Calls `print` with each line of the log, without its newline, as soon as the line is
+    /// complete. The last line doesn't need a newline.
+    async fn for_each_line(
+        mut log: BoxStream<'static, anyhow::Result<Vec<u8>>>,
+        action_name: &str,
+        mut print: impl FnMut(&[u8]),
+    ) {
+        let mut pending = Vec::new();
+        while let Some(chunk) = log.next().await {
+            match chunk {
+                Ok(chunk) => pending.extend_from_slice(&chunk),
+                Err(e) => {
+                    // The full output is still reported when the action finishes.
+                    tracing::debug!("Error reading the output of `{}`: {:#}", action_name, e);
+                    return;
+                }
+            }
+            while let Some(newline) = pending.iter().position(|b| *b == b'\n') {
+                print(&pending[..newline]);
+                pending.drain(..=newline);
+            }
+        }
+        if !pending.is_empty() {
+            print(&pending);
+        }
+    }
+}
+
+impl Drop for OutputTail {
+    fn drop(&mut self) {
+        self.0.abort();
     }
 }
 
@@ -429,6 +574,9 @@ struct RemoteExecutionClientImpl {
     /// How many files to kick off downloading concurrently for one request. This should be smaller
     /// than the files semaphore to ensure we can actually *acquire* that semaphore.
     download_chunk_size: usize,
+    /// Coalesces the FindMissingBlobs calls of concurrent uploads.
+    #[allocative(skip)]
+    find_missing_batcher: FindMissingBatcher,
 }
 
 fn re_platform(x: &RE::Platform) -> remote_execution::TPlatform {
@@ -702,6 +850,7 @@ impl RemoteExecutionClientImpl {
                 cas_semaphore: Arc::new(Semaphore::new(static_metadata.cas_semaphore_size())),
                 download_files_semapore: Arc::new(Semaphore::new(download_concurrency)),
                 download_chunk_size,
+                find_missing_batcher: FindMissingBatcher::default(),
             }
         };
 
@@ -762,6 +911,7 @@ impl RemoteExecutionClientImpl {
         Uploader::upload(
             fs,
             self.client().get_cas_client(),
+            &self.find_missing_batcher,
             materializer,
             dir_path,
             input_dir,
@@ -801,6 +951,7 @@ impl RemoteExecutionClientImpl {
         metadata: RemoteExecutionMetadata,
         request: ExecuteRequest,
         action_digest: &ActionDigest,
+        action_name: &str,
         manager: &mut CommandExecutionManager,
         re_max_queue_time: Option<Duration>,
         platform: &remote_execution::Platform,
@@ -924,6 +1075,7 @@ impl RemoteExecutionClientImpl {
 
         // Obtain a stream of events from RE. If this fails then that is case #1 above so we
         // bail.
+        let log_metadata = knobs.re_stream_output_after.map(|_| metadata.clone());
         let mut receiver = self
             .client()
             .get_execution_client()
@@ -938,6 +1090,8 @@ impl RemoteExecutionClientImpl {
         // this doesn't give us an ExecuteResponse then this is case #1 again so we also fail.
         let action_digest_str = action_digest.to_string();
         let mut exe_stage = Stage::QUEUED;
+        // Aborts tailing the output of the action when execution finishes or is cancelled.
+        let mut output_tail = None;
 
         loop {
             let progress_response = wait_for_response_or_stage_change(
@@ -968,6 +1122,26 @@ impl RemoteExecutionClientImpl {
 
             // Change the stage
             exe_stage = progress_response.stage;
+
+            if exe_stage == Stage::EXECUTING && output_tail.is_none() {
+                if let (Some(after), Some(log_metadata)) =
+                    (knobs.re_stream_output_after, &log_metadata)
+                {
+                    let action_name = if action_name.is_empty() {
+                        &action_digest_str
+                    } else {
+                        action_name
+                    };
+                    output_tail = OutputTail::spawn(
+                        self.client(),
+                        log_metadata,
+                        &progress_response.metadata,
+                        after,
+                        action_name,
+                        manager.inner.events.dupe(),
+                    );
+                }
+            }
         }
     }
 
@@ -1027,6 +1201,7 @@ impl RemoteExecutionClientImpl {
             metadata,
             request,
             &action_digest,
+            &identity.action_key,
             manager,
             re_max_queue_time,
             platform,
@@ -1249,6 +1424,198 @@ impl Drop for RemoteExecutionClientImpl {
     }
 }
 
+/// Picks the first of `candidates` which the RE server supports. If no RE server is configured,
+/// or its capabilities aren't queried or don't list digest functions, this is the first
+/// candidate.
+///
+/// The algorithm picked last time is kept in `cache_path`, so that the daemon doesn't wait for
+/// the RE server when it starts: the server is then asked in the background, and the answer is
+/// used the next time the daemon starts. Otherwise, the server is asked, retrying for up to
+/// `timeout`, and this fails if it can't answer: guessing would make RE unusable until the daemon
+/// restarts if the server doesn't support the guess.
+pub async fn negotiate_digest_algorithm(
+    static_metadata: &RemoteExecutionStaticMetadata,
+    candidates: &[DigestAlgorithmKind],
+    cache_path: &AbsNormPath,
+    timeout: Duration,
+) -> anyhow::Result<DigestAlgorithmKind> {
+    let first = *candidates
+        .first()
+        .context("No digest algorithm candidates")?;
+
+    #[cfg(fbcode_build)]
+    {
+        let _unused = (static_metadata, cache_path, timeout);
+        Ok(first)
+    }
+
+    #[cfg(not(fbcode_build))]
+    {
+        let opts = &static_metadata.0;
+        let Some(engine_address) = &opts.engine_address else {
+            return Ok(first);
+        };
+        if opts.capabilities == Some(false) {
+            return Ok(first);
+        }
+
+        // The answer depends on the server and on what we asked it.
+        let cache_key = format!(
+            "{} {} {}",
+            candidates.iter().join(","),
+            engine_address,
+            opts.instance_name.as_deref().unwrap_or_default()
+        );
+        let cached = match buck2_core::fs::fs_util::read_to_string_if_exists(cache_path) {
+            Ok(cached) => cached.and_then(|cached| {
+                parse_negotiated_digest_algorithm(&cached, &cache_key, candidates)
+            }),
+            Err(e) => {
+                tracing::warn!("Error reading the negotiated digest algorithm: {:#}", e);
+                None
+            }
+        };
+
+        let opts = opts.clone();
+        let candidates = candidates.to_vec();
+        let cache_path = cache_path.to_buf();
+        let negotiate = async move {
+            let res =
+                retry_negotiation(timeout, || negotiate_with_server(&opts, &candidates)).await;
+            if let Ok(algorithm) = &res {
+                let contents = format!("{}\n{}", cache_key, algorithm);
+                let written = cache_path
+                    .parent()
+                    .map_or(Ok(()), buck2_core::fs::fs_util::create_dir_all)
+                    .and_then(|()| buck2_core::fs::fs_util::write(&cache_path, contents));
+                if let Err(e) = written {
+                    tracing::warn!("Error saving the negotiated digest algorithm: {:#}", e);
+                }
+            }
+            res
+        };
+
+        if let Some(algorithm) = cached {
+            tokio::spawn(async move {
+                match negotiate.await {
+                    Ok(negotiated) if negotiated != algorithm => tracing::warn!(
+                        "The RE server now prefers the {} digest algorithm over {}, it will be \
+                        used once the daemon restarts",
+                        negotiated,
+                        algorithm
+                    ),
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!(
+                        "Unable to negotiate the digest algorithm with RE, keeping {}: {:#}",
+                        algorithm,
+                        e
+                    ),
+                }
+            });
+            return Ok(algorithm);
+        }
+
+        match negotiate.await {
+            Err(e) if !e.is::<NoCandidateSupported>() => Err(DigestNegotiationFailed {
+                engine_address: engine_address.clone(),
+                error: format!("{:#}", e),
+            }
+            .into()),
+            res => res,
+        }
+    }
+}
+
+#[cfg(not(fbcode_build))]
+#[derive(Debug, buck2_error::Error)]
+#[error(
+    "Unable to negotiate the digest algorithm with the RE server at `{engine_address}`: {error}. \
+    Check that the server can be reached, or set `buck2.digest_algorithms` instead of \
+    `buck2.digest_algorithm_candidates` to build without it"
+)]
+#[buck2(tier0)]
+struct DigestNegotiationFailed {
+    engine_address: String,
+    error: String,
+}
+
+/// Calls `negotiate` until it succeeds, backing off between attempts, for up to `timeout`.
+/// Servers which support none of the candidates aren't asked again.
+#[cfg(not(fbcode_build))]
+async fn retry_negotiation<F, Fut>(
+    timeout: Duration,
+    negotiate: F,
+) -> anyhow::Result<DigestAlgorithmKind>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<DigestAlgorithmKind>>,
+{
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut delay = Duration::from_millis(100);
+    loop {
+        let e = match tokio::time::timeout_at(deadline, negotiate()).await {
+            Ok(Ok(algorithm)) => return Ok(algorithm),
+            Ok(Err(e)) if e.is::<NoCandidateSupported>() => return Err(e),
+            Ok(Err(e)) => e,
+            Err(_) => return Err(anyhow::anyhow!("Timed out after {:?}", timeout)),
+        };
+        if tokio::time::Instant::now() + delay >= deadline {
+            return Err(e);
+        }
+        tracing::debug!(
+            "Retrying the digest algorithm negotiation in {:?}: {:#}",
+            delay,
+            e
+        );
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+}
+
+#[cfg(not(fbcode_build))]
+#[derive(Debug, buck2_error::Error)]
+#[error(
+    "The RE server supports none of the candidate digest algorithms (candidates: {candidates}, \
+    supported: {supported})"
+)]
+struct NoCandidateSupported {
+    candidates: String,
+    supported: String,
+}
+
+/// Reads the algorithm saved by [negotiate_digest_algorithm], if it was negotiated for `key`.
+#[cfg(not(fbcode_build))]
+fn parse_negotiated_digest_algorithm(
+    contents: &str,
+    key: &str,
+    candidates: &[DigestAlgorithmKind],
+) -> Option<DigestAlgorithmKind> {
+    let (saved_key, algorithm) = contents.split_once('\n')?;
+    let algorithm = algorithm.trim().parse::<DigestAlgorithmKind>().ok()?;
+    (saved_key == key && candidates.contains(&algorithm)).then_some(algorithm)
+}
+
+#[cfg(not(fbcode_build))]
+async fn negotiate_with_server(
+    opts: &buck2_re_configuration::Buck2OssReConfiguration,
+    candidates: &[DigestAlgorithmKind],
+) -> anyhow::Result<DigestAlgorithmKind> {
+    let Some(supported) = REClientBuilder::fetch_digest_functions(opts).await? else {
+        return Ok(candidates[0]);
+    };
+    candidates
+        .iter()
+        .find(|c| supported.contains(&c.to_string()))
+        .copied()
+        .ok_or_else(|| {
+            NoCandidateSupported {
+                candidates: candidates.iter().join(", "),
+                supported: supported.join(", "),
+            }
+            .into()
+        })
+}
+
 fn chunks<T>(v: Vec<T>, chunk_size: usize) -> impl Iterator<Item = Vec<T>> {
     if !v.is_empty() && v.len() <= chunk_size {
         return Either::Left(std::iter::once(v));
@@ -1290,4 +1657,95 @@ mod tests {
         assert_eq!(it.next(), Some(vec![3]));
         assert_eq!(it.next(), None);
     }
+
+    #[cfg(not(fbcode_build))]
+    #[test]
+    fn test_parse_negotiated_digest_algorithm() {
+        let candidates = [DigestAlgorithmKind::Blake3, DigestAlgorithmKind::Sha256];
+        let parse = |contents| parse_negotiated_digest_algorithm(contents, "key", &candidates);
+        assert_eq!(parse("key\nSHA256\n"), Some(DigestAlgorithmKind::Sha256));
+        // Negotiated with another server, or for other candidates.
+        assert_eq!(parse("other\nSHA256"), None);
+        assert_eq!(parse("key\nSHA1"), None);
+        assert_eq!(parse("key\nMD5"), None);
+        assert_eq!(parse("key"), None);
+    }
+
+    #[cfg(not(fbcode_build))]
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_negotiation() -> anyhow::Result<()> {
+        use std::sync::atomic::AtomicUsize;
+        use std::sync::atomic::Ordering;
+
+        // Succeeds once the server can be reached.
+        let attempts = AtomicUsize::new(0);
+        let algorithm = retry_negotiation(Duration::from_secs(5), || async {
+            if attempts.fetch_add(1, Ordering::Relaxed) < 2 {
+                Err(anyhow::anyhow!("Connection refused"))
+            } else {
+                Ok(DigestAlgorithmKind::Blake3)
+            }
+        })
+        .await?;
+        assert_eq!(algorithm, DigestAlgorithmKind::Blake3);
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+
+        // Fails once the time is up instead of guessing.
+        let attempts = AtomicUsize::new(0);
+        let err = retry_negotiation(Duration::from_secs(1), || async {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            Err::<DigestAlgorithmKind, _>(anyhow::anyhow!("Connection refused"))
+        })
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("Connection refused"));
+        assert!(attempts.load(Ordering::Relaxed) > 1);
+
+        // Not retried when the server answered.
+        let attempts = AtomicUsize::new(0);
+        let err = retry_negotiation(Duration::from_secs(5), || async {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            Err::<DigestAlgorithmKind, _>(
+                NoCandidateSupported {
+                    candidates: "BLAKE3".to_owned(),
+                    supported: "SHA256".to_owned(),
+                }
+                .into(),
+            )
+        })
+        .await
+        .unwrap_err();
+        assert!(err.is::<NoCandidateSupported>());
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_output_tail_lines() {
+        let lines = |chunks: Vec<anyhow::Result<&str>>| {
+            let chunks: Vec<_> = chunks
+                .into_iter()
+                .map(|chunk| chunk.map(|c| c.as_bytes().to_vec()))
+                .collect();
+            async move {
+                let log = futures::stream::iter(chunks).boxed();
+                let mut lines = Vec::new();
+                OutputTail::for_each_line(log, "action", |line| {
+                    lines.push(String::from_utf8(line.to_vec()).unwrap())
+                })
+                .await;
+                lines
+            }
+        };
+
+        assert_eq!(
+            vec!["a", "bc", "", "d"],
+            lines(vec![Ok("a\nb"), Ok("c\n\n"), Ok("d")]).await
+        );
+        // Lines that are complete when the log fails are still printed.
+        assert_eq!(
+            vec!["a"],
+            lines(vec![Ok("a\nb"), Err(anyhow::anyhow!("failed")), Ok("c\n")]).await
+        );
+    }
 }
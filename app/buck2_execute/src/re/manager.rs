@@ -15,6 +15,7 @@ use std::sync::Mutex;
 use std::sync::RwLock;
 use std::sync::Weak;
 use std::time::Duration;
+use std::time::Instant;
 
 use allocative::Allocative;
 use anyhow::Context as _;
@@ -26,6 +27,7 @@ use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_re_configuration::RemoteExecutionStaticMetadata;
+use buck2_re_configuration::RemoteExecutionStaticMetadataImpl;
 use chrono::DateTime;
 use chrono::Utc;
 use dupe::Dupe;
@@ -52,6 +54,8 @@ use crate::re::action_identity::ReActionIdentity;
 use crate::re::client::ExecuteResponseOrCancelled;
 use crate::re::client::RemoteExecutionClient;
 use crate::re::re_get_session_id::ReGetSessionId;
+use crate::re::retry::ReCircuitBreakerConfig;
+use crate::re::retry::ReRetryConfig;
 use crate::re::stats::RemoteExecutionClientStats;
 use crate::re::uploader::UploadStats;
 
@@ -78,6 +82,11 @@ use crate::re::uploader::UploadStats;
 ///
 /// This approach allows us to ensure that all RE interactions for a particular buck command use the
 /// same RE session. Concurrent commands will share an RE session.
+///
+/// If a session reuse idle timeout is set, the [ReConnectionManager] additionally keeps the last
+/// connection open after its last handle is dropped, so that commands started within that timeout
+/// reuse its session instead of paying for a new connection. A connection that failed to connect
+/// is never reused.
 
 #[derive(Clone, Allocative)]
 struct RemoteExecutionConfig {
@@ -92,6 +101,8 @@ struct RemoteExecutionConfig {
     buck_out_path: AbsNormPathBuf,
     /// Whether Buck is running in paranoid mode.
     is_paranoid_mode: bool,
+    retries: ReRetryConfig,
+    circuit_breaker: Option<ReCircuitBreakerConfig>,
 }
 
 impl RemoteExecutionConfig {
@@ -104,6 +115,8 @@ impl RemoteExecutionConfig {
             self.logs_dir_path.as_deref(),
             &self.buck_out_path,
             self.is_paranoid_mode,
+            &self.retries,
+            self.circuit_breaker,
         )
         .await
     }
@@ -118,6 +131,8 @@ struct LazyRemoteExecutionClient {
     client: AsyncOnceCell<buck2_error::Result<RemoteExecutionClient>>,
     observers: Mutex<Vec<Weak<dyn ReConnectionObserver>>>,
     config: RemoteExecutionConfig,
+    #[allocative(skip)]
+    created: Instant,
 }
 
 impl LazyRemoteExecutionClient {
@@ -126,9 +141,23 @@ impl LazyRemoteExecutionClient {
             client: AsyncOnceCell::new(),
             observers: Mutex::new(Vec::new()),
             config,
+            created: Instant::now(),
         }
     }
 
+    /// A connection is reused until it fails to connect: until then it is either connected or
+    /// hasn't been asked to connect yet.
+    fn is_healthy(&self) -> bool {
+        !matches!(self.client.get(), Some(Err(_)))
+    }
+
+    /// Whether new commands can use this connection. Connections older than `max_age` aren't
+    /// handed out anymore, so that a new connection reads the credentials again before the ones
+    /// this connection was made with expire.
+    fn is_reusable(&self, max_age: Duration) -> bool {
+        self.is_healthy() && self.created.elapsed() < max_age
+    }
+
     /// Apply F to the client contained in this LazyRemoteExecutionClient if and only if there is a
     /// valid client.
     fn with_client<F, T>(&self, f: F) -> Option<T>
@@ -187,14 +216,39 @@ impl LazyRemoteExecutionClient {
     }
 }
 
+/// A strong reference to the last connection, keeping it open while no command uses it.
+#[derive(Allocative)]
+struct KeptAliveClient {
+    client: Arc<LazyRemoteExecutionClient>,
+    /// Pushed back every time a handle to this connection is dropped.
+    #[allocative(skip)]
+    expires: Instant,
+}
+
+type KeptAlive = Mutex<Option<KeptAliveClient>>;
+
+/// How the RE session is reused across commands, set with the `buck2_re_client` buckconfigs
+/// `session_reuse_idle_timeout_s` and `session_max_age_s`.
+#[derive(Clone, Copy, Dupe, Debug)]
+pub struct ReSessionReuseConfig {
+    /// How long the session stays open once no command uses it.
+    pub idle_timeout: Duration,
+    /// How long the session is handed to new commands after it was created. The next session
+    /// reads the credentials again, so they are refreshed before they expire.
+    pub max_age: Duration,
+}
+
 /// The main manager for the RE connections
 #[derive(Allocative)]
 pub struct ReConnectionManager {
     // We hold a single Weak to the lazy client. ReConnectionHandle will hold a strong Arc to the same. Once the
     // last ReConnectionHandle is dropped, the client we point to will be dropped and we'll create a new one for
-    // the next ReConnectionHandle.
+    // the next ReConnectionHandle (unless it is kept alive below).
     data: RwLock<Weak<LazyRemoteExecutionClient>>,
     config: RemoteExecutionConfig,
+    #[allocative(skip)]
+    session_reuse: Option<ReSessionReuseConfig>,
+    kept_alive: Arc<KeptAlive>,
 }
 
 impl ReConnectionManager {
@@ -206,9 +260,14 @@ impl ReConnectionManager {
         logs_dir_path: Option<AbsNormPathBuf>,
         buck_out_path: AbsNormPathBuf,
         is_paranoid_mode: bool,
+        session_reuse: Option<ReSessionReuseConfig>,
+        retries: ReRetryConfig,
+        circuit_breaker: Option<ReCircuitBreakerConfig>,
     ) -> Self {
         Self {
             data: RwLock::new(Weak::new()),
+            session_reuse,
+            kept_alive: Arc::new(Mutex::new(None)),
             config: RemoteExecutionConfig {
                 fb,
                 skip_remote_cache,
@@ -217,28 +276,58 @@ impl ReConnectionManager {
                 logs_dir_path,
                 buck_out_path,
                 is_paranoid_mode,
+                retries,
+                circuit_breaker,
             },
         }
     }
 
     /// Gets a new guard that holds a RE connection open
     pub fn get_re_connection(&self) -> ReConnectionHandle {
-        ReConnectionHandle::new(self.get_client_handle())
+        let mut handle = ReConnectionHandle::new(self.get_client_handle());
+        if let Some(session_reuse) = self.session_reuse {
+            let client = (*handle.connection).dupe();
+            *self.kept_alive.lock().unwrap() = Some(KeptAliveClient {
+                client,
+                expires: Instant::now() + session_reuse.idle_timeout,
+            });
+            handle.keep_alive =
+                Some((Arc::downgrade(&self.kept_alive), session_reuse.idle_timeout));
+        }
+        handle
     }
 
     fn get_client_handle(&self) -> Arc<LazyRemoteExecutionClient> {
+        // Drop the kept alive connection if it has been idle for too long, if it is too old to
+        // be handed out (its credentials might expire soon) or if it failed to connect. Commands
+        // still using it keep it open until they finish.
+        let reusable = |conn: &LazyRemoteExecutionClient| match self.session_reuse {
+            Some(session_reuse) => conn.is_reusable(session_reuse.max_age),
+            None => true,
+        };
+        {
+            let mut kept_alive = self.kept_alive.lock().unwrap();
+            if let Some(k) = &*kept_alive {
+                if k.expires <= Instant::now() || !reusable(&k.client) {
+                    *kept_alive = None;
+                }
+            }
+        }
+
         if let Some(conn) = self.data.read().unwrap().upgrade() {
-            return conn;
+            if reusable(&conn) {
+                return conn;
+            }
         }
 
         let mut conn = self.data.write().unwrap();
         match conn.upgrade() {
-            None => {
+            Some(conn) if reusable(&conn) => conn,
+            _ => {
                 let new_connection = Arc::new(LazyRemoteExecutionClient::new(self.config.clone()));
                 *conn = Arc::downgrade(&new_connection);
                 new_connection
             }
-            Some(conn) => conn,
         }
     }
 
@@ -284,6 +373,9 @@ pub struct ReConnectionHandle {
     // after that command ended. An alternative would be to register/deregister the connection
     // handle itself as an observer on the lazy client, but that doesn't seem any simpler.
     observer: Option<Arc<dyn ReConnectionObserver>>,
+    /// Where the manager keeps this connection alive when sessions are reused, and for how long
+    /// it should stay alive once this handle is dropped.
+    keep_alive: Option<(Weak<KeptAlive>, Duration)>,
 }
 
 impl ReConnectionHandle {
@@ -291,6 +383,7 @@ impl ReConnectionHandle {
         Self {
             connection: Arc::new(connection),
             observer: None,
+            keep_alive: None,
         }
     }
 
@@ -307,6 +400,29 @@ impl ReConnectionHandle {
             data: Arc::downgrade(&self.connection),
         }
     }
+
+    /// Whether a remote action cache is configured, so that looking up actions in it doesn't
+    /// always fail.
+    pub fn has_action_cache(&self) -> bool {
+        self.connection.config.static_metadata.has_action_cache()
+    }
+}
+
+impl Drop for ReConnectionHandle {
+    fn drop(&mut self) {
+        let Some((kept_alive, idle_timeout)) = &self.keep_alive else {
+            return;
+        };
+        let Some(kept_alive) = kept_alive.upgrade() else {
+            return;
+        };
+        let mut kept_alive = kept_alive.lock().unwrap();
+        if let Some(k) = &mut *kept_alive {
+            if Arc::ptr_eq(&k.client, &*self.connection) {
+                k.expires = Instant::now() + *idle_timeout;
+            }
+        }
+    }
 }
 
 #[derive(Clone, Dupe)]
@@ -504,9 +620,90 @@ impl ManagedRemoteExecutionClient {
         Ok(session_id)
     }
 
+    /// Whether RE is currently considered unhealthy because of repeated transient errors. This
+    /// doesn't connect to RE if that hasn't happened yet.
+    pub fn is_circuit_open(&self) -> bool {
+        self.data.upgrade().map_or(false, |c| {
+            c.with_client(|client| client.is_circuit_open())
+                .unwrap_or(false)
+        })
+    }
+
     /// Construct a dummy ManagedRemoteExecutionClient that won't actually work. This is only
     /// remotely useful in tests.
     pub fn testing_new_dummy() -> Self {
         Self { data: Weak::new() }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager(session_reuse: Option<ReSessionReuseConfig>) -> ReConnectionManager {
+        ReConnectionManager::new(
+            // SAFETY: the RE connection is never made in these tests.
+            unsafe { fbinit::assume_init() },
+            false,
+            0,
+            Arc::new(RemoteExecutionStaticMetadata::default()),
+            None,
+            AbsNormPathBuf::new(std::env::temp_dir()).unwrap(),
+            false,
+            session_reuse,
+            ReRetryConfig::default(),
+            None,
+        )
+    }
+
+    fn reuse(idle_timeout: Duration, max_age: Duration) -> Option<ReSessionReuseConfig> {
+        Some(ReSessionReuseConfig {
+            idle_timeout,
+            max_age,
+        })
+    }
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn test_concurrent_commands_share_session() {
+        for session_reuse in [None, reuse(HOUR, HOUR)] {
+            let manager = manager(session_reuse);
+            let first = manager.get_re_connection();
+            let second = manager.get_re_connection();
+            assert!(Arc::ptr_eq(&*first.connection, &*second.connection));
+        }
+    }
+
+    #[test]
+    fn test_session_closed_without_reuse() {
+        let manager = manager(None);
+        let first = (*manager.get_re_connection().connection).dupe();
+        let second = (*manager.get_re_connection().connection).dupe();
+        assert!(!Arc::ptr_eq(&first, &second));
+    }
+
+    #[test]
+    fn test_session_reused() {
+        let manager = manager(reuse(HOUR, HOUR));
+        let first = (*manager.get_re_connection().connection).dupe();
+        let second = (*manager.get_re_connection().connection).dupe();
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+
+    #[test]
+    fn test_idle_session_closed() {
+        let manager = manager(reuse(Duration::ZERO, HOUR));
+        let first = Arc::downgrade(&*manager.get_re_connection().connection);
+        let _second = manager.get_re_connection();
+        assert!(first.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_old_session_refreshed() {
+        let manager = manager(reuse(HOUR, Duration::ZERO));
+        let first = manager.get_re_connection();
+        let second = manager.get_re_connection();
+        assert!(!Arc::ptr_eq(&*first.connection, &*second.connection));
+    }
+}
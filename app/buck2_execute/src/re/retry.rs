@@ -0,0 +1,310 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::future::Future;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use allocative::Allocative;
+use dupe::Dupe;
+use remote_execution::TCode;
+
+/// How to retry an RPC which failed with a transient error.
+#[derive(Clone, Copy, Debug, Dupe, Allocative, PartialEq, Eq)]
+pub struct ReRetryPolicy {
+    /// Including the first attempt, so 1 disables retries.
+    pub max_attempts: u32,
+    /// Doubled after every failed attempt, up to `max_backoff`.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for ReRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl ReRetryPolicy {
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+
+    /// Run `f` on `args` until it succeeds, fails with an error which is not transient, or runs
+    /// out of attempts. Calls `on_retry` before every retry. `args` are only cloned for attempts
+    /// which may be retried: the last (or only) attempt takes them.
+    pub(super) async fn run<A, R, F, Fut>(
+        &self,
+        args: A,
+        mut f: F,
+        on_retry: impl Fn(),
+    ) -> anyhow::Result<R>
+    where
+        A: Clone,
+        F: FnMut(A) -> Fut,
+        Fut: Future<Output = anyhow::Result<R>>,
+    {
+        let mut attempt = 1;
+        loop {
+            if attempt >= self.max_attempts {
+                return f(args).await;
+            }
+            match f(args.clone()).await {
+                Err(e) if is_transient(&e) => {
+                    let backoff = self.backoff(attempt);
+                    tracing::debug!(
+                        "Retrying RE request after {:?} (attempt {}): {:#}",
+                        backoff,
+                        attempt,
+                        e
+                    );
+                    on_retry();
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+/// Retry policies for each kind of RPC. `execute` is not retried here: a failed execution is
+/// handled by the executor, which can fall back to local execution.
+#[derive(Clone, Debug, Default, Allocative)]
+pub struct ReRetryConfig {
+    pub action_cache: ReRetryPolicy,
+    pub upload: ReRetryPolicy,
+    pub download: ReRetryPolicy,
+    pub write_action_result: ReRetryPolicy,
+    pub get_digest_expirations: ReRetryPolicy,
+    pub extend_digest_ttl: ReRetryPolicy,
+}
+
+/// After `errors` consecutive transient errors, consider the RE backend unhealthy for
+/// `cooldown`, so that executors fall back to local execution instead of waiting on it.
+#[derive(Clone, Copy, Debug, Dupe, Allocative)]
+pub struct ReCircuitBreakerConfig {
+    pub errors: u32,
+    pub cooldown: Duration,
+}
+
+#[derive(Allocative)]
+pub(super) struct ReCircuitBreaker {
+    config: Option<ReCircuitBreakerConfig>,
+    consecutive_errors: AtomicU32,
+    #[allocative(skip)]
+    open_until: Mutex<Option<Instant>>,
+}
+
+impl ReCircuitBreaker {
+    pub(super) fn new(config: Option<ReCircuitBreakerConfig>) -> Self {
+        Self {
+            config,
+            consecutive_errors: AtomicU32::new(0),
+            open_until: Mutex::new(None),
+        }
+    }
+
+    pub(super) fn record<R>(&self, result: &anyhow::Result<R>) {
+        let Some(config) = &self.config else {
+            return;
+        };
+        match result {
+            Ok(_) => self.consecutive_errors.store(0, Ordering::Relaxed),
+            Err(e) if is_transient(e) => {
+                let errors = self.consecutive_errors.fetch_add(1, Ordering::Relaxed) + 1;
+                if errors >= config.errors {
+                    let mut open_until = self.open_until.lock().unwrap();
+                    if open_until.is_none() {
+                        tracing::warn!(
+                            "Remote execution failed {} times in a row, preferring local execution for {:?}",
+                            errors,
+                            config.cooldown
+                        );
+                    }
+                    *open_until = Some(Instant::now() + config.cooldown);
+                    self.consecutive_errors.store(0, Ordering::Relaxed);
+                }
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// Whether the backend is currently considered unhealthy.
+    pub(super) fn is_open(&self) -> bool {
+        let mut open_until = self.open_until.lock().unwrap();
+        match *open_until {
+            Some(t) if t > Instant::now() => true,
+            Some(_) => {
+                *open_until = None;
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+fn error_code(e: &anyhow::Error) -> Option<TCode> {
+    #[cfg(fbcode_build)]
+    {
+        e.chain().find_map(|e| {
+            e.downcast_ref::<remote_execution::REClientError>()
+                .map(|e| e.code.dupe())
+        })
+    }
+
+    #[cfg(not(fbcode_build))]
+    {
+        remote_execution::error_code(e)
+    }
+}
+
+/// Whether the error is worth retrying: the backend is unavailable or overloaded, or the request
+/// timed out.
+pub(super) fn is_transient(e: &anyhow::Error) -> bool {
+    matches!(
+        error_code(e),
+        Some(code) if code == TCode::UNAVAILABLE
+            || code == TCode::DEADLINE_EXCEEDED
+            || code == TCode::RESOURCE_EXHAUSTED
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// Counts how many times it was cloned.
+    struct Args(Rc<Cell<u32>>);
+
+    impl Clone for Args {
+        fn clone(&self) -> Self {
+            self.0.set(self.0.get() + 1);
+            Args(self.0.dupe())
+        }
+    }
+
+    fn policy(max_attempts: u32) -> ReRetryPolicy {
+        ReRetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        }
+    }
+
+    fn unavailable() -> anyhow::Error {
+        remote_execution::REClientError {
+            code: TCode::UNAVAILABLE,
+            message: "unavailable".to_owned(),
+        }
+        .into()
+    }
+
+    /// Runs `policy` on an RPC returning the given results in order, returning the result, the
+    /// number of calls and retries, and the number of times the arguments were cloned.
+    async fn run(
+        policy: ReRetryPolicy,
+        results: Vec<anyhow::Result<u32>>,
+    ) -> (anyhow::Result<u32>, u32, u32, u32) {
+        let clones = Rc::new(Cell::new(0));
+        let calls = Cell::new(0);
+        let retries = Cell::new(0);
+        let mut results = results.into_iter();
+        let res = policy
+            .run(
+                Args(clones.dupe()),
+                |_args| {
+                    calls.set(calls.get() + 1);
+                    futures::future::ready(results.next().unwrap())
+                },
+                || retries.set(retries.get() + 1),
+            )
+            .await;
+        (res, calls.get(), retries.get(), clones.get())
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = ReRetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(500),
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(4), Duration::from_millis(500));
+        assert_eq!(policy.backoff(40), Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn test_run_without_retries() {
+        let (res, calls, retries, clones) = run(policy(1), vec![Err(unavailable())]).await;
+        assert!(res.is_err());
+        assert_eq!((1, 0, 0), (calls, retries, clones));
+    }
+
+    #[tokio::test]
+    async fn test_run_retries_transient_errors() {
+        let (res, calls, retries, clones) =
+            run(policy(3), vec![Err(unavailable()), Ok(1), Ok(2)]).await;
+        assert_eq!(1, res.unwrap());
+        assert_eq!((2, 1, 2), (calls, retries, clones));
+
+        // The last attempt takes the arguments.
+        let (res, calls, retries, clones) = run(
+            policy(3),
+            vec![Err(unavailable()), Err(unavailable()), Err(unavailable())],
+        )
+        .await;
+        assert!(res.is_err());
+        assert_eq!((3, 2, 2), (calls, retries, clones));
+    }
+
+    #[tokio::test]
+    async fn test_run_does_not_retry_other_errors() {
+        let (res, calls, retries, _) = run(
+            policy(3),
+            vec![Err(anyhow::anyhow!("not transient")), Ok(1)],
+        )
+        .await;
+        assert!(res.is_err());
+        assert_eq!((1, 0), (calls, retries));
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let breaker = ReCircuitBreaker::new(Some(ReCircuitBreakerConfig {
+            errors: 2,
+            cooldown: Duration::from_secs(60),
+        }));
+        let transient = || -> anyhow::Result<()> { Err(unavailable()) };
+
+        breaker.record(&transient());
+        breaker.record(&Ok(()));
+        breaker.record(&transient());
+        assert!(!breaker.is_open());
+        breaker.record(&Err(anyhow::anyhow!("not transient")));
+        assert!(!breaker.is_open());
+        breaker.record(&transient());
+        assert!(breaker.is_open());
+    }
+}
@@ -9,16 +9,24 @@
 
 use std::future::Future;
 use std::sync::atomic::AtomicU32;
+use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
+use std::time::Instant;
 
 use allocative::Allocative;
 use futures::FutureExt;
 
+use crate::re::retry::ReRetryPolicy;
+
 #[derive(Default)]
 pub struct RemoteExecutionClientOpStats {
     pub started: u32,
     pub finished_successfully: u32,
     pub finished_with_error: u32,
+    /// Attempts which failed with a transient error and were retried.
+    pub retries: u32,
+    /// Total time spent in finished operations, including retries, in microseconds.
+    pub latency_us: u64,
 }
 
 impl From<&'_ OpStats> for RemoteExecutionClientOpStats {
@@ -27,6 +35,8 @@ impl From<&'_ OpStats> for RemoteExecutionClientOpStats {
             started: stats.started.load(Ordering::Relaxed),
             finished_successfully: stats.finished_successfully.load(Ordering::Relaxed),
             finished_with_error: stats.finished_with_error.load(Ordering::Relaxed),
+            retries: stats.retries.load(Ordering::Relaxed),
+            latency_us: stats.latency_us.load(Ordering::Relaxed),
         }
     }
 }
@@ -44,6 +54,8 @@ pub struct RemoteExecutionClientStats {
     pub materializes: RemoteExecutionClientOpStats,
     pub write_action_results: RemoteExecutionClientOpStats,
     pub get_digest_expirations: RemoteExecutionClientOpStats,
+    /// Whether the circuit breaker currently sends actions to local execution.
+    pub circuit_open: bool,
 }
 
 #[derive(Default, Allocative)]
@@ -51,6 +63,8 @@ pub(super) struct OpStats {
     started: AtomicU32,
     finished_successfully: AtomicU32,
     finished_with_error: AtomicU32,
+    retries: AtomicU32,
+    latency_us: AtomicU64,
 }
 
 impl OpStats {
@@ -61,7 +75,10 @@ impl OpStats {
         // We avoid using `async fn` or `async move` here to avoid doubling the
         // future size. See https://github.com/rust-lang/rust/issues/62958
         self.started.fetch_add(1, Ordering::Relaxed);
-        f.map(|result| {
+        let start = Instant::now();
+        f.map(move |result| {
+            self.latency_us
+                .fetch_add(start.elapsed().as_micros() as u64, Ordering::Relaxed);
             (if result.is_ok() {
                 &self.finished_successfully
             } else {
@@ -71,4 +88,22 @@ impl OpStats {
             result
         })
     }
+
+    /// Like `op`, but retries the operation on `args` according to `policy`.
+    pub(super) async fn op_with_retries<A, R, F, Fut>(
+        &self,
+        policy: &ReRetryPolicy,
+        args: A,
+        f: F,
+    ) -> anyhow::Result<R>
+    where
+        A: Clone,
+        F: FnMut(A) -> Fut,
+        Fut: Future<Output = anyhow::Result<R>>,
+    {
+        self.op(policy.run(args, f, || {
+            self.retries.fetch_add(1, Ordering::Relaxed);
+        }))
+        .await
+    }
 }
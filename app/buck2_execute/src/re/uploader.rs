@@ -7,9 +7,12 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::future::Future;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::sync::Mutex;
 
 use anyhow::Context;
 use buck2_common::cas_digest::TrackedCasDigest;
@@ -24,10 +27,16 @@ use buck2_core::execution_types::executor_config::RemoteExecutorUseCase;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::soft_error;
+use buck2_events::dispatch::get_dispatcher_opt;
 use chrono::Duration;
 use chrono::Utc;
+use futures::channel::oneshot;
+use futures::future::Shared;
 use futures::FutureExt;
+use futures::StreamExt;
+use futures::TryStreamExt;
 use gazebo::prelude::*;
+use remote_execution::DigestWithTtl;
 use remote_execution::GetDigestsTtlRequest;
 use remote_execution::InlinedBlobWithDigest;
 use remote_execution::NamedDigest;
@@ -35,6 +44,7 @@ use remote_execution::REClient;
 use remote_execution::REClientError;
 use remote_execution::TCode;
 use remote_execution::TDigest;
+use remote_execution::UploadProgress;
 use remote_execution::UploadRequest;
 
 use crate::digest::CasDigestFromReExt;
@@ -57,11 +67,219 @@ pub struct UploadStats {
     pub digests_uploaded: u64,
 }
 
+/// How many digests are queried per request. The RE client sends larger requests as sequential
+/// requests of this size, so larger batches are split here and the requests sent concurrently.
+const FIND_MISSING_CHUNK_SIZE: usize = 100;
+
+/// How many requests of a batch are in flight at once.
+const FIND_MISSING_CONCURRENCY: usize = 8;
+
+type DigestTtls = Result<Arc<HashMap<TDigest, i64>>, buck2_error::Error>;
+
+struct FindMissingBatch {
+    id: u64,
+    digests: HashSet<TDigest>,
+    /// Whether uploads other than the leader joined the batch.
+    shared: bool,
+    ttls: Shared<oneshot::Receiver<DigestTtls>>,
+}
+
+#[derive(Default)]
+struct FindMissingQueue {
+    next_id: u64,
+    /// The batch whose request is in flight.
+    in_flight: Option<(u64, Shared<oneshot::Receiver<DigestTtls>>)>,
+    /// The batch which uploads join while a request is in flight. It is sent once that request
+    /// completes.
+    pending: Option<FindMissingBatch>,
+}
+
+/// Coalesces the `get_digests_ttl` calls of concurrent uploads. Actions which become ready
+/// together tend to share most of their inputs (e.g. the toolchain), so querying them in one
+/// request saves both round trips and duplicate lookups.
+///
+/// When no request is in flight for a use case, an upload sends its own right away. Otherwise, it
+/// joins the next batch, which is sent as soon as the request in flight completes. The first
+/// upload to join the batch leads it: it sends the request and shares the result. If the leader
+/// is cancelled, the uploads which joined its batch fall back to querying on their own.
+#[derive(Default)]
+pub struct FindMissingBatcher {
+    queues: Mutex<HashMap<RemoteExecutorUseCase, FindMissingQueue>>,
+}
+
+/// Removes the batch of a leader from the batcher when it is dropped, whether it completed or
+/// was cancelled.
+struct FindMissingBatchGuard<'a> {
+    batcher: &'a FindMissingBatcher,
+    use_case: RemoteExecutorUseCase,
+    id: u64,
+}
+
+impl FindMissingBatchGuard<'_> {
+    /// Moves the batch from pending to in flight, returning its digests and whether it is shared.
+    fn send(&self) -> (HashSet<TDigest>, bool) {
+        let mut queues = self.batcher.queues.lock().unwrap();
+        let queue = queues.entry(self.use_case).or_default();
+        match queue.pending.take() {
+            Some(batch) if batch.id == self.id => {
+                queue.in_flight = Some((batch.id, batch.ttls));
+                (batch.digests, batch.shared)
+            }
+            // Not reachable: only the leader takes its batch.
+            pending => {
+                queue.pending = pending;
+                (HashSet::new(), false)
+            }
+        }
+    }
+}
+
+impl Drop for FindMissingBatchGuard<'_> {
+    fn drop(&mut self) {
+        let mut queues = self.batcher.queues.lock().unwrap();
+        let Some(queue) = queues.get_mut(&self.use_case) else {
+            return;
+        };
+        if queue.pending.as_ref().is_some_and(|b| b.id == self.id) {
+            queue.pending = None;
+        }
+        if queue
+            .in_flight
+            .as_ref()
+            .is_some_and(|(id, _)| *id == self.id)
+        {
+            queue.in_flight = None;
+        }
+        if queue.pending.is_none() && queue.in_flight.is_none() {
+            queues.remove(&self.use_case);
+        }
+    }
+}
+
+impl FindMissingBatcher {
+    async fn get_digests_ttl(
+        &self,
+        client: &REClient,
+        use_case: RemoteExecutorUseCase,
+        identity: Option<&ReActionIdentity<'_>>,
+        digests: Vec<TDigest>,
+    ) -> anyhow::Result<Vec<DigestWithTtl>> {
+        self.query(use_case, digests, |digests, shared| {
+            // A shared request doesn't belong to any one action.
+            let identity = if shared { None } else { identity };
+            Self::get_digests_ttl_unbatched(client, use_case, identity, digests)
+        })
+        .await
+    }
+
+    /// Batches `digests` with those of concurrent calls, and looks them up with `query`, which
+    /// is told whether the digests of several calls are being looked up.
+    async fn query<F, Fut>(
+        &self,
+        use_case: RemoteExecutorUseCase,
+        digests: Vec<TDigest>,
+        query: F,
+    ) -> anyhow::Result<Vec<DigestWithTtl>>
+    where
+        F: Fn(Vec<TDigest>, bool) -> Fut,
+        Fut: Future<Output = anyhow::Result<Vec<DigestWithTtl>>>,
+    {
+        let joined = {
+            let mut queues = self.queues.lock().unwrap();
+            let queue = queues.entry(use_case).or_default();
+            match &mut queue.pending {
+                Some(batch) => {
+                    batch.digests.extend(digests.iter().cloned());
+                    batch.shared = true;
+                    Ok(batch.ttls.clone())
+                }
+                None => {
+                    let (tx, rx) = oneshot::channel();
+                    let id = queue.next_id;
+                    queue.next_id += 1;
+                    queue.pending = Some(FindMissingBatch {
+                        id,
+                        digests: digests.iter().cloned().collect(),
+                        shared: false,
+                        ttls: rx.shared(),
+                    });
+                    Err((tx, id, queue.in_flight.as_ref().map(|(_, t)| t.clone())))
+                }
+            }
+        };
+
+        let ttls = match joined {
+            Ok(ttls) => match ttls.await {
+                Ok(ttls) => ttls?,
+                Err(oneshot::Canceled) => return query(digests, false).await,
+            },
+            Err((tx, id, in_flight)) => {
+                let guard = FindMissingBatchGuard {
+                    batcher: self,
+                    use_case,
+                    id,
+                };
+                if let Some(in_flight) = in_flight {
+                    // Only its completion matters, its waiters handle its result.
+                    let _ignored = in_flight.await;
+                }
+                let (batch, shared) = guard.send();
+                let ttls = query(batch.into_iter().collect(), shared)
+                    .await
+                    .map(|ttls| Arc::new(ttls.into_iter().map(|d| (d.digest, d.ttl)).collect()))
+                    .map_err(buck2_error::Error::from);
+                // Let the next batch go before waking up this one's waiters.
+                drop(guard);
+                // The other uploads in the batch may all have been cancelled.
+                let _ignored = tx.send(ttls.clone());
+                ttls?
+            }
+        };
+
+        digests.into_try_map(|digest| {
+            let ttl = *ttls.get(&digest).with_context(|| {
+                format!("Invalid response from get_digests_ttl: missing {}", digest)
+            })?;
+            anyhow::Ok(DigestWithTtl {
+                digest,
+                ttl,
+                ..Default::default()
+            })
+        })
+    }
+
+    async fn get_digests_ttl_unbatched(
+        client: &REClient,
+        use_case: RemoteExecutorUseCase,
+        identity: Option<&ReActionIdentity<'_>>,
+        digests: Vec<TDigest>,
+    ) -> anyhow::Result<Vec<DigestWithTtl>> {
+        let responses =
+            futures::stream::iter(digests.chunks(FIND_MISSING_CHUNK_SIZE).map(|chunk| {
+                let request = GetDigestsTtlRequest {
+                    digests: chunk.to_vec(),
+                    ..Default::default()
+                };
+                client
+                    .get_digests_ttl(use_case.metadata(identity), request)
+                    .boxed()
+            }))
+            .buffer_unordered(FIND_MISSING_CONCURRENCY)
+            .try_collect::<Vec<_>>()
+            .await?;
+        Ok(responses
+            .into_iter()
+            .flat_map(|r| r.digests_with_ttl)
+            .collect())
+    }
+}
+
 pub struct Uploader {}
 
 impl Uploader {
     async fn find_missing<'a>(
         client: &REClient,
+        batcher: &FindMissingBatcher,
         input_dir: &'a ActionImmutableDirectory,
         blobs: &'a ActionBlobs,
         use_case: &RemoteExecutorUseCase,
@@ -102,15 +320,14 @@ impl Uploader {
             }
 
             // Find out which ones are missing
-            let request = GetDigestsTtlRequest {
-                digests: input_digests.iter().map(|d| d.to_re()).collect(),
-                ..Default::default()
-            };
-            client
-                .get_digests_ttl(use_case.metadata(identity), request)
-                .boxed()
+            batcher
+                .get_digests_ttl(
+                    client,
+                    *use_case,
+                    identity,
+                    input_digests.iter().map(|d| d.to_re()).collect(),
+                )
                 .await?
-                .digests_with_ttl
         };
 
         let mut upload_blobs = Vec::new();
@@ -168,6 +385,7 @@ impl Uploader {
     pub async fn upload(
         fs: &ProjectRoot,
         client: &REClient,
+        batcher: &FindMissingBatcher,
         materializer: &Arc<dyn Materializer>,
         dir_path: &ProjectRelativePath,
         input_dir: &ActionImmutableDirectory,
@@ -176,9 +394,16 @@ impl Uploader {
         identity: Option<&ReActionIdentity<'_>>,
         digest_config: DigestConfig,
     ) -> anyhow::Result<UploadStats> {
-        let (mut upload_blobs, mut missing_digests) =
-            Self::find_missing(client, input_dir, blobs, &use_case, identity, digest_config)
-                .await?;
+        let (mut upload_blobs, mut missing_digests) = Self::find_missing(
+            client,
+            batcher,
+            input_dir,
+            blobs,
+            &use_case,
+            identity,
+            digest_config,
+        )
+        .await?;
 
         if upload_blobs.is_empty() && missing_digests.is_empty() {
             return Ok(UploadStats::default());
@@ -355,7 +580,7 @@ impl Uploader {
         // Upload
         let upload_res = if !upload_files.is_empty() || !upload_blobs.is_empty() {
             client
-                .upload(
+                .upload_with_progress(
                     use_case.metadata(identity),
                     UploadRequest {
                         files_with_digest: Some(upload_files),
@@ -365,6 +590,7 @@ impl Uploader {
                         upload_only_missing: false,
                         ..Default::default()
                     },
+                    large_upload_progress(),
                 )
                 .boxed()
                 .await
@@ -432,6 +658,20 @@ fn error_for_missing_file(
     )
 }
 
+/// Reports the progress of large uploads to the console, if there is one.
+fn large_upload_progress() -> UploadProgress {
+    match get_dispatcher_opt() {
+        Some(dispatcher) => Arc::new(move |digest: &TDigest, bytes_sent: i64| {
+            dispatcher.instant_event(buck2_data::ReLargeUploadProgress {
+                digest: digest.to_string(),
+                bytes_sent: bytes_sent as u64,
+                total_bytes: digest.size_in_bytes as u64,
+            })
+        }),
+        None => Arc::new(|_: &TDigest, _: i64| {}),
+    }
+}
+
 /// This is used for tests. We allow an environment variable to be set to report that some digests
 /// are _always_ missing if they are required. This lets us test our upload paths more easily.
 fn add_injected_missing_digests<'a>(
@@ -466,3 +706,117 @@ fn add_injected_missing_digests<'a>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::pin::pin;
+
+    use super::*;
+
+    /// Records the lookups, holding the first one until `release` is sent.
+    struct FakeRe {
+        calls: Mutex<Vec<(Vec<i64>, bool)>>,
+        released: Shared<oneshot::Receiver<()>>,
+    }
+
+    impl FakeRe {
+        fn new() -> (Self, oneshot::Sender<()>) {
+            let (release, released) = oneshot::channel();
+            let re = Self {
+                calls: Mutex::new(Vec::new()),
+                released: released.shared(),
+            };
+            (re, release)
+        }
+
+        fn query(
+            &self,
+            digests: Vec<TDigest>,
+            shared: bool,
+        ) -> impl Future<Output = anyhow::Result<Vec<DigestWithTtl>>> {
+            let first = {
+                let mut calls = self.calls.lock().unwrap();
+                let mut sizes = digests.map(|d| d.size_in_bytes);
+                sizes.sort();
+                calls.push((sizes, shared));
+                calls.len() == 1
+            };
+            let released = self.released.clone();
+            async move {
+                if first {
+                    let _ignored = released.await;
+                }
+                anyhow::Ok(digests.into_map(|digest| DigestWithTtl {
+                    ttl: digest.size_in_bytes * 10,
+                    digest,
+                    ..Default::default()
+                }))
+            }
+        }
+    }
+
+    fn digests(sizes: &[i64]) -> Vec<TDigest> {
+        sizes.map(|size| TDigest {
+            hash: format!("{:040x}", size),
+            size_in_bytes: *size,
+            ..Default::default()
+        })
+    }
+
+    fn ttls(res: anyhow::Result<Vec<DigestWithTtl>>) -> Vec<(i64, i64)> {
+        res.unwrap().map(|d| (d.digest.size_in_bytes, d.ttl))
+    }
+
+    #[tokio::test]
+    async fn test_batches_while_in_flight() {
+        let batcher = FindMissingBatcher::default();
+        let use_case = RemoteExecutorUseCase::buck2_default();
+        let (re, release) = FakeRe::new();
+        let query = |digests, shared| re.query(digests, shared);
+
+        // The first lookup is sent right away, the others wait for it and are sent together.
+        let (a, b, c, ()) = futures::join!(
+            batcher.query(use_case, digests(&[1]), query),
+            batcher.query(use_case, digests(&[2, 3]), query),
+            batcher.query(use_case, digests(&[3]), query),
+            async { release.send(()).unwrap() },
+        );
+
+        assert_eq!(ttls(a), vec![(1, 10)]);
+        assert_eq!(ttls(b), vec![(2, 20), (3, 30)]);
+        assert_eq!(ttls(c), vec![(3, 30)]);
+        assert_eq!(
+            *re.calls.lock().unwrap(),
+            vec![(vec![1], false), (vec![2, 3], true)]
+        );
+        assert!(batcher.queues.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_leader() {
+        let batcher = FindMissingBatcher::default();
+        let use_case = RemoteExecutorUseCase::buck2_default();
+        let (re, release) = FakeRe::new();
+        let query = |digests, shared| re.query(digests, shared);
+
+        let mut a = pin!(batcher.query(use_case, digests(&[1]), query));
+        assert!(futures::poll!(&mut a).is_pending());
+        let mut b = Box::pin(batcher.query(use_case, digests(&[2]), query));
+        assert!(futures::poll!(&mut b).is_pending());
+        let mut c = pin!(batcher.query(use_case, digests(&[3]), query));
+        assert!(futures::poll!(&mut c).is_pending());
+
+        // The upload which joined the batch of `b` looks its digests up on its own.
+        drop(b);
+        release.send(()).unwrap();
+        let (a, c) = futures::join!(a, c);
+
+        assert_eq!(ttls(a), vec![(1, 10)]);
+        assert_eq!(ttls(c), vec![(3, 30)]);
+        assert_eq!(
+            *re.calls.lock().unwrap(),
+            vec![(vec![1], false), (vec![3], false)]
+        );
+        assert!(batcher.queues.lock().unwrap().is_empty());
+    }
+}
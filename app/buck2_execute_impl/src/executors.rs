@@ -10,9 +10,13 @@
 pub mod action_cache;
 pub mod action_cache_upload_permission_checker;
 pub mod caching;
+pub mod determinism_check;
+pub mod double_execution;
 pub(crate) mod empty_action_result;
+pub mod http_cache;
 pub mod hybrid;
 pub mod local;
+pub mod local_action_cache;
 pub mod re;
 pub mod stacked;
 pub mod to_re_platform;
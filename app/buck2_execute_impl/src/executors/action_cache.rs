@@ -150,7 +150,15 @@ async fn query_action_cache_and_download_result(
             let dep_file_entry = match RemoteDepFile::decode(metadata.unwrap().value.as_slice()) {
                 Ok(entry) => entry,
                 Err(e) => {
-                    return ControlFlow::Break(manager.error("remote_dep_file", e));
+                    // A malformed entry (e.g. written by an incompatible version) can't be
+                    // verified against our inputs, so treat it as a miss and run the action
+                    // rather than failing it.
+                    tracing::warn!(
+                        "Ignoring malformed remote dep file cache entry for `{}`: {:#}",
+                        digest,
+                        e
+                    );
+                    return ControlFlow::Continue(manager);
                 }
             };
             Some(dep_file_entry)
@@ -0,0 +1,194 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fmt::Write;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_events::dispatch::console_warning;
+use buck2_execute::artifact_value::ArtifactValue;
+use buck2_execute::execute::claim::MutexClaimManager;
+use buck2_execute::execute::manager::CommandExecutionManager;
+use buck2_execute::execute::prepared::PreparedCommand;
+use buck2_execute::execute::prepared::PreparedCommandExecutor;
+use buck2_execute::execute::request::CommandExecutionOutput;
+use buck2_execute::execute::request::ExecutorPreference;
+use buck2_execute::execute::result::CommandExecutionResult;
+use buck2_futures::cancellation::CancellationContext;
+use dupe::Dupe;
+use indexmap::IndexMap;
+
+use crate::executors::double_execution::describe;
+
+/// Executes every command twice, and reports the commands whose outputs differ between the two
+/// executions. This is used by `--unstable-check-determinism`, which also disables the caches, so
+/// that both executions actually run.
+///
+/// The outputs of the second execution are the ones used by the build, so that they match what
+/// is left on disk. The first execution is only compared.
+pub struct DeterminismChecker {
+    pub inner: Arc<dyn PreparedCommandExecutor>,
+    pub artifact_fs: ArtifactFs,
+}
+
+#[async_trait]
+impl PreparedCommandExecutor for DeterminismChecker {
+    async fn exec_cmd(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        manager: CommandExecutionManager,
+        cancellations: &CancellationContext,
+    ) -> CommandExecutionResult {
+        // The first execution gets its own claim, since its outputs are not used.
+        let first_manager = CommandExecutionManager::new(
+            Box::new(MutexClaimManager::new()),
+            manager.inner.events.dupe(),
+            manager.inner.liveliness_observer.dupe(),
+        );
+        let first = self
+            .inner
+            .exec_cmd(command, first_manager, cancellations)
+            .await;
+        let result = self.inner.exec_cmd(command, manager, cancellations).await;
+
+        if !first.was_success() || !result.was_success() {
+            return result;
+        }
+        let divergences = output_divergences(&first.outputs, &result.outputs, &self.artifact_fs);
+        if !divergences.is_empty() {
+            let mut message = format!(
+                "Executing `{}` (action digest `{}`) twice produced different outputs:\n",
+                command.target.re_action_key(),
+                command.prepared_action.digest(),
+            );
+            for divergence in &divergences {
+                writeln!(message, "  {}", divergence).unwrap();
+            }
+            write!(
+                message,
+                "Check the outputs for embedded timestamps, absolute paths or nondeterministic ordering.\nCommand line:\n  {}",
+                command.request.all_args_str()
+            )
+            .unwrap();
+            console_warning(message);
+        }
+
+        result
+    }
+
+    fn is_local_execution_possible(&self, executor_preference: ExecutorPreference) -> bool {
+        self.inner.is_local_execution_possible(executor_preference)
+    }
+}
+
+/// Describes the outputs of the second execution which differ from those of the first one.
+fn output_divergences(
+    first: &IndexMap<CommandExecutionOutput, ArtifactValue>,
+    second: &IndexMap<CommandExecutionOutput, ArtifactValue>,
+    artifact_fs: &ArtifactFs,
+) -> Vec<String> {
+    second
+        .iter()
+        .filter_map(|(output, value)| {
+            let first_value = first.get(output);
+            if first_value.map(|v| v.entry()) == Some(value.entry()) {
+                return None;
+            }
+            Some(format!(
+                "{}: {}, then {}",
+                output.as_ref().resolve(artifact_fs).into_path(),
+                describe(first_value),
+                describe(Some(value)),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_common::file_ops::FileMetadata;
+    use buck2_common::file_ops::TrackedFileDigest;
+    use buck2_core::base_deferred_key::BaseDeferredKey;
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::CellResolver;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::fs::buck_out_path::BuckOutPath;
+    use buck2_core::fs::buck_out_path::BuckOutPathResolver;
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+    use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+    use buck2_execute::digest_config::DigestConfig;
+    use buck2_execute::execute::request::OutputType;
+
+    use super::*;
+
+    fn output(path: &str) -> CommandExecutionOutput {
+        CommandExecutionOutput::BuildArtifact {
+            path: BuckOutPath::new(
+                BaseDeferredKey::TargetLabel(ConfiguredTargetLabel::testing_parse(
+                    "cell//pkg:foo",
+                    ConfigurationData::testing_new(),
+                )),
+                ForwardRelativePathBuf::unchecked_new(path.to_owned()),
+            ),
+            output_type: OutputType::File,
+        }
+    }
+
+    fn file(contents: &str) -> ArtifactValue {
+        ArtifactValue::file(FileMetadata {
+            digest: TrackedFileDigest::from_content(
+                contents.as_bytes(),
+                DigestConfig::testing_default().cas_digest_config(),
+            ),
+            is_executable: false,
+        })
+    }
+
+    #[test]
+    fn test_output_divergences() -> anyhow::Result<()> {
+        let project = ProjectRootTemp::new()?;
+        let artifact_fs = ArtifactFs::new(
+            CellResolver::testing_with_name_and_path(
+                CellName::testing_new("cell"),
+                CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("cell_path".into())),
+            ),
+            BuckOutPathResolver::new(ProjectRelativePathBuf::unchecked_new("buck_out/v2".into())),
+            project.path().dupe(),
+        );
+
+        let first = IndexMap::from([
+            (output("same"), file("a")),
+            (output("stamped"), file("built at 1")),
+        ]);
+        let second = IndexMap::from([
+            (output("same"), file("a")),
+            (output("stamped"), file("built at 2")),
+            (output("new"), file("b")),
+        ]);
+        assert!(output_divergences(&first, &first, &artifact_fs).is_empty());
+
+        let divergences = output_divergences(&first, &second, &artifact_fs);
+        assert_eq!(2, divergences.len(), "{:?}", divergences);
+        assert!(
+            divergences[0].contains("stamped: file ") && divergences[0].contains(", then file "),
+            "{}",
+            divergences[0]
+        );
+        assert!(
+            divergences[1].contains("new: missing, then file "),
+            "{}",
+            divergences[1]
+        );
+        Ok(())
+    }
+}
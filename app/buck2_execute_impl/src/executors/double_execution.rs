@@ -0,0 +1,183 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fmt::Write;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use buck2_core::directory::DirectoryEntry;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_events::dispatch::console_warning;
+use buck2_execute::artifact_value::ArtifactValue;
+use buck2_execute::directory::ActionDirectoryMember;
+use buck2_execute::execute::action_digest::ActionDigest;
+use buck2_execute::execute::claim::MutexClaimManager;
+use buck2_execute::execute::manager::CommandExecutionManager;
+use buck2_execute::execute::prepared::PreparedCommand;
+use buck2_execute::execute::prepared::PreparedCommandExecutor;
+use buck2_execute::execute::prepared::PreparedCommandOptionalExecutor;
+use buck2_execute::execute::request::ExecutorPreference;
+use buck2_execute::execute::result::CommandExecutionResult;
+use buck2_futures::cancellation::CancellationContext;
+use dupe::Dupe;
+
+use crate::executors::re::ReExecutor;
+
+/// Executes a sample of the commands both locally and remotely, and reports the commands whose
+/// outputs differ. This is used in paranoid mode to detect toolchains which are not hermetic.
+///
+/// Sampled commands are first looked up in the action cache, and only verified on a cache miss.
+/// The local execution is the one whose outputs are used by the build. The remote execution
+/// bypasses the action cache, and its outputs are never downloaded, only compared. The build
+/// waits for both executions, so each sampled cache miss takes as long as the slower of the two,
+/// and runs locally even when it would have run remotely otherwise. Commands which are not
+/// sampled, or which can only run on one of the executors, go to `inner`.
+pub struct DoubleExecutionVerifier {
+    pub inner: Arc<dyn PreparedCommandExecutor>,
+    pub cache_checker: Arc<dyn PreparedCommandOptionalExecutor>,
+    pub local: Arc<dyn PreparedCommandExecutor>,
+    pub remote: ReExecutor,
+    pub artifact_fs: ArtifactFs,
+    /// Fraction of the commands to execute twice, between 0 and 1.
+    pub sample_rate: f64,
+}
+
+#[async_trait]
+impl PreparedCommandExecutor for DoubleExecutionVerifier {
+    async fn exec_cmd(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        manager: CommandExecutionManager,
+        cancellations: &CancellationContext,
+    ) -> CommandExecutionResult {
+        let executor_preference = command.request.executor_preference();
+        if executor_preference.requires_local()
+            || executor_preference.requires_remote()
+            || !is_sampled(&command.prepared_action.digest(), self.sample_rate)
+        {
+            return self.inner.exec_cmd(command, manager, cancellations).await;
+        }
+
+        // There is nothing to verify if the outputs are reused from the cache.
+        let manager = self
+            .cache_checker
+            .maybe_execute(command, manager, cancellations)
+            .await?;
+
+        // The remote execution gets its own claim, since it doesn't produce any outputs.
+        let remote_manager = CommandExecutionManager::new(
+            Box::new(MutexClaimManager::new()),
+            manager.inner.events.dupe(),
+            manager.inner.liveliness_observer.dupe(),
+        );
+        let (result, remote) = futures::future::join(
+            self.local.exec_cmd(command, manager, cancellations),
+            self.remote
+                .exec_cmd_for_verification(command, remote_manager),
+        )
+        .await;
+
+        if !result.was_success() {
+            return result;
+        }
+        let divergences = match remote {
+            Ok(Some((exit_code, remote_outputs))) => {
+                let mut divergences = Vec::new();
+                if exit_code != 0 {
+                    divergences.push(format!("remote exit code: {}", exit_code));
+                }
+                for (output, local_value) in &result.outputs {
+                    let remote_value = remote_outputs.get(output);
+                    if remote_value.map(|v| v.entry()) != Some(local_value.entry()) {
+                        divergences.push(format!(
+                            "{}: local {}, remote {}",
+                            output.as_ref().resolve(&self.artifact_fs).into_path(),
+                            describe(Some(local_value)),
+                            describe(remote_value),
+                        ));
+                    }
+                }
+                divergences
+            }
+            Ok(None) => return result,
+            Err(e) => {
+                tracing::warn!(
+                    "Error verifying `{}` on remote execution: {:#}",
+                    command.prepared_action.digest(),
+                    e
+                );
+                return result;
+            }
+        };
+
+        if !divergences.is_empty() {
+            let mut message = format!(
+                "Local and remote execution of `{}` (action digest `{}`) differ:\n",
+                command.target.re_action_key(),
+                command.prepared_action.digest(),
+            );
+            for divergence in &divergences {
+                writeln!(message, "  {}", divergence).unwrap();
+            }
+            write!(
+                message,
+                "Command line:\n  {}",
+                command.request.all_args_str()
+            )
+            .unwrap();
+            console_warning(message);
+        }
+
+        result
+    }
+
+    fn is_local_execution_possible(&self, executor_preference: ExecutorPreference) -> bool {
+        self.inner.is_local_execution_possible(executor_preference)
+    }
+}
+
+/// Whether to execute the action twice. This depends on the digest, and not on chance, so that
+/// builds of the same revision verify the same actions.
+fn is_sampled(action_digest: &ActionDigest, sample_rate: f64) -> bool {
+    let bytes = action_digest.raw_digest().as_bytes();
+    let mut prefix = [0; 8];
+    prefix.copy_from_slice(&bytes[..8]);
+    sample_rate > 0.0 && (u64::from_le_bytes(prefix) as f64) / (u64::MAX as f64) <= sample_rate
+}
+
+pub(crate) fn describe(value: Option<&ArtifactValue>) -> String {
+    match value.map(|v| v.entry()) {
+        None => "missing".to_owned(),
+        Some(DirectoryEntry::Dir(d)) => format!("directory {}", d.fingerprint()),
+        Some(DirectoryEntry::Leaf(ActionDirectoryMember::File(f))) => format!("file {}", f.digest),
+        Some(DirectoryEntry::Leaf(ActionDirectoryMember::Symlink(s))) => {
+            format!("symlink to {}", s)
+        }
+        Some(DirectoryEntry::Leaf(ActionDirectoryMember::ExternalSymlink(s))) => {
+            format!("symlink to {}", s)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_sampled() {
+        let low = ActionDigest::new_sha1([0; 20], 1);
+        let high = ActionDigest::new_sha1([0xff; 20], 1);
+        assert!(!is_sampled(&low, 0.0));
+        assert!(!is_sampled(&high, 0.0));
+        assert!(is_sampled(&low, 0.5));
+        assert!(!is_sampled(&high, 0.5));
+        assert!(is_sampled(&low, 1.0));
+        assert!(is_sampled(&high, 1.0));
+    }
+}
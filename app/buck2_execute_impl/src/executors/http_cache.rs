@@ -0,0 +1,825 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A remote cache speaking the HTTP cache protocol of Bazel, for projects which don't have a
+//! gRPC remote execution service.
+//!
+//! Action results are `ActionResult` messages stored at `<url>/ac/<action digest hash>`, and the
+//! blobs they reference (files, `Tree` messages of output directories, stdout and stderr) are
+//! stored at `<url>/cas/<blob digest hash>`. Reads are `GET`s, where a 404 is a miss, and writes
+//! are `PUT`s.
+
+use std::ops::ControlFlow;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_common::file_ops::FileDigest;
+use buck2_common::file_ops::FileMetadata;
+use buck2_common::file_ops::TrackedFileDigest;
+use buck2_core::directory::unordered_entry_walk;
+use buck2_core::directory::DirectoryEntry;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_execute::artifact_value::ArtifactValue;
+use buck2_execute::digest::CasDigestFromReExt;
+use buck2_execute::digest::CasDigestToReExt;
+use buck2_execute::digest_config::DigestConfig;
+use buck2_execute::directory::directory_to_re_tree;
+use buck2_execute::directory::extract_artifact_value;
+use buck2_execute::directory::re_tree_to_directory;
+use buck2_execute::directory::ActionDirectoryMember;
+use buck2_execute::entry::HashingInfo;
+use buck2_execute::execute::action_digest::ActionDigest;
+use buck2_execute::execute::blocking::BlockingExecutor;
+use buck2_execute::execute::kind::CommandExecutionKind;
+use buck2_execute::execute::manager::CommandExecutionManager;
+use buck2_execute::execute::output::CommandStdStreams;
+use buck2_execute::execute::prepared::PreparedCommand;
+use buck2_execute::execute::prepared::PreparedCommandExecutor;
+use buck2_execute::execute::prepared::PreparedCommandOptionalExecutor;
+use buck2_execute::execute::request::CommandExecutionOutput;
+use buck2_execute::execute::request::ExecutorPreference;
+use buck2_execute::execute::result::CommandExecutionMetadata;
+use buck2_execute::execute::result::CommandExecutionResult;
+use buck2_execute::materialize::materializer::Materializer;
+use buck2_futures::cancellation::CancellationContext;
+use buck2_http::HttpClient;
+use buck2_http::HttpError;
+use bytes::Bytes;
+use chrono::Utc;
+use dupe::Dupe;
+use futures::stream;
+use futures::FutureExt;
+use futures::StreamExt;
+use futures::TryStreamExt;
+use indexmap::IndexMap;
+use prost::Message;
+use remote_execution as RE;
+use tokio_util::io::ReaderStream;
+
+use crate::executors::local::calculate_and_declare_output_values;
+use crate::executors::local::create_output_dirs;
+use crate::executors::local_action_cache::member_symlink_target;
+use crate::executors::local_action_cache::set_executable;
+
+/// How many blobs to download or upload at once for an action.
+const HTTP_CACHE_CONCURRENCY: usize = 16;
+
+#[derive(Debug, buck2_error::Error)]
+enum HttpCacheError {
+    #[error("Invalid HTTP cache mode `{0}`, expected `read_only` or `read_write`")]
+    InvalidMode(String),
+    #[error("Blob `{0}` is missing from the HTTP cache")]
+    MissingBlob(String),
+    #[error("Output `{0}` restored from the HTTP cache does not match its action result")]
+    OutputMismatch(String),
+    #[error("Output `{0}` changed before it was uploaded to the HTTP cache")]
+    OutputChanged(String),
+}
+
+/// Whether the outputs of actions executed locally are uploaded to the HTTP cache.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub enum HttpCacheMode {
+    ReadOnly,
+    ReadWrite,
+}
+
+impl FromStr for HttpCacheMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "read_only" => Ok(Self::ReadOnly),
+            "read_write" => Ok(Self::ReadWrite),
+            _ => Err(HttpCacheError::InvalidMode(s.to_owned()).into()),
+        }
+    }
+}
+
+pub struct HttpCache {
+    client: HttpClient,
+    /// The base URL, without a trailing slash.
+    url: String,
+    mode: HttpCacheMode,
+    /// Outputs larger than this are not uploaded.
+    max_upload_bytes: Option<u64>,
+}
+
+impl HttpCache {
+    pub fn new(
+        client: HttpClient,
+        url: &str,
+        mode: HttpCacheMode,
+        max_upload_bytes: Option<u64>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            client,
+            url: url.trim_end_matches('/').to_owned(),
+            mode,
+            max_upload_bytes,
+        })
+    }
+
+    pub fn mode(&self) -> HttpCacheMode {
+        self.mode
+    }
+
+    pub fn max_upload_bytes(&self) -> Option<u64> {
+        self.max_upload_bytes
+    }
+
+    fn action_url(&self, action_digest: &ActionDigest) -> String {
+        format!("{}/ac/{}", self.url, action_digest.raw_digest())
+    }
+
+    fn blob_url(&self, digest: &FileDigest) -> String {
+        format!("{}/cas/{}", self.url, digest.raw_digest())
+    }
+
+    /// Returns `None` if the server doesn't have `url`.
+    async fn get(&self, url: &str) -> anyhow::Result<Option<Bytes>> {
+        match self.client.get(url).await {
+            Ok(response) => Ok(Some(buck2_http::to_bytes(response.into_body()).await?)),
+            Err(HttpError::Status { status, .. }) if status.as_u16() == 404 => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn put(&self, url: &str, body: Bytes) -> anyhow::Result<()> {
+        self.client.put(url, body, Vec::new()).await?;
+        Ok(())
+    }
+
+    async fn get_blob(&self, digest: &FileDigest) -> anyhow::Result<Bytes> {
+        self.get(&self.blob_url(digest))
+            .await?
+            .ok_or_else(|| HttpCacheError::MissingBlob(digest.to_string()).into())
+    }
+
+    async fn put_blob(&self, digest: &FileDigest, body: Bytes) -> anyhow::Result<()> {
+        self.put(&self.blob_url(digest), body).await
+    }
+
+    /// Streams the file at `path`, which must still have the size of `digest`, so that large
+    /// outputs aren't read into memory.
+    async fn put_file(&self, digest: &FileDigest, path: &AbsNormPath) -> anyhow::Result<()> {
+        let file = tokio::fs::File::open(path)
+            .await
+            .with_context(|| format!("Opening `{}`", path))?;
+        let size = file.metadata().await?.len();
+        if size != digest.size() {
+            return Err(HttpCacheError::OutputChanged(path.to_string()).into());
+        }
+        self.client
+            .put_stream(
+                &self.blob_url(digest),
+                ReaderStream::new(file).boxed(),
+                size,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the result of an action, if it's cached and it succeeded.
+    async fn lookup(
+        &self,
+        action_digest: &ActionDigest,
+    ) -> anyhow::Result<Option<RE::ActionResult>> {
+        let Some(data) = self.get(&self.action_url(action_digest)).await? else {
+            return Ok(None);
+        };
+        let result = RE::ActionResult::decode(data).context("Decoding the action result")?;
+        Ok(Some(result).filter(|r| r.exit_code == 0))
+    }
+
+    /// Uploads the outputs of an action, which are on disk, then its result. Actions with an
+    /// output which is a symlink are not stored, as in the gRPC cache.
+    async fn store(
+        &self,
+        action_digest: &ActionDigest,
+        outputs: Vec<(ProjectRelativePathBuf, ArtifactValue)>,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+        artifact_fs: &ArtifactFs,
+        digest_config: DigestConfig,
+    ) -> anyhow::Result<()> {
+        let mut result = RE::ActionResult::default();
+        let mut files = Vec::new();
+        let mut blobs = Vec::new();
+
+        for (path, value) in &outputs {
+            let output_path = artifact_fs.fs().resolve(path);
+            match value.entry() {
+                DirectoryEntry::Leaf(ActionDirectoryMember::File(f)) => {
+                    result.output_files.push(RE::OutputFile {
+                        path: path.to_string(),
+                        digest: Some(f.digest.to_grpc()),
+                        is_executable: f.is_executable,
+                        ..Default::default()
+                    });
+                    files.push((output_path, f.digest.data().dupe()));
+                }
+                DirectoryEntry::Dir(d) => {
+                    let tree = Bytes::from(directory_to_re_tree(d).encode_to_vec());
+                    let tree_digest =
+                        FileDigest::from_content(&tree, digest_config.cas_digest_config());
+                    result.output_directories.push(RE::OutputDirectory {
+                        path: path.to_string(),
+                        tree_digest: Some(tree_digest.to_grpc()),
+                        ..Default::default()
+                    });
+                    blobs.push((tree_digest, tree));
+
+                    let mut walk = unordered_entry_walk(value.entry().as_ref());
+                    while let Some((entry_path, entry)) = walk.next() {
+                        if let DirectoryEntry::Leaf(ActionDirectoryMember::File(f)) = entry {
+                            files
+                                .push((output_path.join(entry_path.get()), f.digest.data().dupe()));
+                        }
+                    }
+                }
+                DirectoryEntry::Leaf(_) => return Ok(()),
+            }
+        }
+
+        for (std_stream, digest) in [
+            (stdout, &mut result.stdout_digest),
+            (stderr, &mut result.stderr_digest),
+        ] {
+            if !std_stream.is_empty() {
+                let blob_digest =
+                    FileDigest::from_content(&std_stream, digest_config.cas_digest_config());
+                *digest = Some(blob_digest.to_grpc());
+                blobs.push((blob_digest, Bytes::from(std_stream)));
+            }
+        }
+
+        let blob_uploads = stream::iter(blobs)
+            .map(|(digest, body)| async move { self.put_blob(&digest, body).await }.boxed());
+        let file_uploads = stream::iter(files)
+            .map(|(path, digest)| async move { self.put_file(&digest, &path).await }.boxed());
+        blob_uploads
+            .chain(file_uploads)
+            .buffer_unordered(HTTP_CACHE_CONCURRENCY)
+            .try_collect::<Vec<()>>()
+            .await?;
+
+        // Only written once the blobs are, so that it is never a dangling reference.
+        self.put(
+            &self.action_url(action_digest),
+            Bytes::from(result.encode_to_vec()),
+        )
+        .await
+    }
+}
+
+/// Serves actions from the HTTP cache, falling back to `next` on a miss.
+pub struct HttpCacheChecker {
+    pub cache: Arc<HttpCache>,
+    pub artifact_fs: ArtifactFs,
+    pub materializer: Arc<dyn Materializer>,
+    pub blocking_executor: Arc<dyn BlockingExecutor>,
+    pub next: Arc<dyn PreparedCommandOptionalExecutor>,
+}
+
+impl HttpCacheChecker {
+    /// Returns the expected values of the outputs of a command from its cached result.
+    async fn expected_outputs(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        result: &RE::ActionResult,
+    ) -> anyhow::Result<
+        Vec<(
+            CommandExecutionOutput,
+            ProjectRelativePathBuf,
+            ArtifactValue,
+        )>,
+    > {
+        let digest_config = command.digest_config;
+        let paths = command.request.paths();
+        let mut builder = paths.input_directory().clone().into_builder();
+        let now = Utc::now();
+
+        for file in &result.output_files {
+            let digest = FileDigest::from_grpc(
+                file.digest.as_ref().context("Output file without digest")?,
+                digest_config,
+            )?;
+            builder.insert(
+                ForwardRelativePath::new(&file.path)?,
+                DirectoryEntry::Leaf(ActionDirectoryMember::File(FileMetadata {
+                    digest: TrackedFileDigest::new(digest, digest_config.cas_digest_config()),
+                    is_executable: file.is_executable,
+                })),
+            )?;
+        }
+
+        for dir in &result.output_directories {
+            let tree_digest = FileDigest::from_grpc(
+                dir.tree_digest
+                    .as_ref()
+                    .context("Output directory without tree digest")?,
+                digest_config,
+            )?;
+            let tree = RE::Tree::decode(self.cache.get_blob(&tree_digest).await?)
+                .context("Decoding the tree of an output directory")?;
+            builder.insert(
+                ForwardRelativePath::new(&dir.path)?,
+                DirectoryEntry::Dir(re_tree_to_directory(&tree, &now, digest_config)?),
+            )?;
+        }
+
+        let mut outputs = Vec::new();
+        for (requested, (path, _)) in command.request.outputs().zip(paths.output_paths()) {
+            let value = extract_artifact_value(&builder, path, digest_config)?
+                .ok_or_else(|| HttpCacheError::OutputMismatch(path.to_string()))?;
+            outputs.push((requested.cloned(), path.to_owned(), value));
+        }
+        Ok(outputs)
+    }
+
+    /// Downloads the outputs of a cached result, and returns them once checked against it.
+    async fn restore(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        result: &RE::ActionResult,
+        cancellations: &CancellationContext<'_>,
+    ) -> anyhow::Result<(IndexMap<CommandExecutionOutput, ArtifactValue>, HashingInfo)> {
+        let expected = self.expected_outputs(command, result).await?;
+
+        create_output_dirs(
+            &self.artifact_fs,
+            command.request,
+            self.materializer.dupe(),
+            self.blocking_executor.dupe(),
+            cancellations,
+        )
+        .await?;
+
+        let mut files = Vec::new();
+        let mut dirs = Vec::new();
+        let mut symlinks = Vec::new();
+        for (_, path, value) in &expected {
+            let output_path = self.artifact_fs.fs().resolve(path);
+            let mut add =
+                |path: AbsNormPathBuf, entry: DirectoryEntry<(), &ActionDirectoryMember>| {
+                    match entry {
+                        DirectoryEntry::Dir(_) => dirs.push(path),
+                        DirectoryEntry::Leaf(ActionDirectoryMember::File(f)) => {
+                            files.push((path, f.digest.data().dupe(), f.is_executable))
+                        }
+                        DirectoryEntry::Leaf(member) => {
+                            symlinks.push((path, member_symlink_target(member)?))
+                        }
+                    }
+                    anyhow::Ok(())
+                };
+            add(output_path.clone(), value.entry().as_ref().map_dir(|_| ()))?;
+            let mut walk = unordered_entry_walk(value.entry().as_ref());
+            while let Some((entry_path, entry)) = walk.next() {
+                add(output_path.join(entry_path.get()), entry.map_dir(|_| ()))?;
+            }
+        }
+
+        self.blocking_executor
+            .execute_io_inline(|| {
+                for dir in &dirs {
+                    fs_util::create_dir_all(dir)?;
+                }
+                for (path, target) in &symlinks {
+                    if let Some(parent) = path.parent() {
+                        fs_util::create_dir_all(parent)?;
+                    }
+                    fs_util::symlink(target, path)?;
+                }
+                Ok(())
+            })
+            .await?;
+
+        stream::iter(files)
+            .map(|(path, digest, is_executable)| async move {
+                let body = self.cache.get_blob(&digest).await?;
+                self.blocking_executor
+                    .execute_io_inline(|| {
+                        if let Some(parent) = path.parent() {
+                            fs_util::create_dir_all(parent)?;
+                        }
+                        fs_util::write(&path, &body)?;
+                        set_executable(&path, is_executable)
+                    })
+                    .await
+            })
+            .buffer_unordered(HTTP_CACHE_CONCURRENCY)
+            .try_collect::<Vec<()>>()
+            .await?;
+
+        let (outputs, hashing_info) = calculate_and_declare_output_values(
+            &self.artifact_fs,
+            self.materializer.as_ref(),
+            self.blocking_executor.as_ref(),
+            command.request,
+            command.digest_config,
+        )
+        .await?;
+
+        for (output, path, value) in &expected {
+            if outputs.get(output).map(|v| v.entry()) != Some(value.entry()) {
+                return Err(HttpCacheError::OutputMismatch(path.to_string()).into());
+            }
+        }
+
+        Ok((outputs, hashing_info))
+    }
+
+    async fn std_stream(
+        &self,
+        raw: &[u8],
+        digest: Option<&RE::Digest>,
+        digest_config: DigestConfig,
+    ) -> anyhow::Result<Vec<u8>> {
+        match digest {
+            Some(digest) if raw.is_empty() => Ok(self
+                .cache
+                .get_blob(&FileDigest::from_grpc(digest, digest_config)?)
+                .await?
+                .to_vec()),
+            _ => Ok(raw.to_vec()),
+        }
+    }
+}
+
+#[async_trait]
+impl PreparedCommandOptionalExecutor for HttpCacheChecker {
+    async fn maybe_execute(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        manager: CommandExecutionManager,
+        cancellations: &CancellationContext,
+    ) -> ControlFlow<CommandExecutionResult, CommandExecutionManager> {
+        let action_digest = command.prepared_action.digest();
+        if !command.request.outputs_cleanup() {
+            return self
+                .next
+                .maybe_execute(command, manager, cancellations)
+                .await;
+        }
+
+        let start_time = SystemTime::now();
+        let start = Instant::now();
+        let result = match self.cache.lookup(&action_digest).await {
+            Ok(Some(result)) => result,
+            Ok(None) => {
+                return self
+                    .next
+                    .maybe_execute(command, manager, cancellations)
+                    .await;
+            }
+            Err(e) => {
+                tracing::warn!("Error reading the HTTP cache: {:#}", e);
+                return self
+                    .next
+                    .maybe_execute(command, manager, cancellations)
+                    .await;
+            }
+        };
+
+        // As for the local action cache, nothing else writes the outputs before we return, so we
+        // only claim once they are restored, and can fall back to executing the action if that
+        // fails.
+        let restored = async {
+            let (outputs, hashing_info) = self.restore(command, &result, cancellations).await?;
+            let stdout = self
+                .std_stream(
+                    &result.stdout_raw,
+                    result.stdout_digest.as_ref(),
+                    command.digest_config,
+                )
+                .await?;
+            let stderr = self
+                .std_stream(
+                    &result.stderr_raw,
+                    result.stderr_digest.as_ref(),
+                    command.digest_config,
+                )
+                .await?;
+            anyhow::Ok((outputs, hashing_info, stdout, stderr))
+        };
+        let (outputs, hashing_info, stdout, stderr) = match restored.boxed().await {
+            Ok(res) => res,
+            Err(e) => {
+                tracing::warn!(
+                    "Discarding HTTP cache entry for `{}`: {:#}",
+                    action_digest,
+                    e
+                );
+                return self
+                    .next
+                    .maybe_execute(command, manager, cancellations)
+                    .await;
+            }
+        };
+
+        let execution_kind = CommandExecutionKind::HttpCache {
+            digest: action_digest,
+            command: command.request.all_args_vec(),
+            env: command.request.env().clone(),
+        };
+        let manager = manager
+            .with_execution_kind(execution_kind.clone())
+            .claim()
+            .await;
+        let wall_time = start.elapsed();
+        ControlFlow::Break(manager.success(
+            execution_kind,
+            outputs,
+            CommandStdStreams::Local { stdout, stderr },
+            CommandExecutionMetadata {
+                wall_time,
+                execution_time: Duration::ZERO,
+                start_time,
+                execution_stats: None,
+                input_materialization_duration: Duration::ZERO,
+                hashing_duration: hashing_info.hashing_duration,
+                hashed_artifacts_count: hashing_info.hashed_artifacts_count,
+                queue_duration: None,
+            },
+        ))
+    }
+}
+
+/// Uploads the outputs of the commands `inner` executes locally to the HTTP cache, in the
+/// background, if the executor config allows cache uploads.
+pub struct HttpCacheWriter {
+    pub cache: Arc<HttpCache>,
+    pub artifact_fs: ArtifactFs,
+    /// Outputs larger than this are not uploaded.
+    pub max_bytes: Option<u64>,
+    pub inner: Arc<dyn PreparedCommandExecutor>,
+}
+
+impl HttpCacheWriter {
+    fn should_upload(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        result: &CommandExecutionResult,
+    ) -> bool {
+        result.was_locally_executed()
+            && command.request.outputs_cleanup()
+            && command.request.allow_cache_upload()
+            && !command.request.remote_cache_read_only()
+            && result
+                .outputs
+                .keys()
+                .all(|o| matches!(o, CommandExecutionOutput::BuildArtifact { .. }))
+            && within_max_bytes(result.calc_output_size_bytes(), self.max_bytes)
+    }
+}
+
+fn within_max_bytes(output_bytes: u64, max_bytes: Option<u64>) -> bool {
+    max_bytes.map_or(true, |max_bytes| output_bytes <= max_bytes)
+}
+
+#[async_trait]
+impl PreparedCommandExecutor for HttpCacheWriter {
+    async fn exec_cmd(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        manager: CommandExecutionManager,
+        cancellations: &CancellationContext,
+    ) -> CommandExecutionResult {
+        let result = self.inner.exec_cmd(command, manager, cancellations).await;
+
+        if let (true, CommandStdStreams::Local { stdout, stderr }) = (
+            self.should_upload(command, &result),
+            &result.report.std_streams,
+        ) {
+            let action_digest = command.prepared_action.digest();
+            let outputs = result
+                .resolve_outputs(&self.artifact_fs)
+                .map(|(output, value)| (output.into_path(), value.dupe()))
+                .collect();
+            let cache = self.cache.dupe();
+            let artifact_fs = self.artifact_fs.clone();
+            let (stdout, stderr) = (stdout.clone(), stderr.clone());
+            let digest_config = command.digest_config;
+            // The action doesn't wait for the upload, which is only an optimization for later
+            // builds.
+            tokio::spawn(async move {
+                let stored = cache
+                    .store(
+                        &action_digest,
+                        outputs,
+                        stdout,
+                        stderr,
+                        &artifact_fs,
+                        digest_config,
+                    )
+                    .await;
+                if let Err(e) = stored {
+                    tracing::warn!(
+                        "Error storing `{}` in the HTTP cache: {:#}",
+                        action_digest,
+                        e
+                    );
+                }
+            });
+        }
+
+        result
+    }
+
+    fn is_local_execution_possible(&self, executor_preference: ExecutorPreference) -> bool {
+        self.inner.is_local_execution_possible(executor_preference)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::CellResolver;
+    use buck2_core::fs::buck_out_path::BuckOutPathResolver;
+    use buck2_core::fs::project::ProjectRootTemp;
+    use buck2_core::fs::project_rel_path::ProjectRelativePath;
+    use buck2_http::HttpClientBuilder;
+    use httptest::matchers::*;
+    use httptest::responders;
+    use httptest::Expectation;
+
+    use super::*;
+
+    fn artifact_fs(project: &ProjectRootTemp) -> ArtifactFs {
+        ArtifactFs::new(
+            CellResolver::testing_with_name_and_path(
+                CellName::testing_new("cell"),
+                CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("cell_path".into())),
+            ),
+            BuckOutPathResolver::new(ProjectRelativePathBuf::unchecked_new("buck_out/v2".into())),
+            project.path().dupe(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_store() -> anyhow::Result<()> {
+        let project = ProjectRootTemp::new()?;
+        let artifact_fs = artifact_fs(&project);
+        let digest_config = DigestConfig::testing_default();
+        let action_digest = ActionDigest::new_sha1([1; 20], 1);
+
+        let output = ProjectRelativePath::unchecked_new("out/file");
+        project.path().write_file(output, "contents", true)?;
+        let file_digest = FileDigest::from_content(b"contents", digest_config.cas_digest_config());
+        let stdout_digest = FileDigest::from_content(b"out", digest_config.cas_digest_config());
+        let value = ArtifactValue::file(FileMetadata {
+            digest: TrackedFileDigest::new(file_digest.dupe(), digest_config.cas_digest_config()),
+            is_executable: true,
+        });
+        let result = RE::ActionResult {
+            output_files: vec![RE::OutputFile {
+                path: "out/file".to_owned(),
+                digest: Some(file_digest.to_grpc()),
+                is_executable: true,
+                ..Default::default()
+            }],
+            stdout_digest: Some(stdout_digest.to_grpc()),
+            ..Default::default()
+        };
+
+        // The action result is only written once the blobs are.
+        let server = httptest::Server::run();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("PUT", format!("/cas/{}", file_digest.raw_digest())),
+                request::body("contents"),
+            ])
+            .respond_with(responders::status_code(200)),
+        );
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("PUT", format!("/cas/{}", stdout_digest.raw_digest())),
+                request::body("out"),
+            ])
+            .respond_with(responders::status_code(200)),
+        );
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("PUT", format!("/ac/{}", action_digest.raw_digest())),
+                request::body(result.encode_to_vec()),
+            ])
+            .respond_with(responders::status_code(200)),
+        );
+
+        let cache = HttpCache::new(
+            HttpClientBuilder::https_with_system_roots()?.build(),
+            &server.url_str("/"),
+            HttpCacheMode::ReadWrite,
+            None,
+        );
+        cache
+            .store(
+                &action_digest,
+                vec![(output.to_buf(), value)],
+                b"out".to_vec(),
+                Vec::new(),
+                &artifact_fs,
+                digest_config,
+            )
+            .await?;
+
+        // Outputs which changed since they were hashed are not uploaded.
+        project
+            .path()
+            .write_file(output, "modified contents", true)?;
+        let value = ArtifactValue::file(FileMetadata {
+            digest: TrackedFileDigest::new(file_digest, digest_config.cas_digest_config()),
+            is_executable: true,
+        });
+        assert!(cache
+            .store(
+                &action_digest,
+                vec![(output.to_buf(), value)],
+                Vec::new(),
+                Vec::new(),
+                &artifact_fs,
+                digest_config,
+            )
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lookup() -> anyhow::Result<()> {
+        let server = httptest::Server::run();
+        let failed = RE::ActionResult {
+            exit_code: 1,
+            ..Default::default()
+        };
+        server.expect(
+            Expectation::matching(request::method_path(
+                "GET",
+                format!("/ac/{}", ActionDigest::new_sha1([1; 20], 1).raw_digest()),
+            ))
+            .respond_with(responders::status_code(200).body(failed.encode_to_vec())),
+        );
+        server.expect(
+            Expectation::matching(request::method_path(
+                "GET",
+                format!("/ac/{}", ActionDigest::new_sha1([2; 20], 1).raw_digest()),
+            ))
+            .respond_with(responders::status_code(404)),
+        );
+
+        let cache = HttpCache::new(
+            HttpClientBuilder::https_with_system_roots()?.build(),
+            &server.url_str("/"),
+            HttpCacheMode::ReadOnly,
+            None,
+        );
+        // Failed actions are not served from the cache.
+        assert!(cache
+            .lookup(&ActionDigest::new_sha1([1; 20], 1))
+            .await?
+            .is_none());
+        assert!(cache
+            .lookup(&ActionDigest::new_sha1([2; 20], 1))
+            .await?
+            .is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_within_max_bytes() {
+        assert!(within_max_bytes(10, None));
+        assert!(within_max_bytes(10, Some(10)));
+        assert!(!within_max_bytes(11, Some(10)));
+    }
+
+    #[test]
+    fn test_mode_from_str() {
+        assert_eq!(
+            HttpCacheMode::from_str("read_only").unwrap(),
+            HttpCacheMode::ReadOnly
+        );
+        assert_eq!(
+            HttpCacheMode::from_str("read_write").unwrap(),
+            HttpCacheMode::ReadWrite
+        );
+        assert!(HttpCacheMode::from_str("write_only").is_err());
+    }
+}
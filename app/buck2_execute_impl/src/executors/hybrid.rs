@@ -7,9 +7,11 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
 use std::sync::atomic::AtomicI64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::sync::Mutex;
 
 use anyhow::Context;
 use async_trait::async_trait;
@@ -18,10 +20,12 @@ use buck2_common::liveliness_observer::LivelinessGuard;
 use buck2_common::liveliness_observer::LivelinessObserver;
 use buck2_common::liveliness_observer::LivelinessObserverExt;
 use buck2_core::execution_types::executor_config::HybridExecutionLevel;
+use buck2_core::soft_error;
 use buck2_events::dispatch::EventDispatcher;
 use buck2_execute::execute::claim::Claim;
 use buck2_execute::execute::claim::ClaimManager;
 use buck2_execute::execute::claim::MutexClaimManager;
+use buck2_execute::execute::kind::CommandExecutionKind;
 use buck2_execute::execute::manager::CommandExecutionManager;
 use buck2_execute::execute::manager::CommandExecutionManagerExt;
 use buck2_execute::execute::prepared::PreparedCommand;
@@ -135,7 +139,8 @@ where
         // works is as follows: when RE takes a claim, we cancel local commands. This means that we
         // can truly race RE and local: if RE finishes even after local started, it'll cancel the
         // local execution, we'll get back here with a ClaimCancelled from local execution, cancel
-        // local's claim, and then resume RE.
+        // local's claim, and then resume RE. Conversely, if local wins, the RE future is dropped,
+        // which drops its `Execute` stream and cancels the operation on the RE server.
         let (local_execution_liveliness_observer, local_execution_liveliness_guard) =
             LivelinessGuard::create();
 
@@ -190,8 +195,13 @@ where
             fallback_on_failure,
         );
 
+        let action_key = command.target.re_action_key();
+
         if executor_preference.requires_local()
             || self.is_action_too_large_for_remote(command.request.paths())
+            || (!executor_preference.requires_remote()
+                && (self.fallback_tracker.is_poisoned(&action_key)
+                    || self.remote.re_client.is_circuit_open()))
         {
             return local_result.await;
         };
@@ -341,6 +351,8 @@ where
             first_res
         };
 
+        self.fallback_tracker.record(&action_key, &res);
+
         res.eligible_for_full_hybrid = !fallback_only;
         res
     }
@@ -540,12 +552,84 @@ struct JobPriority(u8);
 
 pub struct FallbackTracker {
     count_fallbacks: AtomicI64,
+    /// How many times an action must fail on RE with an error (as opposed to a non-zero exit
+    /// code), having succeeded locally, before it's only executed locally. 0 disables this.
+    poisoned_action_threshold: u32,
+    actions: Mutex<HashMap<String, ActionFallbackState>>,
+}
+
+#[derive(Default)]
+struct ActionFallbackState {
+    remote_errors: u32,
+    local_success: bool,
+    poisoned: bool,
 }
 
 impl FallbackTracker {
-    pub fn new() -> Self {
+    pub fn new(poisoned_action_threshold: u32) -> Self {
         FallbackTracker {
             count_fallbacks: AtomicI64::new(0),
+            poisoned_action_threshold,
+            actions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether this action keeps failing on RE but not locally, likely because of the environment
+    /// of the RE workers, so it should be executed locally for the rest of the command.
+    fn is_poisoned(&self, action_key: &str) -> bool {
+        self.actions
+            .lock()
+            .unwrap()
+            .get(action_key)
+            .map_or(false, |state| state.poisoned)
+    }
+
+    fn record(&self, action_key: &str, res: &CommandExecutionResult) {
+        if self.poisoned_action_threshold == 0 {
+            return;
+        }
+
+        let remote_error = res.rejected_execution.as_ref().map_or(false, |rejected| {
+            matches!(
+                &rejected.status,
+                CommandExecutionStatus::Error {
+                    typ: CommandExecutionErrorType::Other,
+                    ..
+                }
+            )
+        });
+        let local_success = matches!(
+            &res.report.status,
+            CommandExecutionStatus::Success {
+                execution_kind: CommandExecutionKind::Local { .. },
+            }
+        );
+        if !remote_error && !local_success {
+            return;
+        }
+
+        let mut actions = self.actions.lock().unwrap();
+        let state = actions.entry(action_key.to_owned()).or_default();
+        if remote_error {
+            state.remote_errors += 1;
+        }
+        state.local_success |= local_success;
+
+        if !state.poisoned
+            && state.local_success
+            && state.remote_errors >= self.poisoned_action_threshold
+        {
+            state.poisoned = true;
+            let _unused = soft_error!(
+                "re_poisoned_action",
+                anyhow::anyhow!(
+                    "`{}` failed on RE {} times but succeeded locally, it will only be executed \
+                    locally for the rest of this command. The RE workers may be misconfigured.",
+                    action_key,
+                    state.remote_errors,
+                ),
+                quiet: false
+            );
         }
     }
 
@@ -592,3 +676,121 @@ impl FallbackTracker {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_execute::digest_config::DigestConfig;
+    use buck2_execute::execute::action_digest::ActionDigest;
+    use buck2_execute::execute::output::CommandStdStreams;
+    use buck2_execute::execute::result::CommandExecutionReport;
+
+    use super::*;
+
+    fn local() -> CommandExecutionKind {
+        CommandExecutionKind::Local {
+            digest: ActionDigest::empty(DigestConfig::testing_default().cas_digest_config()),
+            command: Default::default(),
+            env: Default::default(),
+        }
+    }
+
+    fn remote_error() -> CommandExecutionStatus {
+        CommandExecutionStatus::Error {
+            stage: "remote_call",
+            error: anyhow::anyhow!("Worker lost"),
+            execution_kind: None,
+            typ: CommandExecutionErrorType::Other,
+        }
+    }
+
+    fn report(status: CommandExecutionStatus) -> CommandExecutionReport {
+        CommandExecutionReport {
+            claim: None,
+            status,
+            timing: Default::default(),
+            std_streams: CommandStdStreams::Empty,
+            exit_code: None,
+        }
+    }
+
+    /// The result of a command which was rejected on RE with `rejected`, then ran locally.
+    fn result(
+        status: CommandExecutionStatus,
+        rejected: Option<CommandExecutionStatus>,
+    ) -> CommandExecutionResult {
+        CommandExecutionResult {
+            outputs: Default::default(),
+            report: report(status),
+            rejected_execution: rejected.map(report),
+            did_cache_upload: false,
+            did_dep_file_cache_upload: false,
+            dep_file_key: None,
+            eligible_for_full_hybrid: true,
+            dep_file_metadata: None,
+            action_result: None,
+        }
+    }
+
+    fn local_success_after_remote_error() -> CommandExecutionResult {
+        result(
+            CommandExecutionStatus::Success {
+                execution_kind: local(),
+            },
+            Some(remote_error()),
+        )
+    }
+
+    #[test]
+    fn test_poisoned_action() {
+        let tracker = FallbackTracker::new(2);
+        tracker.record("a", &local_success_after_remote_error());
+        assert!(!tracker.is_poisoned("a"));
+        tracker.record("a", &local_success_after_remote_error());
+        assert!(tracker.is_poisoned("a"));
+        assert!(!tracker.is_poisoned("b"));
+    }
+
+    #[test]
+    fn test_not_poisoned_without_local_success() {
+        let tracker = FallbackTracker::new(2);
+        for _ in 0..3 {
+            tracker.record(
+                "a",
+                &result(
+                    CommandExecutionStatus::Failure {
+                        execution_kind: local(),
+                    },
+                    Some(remote_error()),
+                ),
+            );
+        }
+        assert!(!tracker.is_poisoned("a"));
+    }
+
+    #[test]
+    fn test_not_poisoned_by_remote_failures() {
+        // A non-zero exit on RE is a problem with the action, not with the RE workers.
+        let tracker = FallbackTracker::new(1);
+        tracker.record(
+            "a",
+            &result(
+                CommandExecutionStatus::Success {
+                    execution_kind: local(),
+                },
+                Some(CommandExecutionStatus::Failure {
+                    execution_kind: local(),
+                }),
+            ),
+        );
+        assert!(!tracker.is_poisoned("a"));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let tracker = FallbackTracker::new(0);
+        for _ in 0..3 {
+            tracker.record("a", &local_success_after_remote_error());
+        }
+        assert!(!tracker.is_poisoned("a"));
+    }
+}
@@ -81,6 +81,7 @@ use host_sharing::host_sharing::HostSharingGuard;
 use host_sharing::HostSharingBroker;
 use host_sharing::HostSharingRequirements;
 use indexmap::IndexMap;
+use tokio::sync::Semaphore;
 use tracing::info;
 
 use crate::executors::worker::WorkerHandle;
@@ -108,6 +109,8 @@ pub struct LocalExecutor {
     knobs: ExecutorGlobalKnobs,
     #[allow(unused)]
     worker_pool: Option<Arc<WorkerPool>>,
+    /// `[buck2] action_dispatch_concurrency`, held while a command runs.
+    dispatch_budget: Option<Arc<Semaphore>>,
 }
 
 impl LocalExecutor {
@@ -120,6 +123,7 @@ impl LocalExecutor {
         forkserver: Option<ForkserverClient>,
         knobs: ExecutorGlobalKnobs,
         worker_pool: Option<Arc<WorkerPool>>,
+        dispatch_budget: Option<Arc<Semaphore>>,
     ) -> Self {
         Self {
             artifact_fs,
@@ -130,6 +134,7 @@ impl LocalExecutor {
             forkserver,
             knobs,
             worker_pool,
+            dispatch_budget,
         }
     }
 
@@ -216,6 +221,59 @@ impl LocalExecutor {
         cancellations: &CancellationContext<'_>,
         digest_config: DigestConfig,
         local_resource_holders: &[LocalResourceHolder],
+    ) -> CommandExecutionResult {
+        let result = self
+            .run_request(
+                action_digest,
+                request,
+                manager,
+                cancellation,
+                cancellations,
+                digest_config,
+                local_resource_holders,
+            )
+            .await;
+
+        // The scratch directory is private to this execution of the action, so don't leave it
+        // behind, whether the command ran or not. It is recreated empty before the next execution
+        // anyway.
+        self.remove_scratch(scratch_path(&self.artifact_fs, request))
+            .await;
+
+        result
+    }
+
+    async fn remove_scratch(&self, scratch_path: Option<ProjectRelativePathBuf>) {
+        let Some(scratch_path) = scratch_path else {
+            return;
+        };
+        if let Err(e) = self
+            .blocking_executor
+            .execute_io_inline(|| {
+                CleanOutputPaths::clean(
+                    std::iter::once(scratch_path.as_ref()),
+                    self.artifact_fs.fs(),
+                )
+            })
+            .await
+        {
+            tracing::warn!(
+                "Failed to remove scratch directory `{}`: {:#}",
+                scratch_path,
+                e
+            );
+        }
+    }
+
+    async fn run_request(
+        &self,
+        action_digest: &ActionDigest,
+        request: &CommandExecutionRequest,
+        manager: CommandExecutionManager,
+        cancellation: CancellationObserver,
+        cancellations: &CancellationContext<'_>,
+        digest_config: DigestConfig,
+        local_resource_holders: &[LocalResourceHolder],
     ) -> CommandExecutionResult {
         let args = &request.all_args_vec();
         if args.is_empty() {
@@ -406,6 +464,7 @@ impl LocalExecutor {
                             action_digest: action_digest.to_string(),
                             argv: args.to_vec(),
                             env,
+                            cache_hit: false,
                         }),
                     }
                     .into(),
@@ -431,9 +490,15 @@ impl LocalExecutor {
                         .into_iter()
                         .map(|(k, v)| (OsString::from(k), v.to_owned()))
                         .collect();
-                    Ok(worker
+                    let res = worker
                         .exec_cmd(request.args(), env, request.timeout())
-                        .await)
+                        .await;
+                    if let (GatherOutputStatus::SpawnFailed(_), Some(worker_spec), Some(pool)) =
+                        (&res.0, request.worker(), &self.worker_pool)
+                    {
+                        pool.evict_worker(worker_spec, &worker);
+                    }
+                    Ok(res)
                 } else {
                     self.exec(
                         &args[0],
@@ -481,10 +546,15 @@ impl LocalExecutor {
                 exit_code,
                 execution_stats,
             } => {
-                let (outputs, hashing_time) = match self
-                    .calculate_and_declare_output_values(request, digest_config)
-                    .boxed()
-                    .await
+                let (outputs, hashing_time) = match calculate_and_declare_output_values(
+                    &self.artifact_fs,
+                    self.materializer.as_ref(),
+                    self.blocking_executor.as_ref(),
+                    request,
+                    digest_config,
+                )
+                .boxed()
+                .await
                 {
                     Ok((output_values, hashing_time)) => (output_values, hashing_time),
                     Err(e) => {
@@ -549,69 +619,6 @@ impl LocalExecutor {
         }
     }
 
-    async fn calculate_and_declare_output_values(
-        &self,
-        request: &CommandExecutionRequest,
-        digest_config: DigestConfig,
-    ) -> anyhow::Result<(IndexMap<CommandExecutionOutput, ArtifactValue>, HashingInfo)> {
-        let mut builder = inputs_directory(request.inputs(), &self.artifact_fs)?;
-
-        // Read outputs from disk and add them to the builder
-        let mut entries = Vec::new();
-        let mut total_hashing_time = Duration::ZERO;
-        let mut total_hashed_outputs = 0;
-        for output in request.outputs() {
-            let path = output.resolve(&self.artifact_fs).into_path();
-            let abspath = self.root.join(&path);
-            let (entry, hashing_info) = build_entry_from_disk(
-                abspath,
-                FileDigestConfig::build(digest_config.cas_digest_config()),
-                self.blocking_executor.as_ref(),
-                self.artifact_fs.fs().root(),
-            )
-            .await
-            .with_context(|| format!("collecting output {:?}", path))?;
-            total_hashing_time += hashing_info.hashing_duration;
-            total_hashed_outputs += hashing_info.hashed_artifacts_count;
-            if let Some(entry) = entry {
-                insert_entry(&mut builder, &path, entry)?;
-                entries.push((output.cloned(), path));
-            }
-        }
-
-        let mut to_declare = vec![];
-        let mut mapped_outputs = IndexMap::with_capacity(entries.len());
-
-        for (output, path) in entries {
-            let value = extract_artifact_value(&builder, &path, digest_config)?;
-            if let Some(value) = value {
-                match output {
-                    CommandExecutionOutput::BuildArtifact { .. } => {
-                        to_declare.push((path, value.dupe()));
-                    }
-                    CommandExecutionOutput::TestPath { .. } => {
-                        // Don't declare those as we don't currently have any form of GC so this
-                        // would take up space for nothing, and most importantly, we will never
-                        // need them to be in materializer state for e.g. matching as nothing
-                        // should depend on them.
-                    }
-                }
-
-                mapped_outputs.insert(output, value);
-            }
-        }
-
-        self.materializer.declare_existing(to_declare).await?;
-
-        Ok((
-            mapped_outputs,
-            HashingInfo {
-                hashing_duration: total_hashing_time,
-                hashed_artifacts_count: total_hashed_outputs,
-            },
-        ))
-    }
-
     async fn acquire_worker_permit(
         &self,
         request: &CommandExecutionRequest,
@@ -762,12 +769,27 @@ impl PreparedCommandExecutor for LocalExecutor {
 
         let _worker_permit = self.acquire_worker_permit(request).await;
 
-        let _permit = executor_stage_async(
+        let (_permit, _dispatch_permit) = executor_stage_async(
             buck2_data::LocalStage {
                 stage: Some(buck2_data::LocalQueued {}.into()),
             },
-            self.host_sharing_broker
-                .acquire(request.host_sharing_requirements()),
+            async {
+                let permit = self
+                    .host_sharing_broker
+                    .acquire_with_memory(request.host_sharing_requirements(), request.memory_mb())
+                    .await;
+                let dispatch_permit = match &self.dispatch_budget {
+                    Some(budget) => Some(
+                        budget
+                            .dupe()
+                            .acquire_owned()
+                            .await
+                            .expect("Dispatch budget semaphore is never closed"),
+                    ),
+                    None => None,
+                };
+                (permit, dispatch_permit)
+            },
         )
         .await;
 
@@ -794,6 +816,72 @@ impl PreparedCommandExecutor for LocalExecutor {
     }
 }
 
+/// Hashes the outputs of `request` from disk, and declares them to the materializer.
+pub(crate) async fn calculate_and_declare_output_values(
+    artifact_fs: &ArtifactFs,
+    materializer: &dyn Materializer,
+    blocking_executor: &dyn BlockingExecutor,
+    request: &CommandExecutionRequest,
+    digest_config: DigestConfig,
+) -> anyhow::Result<(IndexMap<CommandExecutionOutput, ArtifactValue>, HashingInfo)> {
+    let mut builder = inputs_directory(request.inputs(), artifact_fs)?;
+
+    // Read outputs from disk and add them to the builder
+    let mut entries = Vec::new();
+    let mut total_hashing_time = Duration::ZERO;
+    let mut total_hashed_outputs = 0;
+    for output in request.outputs() {
+        let path = output.resolve(artifact_fs).into_path();
+        let abspath = artifact_fs.fs().resolve(&path);
+        let (entry, hashing_info) = build_entry_from_disk(
+            abspath,
+            FileDigestConfig::build(digest_config.cas_digest_config()),
+            blocking_executor,
+            artifact_fs.fs().root(),
+        )
+        .await
+        .with_context(|| format!("collecting output {:?}", path))?;
+        total_hashing_time += hashing_info.hashing_duration;
+        total_hashed_outputs += hashing_info.hashed_artifacts_count;
+        if let Some(entry) = entry {
+            insert_entry(&mut builder, &path, entry)?;
+            entries.push((output.cloned(), path));
+        }
+    }
+
+    let mut to_declare = vec![];
+    let mut mapped_outputs = IndexMap::with_capacity(entries.len());
+
+    for (output, path) in entries {
+        let value = extract_artifact_value(&builder, &path, digest_config)?;
+        if let Some(value) = value {
+            match output {
+                CommandExecutionOutput::BuildArtifact { .. } => {
+                    to_declare.push((path, value.dupe()));
+                }
+                CommandExecutionOutput::TestPath { .. } => {
+                    // Don't declare those as we don't currently have any form of GC so this
+                    // would take up space for nothing, and most importantly, we will never
+                    // need them to be in materializer state for e.g. matching as nothing
+                    // should depend on them.
+                }
+            }
+
+            mapped_outputs.insert(output, value);
+        }
+    }
+
+    materializer.declare_existing(to_declare).await?;
+
+    Ok((
+        mapped_outputs,
+        HashingInfo {
+            hashing_duration: total_hashing_time,
+            hashed_artifacts_count: total_hashed_outputs,
+        },
+    ))
+}
+
 /// Either a str or a OsStr, so that we can turn it back into a String without having to check for
 /// valid utf-8, while using the same struct.
 #[derive(Copy, Clone, Dupe, From)]
@@ -827,6 +915,19 @@ pub struct MaterializedInputPaths {
 ///
 /// This also discovers the scratch directory if any was passed (if multiple are passed, one of
 /// them is returned).
+/// The scratch directory of `request`, if it has one.
+fn scratch_path(
+    artifact_fs: &ArtifactFs,
+    request: &CommandExecutionRequest,
+) -> Option<ProjectRelativePathBuf> {
+    request.inputs().iter().find_map(|input| match input {
+        CommandExecutionInput::ScratchPath(path) => {
+            Some(artifact_fs.buck_out_path_resolver().resolve_scratch(path))
+        }
+        _ => None,
+    })
+}
+
 pub async fn materialize_inputs(
     artifact_fs: &ArtifactFs,
     materializer: &dyn Materializer,
@@ -1292,11 +1393,27 @@ mod tests {
             None,
             ExecutorGlobalKnobs::default(),
             None,
+            None,
         );
 
         Ok((executor, temp.path().root().to_buf(), temp))
     }
 
+    #[tokio::test]
+    async fn test_remove_scratch() -> anyhow::Result<()> {
+        let (executor, _root, temp) = test_executor()?;
+        let scratch = ProjectRelativePathBuf::unchecked_new("buck_out/v2/tmp/foo".into());
+        temp.write_file("buck_out/v2/tmp/foo/file", "contents");
+
+        executor.remove_scratch(Some(scratch.clone())).await;
+        assert!(!fs_util::try_exists(temp.path().resolve(&scratch))?);
+
+        // Nothing to remove.
+        executor.remove_scratch(None).await;
+        executor.remove_scratch(Some(scratch)).await;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_exec_cmd_environment() -> anyhow::Result<()> {
         let (executor, root, _tmpdir) = test_executor()?;
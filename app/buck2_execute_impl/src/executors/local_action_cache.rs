@@ -0,0 +1,1170 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A persistent cache of the outputs of locally executed actions, so that builds without a remote
+//! cache get cache hits after a `buck2 clean` or a daemon restart.
+//!
+//! The cache is a directory holding the contents of output files in `blobs`, named after their
+//! digest, and an entry per action digest in `actions` listing the outputs of the action. Entries
+//! are only written once all the blobs they reference are, and are treated as misses if any of
+//! those blobs are missing, so a cache shared by several daemons (or interrupted while being
+//! written or collected) is always usable.
+//!
+//! Entries are keyed by the action digest and the environment the command inherits from the
+//! daemon, which the action digest doesn't cover. Like for the remote action cache, only actions
+//! which allow cache uploads are stored, and `local_only` actions are never cached.
+//!
+//! Outputs downloaded from the remote action cache by `buck2 debug warm` are keyed by the action
+//! digest alone: like a remote cache hit, they are used whatever environment the command would
+//! inherit, when there is no entry for the command itself.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs::File;
+use std::ops::ControlFlow;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_action_metadata_proto::local_action_cache_node::Kind;
+use buck2_action_metadata_proto::LocalActionCacheEntry;
+use buck2_action_metadata_proto::LocalActionCacheNode;
+use buck2_action_metadata_proto::LocalActionCacheOutput;
+use buck2_common::cas_digest::RawDigest;
+use buck2_common::file_ops::FileDigest;
+use buck2_common::file_ops::FileMetadata;
+use buck2_common::file_ops::TrackedFileDigest;
+use buck2_core::directory::unordered_entry_walk;
+use buck2_core::directory::DirectoryEntry;
+use buck2_core::execution_types::executor_config::RemoteExecutorUseCase;
+use buck2_core::fs::artifact_path_resolver::ArtifactFs;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_error::internal_error;
+use buck2_execute::artifact_value::ArtifactValue;
+use buck2_execute::digest::CasDigestFromReExt;
+use buck2_execute::digest::CasDigestToReExt;
+use buck2_execute::digest_config::DigestConfig;
+use buck2_execute::directory::re_tree_to_directory;
+use buck2_execute::directory::ActionDirectoryMember;
+use buck2_execute::directory::INTERNER;
+use buck2_execute::entry::HashingInfo;
+use buck2_execute::execute::action_digest::ActionDigest;
+use buck2_execute::execute::blocking::BlockingExecutor;
+use buck2_execute::execute::environment_inheritance::EnvironmentInheritance;
+use buck2_execute::execute::kind::CommandExecutionKind;
+use buck2_execute::execute::manager::CommandExecutionManager;
+use buck2_execute::execute::output::CommandStdStreams;
+use buck2_execute::execute::prepared::PreparedCommand;
+use buck2_execute::execute::prepared::PreparedCommandExecutor;
+use buck2_execute::execute::prepared::PreparedCommandOptionalExecutor;
+use buck2_execute::execute::request::CommandExecutionOutput;
+use buck2_execute::execute::request::CommandExecutionRequest;
+use buck2_execute::execute::request::ExecutorPreference;
+use buck2_execute::execute::result::CommandExecutionMetadata;
+use buck2_execute::execute::result::CommandExecutionResult;
+use buck2_execute::materialize::materializer::Materializer;
+use buck2_execute::re::manager::ManagedRemoteExecutionClient;
+use buck2_futures::cancellation::CancellationContext;
+use chrono::Utc;
+use dupe::Dupe;
+use futures::FutureExt;
+use indexmap::IndexMap;
+use prost::Message;
+use remote_execution as RE;
+use remote_execution::NamedDigest;
+use remote_execution::NamedDigestWithPermissions;
+
+use crate::executors::local::calculate_and_declare_output_values;
+use crate::executors::local::create_output_dirs;
+
+const BLOBS_DIR: &str = "blobs";
+const ACTIONS_DIR: &str = "actions";
+const TMP_DIR: &str = "tmp";
+
+/// Temporary files older than this are left over from interrupted writes.
+const TMP_FILE_MAX_AGE: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, buck2_error::Error)]
+enum LocalActionCacheError {
+    #[error("Output `{0}` restored from the local action cache does not match its cache entry")]
+    OutputMismatch(String),
+    #[error("Symlink `{0}` has a target which is not valid UTF-8")]
+    NonUtf8SymlinkTarget(AbsNormPathBuf),
+}
+
+/// What warming the local action cache did for an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarmOutcome {
+    /// The action was already cached.
+    AlreadyCached,
+    /// The remote action cache has no successful result for the action.
+    NotFound,
+    /// The outputs of the action were downloaded, adding this many bytes of blobs.
+    Stored(u64),
+}
+
+/// Identifies an entry of the cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalActionCacheKey(String);
+
+impl LocalActionCacheKey {
+    /// The key of an action whose command sets the variables `action_env` and inherits the
+    /// daemon environment according to `env_inheritance`, like a locally executed command does.
+    pub fn new<'a>(
+        action_digest: &ActionDigest,
+        action_env: impl IntoIterator<Item = &'a str>,
+        env_inheritance: Option<&EnvironmentInheritance>,
+        digest_config: DigestConfig,
+    ) -> Self {
+        Self::with_env(
+            action_digest,
+            inherited_env(std::env::vars_os(), action_env, env_inheritance),
+            digest_config,
+        )
+    }
+
+    /// The key of an action whose outputs were downloaded from the remote action cache.
+    pub fn remote(action_digest: &ActionDigest) -> Self {
+        Self(format!(
+            "{}-remote",
+            digest_name(action_digest.raw_digest(), action_digest.size())
+        ))
+    }
+
+    /// The keys under which the outputs of a command are looked up, in order.
+    fn lookup_keys<'a>(
+        action_digest: &ActionDigest,
+        action_env: impl IntoIterator<Item = &'a str>,
+        env_inheritance: Option<&EnvironmentInheritance>,
+        digest_config: DigestConfig,
+    ) -> [Self; 2] {
+        [
+            Self::new(action_digest, action_env, env_inheritance, digest_config),
+            Self::remote(action_digest),
+        ]
+    }
+
+    fn with_env(
+        action_digest: &ActionDigest,
+        env: BTreeMap<OsString, OsString>,
+        digest_config: DigestConfig,
+    ) -> Self {
+        let mut env_bytes = Vec::new();
+        for (key, value) in &env {
+            env_bytes.extend_from_slice(key.as_encoded_bytes());
+            env_bytes.push(b'=');
+            env_bytes.extend_from_slice(value.as_encoded_bytes());
+            env_bytes.push(0);
+        }
+        let env_digest = FileDigest::from_content(&env_bytes, digest_config.cas_digest_config());
+        Self(format!(
+            "{}-{}",
+            digest_name(action_digest.raw_digest(), action_digest.size()),
+            env_digest.raw_digest()
+        ))
+    }
+}
+
+/// The variables a locally executed command inherits from an environment `vars`. Those the
+/// command sets itself, `action_env` and `PWD`, are left out: they are covered by the action
+/// digest.
+fn inherited_env<'a>(
+    vars: impl IntoIterator<Item = (OsString, OsString)>,
+    action_env: impl IntoIterator<Item = &'a str>,
+    env_inheritance: Option<&EnvironmentInheritance>,
+) -> BTreeMap<OsString, OsString> {
+    let mut env = BTreeMap::new();
+    if !env_inheritance.is_some_and(|e| e.clear()) {
+        env.extend(vars);
+    }
+    if let Some(env_inheritance) = env_inheritance {
+        for key in env_inheritance.exclusions() {
+            env.remove(OsString::from(key).as_os_str());
+        }
+        for (key, value) in env_inheritance.values() {
+            env.insert(key.into(), value.clone());
+        }
+    }
+    for key in action_env.into_iter().chain(["PWD"]) {
+        env.remove(OsString::from(key).as_os_str());
+    }
+    env
+}
+
+/// Whether the outputs of a command may be served from and stored in the cache.
+fn is_cacheable(request: &CommandExecutionRequest) -> bool {
+    // Actions which don't clean up their outputs may depend on their previous outputs, and
+    // actions which must run locally usually do so because they aren't hermetic.
+    request.outputs_cleanup()
+        && request.allow_cache_upload()
+        && !request.remote_cache_read_only()
+        && !request.executor_preference().requires_local()
+}
+
+pub struct LocalActionCache {
+    root: AbsNormPathBuf,
+    max_bytes: u64,
+    /// The size of the blobs, updated when storing and recomputed by garbage collection.
+    size: AtomicU64,
+    gc_running: AtomicBool,
+    tmp_counter: AtomicU64,
+}
+
+impl LocalActionCache {
+    /// Opens the cache in `root`, and collects garbage in the background if it's over
+    /// `max_bytes`.
+    pub fn new(root: AbsNormPathBuf, max_bytes: u64) -> Arc<Self> {
+        let cache = Arc::new(Self {
+            root,
+            max_bytes,
+            // Unknown until the first garbage collection.
+            size: AtomicU64::new(u64::MAX),
+            gc_running: AtomicBool::new(false),
+            tmp_counter: AtomicU64::new(0),
+        });
+        cache.maybe_gc();
+        cache
+    }
+
+    fn entry_path(&self, key: &LocalActionCacheKey) -> AbsNormPathBuf {
+        self.sharded_path(ACTIONS_DIR, &key.0)
+    }
+
+    fn blob_path(&self, name: &str) -> AbsNormPathBuf {
+        self.sharded_path(BLOBS_DIR, name)
+    }
+
+    fn sharded_path(&self, dir: &str, name: &str) -> AbsNormPathBuf {
+        let shard = name.get(..2).unwrap_or(name);
+        self.root
+            .join(ForwardRelativePath::unchecked_new(dir))
+            .join(ForwardRelativePath::unchecked_new(shard))
+            .join(ForwardRelativePath::unchecked_new(name))
+    }
+
+    fn tmp_path(&self) -> AbsNormPathBuf {
+        let name = format!(
+            "{}.{}",
+            std::process::id(),
+            self.tmp_counter.fetch_add(1, Ordering::Relaxed)
+        );
+        self.root
+            .join(ForwardRelativePath::unchecked_new(TMP_DIR))
+            .join(ForwardRelativePath::unchecked_new(&name))
+    }
+
+    /// Moves a complete temporary file to `path`.
+    fn publish(&self, tmp: &AbsNormPath, path: &AbsNormPath) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs_util::create_dir_all(parent)?;
+        }
+        fs_util::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Returns the entry of an action if all its blobs are present, and marks it as used.
+    fn lookup(&self, key: &LocalActionCacheKey) -> anyhow::Result<Option<LocalActionCacheEntry>> {
+        let path = self.entry_path(key);
+        let Some(data) = fs_util::read_if_exists(&path)? else {
+            return Ok(None);
+        };
+        let entry = match LocalActionCacheEntry::decode(data.as_slice()) {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::debug!(
+                    "Removing malformed local action cache entry `{}`: {}",
+                    path,
+                    e
+                );
+                fs_util::remove_file(&path)?;
+                return Ok(None);
+            }
+        };
+        for name in entry_blobs(&entry) {
+            if !fs_util::try_exists(self.blob_path(name))? {
+                fs_util::remove_file(&path)?;
+                return Ok(None);
+            }
+        }
+        File::options()
+            .write(true)
+            .open(path.as_path())
+            .and_then(|f| f.set_modified(SystemTime::now()))
+            .with_context(|| format!("Touching `{}`", path))?;
+        Ok(Some(entry))
+    }
+
+    /// Returns the first of `keys` with an entry, and that entry.
+    fn lookup_first(
+        &self,
+        keys: &[LocalActionCacheKey],
+    ) -> anyhow::Result<Option<(LocalActionCacheKey, LocalActionCacheEntry)>> {
+        for key in keys {
+            if let Some(entry) = self.lookup(key)? {
+                return Ok(Some((key.clone(), entry)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn remove(&self, key: &LocalActionCacheKey) -> anyhow::Result<()> {
+        fs_util::remove_all(self.entry_path(key))?;
+        Ok(())
+    }
+
+    /// Writes the outputs of an entry, whose paths must not exist, to disk.
+    fn restore(&self, entry: &LocalActionCacheEntry, fs: &ProjectRoot) -> anyhow::Result<()> {
+        for output in &entry.outputs {
+            let output_path = fs.resolve(ProjectRelativePath::new(&output.path)?);
+            for node in &output.nodes {
+                let path = if node.path.is_empty() {
+                    output_path.clone()
+                } else {
+                    output_path.join(ForwardRelativePath::new(&node.path)?)
+                };
+                if let Some(parent) = path.parent() {
+                    fs_util::create_dir_all(parent)?;
+                }
+                match node.kind() {
+                    Kind::Directory => fs_util::create_dir_all(&path)?,
+                    Kind::Symlink => fs_util::symlink(&node.symlink_target, &path)?,
+                    Kind::File => {
+                        fs_util::copy(self.blob_path(&node.digest), &path)?;
+                        set_executable(&path, node.is_executable)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores the outputs of an action, which are on disk, and returns the size of the blobs that
+    /// were added. Files are copied rather than hard linked, since the outputs can be modified in
+    /// place afterwards.
+    fn store(
+        &self,
+        key: &LocalActionCacheKey,
+        outputs: &[(ProjectRelativePathBuf, ArtifactValue)],
+        stdout: &[u8],
+        stderr: &[u8],
+        fs: &ProjectRoot,
+    ) -> anyhow::Result<u64> {
+        let mut entry = LocalActionCacheEntry {
+            outputs: Vec::with_capacity(outputs.len()),
+            stdout: stdout.to_vec(),
+            stderr: stderr.to_vec(),
+        };
+        let mut added_bytes = 0;
+        for (path, value) in outputs {
+            let output_path = fs.resolve(path);
+            let nodes = output_nodes(value, |p, _| read_symlink_target(output_path.join(p)))?;
+            for node in &nodes {
+                if node.kind() != Kind::File {
+                    continue;
+                }
+                let blob = self.blob_path(&node.digest);
+                if fs_util::try_exists(&blob)? {
+                    continue;
+                }
+                let file = if node.path.is_empty() {
+                    output_path.clone()
+                } else {
+                    output_path.join(ForwardRelativePath::new(&node.path)?)
+                };
+                let tmp = self.tmp_path();
+                fs_util::create_dir_all(tmp.parent().context("Temporary path has no parent")?)?;
+                fs_util::copy(&file, &tmp)?;
+                self.publish(&tmp, &blob)?;
+                added_bytes += fs_util::metadata(&blob)?.len();
+            }
+            entry.outputs.push(LocalActionCacheOutput {
+                path: path.to_string(),
+                nodes,
+            });
+        }
+
+        self.write_entry(key, &entry, added_bytes)?;
+        Ok(added_bytes)
+    }
+
+    /// Writes the entry of an action once the blobs it references, `added_bytes` of which are
+    /// new, are stored.
+    fn write_entry(
+        &self,
+        key: &LocalActionCacheKey,
+        entry: &LocalActionCacheEntry,
+        added_bytes: u64,
+    ) -> anyhow::Result<()> {
+        let tmp = self.tmp_path();
+        fs_util::create_dir_all(tmp.parent().context("Temporary path has no parent")?)?;
+        fs_util::write(&tmp, entry.encode_to_vec())?;
+        self.publish(&tmp, &self.entry_path(key))?;
+
+        // Saturating, since the size is `u64::MAX` until the first garbage collection.
+        let _ = self
+            .size
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |size| {
+                Some(size.saturating_add(added_bytes))
+            });
+        Ok(())
+    }
+
+    /// Downloads the outputs of an action from the remote action cache and stores them, so that
+    /// the next build running the action locally gets a cache hit instead.
+    pub async fn warm(
+        self: &Arc<Self>,
+        re_client: &ManagedRemoteExecutionClient,
+        blocking_executor: &dyn BlockingExecutor,
+        action_digest: ActionDigest,
+        use_case: RemoteExecutorUseCase,
+        digest_config: DigestConfig,
+    ) -> anyhow::Result<WarmOutcome> {
+        let key = LocalActionCacheKey::remote(&action_digest);
+        let cache = self.dupe();
+        let entry = blocking_executor
+            .execute_io_inline({
+                let key = key.clone();
+                move || cache.lookup(&key)
+            })
+            .await?;
+        if entry.is_some() {
+            return Ok(WarmOutcome::AlreadyCached);
+        }
+
+        let Some(response) = re_client
+            .action_cache(action_digest.dupe(), use_case)
+            .await?
+        else {
+            return Ok(WarmOutcome::NotFound);
+        };
+        let result = response.action_result;
+        if result.exit_code != 0 {
+            return Ok(WarmOutcome::NotFound);
+        }
+
+        let mut values = Vec::new();
+        for file in &result.output_files {
+            let digest = FileDigest::from_re(&file.digest.digest, digest_config)?;
+            let value = ArtifactValue::file(FileMetadata {
+                digest: TrackedFileDigest::new(digest, digest_config.cas_digest_config()),
+                is_executable: file.executable,
+            });
+            values.push((file.name.clone(), value));
+        }
+        let trees = re_client
+            .download_typed_blobs::<RE::Tree>(
+                None,
+                result
+                    .output_directories
+                    .iter()
+                    .map(|d| d.tree_digest.clone())
+                    .collect(),
+                use_case,
+            )
+            .await?;
+        for (dir, tree) in result.output_directories.iter().zip(trees) {
+            let dir_value = re_tree_to_directory(&tree, &Utc::now(), digest_config)?
+                .fingerprint(digest_config.as_directory_serializer())
+                .shared(&*INTERNER);
+            values.push((dir.path.clone(), ArtifactValue::dir(dir_value)));
+        }
+
+        let mut entry = LocalActionCacheEntry::default();
+        let mut blobs = HashMap::new();
+        for (path, value) in &values {
+            let mut walk = unordered_entry_walk(value.entry().as_ref());
+            while let Some((_, entry)) = walk.next() {
+                if let DirectoryEntry::Leaf(ActionDirectoryMember::File(f)) = entry {
+                    blobs.insert(
+                        digest_name(f.digest.raw_digest(), f.digest.size()),
+                        f.digest.to_re(),
+                    );
+                }
+            }
+            entry.outputs.push(LocalActionCacheOutput {
+                path: path.clone(),
+                nodes: output_nodes(value, |_, member| member_symlink_target(member))?,
+            });
+        }
+        for symlink in &result.output_symlinks {
+            entry.outputs.push(LocalActionCacheOutput {
+                path: symlink.name.clone(),
+                nodes: vec![LocalActionCacheNode {
+                    kind: Kind::Symlink.into(),
+                    symlink_target: symlink.target.clone(),
+                    ..Default::default()
+                }],
+            });
+        }
+        entry.stdout = match (result.stdout_raw, result.stdout_digest) {
+            (Some(raw), _) => raw,
+            (None, Some(digest)) if digest.size_in_bytes > 0 => {
+                re_client.download_blob(&digest, use_case).await?
+            }
+            (None, _) => Vec::new(),
+        };
+        entry.stderr = match (result.stderr_raw, result.stderr_digest) {
+            (Some(raw), _) => raw,
+            (None, Some(digest)) if digest.size_in_bytes > 0 => {
+                re_client.download_blob(&digest, use_case).await?
+            }
+            (None, _) => Vec::new(),
+        };
+
+        // Download the blobs we don't have to temporary files, and only publish them once they
+        // are complete.
+        let cache = self.dupe();
+        let downloads = blocking_executor
+            .execute_io_inline(move || {
+                let mut downloads = Vec::new();
+                for (name, digest) in blobs {
+                    if !fs_util::try_exists(cache.blob_path(&name))? {
+                        downloads.push((name, digest, cache.tmp_path()));
+                    }
+                }
+                if let Some((_, _, tmp)) = downloads.first() {
+                    fs_util::create_dir_all(tmp.parent().context("Temporary path has no parent")?)?;
+                }
+                Ok(downloads)
+            })
+            .await?;
+        let files = downloads
+            .iter()
+            .map(|(_, digest, tmp)| {
+                Ok(NamedDigestWithPermissions {
+                    named_digest: NamedDigest {
+                        name: tmp.as_maybe_relativized_str()?.to_owned(),
+                        digest: digest.clone(),
+                        ..Default::default()
+                    },
+                    is_executable: false,
+                    ..Default::default()
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        re_client.materialize_files(files, use_case).await?;
+
+        let cache = self.dupe();
+        let added_bytes = blocking_executor
+            .execute_io_inline(move || {
+                let mut added_bytes = 0;
+                for (name, _, tmp) in downloads {
+                    let blob = cache.blob_path(&name);
+                    cache.publish(&tmp, &blob)?;
+                    added_bytes += fs_util::metadata(&blob)?.len();
+                }
+                cache.write_entry(&key, &entry, added_bytes)?;
+                Ok(added_bytes)
+            })
+            .await?;
+        self.maybe_gc();
+        Ok(WarmOutcome::Stored(added_bytes))
+    }
+
+    /// Collects garbage in the background if the cache is over its size limit.
+    fn maybe_gc(self: &Arc<Self>) {
+        if self.size.load(Ordering::Relaxed) <= self.max_bytes
+            || self.gc_running.swap(true, Ordering::AcqRel)
+        {
+            return;
+        }
+        let cache = self.dupe();
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = cache.gc() {
+                tracing::warn!("Error collecting local action cache garbage: {:#}", e);
+            }
+            cache.gc_running.store(false, Ordering::Release);
+        });
+    }
+
+    /// Removes the least recently used entries until the blobs the others reference fit in 80%
+    /// of the size limit, then removes the blobs which are no longer referenced.
+    fn gc(&self) -> anyhow::Result<()> {
+        let target_bytes = self.max_bytes / 5 * 4;
+
+        let mut blob_sizes = HashMap::new();
+        for (path, metadata) in
+            list_sharded_files(&self.root.join(ForwardRelativePath::new(BLOBS_DIR)?))?
+        {
+            if let Some(name) = path.as_path().file_name().and_then(|n| n.to_str()) {
+                blob_sizes.insert(name.to_owned(), (path.clone(), metadata.len()));
+            }
+        }
+
+        let mut entries =
+            list_sharded_files(&self.root.join(ForwardRelativePath::new(ACTIONS_DIR)?))?;
+        // Most recently used first.
+        entries.sort_by_key(|(_, metadata)| {
+            std::cmp::Reverse(metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH))
+        });
+
+        let mut kept_blobs = HashSet::new();
+        let mut kept_bytes = 0;
+        let mut full = false;
+        for (path, _) in entries {
+            let keep = !full
+                && match fs_util::read(&path)
+                    .ok()
+                    .and_then(|data| LocalActionCacheEntry::decode(data.as_slice()).ok())
+                {
+                    Some(entry) => {
+                        let new_blobs: HashSet<_> = entry_blobs(&entry)
+                            .filter(|name| !kept_blobs.contains(*name))
+                            .collect();
+                        let new_bytes = new_blobs
+                            .iter()
+                            .map(|name| blob_sizes.get(*name).map(|(_, size)| *size))
+                            .sum::<Option<u64>>();
+                        match new_bytes {
+                            // An entry with missing blobs would be a miss anyway.
+                            None => false,
+                            Some(new_bytes) if kept_bytes + new_bytes > target_bytes => {
+                                full = true;
+                                false
+                            }
+                            Some(new_bytes) => {
+                                kept_bytes += new_bytes;
+                                kept_blobs.extend(new_blobs.into_iter().map(|n| n.to_owned()));
+                                true
+                            }
+                        }
+                    }
+                    None => false,
+                };
+            if !keep {
+                fs_util::remove_all(&path)?;
+            }
+        }
+
+        for (name, (path, _)) in &blob_sizes {
+            if !kept_blobs.contains(name) {
+                fs_util::remove_all(path)?;
+            }
+        }
+
+        let tmp_dir = self.root.join(ForwardRelativePath::new(TMP_DIR)?);
+        if let Some(dir) = fs_util::read_dir_if_exists(&tmp_dir)? {
+            for file in dir {
+                let file = file?;
+                let age = file
+                    .metadata()?
+                    .modified()?
+                    .elapsed()
+                    .unwrap_or(Duration::ZERO);
+                if age > TMP_FILE_MAX_AGE {
+                    fs_util::remove_all(file.path())?;
+                }
+            }
+        }
+
+        self.size.store(kept_bytes, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// The names of the blobs holding the files of an entry.
+fn entry_blobs(entry: &LocalActionCacheEntry) -> impl Iterator<Item = &str> {
+    entry
+        .outputs
+        .iter()
+        .flat_map(|o| o.nodes.iter())
+        .filter(|n| n.kind() == Kind::File)
+        .map(|n| n.digest.as_str())
+}
+
+fn digest_name(digest: &RawDigest, size: u64) -> String {
+    format!("{}_{}", digest, size)
+}
+
+/// The nodes of an output, given the targets of its symlinks.
+fn output_nodes(
+    value: &ArtifactValue,
+    symlink_target: impl Fn(&ForwardRelativePath, &ActionDirectoryMember) -> anyhow::Result<String>,
+) -> anyhow::Result<Vec<LocalActionCacheNode>> {
+    let mut nodes = Vec::new();
+    if let DirectoryEntry::Dir(_) = value.entry() {
+        nodes.push(LocalActionCacheNode {
+            kind: Kind::Directory.into(),
+            ..Default::default()
+        });
+    }
+    let mut walk = unordered_entry_walk(value.entry().as_ref());
+    while let Some((entry_path, entry)) = walk.next() {
+        let entry_path = entry_path.get();
+        let node = match entry {
+            DirectoryEntry::Dir(_) => LocalActionCacheNode {
+                kind: Kind::Directory.into(),
+                ..Default::default()
+            },
+            DirectoryEntry::Leaf(ActionDirectoryMember::File(f)) => LocalActionCacheNode {
+                kind: Kind::File.into(),
+                digest: digest_name(f.digest.raw_digest(), f.digest.size()),
+                is_executable: f.is_executable,
+                ..Default::default()
+            },
+            DirectoryEntry::Leaf(
+                member @ (ActionDirectoryMember::Symlink(_)
+                | ActionDirectoryMember::ExternalSymlink(_)),
+            ) => LocalActionCacheNode {
+                kind: Kind::Symlink.into(),
+                symlink_target: symlink_target(&entry_path, member)?,
+                ..Default::default()
+            },
+        };
+        nodes.push(LocalActionCacheNode {
+            path: entry_path.as_str().to_owned(),
+            ..node
+        });
+    }
+    nodes.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(nodes)
+}
+
+/// The target of the symlink at `link`, as it is on disk.
+fn read_symlink_target(link: AbsNormPathBuf) -> anyhow::Result<String> {
+    let target = fs_util::read_link(&link)?;
+    match target.to_str() {
+        Some(target) => Ok(target.to_owned()),
+        None => Err(LocalActionCacheError::NonUtf8SymlinkTarget(link).into()),
+    }
+}
+
+/// The target of a symlink of an output downloaded from the remote action cache.
+pub(crate) fn member_symlink_target(member: &ActionDirectoryMember) -> anyhow::Result<String> {
+    match member {
+        ActionDirectoryMember::Symlink(s) => Ok(s.target().as_str().to_owned()),
+        ActionDirectoryMember::ExternalSymlink(s) => Ok(match s.remaining_path() {
+            Some(remaining) => format!("{}/{}", s.target_str(), remaining),
+            None => s.target_str().to_owned(),
+        }),
+        ActionDirectoryMember::File(_) => Err(internal_error!("Not a symlink")),
+    }
+}
+
+fn list_sharded_files(
+    dir: &AbsNormPath,
+) -> anyhow::Result<Vec<(AbsNormPathBuf, std::fs::Metadata)>> {
+    let mut files = Vec::new();
+    let Some(shards) = fs_util::read_dir_if_exists(dir)? else {
+        return Ok(files);
+    };
+    for shard in shards {
+        let shard = shard?;
+        for file in fs_util::read_dir(shard.path())? {
+            let file = file?;
+            files.push((file.path(), file.metadata()?));
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(unix)]
+pub(crate) fn set_executable(path: &AbsNormPath, is_executable: bool) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = if is_executable { 0o755 } else { 0o644 };
+    fs_util::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn set_executable(_path: &AbsNormPath, _is_executable: bool) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// Serves actions from the local action cache, falling back to `next` on a miss.
+pub struct LocalActionCacheChecker {
+    pub cache: Arc<LocalActionCache>,
+    pub artifact_fs: ArtifactFs,
+    pub materializer: Arc<dyn Materializer>,
+    pub blocking_executor: Arc<dyn BlockingExecutor>,
+    pub next: Arc<dyn PreparedCommandOptionalExecutor>,
+}
+
+impl LocalActionCacheChecker {
+    /// Restores the outputs of a cache entry, and returns them once checked against the entry.
+    async fn restore(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        entry: LocalActionCacheEntry,
+        cancellations: &CancellationContext<'_>,
+    ) -> anyhow::Result<(IndexMap<CommandExecutionOutput, ArtifactValue>, HashingInfo)> {
+        create_output_dirs(
+            &self.artifact_fs,
+            command.request,
+            self.materializer.dupe(),
+            self.blocking_executor.dupe(),
+            cancellations,
+        )
+        .await?;
+
+        let cache = self.cache.dupe();
+        let fs = self.artifact_fs.fs().dupe();
+        let entry = Arc::new(entry);
+        self.blocking_executor
+            .execute_io_inline({
+                let entry = entry.dupe();
+                move || cache.restore(&entry, &fs)
+            })
+            .await?;
+
+        let (outputs, hashing_info) = calculate_and_declare_output_values(
+            &self.artifact_fs,
+            self.materializer.as_ref(),
+            self.blocking_executor.as_ref(),
+            command.request,
+            command.digest_config,
+        )
+        .await?;
+
+        // The blobs might have been modified since they were stored.
+        let expected: HashMap<_, _> = entry.outputs.iter().map(|o| (&o.path, &o.nodes)).collect();
+        for (output, value) in &outputs {
+            let path = output.as_ref().resolve(&self.artifact_fs).into_path();
+            let output_path = self.artifact_fs.fs().resolve(&path);
+            let nodes = output_nodes(value, |p, _| read_symlink_target(output_path.join(p)))?;
+            if expected.get(&path.to_string()) != Some(&&nodes) {
+                return Err(LocalActionCacheError::OutputMismatch(path.to_string()).into());
+            }
+        }
+        if outputs.len() != entry.outputs.len() {
+            return Err(LocalActionCacheError::OutputMismatch("<missing>".to_owned()).into());
+        }
+
+        Ok((outputs, hashing_info))
+    }
+}
+
+#[async_trait]
+impl PreparedCommandOptionalExecutor for LocalActionCacheChecker {
+    async fn maybe_execute(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        manager: CommandExecutionManager,
+        cancellations: &CancellationContext,
+    ) -> ControlFlow<CommandExecutionResult, CommandExecutionManager> {
+        let action_digest = command.prepared_action.digest();
+        if !is_cacheable(command.request) {
+            return self
+                .next
+                .maybe_execute(command, manager, cancellations)
+                .await;
+        }
+        let keys = LocalActionCacheKey::lookup_keys(
+            &action_digest,
+            command.request.env().keys().map(String::as_str),
+            command.request.local_environment_inheritance(),
+            command.digest_config,
+        );
+
+        let start_time = SystemTime::now();
+        let start = Instant::now();
+        let cache = self.cache.dupe();
+        let entry = self
+            .blocking_executor
+            .execute_io_inline(move || cache.lookup_first(&keys))
+            .await;
+        let (key, entry) = match entry {
+            Ok(Some(found)) => found,
+            Ok(None) => {
+                return self
+                    .next
+                    .maybe_execute(command, manager, cancellations)
+                    .await
+            }
+            Err(e) => {
+                tracing::warn!("Error reading the local action cache: {:#}", e);
+                return self
+                    .next
+                    .maybe_execute(command, manager, cancellations)
+                    .await;
+            }
+        };
+        let (stdout, stderr) = (entry.stdout.clone(), entry.stderr.clone());
+
+        // Nothing else writes the outputs before we return, so we only claim once they are
+        // restored, and can fall back to executing the action if that fails.
+        let (outputs, hashing_info) =
+            match self.restore(command, entry, cancellations).boxed().await {
+                Ok(res) => res,
+                Err(e) => {
+                    tracing::warn!(
+                        "Discarding local action cache entry for `{}`: {:#}",
+                        action_digest,
+                        e
+                    );
+                    let cache = self.cache.dupe();
+                    let _ignored = self
+                        .blocking_executor
+                        .execute_io_inline(move || cache.remove(&key))
+                        .await;
+                    return self
+                        .next
+                        .maybe_execute(command, manager, cancellations)
+                        .await;
+                }
+            };
+
+        let execution_kind = CommandExecutionKind::LocalActionCache {
+            digest: action_digest,
+            command: command.request.all_args_vec(),
+            env: command.request.env().clone(),
+        };
+        let manager = manager
+            .with_execution_kind(execution_kind.clone())
+            .claim()
+            .await;
+        let wall_time = start.elapsed();
+        ControlFlow::Break(manager.success(
+            execution_kind,
+            outputs,
+            CommandStdStreams::Local { stdout, stderr },
+            CommandExecutionMetadata {
+                wall_time,
+                execution_time: Duration::ZERO,
+                start_time,
+                execution_stats: None,
+                input_materialization_duration: Duration::ZERO,
+                hashing_duration: hashing_info.hashing_duration,
+                hashed_artifacts_count: hashing_info.hashed_artifacts_count,
+                queue_duration: None,
+            },
+        ))
+    }
+}
+
+/// Stores the outputs of the commands `inner` executes locally in the local action cache.
+pub struct LocalActionCacheWriter {
+    pub cache: Arc<LocalActionCache>,
+    pub artifact_fs: ArtifactFs,
+    pub blocking_executor: Arc<dyn BlockingExecutor>,
+    pub inner: Arc<dyn PreparedCommandExecutor>,
+}
+
+#[async_trait]
+impl PreparedCommandExecutor for LocalActionCacheWriter {
+    async fn exec_cmd(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        manager: CommandExecutionManager,
+        cancellations: &CancellationContext,
+    ) -> CommandExecutionResult {
+        let result = self.inner.exec_cmd(command, manager, cancellations).await;
+
+        // Test outputs are never looked up.
+        let cacheable = result.was_locally_executed()
+            && is_cacheable(command.request)
+            && result
+                .outputs
+                .keys()
+                .all(|o| matches!(o, CommandExecutionOutput::BuildArtifact { .. }));
+        if let (true, CommandStdStreams::Local { stdout, stderr }) =
+            (cacheable, &result.report.std_streams)
+        {
+            let action_digest = command.prepared_action.digest();
+            let key = LocalActionCacheKey::new(
+                &action_digest,
+                command.request.env().keys().map(String::as_str),
+                command.request.local_environment_inheritance(),
+                command.digest_config,
+            );
+            let outputs: Vec<_> = result
+                .resolve_outputs(&self.artifact_fs)
+                .map(|(output, value)| (output.into_path(), value.dupe()))
+                .collect();
+            let cache = self.cache.dupe();
+            let fs = self.artifact_fs.fs().dupe();
+            let stored = self
+                .blocking_executor
+                .execute_io_inline(move || cache.store(&key, &outputs, stdout, stderr, &fs))
+                .await;
+            match stored {
+                Ok(_) => self.cache.maybe_gc(),
+                Err(e) => tracing::warn!(
+                    "Error storing `{}` in the local action cache: {:#}",
+                    action_digest,
+                    e
+                ),
+            }
+        }
+
+        result
+    }
+
+    fn is_local_execution_possible(&self, executor_preference: ExecutorPreference) -> bool {
+        self.inner.is_local_execution_possible(executor_preference)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::fs::project::ProjectRootTemp;
+
+    use super::*;
+
+    #[test]
+    fn test_store_restore_gc() -> anyhow::Result<()> {
+        let project = ProjectRootTemp::new()?;
+        let fs = project.path();
+        let digest_config = DigestConfig::testing_default();
+        let cache = LocalActionCache {
+            root: fs.resolve(ProjectRelativePath::unchecked_new("cache")),
+            max_bytes: 1,
+            size: AtomicU64::new(0),
+            gc_running: AtomicBool::new(false),
+            tmp_counter: AtomicU64::new(0),
+        };
+
+        let output = ProjectRelativePathBuf::unchecked_new("out/file".to_owned());
+        fs.write_file(&output, "contents", true)?;
+        let value = ArtifactValue::file(FileMetadata {
+            digest: TrackedFileDigest::from_content(b"contents", digest_config.cas_digest_config()),
+            is_executable: true,
+        });
+        let action = LocalActionCacheKey::with_env(
+            &ActionDigest::new_sha1([1; 20], 1),
+            BTreeMap::new(),
+            digest_config,
+        );
+        let added = cache.store(&action, &[(output.clone(), value)], b"out", b"", fs)?;
+        assert_eq!(added, 8);
+
+        // The blob is a copy, so writing to the output in place doesn't corrupt the cache.
+        fs.write_file(&output, "modified", true)?;
+        fs_util::remove_file(fs.resolve(&output))?;
+        let entry = cache.lookup(&action)?.context("entry")?;
+        assert_eq!(entry.stdout, b"out");
+        cache.restore(&entry, fs)?;
+        assert_eq!(fs_util::read_to_string(fs.resolve(&output))?, "contents");
+
+        // Nothing fits in a single byte.
+        cache.gc()?;
+        assert!(cache.lookup(&action)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lookup_remote_entry() -> anyhow::Result<()> {
+        let project = ProjectRootTemp::new()?;
+        let fs = project.path();
+        let digest_config = DigestConfig::testing_default();
+        let cache = LocalActionCache {
+            root: fs.resolve(ProjectRelativePath::unchecked_new("cache")),
+            max_bytes: u64::MAX,
+            size: AtomicU64::new(0),
+            gc_running: AtomicBool::new(false),
+            tmp_counter: AtomicU64::new(0),
+        };
+        let action = ActionDigest::new_sha1([1; 20], 1);
+        let entry = |stdout: &[u8]| LocalActionCacheEntry {
+            stdout: stdout.to_vec(),
+            ..Default::default()
+        };
+        let lookup = |env_inheritance: &EnvironmentInheritance| {
+            cache.lookup_first(&LocalActionCacheKey::lookup_keys(
+                &action,
+                [],
+                Some(env_inheritance),
+                digest_config,
+            ))
+        };
+
+        let clean = EnvironmentInheritance::empty();
+        let inherit = EnvironmentInheritance::local_command_exclusions();
+        assert!(lookup(&clean)?.is_none());
+
+        // A warmed entry is used whatever the command inherits.
+        let remote = LocalActionCacheKey::remote(&action);
+        cache.write_entry(&remote, &entry(b"remote"), 0)?;
+        for env_inheritance in [&clean, &inherit] {
+            let (key, found) = lookup(env_inheritance)?.context("entry")?;
+            assert_eq!(remote, key);
+            assert_eq!(b"remote", found.stdout.as_slice());
+        }
+
+        // The entry of the command itself comes first.
+        let local = LocalActionCacheKey::new(&action, [], Some(&clean), digest_config);
+        cache.write_entry(&local, &entry(b"local"), 0)?;
+        let (key, found) = lookup(&clean)?.context("entry")?;
+        assert_eq!(local, key);
+        assert_eq!(b"local", found.stdout.as_slice());
+        assert_eq!(remote, lookup(&inherit)?.context("entry")?.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_includes_inherited_env() {
+        let digest_config = DigestConfig::testing_default();
+        let action = ActionDigest::new_sha1([1; 20], 1);
+        let vars = |value: &str, set: &str| {
+            vec![
+                (OsString::from("PATH"), OsString::from(value)),
+                (OsString::from("LD_PRELOAD"), OsString::from(value)),
+                (OsString::from("CC"), OsString::from(set)),
+                (OsString::from("PWD"), OsString::from(set)),
+            ]
+        };
+        let key = |vars: Vec<(OsString, OsString)>, inheritance: &EnvironmentInheritance| {
+            LocalActionCacheKey::with_env(
+                &action,
+                inherited_env(vars, ["CC"], Some(inheritance)),
+                digest_config,
+            )
+        };
+
+        let inherit = EnvironmentInheritance::local_command_exclusions();
+        assert_eq!(key(vars("a", "x"), &inherit), key(vars("a", "x"), &inherit));
+        assert_ne!(key(vars("a", "x"), &inherit), key(vars("b", "x"), &inherit));
+        let env = inherited_env(vars("a", "x"), ["CC"], Some(&inherit));
+        assert_eq!(
+            vec![(OsString::from("PATH"), OsString::from("a"))],
+            env.into_iter().collect::<Vec<_>>()
+        );
+
+        // The variables the command sets itself don't matter.
+        assert_eq!(key(vars("a", "x"), &inherit), key(vars("a", "y"), &inherit));
+
+        // Commands with a clean environment don't depend on the daemon one.
+        let clean = EnvironmentInheritance::empty();
+        assert_eq!(key(vars("a", "x"), &clean), key(vars("b", "x"), &clean));
+        assert_ne!(key(vars("a", "x"), &clean), key(vars("a", "x"), &inherit));
+    }
+
+    #[test]
+    fn test_unknown_size_does_not_wrap() -> anyhow::Result<()> {
+        let project = ProjectRootTemp::new()?;
+        let cache = LocalActionCache {
+            root: project
+                .path()
+                .resolve(ProjectRelativePath::unchecked_new("cache")),
+            max_bytes: 1,
+            size: AtomicU64::new(u64::MAX),
+            gc_running: AtomicBool::new(false),
+            tmp_counter: AtomicU64::new(0),
+        };
+        let key = LocalActionCacheKey::remote(&ActionDigest::new_sha1([1; 20], 1));
+        cache.write_entry(&key, &LocalActionCacheEntry::default(), 8)?;
+        assert_eq!(u64::MAX, cache.size.load(Ordering::Relaxed));
+        Ok(())
+    }
+}
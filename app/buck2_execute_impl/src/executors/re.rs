@@ -19,6 +19,7 @@ use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::soft_error;
 use buck2_events::dispatch::span_async;
+use buck2_execute::artifact_value::ArtifactValue;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::execute::action_digest::ActionDigest;
 use buck2_execute::execute::blobs::ActionBlobs;
@@ -30,6 +31,7 @@ use buck2_execute::execute::output::CommandStdStreams;
 use buck2_execute::execute::prepared::PreparedAction;
 use buck2_execute::execute::prepared::PreparedCommand;
 use buck2_execute::execute::prepared::PreparedCommandExecutor;
+use buck2_execute::execute::request::CommandExecutionOutput;
 use buck2_execute::execute::request::CommandExecutionPaths;
 use buck2_execute::execute::request::CommandExecutionRequest;
 use buck2_execute::execute::request::ExecutorPreference;
@@ -52,6 +54,7 @@ use remote_execution::TCode;
 use tracing::info;
 
 use crate::re::download::download_action_results;
+use crate::re::download::CasDownloader;
 use crate::re::download::DownloadResult;
 use crate::re::paranoid_download::ParanoidDownloader;
 use crate::storage_resource_exhausted::is_storage_resource_exhausted;
@@ -90,7 +93,7 @@ impl ReExecutor {
     ) -> ControlFlow<CommandExecutionResult, CommandExecutionManager> {
         let re_client = &self.re_client;
 
-        let upload_response = span_async(buck2_data::ReUploadStart {}, async move {
+        let upload = span_async(buck2_data::ReUploadStart {}, async move {
             let res = re_client
                 .upload(
                     &self.project_fs,
@@ -113,8 +116,20 @@ impl ReExecutor {
                 ),
                 Err(e) => (Err(e), buck2_data::ReUploadEnd::default()),
             }
-        })
-        .await;
+        });
+
+        // Uploading a large input tree can take a while, so don't hold up cancellation until
+        // it finishes: dropping the upload is fine, the blobs are content-addressed.
+        let liveliness_observer = manager.inner.liveliness_observer.dupe();
+        let alive = liveliness_observer.while_alive();
+        futures::pin_mut!(upload);
+        futures::pin_mut!(alive);
+        let upload_response = match futures::future::select(upload, alive).await {
+            futures::future::Either::Left((res, _)) => res,
+            futures::future::Either::Right(((), _)) => {
+                return ControlFlow::Break(manager.cancel());
+            }
+        };
 
         match upload_response {
             Ok(()) => {}
@@ -166,7 +181,7 @@ impl ReExecutor {
                 &identity,
                 &mut manager,
                 self.skip_cache_read,
-                self.skip_cache_write,
+                self.skip_cache_write || request.remote_cache_read_only(),
                 self.re_max_queue_time_ms.map(Duration::from_millis),
                 self.re_resource_units,
                 &self.knobs,
@@ -261,6 +276,77 @@ impl ReExecutor {
 
         ControlFlow::Continue((manager, response))
     }
+
+    /// Execute the command remotely and return its exit code and the values of its outputs,
+    /// without downloading them. This is used to compare the outputs with those of another
+    /// execution of the same command, so the outputs are not declared to the materializer.
+    ///
+    /// Returns `None` if the command could not be executed remotely.
+    pub async fn exec_cmd_for_verification(
+        &self,
+        command: &PreparedCommand<'_, '_>,
+        manager: CommandExecutionManager,
+    ) -> anyhow::Result<Option<(i32, IndexMap<CommandExecutionOutput, ArtifactValue>)>> {
+        let PreparedCommand {
+            request,
+            target,
+            prepared_action:
+                PreparedAction {
+                    action_and_blobs,
+                    platform,
+                    remote_execution_dependencies,
+                },
+            digest_config,
+        } = command;
+
+        let identity =
+            ReActionIdentity::new(*target, self.re_action_key.as_deref(), request.paths());
+
+        let ControlFlow::Continue(manager) = self
+            .upload(
+                manager,
+                &identity,
+                &action_and_blobs.blobs,
+                request.paths(),
+                *digest_config,
+            )
+            .await
+        else {
+            return Ok(None);
+        };
+
+        let ControlFlow::Continue((_manager, response)) = self
+            .re_execute(
+                manager,
+                &identity,
+                request,
+                &action_and_blobs.action,
+                *digest_config,
+                platform,
+                self.dependencies
+                    .iter()
+                    .chain(remote_execution_dependencies.iter()),
+            )
+            .await
+        else {
+            return Ok(None);
+        };
+
+        let artifacts = CasDownloader {
+            materializer: &*self.materializer,
+            re_client: &self.re_client,
+            re_use_case: self.re_use_case,
+            digest_config: *digest_config,
+            paranoid: None,
+        }
+        .extract_artifacts(&identity, request.paths(), request.outputs(), &response)
+        .await?;
+
+        Ok(Some((
+            response.action_result.exit_code,
+            artifacts.mapped_outputs,
+        )))
+    }
 }
 
 #[async_trait]
@@ -305,7 +391,6 @@ impl PreparedCommandExecutor for ReExecutor {
         let identity =
             ReActionIdentity::new(*target, self.re_action_key.as_deref(), request.paths());
 
-        // TODO(bobyf, torozco): remote execution probably needs to explicitly handle cancellations
         let manager = self
             .upload(
                 manager,
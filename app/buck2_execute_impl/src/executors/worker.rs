@@ -368,6 +368,23 @@ impl WorkerPool {
             (true, fut)
         }
     }
+
+    /// Forget a worker we could no longer talk to (e.g. because it crashed), so that the next
+    /// command using this worker spec spawns a new one instead of failing as well.
+    pub fn evict_worker(&self, worker_spec: &WorkerSpec, worker: &Arc<WorkerHandle>) {
+        let mut workers = self.workers.lock();
+        if let Some(Some(Ok(current))) = workers.get(&worker_spec.id).map(|fut| fut.peek()) {
+            if Arc::ptr_eq(current, worker) {
+                tracing::warn!(
+                    "Evicting worker {:?}, see worker logs:\n{}\n{}",
+                    worker_spec.exe,
+                    worker.stdout_path,
+                    worker.stderr_path,
+                );
+                workers.remove(&worker_spec.id);
+            }
+        }
+    }
 }
 
 pub struct WorkerHandle {
@@ -466,3 +483,48 @@ impl WorkerHandle {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+    use dupe::Dupe;
+    use tonic::transport::Endpoint;
+
+    use super::*;
+
+    fn worker_handle() -> Arc<WorkerHandle> {
+        let log_path = if cfg!(windows) {
+            AbsNormPath::new("C:\\worker.log").unwrap().to_buf()
+        } else {
+            AbsNormPath::new("/worker.log").unwrap().to_buf()
+        };
+        Arc::new(WorkerHandle::new(
+            WorkerClient::new(Endpoint::from_static("http://127.0.0.1:1").connect_lazy()),
+            log_path.clone(),
+            log_path,
+            LivelinessGuard::create().1,
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_evict_worker() {
+        let pool = WorkerPool::new(None);
+        let worker_spec = WorkerSpec {
+            id: WorkerId(1),
+            exe: vec!["worker".to_owned()],
+            concurrency: None,
+            remote_key: None,
+        };
+        let worker = worker_handle();
+        let fut: WorkerFuture = futures::future::ready(Ok(worker.dupe())).boxed().shared();
+        fut.clone().await.ok().unwrap();
+        pool.workers.lock().insert(worker_spec.id, fut);
+
+        // A worker which was already replaced doesn't evict its replacement.
+        pool.evict_worker(&worker_spec, &worker_handle());
+        assert!(pool.workers.lock().contains_key(&worker_spec.id));
+
+        pool.evict_worker(&worker_spec, &worker);
+        assert!(!pool.workers.lock().contains_key(&worker_spec.id));
+    }
+}
@@ -8,6 +8,11 @@
  */
 
 pub mod deferred;
+#[cfg(fbcode_build)]
+pub mod eden;
+#[cfg(fbcode_build)]
+mod eden_api;
+mod eden_object;
 pub mod immediate;
 pub mod io;
 pub mod sqlite;
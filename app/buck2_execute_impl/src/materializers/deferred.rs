@@ -12,10 +12,13 @@ mod extension;
 mod file_tree;
 mod io_handler;
 mod subscriptions;
+pub mod throttle;
+mod verify;
 
 #[cfg(test)]
 mod tests;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::fmt::Display;
@@ -25,6 +28,7 @@ use std::sync::atomic::AtomicU64;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::sync::Weak;
 use std::task::Context;
 use std::task::Poll;
 
@@ -37,6 +41,7 @@ use buck2_common::liveliness_observer::LivelinessGuard;
 use buck2_core::buck2_env;
 use buck2_core::directory::unordered_entry_walk;
 use buck2_core::directory::DirectoryEntry;
+use buck2_core::directory::FingerprintedDirectory;
 use buck2_core::fs::paths::RelativePathBuf;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
@@ -115,6 +120,9 @@ use crate::materializers::deferred::io_handler::DefaultIoHandler;
 use crate::materializers::deferred::io_handler::IoHandler;
 use crate::materializers::deferred::subscriptions::MaterializerSubscriptionOperation;
 use crate::materializers::deferred::subscriptions::MaterializerSubscriptions;
+use crate::materializers::deferred::throttle::MaterializationThrottle;
+use crate::materializers::deferred::throttle::MaterializationThrottleConfig;
+use crate::materializers::io::MaterializationDedup;
 use crate::materializers::sqlite::MaterializerState;
 use crate::materializers::sqlite::MaterializerStateSqliteDb;
 
@@ -163,6 +171,10 @@ pub struct DeferredMaterializerAccessor<T: IoHandler + 'static> {
 
     /// Logs verbose events about materializer to the event log when enabled.
     verbose_materializer_log: bool,
+
+    /// Deduplicates the contents of deferred writes.
+    #[allocative(skip)]
+    write_cache: WriteCache,
 }
 
 pub type DeferredMaterializer = DeferredMaterializerAccessor<DefaultIoHandler>;
@@ -179,6 +191,40 @@ impl<T: IoHandler> Drop for DeferredMaterializerAccessor<T> {
 pub struct DeferredMaterializerStats {
     declares: AtomicU64,
     declares_reused: AtomicU64,
+    writes_deduped: AtomicU64,
+    /// Materializations held back by the throttle.
+    materializations_waiting: AtomicU64,
+    materializations_in_flight: AtomicU64,
+}
+
+/// The compressed contents of the deferred writes which are still declared, keyed by their
+/// digest. Identical contents written by many actions (e.g. the same generated config or
+/// argsfile in every target) are compressed once and held in memory once.
+#[derive(Default)]
+struct WriteCache {
+    entries: Mutex<WriteCacheEntries>,
+}
+
+#[derive(Default)]
+struct WriteCacheEntries {
+    entries: HashMap<FileMetadata, Weak<WriteFile>>,
+    /// Entries whose writes were dropped are removed when the cache reaches this size.
+    prune_at: usize,
+}
+
+impl WriteCache {
+    fn get(&self, meta: &FileMetadata) -> Option<Arc<WriteFile>> {
+        self.entries.lock().entries.get(meta)?.upgrade()
+    }
+
+    fn insert(&self, meta: FileMetadata, write: &Arc<WriteFile>) {
+        let mut cache = self.entries.lock();
+        if cache.entries.len() >= cache.prune_at {
+            cache.entries.retain(|_, w| w.strong_count() > 0);
+            cache.prune_at = std::cmp::max(1024, cache.entries.len() * 2);
+        }
+        cache.entries.insert(meta, Arc::downgrade(write));
+    }
 }
 
 fn access_time_update_max_buffer_size() -> anyhow::Result<usize> {
@@ -192,6 +238,8 @@ pub struct DeferredMaterializerConfigs {
     pub update_access_times: AccessTimesUpdates,
     pub verbose_materializer_log: bool,
     pub clean_stale_config: Option<CleanStaleConfig>,
+    pub dedup: MaterializationDedup,
+    pub throttle: MaterializationThrottleConfig,
 }
 
 pub struct TtlRefreshConfiguration {
@@ -546,6 +594,27 @@ pub struct ArtifactMaterializationData {
     processing: Processing,
 }
 
+impl ArtifactMaterializationData {
+    /// Whether this is a declared, non-empty directory whose members can be declared and
+    /// materialized separately. Directories that are being materialized already can't be split.
+    fn can_split(&self) -> bool {
+        match (&self.stage, &self.processing) {
+            (
+                ArtifactMaterializationStage::Declared {
+                    entry: DirectoryEntry::Dir(dir),
+                    method,
+                },
+                Processing::Done(..)
+                | Processing::Active {
+                    future: ProcessingFuture::Cleaning(_),
+                    ..
+                },
+            ) => method.materializes_members() && dir.entries().into_iter().next().is_some(),
+            _ => false,
+        }
+    }
+}
+
 /// Represents a processing future + the version at which it was issued. When receiving
 /// notifications about processing futures that finish, their changes are only applied if their
 /// version is greater than the current version.
@@ -599,7 +668,10 @@ pub struct DirectoryMetadata {
 }
 
 impl ArtifactMetadata {
-    fn matches_entry(&self, entry: &ActionDirectoryEntry<ActionSharedDirectory>) -> bool {
+    fn matches_entry<D>(&self, entry: &ActionDirectoryEntry<D>) -> bool
+    where
+        D: FingerprintedDirectory<ActionDirectoryMember, TrackedFileDigest>,
+    {
         match (&self.0, entry) {
             (
                 DirectoryEntry::Dir(DirectoryMetadata { fingerprint, .. }),
@@ -702,6 +774,21 @@ pub enum ArtifactMaterializationMethod {
     Test,
 }
 
+impl ArtifactMaterializationMethod {
+    /// Whether the members of a directory materialized with this method can be materialized
+    /// separately, that is, without the rest of the directory.
+    fn materializes_members(&self) -> bool {
+        match self {
+            ArtifactMaterializationMethod::CasDownload { .. } => true,
+            ArtifactMaterializationMethod::LocalCopy(..)
+            | ArtifactMaterializationMethod::Write(..)
+            | ArtifactMaterializationMethod::HttpDownload { .. } => false,
+            #[cfg(test)]
+            ArtifactMaterializationMethod::Test => true,
+        }
+    }
+}
+
 trait MaterializationMethodToProto {
     fn to_proto(&self) -> buck2_data::MaterializationMethod;
 }
@@ -847,19 +934,31 @@ impl<T: IoHandler + Allocative> Materializer for DeferredMaterializerAccessor<T>
                 is_executable,
             };
 
-            // NOTE: The zstd crate doesn't release extra capacity of its encoding buffer so it's
-            // important to do so here (or the compressed Vec is the same capacity as the input!).
-            let compressed_data = zstd::bulk::compress(&content, 0)
-                .with_context(|| format!("Error compressing {} bytes", content.len()))?
-                .into_boxed_slice();
+            let write = match self.write_cache.get(&meta) {
+                Some(write) => {
+                    self.stats.writes_deduped.fetch_add(1, Ordering::Relaxed);
+                    write
+                }
+                None => {
+                    // NOTE: The zstd crate doesn't release extra capacity of its encoding buffer so
+                    // it's important to do so here (or the compressed Vec is the same capacity as
+                    // the input!).
+                    let compressed_data = zstd::bulk::compress(&content, 0)
+                        .with_context(|| format!("Error compressing {} bytes", content.len()))?
+                        .into_boxed_slice();
+                    let write = Arc::new(WriteFile {
+                        compressed_data,
+                        decompressed_size: content.len(),
+                        is_executable,
+                    });
+                    self.write_cache.insert(meta.dupe(), &write);
+                    write
+                }
+            };
 
             paths.push(path);
             values.push(ArtifactValue::file(meta));
-            methods.push(ArtifactMaterializationMethod::Write(Arc::new(WriteFile {
-                compressed_data,
-                decompressed_size: content.len(),
-                is_executable,
-            })));
+            methods.push(ArtifactMaterializationMethod::Write(write));
         }
 
         for (path, (value, method)) in std::iter::zip(paths, std::iter::zip(values.iter(), methods))
@@ -977,6 +1076,14 @@ impl<T: IoHandler + Allocative> Materializer for DeferredMaterializerAccessor<T>
         snapshot.deferred_materializer_declares = self.stats.declares.load(Ordering::Relaxed);
         snapshot.deferred_materializer_declares_reused =
             self.stats.declares_reused.load(Ordering::Relaxed);
+        snapshot.deferred_materializer_writes_deduped =
+            self.stats.writes_deduped.load(Ordering::Relaxed);
+        snapshot.deferred_materializer_materializations_waiting =
+            self.stats.materializations_waiting.load(Ordering::Relaxed);
+        snapshot.deferred_materializer_materializations_in_flight = self
+            .stats
+            .materializations_in_flight
+            .load(Ordering::Relaxed);
         snapshot.deferred_materializer_queue_size = self.command_sender.counters.queue_size() as _;
     }
 }
@@ -1034,6 +1141,8 @@ impl DeferredMaterializerAccessor<DefaultIoHandler> {
             re_client_manager,
             io_executor,
             http_client,
+            configs.dedup,
+            MaterializationThrottle::new(&configs.throttle, stats.dupe()),
         ));
 
         let command_processor = {
@@ -1092,6 +1201,7 @@ impl DeferredMaterializerAccessor<DefaultIoHandler> {
             materializer_state_info,
             stats,
             verbose_materializer_log: configs.verbose_materializer_log,
+            write_cache: WriteCache::default(),
         })
     }
 }
@@ -1337,6 +1447,7 @@ impl<T: IoHandler> DeferredMaterializerCommandProcessor<T> {
                         let dispatcher = self.daemon_dispatcher.dupe();
                         let cmd = CleanStaleArtifactsCommand {
                             keep_since_time: chrono::Utc::now() - config.artifact_ttl,
+                            max_size: config.max_size,
                             dry_run: config.dry_run,
                             tracked_only: false,
                             dispatcher,
@@ -1833,14 +1944,37 @@ impl<T: IoHandler> DeferredMaterializerCommandProcessor<T> {
         // Get the data about the artifact, or return early if materializing/materialized
         let mut path_iter = path.iter();
         let data = match self.tree.prefix_get_mut(&mut path_iter) {
-            // Never declared, nothing to do
             None => {
-                tracing::debug!("not known");
-                return Ok(None);
+                // The path may be a directory that was split into its members.
+                let members = self.tree.find_artifacts_under(path);
+                if members.is_empty() {
+                    // Never declared, nothing to do
+                    tracing::debug!("not known");
+                    return Ok(None);
+                }
+                tracing::debug!("materialize members");
+                let tasks = members
+                    .iter()
+                    .filter_map(|p| {
+                        self.materialize_artifact_recurse(stack, p, event_dispatcher.dupe())
+                    })
+                    .collect::<Vec<_>>();
+                return Ok(Some(
+                    future::try_join_all(tasks).map_ok(|_| ()).boxed().shared(),
+                ));
             }
             Some(data) => data,
         };
 
+        // When `path` is a member of a declared directory (e.g. a projection of an output
+        // directory), download just that member rather than the whole directory, using the
+        // digests of the members in the entry of the directory.
+        if !path_iter.as_path().is_empty() && data.can_split() {
+            let artifact_path = path.strip_suffix(path_iter.as_path()).unwrap().to_buf();
+            self.split_declared_directory(&artifact_path);
+            return self.materialize_artifact_inner(stack, path, event_dispatcher);
+        }
+
         let path = path.strip_suffix(path_iter.as_path()).unwrap();
 
         let cleaning_fut = match &data.processing {
@@ -2040,6 +2174,48 @@ impl<T: IoHandler> DeferredMaterializerCommandProcessor<T> {
         Ok(Some(task))
     }
 
+    /// Replaces the declared directory at `path` with a declaration of each of its members, so
+    /// that they can be materialized without the rest of the directory. The members wait for
+    /// the cleanup of the directory, if it's still running.
+    fn split_declared_directory(&mut self, path: &ProjectRelativePath) {
+        let Some(FileTree::Data(data)) = self.tree.remove(&mut path.iter()) else {
+            unreachable!("split a directory that was not declared: {}", path)
+        };
+        let ArtifactMaterializationData {
+            deps,
+            stage,
+            processing,
+        } = *data;
+        let ArtifactMaterializationStage::Declared {
+            entry: DirectoryEntry::Dir(dir),
+            method,
+        } = stage
+        else {
+            unreachable!("split an artifact that is not a directory: {}", path)
+        };
+
+        tracing::debug!(path = %path, "split declared directory");
+        for (name, entry) in dir.entries() {
+            let processing = match &processing {
+                Processing::Done(version) => Processing::Done(*version),
+                Processing::Active { future, version } => Processing::Active {
+                    future: future.clone(),
+                    version: *version,
+                },
+            };
+            let member = Box::new(ArtifactMaterializationData {
+                deps: deps.dupe(),
+                stage: ArtifactMaterializationStage::Declared {
+                    entry: entry.clone(),
+                    method: method.dupe(),
+                },
+                processing,
+            });
+            self.tree
+                .insert(path.join(name).iter().map(|f| f.to_owned()), member);
+        }
+    }
+
     #[instrument(level = "debug", skip(self, result), fields(path = %artifact_path))]
     fn materialization_finished(
         &mut self,
@@ -2302,8 +2478,12 @@ impl ArtifactTree {
                 }
             }
             Err(e) => {
-                // NOTE: This shouldn't normally happen?
-                soft_error!("cleanup_finished_vacant", e, quiet: true).unwrap();
+                // If the directory was split into its members, they just await the finished
+                // cleanup future before materializing.
+                if self.find_artifacts_under(&artifact_path).is_empty() {
+                    // NOTE: This shouldn't normally happen?
+                    soft_error!("cleanup_finished_vacant", e, quiet: true).unwrap();
+                }
             }
         }
     }
@@ -2394,6 +2574,22 @@ impl<V: 'static> FileTree<V> {
         artifacts
     }
 
+    /// Finds all the artifacts strictly under `path`.
+    fn find_artifacts_under(&self, path: &ProjectRelativePath) -> Vec<ProjectRelativePathBuf> {
+        match self.get_subtree(&mut path.iter()) {
+            Ok(Some(children)) => children
+                .iter()
+                .flat_map(|(name, child)| {
+                    let child_path = path.join(name);
+                    child
+                        .iter_with_paths()
+                        .map(move |(p, _)| child_path.join(p))
+                })
+                .collect(),
+            Ok(None) | Err(_) => Vec::new(),
+        }
+    }
+
     /// Removes path from FileTree. Returns an iterator of pairs of path and entry removed
     /// from the tree.
     fn remove_path(
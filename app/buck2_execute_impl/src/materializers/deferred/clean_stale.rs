@@ -55,6 +55,9 @@ use crate::materializers::sqlite::MaterializerStateSqliteDb;
 #[derive(Debug, Clone)]
 pub struct CleanStaleArtifactsCommand {
     pub keep_since_time: DateTime<Utc>,
+    /// If set, also clean the least recently used artifacts until the retained ones fit in this
+    /// many bytes.
+    pub max_size: Option<u64>,
     pub dry_run: bool,
     pub tracked_only: bool,
     pub dispatcher: EventDispatcher,
@@ -245,6 +248,10 @@ impl CleanStaleArtifactsCommand {
             .visit_recursively(gen_path, gen_subtree)?;
         };
 
+        if let Some(max_size) = self.max_size {
+            evict_least_recently_used(&mut found_paths, max_size);
+        }
+
         let mut stats = stats_for_paths(&found_paths);
         stats.scan_duration_s = (Instant::now() - start_time).as_secs();
 
@@ -327,7 +334,7 @@ fn stats_for_paths(paths: &Vec<FoundPath>) -> buck2_data::CleanStaleStats {
                 stats.stale_artifact_count += 1;
                 stats.stale_bytes += *size;
             }
-            FoundPath::Retained(size) => {
+            FoundPath::Retained(size, _) => {
                 stats.retained_artifact_count += 1;
                 stats.retained_bytes += *size;
             }
@@ -336,6 +343,42 @@ fn stats_for_paths(paths: &Vec<FoundPath>) -> buck2_data::CleanStaleStats {
     stats
 }
 
+/// Marks the least recently used retained artifacts as stale until the size of the retained ones
+/// is at most `max_size`. Artifacts used by builds since the daemon started are never evicted.
+fn evict_least_recently_used(found_paths: &mut [FoundPath], max_size: u64) {
+    let mut retained_bytes: u64 = found_paths
+        .iter()
+        .map(|x| match x {
+            FoundPath::Retained(size, _) => *size,
+            _ => 0,
+        })
+        .sum();
+    if retained_bytes <= max_size {
+        return;
+    }
+
+    let mut candidates: Vec<(DateTime<Utc>, usize)> = found_paths
+        .iter()
+        .enumerate()
+        .filter_map(|(i, x)| match x {
+            FoundPath::Retained(_, Some((_, last_access_time))) => Some((*last_access_time, i)),
+            _ => None,
+        })
+        .collect();
+    candidates.sort();
+
+    for (_, i) in candidates {
+        if retained_bytes <= max_size {
+            break;
+        }
+        if let FoundPath::Retained(size, Some((path, _))) = &found_paths[i] {
+            tracing::trace!(path = %path, "marking as stale to fit in max size");
+            retained_bytes -= *size;
+            found_paths[i] = FoundPath::Stale(path.clone(), *size);
+        }
+    }
+}
+
 fn create_clean_fut<T: IoHandler>(
     found_paths: Vec<FoundPath>,
     mut stats: CleanStaleStats,
@@ -468,7 +511,8 @@ enum FoundPath {
     Untracked(ProjectRelativePathBuf, FileType, u64),
     /// These will be invalidated in the materiaizer.
     Stale(ProjectRelativePathBuf, u64),
-    Retained(u64),
+    /// The path and last access time are set if this can be evicted to fit in a max size.
+    Retained(u64, Option<(ProjectRelativePathBuf, DateTime<Utc>)>),
 }
 
 impl<'a, T: IoHandler> StaleFinder<'a, T> {
@@ -554,11 +598,18 @@ impl<'a, T: IoHandler> StaleFinder<'a, T> {
                         .push(FoundPath::Stale(path, metadata.size()));
                 }
                 ArtifactTree::Data(box ArtifactMaterializationData {
-                    stage: ArtifactMaterializationStage::Materialized { metadata, .. },
+                    stage:
+                        ArtifactMaterializationStage::Materialized {
+                            active,
+                            last_access_time,
+                            metadata,
+                        },
                     ..
                 }) => {
                     tracing::trace!(path = %path, file_type = ?file_type, "marking as retained");
-                    self.found_paths.push(FoundPath::Retained(metadata.size()));
+                    let evictable = (!active).then(|| (path, *last_access_time));
+                    self.found_paths
+                        .push(FoundPath::Retained(metadata.size(), evictable));
                 }
                 _ => {
                     // What we have on disk does not match what we have in the materializer (which is
@@ -581,16 +632,17 @@ fn find_stale_tracked_only(
         if let ArtifactMaterializationStage::Materialized {
             last_access_time,
             active,
-            ..
+            metadata,
         } = &v.stage
         {
             let path = ProjectRelativePathBuf::from(f_path);
             if *last_access_time < keep_since_time && !active {
                 tracing::trace!(path = %path, "stale artifact");
-                found_paths.push(FoundPath::Stale(path, 0));
+                found_paths.push(FoundPath::Stale(path, metadata.size()));
             } else {
                 tracing::trace!(path = %path, "retaining artifact");
-                found_paths.push(FoundPath::Retained(0));
+                let evictable = (!active).then(|| (path, *last_access_time));
+                found_paths.push(FoundPath::Retained(metadata.size(), evictable));
             }
         }
     }
@@ -602,6 +654,8 @@ pub struct CleanStaleConfig {
     pub start_offset: std::time::Duration,
     pub clean_period: std::time::Duration,
     pub artifact_ttl: std::time::Duration,
+    /// Size in bytes that retained artifacts are evicted down to, least recently used first.
+    pub max_size: Option<u64>,
     pub dry_run: bool,
 }
 
@@ -637,6 +691,10 @@ impl CleanStaleConfig {
                 property: "clean_stale_dry_run",
             })?
             .unwrap_or(false);
+        let clean_stale_max_size_gb: Option<f64> = root_config.parse(BuckconfigKeyRef {
+            section: "buck2",
+            property: "clean_stale_max_size_gb",
+        })?;
 
         let secs_in_hour = 60.0 * 60.0;
         let clean_stale_config = if clean_stale_enabled {
@@ -650,6 +708,7 @@ impl CleanStaleConfig {
                 start_offset: std::time::Duration::from_secs_f64(
                     secs_in_hour * clean_stale_start_offset_hours,
                 ),
+                max_size: clean_stale_max_size_gb.map(|gb| (gb * 1e9) as u64),
                 dry_run: clean_stale_dry_run,
             })
         } else {
@@ -658,3 +717,35 @@ impl CleanStaleConfig {
         Ok(clean_stale_config)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_evict_least_recently_used() {
+        let path = |p: &str| ProjectRelativePathBuf::unchecked_new(p.to_owned());
+        let time = |t: i64| Utc.timestamp_opt(t, 0).single().unwrap();
+        let mut found_paths = vec![
+            FoundPath::Retained(10, Some((path("new"), time(3)))),
+            FoundPath::Retained(10, Some((path("old"), time(1)))),
+            FoundPath::Retained(10, None),
+            FoundPath::Retained(10, Some((path("middle"), time(2)))),
+            FoundPath::Stale(path("stale"), 10),
+        ];
+
+        evict_least_recently_used(&mut found_paths, 25);
+
+        let stale: Vec<_> = found_paths
+            .iter()
+            .filter_map(|x| match x {
+                FoundPath::Stale(p, _) => Some(p.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(stale, vec!["old", "middle", "stale"]);
+        assert_eq!(stats_for_paths(&found_paths).retained_bytes, 20);
+    }
+}
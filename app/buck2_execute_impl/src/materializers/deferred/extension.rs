@@ -23,6 +23,7 @@ use buck2_execute::directory::ActionDirectoryMember;
 use buck2_execute::materialize::materializer::DeferredMaterializerEntry;
 use buck2_execute::materialize::materializer::DeferredMaterializerExtensions;
 use buck2_execute::materialize::materializer::DeferredMaterializerSubscription;
+use buck2_execute::materialize::materializer::VerifyBuckOutReport;
 use chrono::DateTime;
 use chrono::Duration;
 use chrono::TimeZone;
@@ -43,6 +44,7 @@ use crate::materializers::deferred::clean_stale::CleanStaleArtifactsExtensionCom
 use crate::materializers::deferred::io_handler::create_ttl_refresh;
 use crate::materializers::deferred::io_handler::IoHandler;
 use crate::materializers::deferred::subscriptions::MaterializerSubscriptionOperation;
+use crate::materializers::deferred::verify;
 use crate::materializers::deferred::ArtifactMaterializationMethod;
 use crate::materializers::deferred::ArtifactMaterializationStage;
 use crate::materializers::deferred::DeferredMaterializerAccessor;
@@ -404,6 +406,7 @@ impl<T: IoHandler> DeferredMaterializerExtensions for DeferredMaterializerAccess
     async fn clean_stale_artifacts(
         &self,
         keep_since_time: DateTime<Utc>,
+        max_size: Option<u64>,
         dry_run: bool,
         tracked_only: bool,
     ) -> anyhow::Result<buck2_cli_proto::CleanStaleResponse> {
@@ -414,6 +417,7 @@ impl<T: IoHandler> DeferredMaterializerExtensions for DeferredMaterializerAccess
                 CleanStaleArtifactsExtensionCommand {
                     cmd: CleanStaleArtifactsCommand {
                         keep_since_time,
+                        max_size,
                         dry_run,
                         tracked_only,
                         dispatcher,
@@ -424,6 +428,10 @@ impl<T: IoHandler> DeferredMaterializerExtensions for DeferredMaterializerAccess
         recv.await?.await.map(|res| res.into())
     }
 
+    async fn verify_buck_out(&self, repair: bool) -> anyhow::Result<VerifyBuckOutReport> {
+        verify::verify_buck_out(&self.command_sender, &self.io, repair).await
+    }
+
     async fn test_iter(&self, count: usize) -> anyhow::Result<String> {
         let (sender, receiver) = oneshot::channel();
         self.command_sender
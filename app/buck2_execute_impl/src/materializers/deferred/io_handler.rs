@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::str::FromStr;
@@ -16,6 +17,7 @@ use allocative::Allocative;
 use anyhow::Context;
 use async_trait::async_trait;
 use buck2_common::file_ops::FileDigest;
+use buck2_common::file_ops::FileMetadata;
 use buck2_core::buck2_env;
 use buck2_core::directory::unordered_entry_walk;
 use buck2_core::directory::DirectoryEntry;
@@ -24,6 +26,7 @@ use buck2_core::fs::fs_util::IoError;
 use buck2_core::fs::fs_util::ReadDir;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_events::dispatch::EventDispatcher;
 use buck2_execute::artifact_value::ArtifactValue;
@@ -59,6 +62,7 @@ use remote_execution::TDigest;
 use tracing::instrument;
 
 use crate::materializers::deferred::clean_stale::CleanInvalidatedPathRequest;
+use crate::materializers::deferred::throttle::MaterializationThrottle;
 use crate::materializers::deferred::ArtifactMaterializationMethod;
 use crate::materializers::deferred::ArtifactMaterializationStage;
 use crate::materializers::deferred::ArtifactTree;
@@ -71,6 +75,7 @@ use crate::materializers::deferred::Version;
 use crate::materializers::deferred::WriteFile;
 use crate::materializers::immediate;
 use crate::materializers::io::materialize_files;
+use crate::materializers::io::MaterializationDedup;
 use crate::materializers::io::MaterializeTreeStructure;
 
 #[derive(Allocative)]
@@ -82,6 +87,9 @@ pub struct DefaultIoHandler {
     /// Executor for blocking IO operations
     io_executor: Arc<dyn BlockingExecutor>,
     http_client: HttpClient,
+    dedup: MaterializationDedup,
+    #[allocative(skip)]
+    throttle: MaterializationThrottle,
 }
 
 struct MaterializationStat {
@@ -137,6 +145,7 @@ pub trait IoHandler: Sized + Sync + Send + 'static {
     fn read_dir(&self, path: &AbsNormPathBuf) -> Result<ReadDir, IoError>;
     fn buck_out_path(&self) -> &ProjectRelativePathBuf;
     fn re_client_manager(&self) -> &Arc<ReConnectionManager>;
+    fn io_executor(&self) -> &Arc<dyn BlockingExecutor>;
     fn fs(&self) -> &ProjectRoot;
     fn digest_config(&self) -> DigestConfig;
 }
@@ -149,6 +158,8 @@ impl DefaultIoHandler {
         re_client_manager: Arc<ReConnectionManager>,
         io_executor: Arc<dyn BlockingExecutor>,
         http_client: HttpClient,
+        dedup: MaterializationDedup,
+        throttle: MaterializationThrottle,
     ) -> Self {
         Self {
             fs,
@@ -157,6 +168,8 @@ impl DefaultIoHandler {
             re_client_manager,
             io_executor,
             http_client,
+            dedup,
+            throttle,
         }
     }
     /// Materializes an `entry` at `path`, using the materialization `method`
@@ -183,34 +196,27 @@ impl DefaultIoHandler {
         // Materialize files
         match method.as_ref() {
             ArtifactMaterializationMethod::CasDownload { info } => {
-                let mut files = Vec::new();
-
-                {
-                    let mut walk = unordered_entry_walk(entry.as_ref());
-
-                    while let Some((entry_path, entry)) = walk.next() {
-                        if let DirectoryEntry::Leaf(ActionDirectoryMember::File(f)) = entry {
-                            let name = path.join_normalized(entry_path.get())?;
-                            let digest = maybe_tombstone_digest(f.digest.data())?.to_re();
-
-                            tracing::trace!(name = %name, digest = %digest, "push download");
-                            let name = self
-                                .fs
-                                .resolve(&name)
-                                .as_maybe_relativized_str()?
-                                .to_owned();
-
-                            files.push(NamedDigestWithPermissions {
-                                named_digest: NamedDigest {
-                                    name,
-                                    digest,
-                                    ..Default::default()
-                                },
-                                is_executable: f.is_executable,
-                                ..Default::default()
-                            });
-                        }
-                    }
+                let (downloads, duplicates) = cas_download_files(&entry, &path, self.dedup)?;
+                let mut files = Vec::with_capacity(downloads.len());
+                for (name, f) in downloads {
+                    let digest = maybe_tombstone_digest(f.digest.data())?.to_re();
+
+                    tracing::trace!(name = %name, digest = %digest, "push download");
+                    let name = self
+                        .fs
+                        .resolve(&name)
+                        .as_maybe_relativized_str()?
+                        .to_owned();
+
+                    files.push(NamedDigestWithPermissions {
+                        named_digest: NamedDigest {
+                            name,
+                            digest,
+                            ..Default::default()
+                        },
+                        is_executable: f.is_executable,
+                        ..Default::default()
+                    });
                 }
                 stat.file_count = files.len().try_into().unwrap_or_default();
                 stat.total_bytes = files
@@ -236,6 +242,20 @@ impl DefaultIoHandler {
                             )
                         })),
                     })?;
+
+                if !duplicates.is_empty() {
+                    self.io_executor
+                        .execute_io_inline(|| {
+                            for (src, dest) in &duplicates {
+                                self.dedup.duplicate_file(
+                                    &self.fs.resolve(src),
+                                    &self.fs.resolve(dest),
+                                )?;
+                            }
+                            Ok(())
+                        })
+                        .await?;
+                }
             }
             ArtifactMaterializationMethod::HttpDownload { info } => {
                 async {
@@ -286,6 +306,7 @@ impl DefaultIoHandler {
                                 a.dest_entry.as_ref(),
                                 &self.fs.root().join(&a.src),
                                 &self.fs.root().join(&a.dest),
+                                self.dedup,
                             )?;
                         }
                         Ok(())
@@ -397,32 +418,33 @@ impl IoHandler for DefaultIoHandler {
                 _ => None,
             },
         };
-        event_dispatcher
-            .span_async(materialization_start, async move {
-                let path_string = path.as_str().to_owned();
-                let mut stat = MaterializationStat {
-                    file_count: 0,
-                    total_bytes: 0,
-                };
-                let res = self
-                    .materialize_entry_span(path, method.dupe(), entry, &mut stat, cancellations)
-                    .await;
-                let error = res.as_ref().err().map(|e| format!("{:#}", e));
-
-                (
-                    res,
-                    buck2_data::MaterializationEnd {
-                        action_digest: None,
-                        file_count: stat.file_count,
-                        total_bytes: stat.total_bytes,
-                        path: path_string,
-                        success: error.is_none(),
-                        error,
-                        method: Some(method.to_proto() as i32),
-                    },
-                )
-            })
-            .await?;
+        let size_entry = entry.dupe();
+        let bytes = move || size_entry.calc_output_count_and_bytes().bytes;
+        let materialize = event_dispatcher.span_async(materialization_start, async move {
+            let path_string = path.as_str().to_owned();
+            let mut stat = MaterializationStat {
+                file_count: 0,
+                total_bytes: 0,
+            };
+            let res = self
+                .materialize_entry_span(path, method.dupe(), entry, &mut stat, cancellations)
+                .await;
+            let error = res.as_ref().err().map(|e| format!("{:#}", e));
+
+            (
+                res,
+                buck2_data::MaterializationEnd {
+                    action_digest: None,
+                    file_count: stat.file_count,
+                    total_bytes: stat.total_bytes,
+                    path: path_string,
+                    success: error.is_none(),
+                    error,
+                    method: Some(method.to_proto() as i32),
+                },
+            )
+        });
+        self.throttle.run(bytes, materialize).await?;
         Ok(())
     }
 
@@ -447,6 +469,10 @@ impl IoHandler for DefaultIoHandler {
         &self.re_client_manager
     }
 
+    fn io_executor(&self) -> &Arc<dyn BlockingExecutor> {
+        &self.io_executor
+    }
+
     fn fs(&self) -> &ProjectRoot {
         &self.fs
     }
@@ -456,6 +482,42 @@ impl IoHandler for DefaultIoHandler {
     }
 }
 
+/// The files of `entry` to download from the CAS to materialize it at `path`. Unless `dedup` is
+/// `Copy`, files with the same digest and permissions are only downloaded once: the other paths
+/// are returned as `(downloaded path, path)`, to be duplicated from it once it is downloaded.
+fn cas_download_files(
+    entry: &ActionDirectoryEntry<ActionSharedDirectory>,
+    path: &ProjectRelativePath,
+    dedup: MaterializationDedup,
+) -> anyhow::Result<(
+    Vec<(ProjectRelativePathBuf, FileMetadata)>,
+    Vec<(ProjectRelativePathBuf, ProjectRelativePathBuf)>,
+)> {
+    let mut files = Vec::new();
+    let mut duplicates = Vec::new();
+    let mut downloaded = HashMap::new();
+    let mut walk = unordered_entry_walk(entry.as_ref());
+
+    while let Some((entry_path, entry)) = walk.next() {
+        if let DirectoryEntry::Leaf(ActionDirectoryMember::File(f)) = entry {
+            let name = path.join_normalized(entry_path.get())?;
+            if dedup != MaterializationDedup::Copy {
+                match downloaded.entry((*f.digest.data(), f.is_executable)) {
+                    Entry::Occupied(src) => {
+                        duplicates.push((src.get().clone(), name));
+                        continue;
+                    }
+                    Entry::Vacant(v) => {
+                        v.insert(name.clone());
+                    }
+                }
+            }
+            files.push((name, f.dupe()));
+        }
+    }
+    Ok((files, duplicates))
+}
+
 /// This is used for testing to ingest digests (via BUCK2_TEST_TOMBSTONED_DIGESTS).
 fn maybe_tombstone_digest(digest: &FileDigest) -> anyhow::Result<&FileDigest> {
     // This has to be of size 1 since size 0 will result in the RE client just producing an empty
@@ -650,3 +712,74 @@ impl IoRequest for CleanIoRequest {
         Ok(res?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_common::file_ops::TrackedFileDigest;
+    use buck2_execute::directory::insert_file;
+    use buck2_execute::directory::ActionDirectoryBuilder;
+    use buck2_execute::directory::INTERNER;
+
+    use super::*;
+
+    fn file(content: &str, is_executable: bool) -> FileMetadata {
+        FileMetadata {
+            digest: TrackedFileDigest::from_content(
+                content.as_bytes(),
+                DigestConfig::testing_default().cas_digest_config(),
+            ),
+            is_executable,
+        }
+    }
+
+    fn path(p: &str) -> ProjectRelativePathBuf {
+        ProjectRelativePathBuf::unchecked_new(p.to_owned())
+    }
+
+    fn entry() -> anyhow::Result<ActionDirectoryEntry<ActionSharedDirectory>> {
+        let mut dir = ActionDirectoryBuilder::empty();
+        insert_file(&mut dir, &path("a"), file("foo", false))?;
+        insert_file(&mut dir, &path("b/c"), file("foo", false))?;
+        insert_file(&mut dir, &path("b/d"), file("foo", true))?;
+        insert_file(&mut dir, &path("e"), file("bar", false))?;
+        Ok(ActionDirectoryEntry::Dir(
+            dir.fingerprint(DigestConfig::testing_default().as_directory_serializer())
+                .shared(&*INTERNER),
+        ))
+    }
+
+    #[test]
+    fn test_cas_download_files_copy() -> anyhow::Result<()> {
+        let (mut files, duplicates) =
+            cas_download_files(&entry()?, &path("out"), MaterializationDedup::Copy)?;
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            vec![
+                path("out/a"),
+                path("out/b/c"),
+                path("out/b/d"),
+                path("out/e")
+            ],
+            files.into_iter().map(|(p, _)| p).collect::<Vec<_>>()
+        );
+        assert!(duplicates.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cas_download_files_dedup() -> anyhow::Result<()> {
+        let (files, duplicates) =
+            cas_download_files(&entry()?, &path("out"), MaterializationDedup::Reflink)?;
+        // `b/d` has the same contents as `a` and `b/c`, but is executable.
+        assert_eq!(3, files.len());
+        assert_eq!(1, duplicates.len());
+        let (src, dest) = &duplicates[0];
+        let mut pair = vec![src.clone(), dest.clone()];
+        pair.sort();
+        assert_eq!(vec![path("out/a"), path("out/b/c")], pair);
+        // The duplicate is not downloaded, its source is.
+        assert!(files.iter().any(|(p, _)| p == src));
+        assert!(!files.iter().any(|(p, _)| p == dest));
+        Ok(())
+    }
+}
@@ -58,6 +58,22 @@ fn test_find_artifacts() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_write_cache() {
+    let cache = WriteCache::default();
+    let meta = FileMetadata::empty(DigestConfig::testing_default().cas_digest_config());
+    let write = Arc::new(WriteFile {
+        compressed_data: Box::new([]),
+        decompressed_size: 0,
+        is_executable: false,
+    });
+    assert!(cache.get(&meta).is_none());
+    cache.insert(meta.dupe(), &write);
+    assert!(Arc::ptr_eq(&cache.get(&meta).unwrap(), &write));
+    drop(write);
+    assert!(cache.get(&meta).is_none());
+}
+
 #[test]
 fn test_remove_path() {
     fn insert(tree: &mut FileTree<String>, path: &str) {
@@ -108,6 +124,7 @@ mod state_machine {
     use super::*;
     use crate::materializers::deferred::clean_stale::CleanInvalidatedPathRequest;
     use crate::materializers::deferred::subscriptions::SubscriptionHandle;
+    use crate::materializers::deferred::verify::repair_corrupt_artifacts;
     use crate::materializers::sqlite::testing_materializer_state_sqlite_db;
 
     #[derive(Debug, Eq, PartialEq, Allocative)]
@@ -326,6 +343,10 @@ mod state_machine {
             unimplemented!()
         }
 
+        fn io_executor(&self) -> &Arc<dyn BlockingExecutor> {
+            unimplemented!()
+        }
+
         fn fs(&self) -> &ProjectRoot {
             &self.fs
         }
@@ -517,6 +538,7 @@ mod state_machine {
                 },
                 stats: Arc::new(DeferredMaterializerStats::default()),
                 verbose_materializer_log: true,
+                write_cache: WriteCache::default(),
             },
             handle,
             daemon_dispatcher_events,
@@ -581,6 +603,57 @@ mod state_machine {
         .await
     }
 
+    #[tokio::test]
+    async fn test_materialize_member_of_directory() -> anyhow::Result<()> {
+        ignore_stack_overflow_checks_for_future(async {
+            let (mut dm, _) = make_processor(Default::default());
+            let digest_config = dm.io.digest_config();
+
+            let file = FileMetadata::empty(digest_config.cas_digest_config());
+            let mut dir = ActionDirectoryBuilder::empty();
+            insert_file(&mut dir, &make_path("a"), file.dupe())?;
+            insert_file(&mut dir, &make_path("b/c"), file.dupe())?;
+            let value = ArtifactValue::new(
+                ActionDirectoryEntry::Dir(
+                    dir.fingerprint(digest_config.as_directory_serializer())
+                        .shared(&*INTERNER),
+                ),
+                None,
+            );
+
+            let path = make_path("foo/dir");
+            dm.declare(&path, value, Box::new(ArtifactMaterializationMethod::Test));
+            assert_eq!(dm.io.take_log(), &[(Op::Clean, path.clone())]);
+
+            // Only the member is materialized.
+            let member = make_path("foo/dir/b/c");
+            let res = dm
+                .materialize_artifact(&member, EventDispatcher::null())
+                .context("Expected a future")?
+                .await;
+            assert_eq!(dm.io.take_log(), &[(Op::Materialize, member.clone())]);
+            dm.materialization_finished(
+                member.clone(),
+                Utc::now(),
+                dm.version_tracker.current(),
+                res,
+            );
+
+            // The rest of the directory is materialized when the directory is.
+            dm.materialize_artifact(&path, EventDispatcher::null())
+                .context("Expected a future")?
+                .await
+                .map_err(|_| anyhow::anyhow!("error materializing"))?;
+            assert_eq!(
+                dm.io.take_log(),
+                &[(Op::Materialize, make_path("foo/dir/a"))]
+            );
+
+            Ok(())
+        })
+        .await
+    }
+
     fn make_artifact_value_with_symlink_dep(
         target_path: &ProjectRelativePathBuf,
         target_from_symlink: &RelativePathBuf,
@@ -1110,7 +1183,7 @@ mod state_machine {
             let (dm, _, _) = make_materializer(io, None).await;
 
             let res = dm
-                .clean_stale_artifacts(DateTime::<Utc>::MAX_UTC, false, false)
+                .clean_stale_artifacts(DateTime::<Utc>::MAX_UTC, None, false, false)
                 .await?;
 
             let &buck2_data::CleanStaleStats {
@@ -1157,7 +1230,7 @@ mod state_machine {
             // Interrupt while scanning buck-out
             let dm = Arc::new(dm);
             let dm_dup = dm.dupe();
-            let fut = dm_dup.clean_stale_artifacts(DateTime::<Utc>::MAX_UTC, false, false);
+            let fut = dm_dup.clean_stale_artifacts(DateTime::<Utc>::MAX_UTC, None, false, false);
             thread::spawn(move || {
                 // Wait until a read_dir request is about to execute
                 read_dir_barriers.0.wait();
@@ -1194,7 +1267,7 @@ mod state_machine {
             // Interrupt while deleting files
             let dm = Arc::new(dm);
             let dm_dup = dm.dupe();
-            let fut = dm_dup.clean_stale_artifacts(DateTime::<Utc>::MAX_UTC, false, false);
+            let fut = dm_dup.clean_stale_artifacts(DateTime::<Utc>::MAX_UTC, None, false, false);
             thread::spawn(move || {
                 // Wait until a single clean request is about to execute
                 clean_barriers.0.wait();
@@ -1238,6 +1311,7 @@ mod state_machine {
                 clean_period: std::time::Duration::from_secs(1),
                 artifact_ttl: std::time::Duration::from_secs(0),
                 start_offset: std::time::Duration::from_secs(0),
+                max_size: None,
                 dry_run: true,
             };
             let io = Arc::new(StubIoHandler::new(project_root.dupe()));
@@ -1316,6 +1390,94 @@ mod state_machine {
         })
         .await
     }
+
+    fn done_version(
+        dm: &DeferredMaterializerCommandProcessor<StubIoHandler>,
+        path: &ProjectRelativePath,
+    ) -> Version {
+        match &dm.tree.prefix_get(&mut path.iter()).unwrap().processing {
+            Processing::Done(version) => *version,
+            Processing::Active { .. } => panic!("`{}` is being processed", path),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repair_corrupt_artifacts() -> anyhow::Result<()> {
+        ignore_stack_overflow_checks_for_future(async {
+            let (mut dm, _) = make_processor(Default::default());
+            let digest_config = dm.io.digest_config();
+            let value = ArtifactValue::file(digest_config.empty_file());
+
+            // Declared by this daemon.
+            let active = make_path("foo/active");
+            dm.io.fs().write_file(&active, "corrupt", false)?;
+            dm.declare_existing(&active, value.dupe());
+
+            // Materialized by a previous daemon.
+            let previous = make_path("foo/previous");
+            dm.io.fs().write_file(&previous, "corrupt", false)?;
+            dm.tree.insert(
+                previous.iter().map(|f| f.to_owned()),
+                Box::new(ArtifactMaterializationData {
+                    deps: None,
+                    stage: ArtifactMaterializationStage::Materialized {
+                        metadata: ArtifactMetadata::new(value.entry()),
+                        last_access_time: Utc::now(),
+                        active: false,
+                    },
+                    processing: Processing::Done(Version(0)),
+                }),
+            );
+            dm.io.take_log();
+
+            let artifacts = [&active, &previous]
+                .into_iter()
+                .map(|path| (path.clone(), done_version(&dm, path)))
+                .collect();
+            let repairs = repair_corrupt_artifacts(&mut dm, artifacts)?;
+
+            // Both are forgotten and deleted, so they are materialized again the next time they
+            // are declared.
+            assert_eq!(repairs.removed, vec![previous.clone()]);
+            assert_eq!(repairs.removed_in_use, vec![active.clone()]);
+            for path in [&active, &previous] {
+                assert!(dm.tree.prefix_get(&mut path.iter()).is_none());
+                assert!(!fs_util::try_exists(dm.io.fs().resolve(path))?);
+            }
+            // Nothing is materialized right away.
+            assert_eq!(dm.io.take_log(), &[]);
+
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_repair_skips_redeclared_artifacts() -> anyhow::Result<()> {
+        ignore_stack_overflow_checks_for_future(async {
+            let (mut dm, _) = make_processor(Default::default());
+            let digest_config = dm.io.digest_config();
+
+            let path = make_path("foo/bar");
+            dm.declare_existing(&path, ArtifactValue::file(digest_config.empty_file()));
+            let version = done_version(&dm, &path);
+            // Declared again after it was hashed.
+            dm.declare(
+                &path,
+                ArtifactValue::dir(digest_config.empty_directory()),
+                Box::new(ArtifactMaterializationMethod::Test),
+            );
+            dm.io.take_log();
+
+            let repairs = repair_corrupt_artifacts(&mut dm, vec![(path.clone(), version)])?;
+            assert!(repairs.removed.is_empty());
+            assert!(repairs.removed_in_use.is_empty());
+            assert_eq!(dm.io.take_log(), &[]);
+
+            Ok(())
+        })
+        .await
+    }
 }
 
 #[test]
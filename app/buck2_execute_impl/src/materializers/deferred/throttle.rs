@@ -0,0 +1,193 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Limits on how much the deferred materializer writes to disk at once, so that materializing
+//! thousands of artifacts doesn't saturate the disk buck-out is on.
+
+use std::future::Future;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use buck2_common::legacy_configs::configs::LegacyBuckConfig;
+use buck2_common::legacy_configs::key::BuckconfigKeyRef;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use parking_lot::Mutex;
+use tokio::sync::Semaphore;
+use tokio::time::Instant;
+
+use crate::materializers::deferred::DeferredMaterializerStats;
+
+pub struct MaterializationThrottleConfig {
+    /// Maximum number of artifacts materialized at once.
+    pub max_concurrency: Option<usize>,
+    /// Maximum rate at which artifacts are materialized, on average.
+    pub max_bytes_per_sec: Option<u64>,
+}
+
+impl MaterializationThrottleConfig {
+    /// Network filesystems are usually much slower than local disks, so they have separate limits,
+    /// which default to the ones for local disks.
+    pub fn from_buck_config(
+        root_config: &LegacyBuckConfig,
+        fs: &ProjectRoot,
+        buck_out_path: &ProjectRelativePath,
+    ) -> anyhow::Result<Self> {
+        let parse = |property| {
+            root_config.parse::<u64>(BuckconfigKeyRef {
+                section: "buck2",
+                property,
+            })
+        };
+        let max_concurrency = parse("materializer_max_concurrency")?;
+        let max_bytes_per_sec = parse("materializer_max_bytes_per_sec")?;
+
+        let (max_concurrency, max_bytes_per_sec) = if is_network_filesystem(fs, buck_out_path) {
+            (
+                parse("materializer_max_concurrency_network_fs")?.or(max_concurrency),
+                parse("materializer_max_bytes_per_sec_network_fs")?.or(max_bytes_per_sec),
+            )
+        } else {
+            (max_concurrency, max_bytes_per_sec)
+        };
+
+        // `0` means unlimited.
+        Ok(Self {
+            max_concurrency: max_concurrency.filter(|v| *v > 0).map(|v| v as usize),
+            max_bytes_per_sec: max_bytes_per_sec.filter(|v| *v > 0),
+        })
+    }
+}
+
+/// buck-out doesn't exist before the first build, in which case it's on the same filesystem as the
+/// project.
+fn is_network_filesystem(fs: &ProjectRoot, buck_out_path: &ProjectRelativePath) -> bool {
+    let buck_out = fs.resolve(buck_out_path);
+    let path = match fs_util::try_exists(&buck_out) {
+        Ok(true) => buck_out.as_abs_path(),
+        _ => fs.root().as_abs_path(),
+    };
+    fs_util::is_network_filesystem(path).unwrap_or_else(|e| {
+        tracing::debug!("Assuming buck-out is on a local disk: {:#}", e);
+        false
+    })
+}
+
+pub(super) struct MaterializationThrottle {
+    concurrency: Option<Semaphore>,
+    rate: Option<RateLimiter>,
+    stats: Arc<DeferredMaterializerStats>,
+}
+
+impl MaterializationThrottle {
+    pub(super) fn new(
+        config: &MaterializationThrottleConfig,
+        stats: Arc<DeferredMaterializerStats>,
+    ) -> Self {
+        Self {
+            concurrency: config.max_concurrency.map(Semaphore::new),
+            rate: config.max_bytes_per_sec.map(RateLimiter::new),
+            stats,
+        }
+    }
+
+    /// Runs `materialize` once the limits allow materializing `bytes` more. Computing the size
+    /// walks the whole entry, so it is only done when there is a rate limit.
+    pub(super) async fn run<F: Future>(
+        &self,
+        bytes: impl FnOnce() -> u64,
+        materialize: F,
+    ) -> F::Output {
+        let (_permit, _in_flight) = {
+            let _waiting = CounterGuard::new(&self.stats.materializations_waiting);
+            let permit = match &self.concurrency {
+                Some(semaphore) => Some(
+                    semaphore
+                        .acquire()
+                        .await
+                        .expect("Materialization semaphore is never closed"),
+                ),
+                None => None,
+            };
+            if let Some(rate) = &self.rate {
+                rate.wait(bytes()).await;
+            }
+            (
+                permit,
+                CounterGuard::new(&self.stats.materializations_in_flight),
+            )
+        };
+        materialize.await
+    }
+}
+
+/// Spaces materializations out so that, on average, no more than `bytes_per_sec` are
+/// materialized. A materialization never waits for itself, only for the ones before it.
+struct RateLimiter {
+    bytes_per_sec: u64,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    async fn wait(&self, bytes: u64) {
+        let start = {
+            let mut next = self.next.lock();
+            let start = std::cmp::max(*next, Instant::now());
+            *next = start + Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+            start
+        };
+        tokio::time::sleep_until(start).await;
+    }
+}
+
+/// Counts the futures in a state, including those that get cancelled in it.
+struct CounterGuard<'a>(&'a AtomicU64);
+
+impl<'a> CounterGuard<'a> {
+    fn new(counter: &'a AtomicU64) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Self(counter)
+    }
+}
+
+impl Drop for CounterGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limiter_spaces_out_materializations() {
+        let limiter = RateLimiter::new(100);
+        let start = Instant::now();
+
+        limiter.wait(200).await;
+        assert_eq!(start, Instant::now());
+
+        limiter.wait(50).await;
+        assert_eq!(start + Duration::from_secs(2), Instant::now());
+
+        limiter.wait(0).await;
+        assert_eq!(start + Duration::from_millis(2500), Instant::now());
+    }
+}
@@ -0,0 +1,244 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Re-hashing of materialized artifacts, to find the ones that were corrupted or modified behind
+//! the materializer's back.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use buck2_common::file_ops::FileDigestConfig;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_execute::entry::build_entry_from_disk;
+use buck2_execute::execute::clean_output_paths::cleanup_path;
+use buck2_execute::materialize::materializer::CorruptArtifact;
+use buck2_execute::materialize::materializer::VerifyBuckOutReport;
+use derivative::Derivative;
+use derive_more::Display;
+use dupe::Dupe;
+use futures::StreamExt;
+use tokio::sync::oneshot;
+use tokio::sync::oneshot::Sender;
+
+use crate::materializers::deferred::extension::ExtensionCommand;
+use crate::materializers::deferred::io_handler::IoHandler;
+use crate::materializers::deferred::ArtifactMaterializationStage;
+use crate::materializers::deferred::ArtifactMetadata;
+use crate::materializers::deferred::DeferredMaterializerCommandProcessor;
+use crate::materializers::deferred::MaterializerCommand;
+use crate::materializers::deferred::MaterializerSender;
+use crate::materializers::deferred::Processing;
+use crate::materializers::deferred::Version;
+
+/// How many artifacts we hash concurrently.
+const CONCURRENT_CHECKS: usize = 64;
+
+struct MaterializedArtifact {
+    path: ProjectRelativePathBuf,
+    metadata: ArtifactMetadata,
+    version: Version,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct ListMaterializedArtifacts {
+    #[derivative(Debug = "ignore")]
+    sender: Sender<Vec<MaterializedArtifact>>,
+}
+
+impl<T: IoHandler> ExtensionCommand<T> for ListMaterializedArtifacts {
+    fn execute(self: Box<Self>, processor: &mut DeferredMaterializerCommandProcessor<T>) {
+        let artifacts = processor
+            .tree
+            .iter_with_paths()
+            .filter_map(|(path, data)| match (&data.stage, &data.processing) {
+                // Artifacts that are being materialized or cleaned are skipped: their contents
+                // are expected to change.
+                (
+                    ArtifactMaterializationStage::Materialized { metadata, .. },
+                    Processing::Done(version),
+                ) => Some(MaterializedArtifact {
+                    path: ProjectRelativePathBuf::from(path),
+                    metadata: metadata.dupe(),
+                    version: *version,
+                }),
+                _ => None,
+            })
+            .collect();
+        let _ignored = self.sender.send(artifacts);
+    }
+}
+
+/// The corrupt artifacts that were deleted and forgotten.
+#[derive(Default)]
+pub(super) struct Repairs {
+    /// The artifacts that are materialized again (or rebuilt) the next time they are declared.
+    pub(super) removed: Vec<ProjectRelativePathBuf>,
+    /// The artifacts declared by the running daemon, whose build graph still depends on them and
+    /// won't declare them again until the daemon restarts.
+    pub(super) removed_in_use: Vec<ProjectRelativePathBuf>,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct RepairCorruptArtifacts {
+    /// The artifacts to repair, with the version at which they were found to be corrupt.
+    artifacts: Vec<(ProjectRelativePathBuf, Version)>,
+    #[derivative(Debug = "ignore")]
+    sender: Sender<anyhow::Result<Repairs>>,
+}
+
+impl<T: IoHandler> ExtensionCommand<T> for RepairCorruptArtifacts {
+    fn execute(self: Box<Self>, processor: &mut DeferredMaterializerCommandProcessor<T>) {
+        let res = repair_corrupt_artifacts(processor, self.artifacts);
+        let _ignored = self.sender.send(res);
+    }
+}
+
+pub(super) fn repair_corrupt_artifacts<T: IoHandler>(
+    processor: &mut DeferredMaterializerCommandProcessor<T>,
+    artifacts: Vec<(ProjectRelativePathBuf, Version)>,
+) -> anyhow::Result<Repairs> {
+    // Artifacts that were declared or materialized again since we hashed them are left alone.
+    // The others are forgotten, so that they are materialized again (e.g. downloaded from the
+    // CAS) or rebuilt the next time they are declared, which for the artifacts declared by this
+    // daemon is after it restarts.
+    let mut repairs = Repairs::default();
+    for (path, version) in artifacts {
+        let Some(data) = processor.tree.prefix_get(&mut path.iter()) else {
+            continue;
+        };
+        match (&data.stage, &data.processing) {
+            (ArtifactMaterializationStage::Materialized { active, .. }, Processing::Done(v))
+                if *v == version =>
+            {
+                if *active {
+                    repairs.removed_in_use.push(path);
+                } else {
+                    repairs.removed.push(path);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Always invalidate materializer state before deleting from the filesystem, see `declare`.
+    // We block the command thread while deleting, like `Fsck` does, so that nothing gets declared
+    // at these paths in the meantime. There are normally few corrupt artifacts.
+    let paths: Vec<_> = repairs
+        .removed
+        .iter()
+        .chain(&repairs.removed_in_use)
+        .cloned()
+        .collect();
+    processor
+        .tree
+        .invalidate_paths_and_collect_futures(paths.clone(), processor.sqlite_db.as_mut())?;
+    for path in &paths {
+        cleanup_path(processor.io.fs(), path)
+            .with_context(|| format!("Error deleting corrupt artifact `{}`", path))?;
+    }
+
+    Ok(repairs)
+}
+
+#[derive(Debug, Display)]
+enum Corruption {
+    #[display(fmt = "missing")]
+    Missing,
+    #[display(fmt = "contents differ from the declared digest")]
+    Modified,
+    #[display(fmt = "unreadable: {:#}", _0)]
+    Unreadable(anyhow::Error),
+}
+
+async fn check_artifact<T: IoHandler>(
+    io: &T,
+    artifact: &MaterializedArtifact,
+) -> Option<Corruption> {
+    let digest_config = io.digest_config();
+    let entry = build_entry_from_disk(
+        io.fs().resolve(&artifact.path),
+        FileDigestConfig::build(digest_config.cas_digest_config()),
+        &**io.io_executor(),
+        io.fs().root(),
+    )
+    .await;
+    match entry {
+        Err(e) => Some(Corruption::Unreadable(e)),
+        Ok((None, _)) => Some(Corruption::Missing),
+        Ok((Some(entry), _)) => {
+            let entry = entry.map_dir(|d| d.fingerprint(digest_config.as_directory_serializer()));
+            if artifact.metadata.matches_entry(&entry) {
+                None
+            } else {
+                Some(Corruption::Modified)
+            }
+        }
+    }
+}
+
+pub(super) async fn verify_buck_out<T: IoHandler>(
+    command_sender: &MaterializerSender<T>,
+    io: &Arc<T>,
+    repair: bool,
+) -> anyhow::Result<VerifyBuckOutReport> {
+    let (sender, receiver) = oneshot::channel();
+    command_sender.send(MaterializerCommand::Extension(
+        Box::new(ListMaterializedArtifacts { sender }) as _,
+    ))?;
+    let artifacts = receiver.await.context("No response from materializer")?;
+
+    let checked = artifacts.len() as u64;
+    let mut corrupt: Vec<(MaterializedArtifact, Corruption)> = futures::stream::iter(artifacts)
+        .map(|artifact| async move {
+            let corruption = check_artifact(&**io, &artifact).await;
+            corruption.map(|c| (artifact, c))
+        })
+        .buffer_unordered(CONCURRENT_CHECKS)
+        .filter_map(futures::future::ready)
+        .collect()
+        .await;
+    corrupt.sort_by(|(a, _), (b, _)| a.path.cmp(&b.path));
+
+    let repairs = if repair && !corrupt.is_empty() {
+        let (sender, receiver) = oneshot::channel();
+        command_sender.send(MaterializerCommand::Extension(
+            Box::new(RepairCorruptArtifacts {
+                artifacts: corrupt
+                    .iter()
+                    .map(|(artifact, _)| (artifact.path.clone(), artifact.version))
+                    .collect(),
+                sender,
+            }) as _,
+        ))?;
+        receiver.await.context("No response from materializer")??
+    } else {
+        Repairs::default()
+    };
+    let repaired: HashSet<_> = repairs.removed.into_iter().collect();
+    let in_use: HashSet<_> = repairs.removed_in_use.into_iter().collect();
+
+    Ok(VerifyBuckOutReport {
+        checked,
+        corrupt: corrupt
+            .into_iter()
+            .map(|(artifact, corruption)| CorruptArtifact {
+                repaired: repaired.contains(&artifact.path),
+                reason: if in_use.contains(&artifact.path) {
+                    format!("{}, deleted but still used by this daemon", corruption)
+                } else {
+                    corruption.to_string()
+                },
+                path: artifact.path,
+            })
+            .collect(),
+    })
+}
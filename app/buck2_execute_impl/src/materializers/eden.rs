@@ -0,0 +1,215 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::sync::Arc;
+
+use allocative::Allocative;
+use anyhow::Context as _;
+use async_trait::async_trait;
+use buck2_core::buck2_env;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_execute::artifact_value::ArtifactValue;
+use buck2_execute::digest_config::DigestConfig;
+use buck2_execute::execute::blocking::BlockingExecutor;
+use buck2_execute::materialize::materializer::ArtifactNotMaterializedReason;
+use buck2_execute::materialize::materializer::CasDownloadInfo;
+use buck2_execute::materialize::materializer::CopiedArtifact;
+use buck2_execute::materialize::materializer::DeclareMatchOutcome;
+use buck2_execute::materialize::materializer::HttpDownloadInfo;
+use buck2_execute::materialize::materializer::MaterializationError;
+use buck2_execute::materialize::materializer::Materializer;
+use buck2_execute::materialize::materializer::WriteRequest;
+use buck2_execute::re::manager::ReConnectionManager;
+use buck2_futures::cancellation::CancellationContext;
+use buck2_http::HttpClient;
+use dupe::Dupe;
+use fbinit::FacebookInit;
+use futures::future::FutureExt;
+use futures::stream;
+use futures::stream::BoxStream;
+use futures::stream::StreamExt;
+use gazebo::prelude::*;
+use tokio::sync::Semaphore;
+
+use crate::materializers::eden_api::EdenBuckOut;
+use crate::materializers::immediate::cas_download;
+use crate::materializers::immediate::ImmediateMaterializer;
+
+/// Materializer for a buck-out that is an Eden mount backed by the RE CAS. Artifacts downloaded
+/// from the CAS are only declared to Eden, which fetches them the first time they are read.
+/// Everything else is written to buck-out immediately, which Eden keeps in its overlay.
+#[derive(Allocative)]
+pub struct EdenMaterializer {
+    fs: ProjectRoot,
+    re_client_manager: Arc<ReConnectionManager>,
+    io_executor: Arc<dyn BlockingExecutor>,
+    delegator: ImmediateMaterializer,
+    eden_buck_out: EdenBuckOut,
+}
+
+impl EdenMaterializer {
+    pub fn new(
+        fb: FacebookInit,
+        fs: ProjectRoot,
+        digest_config: DigestConfig,
+        buck_out_path: ProjectRelativePathBuf,
+        re_client_manager: Arc<ReConnectionManager>,
+        io_executor: Arc<dyn BlockingExecutor>,
+        http_client: HttpClient,
+    ) -> anyhow::Result<Self> {
+        let eden_semaphore =
+            buck2_env!("BUCK2_EDEN_SEMAPHORE", type=usize, default=2048, applicability=internal)?;
+
+        let eden_buck_out = EdenBuckOut::new(
+            fb,
+            &fs,
+            buck_out_path.clone(),
+            Semaphore::new(eden_semaphore),
+        )?
+        .with_context(|| {
+            format!(
+                "`[buck2] materializations = eden` requires `{}` to be the root of an Eden mount",
+                fs.resolve(&buck_out_path)
+            )
+        })?;
+
+        let delegator = ImmediateMaterializer::new(
+            fs.dupe(),
+            digest_config,
+            re_client_manager.dupe(),
+            io_executor.dupe(),
+            http_client,
+        );
+
+        Ok(Self {
+            fs,
+            re_client_manager,
+            io_executor,
+            delegator,
+            eden_buck_out,
+        })
+    }
+}
+
+#[async_trait]
+impl Materializer for EdenMaterializer {
+    fn name(&self) -> &str {
+        "eden"
+    }
+
+    async fn declare_existing(
+        &self,
+        artifacts: Vec<(ProjectRelativePathBuf, ArtifactValue)>,
+    ) -> anyhow::Result<()> {
+        self.delegator.declare_existing(artifacts).await
+    }
+
+    async fn declare_copy_impl(
+        &self,
+        path: ProjectRelativePathBuf,
+        value: ArtifactValue,
+        srcs: Vec<CopiedArtifact>,
+        cancellations: &CancellationContext,
+    ) -> anyhow::Result<()> {
+        self.delegator
+            .declare_copy_impl(path, value, srcs, cancellations)
+            .await
+    }
+
+    async fn declare_cas_many_impl<'a, 'b>(
+        &self,
+        info: Arc<CasDownloadInfo>,
+        artifacts: Vec<(ProjectRelativePathBuf, ArtifactValue)>,
+        cancellations: &CancellationContext,
+    ) -> anyhow::Result<()> {
+        let results = futures::future::try_join_all(artifacts.iter().map(|(path, value)| {
+            self.eden_buck_out
+                .set_path_object_id(path, value)
+                .map(|res| res.map(|declared| (path, value, declared)))
+        }))
+        .await?;
+
+        // Eden can't be pointed at artifacts that don't have a CAS object of their own, so we
+        // write those out ourselves.
+        let rest: Vec<_> = results
+            .into_iter()
+            .filter(|(_, _, declared)| !declared)
+            .map(|(path, value, _)| (path.clone(), value.dupe()))
+            .collect();
+        if rest.is_empty() {
+            return Ok(());
+        }
+
+        cas_download(
+            &self.fs,
+            self.io_executor.as_ref(),
+            self.re_client_manager.as_ref(),
+            &info,
+            rest,
+            cancellations,
+        )
+        .await
+    }
+
+    async fn declare_http(
+        &self,
+        path: ProjectRelativePathBuf,
+        info: HttpDownloadInfo,
+        cancellations: &CancellationContext,
+    ) -> anyhow::Result<()> {
+        self.delegator.declare_http(path, info, cancellations).await
+    }
+
+    async fn declare_write<'a>(
+        &self,
+        gen: Box<dyn FnOnce() -> anyhow::Result<Vec<WriteRequest>> + Send + 'a>,
+    ) -> anyhow::Result<Vec<ArtifactValue>> {
+        self.delegator.declare_write(gen).await
+    }
+
+    async fn declare_match(
+        &self,
+        artifacts: Vec<(ProjectRelativePathBuf, ArtifactValue)>,
+    ) -> anyhow::Result<DeclareMatchOutcome> {
+        self.delegator.declare_match(artifacts).await
+    }
+
+    async fn has_artifact_at(&self, path: ProjectRelativePathBuf) -> anyhow::Result<bool> {
+        self.delegator.has_artifact_at(path).await
+    }
+
+    async fn invalidate_many(&self, paths: Vec<ProjectRelativePathBuf>) -> anyhow::Result<()> {
+        // Declarations replace whatever is at their path, so like the immediate materializer we
+        // don't need to do anything here.
+        self.delegator.invalidate_many(paths).await
+    }
+
+    async fn materialize_many(
+        &self,
+        artifact_paths: Vec<ProjectRelativePathBuf>,
+    ) -> anyhow::Result<BoxStream<'static, Result<(), MaterializationError>>> {
+        // Eden makes every declared artifact available on disk, fetching it on first read.
+        Ok(stream::iter(artifact_paths.into_iter().map(|_| Ok(()))).boxed())
+    }
+
+    async fn try_materialize_final_artifact(
+        &self,
+        _artifact_path: ProjectRelativePathBuf,
+    ) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+
+    async fn get_materialized_file_paths(
+        &self,
+        paths: Vec<ProjectRelativePathBuf>,
+    ) -> anyhow::Result<Vec<Result<ProjectRelativePathBuf, ArtifactNotMaterializedReason>>> {
+        Ok(paths.into_map(Ok))
+    }
+}
@@ -0,0 +1,92 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Thin wrapper around the Eden Thrift API for a buck-out that is an Eden mount backed by the RE
+//! CAS.
+
+use allocative::Allocative;
+use anyhow::Context as _;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_eden::connection::EdenConnectionManager;
+use buck2_execute::artifact_value::ArtifactValue;
+use edenfs::CheckoutMode;
+use edenfs::ObjectType;
+use edenfs::SetPathObjectIdParams;
+use fbinit::FacebookInit;
+use tokio::sync::Semaphore;
+
+use crate::materializers::eden_object::eden_object;
+use crate::materializers::eden_object::mount_relative_path;
+use crate::materializers::eden_object::EdenObjectType;
+
+#[derive(Allocative)]
+pub struct EdenBuckOut {
+    buck_out_path: ProjectRelativePathBuf,
+    connection_manager: EdenConnectionManager,
+}
+
+impl EdenBuckOut {
+    /// Returns `None` if buck-out is not the root of an Eden mount.
+    pub fn new(
+        fb: FacebookInit,
+        fs: &ProjectRoot,
+        buck_out_path: ProjectRelativePathBuf,
+        semaphore: Semaphore,
+    ) -> anyhow::Result<Option<Self>> {
+        let mount = fs.resolve(&buck_out_path);
+        let connection_manager = match EdenConnectionManager::new(fb, &mount, semaphore)? {
+            Some(connection_manager) => connection_manager,
+            None => return Ok(None),
+        };
+        Ok(Some(Self {
+            buck_out_path,
+            connection_manager,
+        }))
+    }
+
+    /// Point `path` at the CAS object `value` was declared with, so that Eden fetches it the first
+    /// time it is read. Returns `false` if the value cannot be represented as a CAS object, in
+    /// which case nothing was done.
+    pub async fn set_path_object_id(
+        &self,
+        path: &ProjectRelativePath,
+        value: &ArtifactValue,
+    ) -> anyhow::Result<bool> {
+        let Some((object_id, object_type)) = eden_object(value) else {
+            return Ok(false);
+        };
+        let object_type = match object_type {
+            EdenObjectType::Tree => ObjectType::TREE,
+            EdenObjectType::RegularFile => ObjectType::REGULAR_FILE,
+            EdenObjectType::ExecutableFile => ObjectType::EXECUTABLE_FILE,
+        };
+
+        let params = SetPathObjectIdParams {
+            mountPoint: self.connection_manager.get_mount_point(),
+            path: mount_relative_path(&self.buck_out_path, path)?,
+            objectId: object_id,
+            type_: object_type,
+            // Anything already at this path is an output of a previous build: replace it.
+            mode: CheckoutMode::FORCE,
+            ..Default::default()
+        };
+
+        self.connection_manager
+            .with_eden(|eden| {
+                tracing::trace!("setPathObjectId({})", path);
+                eden.setPathObjectId(&params)
+            })
+            .await
+            .with_context(|| format!("Error setting Eden object id for `{}`", path))?;
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,132 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! How artifacts are declared to an Eden mount backed by the RE CAS. This doesn't depend on the
+//! Eden Thrift bindings, which are only available in fbcode builds.
+
+#![cfg_attr(not(fbcode_build), allow(dead_code))]
+
+use anyhow::Context as _;
+use buck2_core::directory::DirectoryEntry;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_execute::artifact_value::ArtifactValue;
+use buck2_execute::digest::CasDigestToReExt;
+use buck2_execute::directory::ActionDirectoryMember;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EdenObjectType {
+    Tree,
+    RegularFile,
+    ExecutableFile,
+}
+
+/// The object id and type Eden should fetch `value` with, or `None` if the value has no CAS
+/// object of its own.
+pub(crate) fn eden_object(value: &ArtifactValue) -> Option<(Vec<u8>, EdenObjectType)> {
+    let (digest, object_type) = match value.entry() {
+        DirectoryEntry::Dir(d) => (d.fingerprint().to_re(), EdenObjectType::Tree),
+        DirectoryEntry::Leaf(ActionDirectoryMember::File(f)) => (
+            f.digest.to_re(),
+            if f.is_executable {
+                EdenObjectType::ExecutableFile
+            } else {
+                EdenObjectType::RegularFile
+            },
+        ),
+        // Symlinks have no CAS object of their own.
+        DirectoryEntry::Leaf(
+            ActionDirectoryMember::Symlink(..) | ActionDirectoryMember::ExternalSymlink(..),
+        ) => return None,
+    };
+    // The RE backing store identifies objects by `hash:size`.
+    let object_id = format!("{}:{}", digest.hash, digest.size_in_bytes).into_bytes();
+    Some((object_id, object_type))
+}
+
+/// `path` relative to the root of the mount at `buck_out_path`.
+pub(crate) fn mount_relative_path(
+    buck_out_path: &ProjectRelativePath,
+    path: &ProjectRelativePath,
+) -> anyhow::Result<Vec<u8>> {
+    let path = path.strip_prefix(buck_out_path).with_context(|| {
+        format!(
+            "Path `{}` is not in buck-out (`{}`). This is a bug in Buck, not a user error.",
+            path, buck_out_path
+        )
+    })?;
+    Ok(path.as_str().as_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_common::file_ops::FileMetadata;
+    use buck2_common::file_ops::TrackedFileDigest;
+    use buck2_execute::digest_config::DigestConfig;
+    use buck2_execute::directory::new_symlink;
+
+    use super::*;
+
+    #[test]
+    fn test_eden_object_file() {
+        let digest_config = DigestConfig::testing_default();
+        let digest = TrackedFileDigest::from_content(b"foo", digest_config.cas_digest_config());
+        let expected_id = format!("{}:3", digest.raw_digest()).into_bytes();
+        for (is_executable, object_type) in [
+            (false, EdenObjectType::RegularFile),
+            (true, EdenObjectType::ExecutableFile),
+        ] {
+            let value = ArtifactValue::file(FileMetadata {
+                digest: digest.clone(),
+                is_executable,
+            });
+            assert_eq!(
+                Some((expected_id.clone(), object_type)),
+                eden_object(&value)
+            );
+        }
+    }
+
+    #[test]
+    fn test_eden_object_dir() {
+        let digest_config = DigestConfig::testing_default();
+        let dir = digest_config.empty_directory();
+        let expected_id = format!(
+            "{}:{}",
+            dir.fingerprint().raw_digest(),
+            dir.fingerprint().size()
+        )
+        .into_bytes();
+        assert_eq!(
+            Some((expected_id, EdenObjectType::Tree)),
+            eden_object(&ArtifactValue::dir(dir))
+        );
+    }
+
+    #[test]
+    fn test_eden_object_symlink() {
+        let value = ArtifactValue::new(DirectoryEntry::Leaf(new_symlink("foo").unwrap()), None);
+        assert_eq!(None, eden_object(&value));
+    }
+
+    #[test]
+    fn test_mount_relative_path() {
+        let buck_out = ProjectRelativePath::unchecked_new("buck-out/v2");
+        assert_eq!(
+            b"gen/root/foo".to_vec(),
+            mount_relative_path(
+                buck_out,
+                ProjectRelativePath::unchecked_new("buck-out/v2/gen/root/foo")
+            )
+            .unwrap()
+        );
+        assert!(
+            mount_relative_path(buck_out, ProjectRelativePath::unchecked_new("src/foo")).is_err()
+        );
+    }
+}
@@ -9,6 +9,7 @@
 
 use std::collections::HashMap;
 
+use allocative::Allocative;
 use buck2_core::directory::DirectoryEntry;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
@@ -20,6 +21,65 @@ use buck2_execute::directory::ActionDirectoryEntry;
 use buck2_execute::directory::ActionDirectoryMember;
 use buck2_execute::directory::ActionSharedDirectory;
 use buck2_execute::execute::blocking::IoRequest;
+use dupe::Dupe;
+
+#[derive(Debug, buck2_error::Error)]
+pub enum MaterializationDedupError {
+    #[error(
+        "Invalid value for buckconfig `[buck2] materialization_dedup`. Got `{0}`. Expected one of `copy` or `reflink`."
+    )]
+    InvalidValueForConfig(String),
+}
+
+/// How a file is materialized when a file with the same contents is already materialized
+/// elsewhere, e.g. when copying an artifact, or when downloading the same digest to several paths.
+#[derive(Clone, Copy, Debug, Dupe, PartialEq, Eq, Allocative)]
+pub enum MaterializationDedup {
+    /// Copy the file.
+    Copy,
+    /// Clone the existing file, on filesystems which support it (btrfs, XFS, APFS). Unlike
+    /// hardlinks, clones are independent files, so writing to one does not modify the other.
+    Reflink,
+}
+
+impl MaterializationDedup {
+    pub fn try_new_from_config_value(config_value: Option<&str>) -> anyhow::Result<Self> {
+        match config_value {
+            None | Some("") | Some("copy") => Ok(MaterializationDedup::Copy),
+            Some("reflink") => Ok(MaterializationDedup::Reflink),
+            Some(v) => Err(MaterializationDedupError::InvalidValueForConfig(v.to_owned()).into()),
+        }
+    }
+
+    /// Materializes `dest` as a duplicate of the file at `src`, falling back to a copy if it
+    /// can't be cloned.
+    pub(crate) fn duplicate_file(
+        self,
+        src: &AbsNormPath,
+        dest: &AbsNormPath,
+    ) -> anyhow::Result<()> {
+        match self {
+            MaterializationDedup::Copy => copy(src, dest),
+            MaterializationDedup::Reflink => {
+                // Unlike a copy, a clone can't overwrite an existing file.
+                fs_util::remove_all(dest)?;
+                match fs_util::reflink(src, dest) {
+                    Ok(()) => Ok(()),
+                    Err(e) => {
+                        // E.g. the filesystem doesn't support reflinks.
+                        tracing::debug!("Falling back to copy: {:#}", anyhow::Error::from(e));
+                        copy(src, dest)
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn copy(src: &AbsNormPath, dest: &AbsNormPath) -> anyhow::Result<()> {
+    fs_util::copy(src, dest)?;
+    Ok(())
+}
 
 pub struct MaterializeTreeStructure {
     pub path: ProjectRelativePathBuf,
@@ -41,11 +101,13 @@ impl IoRequest for MaterializeTreeStructure {
 /// - `file_src`: takes the destination path of a file, and returns its
 ///   source path (where it should be copied from). If it returns [`None`],
 ///   the file is not materialized.
+/// - `dedup`: how files are duplicated from their source path.
 fn materialize<F, D>(
     entry: DirectoryEntry<&D, &ActionDirectoryMember>,
     dest: &AbsNormPath,
     materialize_dirs_and_syms: bool,
     mut file_src: F,
+    dedup: MaterializationDedup,
 ) -> anyhow::Result<()>
 where
     F: FnMut(&AbsNormPath) -> Option<AbsNormPathBuf>,
@@ -58,7 +120,13 @@ where
             fs_util::create_dir_all(parent)?;
         }
     }
-    materialize_recursively(entry, &mut dest, materialize_dirs_and_syms, &mut file_src)
+    materialize_recursively(
+        entry,
+        &mut dest,
+        materialize_dirs_and_syms,
+        &mut file_src,
+        dedup,
+    )
 }
 
 /// Materializes the directories and symlinks of an entry at `dest`. Files
@@ -71,17 +139,25 @@ where
     P: AsRef<AbsNormPath>,
     D: ActionDirectory,
 {
-    materialize(entry, dest.as_ref(), true, |_: &AbsNormPath| None)
+    materialize(
+        entry,
+        dest.as_ref(),
+        true,
+        |_: &AbsNormPath| None,
+        MaterializationDedup::Copy,
+    )
 }
 
 /// Materializes the files of an the entry rooted at `dest`.
 ///
 /// Files are copied from `src`. In other words, if a file would be
-/// materialized at `dest/p`, then it's copied from `src/p`.
+/// materialized at `dest/p`, then it's copied from `src/p`, or cloned from it
+/// according to `dedup`.
 pub(crate) fn materialize_files<P, D>(
     entry: DirectoryEntry<&D, &ActionDirectoryMember>,
     src: P,
     dest: P,
+    dedup: MaterializationDedup,
 ) -> anyhow::Result<()>
 where
     P: AsRef<AbsNormPath>,
@@ -100,7 +176,7 @@ where
             Some(src.join(subpath))
         }
     };
-    materialize(entry, dest, false, file_src)
+    materialize(entry, dest, false, file_src, dedup)
 }
 
 /// Materializes the files of an entry rooted at `dest`.
@@ -118,7 +194,13 @@ where
     D: ActionDirectory,
 {
     let file_src = |d: &AbsNormPath| srcs.remove(d);
-    materialize(entry, dest.as_ref(), false, file_src)
+    materialize(
+        entry,
+        dest.as_ref(),
+        false,
+        file_src,
+        MaterializationDedup::Copy,
+    )
 }
 
 fn materialize_recursively<F, D>(
@@ -126,6 +208,7 @@ fn materialize_recursively<F, D>(
     dest: &mut AbsNormPathBuf,
     materialize_dirs_and_syms: bool,
     file_src: &mut F,
+    dedup: MaterializationDedup,
 ) -> anyhow::Result<()>
 where
     F: FnMut(&AbsNormPath) -> Option<AbsNormPathBuf>,
@@ -138,14 +221,14 @@ where
             }
             for (name, entry) in d.entries() {
                 dest.push(name);
-                materialize_recursively(entry, dest, materialize_dirs_and_syms, file_src)?;
+                materialize_recursively(entry, dest, materialize_dirs_and_syms, file_src, dedup)?;
                 dest.pop();
             }
             Ok(())
         }
         DirectoryEntry::Leaf(ActionDirectoryMember::File(_)) => {
             if let Some(src) = file_src(dest) {
-                fs_util::copy(src, dest)?;
+                dedup.duplicate_file(&src, dest)?;
             }
             Ok(())
         }
@@ -163,3 +246,45 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_try_new_from_config_value() {
+        assert_eq!(
+            MaterializationDedup::Copy,
+            MaterializationDedup::try_new_from_config_value(None).unwrap()
+        );
+        assert_eq!(
+            MaterializationDedup::Reflink,
+            MaterializationDedup::try_new_from_config_value(Some("reflink")).unwrap()
+        );
+        assert!(MaterializationDedup::try_new_from_config_value(Some("hardlink")).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_file() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let root = AbsNormPath::new(tempdir.path())?;
+        let src = root.join(ForwardRelativePath::new("src")?);
+        fs_util::write(&src, "contents")?;
+
+        for dedup in [MaterializationDedup::Copy, MaterializationDedup::Reflink] {
+            let dest = root.join(ForwardRelativePathBuf::new(format!("{:?}", dedup))?);
+            // An existing file is replaced.
+            fs_util::write(&dest, "previous contents")?;
+            dedup.duplicate_file(&src, &dest)?;
+            assert_eq!("contents", fs_util::read_to_string(&dest)?);
+
+            // Whether the file was cloned or copied, it is independent of its source.
+            fs_util::write(&dest, "changed")?;
+            assert_eq!("contents", fs_util::read_to_string(&src)?);
+        }
+        Ok(())
+    }
+}
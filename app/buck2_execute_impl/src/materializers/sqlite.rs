@@ -8,6 +8,9 @@
  */
 
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::ffi::OsString;
 use std::sync::Arc;
 
 use allocative::Allocative;
@@ -635,6 +638,114 @@ impl MaterializerStateSqliteDb {
     pub fn identity(&self) -> &MaterializerStateIdentity {
         &self.identity
     }
+
+    /// Drops the entries of a loaded `MaterializerState` whose artifacts are no longer in
+    /// buck-out, e.g. because it was partially deleted while the daemon was not running, both
+    /// from the state and from the DB. Otherwise, the materializer would consider them
+    /// materialized and never write them again. Directories are walked to check that the total
+    /// size of their files didn't change.
+    pub async fn reconcile(
+        &mut self,
+        state: MaterializerState,
+        fs: ProjectRoot,
+        io_executor: Arc<dyn BlockingExecutor>,
+    ) -> anyhow::Result<MaterializerState> {
+        io_executor
+            .execute_io_inline(|| self.reconcile_impl(state, &fs))
+            .await
+    }
+
+    fn reconcile_impl(
+        &mut self,
+        state: MaterializerState,
+        fs: &ProjectRoot,
+    ) -> anyhow::Result<MaterializerState> {
+        // Rather than a stat per entry, list each directory containing entries once: there are
+        // usually many outputs per directory, and a deleted directory drops all of its entries
+        // with a single failed lookup. `read_dir` does not follow symlinks, so a symlink pointing
+        // to an artifact which is not materialized is still on disk.
+        let mut listings: HashMap<ProjectRelativePathBuf, Option<HashSet<OsString>>> =
+            HashMap::new();
+        let mut present = Vec::with_capacity(state.len());
+        let mut missing = Vec::new();
+        for (path, value) in state {
+            let on_disk = match (path.parent(), path.file_name()) {
+                (Some(dir), Some(name)) => {
+                    if !listings.contains_key(dir) {
+                        listings.insert(dir.to_buf(), list_dir(&fs.resolve(dir)));
+                    }
+                    listings[dir]
+                        .as_ref()
+                        .is_some_and(|names| names.contains(OsStr::new(name.as_str())))
+                }
+                // The project root itself.
+                _ => true,
+            };
+            // Some of the contents of a directory may have been deleted, so they are counted too.
+            let on_disk = on_disk
+                && match &value.0 .0 {
+                    DirectoryEntry::Dir(meta) => {
+                        size_on_disk(&fs.resolve(&path)).ok() == Some(meta.total_size)
+                    }
+                    DirectoryEntry::Leaf(_) => true,
+                };
+            if on_disk {
+                present.push((path, value));
+            } else {
+                missing.push(path);
+            }
+        }
+
+        if !missing.is_empty() {
+            tracing::info!(
+                "Dropping {} materializer state entries which are not on disk",
+                missing.len()
+            );
+            self.materializer_state_table()
+                .delete(missing)
+                .context("Error deleting stale materializer state entries")?;
+        }
+
+        Ok(present)
+    }
+}
+
+/// The names of the entries of `dir`, or `None` if it doesn't exist or can't be listed, in
+/// which case the artifacts in it are treated as missing.
+fn list_dir(dir: &AbsNormPath) -> Option<HashSet<OsString>> {
+    let res = (|| -> anyhow::Result<Option<HashSet<OsString>>> {
+        match fs_util::read_dir_if_exists(dir)? {
+            Some(entries) => Ok(Some(
+                entries
+                    .map(|e| Ok(e?.file_name()))
+                    .collect::<anyhow::Result<_>>()?,
+            )),
+            None => Ok(None),
+        }
+    })();
+    res.unwrap_or_else(|e| {
+        tracing::debug!("Error listing `{}`: {:#}", dir, e);
+        None
+    })
+}
+
+/// The total size of the files under `dir`, not following symlinks, which is what
+/// `DirectoryMetadata::total_size` records for a materialized directory.
+fn size_on_disk(dir: &AbsNormPath) -> anyhow::Result<u64> {
+    let mut size = 0;
+    let mut dirs = vec![dir.to_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs_util::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(AbsNormPathBuf::new(entry.path())?);
+            } else if file_type.is_file() {
+                size += entry.metadata()?.len();
+            }
+        }
+    }
+    Ok(size)
 }
 
 struct MaterializerStateTables {
@@ -1061,6 +1172,81 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_reconcile_drops_missing_artifacts() -> anyhow::Result<()> {
+        let digest_config = DigestConfig::testing_default();
+
+        let fs = ProjectRootTemp::new()?;
+        let versions = HashMap::from([("version".to_owned(), "0".to_owned())]);
+
+        let present = ProjectRelativePath::unchecked_new("buck-out/v2/gen/present").to_owned();
+        let symlink = ProjectRelativePath::unchecked_new("buck-out/v2/gen/symlink").to_owned();
+        let missing = ProjectRelativePath::unchecked_new("buck-out/v2/gen/missing").to_owned();
+        let deleted_dir =
+            ProjectRelativePath::unchecked_new("buck-out/v2/gen/deleted/out").to_owned();
+        fs.write_file("buck-out/v2/gen/present", "");
+        // A symlink to an artifact which is not materialized is still on disk.
+        fs_util::symlink("missing", fs.path().resolve(&symlink))?;
+
+        let metadata = ArtifactMetadata(DirectoryEntry::Leaf(ActionDirectoryMember::File(
+            FileMetadata {
+                digest: TrackedFileDigest::from_content(b"", digest_config.cas_digest_config()),
+                is_executable: false,
+            },
+        )));
+        let timestamp = now_seconds();
+
+        let (mut db, _) = testing_materializer_state_sqlite_db(
+            fs.path(),
+            versions.clone(),
+            HashMap::new(),
+            None,
+        )?;
+        for path in [&present, &symlink, &missing, &deleted_dir] {
+            db.materializer_state_table()
+                .insert(path, &metadata, timestamp)?;
+        }
+
+        // A directory whose parent is a file can't be listed.
+        let under_file =
+            ProjectRelativePath::unchecked_new("buck-out/v2/gen/present/out").to_owned();
+        db.materializer_state_table()
+            .insert(&under_file, &metadata, timestamp)?;
+
+        // Directories are only kept if all their files are still there.
+        let dir_metadata = ArtifactMetadata(DirectoryEntry::Dir(DirectoryMetadata {
+            fingerprint: TrackedFileDigest::from_content(
+                b"directory",
+                digest_config.cas_digest_config(),
+            ),
+            total_size: 6,
+        }));
+        let full_dir = ProjectRelativePath::unchecked_new("buck-out/v2/gen/full").to_owned();
+        let partial_dir = ProjectRelativePath::unchecked_new("buck-out/v2/gen/partial").to_owned();
+        fs.write_file("buck-out/v2/gen/full/a", "abc");
+        fs.write_file("buck-out/v2/gen/full/b/c", "def");
+        fs.write_file("buck-out/v2/gen/partial/a", "abc");
+        for path in [&full_dir, &partial_dir] {
+            db.materializer_state_table()
+                .insert(path, &dir_metadata, timestamp)?;
+        }
+
+        let state = db.materializer_state_table().read_all(digest_config)?;
+        let mut state = db.reconcile_impl(state, fs.path())?;
+        state.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let expected = vec![
+            (full_dir, (dir_metadata, timestamp)),
+            (present, (metadata.clone(), timestamp)),
+            (symlink, (metadata, timestamp)),
+        ];
+        assert_eq!(state, expected);
+        let mut stored = db.materializer_state_table().read_all(digest_config)?;
+        stored.sort_by(|(a, _), (b, _)| a.cmp(b));
+        assert_eq!(stored, expected);
+
+        Ok(())
+    }
+
     #[test]
     fn test_delete_many() -> anyhow::Result<()> {
         let conn = Connection::open_in_memory()?;
@@ -280,7 +280,7 @@ impl CasDownloader<'_> {
         .await
     }
 
-    async fn extract_artifacts<'a>(
+    pub(crate) async fn extract_artifacts<'a>(
         &self,
         identity: &ReActionIdentity<'_>,
         paths: &CommandExecutionPaths,
@@ -396,9 +396,9 @@ enum DownloadError {
     InvalidPathFromRe,
 }
 
-struct ExtractedArtifacts {
+pub(crate) struct ExtractedArtifacts {
     to_declare: Vec<(ProjectRelativePathBuf, ArtifactValue)>,
-    mapped_outputs: IndexMap<CommandExecutionOutput, ArtifactValue>,
+    pub(crate) mapped_outputs: IndexMap<CommandExecutionOutput, ArtifactValue>,
     now: DateTime<Utc>,
     expires: DateTime<Utc>,
     ttl: Duration,
@@ -16,6 +16,7 @@ use async_trait::async_trait;
 use buck2_common::ignores::ignore_set::IgnoreSet;
 use buck2_common::legacy_configs::configs::LegacyBuckConfig;
 use buck2_common::legacy_configs::key::BuckconfigKeyRef;
+use buck2_core::cells::cell_path::CellPath;
 use buck2_core::cells::name::CellName;
 use buck2_core::cells::CellResolver;
 use buck2_core::fs::project::ProjectRoot;
@@ -23,6 +24,7 @@ use buck2_core::is_open_source;
 use dice::DiceTransactionUpdater;
 
 use crate::fs_hash_crawler::FsHashCrawler;
+use crate::manifest::ManifestFileWatcher;
 use crate::mergebase::Mergebase;
 use crate::notify::NotifyFileWatcher;
 use crate::watchman::interface::WatchmanFileWatcher;
@@ -33,6 +35,12 @@ pub trait FileWatcher: Allocative + Send + Sync + 'static {
         &self,
         dice: DiceTransactionUpdater,
     ) -> anyhow::Result<(DiceTransactionUpdater, Mergebase)>;
+
+    /// The paths which changed since the last `sync`, and will be invalidated by the next one.
+    /// `None` if the watcher only finds out about changes when it syncs.
+    fn pending_changes(&self) -> Option<Vec<CellPath>> {
+        None
+    }
 }
 
 impl dyn FileWatcher {
@@ -69,6 +77,10 @@ impl dyn FileWatcher {
                 FsHashCrawler::new(project_root, cells, ignore_specs)
                     .context("Creating fs_crawler file watcher")?,
             )),
+            "manifest" => Ok(Arc::new(
+                ManifestFileWatcher::new(project_root, root_config, cells, ignore_specs)
+                    .context("Creating manifest file watcher")?,
+            )),
             other => Err(anyhow::anyhow!("Invalid buck2.file_watcher: {}", other)),
         }
     }
@@ -116,7 +116,7 @@ struct FsEvent {
 }
 
 #[derive(Allocative)]
-enum EntryInfo {
+pub(crate) enum EntryInfo {
     #[allocative(skip)]
     File(Hash),
     Directory,
@@ -134,16 +134,20 @@ impl EntryInfo {
 }
 
 #[derive(Allocative)]
-struct FsSnapshot(HashMap<CellPath, EntryInfo>);
+pub(crate) struct FsSnapshot(HashMap<CellPath, EntryInfo>);
 
 impl FsSnapshot {
+    pub(crate) fn new() -> Self {
+        FsSnapshot(HashMap::new())
+    }
+
     fn build(root: &ProjectRoot, cells: &CellResolver) -> anyhow::Result<Self> {
-        let mut snapshot = FsSnapshot(HashMap::new());
+        let mut snapshot = FsSnapshot::new();
         snapshot.build_fs_snapshot(root, cells, root.root())?;
         Ok(snapshot)
     }
 
-    fn add_entry(&mut self, cell: CellPath, info: EntryInfo) {
+    pub(crate) fn add_entry(&mut self, cell: CellPath, info: EntryInfo) {
         self.0.insert(cell, info);
     }
 
@@ -183,7 +187,7 @@ impl FsSnapshot {
         Ok(events)
     }
 
-    fn get_updates_for_dice(
+    pub(crate) fn get_updates_for_dice(
         &self,
         new_snapshot: &FsSnapshot,
         ignore_specs: &HashMap<CellName, IgnoreSet>,
@@ -0,0 +1,235 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::mem;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use allocative::Allocative;
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_common::ignores::ignore_set::IgnoreSet;
+use buck2_common::invocation_paths::InvocationPaths;
+use buck2_common::legacy_configs::configs::LegacyBuckConfig;
+use buck2_common::legacy_configs::key::BuckconfigKeyRef;
+use buck2_core::cells::name::CellName;
+use buck2_core::cells::CellResolver;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_events::dispatch::span_async;
+use dice::DiceTransactionUpdater;
+use dupe::Dupe;
+
+use crate::file_watcher::FileWatcher;
+use crate::fs_hash_crawler::EntryInfo;
+use crate::fs_hash_crawler::FsSnapshot;
+use crate::mergebase::Mergebase;
+
+#[derive(Debug, buck2_error::Error)]
+enum ManifestFileWatcherError {
+    #[error("`buck2.file_watcher = manifest` requires `buck2.file_watcher_manifest` to be set")]
+    MissingManifestPath,
+    #[error("Invalid line {0} in file watcher manifest, expected `<digest> <path>`: `{1}`")]
+    InvalidLine(usize, String),
+}
+
+// Never looks at the repository: the state of every file comes from a manifest listing
+// one `<digest> <path>` per line (the format of `sha256sum`), with paths relative to the
+// project root. The manifest is re-read on each sync and diffed against the previous one,
+// so e.g. a CI checkout step can write it out before each command and get invalidation
+// without a crawl or a running watcher. Files not listed in the manifest don't exist as
+// far as invalidation is concerned.
+#[derive(Allocative)]
+pub struct ManifestFileWatcher {
+    manifest: AbsNormPathBuf,
+    cells: CellResolver,
+    ignore_specs: HashMap<CellName, IgnoreSet>,
+    snapshot: Arc<Mutex<FsSnapshot>>,
+}
+
+impl ManifestFileWatcher {
+    pub fn new(
+        root: &ProjectRoot,
+        root_config: &LegacyBuckConfig,
+        cells: CellResolver,
+        ignore_specs: HashMap<CellName, IgnoreSet>,
+    ) -> anyhow::Result<Self> {
+        let manifest = root_config
+            .get(BuckconfigKeyRef {
+                section: "buck2",
+                property: "file_watcher_manifest",
+            })
+            .ok_or(ManifestFileWatcherError::MissingManifestPath)?;
+        let manifest = Path::new(manifest);
+        let manifest = if manifest.is_absolute() {
+            AbsNormPathBuf::new(manifest.to_owned())?
+        } else {
+            root.resolve(ProjectRelativePath::new(manifest)?)
+        };
+        let snapshot = Arc::new(Mutex::new(read_manifest(&manifest, &cells)?));
+        Ok(Self {
+            manifest,
+            cells,
+            ignore_specs,
+            snapshot,
+        })
+    }
+
+    async fn update(
+        &self,
+        mut dice: DiceTransactionUpdater,
+    ) -> anyhow::Result<(buck2_data::FileWatcherStats, DiceTransactionUpdater)> {
+        let manifest = self.manifest.clone();
+        let cells = self.cells.dupe();
+        let new_snapshot =
+            tokio::task::spawn_blocking(move || read_manifest(&manifest, &cells)).await??;
+        let mut guard = self.snapshot.lock().unwrap();
+        let old_snapshot = mem::replace(&mut *guard, new_snapshot);
+        let (stats, changes) = old_snapshot.get_updates_for_dice(&guard, &self.ignore_specs)?;
+        changes.write_to_dice(&mut dice)?;
+        Ok((stats, dice))
+    }
+}
+
+#[async_trait]
+impl FileWatcher for ManifestFileWatcher {
+    async fn sync(
+        &self,
+        dice: DiceTransactionUpdater,
+    ) -> anyhow::Result<(DiceTransactionUpdater, Mergebase)> {
+        span_async(
+            buck2_data::FileWatcherStart {
+                provider: buck2_data::FileWatcherProvider::Manifest as i32,
+            },
+            async {
+                let (stats, res) = match self.update(dice).await {
+                    Ok((stats, dice)) => {
+                        let mergebase = Mergebase(Arc::new(stats.branched_from_revision.clone()));
+                        ((Some(stats)), Ok((dice, mergebase)))
+                    }
+                    Err(e) => (None, Err(e)),
+                };
+                (res, buck2_data::FileWatcherEnd { stats })
+            },
+        )
+        .await
+    }
+}
+
+fn read_manifest(manifest: &AbsNormPathBuf, cells: &CellResolver) -> anyhow::Result<FsSnapshot> {
+    let contents = fs_util::read_to_string(manifest)
+        .with_context(|| format!("Reading file watcher manifest `{}`", manifest))?;
+    parse_manifest(&contents, cells)
+}
+
+fn parse_manifest(contents: &str, cells: &CellResolver) -> anyhow::Result<FsSnapshot> {
+    let mut snapshot = FsSnapshot::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (digest, path) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| ManifestFileWatcherError::InvalidLine(i + 1, line.to_owned()))?;
+        // `sha256sum` prefixes paths it hashed in binary mode with `*`.
+        let path = path.trim_start();
+        let path = path.strip_prefix('*').unwrap_or(path);
+        let path = ProjectRelativePath::new(path)
+            .with_context(|| ManifestFileWatcherError::InvalidLine(i + 1, line.to_owned()))?;
+
+        // We ignore the buck-out prefix, same as the other file watchers.
+        if path.starts_with(InvocationPaths::buck_out_dir_prefix()) {
+            continue;
+        }
+
+        snapshot.add_entry(
+            cells.get_cell_path(path)?,
+            EntryInfo::File(blake3::hash(digest.as_bytes())),
+        );
+
+        // Directories are implied by the files they contain, so that adding the first file
+        // to (or removing the last file from) a directory invalidates its listing too.
+        let mut dir = path.parent();
+        while let Some(d) = dir {
+            if d.is_empty() {
+                break;
+            }
+            snapshot.add_entry(cells.get_cell_path(d)?, EntryInfo::Directory);
+            dir = d.parent();
+        }
+    }
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::CellResolver;
+    use buck2_data::FileWatcherEventType;
+    use buck2_data::FileWatcherKind;
+
+    use crate::manifest::parse_manifest;
+
+    #[test]
+    fn test_parse_manifest() -> anyhow::Result<()> {
+        let cells = CellResolver::testing_with_name_and_path(
+            CellName::testing_new("root"),
+            CellRootPathBuf::testing_new(""),
+        );
+        let old = parse_manifest(
+            "# comment\n\
+             aaaa  dir1/file1\n\
+             bbbb *dir2/file2\n\
+             cccc  buck-out/v2/ignored\n",
+            &cells,
+        )?;
+        let new = parse_manifest("aaab  dir1/file1\ncccc  dir1/file3\n", &cells)?;
+
+        let (stats, _) = old.get_updates_for_dice(&new, &Default::default())?;
+        let mut events = stats
+            .events
+            .iter()
+            .map(|e| (e.path.as_str(), e.event, e.kind))
+            .collect::<Vec<_>>();
+        events.sort();
+
+        let file = FileWatcherKind::File as i32;
+        let dir = FileWatcherKind::Directory as i32;
+        let mut expected = vec![
+            (
+                "root//dir1/file1",
+                FileWatcherEventType::Modify as i32,
+                file,
+            ),
+            (
+                "root//dir1/file3",
+                FileWatcherEventType::Create as i32,
+                file,
+            ),
+            ("root//dir2", FileWatcherEventType::Delete as i32, dir),
+            (
+                "root//dir2/file2",
+                FileWatcherEventType::Delete as i32,
+                file,
+            ),
+        ];
+        expected.sort();
+        assert_eq!(events, expected);
+
+        assert!(parse_manifest("no_path_here\n", &cells).is_err());
+        Ok(())
+    }
+}
@@ -137,6 +137,14 @@ impl NotifyFileData {
         Ok(())
     }
 
+    fn pending_changes(&self) -> Vec<CellPath> {
+        let mut paths = OrderedSet::new();
+        for (cell_path, _) in &self.events {
+            paths.insert(cell_path.clone());
+        }
+        paths.into_iter().collect()
+    }
+
     fn sync(self) -> (buck2_data::FileWatcherStats, FileChangeTracker) {
         // The changes that go into the DICE transaction
         let mut changed = FileChangeTracker::new();
@@ -239,4 +247,12 @@ impl FileWatcher for NotifyFileWatcher {
         )
         .await
     }
+
+    fn pending_changes(&self) -> Option<Vec<CellPath>> {
+        // If the watcher failed, the changes are unknown until the next sync reports the error.
+        match &*self.data.lock().unwrap() {
+            Ok(data) => Some(data.pending_changes()),
+            Err(_) => None,
+        }
+    }
 }
@@ -109,9 +109,54 @@ impl HttpClient {
         self.request(req).await
     }
 
-    async fn send_request_impl(
+    /// Send a PUT request whose body is streamed, e.g. from a file which shouldn't be read into
+    /// memory. Redirects are not followed, since the body can't be sent again.
+    pub async fn put_stream(
         &self,
-        mut request: Request<Bytes>,
+        uri: &str,
+        body: BoxStream<'static, std::io::Result<Bytes>>,
+        content_length: u64,
+    ) -> Result<Response<BoxStream<hyper::Result<Bytes>>>, HttpError> {
+        let (mut sender, request_body) = Body::channel();
+        let request = self
+            .request_builder(uri)
+            .method(Method::PUT)
+            .header(http::header::CONTENT_LENGTH, content_length)
+            .body(request_body)
+            .map_err(HttpError::BuildRequest)?;
+        let request_uri = request.uri().clone();
+
+        let send_body = async move {
+            let mut body = body;
+            while let Some(chunk) = body.next().await {
+                match chunk {
+                    Ok(chunk) => {
+                        // The request failed, which is reported by the response.
+                        if sender.send_data(chunk).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        sender.abort();
+                        return Err(e);
+                    }
+                }
+            }
+            Ok(())
+        };
+        let (resp, sent) = futures::join!(self.send_request_impl(request), send_body);
+        sent.map_err(|source| HttpError::ReadBody {
+            uri: uri.to_owned(),
+            source,
+        })?;
+        let resp = resp?;
+        tracing::debug!("http: response: {:?}", resp.status());
+        error_for_status(&request_uri, resp).await
+    }
+
+    async fn send_request_impl<B: Into<Body>>(
+        &self,
+        mut request: Request<B>,
     ) -> Result<Response<BoxStream<hyper::Result<Bytes>>>, HttpError> {
         let uri = request.uri().to_string();
         let now = tokio::time::Instant::now();
@@ -124,6 +169,7 @@ impl HttpClient {
             );
             change_scheme_to_http(&mut request);
         }
+        let request = request.map(Into::into);
         let resp = self.inner.request(request).await.map_err(|e| {
             if is_hyper_error_due_to_timeout(&e) {
                 HttpError::Timeout {
@@ -162,25 +208,7 @@ impl HttpClient {
             resp
         };
 
-        if !resp.status().is_success() {
-            // Handle x2p errors as indicated by headers.
-            if let Some(x2p_err) = X2PAgentError::from_headers(&uri, resp.headers()) {
-                return Err(HttpError::X2P {
-                    uri: uri.to_string(),
-                    source: x2p_err,
-                });
-            }
-
-            let status = resp.status();
-            let text = read_truncated_error_response(resp).await;
-            return Err(HttpError::Status {
-                status,
-                uri: uri.to_string(),
-                text,
-            });
-        }
-
-        Ok(resp)
+        error_for_status(&uri, resp).await
     }
 
     pub fn stats(&self) -> &HttpNetworkStats {
@@ -204,16 +232,41 @@ impl HttpClient {
 /// ProxyConnector<HttpsConnector<..>>, etc); thus wrap the client so we can switch
 /// out the concrete type without exposing implementation details to callers.
 pub(super) trait RequestClient: Send + Sync {
-    fn request(&self, request: Request<Bytes>) -> ResponseFuture;
+    fn request(&self, request: Request<Body>) -> ResponseFuture;
 }
 
 impl<C> RequestClient for hyper::Client<C>
 where
     C: Connect + Clone + Send + Sync + 'static,
 {
-    fn request(&self, request: Request<Bytes>) -> ResponseFuture {
-        self.request(request.map(Body::from))
+    fn request(&self, request: Request<Body>) -> ResponseFuture {
+        self.request(request)
+    }
+}
+
+async fn error_for_status<'a>(
+    uri: &Uri,
+    resp: Response<BoxStream<'a, hyper::Result<Bytes>>>,
+) -> Result<Response<BoxStream<'a, hyper::Result<Bytes>>>, HttpError> {
+    if !resp.status().is_success() {
+        // Handle x2p errors as indicated by headers.
+        if let Some(x2p_err) = X2PAgentError::from_headers(uri, resp.headers()) {
+            return Err(HttpError::X2P {
+                uri: uri.to_string(),
+                source: x2p_err,
+            });
+        }
+
+        let status = resp.status();
+        let text = read_truncated_error_response(resp).await;
+        return Err(HttpError::Status {
+            status,
+            uri: uri.to_string(),
+            text,
+        });
     }
+
+    Ok(resp)
 }
 
 async fn read_truncated_error_response(
@@ -246,7 +299,7 @@ pub async fn to_bytes(body: BoxStream<'_, hyper::Result<Bytes>>) -> anyhow::Resu
 
 /// x2pagent proxies only speak plain HTTP, so we need to mutate requests prior
 /// to sending them off.
-fn change_scheme_to_http(request: &mut Request<Bytes>) {
+fn change_scheme_to_http<B>(request: &mut Request<B>) {
     let uri = request.uri().clone();
     let mut parts = uri.into_parts();
     parts.scheme = Some(Scheme::HTTP);
@@ -351,6 +404,54 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_put_stream_success() -> anyhow::Result<()> {
+        let test_server = httptest::Server::run();
+        test_server.expect(
+            Expectation::matching(all_of![
+                request::method_path("PUT", "/foo"),
+                request::body("Hello, world!")
+            ])
+            .respond_with(responders::status_code(200)),
+        );
+
+        let client = HttpClientBuilder::https_with_system_roots()?.build();
+        let body = futures::stream::iter([
+            Ok(Bytes::from_static(b"Hello, ")),
+            Ok(Bytes::from_static(b"world!")),
+        ])
+        .boxed();
+        let resp = client
+            .put_stream(&test_server.url_str("/foo"), body, 13)
+            .await?;
+        assert_eq!(200, resp.status().as_u16());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_put_stream_body_error() -> anyhow::Result<()> {
+        let test_server = httptest::Server::run();
+        test_server.expect(
+            Expectation::matching(request::method_path("PUT", "/foo"))
+                .times(..)
+                .respond_with(responders::status_code(200)),
+        );
+
+        let client = HttpClientBuilder::https_with_system_roots()?.build();
+        let body = futures::stream::iter([
+            Ok(Bytes::from_static(b"Hello, ")),
+            Err(std::io::Error::new(std::io::ErrorKind::Other, "disk error")),
+        ])
+        .boxed();
+        let result = client
+            .put_stream(&test_server.url_str("/foo"), body, 13)
+            .await;
+        assert!(matches!(result, Err(HttpError::ReadBody { .. })));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_simple_post_success() -> anyhow::Result<()> {
         let test_server = httptest::Server::run();
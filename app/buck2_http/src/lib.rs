@@ -68,6 +68,12 @@ pub enum HttpError {
         uri: String,
         text: String,
     },
+    #[error("HTTP: Error reading the body of request to {uri}")]
+    ReadBody {
+        uri: String,
+        #[source]
+        source: std::io::Error,
+    },
     #[error("HTTP Error: Exceeded max redirects ({max_redirects}) while fetching URI: {uri}. ")]
     TooManyRedirects { uri: String, max_redirects: usize },
     #[error("HTTP: Error mutating request")]
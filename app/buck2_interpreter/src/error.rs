@@ -37,6 +37,21 @@ impl BuckStarlarkError {
     pub fn inner(&self) -> &starlark::Error {
         &self.e
     }
+
+    /// Where the error is, for tools which annotate files with errors.
+    fn diagnostic(&self, code: &str) -> Option<buck2_data::ErrorDiagnostic> {
+        let span = self.e.span()?;
+        let resolved = span.resolve_span();
+        Some(buck2_data::ErrorDiagnostic {
+            path: span.filename().to_owned(),
+            begin_line: resolved.begin.line as u32 + 1,
+            begin_column: resolved.begin.column as u32 + 1,
+            end_line: resolved.end.line as u32 + 1,
+            end_column: resolved.end.column as u32 + 1,
+            code: code.to_owned(),
+            suggestion: starlark::errors::suggested_name(&self.e).map(str::to_owned),
+        })
+    }
 }
 
 impl std::error::Error for BuckStarlarkError {
@@ -92,6 +107,9 @@ impl std::error::Error for BuckStarlarkError {
             Some(variant_name),
             None, /* action error */
         );
+        if let Some(diagnostic) = self.diagnostic(variant_name) {
+            request.provide_value(diagnostic);
+        }
     }
 }
 
@@ -114,3 +132,20 @@ impl fmt::Display for BuckStarlarkError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostic() {
+        let e = BuckStarlarkError::new(starlark::assert::fail(
+            "discreet = 1\ndiscrete",
+            "not found",
+        ));
+        let diagnostic = e.diagnostic("BuckStarlarkError::Scope").unwrap();
+        assert_eq!(2, diagnostic.begin_line);
+        assert_eq!(1, diagnostic.begin_column);
+        assert_eq!(Some("discreet"), diagnostic.suggestion.as_deref());
+    }
+}
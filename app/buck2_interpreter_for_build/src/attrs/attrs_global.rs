@@ -23,6 +23,7 @@ use buck2_node::attrs::attr_type::AttrType;
 use buck2_node::attrs::coercion_context::AttrCoercionContext;
 use buck2_node::attrs::configurable::AttrIsConfigurable;
 use buck2_node::attrs::display::AttrDisplayWithContextExt;
+use buck2_node::deprecation::Deprecation;
 use buck2_node::provider_id_set::ProviderIdSet;
 use derive_more::Display;
 use dupe::Dupe;
@@ -438,6 +439,25 @@ fn attr_module(registry: &mut MethodsBuilder) {
         )))
     }
 
+    /// Marks an attribute as deprecated: targets which set it get a warning with `message`, and
+    /// `replaced_by`, the attribute to set instead, if given. It still works as the inner
+    /// attribute, and should have a default for targets which have migrated away from it.
+    ///
+    /// ```python
+    /// attrs.deprecated(attrs.bool(default = False), message = "Set `mode` instead", replaced_by = "mode")
+    /// ```
+    fn deprecated<'v>(
+        #[starlark(this)] _this: Value<'v>,
+        #[starlark(require = pos)] inner: &StarlarkAttribute,
+        #[starlark(require = named)] message: &str,
+        #[starlark(require = named)] replaced_by: Option<&str>,
+    ) -> anyhow::Result<StarlarkAttribute> {
+        Ok(inner.with_deprecation(Deprecation {
+            message: message.to_owned(),
+            replaced_by: replaced_by.map(str::to_owned),
+        }))
+    }
+
     /// Takes a target (as per `deps`) and passes a `label` to the rule.
     /// Validates that the target exists, but does not introduce a dependency on it.
     fn label<'v>(
@@ -63,6 +63,10 @@ enum BuildAttrCoercionContextError {
         "Directory `{1}` of package `{0}` may not cover any subpackages, but includes subpackage `{2}`."
     )]
     SourceDirectoryIncludesSubPackage(PackageLabel, String, PackageRelativePathBuf),
+    #[error(
+        "Relative label `{1}` is not allowed in package `{0}` by `project.forbid_relative_labels`, use `{0}{1}` instead."
+    )]
+    RelativeLabelForbidden(PackageLabel, String),
 }
 
 /// An incomplete attr coercion context. Will be replaced with a real one later.
@@ -78,6 +82,8 @@ pub struct BuildAttrCoercionContext {
     enclosing_package: Option<(PackageLabel, PackageListing)>,
     /// Does this package (if present) have a package boundary exception on it.
     package_boundary_exception: bool,
+    /// Can labels in this package (if present) be relative to it.
+    relative_labels_allowed: bool,
     /// Allocator for `label_cache`.
     alloc: Bump,
     global_label_interner: Arc<ConcurrentTargetLabelInterner>,
@@ -111,6 +117,7 @@ impl BuildAttrCoercionContext {
         cell_alias_resolver: CellAliasResolver,
         enclosing_package: Option<(PackageLabel, PackageListing)>,
         package_boundary_exception: bool,
+        relative_labels_allowed: bool,
         global_label_interner: Arc<ConcurrentTargetLabelInterner>,
     ) -> Self {
         Self {
@@ -119,6 +126,7 @@ impl BuildAttrCoercionContext {
             cell_alias_resolver,
             enclosing_package,
             package_boundary_exception,
+            relative_labels_allowed,
             alloc: Bump::new(),
             global_label_interner,
             label_cache: RefCell::new(HashTable::new()),
@@ -141,6 +149,7 @@ impl BuildAttrCoercionContext {
             cell_alias_resolver,
             None,
             false,
+            true,
             global_label_interner,
         )
     }
@@ -150,6 +159,7 @@ impl BuildAttrCoercionContext {
         cell_alias_resolver: CellAliasResolver,
         enclosing_package: (PackageLabel, PackageListing),
         package_boundary_exception: bool,
+        relative_labels_allowed: bool,
         global_label_interner: Arc<ConcurrentTargetLabelInterner>,
     ) -> Self {
         Self::new(
@@ -158,11 +168,21 @@ impl BuildAttrCoercionContext {
             cell_alias_resolver,
             Some(enclosing_package),
             package_boundary_exception,
+            relative_labels_allowed,
             global_label_interner,
         )
     }
 
     pub fn parse_pattern<P: PatternType>(&self, value: &str) -> anyhow::Result<ParsedPattern<P>> {
+        if !self.relative_labels_allowed && ProvidersLabel::maybe_relative_label(value) {
+            if let Some((package, _)) = &self.enclosing_package {
+                return Err(BuildAttrCoercionContextError::RelativeLabelForbidden(
+                    package.dupe(),
+                    value.to_owned(),
+                )
+                .into());
+            }
+        }
         ParsedPattern::parsed_opt_absolute(
             value,
             self.enclosing_package.as_ref().map(|x| x.0.as_cell_path()),
@@ -74,6 +74,7 @@ pub fn coercion_ctx_listing(package_listing: PackageListing) -> impl AttrCoercio
         cell_alias_resolver,
         (package, package_listing),
         false,
+        true,
         Arc::new(ConcurrentTargetLabelInterner::default()),
     )
 }
@@ -13,9 +13,14 @@ use allocative::Allocative;
 use buck2_node::attrs::attr::Attribute;
 use buck2_node::attrs::attr_type::AttrType;
 use buck2_node::attrs::coerced_attr::CoercedAttr;
+use buck2_node::attrs::display::AttrDisplayWithContextExt;
+use buck2_node::deprecation::Deprecation;
 use dupe::Dupe;
 use starlark::any::ProvidesStaticType;
 use starlark::environment::GlobalsBuilder;
+use starlark::environment::Methods;
+use starlark::environment::MethodsBuilder;
+use starlark::environment::MethodsStatic;
 use starlark::starlark_module;
 use starlark::starlark_simple_value;
 use starlark::values::starlark_value;
@@ -27,6 +32,8 @@ use starlark::values::StarlarkValue;
 enum StarlarkAttributeError {
     #[error("`attrs.default_only()` cannot be used in nested attributes")]
     DefaultOnlyInNested,
+    #[error("`attrs.deprecated()` cannot be used in nested attributes")]
+    DeprecatedInNested,
 }
 
 #[derive(
@@ -41,7 +48,35 @@ pub(crate) struct StarlarkAttribute(Attribute);
 starlark_simple_value!(StarlarkAttribute);
 
 #[starlark_value(type = "attribute")]
-impl<'v> StarlarkValue<'v> for StarlarkAttribute {}
+impl<'v> StarlarkValue<'v> for StarlarkAttribute {
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(attribute_methods)
+    }
+}
+
+/// Attribute object, returned from e.g. `attrs.string()`, or as a value of `rule.attributes()`.
+#[starlark_module]
+fn attribute_methods(builder: &mut MethodsBuilder) {
+    /// The type of the attribute without its default, e.g. `attrs.list(attrs.string())`.
+    #[starlark(attribute)]
+    fn r#type(this: &StarlarkAttribute) -> anyhow::Result<String> {
+        Ok(this.0.coercer().to_string())
+    }
+
+    /// The default value of the attribute as Starlark source, or `None` if the attribute must be
+    /// specified.
+    #[starlark(attribute)]
+    fn default(this: &StarlarkAttribute) -> anyhow::Result<Option<String>> {
+        Ok(this.0.default().map(|x| x.as_display_no_ctx().to_string()))
+    }
+
+    /// The documentation of the attribute, as passed as `doc` when it was created.
+    #[starlark(attribute)]
+    fn doc(this: &StarlarkAttribute) -> anyhow::Result<String> {
+        Ok(this.0.doc().to_owned())
+    }
+}
 
 impl StarlarkAttribute {
     pub fn new(attr: Attribute) -> Self {
@@ -57,6 +92,9 @@ impl StarlarkAttribute {
         if self.0.is_default_only() {
             return Err(StarlarkAttributeError::DefaultOnlyInNested.into());
         }
+        if self.0.deprecation().is_some() {
+            return Err(StarlarkAttributeError::DeprecatedInNested.into());
+        }
         Ok(self.0.coercer().dupe())
     }
 
@@ -67,6 +105,10 @@ impl StarlarkAttribute {
     pub fn default(&self) -> Option<&Arc<CoercedAttr>> {
         self.0.default()
     }
+
+    pub fn with_deprecation(&self, deprecation: Deprecation) -> Self {
+        Self(self.0.clone().with_deprecation(deprecation))
+    }
 }
 
 #[starlark_module]
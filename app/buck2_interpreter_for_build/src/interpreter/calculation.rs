@@ -15,11 +15,13 @@ use std::time::Instant;
 
 use allocative::Allocative;
 use async_trait::async_trait;
+use buck2_common::dice::cells::HasCellResolver;
 use buck2_common::package_listing::dice::DicePackageListingResolver;
 use buck2_core::build_file_path::BuildFilePath;
 use buck2_core::bzl::ImportPath;
 use buck2_core::cells::build_file_cell::BuildFileCell;
 use buck2_core::package::PackageLabel;
+use buck2_error::BuckErrorContext;
 use buck2_events::dispatch::async_record_root_spans;
 use buck2_events::span::SpanId;
 use buck2_futures::cancellation::CancellationContext;
@@ -261,6 +263,66 @@ impl PackageValuesCalculation for PackageValuesCalculationInstance {
             .await?;
         super_package.package_values().package_values_json()
     }
+
+    async fn package_values_with_origins(
+        &self,
+        ctx: &mut DiceComputations<'_>,
+        package: PackageLabel,
+    ) -> anyhow::Result<SmallMap<MetadataKey, (serde_json::Value, PackageLabel)>> {
+        // Same walk as `eval_parent_package_file`, which may cross cell boundaries.
+        let cell_resolver = ctx.get_cell_resolver().await?;
+        let path = cell_resolver.resolve_path(package.as_cell_path())?;
+        let mut dirs = Vec::new();
+        let mut dir = Some(&*path);
+        while let Some(d) = dir {
+            dirs.push(PackageLabel::from_cell_path(
+                cell_resolver.get_cell_path(d)?.as_ref(),
+            ));
+            dir = d.parent();
+        }
+
+        let mut values_by_dir = Vec::new();
+        for dir in dirs.into_iter().rev() {
+            let values = ctx
+                .get_interpreter_calculator(dir.cell_name(), BuildFileCell::new(dir.cell_name()))
+                .await?
+                .eval_package_file(dir.dupe())
+                .await?
+                .package_values()
+                .package_values_json()?;
+            values_by_dir.push((dir, values));
+        }
+        package_values_origins(values_by_dir)
+    }
+}
+
+/// The package values of the last directory, each with the directory which set it, given the
+/// package values of each directory from the root down.
+fn package_values_origins(
+    values_by_dir: Vec<(PackageLabel, SmallMap<MetadataKey, serde_json::Value>)>,
+) -> anyhow::Result<SmallMap<MetadataKey, (serde_json::Value, PackageLabel)>> {
+    let mut origins = SmallMap::new();
+    let mut previous = SmallMap::new();
+    for (dir, values) in values_by_dir {
+        // Directories without a `PACKAGE` file evaluate to their parent's values, so values
+        // only change in directories whose `PACKAGE` file wrote them.
+        for (key, value) in &values {
+            if previous.get(key) != Some(value) {
+                origins.insert(key.clone(), dir.dupe());
+            }
+        }
+        previous = values;
+    }
+    previous
+        .into_iter()
+        .map(|(key, value)| {
+            let origin = origins
+                .get(&key)
+                .cloned()
+                .internal_error("package value must have an origin")?;
+            Ok((key, (value, origin)))
+        })
+        .collect()
 }
 
 pub struct IntepreterResultsKeyActivationData {
@@ -268,3 +330,39 @@ pub struct IntepreterResultsKeyActivationData {
     pub result: buck2_error::Result<Arc<EvaluationResult>>,
     pub spans: SmallVec<[SpanId; 1]>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_values_origins() {
+        let key = |k: &str| MetadataKey::try_from(k.to_owned()).unwrap();
+        let root = PackageLabel::testing_parse("root//");
+        let foo = PackageLabel::testing_parse("root//foo");
+        let bar = PackageLabel::testing_parse("root//foo/bar");
+        let values_by_dir = vec![
+            (
+                root.dupe(),
+                SmallMap::from_iter([(key("a.x"), serde_json::json!(1))]),
+            ),
+            (
+                foo.dupe(),
+                SmallMap::from_iter([
+                    (key("a.x"), serde_json::json!(1)),
+                    (key("a.y"), serde_json::json!(2)),
+                ]),
+            ),
+            (
+                bar.dupe(),
+                SmallMap::from_iter([
+                    (key("a.x"), serde_json::json!(3)),
+                    (key("a.y"), serde_json::json!(2)),
+                ]),
+            ),
+        ];
+        let origins = package_values_origins(values_by_dir).unwrap();
+        assert_eq!(Some(&(serde_json::json!(3), bar)), origins.get(&key("a.x")));
+        assert_eq!(Some(&(serde_json::json!(2), foo)), origins.get(&key("a.y")));
+    }
+}
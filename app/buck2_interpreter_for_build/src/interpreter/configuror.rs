@@ -116,6 +116,7 @@ impl BuildInterpreterConfiguror {
         package_listing: PackageListing,
         super_package: SuperPackage,
         package_boundary_exception: bool,
+        relative_labels_allowed: bool,
         loaded_modules: &LoadedModules,
         implicit_import: Option<&Arc<ImplicitImport>>,
     ) -> anyhow::Result<ModuleInternals> {
@@ -142,6 +143,7 @@ impl BuildInterpreterConfiguror {
             cell_info.cell_alias_resolver().dupe(),
             (buildfile_path.package().dupe(), package_listing.dupe()),
             package_boundary_exception,
+            relative_labels_allowed,
             self.global_target_interner.dupe(),
         );
 
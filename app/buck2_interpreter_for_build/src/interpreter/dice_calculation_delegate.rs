@@ -20,6 +20,7 @@ use buck2_common::legacy_configs::dice::OpaqueLegacyBuckConfigOnDice;
 use buck2_common::package_boundary::HasPackageBoundaryExceptions;
 use buck2_common::package_listing::dice::DicePackageListingResolver;
 use buck2_common::package_listing::listing::PackageListing;
+use buck2_common::relative_label_policy::HasRelativeLabelPolicy;
 use buck2_core::build_file_path::BuildFilePath;
 use buck2_core::cells::build_file_cell::BuildFileCell;
 use buck2_core::cells::name::CellName;
@@ -476,6 +477,10 @@ impl<'c, 'd: 'c> DiceCalculationDelegate<'c, 'd> {
             .get_package_boundary_exception(package.as_cell_path())
             .await?
             .is_some();
+        let relative_labels_allowed = self
+            .ctx
+            .relative_labels_allowed(package.as_cell_path())
+            .await?;
         let buckconfig = self.get_legacy_buck_config_for_starlark().await?;
         let root_buckconfig = self.ctx.get_legacy_root_config_on_dice().await?;
         let module_id = build_file_path.to_string();
@@ -504,6 +509,7 @@ impl<'c, 'd: 'c> DiceCalculationDelegate<'c, 'd> {
                             listing,
                             super_package,
                             package_boundary_exception,
+                            relative_labels_allowed,
                             ast,
                             deps.get_loaded_modules(),
                             provider,
@@ -38,6 +38,7 @@ use crate::interpreter::functions::starlark::register_set_starlark_peak_allocate
 use crate::interpreter::functions::warning::register_warning;
 use crate::interpreter::natives::register_module_natives;
 use crate::interpreter::selector::register_select;
+use crate::native_rules::register_native_rules;
 use crate::plugins::register_plugins;
 use crate::rule::register_rule_function;
 use crate::super_package::defs::register_package_natives;
@@ -66,6 +67,7 @@ fn register_buck2_natives(builder: &mut GlobalsBuilder) {
     register_buck_regex(builder);
     register_load_symbols(builder);
     register_rule_function(builder);
+    register_native_rules(builder);
     register_attrs(builder);
     register_plugins(builder);
     register_providers_label(builder);
@@ -90,7 +92,7 @@ pub fn starlark_library_extensions_for_buck2() -> &'static [LibraryExtension] {
         LibraryExtension::Json,
         LibraryExtension::Map,
         LibraryExtension::Partial,
-        LibraryExtension::Pprint,
+        // `pprint` comes from the build API, which adds depth limits and renders artifacts.
         LibraryExtension::Pstr,
         LibraryExtension::Prepr,
         LibraryExtension::Print,
@@ -27,6 +27,7 @@ use buck2_core::cells::cell_path::CellPath;
 use buck2_core::soft_error;
 use buck2_error::BuckErrorContext;
 use buck2_event_observer::humanized::HumanizedBytes;
+use buck2_events::dispatch::console_message;
 use buck2_events::dispatch::get_dispatcher;
 use buck2_interpreter::error::BuckStarlarkError;
 use buck2_interpreter::factory::StarlarkEvaluatorProvider;
@@ -90,6 +91,17 @@ enum StarlarkPeakMemorySoftError {
     CloseToThreshold(BuildFilePath, HumanizedBytes, HumanizedBytes, String),
 }
 
+#[derive(Debug, buck2_error::Error)]
+#[error(
+    "`{0}` declares targets but has no oncall (required by `buildfile.require_oncall`). \
+    Call `oncall()` in the build file, or `package(oncall = ...)` in an enclosing `PACKAGE` file."
+)]
+struct MissingOncallSoftError(BuildFilePath);
+
+#[derive(Debug, buck2_error::Error)]
+#[error("`{0}` uses deprecated rules or attributes:\n{1}")]
+struct DeprecatedUsesSoftError(BuildFilePath, String);
+
 #[derive(Debug, buck2_error::Error)]
 #[error("Error parsing: `{1}`")]
 pub struct ParseError(#[source] pub BuckStarlarkError, OwnedStarlarkPath);
@@ -345,6 +357,7 @@ impl InterpreterForCell {
         package_listing: &PackageListing,
         super_package: SuperPackage,
         package_boundary_exception: bool,
+        relative_labels_allowed: bool,
         loaded_modules: &LoadedModules,
     ) -> anyhow::Result<(Module, ModuleInternals)> {
         let internals = self.global_state.configuror.new_extra_context(
@@ -353,6 +366,7 @@ impl InterpreterForCell {
             package_listing.dupe(),
             super_package,
             package_boundary_exception,
+            relative_labels_allowed,
             loaded_modules,
             self.package_import(build_file),
         )?;
@@ -600,7 +614,7 @@ impl InterpreterForCell {
         let extra_context = PerFileTypeContext::Package(PackageFileEvalCtx {
             path: package_file_path.clone(),
             parent,
-            visibility: RefCell::new(None),
+            package_fields: RefCell::new(None),
         });
 
         let per_file_context = self
@@ -644,6 +658,7 @@ impl InterpreterForCell {
         listing: PackageListing,
         super_package: SuperPackage,
         package_boundary_exception: bool,
+        relative_labels_allowed: bool,
         ast: AstModule,
         loaded_modules: LoadedModules,
         eval_provider: &mut dyn StarlarkEvaluatorProvider,
@@ -654,6 +669,7 @@ impl InterpreterForCell {
             &listing,
             super_package,
             package_boundary_exception,
+            relative_labels_allowed,
             &loaded_modules,
         )?;
         let eval_result = self.eval(
@@ -667,6 +683,41 @@ impl InterpreterForCell {
         )?;
 
         let internals = eval_result.additional.into_build()?;
+        if internals.has_targets_without_oncall() {
+            let require_oncall_key = BuckconfigKeyRef {
+                section: "buildfile",
+                property: "require_oncall",
+            };
+            if LegacyBuckConfig::parse_value(
+                require_oncall_key,
+                buckconfigs
+                    .read_current_cell_config(require_oncall_key)?
+                    .as_deref(),
+            )?
+            .unwrap_or(false)
+            {
+                soft_error!(
+                    "missing_oncall",
+                    MissingOncallSoftError(build_file.clone()).into()
+                )?;
+            }
+        }
+        let deprecated_uses = internals.deprecated_uses();
+        if !deprecated_uses.is_empty() {
+            let uses = deprecated_uses
+                .iter()
+                .map(|u| format!("  {}", u))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let err = DeprecatedUsesSoftError(build_file.clone(), uses);
+            if buck2_core::is_open_source() {
+                // Soft errors are errors in open source, but using a deprecated rule is fine
+                // until it is removed.
+                console_message(err.to_string());
+            } else {
+                soft_error!("deprecated_rule_or_attribute", err.into(), task: false)?;
+            }
+        }
         let starlark_peak_allocated_bytes = env.heap().peak_allocated_bytes() as u64;
         let buckconfig_key = BuckconfigKeyRef {
             section: "buck2",
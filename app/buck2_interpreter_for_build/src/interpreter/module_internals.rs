@@ -21,6 +21,7 @@ use buck2_core::package::package_relative_path::PackageRelativePath;
 use buck2_core::target::name::TargetNameRef;
 use buck2_events::dispatch::console_message;
 use buck2_interpreter::package_imports::ImplicitImport;
+use buck2_node::deprecation::DeprecatedUse;
 use buck2_node::nodes::eval_result::EvaluationResult;
 use buck2_node::nodes::targets_map::TargetsMap;
 use buck2_node::nodes::targets_map::TargetsMapRecordError;
@@ -29,6 +30,7 @@ use buck2_node::oncall::Oncall;
 use buck2_node::package::Package;
 use buck2_node::super_package::SuperPackage;
 use dupe::Dupe;
+use dupe::OptionDupedExt;
 use starlark::environment::FrozenModule;
 use starlark::values::OwnedFrozenValue;
 
@@ -186,18 +188,45 @@ impl ModuleInternals {
         match &mut *self.state.borrow_mut() {
             State::BeforeTargets(x) => {
                 x.has_read_oncall = true;
-                x.oncall.dupe()
+                x.oncall
+                    .dupe()
+                    .or_else(|| self.super_package.oncall().duped())
             }
             State::RecordingTargets(t) => t.package.oncall.dupe(),
         }
     }
 
+    /// Whether this file declared targets without an oncall, either from the `BUCK` file
+    /// itself or from an enclosing `PACKAGE` file.
+    pub(crate) fn has_targets_without_oncall(&self) -> bool {
+        match &*self.state.borrow() {
+            State::BeforeTargets(_) => false,
+            State::RecordingTargets(t) => {
+                t.package.oncall.is_none() && t.recorder.targets.len() != 0
+            }
+        }
+    }
+
+    /// The deprecated rules and attributes used by the targets declared so far.
+    pub(crate) fn deprecated_uses(&self) -> Vec<DeprecatedUse> {
+        match &*self.state.borrow() {
+            State::BeforeTargets(_) => Vec::new(),
+            State::RecordingTargets(t) => t
+                .recorder
+                .targets
+                .values()
+                .flat_map(|t| t.deprecated_uses())
+                .collect(),
+        }
+    }
+
     fn recording_targets(&self) -> RefMut<RecordingTargets> {
         RefMut::map(self.state.borrow_mut(), |state| {
             loop {
                 match state {
                     State::BeforeTargets(BeforeTargets { oncall, .. }) => {
-                        let oncall = mem::take(oncall);
+                        let oncall =
+                            mem::take(oncall).or_else(|| self.super_package.oncall().duped());
                         *state = State::RecordingTargets(RecordingTargets {
                             package: Arc::new(Package {
                                 buildfile_path: self.buildfile_path.dupe(),
@@ -305,6 +305,7 @@ impl Tester {
             package_listing,
             SuperPackage::empty::<SuperPackageValuesImpl>(),
             false,
+            true,
             ast,
             loaded_modules,
             &mut provider,
@@ -15,6 +15,7 @@ use std::sync::Once;
 pub mod attrs;
 pub mod interpreter;
 pub mod label;
+pub(crate) mod native_rules;
 pub mod nodes;
 pub(crate) mod plugins;
 pub mod rule;
@@ -0,0 +1,191 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! The configuration rules implemented by buck2 itself, so that constraints and config settings
+//! can be declared without a prelude. A prelude defining rules with the same names shadows them.
+
+use std::sync::Arc;
+
+use allocative::Allocative;
+use buck2_core::plugins::PluginKindSet;
+use buck2_node::attrs::attr::Attribute;
+use buck2_node::attrs::attr_type::any::AnyAttrType;
+use buck2_node::attrs::attr_type::dict::DictLiteral;
+use buck2_node::attrs::attr_type::AttrType;
+use buck2_node::attrs::coerced_attr::CoercedAttr;
+use buck2_node::attrs::spec::AttributeSpec;
+use buck2_node::nodes::unconfigured::RuleKind;
+use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_node::provider_id_set::ProviderIdSet;
+use buck2_node::rule::Rule;
+use buck2_node::rule_type::NativeRuleType;
+use buck2_node::rule_type::RuleType;
+use buck2_util::arc_str::ArcSlice;
+use derive_more::Display;
+use dupe::Dupe;
+use starlark::any::ProvidesStaticType;
+use starlark::environment::GlobalsBuilder;
+use starlark::environment::Methods;
+use starlark::environment::MethodsStatic;
+use starlark::eval::Arguments;
+use starlark::eval::Evaluator;
+use starlark::eval::ParametersSpec;
+use starlark::starlark_simple_value;
+use starlark::typing::Ty;
+use starlark::values::starlark_value;
+use starlark::values::FrozenValue;
+use starlark::values::NoSerialize;
+use starlark::values::StarlarkValue;
+use starlark::values::Value;
+
+use crate::interpreter::build_context::BuildContext;
+use crate::interpreter::module_internals::ModuleInternals;
+use crate::nodes::unconfigured::TargetNodeExt;
+use crate::rule::rule_methods;
+use crate::rule::ty_rule;
+use crate::rule::RuleCallable;
+
+/// The callable of a native rule, which records targets like the output of `rule()`.
+#[derive(Debug, Display, ProvidesStaticType, NoSerialize, Allocative)]
+#[display(fmt = "{}()", "rule.rule_type.name()")]
+pub struct NativeRuleCallable {
+    rule: Arc<Rule>,
+    signature: ParametersSpec<FrozenValue>,
+    ty: Ty,
+}
+starlark_simple_value!(NativeRuleCallable);
+
+impl NativeRuleCallable {
+    fn new(rule_type: NativeRuleType) -> anyhow::Result<Self> {
+        let attributes = AttributeSpec::from(native_rule_attributes(rule_type), false)?;
+
+        let mut signature =
+            ParametersSpec::with_capacity(rule_type.name().to_owned(), attributes.len());
+        signature.no_more_positional_args();
+        for (name, _idx, attribute) in attributes.attr_specs() {
+            match attribute.default() {
+                Some(_) => signature.optional(name),
+                None => signature.required(name),
+            };
+        }
+
+        Ok(NativeRuleCallable {
+            signature: signature.finish(),
+            ty: ty_rule(&attributes)?,
+            rule: Arc::new(Rule {
+                attributes,
+                rule_type: RuleType::Native(rule_type),
+                rule_kind: RuleKind::Configuration,
+                cfg: None,
+                uses_plugins: Vec::new(),
+                deprecation: None,
+            }),
+        })
+    }
+
+    pub(crate) fn attributes(&self) -> &AttributeSpec {
+        &self.rule.attributes
+    }
+}
+
+/// The attributes of the native rules, matching those of the prelude's rules.
+fn native_rule_attributes(rule_type: NativeRuleType) -> Vec<(String, Attribute)> {
+    // Like `attrs.configuration_label()`.
+    let configuration_label = || AttrType::dep(ProviderIdSet::EMPTY, PluginKindSet::EMPTY);
+    match rule_type {
+        NativeRuleType::ConstraintSetting => Vec::new(),
+        NativeRuleType::ConstraintValue => vec![(
+            "constraint_setting".to_owned(),
+            Attribute::new(
+                None,
+                "the `constraint_setting` this is a value of",
+                configuration_label(),
+            ),
+        )],
+        NativeRuleType::ConfigSetting => vec![
+            (
+                "constraint_values".to_owned(),
+                Attribute::new(
+                    Some(Arc::new(AnyAttrType::empty_list())),
+                    "the `constraint_value`s which must all match",
+                    AttrType::list(configuration_label()),
+                ),
+            ),
+            (
+                "values".to_owned(),
+                Attribute::new(
+                    Some(Arc::new(CoercedAttr::Dict(DictLiteral(ArcSlice::new([]))))),
+                    "the `section.key` buckconfigs and the values they must all have",
+                    AttrType::dict(AttrType::string(), AttrType::string(), false),
+                ),
+            ),
+        ],
+    }
+}
+
+#[starlark_value(type = "rule")]
+impl<'v> StarlarkValue<'v> for NativeRuleCallable {
+    type Canonical = RuleCallable<'v>;
+
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(rule_methods)
+    }
+
+    fn invoke(
+        &self,
+        _me: Value<'v>,
+        args: &Arguments<'v, '_>,
+        eval: &mut Evaluator<'v, '_, '_>,
+    ) -> starlark::Result<Value<'v>> {
+        let ignore_attrs_for_profiling =
+            BuildContext::from_context(eval)?.ignore_attrs_for_profiling;
+        let record_target_call_stack =
+            ModuleInternals::from_context(eval, self.rule.rule_type.name())?
+                .record_target_call_stacks();
+        let call_stack = if record_target_call_stack {
+            Some(eval.call_stack())
+        } else {
+            None
+        };
+        let arg_count = args.len()?;
+        self.signature
+            .parser(args, eval, |param_parser, eval| {
+                let internals = ModuleInternals::from_context(eval, self.rule.rule_type.name())?;
+                let target_node = TargetNode::from_params(
+                    self.rule.dupe(),
+                    internals.package(),
+                    internals,
+                    param_parser,
+                    arg_count,
+                    ignore_attrs_for_profiling,
+                    call_stack,
+                )?;
+                internals.record(target_node)?;
+                Ok(Value::new_none())
+            })
+            .map_err(Into::into)
+    }
+
+    fn typechecker_ty(&self) -> Option<Ty> {
+        Some(self.ty.clone())
+    }
+
+    fn get_type_starlark_repr() -> Ty {
+        RuleCallable::get_type_starlark_repr()
+    }
+}
+
+pub(crate) fn register_native_rules(builder: &mut GlobalsBuilder) {
+    for rule_type in NativeRuleType::ALL {
+        let callable =
+            NativeRuleCallable::new(rule_type).expect("native rule attributes are valid");
+        builder.set(rule_type.name(), callable);
+    }
+}
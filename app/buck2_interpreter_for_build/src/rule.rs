@@ -22,6 +22,7 @@ use buck2_interpreter::types::rule::FROZEN_RULE_GET_IMPL;
 use buck2_interpreter::types::transition::transition_id_from_value;
 use buck2_node::attrs::attr::Attribute;
 use buck2_node::attrs::spec::AttributeSpec;
+use buck2_node::deprecation::Deprecation;
 use buck2_node::nodes::unconfigured::RuleKind;
 use buck2_node::nodes::unconfigured::TargetNode;
 use buck2_node::rule::Rule;
@@ -36,6 +37,9 @@ use starlark::docs::DocFunction;
 use starlark::docs::DocItem;
 use starlark::docs::DocStringKind;
 use starlark::environment::GlobalsBuilder;
+use starlark::environment::Methods;
+use starlark::environment::MethodsBuilder;
+use starlark::environment::MethodsStatic;
 use starlark::eval::Arguments;
 use starlark::eval::Evaluator;
 use starlark::eval::ParametersSpec;
@@ -43,12 +47,16 @@ use starlark::starlark_module;
 use starlark::starlark_simple_value;
 use starlark::typing::Param;
 use starlark::typing::Ty;
+use starlark::typing::TyStarlarkValue;
+use starlark::typing::TyUser;
+use starlark::typing::TyUserParams;
 use starlark::values::dict::DictOf;
 use starlark::values::list::UnpackList;
 use starlark::values::list_or_tuple::UnpackListOrTuple;
 use starlark::values::starlark_value;
 use starlark::values::typing::FrozenStarlarkCallable;
 use starlark::values::typing::StarlarkCallable;
+use starlark::values::typing::TypeInstanceId;
 use starlark::values::AllocValue;
 use starlark::values::Freeze;
 use starlark::values::Freezer;
@@ -67,6 +75,7 @@ use crate::attrs::starlark_attribute::StarlarkAttribute;
 use crate::interpreter::build_context::BuildContext;
 use crate::interpreter::build_context::PerFileTypeContext;
 use crate::interpreter::module_internals::ModuleInternals;
+use crate::native_rules::NativeRuleCallable;
 use crate::nodes::attr_spec::AttributeSpecExt;
 use crate::nodes::unconfigured::TargetNodeExt;
 use crate::plugins::plugin_kind_from_value;
@@ -98,6 +107,8 @@ pub struct RuleCallable<'v> {
     rule_kind: RuleKind,
     /// The raw docstring for this rule
     docs: Option<String>,
+    /// Set if targets should migrate away from this rule.
+    deprecation: Option<Deprecation>,
     /// When evaluating rule function, take only the `name` argument, ignore the others.
     ignore_attrs_for_profiling: bool,
     /// Optional map of the promise artifact name to starlark function.
@@ -141,6 +152,8 @@ enum RuleError {
     IsConfigurationAndToolchain,
     #[error("`rule` can only be declared in bzl files")]
     RuleNonInBzl,
+    #[error("`replaced_by` can only be specified together with `deprecated`")]
+    ReplacedByWithoutDeprecated,
 }
 
 impl<'v> AllocValue<'v> for RuleCallable<'v> {
@@ -158,6 +171,7 @@ impl<'v> RuleCallable<'v> {
         is_configuration_rule: bool,
         is_toolchain_rule: bool,
         uses_plugins: Vec<Value<'v>>,
+        deprecation: Option<Deprecation>,
         artifact_promise_mappings: Option<ArtifactPromiseMappings<'v>>,
         eval: &mut Evaluator<'v, '_, '_>,
     ) -> anyhow::Result<RuleCallable<'v>> {
@@ -198,7 +212,7 @@ impl<'v> RuleCallable<'v> {
 
         let attributes =
             AttributeSpec::from(sorted_validated_attrs, artifact_promise_mappings.is_some())?;
-        let ty = Ty::ty_function(attributes.ty_function());
+        let ty = ty_rule(&attributes)?;
 
         Ok(RuleCallable {
             import_path: bzl_path,
@@ -210,6 +224,7 @@ impl<'v> RuleCallable<'v> {
             rule_kind,
             uses_plugins,
             docs: Some(doc.to_owned()),
+            deprecation,
             ignore_attrs_for_profiling: build_context.ignore_attrs_for_profiling,
             artifact_promise_mappings,
         })
@@ -241,6 +256,11 @@ impl<'v> RuleCallable<'v> {
 
 #[starlark_value(type = "rule")]
 impl<'v> StarlarkValue<'v> for RuleCallable<'v> {
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(rule_methods)
+    }
+
     fn export_as(
         &self,
         variable_name: &str,
@@ -308,6 +328,7 @@ impl<'v> Freeze for RuleCallable<'v> {
                 cfg: self.cfg,
                 rule_kind: self.rule_kind,
                 uses_plugins: self.uses_plugins,
+                deprecation: self.deprecation,
             }),
             rule_type,
             implementation: frozen_impl,
@@ -381,6 +402,11 @@ impl FrozenRuleCallable {
 impl<'v> StarlarkValue<'v> for FrozenRuleCallable {
     type Canonical = RuleCallable<'v>;
 
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(rule_methods)
+    }
+
     fn invoke(
         &self,
         _me: Value<'v>,
@@ -429,6 +455,48 @@ impl<'v> StarlarkValue<'v> for FrozenRuleCallable {
     }
 }
 
+/// Rule object, returned from `rule()`.
+#[starlark_module]
+pub(crate) fn rule_methods(builder: &mut MethodsBuilder) {
+    /// The attributes of the rule as a dict from name to attribute object, including those common
+    /// to all rules such as `name` and `visibility`. This lets wrapper macros and documentation
+    /// generators inspect a rule rather than repeating its attributes.
+    ///
+    /// ```python
+    /// for name, attr in my_rule.attributes().items():
+    ///     print(name, attr.type, attr.default, attr.doc)
+    /// ```
+    fn attributes<'v>(this: Value<'v>) -> anyhow::Result<SmallMap<String, StarlarkAttribute>> {
+        let attributes = if let Some(rule) = this.downcast_ref::<RuleCallable<'v>>() {
+            &rule.attributes
+        } else if let Some(rule) = this.downcast_ref::<FrozenRuleCallable>() {
+            rule.attributes()
+        } else {
+            this.downcast_ref::<NativeRuleCallable>()
+                .context("Expecting rule")?
+                .attributes()
+        };
+        Ok(attributes
+            .attr_specs()
+            .map(|(name, _, attr)| (name.to_owned(), StarlarkAttribute::new(attr.clone())))
+            .collect())
+    }
+}
+
+/// Type of a rule for the typechecker: a function taking the rule's attributes, which also has
+/// the methods of `rule`.
+pub(crate) fn ty_rule(attributes: &AttributeSpec) -> anyhow::Result<Ty> {
+    Ok(Ty::custom(TyUser::new(
+        RuleCallable::TYPE.to_owned(),
+        TyStarlarkValue::new::<RuleCallable>(),
+        TypeInstanceId::gen(),
+        TyUserParams {
+            callable: Some(attributes.ty_function().callable().dupe()),
+            ..TyUserParams::default()
+        },
+    )?))
+}
+
 #[starlark_module]
 pub fn register_rule_function(builder: &mut GlobalsBuilder) {
     /// Define a rule. As a simple example:
@@ -443,6 +511,10 @@ pub fn register_rule_function(builder: &mut GlobalsBuilder) {
     ///     "exe": attrs.option(attrs.bool(), default = False),
     /// })
     /// ```
+    ///
+    /// A rule which should no longer be used can be marked with `deprecated`, the message to show
+    /// to its users, and `replaced_by`, the rule to use instead. Each package declaring targets
+    /// with it gets a `deprecated_rule_or_attribute` soft error, and the build report lists them.
     fn rule<'v>(
         #[starlark(require = named)] r#impl: StarlarkCallable<
             'v,
@@ -456,8 +528,18 @@ pub fn register_rule_function(builder: &mut GlobalsBuilder) {
         #[starlark(require = named, default = false)] is_toolchain_rule: bool,
         #[starlark(require = named, default = UnpackListOrTuple::default())]
         uses_plugins: UnpackListOrTuple<Value<'v>>,
+        #[starlark(require = named)] deprecated: Option<&str>,
+        #[starlark(require = named)] replaced_by: Option<&str>,
         eval: &mut Evaluator<'v, '_, '_>,
     ) -> anyhow::Result<RuleCallable<'v>> {
+        let deprecation = match (deprecated, replaced_by) {
+            (Some(message), replaced_by) => Some(Deprecation {
+                message: message.to_owned(),
+                replaced_by: replaced_by.map(str::to_owned),
+            }),
+            (None, Some(_)) => return Err(RuleError::ReplacedByWithoutDeprecated.into()),
+            (None, None) => None,
+        };
         RuleCallable::new(
             r#impl,
             attrs,
@@ -466,6 +548,7 @@ pub fn register_rule_function(builder: &mut GlobalsBuilder) {
             is_configuration_rule,
             is_toolchain_rule,
             uses_plugins.items,
+            deprecation,
             None,
             eval,
         )
@@ -496,6 +579,7 @@ pub fn register_rule_function(builder: &mut GlobalsBuilder) {
             false,
             false,
             Vec::new(),
+            None,
             Some(ArtifactPromiseMappings {
                 mappings: artifact_promise_mappings
                     .iter()
@@ -12,10 +12,12 @@ use std::sync::Arc;
 
 use buck2_interpreter::paths::package::PackageFilePath;
 use buck2_node::cfg_constructor::CfgConstructorImpl;
+use buck2_node::oncall::Oncall;
 use buck2_node::super_package::SuperPackage;
 use buck2_node::visibility::VisibilitySpecification;
 use buck2_node::visibility::WithinViewSpecification;
 use dupe::Dupe;
+use dupe::OptionDupedExt;
 use starlark::values::OwnedFrozenRef;
 use starlark::values::OwnedFrozenValue;
 use starlark_map::small_map::SmallMap;
@@ -25,11 +27,13 @@ use crate::interpreter::package_file_extra::MAKE_CFG_CONSTRUCTOR;
 use crate::super_package::package_value::OwnedFrozenStarlarkPackageValue;
 use crate::super_package::package_value::SuperPackageValuesImpl;
 
+/// Arguments of the `package()` call in a `PACKAGE` file.
 #[derive(Debug, Default)]
-pub(crate) struct PackageFileVisibilityFields {
+pub(crate) struct PackageFileFields {
     pub(crate) visibility: VisibilitySpecification,
     pub(crate) within_view: WithinViewSpecification,
     pub(crate) inherit: bool,
+    pub(crate) oncall: Option<Oncall>,
 }
 
 #[derive(Debug)]
@@ -38,7 +42,7 @@ pub struct PackageFileEvalCtx {
     /// Parent file context.
     /// When evaluating root `PACKAGE` file, parent is still defined.
     pub(crate) parent: SuperPackage,
-    pub(crate) visibility: RefCell<Option<PackageFileVisibilityFields>>,
+    pub(crate) package_fields: RefCell<Option<PackageFileFields>>,
 }
 
 impl PackageFileEvalCtx {
@@ -83,11 +87,12 @@ impl PackageFileEvalCtx {
         let merged_package_values =
             SuperPackageValuesImpl::merge(self.parent.package_values(), package_values)?;
 
-        let PackageFileVisibilityFields {
+        let PackageFileFields {
             visibility,
             within_view,
             inherit,
-        } = self.visibility.into_inner().unwrap_or_default();
+            oncall,
+        } = self.package_fields.into_inner().unwrap_or_default();
 
         let (visibility, within_view) = if inherit {
             (
@@ -98,10 +103,14 @@ impl PackageFileEvalCtx {
             (visibility, within_view)
         };
 
+        // Oncall is always inherited unless overridden, regardless of `inherit`.
+        let oncall = oncall.or_else(|| self.parent.oncall().duped());
+
         Ok(SuperPackage::new(
             merged_package_values,
             visibility,
             within_view,
+            oncall,
             cfg_constructor,
         ))
     }
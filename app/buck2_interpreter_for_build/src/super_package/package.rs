@@ -11,6 +11,7 @@ use buck2_core::cells::name::CellName;
 use buck2_core::cells::CellAliasResolver;
 use buck2_core::cells::CellResolver;
 use buck2_core::pattern::pattern::ParsedPattern;
+use buck2_node::oncall::Oncall;
 use buck2_node::visibility::VisibilityPattern;
 use buck2_node::visibility::VisibilitySpecification;
 use buck2_node::visibility::VisibilityWithinViewBuilder;
@@ -23,7 +24,7 @@ use starlark::values::none::NoneType;
 
 use crate::interpreter::build_context::BuildContext;
 use crate::interpreter::build_context::PerFileTypeContext;
-use crate::super_package::eval_ctx::PackageFileVisibilityFields;
+use crate::super_package::eval_ctx::PackageFileFields;
 
 #[derive(Debug, buck2_error::Error)]
 enum PackageFileError {
@@ -83,12 +84,18 @@ fn parse_within_view(
 /// Globals for `PACKAGE` files and `bzl` files included from `PACKAGE` files.
 #[starlark_module]
 pub(crate) fn register_package_function(globals: &mut GlobalsBuilder) {
+    /// Declare properties of all the targets in this directory and its subdirectories.
+    ///
+    /// `oncall` applies to every `BUCK` file below this `PACKAGE` file which does not
+    /// call `oncall()` itself. It is inherited by nested `PACKAGE` files unless they
+    /// declare their own, regardless of `inherit`.
     fn package(
         #[starlark(require=named, default=false)] inherit: bool,
         #[starlark(require=named, default=UnpackListOrTuple::default())]
         visibility: UnpackListOrTuple<String>,
         #[starlark(require=named, default=UnpackListOrTuple::default())]
         within_view: UnpackListOrTuple<String>,
+        #[starlark(require=named)] oncall: Option<&str>,
         eval: &mut Evaluator,
     ) -> anyhow::Result<NoneType> {
         let build_context = BuildContext::from_context(eval)?;
@@ -109,13 +116,14 @@ pub(crate) fn register_package_function(globals: &mut GlobalsBuilder) {
             build_context.cell_info().cell_alias_resolver(),
         )?;
 
-        match &mut *package_file_eval_ctx.visibility.borrow_mut() {
+        match &mut *package_file_eval_ctx.package_fields.borrow_mut() {
             Some(_) => return Err(PackageFileError::AtMostOnce.into()),
             x => {
-                *x = Some(PackageFileVisibilityFields {
+                *x = Some(PackageFileFields {
                     visibility,
                     within_view,
                     inherit,
+                    oncall: oncall.map(Oncall::new),
                 })
             }
         };
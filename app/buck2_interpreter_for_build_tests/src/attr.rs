@@ -120,6 +120,7 @@ fn attr_coercer_coerces() -> anyhow::Result<()> {
         cell_alias_resolver,
         enclosing_package,
         false,
+        true,
         Arc::new(ConcurrentTargetLabelInterner::default()),
     );
     let label_coercer = AttrType::dep(ProviderIdSet::EMPTY, PluginKindSet::EMPTY);
@@ -212,6 +213,40 @@ fn attr_coercer_coerces() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn attr_coercer_forbids_relative_labels() -> anyhow::Result<()> {
+    let heap = Heap::new();
+    let some_cells = cells(None)?;
+    let package = PackageLabel::new(
+        CellName::testing_new("root"),
+        CellRelativePath::unchecked_new("foo"),
+    );
+    let coercer_ctx = BuildAttrCoercionContext::new_with_package(
+        some_cells.1,
+        some_cells.0,
+        (package, PackageListing::testing_empty()),
+        false,
+        false,
+        Arc::new(ConcurrentTargetLabelInterner::default()),
+    );
+    let label_coercer = AttrType::dep(ProviderIdSet::EMPTY, PluginKindSet::EMPTY);
+
+    label_coercer.coerce(
+        AttrIsConfigurable::Yes,
+        &coercer_ctx,
+        heap.alloc("root//foo:bar"),
+    )?;
+    let err = label_coercer
+        .coerce(AttrIsConfigurable::Yes, &coercer_ctx, heap.alloc(":bar"))
+        .unwrap_err();
+    assert!(
+        format!("{:#}", err).contains("use `root//foo:bar` instead"),
+        "{:#}",
+        err
+    );
+    Ok(())
+}
+
 #[test]
 fn dep_works() -> buck2_error::Result<()> {
     let mut t = Tester::new().unwrap();
@@ -295,6 +330,7 @@ fn coercing_src_to_path_works() -> anyhow::Result<()> {
             PackageListing::testing_files(&["baz/quz.cpp"]),
         ),
         false,
+        true,
         Arc::new(ConcurrentTargetLabelInterner::default()),
     );
     let no_package_ctx = BuildAttrCoercionContext::new_no_package(
@@ -8,7 +8,11 @@
  */
 
 use buck2_build_api::interpreter::rule_defs::transitive_set::transitive_set_definition::register_transitive_set;
+use buck2_common::package_listing::listing::testing::PackageListingExt;
+use buck2_common::package_listing::listing::PackageListing;
+use buck2_core::build_file_path::BuildFilePath;
 use buck2_core::bzl::ImportPath;
+use buck2_core::target::name::TargetNameRef;
 use buck2_interpreter::file_loader::LoadedModules;
 use buck2_interpreter_for_build::interpreter::testing::Tester;
 use buck2_interpreter_for_build::nodes::attr_spec::AttributeSpecExt;
@@ -56,6 +60,37 @@ fn rule_creates_callable() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn rule_attributes() -> buck2_error::Result<()> {
+    let mut tester = rule_tester();
+    tester.run_starlark_bzl_test(indoc!(
+        r#"
+        def impl(ctx):
+            pass
+
+        frozen_rule = rule(
+            impl=impl,
+            attrs={
+                "param1": attrs.string(default="something", doc = "foo"),
+                "param2": attrs.list(attrs.string(), doc = "bar"),
+            }
+        )
+
+        def test():
+            attributes = frozen_rule.attributes()
+            assert_true("name" in attributes)
+            assert_true("visibility" in attributes)
+            assert_eq("attrs.string()", attributes["param1"].type)
+            assert_eq("\"something\"", attributes["param1"].default)
+            assert_eq("foo", attributes["param1"].doc)
+            assert_eq("attrs.list(attrs.string())", attributes["param2"].type)
+            assert_eq(None, attributes["param2"].default)
+            assert_eq("bar", attributes["param2"].doc)
+        "#
+    ))?;
+    Ok(())
+}
+
 #[test]
 fn freeze_fails_if_not_assigned() {
     let mut tester = rule_tester();
@@ -260,6 +295,88 @@ fn udr_rejects_invalid_parameters() {
     );
 }
 
+#[test]
+fn native_rules_are_recorded() -> buck2_error::Result<()> {
+    let content = indoc!(
+        r#"
+        def test():
+            constraint_setting(name="os")
+            constraint_value(name="linux", constraint_setting=":os")
+            config_setting(name="linux_only", constraint_values=[":linux"])
+            config_setting(name="release", values={"build.mode": "release"})
+            assert_eq("config_setting()", repr(config_setting))
+        "#
+    );
+    let mut tester = rule_tester();
+    let result = tester.run_starlark_test(content)?;
+
+    let actual = targets_to_json(
+        &result,
+        Tester::build_file_path().package(),
+        AttrInspectOptions::All,
+    )?;
+    assert_eq!(json!("constraint_setting"), actual["os"]["__type__"]);
+    assert_eq!(json!("constraint_value"), actual["linux"]["__type__"]);
+    assert_eq!(
+        json!("root//some/package:os"),
+        actual["linux"]["constraint_setting"]
+    );
+    assert_eq!(json!("config_setting"), actual["linux_only"]["__type__"]);
+    assert_eq!(
+        json!(["root//some/package:linux"]),
+        actual["linux_only"]["constraint_values"]
+    );
+    assert_eq!(json!({}), actual["linux_only"]["values"]);
+    assert_eq!(json!([]), actual["release"]["constraint_values"]);
+    assert_eq!(
+        json!({"build.mode": "release"}),
+        actual["release"]["values"]
+    );
+    Ok(())
+}
+
+#[test]
+fn native_rules_reject_invalid_parameters() {
+    let run = |content: &str, msg: &str| {
+        let mut tester = rule_tester();
+        tester.run_starlark_test_expecting_error(
+            &format!("def hide_type(v): return v\n\ndef test():\n    {}", content),
+            msg,
+        );
+    };
+
+    run(
+        r#"hide_type(constraint_value)(name="linux")"#,
+        "Missing parameter `constraint_setting`",
+    );
+    run(
+        r#"hide_type(constraint_setting)(name="os", constraint_values=[])"#,
+        "Found `constraint_values` extra named parameter",
+    );
+    run(
+        r#"hide_type(config_setting)(name="c", values={"build.mode": 1})"#,
+        "coercing attribute `values`",
+    );
+}
+
+#[test]
+fn native_rules_are_shadowed() -> buck2_error::Result<()> {
+    let mut tester = rule_tester();
+    tester.run_starlark_bzl_test(indoc!(
+        r#"
+        def impl(ctx):
+            pass
+
+        config_setting = rule(impl=impl, attrs={"flavor": attrs.string()})
+
+        def test():
+            assert_true("flavor" in config_setting.attributes())
+            assert_true("values" not in config_setting.attributes())
+        "#
+    ))?;
+    Ok(())
+}
+
 #[test]
 fn option_allows_none() -> anyhow::Result<()> {
     let mut tester = rule_tester();
@@ -391,3 +508,115 @@ fn returns_documentation() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_deprecated_uses() -> anyhow::Result<()> {
+    let mut tester = rule_tester();
+    tester.add_import(
+        &ImportPath::testing_new("root//:rules.bzl"),
+        indoc!(
+            r#"
+            def _impl(ctx):
+                pass
+
+            old_library = rule(
+                impl = _impl,
+                attrs = {},
+                deprecated = "Old libraries are slow",
+                replaced_by = "new_library",
+            )
+
+            new_library = rule(
+                impl = _impl,
+                attrs = {
+                    "legacy_flag": attrs.deprecated(
+                        attrs.bool(default = False),
+                        message = "Set `mode` instead",
+                    ),
+                    "mode": attrs.string(default = ""),
+                },
+            )
+            "#
+        ),
+    )?;
+
+    let eval_result = tester.eval_build_file(
+        &BuildFilePath::testing_new("root//some/package:BUCK"),
+        indoc!(
+            r#"
+            load("@root//:rules.bzl", "new_library", "old_library")
+
+            old_library(name = "old")
+            new_library(name = "legacy", legacy_flag = True)
+            new_library(name = "migrated", mode = "fast")
+            "#
+        ),
+        PackageListing::testing_empty(),
+    )?;
+    let deprecated_uses = |name: &str| {
+        eval_result
+            .targets()
+            .get(TargetNameRef::new(name).unwrap())
+            .unwrap()
+            .deprecated_uses()
+            .iter()
+            .map(|u| u.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        vec![
+            "`root//some/package:old`: rule `old_library` is deprecated: Old libraries are slow \
+            (use `new_library` instead)"
+        ],
+        deprecated_uses("old")
+    );
+    assert_eq!(
+        vec![
+            "`root//some/package:legacy`: attribute `legacy_flag` of rule `new_library` is \
+            deprecated: Set `mode` instead"
+        ],
+        deprecated_uses("legacy")
+    );
+    // Attributes left to their default don't count.
+    assert_eq!(Vec::<String>::new(), deprecated_uses("migrated"));
+    Ok(())
+}
+
+#[test]
+fn test_deprecation_errors() {
+    let mut tester = rule_tester();
+    tester.run_starlark_bzl_test_expecting_error(
+        indoc!(
+            r#"
+            def impl(ctx):
+                pass
+
+            foo_library = rule(impl = impl, attrs = {}, replaced_by = "bar_library")
+
+            def test():
+                pass
+            "#
+        ),
+        "can only be specified together with `deprecated`",
+    );
+    tester.run_starlark_bzl_test_expecting_error(
+        indoc!(
+            r#"
+            def impl(ctx):
+                pass
+
+            foo_library = rule(
+                impl = impl,
+                attrs = {
+                    "srcs": attrs.list(attrs.deprecated(attrs.string(), message = "No")),
+                },
+            )
+
+            def test():
+                pass
+            "#
+        ),
+        "cannot be used in nested attributes",
+    );
+}
@@ -141,3 +141,47 @@ simple(name = "a")
         a.visibility().unwrap(),
     );
 }
+
+#[tokio::test]
+async fn test_package_oncall() {
+    let fs = ProjectRootTemp::new().unwrap();
+
+    fs.write_file("rules.bzl", RULES_BZL);
+    fs.write_file(
+        "PACKAGE",
+        r#"
+package(
+    oncall = "root_team",
+)
+"#,
+    );
+    fs.write_file(
+        "inherited/BUCK",
+        r#"
+load("//:rules.bzl", "simple")
+simple(name = "a")
+"#,
+    );
+    fs.write_file(
+        "overridden/BUCK",
+        r#"
+load("//:rules.bzl", "simple")
+oncall("buck_file_team")
+simple(name = "b")
+"#,
+    );
+
+    let mut ctx = calculation(&fs).await;
+
+    let a = ctx
+        .get_target_node(&TargetLabel::testing_parse("root//inherited:a"))
+        .await
+        .unwrap();
+    assert_eq!(Some("root_team"), a.oncall());
+
+    let b = ctx
+        .get_target_node(&TargetLabel::testing_parse("root//overridden:b"))
+        .await
+        .unwrap();
+    assert_eq!(Some("buck_file_team"), b.oncall());
+}
@@ -16,6 +16,7 @@ use allocative::Allocative;
 use crate::attrs::attr_type::AttrType;
 use crate::attrs::coerced_attr::CoercedAttr;
 use crate::attrs::display::AttrDisplayWithContextExt;
+use crate::deprecation::Deprecation;
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash, Allocative)]
 enum AttributeDefault {
@@ -34,6 +35,8 @@ pub struct Attribute {
     /// The coercer to take this parameter's value from Starlark value -> an
     /// internal representation
     coercer: AttrType,
+    /// Set if targets should stop setting this attribute.
+    deprecation: Option<Deprecation>,
 }
 
 impl Attribute {
@@ -45,6 +48,7 @@ impl Attribute {
             },
             doc: doc.to_owned(),
             coercer,
+            deprecation: None,
         }
     }
 
@@ -53,6 +57,7 @@ impl Attribute {
             default: AttributeDefault::DefaultOnly(default),
             doc: doc.to_owned(),
             coercer,
+            deprecation: None,
         }
     }
 
@@ -75,6 +80,17 @@ impl Attribute {
     pub fn doc(&self) -> &str {
         &self.doc
     }
+
+    pub fn deprecation(&self) -> Option<&Deprecation> {
+        self.deprecation.as_ref()
+    }
+
+    pub fn with_deprecation(self, deprecation: Deprecation) -> Self {
+        Attribute {
+            deprecation: Some(deprecation),
+            ..self
+        }
+    }
 }
 
 impl Display for Attribute {
@@ -73,18 +73,33 @@ pub fn configuration_ctx() -> impl AttrConfigurationContext {
         ResolvedConfigurationSettings::new(UnorderedMap::from_iter([
             (
                 ConfigurationSettingKey::testing_parse("root//other:config"),
-                ConfigurationNode::new(Some(ConfigSettingData {
-                    constraints: BTreeMap::new(),
-                    buckconfigs: BTreeMap::new(),
-                })),
+                ConfigurationNode::new(
+                    ConfigSettingData {
+                        constraints: BTreeMap::new(),
+                        buckconfigs: BTreeMap::new(),
+                    },
+                    true,
+                ),
             ),
             (
                 ConfigurationSettingKey::testing_parse("root//some:config"),
-                ConfigurationNode::new(None),
+                ConfigurationNode::new(
+                    ConfigSettingData {
+                        constraints: BTreeMap::new(),
+                        buckconfigs: BTreeMap::new(),
+                    },
+                    false,
+                ),
             ),
             (
                 ConfigurationSettingKey::testing_parse("cell1//other:config"),
-                ConfigurationNode::new(None),
+                ConfigurationNode::new(
+                    ConfigSettingData {
+                        constraints: BTreeMap::new(),
+                        buckconfigs: BTreeMap::new(),
+                    },
+                    false,
+                ),
             ),
         ])),
     )
@@ -12,6 +12,7 @@ use std::sync::Arc;
 
 use allocative::Allocative;
 use buck2_core::configuration::config_setting::ConfigSettingData;
+use buck2_core::configuration::constraints::ConstraintKey;
 use buck2_core::configuration::pair::ConfigurationNoExec;
 use buck2_core::target::label::label::TargetLabel;
 use dupe::Dupe;
@@ -90,6 +91,22 @@ impl ResolvedConfigurationSettings {
         };
         configuration_node.configuration_data()
     }
+
+    /// The resolved settings, with whether each of them matches the configuration, in no
+    /// particular order.
+    pub fn iter_matches(&self) -> impl Iterator<Item = (&ConfigurationSettingKey, bool)> {
+        self.settings
+            .entries_unordered()
+            .map(|(key, node)| (key, node.configuration_data().is_some()))
+    }
+
+    /// The constraints of the configuration compared to resolve the settings, possibly with
+    /// duplicates.
+    pub fn constraint_keys(&self) -> impl Iterator<Item = &ConstraintKey> {
+        self.settings
+            .values_unordered()
+            .flat_map(|node| node.constraint_keys())
+    }
 }
 
 /// A ConfigurationNode contains the information about a config_setting() or similar target in a certain configuration.
@@ -98,16 +115,31 @@ pub struct ConfigurationNode(Arc<ConfigurationNodeData>);
 
 #[derive(Debug, Eq, PartialEq, Hash, Allocative)]
 struct ConfigurationNodeData {
-    /// `None` when config settings does not match the configuration.
-    config_setting: Option<ConfigSettingData>,
+    config_setting: ConfigSettingData,
+    /// Whether the config setting matches the configuration.
+    matches: bool,
 }
 
 impl ConfigurationNode {
-    pub fn new(config_setting: Option<ConfigSettingData>) -> Self {
-        Self(Arc::new(ConfigurationNodeData { config_setting }))
+    pub fn new(config_setting: ConfigSettingData, matches: bool) -> Self {
+        Self(Arc::new(ConfigurationNodeData {
+            config_setting,
+            matches,
+        }))
     }
 
+    /// `None` when config settings does not match the configuration.
     pub fn configuration_data(&self) -> Option<&ConfigSettingData> {
-        self.0.config_setting.as_ref()
+        if self.0.matches {
+            Some(&self.0.config_setting)
+        } else {
+            None
+        }
+    }
+
+    /// The constraints of the configuration compared to check whether the config setting
+    /// matches, whether it does or not.
+    pub fn constraint_keys(&self) -> impl Iterator<Item = &ConstraintKey> {
+        self.0.config_setting.constraints.keys()
     }
 }
@@ -0,0 +1,63 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Rules and attributes can be marked deprecated, so that the targets still using them get a
+//! warning pointing at their replacement while they are being migrated.
+
+use std::fmt;
+use std::fmt::Display;
+
+use allocative::Allocative;
+use buck2_core::target::label::label::TargetLabel;
+
+/// Created by `rule(deprecated = ...)` or `attrs.deprecated(...)`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Allocative)]
+pub struct Deprecation {
+    /// Why it is deprecated, and how to migrate away from it.
+    pub message: String,
+    /// The rule or attribute to use instead, if any.
+    pub replaced_by: Option<String>,
+}
+
+impl Display for Deprecation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(replaced_by) = &self.replaced_by {
+            write!(f, " (use `{}` instead)", replaced_by)?;
+        }
+        Ok(())
+    }
+}
+
+/// A target which is declared with a deprecated rule, or which sets a deprecated attribute.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Allocative)]
+pub struct DeprecatedUse {
+    pub target: TargetLabel,
+    pub rule: String,
+    /// The deprecated attribute, or `None` if the rule itself is deprecated.
+    pub attr: Option<String>,
+    pub deprecation: Deprecation,
+}
+
+impl Display for DeprecatedUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.attr {
+            None => write!(
+                f,
+                "`{}`: rule `{}` is deprecated: {}",
+                self.target, self.rule, self.deprecation
+            ),
+            Some(attr) => write!(
+                f,
+                "`{}`: attribute `{}` of rule `{}` is deprecated: {}",
+                self.target, attr, self.rule, self.deprecation
+            ),
+        }
+    }
+}
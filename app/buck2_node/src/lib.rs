@@ -16,6 +16,7 @@ pub mod call_stack;
 pub mod cfg_constructor;
 pub mod configuration;
 pub mod configured_universe;
+pub mod deprecation;
 pub mod execution;
 pub mod load_patterns;
 pub mod metadata;
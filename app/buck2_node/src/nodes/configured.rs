@@ -18,6 +18,7 @@ use allocative::Allocative;
 use buck2_core::build_file_path::BuildFilePath;
 use buck2_core::bzl::ImportPath;
 use buck2_core::cells::cell_path::CellPath;
+use buck2_core::configuration::constraints::ConstraintKey;
 use buck2_core::configuration::data::ConfigurationData;
 use buck2_core::configuration::pair::ConfigurationNoExec;
 use buck2_core::configuration::transition::applied::TransitionApplied;
@@ -58,6 +59,7 @@ use crate::attrs::internal::TESTS_ATTRIBUTE_FIELD;
 use crate::configuration::resolved::ConfigurationSettingKey;
 use crate::configuration::resolved::ResolvedConfiguration;
 use crate::configuration::resolved::ResolvedConfigurationSettings;
+use crate::deprecation::DeprecatedUse;
 use crate::nodes::attributes::DEPS;
 use crate::nodes::attributes::EXECUTION_PLATFORM;
 use crate::nodes::attributes::ONCALL;
@@ -144,6 +146,13 @@ impl TargetNodeOrForward {
         }
     }
 
+    fn deprecated_uses(&self) -> Vec<DeprecatedUse> {
+        match self {
+            TargetNodeOrForward::TargetNode(node) => node.deprecated_uses(),
+            TargetNodeOrForward::Forward(_, forward) => forward.deprecated_uses(),
+        }
+    }
+
     fn attr_or_none<'a>(
         &'a self,
         name: &str,
@@ -344,6 +353,23 @@ impl ConfiguredTargetNode {
             .filter(|x| x.rule_kind() == RuleKind::Configuration)
     }
 
+    /// The config settings inspected to configure this node (resolving its `select`s), with
+    /// whether each of them matches the configuration of the node, in no particular order.
+    ///
+    /// Along with its deps, this is all the node reads from its configuration: two nodes of the
+    /// same target with the same matches and equivalent deps are equivalent.
+    pub fn configuration_setting_matches(
+        &self,
+    ) -> impl Iterator<Item = (&ConfigurationSettingKey, bool)> {
+        self.0.resolved_configuration.settings().iter_matches()
+    }
+
+    /// The constraints of its configuration this node inspected to resolve its settings,
+    /// possibly with duplicates.
+    pub fn inspected_constraints(&self) -> impl Iterator<Item = &ConstraintKey> {
+        self.0.resolved_configuration.settings().constraint_keys()
+    }
+
     pub fn toolchain_deps(&self) -> impl Iterator<Item = &ConfiguredTargetNode> {
         // Since we validate that all toolchain dependencies are of kind Toolchain,
         // we can use that to filter the deps.
@@ -438,6 +464,11 @@ impl ConfiguredTargetNode {
         self.as_ref().oncall()
     }
 
+    #[inline]
+    pub fn deprecated_uses(&self) -> Vec<DeprecatedUse> {
+        self.as_ref().deprecated_uses()
+    }
+
     pub fn attrs<'a>(
         &'a self,
         opts: AttrInspectOptions,
@@ -642,6 +673,11 @@ impl<'a> ConfiguredTargetNodeRef<'a> {
         self.0.get().target_node.oncall()
     }
 
+    /// The deprecated rule and attributes used by the unconfigured target.
+    pub fn deprecated_uses(self) -> Vec<DeprecatedUse> {
+        self.0.get().target_node.deprecated_uses()
+    }
+
     pub fn special_attrs(self) -> impl Iterator<Item = (&'a str, ConfiguredAttr)> {
         let typ_attr = ConfiguredAttr::String(StringLiteral(self.rule_type().name().into()));
         let deps_attr = ConfiguredAttr::List(
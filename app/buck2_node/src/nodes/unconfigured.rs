@@ -39,6 +39,7 @@ use crate::attrs::traversal::CoercedAttrTraversal;
 use crate::attrs::values::AttrValues;
 use crate::call_stack::StarlarkCallStack;
 use crate::configuration::resolved::ConfigurationSettingKey;
+use crate::deprecation::DeprecatedUse;
 use crate::metadata::map::MetadataMap;
 use crate::nodes::attributes::CONFIGURATION_DEPS;
 use crate::nodes::attributes::DEPS;
@@ -235,6 +236,11 @@ impl TargetNode {
         self.as_ref().platform_deps()
     }
 
+    #[inline]
+    pub fn deprecated_uses(&self) -> Vec<DeprecatedUse> {
+        self.as_ref().deprecated_uses()
+    }
+
     /// Return `None` if attribute is not present or unknown.
     #[inline]
     pub fn attr_or_none<'a>(
@@ -440,6 +446,31 @@ impl<'a> TargetNodeRef<'a> {
             .attrs(&self.0.get().attributes, opts)
     }
 
+    /// The deprecated rule and attributes this target uses. Attributes only count if they are
+    /// set explicitly.
+    pub fn deprecated_uses(self) -> Vec<DeprecatedUse> {
+        let rule = self.rule_type().name();
+        let rule_use = self
+            .rule
+            .deprecation
+            .as_ref()
+            .map(|deprecation| DeprecatedUse {
+                target: self.label().dupe(),
+                rule: rule.to_owned(),
+                attr: None,
+                deprecation: deprecation.clone(),
+            });
+        let attr_uses = self.attrs(AttrInspectOptions::DefinedOnly).filter_map(|a| {
+            Some(DeprecatedUse {
+                target: self.label().dupe(),
+                rule: rule.to_owned(),
+                attr: Some(a.name.to_owned()),
+                deprecation: a.attr.deprecation()?.clone(),
+            })
+        });
+        rule_use.into_iter().chain(attr_uses).collect()
+    }
+
     pub fn special_attrs(self) -> impl Iterator<Item = (&'a str, CoercedAttr)> + 'a {
         let typ_attr = CoercedAttr::String(StringLiteral(self.rule_type().name().into()));
         let deps_attr = CoercedAttr::List(
@@ -666,6 +697,7 @@ pub mod testing {
                     rule_kind: RuleKind::Normal,
                     cfg: None,
                     uses_plugins: Vec::new(),
+                    deprecation: None,
                 }),
                 Arc::new(Package {
                     buildfile_path,
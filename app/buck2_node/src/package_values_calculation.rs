@@ -22,6 +22,14 @@ pub trait PackageValuesCalculation: Send + Sync + 'static {
         ctx: &mut DiceComputations<'_>,
         package: PackageLabel,
     ) -> anyhow::Result<SmallMap<MetadataKey, serde_json::Value>>;
+
+    /// Package values visible in `package`, each with the directory whose `PACKAGE` file set
+    /// it. Unlike `package_values`, this works for directories without a build file.
+    async fn package_values_with_origins(
+        &self,
+        ctx: &mut DiceComputations<'_>,
+        package: PackageLabel,
+    ) -> anyhow::Result<SmallMap<MetadataKey, (serde_json::Value, PackageLabel)>>;
 }
 
 pub static PACKAGE_VALUES_CALCULATION: LateBinding<&'static dyn PackageValuesCalculation> =
@@ -11,7 +11,10 @@ use std::borrow::Cow;
 
 use buck2_core::build_file_path::BuildFilePath;
 use buck2_core::cells::cell_path::CellPath;
+use buck2_core::configuration::data::ConfigurationData;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
 use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+use buck2_core::target::label::label::TargetLabel;
 use buck2_query::query::environment::QueryTarget;
 use buck2_query::query::graph::node::LabeledNode;
 use dupe::Dupe;
@@ -19,6 +22,7 @@ use starlark_map::Hashed;
 
 use crate::attrs::attr_type::any_matches::AnyMatches;
 use crate::attrs::configured_attr::ConfiguredAttr;
+use crate::attrs::configured_traversal::ConfiguredAttrTraversal;
 use crate::attrs::inspect_options::AttrInspectOptions;
 use crate::nodes::configured::ConfiguredTargetNode;
 use crate::nodes::configured::ConfiguredTargetNodeRef;
@@ -87,6 +91,30 @@ impl QueryTarget for ConfiguredTargetNode {
         Ok(())
     }
 
+    fn attr_deps(&self, attr: &str) -> anyhow::Result<Vec<Self::Key>> {
+        struct DepsCollector {
+            deps: Vec<ConfiguredTargetLabel>,
+        }
+        impl ConfiguredAttrTraversal for DepsCollector {
+            fn dep(&mut self, dep: &ConfiguredProvidersLabel) -> anyhow::Result<()> {
+                self.deps.push(dep.target().dupe());
+                Ok(())
+            }
+
+            fn configuration_dep(&mut self, dep: &TargetLabel) -> anyhow::Result<()> {
+                // Like uquery, return configuration deps too. Config settings are analyzed
+                // unbound.
+                self.deps.push(dep.configure(ConfigurationData::unbound()));
+                Ok(())
+            }
+        }
+        let mut collector = DepsCollector { deps: Vec::new() };
+        if let Some(attr) = self.get(attr, AttrInspectOptions::All) {
+            attr.traverse(self.label().pkg(), &mut collector)?;
+        }
+        Ok(collector.deps)
+    }
+
     fn attr_any_matches(
         attr: &Self::Attr<'_>,
         filter: &dyn Fn(&str) -> anyhow::Result<bool>,
@@ -144,3 +172,65 @@ impl<'a> LabeledNode for ConfiguredTargetNodeRef<'a> {
         ConfiguredTargetNodeRef::hashed_label(*self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::execution_types::execution::ExecutionPlatformResolution;
+    use buck2_core::plugins::PluginKindSet;
+    use buck2_core::provider::label::ProvidersLabel;
+    use buck2_util::arc_str::ArcSlice;
+
+    use super::*;
+    use crate::attrs::attr::Attribute;
+    use crate::attrs::attr_type::list::ListLiteral;
+    use crate::attrs::attr_type::AttrType;
+    use crate::attrs::coerced_attr::CoercedAttr;
+    use crate::configuration::resolved::ConfigurationSettingKey;
+    use crate::provider_id_set::ProviderIdSet;
+
+    #[test]
+    fn test_attr_deps() -> anyhow::Result<()> {
+        let cfg = ConfigurationData::testing_new();
+        let node = ConfiguredTargetNode::testing_new(
+            ConfiguredTargetLabel::testing_parse("cell//pkg:foo", cfg.dupe()),
+            "foo_rule",
+            ExecutionPlatformResolution::new(None, Vec::new()),
+            vec![
+                (
+                    "deps",
+                    Attribute::new(
+                        None,
+                        "",
+                        AttrType::list(AttrType::dep(ProviderIdSet::EMPTY, PluginKindSet::EMPTY)),
+                    ),
+                    CoercedAttr::List(ListLiteral(ArcSlice::new([CoercedAttr::Dep(
+                        ProvidersLabel::default_for(TargetLabel::testing_parse("cell//pkg:a")),
+                    )]))),
+                ),
+                (
+                    "constraints",
+                    Attribute::new(None, "", AttrType::list(AttrType::configuration_dep())),
+                    CoercedAttr::List(ListLiteral(ArcSlice::new([CoercedAttr::ConfigurationDep(
+                        ConfigurationSettingKey::testing_parse("cell//constraints:linux"),
+                    )]))),
+                ),
+            ],
+            vec![],
+        );
+
+        assert_eq!(
+            vec![ConfiguredTargetLabel::testing_parse("cell//pkg:a", cfg)],
+            node.attr_deps("deps")?
+        );
+        // Like uquery, configuration deps are returned, in the configuration they are analyzed in.
+        assert_eq!(
+            vec![ConfiguredTargetLabel::testing_parse(
+                "cell//constraints:linux",
+                ConfigurationData::unbound()
+            )],
+            node.attr_deps("constraints")?
+        );
+        assert!(node.attr_deps("missing")?.is_empty());
+        Ok(())
+    }
+}
@@ -17,6 +17,7 @@ use buck2_query::query::graph::node::LabeledNode;
 use dupe::Dupe;
 
 use crate::attrs::coerced_attr::CoercedAttr;
+use crate::attrs::coerced_deps_collector::CoercedDepsCollector;
 use crate::attrs::inspect_options::AttrInspectOptions;
 use crate::nodes::unconfigured::TargetNode;
 use crate::nodes::unconfigured::TargetNodeData;
@@ -67,6 +68,31 @@ impl QueryTarget for TargetNode {
         Some(self.tests().map(|t| t.target().dupe()))
     }
 
+    fn attr_deps(&self, attr: &str) -> anyhow::Result<Vec<Self::Key>> {
+        let mut collector = CoercedDepsCollector::new();
+        if let Some(attr) = self.attr_or_none(attr, AttrInspectOptions::All) {
+            attr.traverse(self.label().pkg(), &mut collector)?;
+        }
+        let CoercedDepsCollector {
+            deps,
+            transition_deps,
+            exec_deps,
+            toolchain_deps,
+            configuration_deps,
+            platform_deps,
+            plugin_deps: _,
+        } = collector;
+        // Configuration deps include the conditions of `select`s, which cquery has resolved.
+        Ok(deps
+            .into_iter()
+            .chain(transition_deps.into_iter().map(|(dep, _)| dep))
+            .chain(exec_deps)
+            .chain(toolchain_deps)
+            .chain(configuration_deps.into_iter().map(|dep| dep.0))
+            .chain(platform_deps)
+            .collect())
+    }
+
     fn attr_any_matches(
         attr: &Self::Attr<'_>,
         filter: &dyn Fn(&str) -> anyhow::Result<bool>,
@@ -122,3 +148,81 @@ impl QueryTarget for TargetNode {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::plugins::PluginKindSet;
+    use buck2_core::provider::label::ProvidersLabel;
+    use buck2_util::arc_str::ArcSlice;
+
+    use super::*;
+    use crate::attrs::attr::Attribute;
+    use crate::attrs::attr_type::list::ListLiteral;
+    use crate::attrs::attr_type::string::StringLiteral;
+    use crate::attrs::attr_type::AttrType;
+    use crate::configuration::resolved::ConfigurationSettingKey;
+    use crate::nodes::unconfigured::testing::TargetNodeExt;
+    use crate::provider_id_set::ProviderIdSet;
+    use crate::rule_type::RuleType;
+    use crate::rule_type::StarlarkRuleType;
+
+    #[test]
+    fn test_attr_deps() -> anyhow::Result<()> {
+        let node = TargetNode::testing_new(
+            TargetLabel::testing_parse("cell//pkg:foo"),
+            RuleType::Starlark(Arc::new(StarlarkRuleType {
+                import_path: ImportPath::testing_new("cell//pkg:rules.bzl"),
+                name: "foo_rule".to_owned(),
+            })),
+            vec![
+                (
+                    "deps",
+                    Attribute::new(
+                        None,
+                        "",
+                        AttrType::list(AttrType::dep(ProviderIdSet::EMPTY, PluginKindSet::EMPTY)),
+                    ),
+                    CoercedAttr::List(ListLiteral(ArcSlice::new([
+                        CoercedAttr::Dep(ProvidersLabel::default_for(TargetLabel::testing_parse(
+                            "cell//pkg:a",
+                        ))),
+                        CoercedAttr::Dep(ProvidersLabel::default_for(TargetLabel::testing_parse(
+                            "cell//pkg:b",
+                        ))),
+                    ]))),
+                ),
+                (
+                    "constraints",
+                    Attribute::new(None, "", AttrType::list(AttrType::configuration_dep())),
+                    CoercedAttr::List(ListLiteral(ArcSlice::new([CoercedAttr::ConfigurationDep(
+                        ConfigurationSettingKey::testing_parse("cell//constraints:linux"),
+                    )]))),
+                ),
+                (
+                    "version",
+                    Attribute::new(None, "", AttrType::string()),
+                    CoercedAttr::String(StringLiteral("1.0".into())),
+                ),
+            ],
+            vec![],
+        );
+
+        assert_eq!(
+            vec![
+                TargetLabel::testing_parse("cell//pkg:a"),
+                TargetLabel::testing_parse("cell//pkg:b"),
+            ],
+            node.attr_deps("deps")?
+        );
+        assert_eq!(
+            vec![TargetLabel::testing_parse("cell//constraints:linux")],
+            node.attr_deps("constraints")?
+        );
+        assert!(node.attr_deps("version")?.is_empty());
+        assert!(node.attr_deps("missing")?.is_empty());
+        Ok(())
+    }
+}
@@ -35,6 +35,7 @@ pub struct StarlarkRuleType {
 )]
 pub enum RuleType {
     Starlark(Arc<StarlarkRuleType>),
+    Native(NativeRuleType),
     #[display(fmt = "forward")]
     Forward,
 }
@@ -43,15 +44,56 @@ impl RuleType {
     pub fn name(&self) -> &str {
         match self {
             RuleType::Starlark(rule_type) => rule_type.name.as_str(),
+            RuleType::Native(rule_type) => rule_type.name(),
             RuleType::Forward => "forward",
         }
     }
 }
 
+/// A configuration rule implemented by buck2 itself, available in `BUCK` files without a prelude.
+/// Their analysis doesn't run any Starlark.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Dupe,
+    derive_more::Display,
+    Eq,
+    PartialEq,
+    Hash,
+    Allocative
+)]
+pub enum NativeRuleType {
+    #[display(fmt = "constraint_setting")]
+    ConstraintSetting,
+    #[display(fmt = "constraint_value")]
+    ConstraintValue,
+    #[display(fmt = "config_setting")]
+    ConfigSetting,
+}
+
+impl NativeRuleType {
+    pub const ALL: [NativeRuleType; 3] = [
+        NativeRuleType::ConstraintSetting,
+        NativeRuleType::ConstraintValue,
+        NativeRuleType::ConfigSetting,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NativeRuleType::ConstraintSetting => "constraint_setting",
+            NativeRuleType::ConstraintValue => "constraint_value",
+            NativeRuleType::ConfigSetting => "config_setting",
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use buck2_core::bzl::ImportPath;
 
+    use crate::rule_type::NativeRuleType;
+    use crate::rule_type::RuleType;
     use crate::rule_type::StarlarkRuleType;
 
     #[test]
@@ -64,4 +106,12 @@ mod tests {
             &StarlarkRuleType { import_path, name }.to_string()
         );
     }
+
+    #[test]
+    fn native_rule_type_name() {
+        for rule_type in NativeRuleType::ALL {
+            assert_eq!(rule_type.name(), rule_type.to_string());
+            assert_eq!(rule_type.name(), RuleType::Native(rule_type).name());
+        }
+    }
 }
@@ -14,6 +14,7 @@ use dupe::Dupe;
 
 use crate::cfg_constructor::CfgConstructorImpl;
 use crate::metadata::super_package_values::SuperPackageValues;
+use crate::oncall::Oncall;
 use crate::visibility::VisibilitySpecification;
 use crate::visibility::WithinViewSpecification;
 
@@ -22,6 +23,8 @@ pub(crate) struct SuperPackageData {
     package_values: Arc<dyn SuperPackageValues>,
     visibility: VisibilitySpecification,
     within_view: WithinViewSpecification,
+    /// Oncall declared by this `PACKAGE` file or inherited from the closest parent.
+    oncall: Option<Oncall>,
     /// Set only for the repo root package.
     cfg_constructor: Option<Arc<dyn CfgConstructorImpl>>,
 }
@@ -36,12 +39,14 @@ impl SuperPackage {
         package_values: Arc<dyn SuperPackageValues>,
         visibility: VisibilitySpecification,
         within_view: WithinViewSpecification,
+        oncall: Option<Oncall>,
         cfg_constructor: Option<Arc<dyn CfgConstructorImpl>>,
     ) -> SuperPackage {
         SuperPackage(Arc::new(SuperPackageData {
             package_values,
             visibility,
             within_view,
+            oncall,
             cfg_constructor,
         }))
     }
@@ -52,6 +57,7 @@ impl SuperPackage {
             VisibilitySpecification::default(),
             WithinViewSpecification::default(),
             None,
+            None,
         )
     }
 
@@ -67,6 +73,10 @@ impl SuperPackage {
         &self.0.within_view
     }
 
+    pub fn oncall(&self) -> Option<&Oncall> {
+        self.0.oncall.as_ref()
+    }
+
     pub fn cfg_constructor(&self) -> Option<&Arc<dyn CfgConstructorImpl>> {
         self.0.cfg_constructor.as_ref()
     }
@@ -78,22 +88,26 @@ impl PartialEq for SuperPackage {
             package_values: this_values,
             visibility: this_visibility,
             within_view: this_within_view,
+            oncall: this_oncall,
             cfg_constructor: this_cfg_constructor,
         } = &*self.0;
         let SuperPackageData {
             package_values: other_values,
             visibility: other_visibility,
             within_view: other_within_view,
+            oncall: other_oncall,
             cfg_constructor: other_cfg_constructor,
         } = &*other.0;
-        (this_visibility, this_within_view) == (other_visibility, other_within_view) && {
-            // If either package values are not empty, we cannot compare them
-            // because we cannot reliably compare arbitrary Starlark values.
-            // So if either package values are not empty, we consider super package not equal.
-            this_values.is_empty() && other_values.is_empty()
+        (this_visibility, this_within_view, this_oncall)
+            == (other_visibility, other_within_view, other_oncall)
+            && {
+                // If either package values are not empty, we cannot compare them
+                // because we cannot reliably compare arbitrary Starlark values.
+                // So if either package values are not empty, we consider super package not equal.
+                this_values.is_empty() && other_values.is_empty()
             &&
                 // Same logic for cfg constructors.
                 this_cfg_constructor.is_none() && other_cfg_constructor.is_none()
-        }
+            }
     }
 }
@@ -30,6 +30,7 @@ use crate::query::graph::successors::AsyncChildVisitor;
 use crate::query::graph::successors::GraphSuccessors;
 use crate::query::syntax::simple::eval::error::QueryError;
 use crate::query::syntax::simple::eval::file_set::FileSet;
+use crate::query::syntax::simple::eval::profile::record_traversed_nodes;
 use crate::query::syntax::simple::eval::set::TargetSet;
 use crate::query::traversal::AsyncNodeLookup;
 use crate::query::traversal::ChildVisitor;
@@ -109,6 +110,11 @@ pub trait QueryTarget: LabeledNode + Dupe + Send + Sync + 'static {
         None::<iter::Empty<Self::Key>>
     }
 
+    /// The targets referenced by attribute `attr`, e.g. its `deps`. `labels()` function uses this.
+    fn attr_deps(&self, _attr: &str) -> anyhow::Result<Vec<Self::Key>> {
+        Err(anyhow::anyhow!(QueryError::FunctionUnimplemented("labels")))
+    }
+
     fn attr_any_matches(
         attr: &Self::Attr<'_>,
         filter: &dyn Fn(&str) -> anyhow::Result<bool>,
@@ -230,6 +236,7 @@ pub trait QueryEnvironment: Send + Sync {
             QueryTargetDepsSuccessors,
         )
         .await?;
+        record_traversed_nodes(graph.node_count());
 
         let graph = graph.reverse();
 
@@ -386,6 +393,7 @@ pub async fn deps<Env: QueryEnvironment + ?Sized>(
             target: &Q,
             mut func: impl ChildVisitor<Q>,
         ) -> anyhow::Result<()> {
+            record_traversed_nodes(1);
             let res: anyhow::Result<_> = try {
                 match self.filter {
                     Some(filter) => {
@@ -45,6 +45,10 @@ impl<N: LabeledNode> Graph<N> {
             .get(node)
             .map(|index| &self.nodes[*index as usize].node)
     }
+
+    pub(crate) fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
 }
 
 struct GraphBuilder<N: LabeledNode> {
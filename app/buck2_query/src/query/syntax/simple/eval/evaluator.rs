@@ -9,6 +9,8 @@
 
 //! Implementation of the cli and query_* attr query language.
 
+use std::future::Future;
+
 use buck2_query_parser::parse_expr;
 use buck2_query_parser::spanned::Spanned;
 use buck2_query_parser::Expr;
@@ -21,6 +23,7 @@ use crate::query::environment::QueryEnvironment;
 use crate::query::syntax::simple::eval::error::QueryError;
 use crate::query::syntax::simple::eval::file_set::FileNode;
 use crate::query::syntax::simple::eval::file_set::FileSet;
+use crate::query::syntax::simple::eval::profile::QueryProfile;
 use crate::query::syntax::simple::eval::set::TargetSet;
 use crate::query::syntax::simple::eval::values::QueryEvaluationValue;
 use crate::query::syntax::simple::eval::values::QueryResult;
@@ -29,11 +32,28 @@ use crate::query::syntax::simple::functions::QueryFunctions;
 pub struct QueryEvaluator<'e, Env: QueryEnvironment> {
     env: &'e Env,
     functions: &'e dyn QueryFunctions<Env = Env>,
+    profile: Option<&'e QueryProfile>,
 }
 
 impl<'e, Env: QueryEnvironment> QueryEvaluator<'e, Env> {
     pub fn new(env: &'e Env, functions: &'e dyn QueryFunctions<Env = Env>) -> Self {
-        Self { env, functions }
+        Self {
+            env,
+            functions,
+            profile: None,
+        }
+    }
+
+    /// Records the evaluation time of the functions in `profile`.
+    pub fn with_profile(self, profile: Option<&'e QueryProfile>) -> Self {
+        Self { profile, ..self }
+    }
+
+    async fn profile_call<R>(&self, function: &str, call: impl Future<Output = R>) -> R {
+        match self.profile {
+            Some(profile) => profile.record_call(function, call).await,
+            None => call.await,
+        }
     }
 
     pub fn env(&self) -> &Env {
@@ -60,7 +80,10 @@ impl<'e, Env: QueryEnvironment> QueryEvaluator<'e, Env> {
                 function_name,
                 args,
             } => match self.functions.get(function_name) {
-                Some(func) => func.invoke(self, args).await,
+                Some(func) => {
+                    self.profile_call(function_name.fragment(), func.invoke(self, args))
+                        .await
+                }
                 None => Err(QueryError::UnknownFunction(
                     (*function_name.fragment()).to_owned(),
                 )),
@@ -79,7 +102,13 @@ impl<'e, Env: QueryEnvironment> QueryEvaluator<'e, Env> {
                     value = right
                         .async_into_map_res(|right| async move {
                             match self.functions.get_op(*op) {
-                                Some(func) => func.invoke(self.env(), value, right).await,
+                                Some(func) => {
+                                    self.profile_call(
+                                        func.name(),
+                                        func.invoke(self.env(), value, right),
+                                    )
+                                    .await
+                                }
                                 None => Err(QueryError::UnsupportedBinaryOp(op.to_string())),
                             }
                         })
@@ -0,0 +1,184 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Profiling of query evaluation (`buck2 cquery --profile` and friends), to find out which parts
+//! of a slow query are worth rewriting.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use dupe::Dupe;
+
+#[derive(Default)]
+struct FunctionProfile {
+    calls: u64,
+    time: Duration,
+    nodes: u64,
+}
+
+#[derive(Default)]
+struct QueryProfileData {
+    literals: u64,
+    literals_time: Duration,
+    functions: HashMap<String, FunctionProfile>,
+}
+
+/// The call being profiled, which nested calls and graph traversals report to.
+#[derive(Default)]
+struct ProfiledCall {
+    /// Time spent in nested calls, i.e. evaluating the arguments.
+    nested_time: Mutex<Duration>,
+    /// Graph nodes traversed by the call itself.
+    nodes: AtomicU64,
+}
+
+tokio::task_local! {
+    static PROFILED_CALL: Arc<ProfiledCall>;
+}
+
+/// Records that a graph walker traversed `nodes` nodes, for the profile of the query function
+/// being evaluated, if any.
+pub fn record_traversed_nodes(nodes: usize) {
+    let _ignored = PROFILED_CALL.try_with(|call| {
+        call.nodes.fetch_add(nodes as u64, Ordering::Relaxed);
+    });
+}
+
+/// Evaluation time of each function and operator of a query, and of the resolution of its
+/// literals. The time of a function excludes the evaluation of its arguments, but arguments
+/// evaluated concurrently overlap, so the times don't add up to the total.
+#[derive(Default)]
+pub struct QueryProfile {
+    data: Mutex<QueryProfileData>,
+}
+
+impl QueryProfile {
+    pub fn record_literals(&self, literals: usize, time: Duration) {
+        let mut data = self.data.lock().unwrap();
+        data.literals += literals as u64;
+        data.literals_time += time;
+    }
+
+    /// Evaluates `call`, a call of `function`, and records the time it took, less the time of the
+    /// calls nested in it, and the number of graph nodes it traversed.
+    pub async fn record_call<R>(&self, function: &str, call: impl Future<Output = R>) -> R {
+        let profiled = Arc::new(ProfiledCall::default());
+        let start = Instant::now();
+        let result = PROFILED_CALL.scope(profiled.dupe(), call).await;
+        let time = start.elapsed();
+
+        let _ignored = PROFILED_CALL.try_with(|outer| {
+            *outer.nested_time.lock().unwrap() += time;
+        });
+        let nested_time = *profiled.nested_time.lock().unwrap();
+
+        let mut data = self.data.lock().unwrap();
+        let profile = data.functions.entry(function.to_owned()).or_default();
+        profile.calls += 1;
+        profile.time += time.saturating_sub(nested_time);
+        profile.nodes += profiled.nodes.load(Ordering::Relaxed);
+        result
+    }
+}
+
+/// A table of the functions, the slowest first.
+impl Display for QueryProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let data = self.data.lock().unwrap();
+        writeln!(
+            f,
+            "Resolved {} literals in {:.3}s",
+            data.literals,
+            data.literals_time.as_secs_f64()
+        )?;
+
+        let mut functions: Vec<_> = data.functions.iter().collect();
+        functions.sort_by(|(a, a_profile), (b, b_profile)| {
+            b_profile.time.cmp(&a_profile.time).then_with(|| a.cmp(b))
+        });
+        writeln!(
+            f,
+            "{:<24} {:>8} {:>10} {:>12}",
+            "function", "calls", "self time", "nodes"
+        )?;
+        for (function, profile) in functions {
+            writeln!(
+                f,
+                "{:<24} {:>8} {:>9.3}s {:>12}",
+                function,
+                profile.calls,
+                profile.time.as_secs_f64(),
+                profile.nodes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_call() {
+        let profile = QueryProfile::default();
+        let result = profile
+            .record_call("deps", async {
+                record_traversed_nodes(3);
+                profile
+                    .record_call("rdeps", async {
+                        record_traversed_nodes(5);
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        "result"
+                    })
+                    .await
+            })
+            .await;
+        assert_eq!("result", result);
+        // Outside of a profiled call.
+        record_traversed_nodes(7);
+
+        let data = profile.data.lock().unwrap();
+        let deps = &data.functions["deps"];
+        let rdeps = &data.functions["rdeps"];
+        assert_eq!((1, 3), (deps.calls, deps.nodes));
+        assert_eq!((1, 5), (rdeps.calls, rdeps.nodes));
+        // The time of `rdeps` isn't counted as the time of `deps`.
+        assert!(rdeps.time >= Duration::from_millis(50));
+        assert!(deps.time < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_display() {
+        let profile = QueryProfile::default();
+        profile.record_literals(2, Duration::from_millis(1500));
+        profile.data.lock().unwrap().functions.insert(
+            "deps".to_owned(),
+            FunctionProfile {
+                calls: 2,
+                time: Duration::from_millis(250),
+                nodes: 10,
+            },
+        );
+        assert_eq!(
+            "Resolved 2 literals in 1.500s\n\
+            function                    calls  self time        nodes\n\
+            deps                            2     0.250s           10\n",
+            profile.to_string()
+        );
+    }
+}
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Display;
 
@@ -210,8 +211,36 @@ impl<T: QueryTarget> TargetSet<T> {
     }
 
     pub fn kind(&self, regex: &str) -> anyhow::Result<TargetSet<T>> {
-        let re = Regex::new(regex)?;
-        self.filter(|node| Ok(re.is_match(&node.rule_type())?))
+        self.kind_regex(&Regex::new(regex)?)
+    }
+
+    /// Filter targets by rule type using an already compiled regex partial match.
+    ///
+    /// Large sets usually contain only a handful of distinct rule types, so the
+    /// regex is evaluated once per rule type rather than once per node.
+    pub fn kind_regex(&self, re: &Regex) -> anyhow::Result<TargetSet<T>> {
+        let mut matched_rule_types: HashMap<String, bool> = HashMap::new();
+        let mut targets = LabelIndexedSet::new();
+        for target in self.targets.iter() {
+            let rule_type = target.rule_type();
+            let is_match = match matched_rule_types.get(rule_type.as_ref()) {
+                Some(is_match) => *is_match,
+                None => {
+                    let is_match = re.is_match(&rule_type)?;
+                    matched_rule_types.insert(rule_type.into_owned(), is_match);
+                    is_match
+                }
+            };
+            if is_match {
+                targets.insert_unique_unchecked(target.dupe());
+            }
+        }
+        Ok(Self { targets })
+    }
+
+    /// Filter targets whose rule type is exactly `rule_type`.
+    pub fn exactly_kind(&self, rule_type: &str) -> anyhow::Result<TargetSet<T>> {
+        self.filter(|node| Ok(node.rule_type() == rule_type))
     }
 
     pub fn intersect(&self, right: &TargetSet<T>) -> anyhow::Result<TargetSet<T>> {
@@ -12,6 +12,7 @@
 #![cfg(test)]
 
 use std::borrow::Cow;
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use buck2_core::build_file_path::BuildFilePath;
@@ -30,24 +31,40 @@ use crate::query::syntax::simple::eval::error::QueryError;
 use crate::query::syntax::simple::eval::evaluator::QueryEvaluator;
 use crate::query::syntax::simple::eval::file_set::FileSet;
 use crate::query::syntax::simple::eval::set::TargetSet;
+use crate::query::syntax::simple::functions::DefaultQueryFunctions;
 use crate::query::syntax::simple::functions::DefaultQueryFunctionsModule;
 
 #[derive(Clone, Hash, PartialEq, Eq, Debug, Display)]
-struct TargetRef(String);
+pub(crate) struct TargetRef(String);
 
 impl NodeKey for TargetRef {}
 
 #[derive(Debug, Display)]
-struct TargetAttr(String);
+pub(crate) struct TargetAttr(String);
 
 #[derive(Debug, Clone, Dupe, Eq, PartialEq)]
-struct Target {}
+pub(crate) struct Target(Arc<TargetData>);
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct TargetData {
+    key: TargetRef,
+    rule_type: String,
+}
+
+impl Target {
+    fn new(name: &str, rule_type: &str) -> Self {
+        Self(Arc::new(TargetData {
+            key: TargetRef(name.to_owned()),
+            rule_type: rule_type.to_owned(),
+        }))
+    }
+}
 
 impl LabeledNode for Target {
     type Key = TargetRef;
 
     fn node_key(&self) -> &Self::Key {
-        unimplemented!()
+        &self.0.key
     }
 }
 
@@ -59,7 +76,7 @@ impl QueryTarget for Target {
     }
 
     fn rule_type(&self) -> Cow<str> {
-        unimplemented!()
+        Cow::Borrowed(&self.0.rule_type)
     }
 
     fn name(&self) -> Cow<str> {
@@ -123,7 +140,7 @@ impl QueryTarget for Target {
     }
 }
 
-struct Env;
+pub(crate) struct Env;
 #[async_trait]
 impl QueryEnvironment for Env {
     type Target = Target;
@@ -198,3 +215,52 @@ pub async fn test_missing_arg() -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+fn rule_targets() -> TargetSet<Target> {
+    [
+        Target::new("a", "java_library"),
+        Target::new("b", "java_binary"),
+        Target::new("c", "cxx_library"),
+        Target::new("d", "cxx_library_wrapper"),
+    ]
+    .into_iter()
+    .collect()
+}
+
+fn names(targets: &TargetSet<Target>) -> Vec<&str> {
+    targets.iter().map(|t| t.node_key().0.as_str()).collect()
+}
+
+#[test]
+fn test_kind() -> anyhow::Result<()> {
+    let functions = DefaultQueryFunctions::<Env>::new();
+    let targets = rule_targets();
+    assert_eq!(vec!["a", "b"], names(&functions.kind("java.*", &targets)?));
+    // Regexes are not anchored.
+    assert_eq!(
+        vec!["c", "d"],
+        names(&functions.kind("cxx_library", &targets)?)
+    );
+    assert_eq!(
+        vec!["c"],
+        names(&functions.kind("^cxx_library$", &targets)?)
+    );
+    // Inline flags.
+    assert_eq!(vec!["a"], names(&functions.kind("(?i)JAVA_LIB", &targets)?));
+    assert!(functions.kind("JAVA_LIB", &targets)?.is_empty());
+    assert!(functions.kind("(", &targets).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_exactly_kind() -> anyhow::Result<()> {
+    let functions = DefaultQueryFunctions::<Env>::new();
+    let targets = rule_targets();
+    assert_eq!(
+        vec!["c"],
+        names(&functions.exactly_kind("cxx_library", &targets)?)
+    );
+    // The rule type is not a regex.
+    assert!(functions.exactly_kind("java.*", &targets)?.is_empty());
+    Ok(())
+}
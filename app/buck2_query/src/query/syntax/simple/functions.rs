@@ -7,9 +7,12 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::sync::Arc;
+use std::sync::Mutex;
 
 use allocative::Allocative;
 use async_trait::async_trait;
@@ -18,9 +21,12 @@ use buck2_query_derive::query_module;
 use buck2_query_parser::spanned::Spanned;
 use buck2_query_parser::BinaryOp;
 use buck2_query_parser::Expr;
+use dupe::Dupe;
+use fancy_regex::Regex;
 use gazebo::variants::VariantName;
 
 use crate::query::environment::QueryEnvironment;
+use crate::query::environment::QueryTarget;
 use crate::query::syntax::simple::eval::error::QueryError;
 use crate::query::syntax::simple::eval::evaluator::QueryEvaluator;
 use crate::query::syntax::simple::eval::file_set::FileSet;
@@ -343,19 +349,43 @@ impl<Env: QueryEnvironment> DefaultQueryFunctionsModule<Env> {
     /// The `kind(regex, targets)` operator evaluates the specified target expression, `targets`, and returns the targets where the rule type matches the specified `regex`.
     /// The specified pattern can be a regular expression. For example,
     /// `buck2 query "kind('java.*', deps('//foo:bar'))"` returns the targets that match the rule type `java.*` (`java_library`, `java_binary`, etc.) in the transitive dependencies of `//foo:bar`.
+    ///
+    /// Inline flags are supported, so `kind('(?i)JAVA_.*', ...)` matches rule types case-insensitively.
     async fn kind(&self, regex: String, targets: TargetSet<Env::Target>) -> QueryFuncResult<Env> {
-        Ok(targets.kind(&regex)?.into())
+        Ok(self.implementation.kind(&regex, &targets)?.into())
+    }
+
+    /// The `exactly_kind(rule_type, targets)` operator returns the targets whose rule type is exactly `rule_type`.
+    /// Unlike `kind()`, the argument is not a regex, so `exactly_kind(cxx_library, ...)` does not match `cxx_library_wrapper`.
+    async fn exactly_kind(
+        &self,
+        rule_type: String,
+        targets: TargetSet<Env::Target>,
+    ) -> QueryFuncResult<Env> {
+        Ok(self
+            .implementation
+            .exactly_kind(&rule_type, &targets)?
+            .into())
     }
 
-    /// This function is not implemented, and won't be, because buck2 query core does not support
-    /// returning both files and targets from a single function.
+    /// The `labels(attribute, targets)` operator returns the targets referenced by the given attribute of the given targets.
     ///
-    /// In buck1 it returns targets and files referenced by the given attribute
-    /// in the given targets.
+    /// For example, `buck2 query "labels(deps, //foo:bar)"` returns the direct `deps` of `//foo:bar`, and `buck2 cquery "labels(srcs, //foo:bar)"` returns the targets generating its sources.
     ///
-    /// Some discussion in T126638795.
-    async fn labels(&self, attr: String, targets: TargetSet<Env::Target>) -> QueryFuncResult<Env> {
-        self.implementation.labels(&attr, &targets)
+    /// Configuration deps, e.g. those of `target_compatible_with`, are returned too. In `query`, this includes the conditions of the `select`s of the attribute.
+    ///
+    /// Unlike buck1, source files referenced by the attribute are not returned, because a query function can't return both files and targets: use `inputs()` to get them.
+    async fn labels(
+        &self,
+        env: &Env,
+        attr: String,
+        targets: TargetSet<Env::Target>,
+    ) -> QueryFuncResult<Env> {
+        Ok(self
+            .implementation
+            .labels(env, &attr, &targets)
+            .await?
+            .into())
     }
 
     /// The `owner(inputfile)` operator returns the targets that own the specified inputfile.
@@ -366,6 +396,8 @@ impl<Env: QueryEnvironment> DefaultQueryFunctionsModule<Env> {
     /// It is possible for the specified file to have multiple owners, in which case, owner() returns a set of targets.
     ///
     /// If no owner for the file is found, owner() outputs the message: `No owner was found for <file>`
+    ///
+    /// In cquery, the file can also be a generated file in `buck-out`, such as `buck-out/v2/gen/root/<hash>/foo/__bar__/bar.h`: its owner is the target with an action producing it (`//foo:bar`), in the configuration the file was built in. Finding it requires the analysis of that target.
     async fn owner(&self, env: &Env, files: FileSet) -> QueryFuncResult<Env> {
         Ok(self.implementation.owner(env, &files).await?.into())
     }
@@ -512,15 +544,30 @@ impl<Env: QueryEnvironment> DefaultQueryFunctionsModule<Env> {
 #[derive(Allocative)]
 #[allocative(bound = "")]
 pub struct DefaultQueryFunctions<Env: QueryEnvironment> {
+    /// Regexes used by `kind()` in this evaluation, keyed by pattern. `kind()` is commonly
+    /// repeated with the same pattern (e.g. in a `deps()` filter expression), so compile once.
+    #[allocative(skip)]
+    kind_regexes: Mutex<HashMap<String, Arc<Regex>>>,
     _marker: std::marker::PhantomData<Env>,
 }
 
 impl<Env: QueryEnvironment> DefaultQueryFunctions<Env> {
     pub fn new() -> Self {
         Self {
+            kind_regexes: Mutex::new(HashMap::new()),
             _marker: PhantomData,
         }
     }
+
+    fn kind_regex(&self, regex: &str) -> anyhow::Result<Arc<Regex>> {
+        let mut kind_regexes = self.kind_regexes.lock().unwrap();
+        if let Some(re) = kind_regexes.get(regex) {
+            return Ok(re.dupe());
+        }
+        let re = Arc::new(Regex::new(regex)?);
+        kind_regexes.insert(regex.to_owned(), re.dupe());
+        Ok(re)
+    }
 }
 
 impl<Env: QueryEnvironment> DefaultQueryFunctions<Env> {
@@ -642,12 +689,37 @@ impl<Env: QueryEnvironment> DefaultQueryFunctions<Env> {
         targets.inputs()
     }
 
-    pub fn labels(
+    pub fn kind(
         &self,
-        _attr: &str,
-        _targets: &TargetSet<Env::Target>,
-    ) -> Result<QueryValue<Env::Target>, QueryError> {
-        Err(QueryError::FunctionUnimplemented("labels"))
+        regex: &str,
+        targets: &TargetSet<Env::Target>,
+    ) -> anyhow::Result<TargetSet<Env::Target>> {
+        targets.kind_regex(&self.kind_regex(regex)?)
+    }
+
+    pub fn exactly_kind(
+        &self,
+        rule_type: &str,
+        targets: &TargetSet<Env::Target>,
+    ) -> anyhow::Result<TargetSet<Env::Target>> {
+        targets.exactly_kind(rule_type)
+    }
+
+    pub async fn labels(
+        &self,
+        env: &Env,
+        attr: &str,
+        targets: &TargetSet<Env::Target>,
+    ) -> anyhow::Result<TargetSet<Env::Target>> {
+        let mut labels = TargetSet::new();
+        for target in targets.iter() {
+            for label in target.attr_deps(attr)? {
+                if !labels.contains(&label) {
+                    labels.insert(env.get_node(&label).await?);
+                }
+            }
+        }
+        Ok(labels)
     }
 
     pub async fn owner(
@@ -795,3 +867,23 @@ impl<'a, Env: QueryEnvironment> QueryFunctions for AugmentedQueryFunctions<'a, E
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::syntax::simple::eval::tests::Env;
+
+    #[test]
+    fn test_kind_regex_cache() {
+        let functions = DefaultQueryFunctions::<Env>::new();
+        let re = functions.kind_regex("java.*").unwrap();
+        assert!(Arc::ptr_eq(&re, &functions.kind_regex("java.*").unwrap()));
+        // Flags are part of the pattern.
+        let re_i = functions.kind_regex("(?i)java.*").unwrap();
+        assert!(!Arc::ptr_eq(&re, &re_i));
+        assert!(re_i.is_match("JAVA_LIBRARY").unwrap());
+        // Invalid patterns are not cached.
+        assert!(functions.kind_regex("(").is_err());
+        assert_eq!(2, functions.kind_regexes.lock().unwrap().len());
+    }
+}
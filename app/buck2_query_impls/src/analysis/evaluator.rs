@@ -9,6 +9,8 @@
 
 //! Implementation of common cquery/uquery pieces.
 
+use std::time::Instant;
+
 use anyhow::Context;
 use buck2_common::scope::scope_and_collect_with_dispatcher;
 use buck2_events::dispatch::EventDispatcher;
@@ -16,9 +18,11 @@ use buck2_query::query::environment::QueryEnvironment;
 use buck2_query::query::syntax::simple::eval::evaluator::QueryEvaluator;
 use buck2_query::query::syntax::simple::eval::literals::extract_target_literals;
 use buck2_query::query::syntax::simple::eval::multi_query::MultiQueryResult;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfile;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationValue;
 use buck2_query::query::syntax::simple::functions::QueryFunctions;
+use buck2_query_parser::macros::QueryMacros;
 use buck2_query_parser::multi_query::MaybeMultiQuery;
 use buck2_query_parser::multi_query::MultiQueryItem;
 use futures::Future;
@@ -30,6 +34,8 @@ pub(crate) async fn eval_query<
 >(
     dispatcher: EventDispatcher,
     functions: &F,
+    query_macros: &QueryMacros,
+    profile: Option<&QueryProfile>,
     query: &str,
     query_args: &[String],
     environment: impl Fn(Vec<String>) -> Fut + Send + Sync,
@@ -37,11 +43,20 @@ pub(crate) async fn eval_query<
     let query = MaybeMultiQuery::parse(query, query_args)?;
     match query {
         MaybeMultiQuery::MultiQuery(queries) => {
-            let results = process_multi_query(dispatcher, functions, environment, &queries).await?;
+            let results = process_multi_query(
+                dispatcher,
+                functions,
+                query_macros,
+                profile,
+                environment,
+                &queries,
+            )
+            .await?;
             Ok(QueryEvaluationResult::Multiple(results))
         }
         MaybeMultiQuery::SingleQuery(query) => {
-            let result = eval_single_query(functions, &query, environment).await?;
+            let result =
+                eval_single_query(functions, query_macros, profile, &query, environment).await?;
             Ok(QueryEvaluationResult::Single(result))
         }
     }
@@ -53,6 +68,8 @@ async fn eval_single_query<
     Fut: Future<Output = anyhow::Result<Env>>,
 >(
     functions: &F,
+    query_macros: &QueryMacros,
+    profile: Option<&QueryProfile>,
     query: &str,
     environment: impl Fn(Vec<String>) -> Fut,
 ) -> anyhow::Result<QueryEvaluationValue<<Env as QueryEnvironment>::Target>>
@@ -61,14 +78,25 @@ where
     Env: QueryEnvironment,
     Fut: Future<Output = anyhow::Result<Env>>,
 {
-    let literals = extract_target_literals(functions, query)?;
+    let query = query_macros.expand(query)?;
+    let literals = extract_target_literals(functions, &query)?;
+    let literals_count = literals.len();
+    let start = Instant::now();
     let env = environment(literals).await?;
-    QueryEvaluator::new(&env, functions).eval_query(query).await
+    if let Some(profile) = profile {
+        profile.record_literals(literals_count, start.elapsed());
+    }
+    QueryEvaluator::new(&env, functions)
+        .with_profile(profile)
+        .eval_query(&query)
+        .await
 }
 
 async fn process_multi_query<Env, EnvFut, Qf>(
     dispatcher: EventDispatcher,
     functions: &Qf,
+    query_macros: &QueryMacros,
+    profile: Option<&QueryProfile>,
     env: impl Fn(Vec<String>) -> EnvFut + Send + Sync,
     queries: &[MultiQueryItem],
 ) -> anyhow::Result<MultiQueryResult<Env::Target>>
@@ -86,7 +114,8 @@ where
                 let env = &env;
                 scope.spawn_cancellable(
                     async move {
-                        let result = eval_single_query(functions, &query.query, env);
+                        let result =
+                            eval_single_query(functions, query_macros, profile, &query.query, env);
                         let result: buck2_error::Result<_> = result.await.map_err(|e| e.into());
                         (i, arg, result)
                     },
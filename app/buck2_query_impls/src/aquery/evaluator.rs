@@ -14,6 +14,7 @@ use buck2_build_api::actions::query::ActionQueryNode;
 use buck2_common::events::HasEvents;
 use buck2_common::global_cfg_options::GlobalCfgOptions;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfile;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use dice::LinearRecomputeDiceComputations;
 use dupe::Dupe;
@@ -24,6 +25,7 @@ use crate::aquery::environment::AqueryEnvironment;
 use crate::aquery::functions::aquery_functions;
 use crate::dice::aquery::DiceAqueryDelegate;
 use crate::dice::get_dice_query_delegate;
+use crate::query_macros::get_query_macros;
 use crate::uquery::environment::PreresolvedQueryLiterals;
 
 pub(crate) struct AqueryEvaluator<'c, 'd> {
@@ -35,9 +37,11 @@ impl AqueryEvaluator<'_, '_> {
         &self,
         query: &str,
         query_args: &[String],
+        profile: Option<&QueryProfile>,
     ) -> anyhow::Result<QueryEvaluationResult<ActionQueryNode>> {
         let functions = aquery_functions();
 
+        let query_macros = get_query_macros(&mut self.dice_query_delegate.ctx()).await?;
         eval_query(
             self.dice_query_delegate
                 .ctx()
@@ -45,6 +49,8 @@ impl AqueryEvaluator<'_, '_> {
                 .get_dispatcher()
                 .dupe(),
             &functions,
+            &query_macros,
+            profile,
             query,
             query_args,
             |literals| async move {
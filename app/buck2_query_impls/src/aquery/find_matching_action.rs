@@ -116,6 +116,24 @@ async fn find_matching_action(
     .await
 }
 
+/// Whether an action of `analysis` declares `path_after_target_name` as an output, or an output
+/// directory containing it.
+pub(crate) fn declares_output(
+    analysis: &AnalysisResult,
+    path_after_target_name: &ForwardRelativePathBuf,
+) -> anyhow::Result<bool> {
+    for entry in analysis.iter_deferreds() {
+        if let Some(outputs) = provider::request_value::<ProvideOutputs>(entry.as_complex()) {
+            for build_artifact in &outputs.0? {
+                if check_output_path(build_artifact, path_after_target_name)?.is_some() {
+                    return Ok(true);
+                }
+            }
+        }
+    }
+    Ok(false)
+}
+
 pub(crate) fn init_find_matching_action() {
     FIND_MATCHING_ACTION.init(
         |ctx, working_dir, global_cfg_options, analysis, path_after_target_name| {
@@ -129,3 +147,45 @@ pub(crate) fn init_find_matching_action() {
         },
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use buck2_artifact::artifact::artifact_type::testing::BuildArtifactTestingExt;
+    use buck2_artifact::deferred::id::DeferredId;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::package::PackageLabel;
+    use buck2_core::target::label::label::TargetLabel;
+    use buck2_core::target::name::TargetNameRef;
+
+    use super::*;
+
+    #[test]
+    fn test_check_output_path() -> anyhow::Result<()> {
+        let target = TargetLabel::new(
+            PackageLabel::testing_new("cell", "pkg"),
+            TargetNameRef::unchecked_new("foo"),
+        )
+        .configure(ConfigurationData::testing_new());
+        let artifact = BuildArtifact::testing_new(
+            target,
+            ForwardRelativePathBuf::unchecked_new("out/dir".to_owned()),
+            DeferredId::testing_new(0),
+        );
+        let check = |path: &str| {
+            check_output_path(
+                &artifact,
+                &ForwardRelativePathBuf::unchecked_new(path.to_owned()),
+            )
+        };
+
+        assert!(matches!(check("out/dir")?, Some(ActionKeyMatch::Exact(_))));
+        assert!(matches!(
+            check("out/dir/file")?,
+            Some(ActionKeyMatch::OutputsOf(_))
+        ));
+        // Paths are compared by components, not as strings.
+        assert!(check("out/dir2")?.is_none());
+        assert!(check("out")?.is_none());
+        Ok(())
+    }
+}
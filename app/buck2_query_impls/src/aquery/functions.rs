@@ -14,6 +14,7 @@ use std::marker::PhantomData;
 use buck2_artifact::artifact::provide_outputs::ProvideActionKey;
 use buck2_build_api::actions::query::ActionQueryNode;
 use buck2_build_api::actions::query::ActionQueryNodeData;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_query::query::syntax::simple::eval::error::QueryError;
 use buck2_query::query::syntax::simple::eval::set::TargetSet;
 use buck2_query::query::syntax::simple::eval::values::QueryValue;
@@ -23,6 +24,7 @@ use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
 use buck2_query::query::syntax::simple::functions::QueryFunctions;
 use buck2_query::query_module;
 use buck2_query_parser::BinaryOp;
+use fancy_regex::Regex;
 
 use crate::aquery::environment::AqueryEnvironment;
 
@@ -131,4 +133,59 @@ impl<'a> AqueryFunctions<'a> {
 
         Ok(res.into())
     }
+
+    /// Filter `actions` down to the actions with an output whose path in buck-out matches
+    /// `regex`. For example, `outputs('foo\.o$', deps(//foo:bar))` finds the actions producing
+    /// `foo.o` among the actions needed to build `//foo:bar`.
+    ///
+    /// Use the `inputs` and `outputs` attributes to see all the inputs and outputs an action
+    /// declares.
+    pub(crate) async fn outputs(
+        &self,
+        regex: String,
+        actions: TargetSet<ActionQueryNode>,
+    ) -> Result<QueryValue<ActionQueryNode>, QueryError> {
+        let regex = Regex::new(&regex).map_err(anyhow::Error::from)?;
+
+        let mut res = TargetSet::new();
+        for node in actions.into_iter() {
+            if any_path_matches(&regex, &node.output_paths()?)? {
+                res.insert(node);
+            }
+        }
+
+        Ok(res.into())
+    }
+}
+
+fn any_path_matches(regex: &Regex, paths: &[ProjectRelativePathBuf]) -> anyhow::Result<bool> {
+    for path in paths {
+        if regex.is_match(path.as_str())? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_any_path_matches() -> anyhow::Result<()> {
+        let paths = [
+            ProjectRelativePathBuf::unchecked_new(
+                "buck-out/v2/gen/root/abc/foo/__bar__/bar.o".to_owned(),
+            ),
+            ProjectRelativePathBuf::unchecked_new(
+                "buck-out/v2/gen/root/abc/foo/__bar__/bar.d".to_owned(),
+            ),
+        ];
+        assert!(any_path_matches(&Regex::new(r"bar\.d$")?, &paths)?);
+        // The regex is matched against the whole path in buck-out, not only the file name.
+        assert!(any_path_matches(&Regex::new(r"foo/__bar__/")?, &paths)?);
+        assert!(!any_path_matches(&Regex::new(r"bar\.o\.d$")?, &paths)?);
+        assert!(!any_path_matches(&Regex::new(r"bar")?, &[])?);
+        Ok(())
+    }
 }
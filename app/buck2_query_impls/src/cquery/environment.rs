@@ -10,9 +10,16 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
+use buck2_build_api::buck_out_path_parser::lookup_configuration;
+use buck2_build_api::buck_out_path_parser::BuckOutPathParser;
+use buck2_build_api::buck_out_path_parser::BuckOutPathType;
 use buck2_build_api::query::oneshot::CqueryOwnerBehavior;
+use buck2_common::dice::cells::HasCellResolver;
 use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::CellResolver;
 use buck2_core::configuration::compatibility::MaybeCompatible;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
 use buck2_core::target::label::label::TargetLabel;
 use buck2_error::BuckErrorContext;
@@ -30,6 +37,7 @@ use buck2_query::query::graph::dfs::dfs_postorder;
 use buck2_query::query::graph::successors::AsyncChildVisitor;
 use buck2_query::query::syntax::simple::eval::error::QueryError;
 use buck2_query::query::syntax::simple::eval::file_set::FileSet;
+use buck2_query::query::syntax::simple::eval::profile::record_traversed_nodes;
 use buck2_query::query::syntax::simple::eval::set::TargetSet;
 use buck2_query::query::syntax::simple::functions::docs::QueryEnvironmentDescription;
 use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
@@ -40,6 +48,7 @@ use dice::DiceComputations;
 use dupe::Dupe;
 use tracing::warn;
 
+use crate::aquery::find_matching_action::declares_output;
 use crate::uquery::environment::allbuildfiles;
 use crate::uquery::environment::rbuildfiles;
 use crate::uquery::environment::QueryLiterals;
@@ -186,6 +195,76 @@ impl<'c> CqueryEnvironment<'c> {
         Ok(owners)
     }
 
+    /// The owner of a generated file is the target whose output it is, in the configuration found
+    /// in its buck-out path, if one of its actions declares that output. With the correct owner
+    /// behavior, it must also be in the universe.
+    async fn owner_generated(
+        &self,
+        cell_resolver: &CellResolver,
+        path: &ProjectRelativePath,
+    ) -> anyhow::Result<Vec<ConfiguredTargetNode>> {
+        // Like other paths without an owner, this isn't an error.
+        let Ok(parsed) = BuckOutPathParser::new(cell_resolver).parse(path.as_str()) else {
+            return Ok(Vec::new());
+        };
+        let (target_label, config_hash, path_after_target_name) = match parsed {
+            BuckOutPathType::RuleOutput {
+                target_label,
+                common_attrs,
+                path_after_target_name,
+                ..
+            } => (
+                target_label,
+                common_attrs.config_hash,
+                path_after_target_name,
+            ),
+            // Outputs of anonymous targets, BXL and tests, and scratch files, aren't outputs of a
+            // configured target.
+            _ => return Ok(Vec::new()),
+        };
+
+        let configured_target_label = self.delegate.get_configured_target(&target_label).await?;
+        let configured_target_label =
+            if configured_target_label.cfg().output_hash().as_str() == config_hash {
+                configured_target_label
+            } else {
+                match lookup_configuration(&config_hash) {
+                    Some(cfg) => target_label.configure(cfg),
+                    None => {
+                        warn!(
+                            "Configuration with hash `{}` of {} is not known to this daemon",
+                            config_hash, path
+                        );
+                        return Ok(Vec::new());
+                    }
+                }
+            };
+
+        if let CqueryOwnerBehavior::Correct = self.owner_behavior {
+            let universe = self
+                .universe
+                .as_ref()
+                .internal_error("Target universe not specified")?;
+            if !universe.contains(&configured_target_label) {
+                return Ok(Vec::new());
+            }
+        }
+
+        let analysis = match self
+            .delegate
+            .ctx()
+            .get_analysis_result(&configured_target_label)
+            .await?
+        {
+            MaybeCompatible::Compatible(analysis) => analysis,
+            MaybeCompatible::Incompatible(_) => return Ok(Vec::new()),
+        };
+        if !declares_output(&analysis, &path_after_target_name)? {
+            return Ok(Vec::new());
+        }
+        Ok(vec![self.get_node(&configured_target_label).await?])
+    }
+
     fn owner_correct(&self, path: &CellPath) -> anyhow::Result<Vec<ConfiguredTargetNode>> {
         let universe = self
             .universe
@@ -265,11 +344,18 @@ impl<'c> QueryEnvironment for CqueryEnvironment<'c> {
 
     async fn owner(&self, paths: &FileSet) -> anyhow::Result<TargetSet<Self::Target>> {
         let mut result = TargetSet::new();
+        let cell_resolver = self.delegate.ctx().get_cell_resolver().await?;
 
         for path in paths.iter() {
-            let owners = match &self.owner_behavior {
-                CqueryOwnerBehavior::Deprecated => self.owner_deprecated(path).await?,
-                CqueryOwnerBehavior::Correct => self.owner_correct(path)?,
+            let project_path = cell_resolver.resolve_path(path.as_ref())?;
+            let owners = if project_path.starts_with(ProjectRelativePath::unchecked_new("buck-out"))
+            {
+                self.owner_generated(&cell_resolver, &project_path).await?
+            } else {
+                match &self.owner_behavior {
+                    CqueryOwnerBehavior::Deprecated => self.owner_deprecated(path).await?,
+                    CqueryOwnerBehavior::Correct => self.owner_correct(path)?,
+                }
             };
             if owners.is_empty() {
                 warn!("No owner was found for {}", path);
@@ -304,6 +390,7 @@ impl<'c> QueryEnvironment for CqueryEnvironment<'c> {
                     Ok(())
                 },
             )?;
+            record_traversed_nodes(deps.len());
             Ok(deps)
         } else {
             deps(self, targets, depth, filter).await
@@ -16,6 +16,7 @@ use buck2_common::events::HasEvents;
 use buck2_events::dispatch::console_message;
 use buck2_node::configured_universe::CqueryUniverse;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfile;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
 use dice::DiceComputations;
@@ -28,6 +29,7 @@ use crate::analysis::evaluator::eval_query;
 use crate::cquery::environment::CqueryEnvironment;
 use crate::dice::DiceQueryData;
 use crate::dice::DiceQueryDelegate;
+use crate::query_macros::get_query_macros;
 use crate::uquery::environment::PreresolvedQueryLiterals;
 use crate::uquery::environment::QueryLiterals;
 use crate::uquery::environment::UqueryDelegate;
@@ -38,6 +40,7 @@ pub(crate) async fn eval_cquery(
     query: &str,
     query_args: &[String],
     target_universe: Option<&[String]>,
+    profile: Option<&QueryProfile>,
 ) -> anyhow::Result<QueryEvaluationResult<ConfiguredTargetNode>> {
     let dispatcher = dice_query_delegate
         .ctx()
@@ -60,10 +63,13 @@ pub(crate) async fn eval_cquery(
     };
 
     let target_universe = &target_universe;
+    let query_macros = get_query_macros(&mut dice_query_delegate.ctx()).await?;
 
     eval_query(
         dispatcher,
         &functions,
+        &query_macros,
+        profile,
         query,
         query_args,
         |literals| async move {
@@ -14,8 +14,10 @@ use std::sync::Arc;
 use anyhow::Context;
 use async_trait::async_trait;
 use buck2_artifact::actions::key::ActionKey;
+use buck2_artifact::artifact::artifact_type::BaseArtifactKind;
 use buck2_build_api::actions::artifact::get_artifact_fs::GetArtifactFs;
 use buck2_build_api::actions::calculation::ActionCalculation;
+use buck2_build_api::actions::impls::action_retry_policy::HasActionRetryPolicy;
 use buck2_build_api::actions::query::iter_action_inputs;
 use buck2_build_api::actions::query::ActionInput;
 use buck2_build_api::actions::query::ActionQueryNode;
@@ -243,8 +245,29 @@ fn compute_action_node<'c>(
 ) -> BoxFuture<'c, buck2_error::Result<ActionQueryNode>> {
     async move {
         let action = ActionCalculation::get_action(ctx, &key).await?;
-        let deps = convert_inputs(ctx, node_cache, action.inputs()?.iter()).await?;
-        Ok(ActionQueryNode::new_action(action, deps, fs))
+        let inputs = action.inputs()?;
+        let sources = inputs
+            .iter()
+            .filter_map(|input| match input.unpack_artifact()?.as_parts() {
+                (BaseArtifactKind::Source(source), projected) => {
+                    let path = source.get_path().to_cell_path();
+                    Some(match projected {
+                        Some(projected) => path.join(projected),
+                        None => path,
+                    })
+                }
+                (BaseArtifactKind::Build(..), _) => None,
+            })
+            .collect();
+        let deps = convert_inputs(ctx, node_cache, inputs.iter()).await?;
+        let retry_policy = ctx.per_transaction_data().get_action_retry_policy();
+        Ok(ActionQueryNode::new_action(
+            action,
+            deps,
+            sources,
+            fs,
+            retry_policy,
+        ))
     }
     .boxed()
 }
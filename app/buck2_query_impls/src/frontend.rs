@@ -18,6 +18,7 @@ use buck2_node::configured_universe::CqueryUniverse;
 use buck2_node::configured_universe::UNIVERSE_FROM_LITERALS;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfile;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use dice::DiceComputations;
 
@@ -41,10 +42,11 @@ impl QueryFrontend for QueryFrontendImpl {
         working_dir: &ProjectRelativePath,
         query: &str,
         query_args: &[String],
+        profile: Option<&QueryProfile>,
     ) -> anyhow::Result<QueryEvaluationResult<TargetNode>> {
         ctx.with_linear_recompute(|ctx| async move {
             let evaluator = get_uquery_evaluator(&ctx, working_dir).await?;
-            evaluator.eval_query(query, query_args).await
+            evaluator.eval_query(query, query_args, profile).await
         })
         .await
     }
@@ -58,6 +60,7 @@ impl QueryFrontend for QueryFrontendImpl {
         query_args: &[String],
         global_cfg_options: GlobalCfgOptions,
         target_universe: Option<&[String]>,
+        profile: Option<&QueryProfile>,
     ) -> anyhow::Result<QueryEvaluationResult<ConfiguredTargetNode>> {
         ctx.with_linear_recompute(|ctx| async move {
             let dice_query_delegate =
@@ -75,6 +78,7 @@ impl QueryFrontend for QueryFrontendImpl {
                 query,
                 query_args,
                 target_universe.as_ref().map(|v| &v[..]),
+                profile,
             )
             .await
         })
@@ -88,10 +92,11 @@ impl QueryFrontend for QueryFrontendImpl {
         query: &str,
         query_args: &[String],
         global_cfg_options: GlobalCfgOptions,
+        profile: Option<&QueryProfile>,
     ) -> anyhow::Result<QueryEvaluationResult<ActionQueryNode>> {
         ctx.with_linear_recompute(|ctx| async move {
             let evaluator = get_aquery_evaluator(&ctx, working_dir, global_cfg_options).await?;
-            evaluator.eval_query(query, query_args).await
+            evaluator.eval_query(query, query_args, profile).await
         })
         .await
     }
@@ -18,6 +18,7 @@ pub(crate) mod cquery;
 mod description;
 pub(crate) mod dice;
 pub(crate) mod frontend;
+pub(crate) mod query_macros;
 pub(crate) mod uquery;
 
 pub fn init_late_bindings() {
@@ -0,0 +1,79 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! The query macros of the project, read from the file named by the root buckconfig
+//! `buck2.query_macros_file` (relative to the project root).
+
+use std::sync::Arc;
+
+use allocative::Allocative;
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::dice::file_ops::DiceFileComputations;
+use buck2_common::legacy_configs::dice::HasLegacyConfigs;
+use buck2_common::legacy_configs::key::BuckconfigKeyRef;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_futures::cancellation::CancellationContext;
+use buck2_query_parser::macros::QueryMacros;
+use derive_more::Display;
+use dice::DiceComputations;
+use dice::Key;
+use dupe::Dupe;
+
+#[derive(Hash, Eq, PartialEq, Clone, Dupe, Display, Debug, Allocative)]
+#[display(fmt = "{:?}", self)]
+struct QueryMacrosKey;
+
+#[async_trait]
+impl Key for QueryMacrosKey {
+    type Value = buck2_error::Result<Arc<QueryMacros>>;
+
+    async fn compute(
+        &self,
+        ctx: &mut DiceComputations,
+        _cancellations: &CancellationContext,
+    ) -> Self::Value {
+        let cell_resolver = ctx.get_cell_resolver().await?;
+        let path = ctx
+            .get_legacy_config_property(
+                cell_resolver.root_cell(),
+                BuckconfigKeyRef {
+                    section: "buck2",
+                    property: "query_macros_file",
+                },
+            )
+            .await?;
+        let Some(path) = path else {
+            return Ok(Arc::new(QueryMacros::default()));
+        };
+        let path = cell_resolver.get_cell_path(ProjectRelativePath::new(&path)?)?;
+        let content = DiceFileComputations::read_file(ctx, path.as_ref()).await?;
+        let macros = QueryMacros::parse(&content)
+            .with_context(|| format!("Error parsing query macros file `{}`", path))?;
+        Ok(Arc::new(macros))
+    }
+
+    fn validity(x: &Self::Value) -> bool {
+        x.is_ok()
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        match (x, y) {
+            (Ok(x), Ok(y)) => x == y,
+            _ => false,
+        }
+    }
+}
+
+pub(crate) async fn get_query_macros(
+    ctx: &mut DiceComputations<'_>,
+) -> anyhow::Result<Arc<QueryMacros>> {
+    Ok(ctx.compute(&QueryMacrosKey).await??)
+}
@@ -14,6 +14,7 @@ use buck2_common::events::HasEvents;
 use buck2_common::global_cfg_options::GlobalCfgOptions;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfile;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use buck2_query::query::syntax::simple::functions::DefaultQueryFunctionsModule;
 use dice::LinearRecomputeDiceComputations;
@@ -22,6 +23,7 @@ use dupe::Dupe;
 use crate::analysis::evaluator::eval_query;
 use crate::dice::get_dice_query_delegate;
 use crate::dice::DiceQueryDelegate;
+use crate::query_macros::get_query_macros;
 use crate::uquery::environment::PreresolvedQueryLiterals;
 use crate::uquery::environment::UqueryEnvironment;
 
@@ -35,7 +37,9 @@ impl UqueryEvaluator<'_, '_> {
         &self,
         query: &str,
         query_args: &[String],
+        profile: Option<&QueryProfile>,
     ) -> anyhow::Result<QueryEvaluationResult<TargetNode>> {
+        let query_macros = get_query_macros(&mut self.dice_query_delegate.ctx()).await?;
         eval_query(
             self.dice_query_delegate
                 .ctx()
@@ -43,6 +47,8 @@ impl UqueryEvaluator<'_, '_> {
                 .get_dispatcher()
                 .dupe(),
             &self.functions,
+            &query_macros,
+            profile,
             query,
             query_args,
             |literals| async move {
@@ -0,0 +1,426 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! User-defined query macros.
+//!
+//! A macro file contains definitions like:
+//!
+//! ```text
+//! # Comments start with `#`.
+//! first_party(x) = deps(x) except filter('//third-party', deps(x))
+//! # Indented lines continue the previous definition.
+//! first_party_tests(x, kinds) =
+//!     kind(kinds, first_party(x))
+//! ```
+//!
+//! A call to a macro in a query is replaced with its body, in which the unquoted words equal to a
+//! parameter name are replaced with the corresponding argument. In `set()` and `fileset()`, an
+//! argument which isn't a word is added to the set with `+`. Macros can call other macros, but not
+//! recursively, and take precedence over the built-in functions of the same name.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use allocative::Allocative;
+use anyhow::Context;
+use gazebo::prelude::*;
+
+use crate::parse_expr;
+use crate::span::Span;
+use crate::Expr;
+use crate::SpannedExpr;
+
+#[derive(Debug, buck2_error::Error)]
+#[buck2(input)]
+enum QueryMacrosError {
+    #[error("Expected `name(param, ...) = expression` in query macro definition, got `{0}`")]
+    InvalidDefinition(String),
+    #[error("Query macro `{0}` is defined more than once")]
+    DuplicateMacro(String),
+    #[error("Query macro `{name}` takes {expected} arguments, but was called with {got}")]
+    WrongArgumentCount {
+        name: String,
+        expected: usize,
+        got: usize,
+    },
+    #[error("Query macro `{0}` calls itself")]
+    RecursiveMacro(String),
+}
+
+#[derive(Debug, PartialEq, Allocative)]
+struct QueryMacro {
+    params: Vec<String>,
+    body: String,
+}
+
+/// Query macros, by name.
+#[derive(Debug, Default, PartialEq, Allocative)]
+pub struct QueryMacros {
+    macros: HashMap<String, QueryMacro>,
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+/// Splits `name(param, ...) = body`.
+fn parse_definition(definition: &str) -> Option<(&str, Vec<&str>, &str)> {
+    let (header, body) = definition.split_once('=')?;
+    let (name, params) = header.trim().strip_suffix(')')?.split_once('(')?;
+    let name = name.trim();
+    let params = if params.trim().is_empty() {
+        Vec::new()
+    } else {
+        params.split(',').map(str::trim).collect()
+    };
+    if !is_identifier(name) || !params.iter().all(|p| is_identifier(p)) {
+        return None;
+    }
+    Some((name, params, body.trim()))
+}
+
+impl QueryMacros {
+    /// Parses the content of a macro file.
+    pub fn parse(content: &str) -> anyhow::Result<QueryMacros> {
+        let mut definitions: Vec<String> = Vec::new();
+        for line in content.lines() {
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            match definitions.last_mut() {
+                Some(definition) if line.starts_with(char::is_whitespace) => {
+                    definition.push(' ');
+                    definition.push_str(line.trim());
+                }
+                _ => definitions.push(line.trim().to_owned()),
+            }
+        }
+
+        let mut macros = HashMap::new();
+        for definition in &definitions {
+            let (name, params, body) = parse_definition(definition)
+                .ok_or_else(|| QueryMacrosError::InvalidDefinition(definition.clone()))?;
+            parse_expr(body).with_context(|| format!("In body of query macro `{}`", name))?;
+            let query_macro = QueryMacro {
+                params: params.map(|p| (*p).to_owned()),
+                body: body.to_owned(),
+            };
+            if macros.insert(name.to_owned(), query_macro).is_some() {
+                return Err(QueryMacrosError::DuplicateMacro(name.to_owned()).into());
+            }
+        }
+        Ok(QueryMacros { macros })
+    }
+
+    /// Replaces the calls to macros in `query`. The query is returned unchanged if it doesn't
+    /// call any macro.
+    pub fn expand(&self, query: &str) -> anyhow::Result<String> {
+        if self.macros.is_empty() {
+            return Ok(query.to_owned());
+        }
+        let expr = parse_expr(query)?;
+        let mut expander = Expander {
+            macros: self,
+            stack: Vec::new(),
+            expanded: false,
+        };
+        let mut out = String::new();
+        expander.write_expr(&expr, query, &HashMap::new(), &mut out)?;
+        if expander.expanded {
+            Ok(out)
+        } else {
+            Ok(query.to_owned())
+        }
+    }
+}
+
+/// An argument of a macro call, with the macros it calls expanded.
+struct MacroArg {
+    text: String,
+    /// Whether the argument is a single word, so it can be written in `set()`.
+    is_word: bool,
+}
+
+/// A word written without quotes, which is how parameters are referred to.
+fn unquoted_word<'a>(expr: &SpannedExpr<'a>) -> Option<&'a str> {
+    match expr.value {
+        // Quoted words are two characters longer than their content.
+        Expr::String(word) if expr.position.len() == word.len() => Some(word),
+        _ => None,
+    }
+}
+
+struct Expander<'m> {
+    macros: &'m QueryMacros,
+    /// The macros being expanded, to detect recursion.
+    stack: Vec<&'m str>,
+    expanded: bool,
+}
+
+impl<'m> Expander<'m> {
+    /// Writes `expr`, parsed from `source`, with the macro calls expanded, and the words in `args`
+    /// replaced. Returns whether what was written is a single word.
+    fn write_expr(
+        &mut self,
+        expr: &SpannedExpr,
+        source: &str,
+        args: &HashMap<&str, MacroArg>,
+        out: &mut String,
+    ) -> anyhow::Result<bool> {
+        let is_word = match &expr.value {
+            // The arguments are either atoms or parenthesized, so can be substituted as is.
+            Expr::String(_) => match unquoted_word(expr).and_then(|word| args.get(word)) {
+                Some(arg) => {
+                    out.push_str(&arg.text);
+                    arg.is_word
+                }
+                None => {
+                    write!(out, "{}", expr)?;
+                    true
+                }
+            },
+            Expr::Integer(_) => {
+                write!(out, "{}", expr)?;
+                true
+            }
+            Expr::Set(words) => {
+                self.write_set("set", words, source, args, out)?;
+                false
+            }
+            Expr::FileSet(words) => {
+                self.write_set("fileset", words, source, args, out)?;
+                false
+            }
+            Expr::Function {
+                function_name,
+                args: call_args,
+            } => {
+                let call_args = call_args.try_map(|arg| {
+                    let mut text = String::new();
+                    let is_word = self.write_expr(arg, source, args, &mut text)?;
+                    anyhow::Ok(MacroArg { text, is_word })
+                })?;
+                match self.macros.macros.get_key_value(*function_name.fragment()) {
+                    Some((name, query_macro)) => {
+                        self.write_macro(name, query_macro, call_args, out)?
+                    }
+                    None => write!(
+                        out,
+                        "{}({})",
+                        function_name.fragment(),
+                        call_args.map(|arg| arg.text.as_str()).join(", ")
+                    )?,
+                }
+                false
+            }
+            Expr::BinaryOpSequence(left, ops) => {
+                // Parenthesized like the `Display` of `Expr`, so that the arguments can't change
+                // the precedence.
+                out.push_str(&"(".repeat(ops.len()));
+                self.write_expr(left, source, args, out)?;
+                for (op, right) in ops {
+                    write!(out, " {} ", op)?;
+                    self.write_expr(right, source, args, out)?;
+                    out.push(')');
+                }
+                false
+            }
+        };
+        Ok(is_word)
+    }
+
+    /// Writes `set(...)` or `fileset(...)` with the parameters among `words` replaced. Arguments
+    /// which aren't words can't be written in the set, so they are added to it with `+`.
+    fn write_set(
+        &mut self,
+        function: &str,
+        words: &[Span],
+        source: &str,
+        args: &HashMap<&str, MacroArg>,
+        out: &mut String,
+    ) -> anyhow::Result<()> {
+        let mut set_words = Vec::new();
+        let mut exprs = Vec::new();
+        for word in words {
+            let offset = word.location_offset();
+            let quoted = offset > 0 && matches!(source.as_bytes()[offset - 1], b'\'' | b'"');
+            let arg = if quoted {
+                None
+            } else {
+                args.get(*word.fragment())
+            };
+            match arg {
+                Some(arg) if arg.is_word => set_words.push(arg.text.as_str()),
+                Some(arg) => exprs.push(arg.text.as_str()),
+                // Written back quoted if it was, like `Display` does.
+                None if quoted => {
+                    set_words.push(&source[offset - 1..offset + word.fragment().len() + 1])
+                }
+                None => set_words.push(word.fragment()),
+            }
+        }
+
+        if exprs.is_empty() {
+            write!(out, "{}({})", function, set_words.join(" "))?;
+            return Ok(());
+        }
+        out.push('(');
+        if !set_words.is_empty() {
+            write!(out, "{}({}) + ", function, set_words.join(" "))?;
+        }
+        out.push_str(&exprs.join(" + "));
+        out.push(')');
+        Ok(())
+    }
+
+    fn write_macro(
+        &mut self,
+        name: &'m str,
+        query_macro: &'m QueryMacro,
+        call_args: Vec<MacroArg>,
+        out: &mut String,
+    ) -> anyhow::Result<()> {
+        if query_macro.params.len() != call_args.len() {
+            return Err(QueryMacrosError::WrongArgumentCount {
+                name: name.to_owned(),
+                expected: query_macro.params.len(),
+                got: call_args.len(),
+            }
+            .into());
+        }
+        if self.stack.contains(&name) {
+            return Err(QueryMacrosError::RecursiveMacro(name.to_owned()).into());
+        }
+
+        let args: HashMap<&str, MacroArg> = query_macro
+            .params
+            .iter()
+            .map(|p| p.as_str())
+            .zip(call_args)
+            .collect();
+        // Checked when the macros were parsed.
+        let body = parse_expr(&query_macro.body)?;
+        self.stack.push(name);
+        self.expanded = true;
+        out.push('(');
+        self.write_expr(&body, &query_macro.body, &args, out)?;
+        out.push(')');
+        self.stack.pop();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn macros() -> QueryMacros {
+        QueryMacros::parse(
+            r#"
+# Comment.
+first_party(x) = deps(x) except filter('//third-party', deps(x))
+first_party_of_kind(kinds, x) =
+    kind(kinds, first_party(x))
+everything() = //...
+loop(x) = loop(x)
+with_base(x) = set(//base x 'x')
+named_x(x) = attrfilter(name, 'x', x) + attrfilter(name, "x", x)
+"#,
+        )
+        .unwrap()
+    }
+
+    /// Compares the parsed queries, which ignores redundant parentheses.
+    fn assert_expands(macros: &QueryMacros, expected: &str, query: &str) -> anyhow::Result<()> {
+        assert_eq!(
+            parse_expr(expected)?.to_string(),
+            parse_expr(&macros.expand(query)?)?.to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_expand() -> anyhow::Result<()> {
+        let macros = macros();
+        assert_expands(
+            &macros,
+            "deps(//foo:bar) except filter('//third-party', deps(//foo:bar))",
+            "first_party(//foo:bar)",
+        )?;
+        assert_expands(
+            &macros,
+            "kind(cxx_.*, deps(//a + //b) except filter('//third-party', deps(//a + //b)))",
+            "first_party_of_kind(cxx_.*, //a + //b)",
+        )?;
+        assert_expands(&macros, "//... ^ //foo", "everything() ^ //foo")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_expand_in_set() -> anyhow::Result<()> {
+        let macros = macros();
+        assert_expands(&macros, "set(//base //foo:bar 'x')", "with_base(//foo:bar)")?;
+        assert_expands(
+            &macros,
+            "set(//base '//foo:bar baz' 'x')",
+            "with_base('//foo:bar baz')",
+        )?;
+        assert_expands(
+            &macros,
+            "set(//base 'x') + deps(//foo:bar)",
+            "with_base(deps(//foo:bar))",
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_expand_ignores_quoted_words() -> anyhow::Result<()> {
+        let macros = macros();
+        assert_expands(
+            &macros,
+            "attrfilter(name, 'x', //foo:bar) + attrfilter(name, 'x', //foo:bar)",
+            "named_x(//foo:bar)",
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_expand_without_macros() -> anyhow::Result<()> {
+        let macros = macros();
+        assert_eq!("deps(//foo:bar, 2)", macros.expand("deps(//foo:bar, 2)")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        let macros = macros();
+        let err = format!("{:#}", macros.expand("first_party(//a, //b)").unwrap_err());
+        assert!(
+            err.contains("takes 1 arguments, but was called with 2"),
+            "{}",
+            err
+        );
+        let err = format!("{:#}", macros.expand("loop(//a)").unwrap_err());
+        assert!(err.contains("`loop` calls itself"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(QueryMacros::parse("foo = deps(x)").is_err());
+        assert!(QueryMacros::parse("foo(x y) = deps(x)").is_err());
+        assert!(QueryMacros::parse("foo(x) = deps(x").is_err());
+        assert!(QueryMacros::parse("foo(x) = x\nfoo(y) = y").is_err());
+    }
+}
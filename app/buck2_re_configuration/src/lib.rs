@@ -23,6 +23,9 @@ static BUCK2_RE_CLIENT_CFG_SECTION: &str = "buck2_re_client";
 pub trait RemoteExecutionStaticMetadataImpl: Sized {
     fn from_legacy_config(legacy_config: &LegacyBuckConfig) -> anyhow::Result<Self>;
     fn cas_semaphore_size(&self) -> usize;
+    /// Whether a remote action cache is configured, i.e. whether looking up actions in it can
+    /// succeed.
+    fn has_action_cache(&self) -> bool;
 }
 
 #[allow(unused)]
@@ -205,6 +208,11 @@ mod fbcode {
         fn cas_semaphore_size(&self) -> usize {
             self.cas_connection_count as usize * 30
         }
+
+        fn has_action_cache(&self) -> bool {
+            // The client has a default address.
+            true
+        }
     }
 }
 
@@ -227,6 +235,10 @@ mod not_fbcode {
             // FIXME: make this configurable?
             1024
         }
+
+        fn has_action_cache(&self) -> bool {
+            self.0.action_cache_address.is_some()
+        }
     }
 }
 
@@ -266,6 +278,16 @@ pub struct Buck2OssReConfiguration {
     pub instance_name: Option<String>,
     /// Use the Meta version of the request metadata
     pub use_fbcode_metadata: bool,
+    /// How many blobs too large to be batched (which are streamed instead) to upload at once.
+    pub max_concurrent_large_uploads: Option<usize>,
+    /// The digest algorithm used for RE, e.g. `SHA256`. This isn't read from the
+    /// `buck2_re_client` section but set by the daemon (from `buck2.digest_algorithms`), so that
+    /// the client can check the server supports it.
+    pub digest_function: Option<String>,
+    /// Use a remote execution backend running in the daemon instead of the addresses above, which
+    /// keeps the CAS and the action cache in memory and runs actions locally. This is for testing
+    /// the remote code paths of Buck2 and of rules without an RE cluster.
+    pub fake: bool,
 }
 
 #[derive(Clone, Debug, Default, Allocative)]
@@ -354,6 +376,17 @@ impl Buck2OssReConfiguration {
                     property: "use_fbcode_metadata",
                 })?
                 .unwrap_or(true),
+            max_concurrent_large_uploads: legacy_config.parse(BuckconfigKeyRef {
+                section: BUCK2_RE_CLIENT_CFG_SECTION,
+                property: "max_concurrent_large_uploads",
+            })?,
+            digest_function: None,
+            fake: legacy_config
+                .parse(BuckconfigKeyRef {
+                    section: BUCK2_RE_CLIENT_CFG_SECTION,
+                    property: "fake",
+                })?
+                .unwrap_or(false),
         })
     }
 }
@@ -0,0 +1,163 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use buck2_build_api::actions::execute::dice_data::GetReClient;
+use buck2_cli_proto::new_generic::ActionInputDiffEntry;
+use buck2_cli_proto::new_generic::ActionInputDiffRequest;
+use buck2_cli_proto::new_generic::ActionInputDiffResponse;
+use buck2_core::directory::Directory;
+use buck2_core::directory::DirectoryEntry;
+use buck2_core::directory::DirectoryIterator;
+use buck2_core::execution_types::executor_config::RemoteExecutorUseCase;
+use buck2_execute::digest_config::DigestConfig;
+use buck2_execute::digest_config::HasDigestConfig;
+use buck2_execute::directory::download_action_input_directory;
+use buck2_execute::execute::action_digest::ActionDigest;
+use buck2_execute::re::manager::ManagedRemoteExecutionClient;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::partial_result_dispatcher::NoPartialResult;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::template::run_server_command;
+use buck2_server_ctx::template::ServerCommandTemplate;
+use dice::DiceTransaction;
+
+use crate::ctx::ServerCommandContext;
+
+pub(crate) async fn action_input_diff_command(
+    context: &ServerCommandContext<'_>,
+    partial_result_dispatcher: PartialResultDispatcher<NoPartialResult>,
+    req: ActionInputDiffRequest,
+) -> anyhow::Result<ActionInputDiffResponse> {
+    run_server_command(
+        ActionInputDiffServerCommand { req },
+        context,
+        partial_result_dispatcher,
+    )
+    .await
+}
+
+struct ActionInputDiffServerCommand {
+    req: ActionInputDiffRequest,
+}
+
+/// The inputs of an action, keyed by path. Directories are included so that empty directories
+/// show up, but compare equal: a difference in their contents is reported for the files instead.
+async fn action_inputs(
+    re_client: &ManagedRemoteExecutionClient,
+    action_digest: &str,
+    use_case: RemoteExecutorUseCase,
+    digest_config: DigestConfig,
+) -> anyhow::Result<BTreeMap<String, String>> {
+    let (action_digest, _) =
+        ActionDigest::parse_digest(action_digest, digest_config.cas_digest_config())?;
+    let input_root =
+        download_action_input_directory(&action_digest, re_client, use_case, digest_config).await?;
+
+    let mut inputs = BTreeMap::new();
+    for (path, entry) in input_root.ordered_walk().with_paths() {
+        let description = match entry {
+            DirectoryEntry::Dir(..) => "Directory".to_owned(),
+            DirectoryEntry::Leaf(leaf) => leaf.to_string(),
+        };
+        inputs.insert(path.to_string(), description);
+    }
+    Ok(inputs)
+}
+
+fn diff_inputs(
+    left: &BTreeMap<String, String>,
+    right: &BTreeMap<String, String>,
+) -> Vec<ActionInputDiffEntry> {
+    let mut paths: Vec<&String> = left.keys().chain(right.keys()).collect();
+    paths.sort();
+    paths.dedup();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let left = left.get(path);
+            let right = right.get(path);
+            if left == right {
+                return None;
+            }
+            Some(ActionInputDiffEntry {
+                path: path.clone(),
+                left: left.cloned(),
+                right: right.cloned(),
+            })
+        })
+        .collect()
+}
+
+#[async_trait]
+impl ServerCommandTemplate for ActionInputDiffServerCommand {
+    type StartEvent = buck2_data::ActionInputDiffCommandStart;
+    type EndEvent = buck2_data::ActionInputDiffCommandEnd;
+    type Response = ActionInputDiffResponse;
+    type PartialResult = NoPartialResult;
+
+    async fn command(
+        &self,
+        _server_ctx: &dyn ServerCommandContextTrait,
+        _partial_result_dispatcher: PartialResultDispatcher<Self::PartialResult>,
+        ctx: DiceTransaction,
+    ) -> anyhow::Result<Self::Response> {
+        let digest_config = ctx.global_data().get_digest_config();
+        let re_client = &ctx.per_transaction_data().get_re_client();
+        let use_case = match &self.req.use_case {
+            Some(use_case) => RemoteExecutorUseCase::new(use_case.clone()),
+            None => RemoteExecutorUseCase::buck2_default(),
+        };
+
+        let (left, right) = futures::future::try_join(
+            action_inputs(re_client, &self.req.left, use_case, digest_config),
+            action_inputs(re_client, &self.req.right, use_case, digest_config),
+        )
+        .await?;
+        Ok(ActionInputDiffResponse {
+            entries: diff_inputs(&left, &right),
+        })
+    }
+
+    fn is_success(&self, _response: &Self::Response) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_inputs() {
+        let inputs = |entries: &[(&str, &str)]| {
+            entries
+                .iter()
+                .map(|(k, v)| ((*k).to_owned(), (*v).to_owned()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let left = inputs(&[("a", "1"), ("b", "2"), ("c", "3")]);
+        let right = inputs(&[("b", "2"), ("c", "4"), ("d", "5")]);
+
+        let diff: Vec<_> = diff_inputs(&left, &right)
+            .into_iter()
+            .map(|e| (e.path, e.left, e.right))
+            .collect();
+        assert_eq!(
+            diff,
+            vec![
+                ("a".to_owned(), Some("1".to_owned()), None),
+                ("c".to_owned(), Some("3".to_owned()), Some("4".to_owned())),
+                ("d".to_owned(), None, Some("5".to_owned())),
+            ]
+        );
+    }
+}
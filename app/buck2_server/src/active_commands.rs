@@ -9,9 +9,13 @@
 
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 
 use buck2_cli_proto::ClientContext;
+use buck2_cli_proto::CommandProgress;
 use buck2_event_observer::dice_state::DiceState;
 use buck2_event_observer::pending_estimate::pending_estimate;
 use buck2_event_observer::span_tracker;
@@ -25,11 +29,36 @@ use dupe::Dupe;
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
 use parking_lot::MutexGuard;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::oneshot;
+use tokio::sync::Notify;
 
 static ACTIVE_COMMANDS: Lazy<Mutex<HashMap<TraceId, ActiveCommandHandle>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 
+/// Reattachable commands which finished less than `REATTACH_GRACE_PERIOD` ago, so that a client
+/// which lost its connection to one can still get its result.
+static FINISHED_COMMANDS: Lazy<Mutex<HashMap<TraceId, (Instant, Arc<ActiveCommandOutput>)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// How long a reattachable command waits for a client to attach to it, both while it runs
+/// without any client and after it finished.
+pub static REATTACH_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+fn remove_expired(finished: &mut HashMap<TraceId, (Instant, Arc<ActiveCommandOutput>)>) {
+    finished.retain(|_, (finished_at, _)| finished_at.elapsed() < REATTACH_GRACE_PERIOD);
+}
+
+/// The output of a command which is running, or finished recently if it is reattachable.
+pub fn command_output(trace_id: &TraceId) -> Option<Arc<ActiveCommandOutput>> {
+    if let Some(cmd) = ACTIVE_COMMANDS.lock().get(trace_id) {
+        return Some(cmd.output.dupe());
+    }
+    let mut finished = FINISHED_COMMANDS.lock();
+    remove_expired(&mut finished);
+    finished.get(trace_id).map(|(_, output)| output.dupe())
+}
+
 /// Return the active commands, if you can access them.
 pub fn try_active_commands() -> Option<HashMap<TraceId, ActiveCommandHandle>> {
     // Note that this function is accessed during panic, so have to be super careful
@@ -60,6 +89,168 @@ pub fn broadcast_shutdown(shutdown: &buck2_data::DaemonShutdown) {
     }
 }
 
+/// Makes the returned channel the one notified when the daemon shuts down while this command is
+/// running, in place of the channel of its previous client. The channel is closed right away if
+/// the command already finished.
+pub fn subscribe_shutdown(trace_id: &TraceId) -> oneshot::Receiver<buck2_data::DaemonShutdown> {
+    let (sender, receiver) = oneshot::channel();
+    if let Some(cmd) = ACTIVE_COMMANDS.lock().get(trace_id) {
+        *cmd.daemon_shutdown_channel.lock() = Some(sender);
+    }
+    receiver
+}
+
+/// How many events a reattachable command keeps while no client is attached. Beyond that, the
+/// oldest ones are dropped.
+const MAX_DETACHED_EVENTS: usize = 100_000;
+
+#[derive(Debug, buck2_error::Error)]
+pub enum AttachError {
+    #[error("No command with trace id `{0}` is running")]
+    NoSuchCommand(TraceId),
+    #[error("Command `{0}` was not started with `--reattachable`")]
+    NotReattachable(TraceId),
+}
+
+pub type CommandOutputSender = UnboundedSender<Result<CommandProgress, tonic::Status>>;
+
+/// The client end of a command's event stream. Commands that are not reattachable drop their
+/// events once their client went away. Reattachable ones buffer them until another client
+/// attaches.
+pub struct ActiveCommandOutput {
+    trace_id: TraceId,
+    reattachable: bool,
+    inner: Mutex<ActiveCommandOutputInner>,
+    attached: Notify,
+    detached: Notify,
+}
+
+struct ActiveCommandOutputInner {
+    sender: Option<CommandOutputSender>,
+    /// Incremented on every attach, so that a client going away does not detach its successor.
+    client: u64,
+    detached_events: VecDeque<CommandProgress>,
+    /// Whether the command sent its result, i.e. whether the event stream ends.
+    finished: bool,
+}
+
+/// Detaches its client from the command when dropped, i.e. when the response stream is.
+pub struct AttachedClient {
+    output: Arc<ActiveCommandOutput>,
+    client: u64,
+}
+
+impl Drop for AttachedClient {
+    fn drop(&mut self) {
+        let mut inner = self.output.inner.lock();
+        if inner.client == self.client {
+            inner.sender = None;
+            drop(inner);
+            self.output.detached.notify_one();
+        }
+    }
+}
+
+impl ActiveCommandOutput {
+    fn new(trace_id: TraceId, reattachable: bool) -> Self {
+        Self {
+            trace_id,
+            reattachable,
+            inner: Mutex::new(ActiveCommandOutputInner {
+                sender: None,
+                client: 0,
+                detached_events: VecDeque::new(),
+                finished: false,
+            }),
+            attached: Notify::new(),
+            detached: Notify::new(),
+        }
+    }
+
+    pub fn reattachable(&self) -> bool {
+        self.reattachable
+    }
+
+    /// Sends an event to the attached client, or buffers it if there is none.
+    pub fn send(&self, progress: CommandProgress) {
+        let mut inner = self.inner.lock();
+        if let Some(sender) = &inner.sender {
+            // This only fails if the client is going away, in which case it will get detached.
+            let _ignored = sender.send(Ok(progress));
+            return;
+        }
+        if !self.reattachable || inner.finished {
+            return;
+        }
+        if inner.detached_events.len() >= MAX_DETACHED_EVENTS {
+            inner.detached_events.pop_front();
+        }
+        inner.detached_events.push_back(progress);
+    }
+
+    /// Ends the event stream of the attached client, if any. Called once the command won't
+    /// produce any more events.
+    pub fn finish(&self) {
+        let mut inner = self.inner.lock();
+        inner.finished = true;
+        inner.sender = None;
+    }
+
+    /// Makes `sender` the client of this command, after flushing the events buffered while no
+    /// client was attached to it. This takes over from the previous client, if any, whose stream
+    /// ends.
+    pub fn attach(
+        self: &Arc<Self>,
+        sender: CommandOutputSender,
+    ) -> Result<AttachedClient, AttachError> {
+        let mut inner = self.inner.lock();
+        for progress in inner.detached_events.drain(..) {
+            // If this client is already gone, the next one won't see these, which is fine.
+            let _ignored = sender.send(Ok(progress));
+        }
+        inner.client += 1;
+        if !inner.finished {
+            inner.sender = Some(sender);
+        }
+        let client = AttachedClient {
+            output: self.dupe(),
+            client: inner.client,
+        };
+        drop(inner);
+        self.attached.notify_one();
+        Ok(client)
+    }
+
+    /// Like `attach`, but for clients other than the one which started the command.
+    pub fn reattach(
+        self: &Arc<Self>,
+        sender: CommandOutputSender,
+    ) -> Result<AttachedClient, AttachError> {
+        if !self.reattachable {
+            return Err(AttachError::NotReattachable(self.trace_id.dupe()));
+        }
+        self.attach(sender)
+    }
+
+    fn is_attached(&self) -> bool {
+        self.inner.lock().sender.is_some()
+    }
+
+    /// Resolves once no client is attached to this command.
+    pub async fn detached(&self) {
+        while self.is_attached() {
+            self.detached.notified().await;
+        }
+    }
+
+    /// Resolves once a client is attached to this command.
+    pub async fn wait_for_attach(&self) {
+        while !self.is_attached() {
+            self.attached.notified().await;
+        }
+    }
+}
+
 /// Allows interactions with commands found via active_commands().
 #[derive(Clone, Dupe)]
 pub struct ActiveCommandHandle {
@@ -72,6 +263,9 @@ pub struct ActiveCommandHandle {
 
     /// State for this command. This is used to expose what this command is doing to other clients.
     state: Arc<ActiveCommandState>,
+
+    /// Where the events of this command are sent.
+    output: Arc<ActiveCommandOutput>,
 }
 
 impl ActiveCommandHandle {
@@ -86,6 +280,10 @@ impl ActiveCommandHandle {
     pub fn state(&self) -> &ActiveCommandState {
         self.state.as_ref()
     }
+
+    pub fn output(&self) -> &Arc<ActiveCommandOutput> {
+        &self.output
+    }
 }
 
 pub struct ActiveCommandDropGuard {
@@ -94,7 +292,14 @@ pub struct ActiveCommandDropGuard {
 
 impl Drop for ActiveCommandDropGuard {
     fn drop(&mut self) {
-        ACTIVE_COMMANDS.lock().remove(&self.trace_id);
+        let removed = ACTIVE_COMMANDS.lock().remove(&self.trace_id);
+        if let Some(cmd) = removed {
+            if cmd.output.reattachable() {
+                let mut finished = FINISHED_COMMANDS.lock();
+                remove_expired(&mut finished);
+                finished.insert(self.trace_id.dupe(), (Instant::now(), cmd.output));
+            }
+        }
     }
 }
 
@@ -219,6 +424,7 @@ pub struct ActiveCommand {
     pub guard: ActiveCommandDropGuard,
     pub state: ActiveCommandStateWriter,
     pub daemon_shutdown_channel: oneshot::Receiver<buck2_data::DaemonShutdown>,
+    pub output: Arc<ActiveCommandOutput>,
 }
 
 impl ActiveCommand {
@@ -228,6 +434,10 @@ impl ActiveCommand {
         let state = Arc::new(ActiveCommandState::new(client_ctx.sanitized_argv.clone()));
 
         let trace_id = event_dispatcher.trace_id().dupe();
+        let output = Arc::new(ActiveCommandOutput::new(
+            trace_id.dupe(),
+            client_ctx.reattachable,
+        ));
         let result = {
             // Scope the guard so it's locked as little as possible
             let mut active_commands = ACTIVE_COMMANDS.lock();
@@ -244,6 +454,7 @@ impl ActiveCommand {
                     dispatcher: event_dispatcher.dupe(),
                     daemon_shutdown_channel: Arc::new(Mutex::new(Some(sender))),
                     state: state.dupe(),
+                    output: output.dupe(),
                 },
             );
 
@@ -269,6 +480,7 @@ impl ActiveCommand {
             guard: ActiveCommandDropGuard { trace_id },
             daemon_shutdown_channel: receiver,
             state: ActiveCommandStateWriter::new(state),
+            output,
         }
     }
 }
@@ -409,4 +621,121 @@ mod tests {
             }
         );
     }
+
+    fn progress(span_id: u64) -> CommandProgress {
+        CommandProgress {
+            progress: Some(buck2_cli_proto::command_progress::Progress::Event(
+                Box::new(buck2_data::BuckEvent {
+                    span_id,
+                    ..Default::default()
+                }),
+            )),
+        }
+    }
+
+    fn received(
+        receiver: &mut tokio::sync::mpsc::UnboundedReceiver<Result<CommandProgress, tonic::Status>>,
+    ) -> Vec<u64> {
+        let mut span_ids = Vec::new();
+        while let Ok(Ok(progress)) = receiver.try_recv() {
+            match progress.progress {
+                Some(buck2_cli_proto::command_progress::Progress::Event(e)) => {
+                    span_ids.push(e.span_id)
+                }
+                _ => panic!("unexpected progress"),
+            }
+        }
+        span_ids
+    }
+
+    #[test]
+    fn test_reattach_flushes_detached_events() {
+        let output = Arc::new(ActiveCommandOutput::new(TraceId::new(), true));
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let client = output.attach(sender).unwrap();
+        output.send(progress(1));
+        assert_eq!(received(&mut receiver), vec![1]);
+
+        drop(client);
+        output.send(progress(2));
+        output.send(progress(3));
+        assert_eq!(received(&mut receiver), Vec::<u64>::new());
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let _client = output.reattach(sender).unwrap();
+        output.send(progress(4));
+        assert_eq!(received(&mut receiver), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_reattach_takes_over() {
+        let output = Arc::new(ActiveCommandOutput::new(TraceId::new(), true));
+
+        let (sender, mut first) = tokio::sync::mpsc::unbounded_channel();
+        let first_client = output.attach(sender).unwrap();
+        let (sender, mut second) = tokio::sync::mpsc::unbounded_channel();
+        let _second_client = output.reattach(sender).unwrap();
+
+        // The first client going away must not detach the second one.
+        drop(first_client);
+        output.send(progress(1));
+        assert_eq!(received(&mut first), Vec::<u64>::new());
+        assert_eq!(received(&mut second), vec![1]);
+
+        output.finish();
+        assert!(matches!(
+            second.try_recv(),
+            Err(tokio::sync::mpsc::error::TryRecvError::Disconnected)
+        ));
+    }
+
+    #[test]
+    fn test_reattach_not_reattachable() {
+        let output = Arc::new(ActiveCommandOutput::new(TraceId::new(), false));
+
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        drop(output.attach(sender).unwrap());
+        output.send(progress(1));
+
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        assert!(matches!(
+            output.reattach(sender),
+            Err(AttachError::NotReattachable(..))
+        ));
+    }
+
+    #[test]
+    fn test_finished_reattachable_command_output() {
+        let output = Arc::new(ActiveCommandOutput::new(TraceId::new(), true));
+        output.send(progress(1));
+        output.finish();
+
+        let trace_id = TraceId::new();
+        ACTIVE_COMMANDS.lock().insert(
+            trace_id.dupe(),
+            ActiveCommandHandle {
+                dispatcher: EventDispatcher::null(),
+                daemon_shutdown_channel: Arc::new(Mutex::new(None)),
+                state: Arc::new(ActiveCommandState::new(Vec::new())),
+                output: output.dupe(),
+            },
+        );
+        drop(ActiveCommandDropGuard {
+            trace_id: trace_id.dupe(),
+        });
+
+        // The command finished, but a client can still get the events it missed.
+        assert!(active_commands().get(&trace_id).is_none());
+        let found = command_output(&trace_id).unwrap();
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let _client = found.reattach(sender).unwrap();
+        assert_eq!(received(&mut receiver), vec![1]);
+
+        // Until the grace period is over.
+        FINISHED_COMMANDS.lock().get_mut(&trace_id).unwrap().0 =
+            Instant::now() - REATTACH_GRACE_PERIOD;
+        assert!(command_output(&trace_id).is_none());
+        assert!(!FINISHED_COMMANDS.lock().contains_key(&trace_id));
+    }
 }
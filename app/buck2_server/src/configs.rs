@@ -15,6 +15,7 @@ use buck2_cli_proto::ConfigOverride;
 use buck2_common::legacy_configs::configs::ConfigDiffEntry;
 use buck2_common::legacy_configs::configs::ConfigDiffMetrics;
 use buck2_common::legacy_configs::configs::LegacyConfigCmdArg;
+use itertools::Itertools;
 
 fn config_type_from_i32(value: i32) -> anyhow::Result<ConfigType> {
     ConfigType::from_i32(value).with_context(|| {
@@ -60,6 +61,43 @@ pub(crate) fn buck_configs(
     }
 }
 
+/// Number of changed keys listed in the console notice before the rest are elided.
+const CONFIG_CHANGE_NOTICE_KEYS: usize = 10;
+
+/// A one-line summary of which buckconfig keys changed since the previous command, to print
+/// on the console. Computations depend on the individual keys they read, so only those which
+/// read one of these keys (or a whole cell config) are invalidated.
+pub(crate) fn config_change_notice(metrics: &ConfigDiffMetrics) -> Option<String> {
+    if !metrics.has_changed() {
+        return None;
+    }
+    let keys: Vec<String> = metrics
+        .diff
+        .iter()
+        .flat_map(|(cell, conf)| {
+            conf.0.iter().flat_map(move |(section, conf)| {
+                conf.0
+                    .keys()
+                    .map(move |name| format!("{}//{}.{}", cell, section, name))
+            })
+        })
+        .collect();
+    let mut notice = format!(
+        "Buckconfig changed, invalidating computations which read {} key{}",
+        metrics.count,
+        if metrics.count == 1 { "" } else { "s" },
+    );
+    if !keys.is_empty() {
+        notice.push_str(": ");
+        notice.push_str(&keys.iter().take(CONFIG_CHANGE_NOTICE_KEYS).join(", "));
+    }
+    let listed = keys.len().min(CONFIG_CHANGE_NOTICE_KEYS);
+    if metrics.count > listed {
+        notice.push_str(&format!(" and {} more", metrics.count - listed));
+    }
+    Some(notice)
+}
+
 fn diff_by_cell(metrics: ConfigDiffMetrics) -> HashMap<String, buck2_data::CellConfigDiff> {
     let mut diff = HashMap::new();
     for (cell, conf) in metrics.diff {
@@ -167,4 +205,29 @@ mod tests {
 
         assert_eq!(buck_configs, expected);
     }
+
+    #[test]
+    fn test_config_change_notice() {
+        assert_eq!(config_change_notice(&ConfigDiffMetrics::default()), None);
+
+        let metrics = ConfigDiffMetrics {
+            diff: smallmap![
+                CellName::testing_new("root") => CellConfigDiff(smallmap![
+                    "apple".to_owned() => SectionConfigDiff(smallmap![
+                        "xcode".to_owned() => ConfigDiffEntry::Added("14".to_owned()),
+                    ])
+                ])
+            ],
+            // The diff is partial when it exceeds `buck2.config_diff_size_limit`.
+            count: 3,
+            size_bytes: 100,
+            diff_size_exceeded: true,
+        };
+        assert_eq!(
+            config_change_notice(&metrics).as_deref(),
+            Some(
+                "Buckconfig changed, invalidating computations which read 3 keys: root//apple.xcode and 2 more"
+            )
+        );
+    }
 }
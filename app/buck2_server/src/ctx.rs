@@ -12,6 +12,9 @@ use std::collections::HashSet;
 use std::io::BufWriter;
 use std::marker::PhantomData;
 use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
 use allocative::Allocative;
 use anyhow::Context;
@@ -19,6 +22,8 @@ use async_trait::async_trait;
 use buck2_build_api::actions::execute::dice_data::set_fallback_executor_config;
 use buck2_build_api::actions::execute::dice_data::SetCommandExecutor;
 use buck2_build_api::actions::execute::dice_data::SetReClient;
+use buck2_build_api::actions::impls::action_retry_policy::ActionRetryPolicy;
+use buck2_build_api::actions::impls::action_retry_policy::HasActionRetryPolicy;
 use buck2_build_api::actions::impls::run_action_knobs::HasRunActionKnobs;
 use buck2_build_api::actions::impls::run_action_knobs::RunActionKnobs;
 use buck2_build_api::build::HasCreateUnhashedSymlinkLock;
@@ -26,8 +31,15 @@ use buck2_build_api::build_signals::create_build_signals;
 use buck2_build_api::build_signals::BuildSignalsInstaller;
 use buck2_build_api::build_signals::SetBuildSignals;
 use buck2_build_api::context::SetBuildContextData;
+use buck2_build_api::invocation_info::HasPerTransactionInvocationInfo;
+use buck2_build_api::invocation_info::InvocationInfo;
+use buck2_build_api::invocation_info::SetInvocationInfo;
 use buck2_build_api::keep_going::HasKeepGoing;
+use buck2_build_api::phase_budgets::HasPhaseBudgets;
+use buck2_build_api::phase_budgets::PhaseBudgets;
 use buck2_build_api::spawner::BuckSpawner;
+use buck2_build_api::stamp::SetStampInfo;
+use buck2_build_api::stamp::StampInfo;
 use buck2_build_signals::CriticalPathBackendName;
 use buck2_build_signals::HasCriticalPathBackend;
 use buck2_cli_proto::client_context::HostArchOverride;
@@ -74,9 +86,13 @@ use buck2_execute::execute::blocking::SetBlockingExecutor;
 use buck2_execute::knobs::ExecutorGlobalKnobs;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_execute::materialize::materializer::SetMaterializer;
+use buck2_execute::materialize::offline::OfflineMaterializer;
 use buck2_execute::re::client::RemoteExecutionClient;
 use buck2_execute::re::manager::ReConnectionHandle;
 use buck2_execute::re::manager::ReConnectionObserver;
+use buck2_execute_impl::executors::http_cache::HttpCache;
+use buck2_execute_impl::executors::http_cache::HttpCacheMode;
+use buck2_execute_impl::executors::local_action_cache::LocalActionCache;
 use buck2_execute_impl::executors::worker::WorkerPool;
 use buck2_execute_impl::low_pass_filter::LowPassFilter;
 use buck2_execute_impl::re::paranoid_download::ParanoidDownloader;
@@ -104,6 +120,7 @@ use buck2_server_ctx::stderr_output_guard::StderrOutputWriter;
 use buck2_server_starlark_debug::create_debugger_handle;
 use buck2_server_starlark_debug::BuckStarlarkDebuggerHandle;
 use buck2_util::arc_str::ArcS;
+use buck2_util::process::async_background_command;
 use buck2_util::truncate::truncate_container;
 use dice::DiceComputations;
 use dice::DiceData;
@@ -169,6 +186,8 @@ pub struct ServerCommandContext<'a> {
     pub oncall: Option<String>,
     /// The client ID, if one was provided via --client-metadata.
     pub client_id_from_client_metadata: Option<String>,
+    /// Exposed to analysis as `ctx.build_info`.
+    invocation_info: Arc<InvocationInfo>,
 
     host_platform_override: HostPlatformOverride,
     host_arch_override: HostArchOverride,
@@ -294,6 +313,15 @@ impl<'a> ServerCommandContext<'a> {
             .find(|m| m.key == "id")
             .map(|m| m.value.clone());
 
+        let invocation_info = Arc::new(InvocationInfo {
+            uuid: base_context.events.trace_id().to_string(),
+            client_metadata: client_context
+                .client_metadata
+                .iter()
+                .map(|m| (m.key.clone(), m.value.clone()))
+                .collect(),
+        });
+
         let heartbeat_guard_handle =
             HeartbeatGuard::new(base_context.events.dupe(), snapshot_collector);
 
@@ -318,6 +346,7 @@ impl<'a> ServerCommandContext<'a> {
             host_xcode_version_override: client_context.host_xcode_version.clone(),
             oncall,
             client_id_from_client_metadata,
+            invocation_info,
             _re_connection_handle: re_connection_handle,
             starlark_profiler_instrumentation_override,
             buck_out_dir: paths.buck_out_dir(),
@@ -351,17 +380,36 @@ impl<'a> ServerCommandContext<'a> {
                 ExecutionStrategy::from_i32(strategy).expect("execution strategy should be valid")
             });
 
+        let check_determinism = self
+            .build_options
+            .as_ref()
+            .map(|opts| opts.unstable_check_determinism)
+            .unwrap_or_default();
+
+        // Both executions must actually run, and neither should populate the caches.
         let skip_cache_read = self
             .build_options
             .as_ref()
             .map(|opts| opts.skip_cache_read)
-            .unwrap_or_default();
+            .unwrap_or_default()
+            || check_determinism;
 
         let skip_cache_write = self
             .build_options
             .as_ref()
             .map(|opts| opts.skip_cache_write)
-            .unwrap_or_default();
+            .unwrap_or_default()
+            || check_determinism;
+
+        let skip_remote_cache_write = self
+            .build_options
+            .as_ref()
+            .map_or(false, |opts| opts.skip_remote_cache_write);
+
+        let offline = self
+            .build_options
+            .as_ref()
+            .map_or(false, |opts| opts.offline);
 
         let mut run_action_knobs = RunActionKnobs {
             hash_all_commands: self.base_context.daemon.hash_all_commands,
@@ -413,6 +461,9 @@ impl<'a> ServerCommandContext<'a> {
             upload_all_actions,
             skip_cache_read,
             skip_cache_write,
+            skip_remote_cache_write,
+            check_determinism,
+            offline,
             create_unhashed_symlink_lock,
             starlark_debugger: self.debugger_handle.dupe(),
             keep_going: self
@@ -426,6 +477,8 @@ impl<'a> ServerCommandContext<'a> {
                 .build_options
                 .as_ref()
                 .map_or(false, |opts| opts.materialize_failed_inputs),
+            local_action_cache: self.base_context.daemon.local_action_cache.dupe(),
+            phase_budgets: self.base_context.daemon.phase_budgets.dupe(),
         }
     }
 
@@ -439,6 +492,7 @@ impl<'a> ServerCommandContext<'a> {
 
         Ok(DiceCommandUpdater {
             file_watcher: self.base_context.daemon.file_watcher.dupe(),
+            project_root: self.base_context.project_root.clone(),
             cell_config_loader: self.cell_configs_loader.dupe(),
             buck_out_dir: self.buck_out_dir.clone(),
             interpreter_platform,
@@ -452,6 +506,7 @@ impl<'a> ServerCommandContext<'a> {
             unstable_typecheck: self.unstable_typecheck,
             skip_targets_with_duplicate_names: self.skip_targets_with_duplicate_names,
             record_target_call_stacks: self.record_target_call_stacks,
+            invocation_info: self.invocation_info.dupe(),
         })
     }
 
@@ -556,6 +611,9 @@ struct DiceCommandDataProvider {
     run_action_knobs: RunActionKnobs,
     skip_cache_read: bool,
     skip_cache_write: bool,
+    skip_remote_cache_write: bool,
+    check_determinism: bool,
+    offline: bool,
     create_unhashed_symlink_lock: Arc<Mutex<()>>,
     starlark_debugger: Option<BuckStarlarkDebuggerHandle>,
     keep_going: bool,
@@ -563,6 +621,8 @@ struct DiceCommandDataProvider {
     paranoid: Option<ParanoidDownloader>,
     spawner: Arc<BuckSpawner>,
     materialize_failed_inputs: bool,
+    local_action_cache: Option<Arc<LocalActionCache>>,
+    phase_budgets: PhaseBudgets,
 }
 
 #[async_trait]
@@ -633,13 +693,29 @@ impl DiceDataProvider for DiceCommandDataProvider {
             })?
             .or(Some(10));
 
+        let re_stream_output_after = root_config
+            .parse::<u64>(BuckconfigKeyRef {
+                section: "buck2",
+                property: "re_stream_output_after_s",
+            })?
+            .map(Duration::from_secs);
+
         let executor_global_knobs = ExecutorGlobalKnobs {
             enable_miniperf,
             log_action_keys,
+            re_stream_output_after,
         };
 
-        let host_sharing_broker =
+        let local_memory_budget_mb = root_config.parse::<usize>(BuckconfigKeyRef {
+            section: "build",
+            property: "local_memory_budget_mb",
+        })?;
+
+        let mut host_sharing_broker =
             HostSharingBroker::new(HostSharingStrategy::SmallerTasksFirst, concurrency);
+        if let Some(budget) = local_memory_budget_mb {
+            host_sharing_broker = host_sharing_broker.with_memory_budget_mb(budget);
+        }
 
         // We use the job count for the low pass filter too. The low pass filter prevents sending
         // RE-eligile tasks to local if their concurrency is higher than our threshold. While it
@@ -664,13 +740,91 @@ impl DiceDataProvider for DiceCommandDataProvider {
         };
         let has_cycle_detector = cycle_detector.is_some();
 
+        // Offline mode can be forced from the config, e.g. in sealed CI environments.
+        let offline = self.offline
+            || root_config
+                .parse::<bool>(BuckconfigKeyRef {
+                    section: "buck2",
+                    property: "offline",
+                })?
+                .unwrap_or(false);
+
         let mut run_action_knobs = self.run_action_knobs.dupe();
+        run_action_knobs.offline = offline;
         run_action_knobs.use_network_action_output_cache |= root_config
             .parse::<bool>(BuckconfigKeyRef {
                 section: "buck2",
                 property: "use_network_action_output_cache",
             })?
             .unwrap_or(false);
+        run_action_knobs.default_timeout = root_config
+            .parse::<u64>(BuckconfigKeyRef {
+                section: "build",
+                property: "action_timeout_s",
+            })?
+            .map(Duration::from_secs);
+        run_action_knobs.clean_env = root_config
+            .parse::<bool>(BuckconfigKeyRef {
+                section: "build",
+                property: "clean_action_env",
+            })?
+            .unwrap_or(false);
+        run_action_knobs.env_allowlist = root_config
+            .parse_list::<String>(BuckconfigKeyRef {
+                section: "build",
+                property: "action_env_allowlist",
+            })?
+            .unwrap_or_default()
+            .into();
+        run_action_knobs.validate_command_lines = root_config
+            .parse::<bool>(BuckconfigKeyRef {
+                section: "build",
+                property: "validate_command_lines",
+            })?
+            .unwrap_or(false);
+
+        let action_retry_policy = Arc::new(ActionRetryPolicy::from_config(root_config)?);
+
+        let http_cache = match root_config.get(BuckconfigKeyRef {
+            section: "buck2",
+            property: "http_cache_url",
+        }) {
+            Some(url) if !offline => {
+                let mode = root_config
+                    .parse::<HttpCacheMode>(BuckconfigKeyRef {
+                        section: "buck2",
+                        property: "http_cache_mode",
+                    })?
+                    .unwrap_or(HttpCacheMode::ReadOnly);
+                let max_upload_bytes = root_config
+                    .parse::<u64>(BuckconfigKeyRef {
+                        section: "buck2",
+                        property: "http_cache_max_upload_mebibytes",
+                    })?
+                    .map(|mebibytes| mebibytes * 1024 * 1024);
+                Some(HttpCache::new(
+                    self.http_client.dupe(),
+                    url,
+                    mode,
+                    max_upload_bytes,
+                ))
+            }
+            _ => None,
+        };
+
+        let paranoid_double_execution_sample_rate = root_config
+            .parse::<f64>(BuckconfigKeyRef {
+                section: "buck2",
+                property: "paranoid_double_execution_sample_rate",
+            })?
+            .unwrap_or(0.0);
+
+        let poisoned_action_threshold = root_config
+            .parse::<u32>(BuckconfigKeyRef {
+                section: "buck2",
+                property: "re_errors_before_prefer_local",
+            })?
+            .unwrap_or(0);
 
         let mut data = UserComputationData {
             data,
@@ -691,34 +845,54 @@ impl DiceDataProvider for DiceCommandDataProvider {
 
         set_fallback_executor_config(&mut data.data, self.executor_config.dupe());
         data.set_re_client(self.re_connection.get_client());
+        // Artifacts that would have to be fetched from the CAS fail instead of reaching the network.
+        let materializer = if offline {
+            Arc::new(OfflineMaterializer::new(self.materializer.dupe())) as Arc<dyn Materializer>
+        } else {
+            self.materializer.dupe()
+        };
         data.set_command_executor(Box::new(CommandExecutorFactory::new(
             self.re_connection.dupe(),
             host_sharing_broker,
             low_pass_filter,
-            self.materializer.dupe(),
+            materializer.dupe(),
             self.blocking_executor.dupe(),
-            self.execution_strategy,
+            if offline {
+                ExecutionStrategy::LocalOnly
+            } else {
+                self.execution_strategy
+            },
             executor_global_knobs,
-            self.upload_all_actions,
+            self.upload_all_actions && !offline,
             self.forkserver.dupe(),
             self.skip_cache_read,
             self.skip_cache_write,
+            self.skip_remote_cache_write,
+            offline,
             ctx.global_data()
                 .get_io_provider()
                 .project_root()
                 .to_owned(),
             worker_pool,
             self.paranoid.dupe(),
+            paranoid_double_execution_sample_rate,
+            self.check_determinism,
             self.materialize_failed_inputs,
+            self.local_action_cache.dupe(),
+            http_cache,
+            poisoned_action_threshold,
+            self.phase_budgets.action_dispatch(),
         )));
         data.set_blocking_executor(self.blocking_executor.dupe());
         data.set_http_client(self.http_client.dupe());
-        data.set_materializer(self.materializer.dupe());
+        data.set_materializer(materializer);
         data.set_build_signals(self.build_signals.build_signals.dupe());
         data.set_run_action_knobs(run_action_knobs);
+        data.set_action_retry_policy(action_retry_policy);
         data.set_create_unhashed_symlink_lock(self.create_unhashed_symlink_lock.dupe());
         data.set_starlark_debugger_handle(self.starlark_debugger.clone().map(|v| Box::new(v) as _));
         data.set_keep_going(self.keep_going);
+        data.set_phase_budgets(self.phase_budgets.dupe());
         data.set_critical_path_backend(critical_path_backend);
         data.spawner = self.spawner.dupe();
 
@@ -726,6 +900,7 @@ impl DiceDataProvider for DiceCommandDataProvider {
             format!("lazy-cycle-detector:{}", has_cycle_detector),
             format!("miniperf:{}", enable_miniperf),
             format!("log-configured-graph-size:{}", log_configured_graph_size),
+            format!("offline:{}", offline),
         ];
         self.events.instant_event(buck2_data::TagEvent { tags });
 
@@ -737,6 +912,28 @@ impl DiceDataProvider for DiceCommandDataProvider {
     }
 }
 
+/// The revision of the repository, for file watchers which don't know it. Only Git and Mercurial
+/// are supported.
+async fn vcs_revision(project_root: &ProjectRoot) -> Option<String> {
+    for (program, args) in [
+        ("git", &["rev-parse", "HEAD"][..]),
+        ("hg", &["log", "-r", ".", "-T", "{node}"][..]),
+    ] {
+        let output = async_background_command(program)
+            .args(args)
+            .current_dir(project_root.root())
+            .output()
+            .await;
+        if let Ok(output) = output {
+            let revision = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+            if output.status.success() && !revision.is_empty() {
+                return Some(revision);
+            }
+        }
+    }
+    None
+}
+
 fn create_cycle_detector() -> Arc<dyn UserCycleDetector> {
     Arc::new(PairDiceCycleDetector(
         CycleDetectorAdapter::<LoadCycleDescriptor>::new(),
@@ -746,6 +943,7 @@ fn create_cycle_detector() -> Arc<dyn UserCycleDetector> {
 
 struct DiceCommandUpdater {
     file_watcher: Arc<dyn FileWatcher>,
+    project_root: ProjectRoot,
     cell_config_loader: Arc<CellConfigLoader>,
     buck_out_dir: ProjectRelativePathBuf,
     interpreter_platform: InterpreterHostPlatform,
@@ -757,6 +955,7 @@ struct DiceCommandUpdater {
     unstable_typecheck: bool,
     record_target_call_stacks: bool,
     skip_targets_with_duplicate_names: bool,
+    invocation_info: Arc<InvocationInfo>,
 }
 
 #[async_trait]
@@ -779,6 +978,15 @@ impl DiceUpdater for DiceCommandUpdater {
         // TODO(cjhopman): The CellResolver and the legacy configs shouldn't be leaves on the graph. This should
         // just be setting the config overrides and host platform override as leaves on the graph.
 
+        let stamp = legacy_configs
+            .get(cell_resolver.root_cell())
+            .context("No config for root cell")?
+            .parse::<bool>(BuckconfigKeyRef {
+                section: "buck2",
+                property: "stamp",
+            })?
+            .unwrap_or(false);
+
         let configuror = BuildInterpreterConfiguror::new(
             prelude_path(&cell_resolver)?,
             self.interpreter_platform,
@@ -792,6 +1000,32 @@ impl DiceUpdater for DiceCommandUpdater {
         )?;
 
         let (mut ctx, mergebase) = self.file_watcher.sync(ctx).await?;
+        // Unless stamping is requested, the stamp info stays the same, so that it doesn't
+        // invalidate anything.
+        let stamp_info = if stamp {
+            let revision = match &*mergebase.0 {
+                Some(revision) => Some(revision.clone()),
+                None => vcs_revision(&self.project_root).await,
+            };
+            StampInfo {
+                revision,
+                timestamp: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs()),
+            }
+        } else {
+            StampInfo::unstamped()
+        };
+        ctx.set_stamp_info(stamp_info)?;
+        // Likewise, the uuid of the command is only exposed when stamping, otherwise every command
+        // would be a new DICE version, and concurrent commands could never share a transaction.
+        let invocation_info = if stamp {
+            self.invocation_info.dupe()
+        } else {
+            Arc::new(self.invocation_info.unstamped())
+        };
+        ctx.set_invocation_info(invocation_info.dupe())?;
+        user_data.set_per_transaction_invocation_info(invocation_info);
         user_data.set_mergebase(mergebase);
 
         ctx.set_buck_out_path(Some(self.buck_out_dir.clone()))?;
@@ -807,6 +1041,12 @@ impl DiceUpdater for DiceCommandUpdater {
             self.unstable_typecheck,
         )?;
 
+        if let Some(notice) = config_metrics
+            .as_ref()
+            .and_then(configs::config_change_notice)
+        {
+            self.events.console_message(notice);
+        }
         let buck_configs = configs::buck_configs(new_configs, config_metrics);
         self.events.instant_event(buck_configs);
 
@@ -44,9 +44,18 @@ use buck2_execute_impl::executors::action_cache::ActionCacheChecker;
 use buck2_execute_impl::executors::action_cache::RemoteDepFileCacheChecker;
 use buck2_execute_impl::executors::action_cache_upload_permission_checker::ActionCacheUploadPermissionChecker;
 use buck2_execute_impl::executors::caching::CacheUploader;
+use buck2_execute_impl::executors::determinism_check::DeterminismChecker;
+use buck2_execute_impl::executors::double_execution::DoubleExecutionVerifier;
+use buck2_execute_impl::executors::http_cache::HttpCache;
+use buck2_execute_impl::executors::http_cache::HttpCacheChecker;
+use buck2_execute_impl::executors::http_cache::HttpCacheMode;
+use buck2_execute_impl::executors::http_cache::HttpCacheWriter;
 use buck2_execute_impl::executors::hybrid::FallbackTracker;
 use buck2_execute_impl::executors::hybrid::HybridExecutor;
 use buck2_execute_impl::executors::local::LocalExecutor;
+use buck2_execute_impl::executors::local_action_cache::LocalActionCache;
+use buck2_execute_impl::executors::local_action_cache::LocalActionCacheChecker;
+use buck2_execute_impl::executors::local_action_cache::LocalActionCacheWriter;
 use buck2_execute_impl::executors::re::ReExecutor;
 use buck2_execute_impl::executors::stacked::StackedExecutor;
 use buck2_execute_impl::executors::to_re_platform::RePlatformFieldsToRePlatform;
@@ -56,6 +65,7 @@ use buck2_execute_impl::re::paranoid_download::ParanoidDownloader;
 use buck2_forkserver::client::ForkserverClient;
 use dupe::Dupe;
 use host_sharing::HostSharingBroker;
+use tokio::sync::Semaphore;
 
 pub fn parse_concurrency(requested: u32) -> anyhow::Result<usize> {
     let mut ret = requested.try_into().context("Invalid concurrency")?;
@@ -85,13 +95,25 @@ pub struct CommandExecutorFactory {
     forkserver: Option<ForkserverClient>,
     skip_cache_read: bool,
     skip_cache_write: bool,
+    /// Don't write to the remote caches, but still write to the local action cache.
+    skip_remote_cache_write: bool,
+    /// Skip the remote caches, which need the network, but not the local action cache.
+    offline: bool,
     project_root: ProjectRoot,
     worker_pool: Arc<WorkerPool>,
     paranoid: Option<ParanoidDownloader>,
+    /// Fraction of the actions to execute both locally and remotely in paranoid mode.
+    paranoid_double_execution_sample_rate: f64,
+    /// Execute every command twice and report the ones whose outputs differ.
+    check_determinism: bool,
     materialize_failed_inputs: bool,
+    local_action_cache: Option<Arc<LocalActionCache>>,
+    http_cache: Option<Arc<HttpCache>>,
     /// Cache permission checks per command.
     cache_upload_permission_checker: Arc<ActionCacheUploadPermissionChecker>,
     fallback_tracker: Arc<FallbackTracker>,
+    /// Slots to run commands locally, shared by all the commands of the daemon.
+    local_dispatch_budget: Option<Arc<Semaphore>>,
 }
 
 impl CommandExecutorFactory {
@@ -107,10 +129,18 @@ impl CommandExecutorFactory {
         forkserver: Option<ForkserverClient>,
         skip_cache_read: bool,
         skip_cache_write: bool,
+        skip_remote_cache_write: bool,
+        offline: bool,
         project_root: ProjectRoot,
         worker_pool: Arc<WorkerPool>,
         paranoid: Option<ParanoidDownloader>,
+        paranoid_double_execution_sample_rate: f64,
+        check_determinism: bool,
         materialize_failed_inputs: bool,
+        local_action_cache: Option<Arc<LocalActionCache>>,
+        http_cache: Option<Arc<HttpCache>>,
+        poisoned_action_threshold: u32,
+        local_dispatch_budget: Option<Arc<Semaphore>>,
     ) -> Self {
         let cache_upload_permission_checker = Arc::new(ActionCacheUploadPermissionChecker::new(
             re_connection.get_client(),
@@ -127,14 +157,106 @@ impl CommandExecutorFactory {
             forkserver,
             skip_cache_read,
             skip_cache_write,
+            skip_remote_cache_write,
+            offline,
             project_root,
             worker_pool,
             paranoid,
+            paranoid_double_execution_sample_rate,
+            check_determinism,
             materialize_failed_inputs,
+            local_action_cache,
+            http_cache,
             cache_upload_permission_checker,
-            fallback_tracker: Arc::new(FallbackTracker::new()),
+            fallback_tracker: Arc::new(FallbackTracker::new(poisoned_action_threshold)),
+            local_dispatch_budget,
         }
     }
+
+    /// Checks the local action cache before the other caches, and stores the outputs of actions
+    /// executed locally in it, if it's enabled.
+    fn with_local_action_cache(
+        &self,
+        artifact_fs: &ArtifactFs,
+        mut response: CommandExecutorResponse,
+    ) -> CommandExecutorResponse {
+        let Some(cache) = &self.local_action_cache else {
+            return response;
+        };
+        if !self.skip_cache_read {
+            response.cache_checker = Arc::new(LocalActionCacheChecker {
+                cache: cache.dupe(),
+                artifact_fs: artifact_fs.clone(),
+                materializer: self.materializer.dupe(),
+                blocking_executor: self.blocking_executor.dupe(),
+                next: response.cache_checker,
+            });
+        }
+        if !self.skip_cache_write {
+            response.executor = Arc::new(LocalActionCacheWriter {
+                cache: cache.dupe(),
+                artifact_fs: artifact_fs.clone(),
+                blocking_executor: self.blocking_executor.dupe(),
+                inner: response.executor,
+            });
+        }
+        response
+    }
+
+    /// Checks the HTTP cache before the other remote caches, and uploads the outputs of actions
+    /// executed locally to it if it's writable. Unlike the remote cache, it is enabled by its own
+    /// config rather than by the executor config, so that it works without remote execution.
+    fn with_http_cache(
+        &self,
+        artifact_fs: &ArtifactFs,
+        mut response: CommandExecutorResponse,
+    ) -> anyhow::Result<CommandExecutorResponse> {
+        let Some(cache) = &self.http_cache else {
+            return Ok(response);
+        };
+        if !self.skip_cache_read {
+            response.cache_checker = Arc::new(HttpCacheChecker {
+                cache: cache.dupe(),
+                artifact_fs: artifact_fs.clone(),
+                materializer: self.materializer.dupe(),
+                blocking_executor: self.blocking_executor.dupe(),
+                next: response.cache_checker,
+            });
+        }
+        if !self.skip_remote_cache_write() && cache.mode() == HttpCacheMode::ReadWrite {
+            response.executor = Arc::new(HttpCacheWriter {
+                cache: cache.dupe(),
+                artifact_fs: artifact_fs.clone(),
+                max_bytes: if force_cache_upload()? {
+                    None
+                } else {
+                    cache.max_upload_bytes()
+                },
+                inner: response.executor,
+            });
+        }
+        Ok(response)
+    }
+
+    /// Whether nothing may be written to the remote caches, which is also the case when nothing
+    /// may be written to any cache.
+    fn skip_remote_cache_write(&self) -> bool {
+        self.skip_cache_write || self.skip_remote_cache_write || self.offline
+    }
+
+    fn with_determinism_check(
+        &self,
+        artifact_fs: &ArtifactFs,
+        mut response: CommandExecutorResponse,
+    ) -> CommandExecutorResponse {
+        if self.check_determinism {
+            response.executor = Arc::new(DeterminismChecker {
+                inner: response.executor,
+                artifact_fs: artifact_fs.clone(),
+            });
+        }
+        response
+    }
 }
 
 impl HasCommandExecutor for CommandExecutorFactory {
@@ -161,6 +283,7 @@ impl HasCommandExecutor for CommandExecutorFactory {
                 self.forkserver.dupe(),
                 self.executor_global_knobs.dupe(),
                 worker_pool,
+                self.local_dispatch_budget.dupe(),
             )
         };
 
@@ -177,12 +300,17 @@ impl HasCommandExecutor for CommandExecutorFactory {
                 ));
             }
 
-            return Ok(CommandExecutorResponse {
-                executor: Arc::new(local_executor_new(&LocalExecutorOptions::default())),
-                platform: Default::default(),
-                cache_checker: Arc::new(NoOpCommandOptionalExecutor {}),
-                cache_uploader: Arc::new(NoOpCacheUploader {}),
-            });
+            let response = self.with_http_cache(
+                artifact_fs,
+                CommandExecutorResponse {
+                    executor: Arc::new(local_executor_new(&LocalExecutorOptions::default())),
+                    platform: Default::default(),
+                    cache_checker: Arc::new(NoOpCommandOptionalExecutor {}),
+                    cache_uploader: Arc::new(NoOpCacheUploader {}),
+                },
+            )?;
+            let response = self.with_local_action_cache(artifact_fs, response);
+            return Ok(self.with_determinism_check(artifact_fs, response));
         }
 
         let remote_executor_new =
@@ -201,8 +329,8 @@ impl HasCommandExecutor for CommandExecutorFactory {
                     re_max_queue_time_ms: options.re_max_queue_time_ms,
                     re_resource_units: options.re_resource_units,
                     knobs: self.executor_global_knobs.dupe(),
-                    skip_cache_read: self.skip_cache_read || !remote_cache_enabled,
-                    skip_cache_write: self.skip_cache_write || !remote_cache_enabled,
+                    skip_cache_read: self.skip_cache_read || self.offline || !remote_cache_enabled,
+                    skip_cache_write: self.skip_remote_cache_write() || !remote_cache_enabled,
                     paranoid: self.paranoid.dupe(),
                     materialize_failed_inputs: self.materialize_failed_inputs,
                     dependencies: dependencies.to_vec(),
@@ -231,6 +359,8 @@ impl HasCommandExecutor for CommandExecutorFactory {
                 remote_cache_enabled,
                 remote_dep_file_cache_enabled,
                 dependencies,
+                // Already merged into `re_properties` when the actions were registered.
+                re_properties_by_category: _,
             } => {
                 // NOTE: While we now have a legit flag for this, we keep the env var. This has been used
                 // in remediating prod incidents in the past, and this is the kind of thing that can easily
@@ -239,8 +369,12 @@ impl HasCommandExecutor for CommandExecutorFactory {
                     buck2_env!("BUCK2_TEST_DISABLE_CACHING", type=bool, applicability=testing)?
                         .unwrap_or(self.skip_cache_read);
 
-                let disable_caching =
-                    disable_caching || (!remote_cache_enabled && !remote_dep_file_cache_enabled);
+                // Without an action cache address (e.g. when only the HTTP cache is used), every
+                // lookup would fail.
+                let disable_caching = disable_caching
+                    || self.offline
+                    || !self.re_connection.has_action_cache()
+                    || (!remote_cache_enabled && !remote_dep_file_cache_enabled);
 
                 // This is for test only as in real life, it would be silly to only use the remote dep file cache and not the regular cache
                 // This will only do anything if cache is not disabled and remote dep file cache is enabled
@@ -303,15 +437,15 @@ impl HasCommandExecutor for CommandExecutorFactory {
                     }
                     RemoteEnabledExecutor::Hybrid {
                         local,
-                        remote,
+                        remote: remote_options,
                         level,
                     } if !self.strategy.ban_hybrid() => {
-                        let re_max_input_files_bytes = remote
+                        let re_max_input_files_bytes = remote_options
                             .re_max_input_files_bytes
                             .unwrap_or(DEFAULT_RE_MAX_INPUT_FILE_BYTES);
                         let local = local_executor_new(local);
                         let remote = remote_executor_new(
-                            remote,
+                            remote_options,
                             re_use_case,
                             re_action_key,
                             *remote_cache_enabled,
@@ -325,7 +459,23 @@ impl HasCommandExecutor for CommandExecutorFactory {
                             let executor_preference = executor_preference
                                 .and(ExecutorPreference::DefaultErasePreferences)?;
 
-                            Some(Arc::new(HybridExecutor {
+                            // Bypass the cache and don't download anything, the outputs are only
+                            // compared with the local ones.
+                            let verifier_remote = ReExecutor {
+                                skip_cache_read: true,
+                                skip_cache_write: true,
+                                paranoid: None,
+                                ..remote_executor_new(
+                                    remote_options,
+                                    re_use_case,
+                                    re_action_key,
+                                    *remote_cache_enabled,
+                                    dependencies,
+                                )
+                            };
+                            let verifier_local = Arc::new(local.clone());
+
+                            let executor = Arc::new(HybridExecutor {
                                 local,
                                 remote: StackedExecutor {
                                     optional: cache_checker_new(),
@@ -339,7 +489,20 @@ impl HasCommandExecutor for CommandExecutorFactory {
                                 re_max_input_files_bytes,
                                 low_pass_filter,
                                 fallback_tracker,
-                            }))
+                            });
+
+                            if self.paranoid_double_execution_sample_rate > 0.0 {
+                                Some(Arc::new(DoubleExecutionVerifier {
+                                    inner: executor,
+                                    cache_checker: cache_checker_new(),
+                                    local: verifier_local,
+                                    remote: verifier_remote,
+                                    artifact_fs: artifact_fs.clone(),
+                                    sample_rate: self.paranoid_double_execution_sample_rate,
+                                }))
+                            } else {
+                                Some(executor)
+                            }
                         } else {
                             Some(Arc::new(HybridExecutor {
                                 local,
@@ -361,7 +524,9 @@ impl HasCommandExecutor for CommandExecutorFactory {
                     cache_checker_new()
                 };
 
-                let cache_uploader = if force_cache_upload()? {
+                let cache_uploader = if self.skip_remote_cache_write || self.offline {
+                    Arc::new(NoOpCacheUploader {}) as _
+                } else if force_cache_upload()? {
                     Arc::new(CacheUploader::new(
                         artifact_fs.clone(),
                         self.materializer.dupe(),
@@ -401,7 +566,9 @@ impl HasCommandExecutor for CommandExecutorFactory {
 "The desired execution strategy (`{:?}`) is incompatible with the executor config that was selected: {:?}",
 self.strategy, executor_config))?;
 
-        Ok(response)
+        let response = self.with_http_cache(artifact_fs, response)?;
+        let response = self.with_local_action_cache(artifact_fs, response);
+        Ok(self.with_determinism_check(artifact_fs, response))
     }
 }
 
@@ -463,6 +630,7 @@ pub fn get_default_executor_config(host_platform: HostPlatformOverride) -> Comma
             remote_cache_enabled: true,
             remote_dep_file_cache_enabled: false,
             dependencies: vec![],
+            re_properties_by_category: Default::default(),
         }
     };
 
@@ -471,6 +639,7 @@ pub fn get_default_executor_config(host_platform: HostPlatformOverride) -> Comma
         options: CommandGenerationOptions {
             path_separator: get_default_path_separator(host_platform),
             output_paths_behavior: Default::default(),
+            use_remote_persistent_workers: false,
         },
     }
 }
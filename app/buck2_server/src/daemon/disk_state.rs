@@ -26,6 +26,7 @@ use buck2_execute_impl::materializers::deferred::DeferredMaterializerConfigs;
 use buck2_execute_impl::materializers::sqlite::MaterializerState;
 use buck2_execute_impl::materializers::sqlite::MaterializerStateSqliteDb;
 use buck2_execute_impl::materializers::sqlite::DB_SCHEMA_VERSION;
+use dupe::Dupe;
 
 use crate::daemon::server::BuckdServerInitPreferences;
 
@@ -101,18 +102,21 @@ pub(crate) async fn maybe_initialize_materializer_sqlite_db(
     // Most things in the rest of `metadata` should go in the metadata sqlite table.
     // TODO(scottcao): Narrow down what metadata we need and and insert them into the
     // metadata table before a feature rollout.
-    let (db, load_result) = MaterializerStateSqliteDb::initialize(
+    let (mut db, load_result) = MaterializerStateSqliteDb::initialize(
         paths.materializer_state_path(),
         versions,
         metadata,
-        io_executor,
+        io_executor.dupe(),
         digest_config,
         init_ctx.reject_materializer_state.as_ref(),
     )
     .await?;
 
     let materializer_state = match load_result {
-        Ok(s) => Some(s),
+        Ok(s) => Some(
+            db.reconcile(s, paths.project_root().dupe(), io_executor)
+                .await?,
+        ),
         // We know path not found or version mismatch is normal, but some sqlite failures
         // are worth logging here. TODO(scottcao): Refine our error types and figure out what
         // errors to log
@@ -10,10 +10,12 @@
 use std::sync::Arc;
 
 use buck2_common::cas_digest::CasDigestConfig;
+use buck2_common::io::digest_cache::SourceDigestCache;
 use buck2_common::io::fs::FsIoProvider;
 use buck2_common::io::trace::TracingIoProvider;
 use buck2_common::io::IoProvider;
 use buck2_common::legacy_configs::configs::LegacyBuckConfig;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::project::ProjectRoot;
 
 pub async fn create_io_provider(
@@ -22,6 +24,7 @@ pub async fn create_io_provider(
     root_config: &LegacyBuckConfig,
     cas_digest_config: CasDigestConfig,
     trace_io: bool,
+    digest_cache_dir: Option<AbsNormPathBuf>,
 ) -> anyhow::Result<Arc<dyn IoProvider>> {
     #[cfg(fbcode_build)]
     {
@@ -55,11 +58,24 @@ pub async fn create_io_provider(
 
     let _allow_unused = (fb, root_config);
 
+    let mut io = FsIoProvider::new(project_fs, cas_digest_config);
+    if let Some(dir) = digest_cache_dir {
+        // The cache only saves hashing, so we can do without it.
+        match tokio::task::spawn_blocking(move || SourceDigestCache::open(&dir, cas_digest_config))
+            .await?
+        {
+            Ok(cache) => {
+                let cache = Arc::new(cache);
+                SourceDigestCache::spawn_flush_task(&cache);
+                io = io.with_digest_cache(cache);
+            }
+            Err(e) => tracing::warn!("Not caching source file digests: {:#}", e),
+        }
+    }
+
     if trace_io {
-        Ok(Arc::new(TracingIoProvider::new(Box::new(
-            FsIoProvider::new(project_fs, cas_digest_config),
-        ))))
+        Ok(Arc::new(TracingIoProvider::new(Box::new(io))))
     } else {
-        Ok(Arc::new(FsIoProvider::new(project_fs, cas_digest_config)))
+        Ok(Arc::new(io))
     }
 }
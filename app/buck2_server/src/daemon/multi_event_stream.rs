@@ -10,12 +10,15 @@
 use std::pin::Pin;
 use std::task::Context;
 use std::task::Poll;
+use std::time::Duration;
 
 use buck2_cli_proto::CommandProgress;
 use buck2_cli_proto::MultiCommandProgress;
 use futures::stream::Stream;
 use pin_project::pin_project;
 use prost::Message;
+use tokio::time::Interval;
+use tokio::time::MissedTickBehavior;
 
 const PREFERRED_MESSAGE_SIZE_BYTES: usize = 32768; // This is an approximation.
 
@@ -26,6 +29,7 @@ pub struct MultiEventStream<S, E> {
     inner: S,
     done: bool,
     buffered_err: Option<E>,
+    heartbeat: Option<Interval>,
 }
 
 impl<S, E> MultiEventStream<S, E> {
@@ -34,8 +38,18 @@ impl<S, E> MultiEventStream<S, E> {
             inner,
             done: false,
             buffered_err: None,
+            heartbeat: None,
         }
     }
+
+    /// Send an empty batch whenever the inner stream has been idle for `period`, so that the
+    /// client can tell a quiet command from a dead connection.
+    pub fn with_heartbeat(mut self, period: Duration) -> Self {
+        let mut heartbeat = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        self.heartbeat = Some(heartbeat);
+        self
+    }
 }
 
 impl<S, E> Stream for MultiEventStream<S, E>
@@ -84,6 +98,9 @@ where
 
         if !messages.is_empty() {
             tracing::trace!(n = messages.len(), "multi");
+            if let Some(heartbeat) = this.heartbeat.as_mut() {
+                heartbeat.reset();
+            }
             return Poll::Ready(Some(Ok(MultiCommandProgress { messages })));
         }
 
@@ -92,10 +109,16 @@ where
         }
 
         if *this.done {
-            Poll::Ready(None)
-        } else {
-            Poll::Pending
+            return Poll::Ready(None);
         }
+
+        if let Some(heartbeat) = this.heartbeat.as_mut() {
+            if heartbeat.poll_tick(cx).is_ready() {
+                return Poll::Ready(Some(Ok(MultiCommandProgress::default())));
+            }
+        }
+
+        Poll::Pending
     }
 }
 
@@ -185,6 +208,26 @@ mod tests {
         );
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn test_heartbeat() {
+        let s = futures::stream::once(async {
+            tokio::time::sleep(Duration::from_secs(25)).await;
+            Ok::<_, ()>(event(1))
+        });
+        let s = MultiEventStream::new(s).with_heartbeat(Duration::from_secs(10));
+
+        assert_eq!(
+            s.collect::<Vec<_>>().await,
+            vec![
+                Ok(MultiCommandProgress::default()),
+                Ok(MultiCommandProgress::default()),
+                Ok(MultiCommandProgress {
+                    messages: vec![event(1)]
+                }),
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn test_batch_limit() {
         let s = poll_fn(|_| ready_event(0));
@@ -34,6 +34,7 @@ use buck2_common::invocation_paths::InvocationPaths;
 use buck2_common::io::trace::TracingIoProvider;
 use buck2_common::io::IoProvider;
 use buck2_common::legacy_configs::configs::LegacyBuckConfig;
+use buck2_common::legacy_configs::path::is_cell_buckconfig_path;
 use buck2_common::memory;
 use buck2_core::buck2_env;
 use buck2_core::error::reload_hard_error_config;
@@ -50,6 +51,7 @@ use buck2_events::Event;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::materialize::materializer::MaterializationMethod;
 use buck2_execute_impl::materializers::sqlite::MaterializerStateIdentity;
+use buck2_file_watcher::file_watcher::FileWatcher;
 use buck2_futures::cancellation::ExplicitCancellationContext;
 use buck2_futures::drop::DropTogether;
 use buck2_futures::spawn::spawn_cancellable;
@@ -66,6 +68,7 @@ use buck2_server_starlark_debug::run::run_dap_server_command;
 use buck2_test::executor_launcher::get_all_test_executors;
 use buck2_util::system_stats::system_memory_stats;
 use buck2_util::threads::thread_spawn;
+use buck2_wrapper_common::invocation_id::TraceId;
 use dice::DetectCycles;
 use dice::Dice;
 use dice::WhichDice;
@@ -92,12 +95,18 @@ use tonic::Request;
 use tonic::Response;
 use tonic::Status;
 
+use crate::active_commands::command_output;
+use crate::active_commands::subscribe_shutdown;
 use crate::active_commands::ActiveCommand;
+use crate::active_commands::ActiveCommandOutput;
 use crate::active_commands::ActiveCommandStateWriter;
+use crate::active_commands::AttachError;
+use crate::active_commands::REATTACH_GRACE_PERIOD;
 use crate::clean_stale::clean_stale_command;
 use crate::ctx::ServerCommandContext;
 use crate::daemon::multi_event_stream::MultiEventStream;
 use crate::daemon::server_allocative::spawn_allocative;
+use crate::daemon::server_allocative::spawn_heap_snapshot;
 use crate::daemon::state::DaemonState;
 use crate::file_status::file_status_command;
 use crate::lsp::run_lsp_server_command;
@@ -112,6 +121,9 @@ static DEFAULT_KILL_TIMEOUT: Duration = Duration::from_millis(500);
 
 static DEFAULT_INACTIVITY_TIMEOUT: Duration = Duration::from_secs(4 * 86400);
 
+/// How often to send an empty message to a client while a command has no events for it.
+static STREAM_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
 pub trait BuckdServerDelegate: Allocative + Send + Sync {
     fn force_shutdown_with_timeout(&self, reason: String, timeout: Duration);
 }
@@ -415,6 +427,7 @@ impl BuckdServer {
             guard,
             daemon_shutdown_channel,
             state,
+            output,
         } = ActiveCommand::new(&dispatch, client_ctx);
         let data = daemon_state.data()?;
 
@@ -429,10 +442,15 @@ impl BuckdServer {
         let snapshot_collector = SnapshotCollector::new(data.dupe());
         dispatch.instant_event(Box::new(snapshot_collector.create_snapshot()));
 
+        let heap_snapshots = data.heap_snapshots;
+        let buckd_server_data = self.0.dupe();
+        let heap_snapshot_trace_id = client_ctx.trace_id.clone();
+
         let resp = streaming(
             req,
             events,
             state,
+            output,
             dispatch.dupe(),
             daemon_shutdown_channel,
             move |req, cancellations| {
@@ -454,6 +472,15 @@ impl BuckdServer {
                         func(&context, PartialResultDispatcher::new(dispatch.dupe()), req).await?
                     };
                     dispatch.command_result(result_to_command_result(result));
+
+                    if let Some(keep) = heap_snapshots {
+                        spawn_heap_snapshot(
+                            buckd_server_data,
+                            daemon_state.paths.heap_snapshots_dir(),
+                            heap_snapshot_trace_id,
+                            keep,
+                        );
+                    }
                 }
                 .boxed()
             },
@@ -522,6 +549,24 @@ impl BuckdServer {
     }
 }
 
+fn pending_invalidations(file_watcher: &dyn FileWatcher) -> buck2_cli_proto::PendingInvalidations {
+    match file_watcher.pending_changes() {
+        Some(changes) => buck2_cli_proto::PendingInvalidations {
+            known: true,
+            changed_configs: changes
+                .iter()
+                .filter(|path| is_cell_buckconfig_path(path.path()))
+                .map(|path| path.to_string())
+                .collect(),
+            changed_paths: changes.iter().map(|path| path.to_string()).collect(),
+        },
+        None => buck2_cli_proto::PendingInvalidations {
+            known: false,
+            ..Default::default()
+        },
+    }
+}
+
 fn convert_positive_duration(proto_duration: &prost_types::Duration) -> Result<Duration, Status> {
     if proto_duration.seconds < 0 || proto_duration.nanos < 0 {
         return Err(Status::new(
@@ -594,39 +639,35 @@ impl<T: Stream + Send> Stream for SyncStream<T> {
 fn pump_events(
     mut events: ChannelEventSource,
     mut state: ActiveCommandStateWriter,
-    output_send: tokio::sync::mpsc::UnboundedSender<
-        Result<buck2_cli_proto::CommandProgress, tonic::Status>,
-    >,
+    output: Arc<ActiveCommandOutput>,
 ) {
-    // This function returns the receiving channel back to `tonic` as a streaming response.
+    // This function forwards events to whichever client is attached to the command. When there
+    // is none, `output` drops them, or buffers them if the command is reattachable.
     while let Some(next_event) = events.receive() {
-        // Ignoring errors from writing to `output_send` because they occur only when
-        // the receiving end of the channel is closed. This can happen, for example,
-        // if Tonic drops the streaming response due the client disconnecting.
-        // In these cases, ignoring the errors is intentional as no client is listening.
         match next_event {
             // The CommandResult event indicates that the spawned
             // computation won't be producing any more events.
             Event::CommandResult(result) => {
-                let _ignore = output_send.send(Ok(CommandProgress {
+                output.send(CommandProgress {
                     progress: Some(command_progress::Progress::Result(result)),
-                }));
-                return;
+                });
+                break;
             }
             Event::PartialResult(result) => {
-                let _ignore = output_send.send(Ok(CommandProgress {
+                output.send(CommandProgress {
                     progress: Some(command_progress::Progress::PartialResult(Box::new(result))),
-                }));
+                });
             }
             Event::Buck(buck_event) => {
                 state.peek_event(&buck_event);
 
-                let _ignore = output_send.send(Ok(CommandProgress {
+                output.send(CommandProgress {
                     progress: Some(command_progress::Progress::Event(buck_event.into())),
-                }));
+                });
             }
         }
     }
+    output.finish();
 }
 
 /// Dispatches a request to the given function and returns a stream of responses, suitable for streaming to a client.
@@ -638,6 +679,7 @@ fn streaming<
     req: Request<Req>,
     events: ChannelEventSource,
     state: ActiveCommandStateWriter,
+    output: Arc<ActiveCommandOutput>,
     dispatcher: EventDispatcher,
     daemon_shutdown_channel: oneshot::Receiver<buck2_data::DaemonShutdown>,
     func: F,
@@ -669,12 +711,17 @@ where
         &events_ctx,
     );
     let (output_send, output_recv) = tokio::sync::mpsc::unbounded_channel();
+    let client = match output.attach(output_send) {
+        Ok(client) => client,
+        Err(e) => return error_to_response_stream(e.into()),
+    };
 
     // We run the event consumer on new non-tokio thread to avoid the consumer task from getting stuck behind
     // another tokio task in its lifo task slot. See T96012305 and https://github.com/tokio-rs/tokio/issues/4323 for more
     // information.
-    let merge_task = thread_spawn("pump-events", move || {
-        pump_events(events, state, output_send);
+    let merge_task = thread_spawn("pump-events", {
+        let output = output.dupe();
+        move || pump_events(events, state, output)
     });
     if let Err(e) = merge_task {
         return error_to_response_stream(
@@ -684,14 +731,56 @@ where
 
     let events = tokio_stream::wrappers::UnboundedReceiverStream::new(output_recv);
 
-    //
-    // Note that while this is an event, we don't send it through our normal event
-    // processing. The reason for that is that we dont want this event to queue behind any other
-    // events in the (2) unbounded channels that form our event pipeline. So, we inject this one
-    // directly where Tonic is polling for responses (which, unlike the rest of the pipeline, is
-    // not unbounded, and has backpressure).
+    // The stream we ultimately return is the receiving end of the channel that the above task is
+    // writing to, plus the shutdown channel.
+    let events = futures::stream::select(
+        events,
+        daemon_shutdown_stream(trace_id, daemon_shutdown_channel),
+    );
+
+    let events = MultiEventStream::new(events).with_heartbeat(STREAM_HEARTBEAT_INTERVAL);
+
+    let spawned = spawned.into_drop_cancel();
+    if !output.reattachable() {
+        return Response::new(Box::pin(SyncStream {
+            wrapped: sync_wrapper::SyncWrapper::new(DropTogether::new(events, (spawned, client))),
+        }));
+    }
+
+    // A reattachable command outlives its client, but is still cancelled if nobody reattaches to
+    // it in time.
+    rt.spawn(async move {
+        futures::pin_mut!(spawned);
+        loop {
+            tokio::select! {
+                _ = &mut spawned => return,
+                _ = output.detached() => {}
+            }
+            tokio::select! {
+                _ = &mut spawned => return,
+                _ = output.wait_for_attach() => {}
+                _ = tokio::time::sleep(REATTACH_GRACE_PERIOD) => return,
+            }
+        }
+    });
+
+    Response::new(Box::pin(SyncStream {
+        wrapped: sync_wrapper::SyncWrapper::new(DropTogether::new(events, client)),
+    }))
+}
 
-    let daemon_shutdown_stream = daemon_shutdown_channel
+/// The shutdown event of the daemon, if it shuts down while the command runs.
+///
+/// Note that while this is an event, we don't send it through our normal event
+/// processing. The reason for that is that we dont want this event to queue behind any other
+/// events in the (2) unbounded channels that form our event pipeline. So, we inject this one
+/// directly where Tonic is polling for responses (which, unlike the rest of the pipeline, is
+/// not unbounded, and has backpressure).
+fn daemon_shutdown_stream(
+    trace_id: TraceId,
+    daemon_shutdown_channel: oneshot::Receiver<buck2_data::DaemonShutdown>,
+) -> impl Stream<Item = Result<CommandProgress, Status>> {
+    daemon_shutdown_channel
         .map_ok(move |shutdown| CommandProgress {
             progress: Some(command_progress::Progress::Event(Box::new(
                 buck2_data::BuckEvent {
@@ -713,20 +802,27 @@ where
             // If the channel yields an Err, that means we didnt shut down, so for us that is
             // simply something we want to drop from the stream.
             futures::future::ready(e.ok().map(Ok))
-        });
+        })
+}
 
-    // The stream we ultimately return is the receiving end of the channel that the above task is
-    // writing to, plus the shutdown channel.
-    let events = futures::stream::select(events, daemon_shutdown_stream);
+/// Streams the events of a running reattachable command to a new client.
+fn attach(req: AttachRequest) -> anyhow::Result<Response<ResponseStream>> {
+    let trace_id: TraceId = req.trace_id.parse()?;
+    let output =
+        command_output(&trace_id).ok_or_else(|| AttachError::NoSuchCommand(trace_id.dupe()))?;
 
-    let events = MultiEventStream::new(events);
+    let (output_send, output_recv) = tokio::sync::mpsc::unbounded_channel();
+    let client = output.reattach(output_send)?;
 
-    Response::new(Box::pin(SyncStream {
-        wrapped: sync_wrapper::SyncWrapper::new(DropTogether::new(
-            events,
-            spawned.into_drop_cancel(),
-        )),
-    }))
+    let events = tokio_stream::wrappers::UnboundedReceiverStream::new(output_recv);
+    let events = futures::stream::select(
+        events,
+        daemon_shutdown_stream(trace_id.dupe(), subscribe_shutdown(&trace_id)),
+    );
+    let events = MultiEventStream::new(events).with_heartbeat(STREAM_HEARTBEAT_INTERVAL);
+    Ok(Response::new(Box::pin(SyncStream {
+        wrapped: sync_wrapper::SyncWrapper::new(DropTogether::new(events, client)),
+    })))
 }
 
 type ResponseStream =
@@ -812,6 +908,13 @@ impl DaemonApi for BuckdServer {
             let mut daemon_constraints = self.0.base_daemon_constraints.clone();
             daemon_constraints.extra = extra_constraints;
 
+            let pending_invalidations = if req.dirty {
+                let data = daemon_state.data()?;
+                Some(pending_invalidations(&*data.file_watcher))
+            } else {
+                None
+            };
+
             let valid_working_directory = daemon_state.validate_cwd().is_ok();
             let valid_buck_out_mount = daemon_state.validate_buck_out_mount().is_ok();
 
@@ -841,6 +944,7 @@ impl DaemonApi for BuckdServer {
                     .map(|state| state.http_client.http2()),
                 valid_working_directory: Some(valid_working_directory),
                 valid_buck_out_mount: Some(valid_buck_out_mount),
+                pending_invalidations,
                 ..Default::default()
             };
             Ok(base)
@@ -1212,6 +1316,7 @@ impl DaemonApi for BuckdServer {
             guard,
             daemon_shutdown_channel,
             state,
+            output,
         } = active_command;
 
         let this = self.0.dupe();
@@ -1219,6 +1324,7 @@ impl DaemonApi for BuckdServer {
             req,
             event_source,
             state,
+            output,
             dispatcher.dupe(),
             daemon_shutdown_channel,
             move |req, _| {
@@ -1404,6 +1510,15 @@ impl DaemonApi for BuckdServer {
         Ok(Response::new(SetLogFilterResponse {}))
     }
 
+    type AttachStream = ResponseStream;
+    async fn attach(
+        &self,
+        req: Request<AttachRequest>,
+    ) -> Result<Response<ResponseStream>, Status> {
+        self.check_if_accepting_requests()?;
+        Ok(attach(req.into_inner()).unwrap_or_else(error_to_response_stream))
+    }
+
     type TraceIoStream = ResponseStream;
     async fn trace_io(
         &self,
@@ -7,12 +7,19 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use allocative::FlameGraph;
 use allocative::FlameGraphBuilder;
 use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::paths::abs_path::AbsPathBuf;
+use buck2_core::fs::paths::file_name::FileName;
 use buck2_events::dispatch::EventDispatcher;
 use buck2_util::process_stats::process_stats;
 
@@ -109,3 +116,104 @@ pub(crate) async fn spawn_allocative(
     })
     .await?
 }
+
+/// Set while a heap snapshot is taken, so that commands finishing meanwhile don't take another.
+static HEAP_SNAPSHOT_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+/// The retained size of each allocative key (a type or a field) in a flamegraph. Memory is
+/// counted once per key even if the key appears several times in the path leading to it.
+fn retained_size_by_key(fg: &FlameGraph) -> Vec<(String, usize)> {
+    let src = fg.write();
+    let mut sizes: HashMap<&str, usize> = HashMap::new();
+    for line in src.lines() {
+        let Some((stack, size)) = line.rsplit_once(' ') else {
+            continue;
+        };
+        let Ok(size) = size.parse::<usize>() else {
+            continue;
+        };
+        for key in stack.split(';').collect::<HashSet<_>>() {
+            *sizes.entry(key).or_default() += size;
+        }
+    }
+    let mut sizes: Vec<_> = sizes
+        .into_iter()
+        .map(|(key, size)| (key.to_owned(), size))
+        .collect();
+    sizes.sort_by(|(k1, s1), (k2, s2)| s2.cmp(s1).then_with(|| k1.cmp(k2)));
+    sizes
+}
+
+fn write_heap_snapshot(
+    buckd_server_data: &BuckdServerData,
+    dir: &AbsNormPathBuf,
+    trace_id: &str,
+    keep: usize,
+) -> anyhow::Result<()> {
+    let mut graph = FlameGraphBuilder::default();
+    graph.visit_global_roots();
+    graph.visit_root(buckd_server_data);
+    let fg = wrap_flamegraph_with_system_stats(graph.finish().flamegraph());
+
+    let mut snapshot = String::new();
+    for (key, size) in retained_size_by_key(&fg) {
+        snapshot.push_str(&format!("{}\t{}\n", size, key));
+    }
+    let millis = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_millis();
+    fs_util::create_dir_all(dir)?;
+    // Names sort in the order the snapshots were taken.
+    let name = format!("{:016}-{}.txt", millis, trace_id);
+    fs_util::write(dir.join(FileName::new(&name)?), snapshot)?;
+
+    let mut snapshots = Vec::new();
+    for entry in fs_util::read_dir(dir)? {
+        snapshots.push(entry?.path());
+    }
+    snapshots.sort();
+    for old in &snapshots[..snapshots.len().saturating_sub(keep)] {
+        fs_util::remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// Writes the retained size of each allocative key to `dir` once the command `trace_id` is done,
+/// keeping the last `keep` snapshots, for `buck2 debug heap-diff` to compare.
+pub(crate) fn spawn_heap_snapshot(
+    buckd_server_data: Arc<BuckdServerData>,
+    dir: AbsNormPathBuf,
+    trace_id: String,
+    keep: usize,
+) {
+    if HEAP_SNAPSHOT_IN_PROGRESS.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = write_heap_snapshot(&buckd_server_data, &dir, &trace_id, keep) {
+            tracing::warn!("Error writing heap snapshot: {:#}", e);
+        }
+        HEAP_SNAPSHOT_IN_PROGRESS.store(false, Ordering::Release);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retained_size_by_key() {
+        let mut map = FlameGraph::default();
+        map.add_self(1);
+        let mut node = FlameGraph::default();
+        node.add_self(10);
+        node.add_child(allocative::Key::new("Node"), map.clone());
+        let mut root = FlameGraph::default();
+        root.add_child(allocative::Key::new("Node"), node);
+        root.add_child(allocative::Key::new("Map"), map);
+        assert_eq!(
+            vec![("Node".to_owned(), 11), ("Map".to_owned(), 1)],
+            retained_size_by_key(&root)
+        );
+    }
+}
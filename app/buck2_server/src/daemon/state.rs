@@ -16,16 +16,19 @@ use std::time::Instant;
 
 use allocative::Allocative;
 use anyhow::Context;
+use buck2_build_api::phase_budgets::PhaseBudgets;
 use buck2_build_api::spawner::BuckSpawner;
 use buck2_cli_proto::unstable_dice_dump_request::DiceDumpFormat;
 use buck2_common::cas_digest::DigestAlgorithm;
 use buck2_common::cas_digest::DigestAlgorithmKind;
+use buck2_common::file_hashing::init_hashing_threads;
 use buck2_common::ignores::ignore_set::IgnoreSet;
 use buck2_common::init::DaemonStartupConfig;
 use buck2_common::init::Timeout;
 use buck2_common::invocation_paths::InvocationPaths;
 use buck2_common::io::IoProvider;
 use buck2_common::legacy_configs::cells::BuckConfigBasedCells;
+use buck2_common::legacy_configs::configs::LegacyBuckConfig;
 use buck2_common::legacy_configs::key::BuckconfigKeyRef;
 use buck2_core::buck2_env;
 use buck2_core::cells::name::CellName;
@@ -46,13 +49,21 @@ use buck2_execute::execute::blocking::BlockingExecutor;
 use buck2_execute::execute::blocking::BuckBlockingExecutor;
 use buck2_execute::materialize::materializer::MaterializationMethod;
 use buck2_execute::materialize::materializer::Materializer;
+use buck2_execute::re::client::negotiate_digest_algorithm;
 use buck2_execute::re::manager::ReConnectionManager;
+use buck2_execute::re::manager::ReSessionReuseConfig;
+use buck2_execute::re::retry::ReCircuitBreakerConfig;
+use buck2_execute::re::retry::ReRetryConfig;
+use buck2_execute::re::retry::ReRetryPolicy;
+use buck2_execute_impl::executors::local_action_cache::LocalActionCache;
 use buck2_execute_impl::materializers::deferred::clean_stale::CleanStaleConfig;
+use buck2_execute_impl::materializers::deferred::throttle::MaterializationThrottleConfig;
 use buck2_execute_impl::materializers::deferred::AccessTimesUpdates;
 use buck2_execute_impl::materializers::deferred::DeferredMaterializer;
 use buck2_execute_impl::materializers::deferred::DeferredMaterializerConfigs;
 use buck2_execute_impl::materializers::deferred::TtlRefreshConfiguration;
 use buck2_execute_impl::materializers::immediate::ImmediateMaterializer;
+use buck2_execute_impl::materializers::io::MaterializationDedup;
 use buck2_execute_impl::materializers::sqlite::MaterializerState;
 use buck2_execute_impl::materializers::sqlite::MaterializerStateIdentity;
 use buck2_execute_impl::materializers::sqlite::MaterializerStateSqliteDb;
@@ -176,6 +187,19 @@ pub struct DaemonStateData {
 
     /// Tags to be logged per command.
     pub tags: Vec<String>,
+
+    /// Outputs of locally executed actions persisted across `buck2 clean` and restarts, if
+    /// `buck2.local_action_cache_max_bytes` is set.
+    #[allocative(skip)]
+    pub local_action_cache: Option<Arc<LocalActionCache>>,
+
+    /// Limits on how many analyses and local actions run at once, shared by all commands.
+    #[allocative(skip)]
+    pub phase_budgets: PhaseBudgets,
+
+    /// How many retained memory summaries to keep if one should be written after each command,
+    /// i.e. if `buck2.heap_snapshots` is set.
+    pub heap_snapshots: Option<usize>,
 }
 
 impl DaemonStateData {
@@ -295,19 +319,53 @@ impl DaemonState {
                 }
             });
 
-            let digest_algorithms = init_ctx
-                .daemon_startup_config
-                .digest_algorithms
-                .as_ref()
-                .map(|algos| {
-                    algos
-                        .split(',')
-                        .map(DigestAlgorithmKind::from_str)
-                        .collect::<Result<_, _>>()
-                })
-                .transpose()
-                .context("Invalid digest_algorithms")?
-                .unwrap_or_else(|| vec![default_digest_algorithm])
+            // TODO(rafaelc): merge configs from all cells once they are consistent
+            #[cfg_attr(fbcode_build, allow(unused_mut))]
+            let mut static_metadata =
+                RemoteExecutionStaticMetadata::from_legacy_config(root_config)?;
+
+            let parse_algorithms = |algos: &str| {
+                algos
+                    .split(',')
+                    .map(DigestAlgorithmKind::from_str)
+                    .collect::<Result<Vec<_>, _>>()
+            };
+
+            let digest_algorithms =
+                match &init_ctx.daemon_startup_config.digest_algorithm_candidates {
+                    Some(candidates) => {
+                        if init_ctx.daemon_startup_config.digest_algorithms.is_some() {
+                            return Err(anyhow::anyhow!(
+                                "Set either `buck2.digest_algorithms` or \
+                                `buck2.digest_algorithm_candidates`, not both"
+                            ));
+                        }
+                        let candidates = parse_algorithms(candidates)
+                            .context("Invalid digest_algorithm_candidates")?;
+                        let timeout = root_config
+                            .parse::<u64>(BuckconfigKeyRef {
+                                section: "buck2_re_client",
+                                property: "digest_negotiation_timeout_s",
+                            })?
+                            .unwrap_or(5);
+                        let algorithm = negotiate_digest_algorithm(
+                            &static_metadata,
+                            &candidates,
+                            &paths.digest_negotiation_path(),
+                            Duration::from_secs(timeout),
+                        )
+                        .await?;
+                        vec![algorithm]
+                    }
+                    None => init_ctx
+                        .daemon_startup_config
+                        .digest_algorithms
+                        .as_deref()
+                        .map(parse_algorithms)
+                        .transpose()
+                        .context("Invalid digest_algorithms")?
+                        .unwrap_or_else(|| vec![default_digest_algorithm]),
+                }
                 .into_try_map(convert_algorithm_kind)?;
 
             let preferred_source_algorithm = init_ctx
@@ -322,10 +380,17 @@ impl DaemonState {
                 DigestConfig::leak_new(digest_algorithms, preferred_source_algorithm)
                     .context("Error initializing DigestConfig")?;
 
-            // TODO(rafaelc): merge configs from all cells once they are consistent
-            let static_metadata = Arc::new(RemoteExecutionStaticMetadata::from_legacy_config(
-                root_config,
-            )?);
+            #[cfg(not(fbcode_build))]
+            {
+                static_metadata.0.digest_function = Some(
+                    digest_config
+                        .cas_digest_config()
+                        .preferred_algorithm()
+                        .kind()
+                        .to_string(),
+                );
+            }
+            let static_metadata = Arc::new(static_metadata);
 
             let ignore_specs: HashMap<CellName, IgnoreSet> = legacy_configs
                 .iter()
@@ -347,8 +412,20 @@ impl DaemonState {
 
             let disk_state_options = DiskStateOptions::new(root_config, materializations.dupe())?;
             let blocking_executor = Arc::new(BuckBlockingExecutor::default_concurrency(fs.dupe())?);
+            if let Some(hashing_threads) = root_config.parse::<usize>(BuckconfigKeyRef {
+                section: "buck2",
+                property: "hashing_threads",
+            })? {
+                init_hashing_threads(hashing_threads);
+            }
             let cache_dir_path = paths.cache_dir_path();
             let valid_cache_dirs = paths.valid_cache_dirs();
+            let source_digest_cache = root_config
+                .parse::<bool>(BuckconfigKeyRef {
+                    section: "buck2",
+                    property: "source_digest_cache",
+                })?
+                .unwrap_or(false);
 
             let deferred_materializer_configs = {
                 let defer_write_actions = root_config
@@ -399,6 +476,19 @@ impl DaemonState {
 
                 let clean_stale_config = CleanStaleConfig::from_buck_config(root_config)?;
 
+                let dedup = MaterializationDedup::try_new_from_config_value(root_config.get(
+                    BuckconfigKeyRef {
+                        section: "buck2",
+                        property: "materialization_dedup",
+                    },
+                ))?;
+
+                let throttle = MaterializationThrottleConfig::from_buck_config(
+                    root_config,
+                    &fs,
+                    &paths.buck_out_path(),
+                )?;
+
                 DeferredMaterializerConfigs {
                     materialize_final_artifacts: matches!(
                         materializations,
@@ -413,6 +503,8 @@ impl DaemonState {
                     update_access_times,
                     verbose_materializer_log,
                     clean_stale_config,
+                    dedup,
+                    throttle,
                 }
             };
 
@@ -423,6 +515,7 @@ impl DaemonState {
                     root_config,
                     digest_config.cas_digest_config(),
                     init_ctx.enable_trace_io,
+                    source_digest_cache.then(|| paths.source_digest_cache_path()),
                 ),
                 (blocking_executor.dupe() as Arc<dyn BlockingExecutor>).execute_io_inline(|| {
                     // Using `execute_io_inline` is just out of convenience.
@@ -453,6 +546,10 @@ impl DaemonState {
             #[cfg(fbcode_build)]
             let re_disable_fallocate = static_metadata.disable_fallocate;
 
+            let re_session_reuse = re_session_reuse_config(root_config)?;
+
+            let (re_retries, re_circuit_breaker) = re_retry_config(root_config)?;
+
             let re_client_manager = Arc::new(ReConnectionManager::new(
                 fb,
                 false,
@@ -461,6 +558,9 @@ impl DaemonState {
                 Some(paths.re_logs_dir()),
                 paths.buck_out_path(),
                 init_ctx.daemon_startup_config.paranoid,
+                re_session_reuse,
+                re_retries,
+                re_circuit_breaker,
             ));
             // Used only to dispatch events to scribe that are not associated with a specific command (ex. materializer clean up events)
             let daemon_dispatcher = if let Some(sink) = scribe_sink.dupe() {
@@ -471,6 +571,7 @@ impl DaemonState {
                 EventDispatcher::null()
             };
             let materializer = Self::create_materializer(
+                fb,
                 io.project_root().dupe(),
                 digest_config,
                 paths.buck_out_dir(),
@@ -557,6 +658,43 @@ impl DaemonState {
                 })?
                 .unwrap_or(false);
 
+            let local_action_cache = root_config
+                .parse::<u64>(BuckconfigKeyRef {
+                    section: "buck2",
+                    property: "local_action_cache_max_bytes",
+                })?
+                .filter(|max_bytes| *max_bytes > 0)
+                .map(|max_bytes| LocalActionCache::new(paths.local_action_cache_dir(), max_bytes));
+
+            let phase_budgets = PhaseBudgets::new(
+                root_config.parse::<usize>(BuckconfigKeyRef {
+                    section: "buck2",
+                    property: "analysis_concurrency",
+                })?,
+                root_config.parse::<usize>(BuckconfigKeyRef {
+                    section: "buck2",
+                    property: "action_dispatch_concurrency",
+                })?,
+            );
+
+            // How long a command waits for the computations of a cancelled command to terminate
+            // before running alongside them, 0 to wait until they do.
+            let dice_cleanup_timeout = root_config
+                .parse::<u64>(BuckconfigKeyRef {
+                    section: "buck2",
+                    property: "dice_cleanup_timeout_s",
+                })?
+                .unwrap_or(30);
+            let dice_cleanup_timeout =
+                (dice_cleanup_timeout > 0).then(|| Duration::from_secs(dice_cleanup_timeout));
+
+            let heap_snapshots = root_config
+                .parse::<usize>(BuckconfigKeyRef {
+                    section: "buck2",
+                    property: "heap_snapshots",
+                })?
+                .filter(|keep| *keep > 0);
+
             let tags = vec![
                 format!("dice-detect-cycles:{}", dice.detect_cycles().variant_name()),
                 format!("which-dice:{}", dice.which_dice().variant_name()),
@@ -567,6 +705,8 @@ impl DaemonState {
                 ),
                 format!("paranoid:{}", paranoid.is_some()),
                 format!("remote-dep-files:{}", remote_dep_files_enabled),
+                format!("local-action-cache:{}", local_action_cache.is_some()),
+                format!("heap-snapshots:{}", heap_snapshots.is_some()),
                 #[cfg(fbcode_build)]
                 format!("disable-fallocate:{}", re_disable_fallocate),
             ];
@@ -577,7 +717,7 @@ impl DaemonState {
             // disable the eager spawn for watchman until we fix dice commit to avoid a panic TODO(bobyf)
             // tokio::task::spawn(watchman_query.sync());
             Ok(Arc::new(DaemonStateData {
-                dice_manager: ConcurrencyHandler::new(dice),
+                dice_manager: ConcurrencyHandler::new(dice, dice_cleanup_timeout),
                 file_watcher,
                 io,
                 re_client_manager,
@@ -596,12 +736,16 @@ impl DaemonState {
                 paranoid,
                 spawner: Arc::new(BuckSpawner::new(daemon_state_data_rt)),
                 tags,
+                local_action_cache,
+                phase_budgets,
+                heap_snapshots,
             }))
         })
         .await?
     }
 
     fn create_materializer(
+        fb: FacebookInit,
         fs: ProjectRoot,
         digest_config: DigestConfig,
         buck_out_path: ProjectRelativePathBuf,
@@ -636,6 +780,31 @@ impl DaemonState {
                     daemon_dispatcher,
                 )?))
             }
+            MaterializationMethod::Eden => {
+                #[cfg(fbcode_build)]
+                {
+                    use buck2_execute_impl::materializers::eden::EdenMaterializer;
+
+                    Ok(Arc::new(EdenMaterializer::new(
+                        fb,
+                        fs,
+                        digest_config,
+                        buck_out_path,
+                        re_client_manager,
+                        blocking_executor,
+                        http_client,
+                    )?))
+                }
+
+                // Rejected when `[buck2] materializations` is parsed.
+                #[cfg(not(fbcode_build))]
+                {
+                    let _unused = fb;
+                    Err(anyhow::anyhow!(
+                        "The Eden materializer is not supported in the open source build"
+                    ))
+                }
+            }
         }
     }
 
@@ -829,6 +998,83 @@ fn convert_algorithm_kind(kind: DigestAlgorithmKind) -> anyhow::Result<DigestAlg
     })
 }
 
+/// The RE session is only reused across commands if `session_reuse_idle_timeout_s` is set. It is
+/// then handed to new commands for up to `session_max_age_s` (an hour by default), after which the
+/// next command connects again with fresh credentials.
+fn re_session_reuse_config(
+    root_config: &LegacyBuckConfig,
+) -> anyhow::Result<Option<ReSessionReuseConfig>> {
+    let Some(idle_timeout) = root_config.parse::<u64>(BuckconfigKeyRef {
+        section: "buck2_re_client",
+        property: "session_reuse_idle_timeout_s",
+    })?
+    else {
+        return Ok(None);
+    };
+    let max_age = root_config
+        .parse::<u64>(BuckconfigKeyRef {
+            section: "buck2_re_client",
+            property: "session_max_age_s",
+        })?
+        .unwrap_or(3600);
+    Ok(Some(ReSessionReuseConfig {
+        idle_timeout: Duration::from_secs(idle_timeout),
+        max_age: Duration::from_secs(max_age),
+    }))
+}
+
+/// Parse the `buck2_re_client` settings for retrying RPCs which fail with transient errors, and
+/// for preferring local execution when RE keeps failing.
+fn re_retry_config(
+    root_config: &LegacyBuckConfig,
+) -> anyhow::Result<(ReRetryConfig, Option<ReCircuitBreakerConfig>)> {
+    fn get<T: FromStr>(config: &LegacyBuckConfig, property: &str) -> anyhow::Result<Option<T>>
+    where
+        anyhow::Error: From<<T as FromStr>::Err>,
+    {
+        config.parse(BuckconfigKeyRef {
+            section: "buck2_re_client",
+            property,
+        })
+    }
+
+    let default = ReRetryPolicy::default();
+    let default = ReRetryPolicy {
+        max_attempts: get(root_config, "retry_attempts")?.unwrap_or(default.max_attempts),
+        initial_backoff: get(root_config, "retry_initial_backoff_ms")?
+            .map_or(default.initial_backoff, Duration::from_millis),
+        max_backoff: get(root_config, "retry_max_backoff_ms")?
+            .map_or(default.max_backoff, Duration::from_millis),
+    };
+    let policy = |rpc: &str| -> anyhow::Result<ReRetryPolicy> {
+        Ok(ReRetryPolicy {
+            max_attempts: get(root_config, &format!("{}_retry_attempts", rpc))?
+                .unwrap_or(default.max_attempts),
+            ..default
+        })
+    };
+    let retries = ReRetryConfig {
+        action_cache: policy("action_cache")?,
+        upload: policy("upload")?,
+        download: policy("download")?,
+        write_action_result: policy("write_action_result")?,
+        get_digest_expirations: policy("get_digest_expirations")?,
+        extend_digest_ttl: policy("extend_digest_ttl")?,
+    };
+
+    let circuit_breaker = match get(root_config, "circuit_breaker_errors")? {
+        None | Some(0) => None,
+        Some(errors) => Some(ReCircuitBreakerConfig {
+            errors,
+            cooldown: Duration::from_secs(
+                get(root_config, "circuit_breaker_cooldown_s")?.unwrap_or(30),
+            ),
+        }),
+    };
+
+    Ok((retries, circuit_breaker))
+}
+
 /// Sensible defaults for http client when building from a DaemonStartupConfig.
 const DEFAULT_MAX_REDIRECTS: usize = 10;
 const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 5000;
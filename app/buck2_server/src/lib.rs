@@ -12,6 +12,7 @@
 #![feature(once_cell_try)]
 #![feature(used_with_arg)]
 
+mod action_input_diff;
 pub mod active_commands;
 pub mod builtin_docs;
 mod clean_stale;
@@ -31,3 +32,5 @@ pub mod profile;
 mod snapshot;
 mod subscription;
 mod trace_io;
+mod verify_buck_out;
+mod warm;
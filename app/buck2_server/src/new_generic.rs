@@ -14,8 +14,11 @@ use buck2_server_ctx::other_server_commands::OTHER_SERVER_COMMANDS;
 use buck2_server_ctx::partial_result_dispatcher::NoPartialResult;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 
+use crate::action_input_diff::action_input_diff_command;
 use crate::ctx::ServerCommandContext;
 use crate::materialize::materialize_command;
+use crate::verify_buck_out::verify_buck_out_command;
+use crate::warm::warm_command;
 
 pub(crate) async fn new_generic_command(
     context: &ServerCommandContext<'_>,
@@ -44,6 +47,15 @@ pub(crate) async fn new_generic_command(
                 .expand_external_cell(context, partial_result_dispatcher, e)
                 .await?,
         ),
+        NewGenericRequest::Warm(w) => {
+            NewGenericResponse::Warm(warm_command(context, partial_result_dispatcher, w).await?)
+        }
+        NewGenericRequest::ActionInputDiff(d) => NewGenericResponse::ActionInputDiff(
+            action_input_diff_command(context, partial_result_dispatcher, d).await?,
+        ),
+        NewGenericRequest::VerifyBuckOut(v) => NewGenericResponse::VerifyBuckOut(
+            verify_buck_out_command(context, partial_result_dispatcher, v).await?,
+        ),
     };
     let resp = serde_json::to_string(&resp).context("Could not serialize `NewGenericResponse`")?;
     Ok(buck2_cli_proto::NewGenericResponseMessage {
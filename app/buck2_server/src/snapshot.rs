@@ -122,6 +122,21 @@ impl SnapshotCollector {
                 stats.get_digest_expirations.finished_successfully;
             snapshot.re_get_digest_expirations_finished_with_error =
                 stats.get_digest_expirations.finished_with_error;
+            snapshot.re_uploads_retries = stats.uploads.retries;
+            snapshot.re_uploads_latency_us = stats.uploads.latency_us;
+            snapshot.re_downloads_retries = stats.downloads.retries;
+            snapshot.re_downloads_latency_us = stats.downloads.latency_us;
+            snapshot.re_action_cache_retries = stats.action_cache.retries;
+            snapshot.re_action_cache_latency_us = stats.action_cache.latency_us;
+            snapshot.re_executes_retries = stats.executes.retries;
+            snapshot.re_executes_latency_us = stats.executes.latency_us;
+            snapshot.re_materializes_retries = stats.materializes.retries;
+            snapshot.re_materializes_latency_us = stats.materializes.latency_us;
+            snapshot.re_write_action_results_retries = stats.write_action_results.retries;
+            snapshot.re_write_action_results_latency_us = stats.write_action_results.latency_us;
+            snapshot.re_get_digest_expirations_retries = stats.get_digest_expirations.retries;
+            snapshot.re_get_digest_expirations_latency_us = stats.get_digest_expirations.latency_us;
+            snapshot.re_circuit_open = stats.circuit_open;
 
             Ok(())
         }
@@ -0,0 +1,81 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_cli_proto::new_generic::VerifyBuckOutEntry;
+use buck2_cli_proto::new_generic::VerifyBuckOutRequest;
+use buck2_cli_proto::new_generic::VerifyBuckOutResponse;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::partial_result_dispatcher::NoPartialResult;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::template::run_server_command;
+use buck2_server_ctx::template::ServerCommandTemplate;
+use dice::DiceTransaction;
+
+use crate::ctx::ServerCommandContext;
+
+pub(crate) async fn verify_buck_out_command(
+    context: &ServerCommandContext<'_>,
+    partial_result_dispatcher: PartialResultDispatcher<NoPartialResult>,
+    req: VerifyBuckOutRequest,
+) -> anyhow::Result<VerifyBuckOutResponse> {
+    run_server_command(
+        VerifyBuckOutServerCommand { req },
+        context,
+        partial_result_dispatcher,
+    )
+    .await
+}
+
+struct VerifyBuckOutServerCommand {
+    req: VerifyBuckOutRequest,
+}
+
+#[async_trait]
+impl ServerCommandTemplate for VerifyBuckOutServerCommand {
+    type StartEvent = buck2_data::VerifyBuckOutCommandStart;
+    type EndEvent = buck2_data::VerifyBuckOutCommandEnd;
+    type Response = VerifyBuckOutResponse;
+    type PartialResult = NoPartialResult;
+
+    async fn command(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        _partial_result_dispatcher: PartialResultDispatcher<Self::PartialResult>,
+        _ctx: DiceTransaction,
+    ) -> anyhow::Result<Self::Response> {
+        let materializer = server_ctx.materializer();
+        let deferred_materializer = materializer
+            .as_deferred_materializer_extension()
+            .context("Deferred materializer is not in use")?;
+
+        let report = deferred_materializer
+            .verify_buck_out(self.req.repair)
+            .await
+            .context("Failed to verify buck-out")?;
+
+        Ok(VerifyBuckOutResponse {
+            checked: report.checked,
+            corrupt: report
+                .corrupt
+                .into_iter()
+                .map(|a| VerifyBuckOutEntry {
+                    path: a.path.to_string(),
+                    reason: a.reason,
+                    repaired: a.repaired,
+                })
+                .collect(),
+        })
+    }
+
+    fn is_success(&self, _response: &Self::Response) -> bool {
+        true
+    }
+}
@@ -0,0 +1,171 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use buck2_build_api::actions::execute::dice_data::GetReClient;
+use buck2_cli_proto::new_generic::WarmAction;
+use buck2_cli_proto::new_generic::WarmRequest;
+use buck2_cli_proto::new_generic::WarmResponse;
+use buck2_core::execution_types::executor_config::RemoteExecutorUseCase;
+use buck2_events::dispatch::with_dispatcher_async;
+use buck2_events::dispatch::EventDispatcher;
+use buck2_execute::digest_config::DigestConfig;
+use buck2_execute::digest_config::HasDigestConfig;
+use buck2_execute::execute::action_digest::ActionDigest;
+use buck2_execute::execute::blocking::BlockingExecutor;
+use buck2_execute::re::manager::ManagedRemoteExecutionClient;
+use buck2_execute_impl::executors::local_action_cache::LocalActionCache;
+use buck2_execute_impl::executors::local_action_cache::WarmOutcome;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::partial_result_dispatcher::NoPartialResult;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::template::run_server_command;
+use buck2_server_ctx::template::ServerCommandTemplate;
+use dice::DiceTransaction;
+use dupe::Dupe;
+use futures::StreamExt;
+
+use crate::ctx::ServerCommandContext;
+
+/// How many actions are fetched from the remote action cache concurrently.
+const WARM_CONCURRENCY: usize = 32;
+
+#[derive(Debug, buck2_error::Error)]
+enum WarmError {
+    #[error(
+        "The local action cache is not enabled, set `buck2.local_action_cache_max_bytes` to enable it"
+    )]
+    LocalActionCacheDisabled,
+}
+
+pub(crate) async fn warm_command(
+    context: &ServerCommandContext<'_>,
+    partial_result_dispatcher: PartialResultDispatcher<NoPartialResult>,
+    req: WarmRequest,
+) -> anyhow::Result<WarmResponse> {
+    let daemon = &context.base_context.daemon;
+    run_server_command(
+        WarmServerCommand {
+            req,
+            cache: daemon.local_action_cache.dupe(),
+            blocking_executor: daemon.blocking_executor.dupe(),
+        },
+        context,
+        partial_result_dispatcher,
+    )
+    .await
+}
+
+struct WarmServerCommand {
+    req: WarmRequest,
+    cache: Option<Arc<LocalActionCache>>,
+    blocking_executor: Arc<dyn BlockingExecutor>,
+}
+
+/// Downloads the outputs of actions into the local action cache.
+struct Warmer {
+    cache: Arc<LocalActionCache>,
+    blocking_executor: Arc<dyn BlockingExecutor>,
+    re_client: ManagedRemoteExecutionClient,
+    digest_config: DigestConfig,
+}
+
+impl Warmer {
+    async fn warm_action(&self, action: &WarmAction) -> anyhow::Result<WarmOutcome> {
+        let (action_digest, _) = ActionDigest::parse_digest(
+            &action.action_digest,
+            self.digest_config.cas_digest_config(),
+        )?;
+        let use_case = match &action.use_case {
+            Some(use_case) => RemoteExecutorUseCase::new(use_case.clone()),
+            None => RemoteExecutorUseCase::buck2_default(),
+        };
+        self.cache
+            .warm(
+                &self.re_client,
+                self.blocking_executor.as_ref(),
+                action_digest,
+                use_case,
+                self.digest_config,
+            )
+            .await
+    }
+
+    async fn warm(&self, actions: &[WarmAction]) -> WarmResponse {
+        let mut response = WarmResponse::default();
+        let mut outcomes = futures::stream::iter(actions)
+            .map(|action| async move { (action, self.warm_action(action).await) })
+            .buffer_unordered(WARM_CONCURRENCY);
+        while let Some((action, outcome)) = outcomes.next().await {
+            match outcome {
+                Ok(WarmOutcome::Stored(bytes)) => {
+                    response.stored += 1;
+                    response.bytes_downloaded += bytes;
+                }
+                Ok(WarmOutcome::AlreadyCached) => response.already_cached += 1,
+                Ok(WarmOutcome::NotFound) => response.not_found += 1,
+                Err(e) => {
+                    tracing::warn!("Error warming action `{}`: {:#}", action.action_digest, e);
+                    response.failed += 1;
+                }
+            }
+        }
+        response
+    }
+}
+
+#[async_trait]
+impl ServerCommandTemplate for WarmServerCommand {
+    type StartEvent = buck2_data::WarmCommandStart;
+    type EndEvent = buck2_data::WarmCommandEnd;
+    type Response = WarmResponse;
+    type PartialResult = NoPartialResult;
+
+    async fn command(
+        &self,
+        _server_ctx: &dyn ServerCommandContextTrait,
+        _partial_result_dispatcher: PartialResultDispatcher<Self::PartialResult>,
+        ctx: DiceTransaction,
+    ) -> anyhow::Result<Self::Response> {
+        let Some(cache) = &self.cache else {
+            return Err(WarmError::LocalActionCacheDisabled.into());
+        };
+        let warmer = Warmer {
+            cache: cache.dupe(),
+            blocking_executor: self.blocking_executor.dupe(),
+            re_client: ctx.per_transaction_data().get_re_client(),
+            digest_config: ctx.global_data().get_digest_config(),
+        };
+        if self.req.wait {
+            return Ok(warmer.warm(&self.req.actions).await);
+        }
+
+        // The downloads outlive the command, so their events are not part of its log.
+        let actions = self.req.actions.clone();
+        tokio::spawn(with_dispatcher_async(EventDispatcher::null(), async move {
+            let response = warmer.warm(&actions).await;
+            tracing::info!(
+                "Warmed {} of {} actions ({} bytes downloaded): {} already cached, {} not in the remote cache, {} failed",
+                response.stored,
+                actions.len(),
+                response.bytes_downloaded,
+                response.already_cached,
+                response.not_found,
+                response.failed,
+            );
+        }));
+        Ok(WarmResponse::default())
+    }
+
+    fn is_success(&self, _response: &Self::Response) -> bool {
+        true
+    }
+}
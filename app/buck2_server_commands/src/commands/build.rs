@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::HashSet;
 use std::io::BufWriter;
 use std::io::Write;
 use std::sync::Arc;
@@ -239,6 +240,10 @@ async fn build(
             .unwrap();
     let materialization_context =
         ConvertMaterializationContext::from(final_artifact_materializations);
+    let requested_targets = RequestedTargets::new(
+        final_artifact_materializations == Materializations::MaterializeRequested,
+        &parsed_patterns,
+    );
 
     let want_configured_graph_size = ctx
         .parse_legacy_config_property(
@@ -259,6 +264,7 @@ async fn build(
                 target_resolution_config,
                 build_providers,
                 &materialization_context,
+                &requested_targets,
                 build_opts.fail_fast,
                 MissingTargetBehavior::from_skip(build_opts.skip_missing_targets),
                 build_opts.skip_incompatible_targets,
@@ -414,6 +420,7 @@ async fn build_targets(
     target_resolution_config: TargetResolutionConfig,
     build_providers: Arc<BuildProviders>,
     materialization_context: &MaterializationContext,
+    requested_targets: &RequestedTargets,
     fail_fast: bool,
     missing_target_behavior: MissingTargetBehavior,
     skip_incompatible_targets: bool,
@@ -430,6 +437,7 @@ async fn build_targets(
                 global_cfg_options,
                 build_providers,
                 materialization_context,
+                requested_targets,
                 missing_target_behavior,
                 skip_incompatible_targets,
                 want_configured_graph_size,
@@ -442,6 +450,7 @@ async fn build_targets(
             universe,
             build_providers,
             materialization_context,
+            requested_targets,
             want_configured_graph_size,
         )
         .map(BuildEvent::Configured)
@@ -457,6 +466,7 @@ fn build_targets_in_universe<'a>(
     universe: CqueryUniverse,
     build_providers: Arc<BuildProviders>,
     materialization_context: &'a MaterializationContext,
+    requested_targets: &'a RequestedTargets,
     want_configured_graph_size: bool,
 ) -> impl Stream<Item = ConfiguredBuildEvent> + Unpin + 'a {
     let providers_to_build = build_providers_to_providers_to_build(&build_providers);
@@ -465,15 +475,19 @@ fn build_targets_in_universe<'a>(
         .into_iter()
         .map(|p| {
             let providers_to_build = providers_to_build.clone();
+            let materialization_context = requested_targets
+                .materialization_context(p.target().unconfigured(), materialization_context);
             async move {
                 build::build_configured_label(
                     ctx,
-                    materialization_context,
+                    &materialization_context,
                     p,
                     &providers_to_build,
                     build::BuildConfiguredLabelOptions {
                         skippable: false,
                         want_configured_graph_size,
+                        materialize_default_outputs_only: requested_targets
+                            .materialize_default_outputs_only(),
                     },
                 )
                 .await
@@ -489,6 +503,7 @@ fn build_targets_with_global_target_platform<'a>(
     global_cfg_options: GlobalCfgOptions,
     build_providers: Arc<BuildProviders>,
     materialization_context: &'a MaterializationContext,
+    requested_targets: &'a RequestedTargets,
     missing_target_behavior: MissingTargetBehavior,
     skip_incompatible_targets: bool,
     want_configured_graph_size: bool,
@@ -501,6 +516,7 @@ fn build_targets_with_global_target_platform<'a>(
             global_cfg_options.dupe(),
             build_providers.dupe(),
             materialization_context,
+            requested_targets,
             missing_target_behavior,
             skip_incompatible_targets,
             want_configured_graph_size,
@@ -511,6 +527,51 @@ fn build_targets_with_global_target_platform<'a>(
     .flatten_unordered(None)
 }
 
+/// With `--materialize-requested`, the targets named on the command line. Only their default
+/// outputs are materialized: targets only matched by a pattern like `//foo/...` or `//foo:` are
+/// built, but their outputs stay in the CAS.
+struct RequestedTargets(Option<HashSet<TargetLabel>>);
+
+impl RequestedTargets {
+    fn new(
+        materialize_requested_only: bool,
+        patterns: &[ParsedPattern<ConfiguredProvidersPatternExtra>],
+    ) -> Self {
+        if !materialize_requested_only {
+            return RequestedTargets(None);
+        }
+        // Collected from the patterns rather than from the resolved pattern, in which a target's
+        // package is merged into `PackageSpec::All` when another pattern also matches it.
+        RequestedTargets(Some(
+            patterns
+                .iter()
+                .filter_map(|pattern| match pattern {
+                    ParsedPattern::Target(package, name, _) => {
+                        Some(TargetLabel::new(package.dupe(), name))
+                    }
+                    ParsedPattern::Package(..) | ParsedPattern::Recursive(..) => None,
+                })
+                .collect(),
+        ))
+    }
+
+    fn materialize_default_outputs_only(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// How the outputs of `target` are materialized.
+    fn materialization_context(
+        &self,
+        target: &TargetLabel,
+        materialization_context: &MaterializationContext,
+    ) -> MaterializationContext {
+        match &self.0 {
+            Some(requested) if !requested.contains(target) => MaterializationContext::Skip,
+            _ => materialization_context.dupe(),
+        }
+    }
+}
+
 struct TargetBuildSpec {
     target: TargetNode,
     providers: ProvidersName,
@@ -548,6 +609,7 @@ async fn build_targets_for_spec<'a>(
     global_cfg_options: GlobalCfgOptions,
     build_providers: Arc<BuildProviders>,
     materialization_context: &'a MaterializationContext,
+    requested_targets: &'a RequestedTargets,
     missing_target_behavior: MissingTargetBehavior,
     skip_incompatible_targets: bool,
     want_configured_graph_size: bool,
@@ -631,6 +693,7 @@ async fn build_targets_for_spec<'a>(
                     build_spec,
                     &providers_to_build,
                     materialization_context,
+                    requested_targets,
                 )
                 .await
             }
@@ -646,6 +709,7 @@ async fn build_target<'a>(
     spec: TargetBuildSpec,
     providers_to_build: &ProvidersToBuild,
     materialization_context: &MaterializationContext,
+    requested_targets: &RequestedTargets,
 ) -> impl Stream<Item = BuildEvent> + 'a {
     let providers_label = ProvidersLabel::new(spec.target.label().dupe(), spec.providers);
     let providers_label = match ctx
@@ -663,17 +727,68 @@ async fn build_target<'a>(
         }
     };
 
+    let materialization_context =
+        requested_targets.materialization_context(spec.target.label(), materialization_context);
+
     build::build_configured_label(
         ctx,
-        materialization_context,
+        &materialization_context,
         providers_label,
         providers_to_build,
         build::BuildConfiguredLabelOptions {
             skippable: spec.skippable,
             want_configured_graph_size: spec.want_configured_graph_size,
+            materialize_default_outputs_only: requested_targets.materialize_default_outputs_only(),
         },
     )
     .await
     .map(BuildEvent::Configured)
     .right_stream()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requested_targets(materialize_requested_only: bool, patterns: &[&str]) -> RequestedTargets {
+        RequestedTargets::new(
+            materialize_requested_only,
+            &patterns
+                .iter()
+                .map(|p| ParsedPattern::testing_parse(p))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn is_skipped(requested_targets: &RequestedTargets, target: &str) -> bool {
+        matches!(
+            requested_targets.materialization_context(
+                &TargetLabel::testing_parse(target),
+                &MaterializationContext::force_materializations(),
+            ),
+            MaterializationContext::Skip
+        )
+    }
+
+    #[test]
+    fn test_requested_targets() {
+        let requested = requested_targets(true, &["cell//foo:bar", "cell//foo/..."]);
+        assert!(requested.materialize_default_outputs_only());
+        // Still requested although `cell//foo/...` also matches it.
+        assert!(!is_skipped(&requested, "cell//foo:bar"));
+        assert!(is_skipped(&requested, "cell//foo:baz"));
+        assert!(is_skipped(&requested, "cell//foo/qux:bar"));
+
+        let requested = requested_targets(true, &["cell//foo:", "cell//foo:bar"]);
+        assert!(!is_skipped(&requested, "cell//foo:bar"));
+        assert!(is_skipped(&requested, "cell//foo:baz"));
+    }
+
+    #[test]
+    fn test_requested_targets_disabled() {
+        let requested = requested_targets(false, &["cell//foo:bar", "cell//foo/..."]);
+        assert!(!requested.materialize_default_outputs_only());
+        assert!(!is_skipped(&requested, "cell//foo:bar"));
+        assert!(!is_skipped(&requested, "cell//foo:baz"));
+    }
+}
@@ -7,6 +7,11 @@
  * of this source tree.
  */
 
+use std::io::Write;
+
+use buck2_query::query::syntax::simple::eval::profile::QueryProfile;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+
 pub mod aquery;
 pub mod cquery;
 pub mod printer;
@@ -19,4 +24,22 @@ enum QueryCommandError {
         "query result was a set of files and one or more --output-attribute was requested, but files have not attributes"
     )]
     FileSetHasNoAttributes,
+    #[error("`--output-format files` prints paths, so it can't be used with --output-attribute")]
+    FilesOutputHasNoAttributes,
+}
+
+/// When the evaluation of a query fails there is no response to return its profile in, so the
+/// profile is printed to stderr before the error.
+fn print_profile_on_error<T>(
+    server_ctx: &dyn ServerCommandContextTrait,
+    profile: Option<&QueryProfile>,
+    result: anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    if let (Err(_), Some(profile)) = (&result, profile) {
+        // Failing to print the profile mustn't hide the error.
+        if let Ok(mut stderr) = server_ctx.stderr() {
+            let _ignored = write!(stderr, "{}", profile);
+        }
+    }
+    result
 }
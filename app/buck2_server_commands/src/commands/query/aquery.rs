@@ -13,16 +13,25 @@ use async_trait::async_trait;
 use buck2_build_api::actions::query::ActionQueryNode;
 use buck2_build_api::query::oneshot::QUERY_FRONTEND;
 use buck2_common::dice::cells::HasCellResolver;
+use buck2_core::cells::CellResolver;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_error::BuckErrorContext;
 use buck2_query::query::environment::AttrFmtOptions;
+use buck2_query::query::syntax::simple::eval::multi_query::MultiQueryResult;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfile;
+use buck2_query::query::syntax::simple::eval::set::TargetSet;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
+use buck2_query::query::syntax::simple::eval::values::QueryEvaluationValue;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::global_cfg_options::global_cfg_options_from_client_context;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 use buck2_server_ctx::template::run_server_command;
 use buck2_server_ctx::template::ServerCommandTemplate;
+use dice::DiceComputations;
 use dice::DiceTransaction;
+use futures::FutureExt;
 
+use crate::commands::query::print_profile_on_error;
 use crate::commands::query::printer::QueryResultPrinter;
 use crate::commands::query::printer::ShouldPrintProviders;
 use crate::commands::query::query_target_ext::QueryCommandTarget;
@@ -32,6 +41,17 @@ impl QueryCommandTarget for ActionQueryNode {
         None
     }
 
+    /// The outputs of the action, which are what tools consuming the files of an aquery want,
+    /// unlike its inputs.
+    fn files_for_each(
+        &self,
+        _resolver: &CellResolver,
+        func: &mut dyn FnMut(ProjectRelativePathBuf),
+    ) -> anyhow::Result<()> {
+        self.output_paths()?.into_iter().for_each(func);
+        Ok(())
+    }
+
     fn attr_to_string_alternate(&self, _options: AttrFmtOptions, attr: &Self::Attr<'_>) -> String {
         format!("{:#}", attr)
     }
@@ -105,6 +125,7 @@ async fn aquery(
         &cell_resolver,
         &request.output_attributes,
         request.unstable_output_format,
+        &request.dot_edges,
     )?;
 
     let buck2_cli_proto::AqueryRequest {
@@ -121,6 +142,7 @@ async fn aquery(
     )
     .await?;
 
+    let profile = request.profile.then(QueryProfile::default);
     let query_result = QUERY_FRONTEND
         .get()?
         .eval_aquery(
@@ -129,8 +151,16 @@ async fn aquery(
             query,
             query_args,
             global_cfg_options,
+            profile.as_ref(),
         )
-        .await?;
+        .await;
+    let query_result = print_profile_on_error(server_ctx, profile.as_ref(), query_result)?;
+
+    let query_result = if request.show_inputs_digests {
+        with_inputs_digests(&mut ctx, query_result).await?
+    } else {
+        query_result
+    };
 
     match query_result {
         QueryEvaluationResult::Single(targets) => {
@@ -144,5 +174,52 @@ async fn aquery(
                 .await?
         }
     };
-    Ok(buck2_cli_proto::AqueryResponse {})
+    Ok(buck2_cli_proto::AqueryResponse {
+        profile: profile.map(|profile| profile.to_string()),
+    })
+}
+
+async fn with_inputs_digests(
+    ctx: &mut DiceComputations<'_>,
+    result: QueryEvaluationResult<ActionQueryNode>,
+) -> anyhow::Result<QueryEvaluationResult<ActionQueryNode>> {
+    async fn value_with_inputs_digests(
+        ctx: &mut DiceComputations<'_>,
+        value: QueryEvaluationValue<ActionQueryNode>,
+    ) -> anyhow::Result<QueryEvaluationValue<ActionQueryNode>> {
+        match value {
+            QueryEvaluationValue::TargetSet(targets) => {
+                let nodes = ctx
+                    .try_compute_join(targets.into_iter(), |ctx, node| {
+                        node.with_inputs_digests(ctx).boxed()
+                    })
+                    .await?;
+                Ok(QueryEvaluationValue::TargetSet(
+                    nodes.into_iter().collect::<TargetSet<_>>(),
+                ))
+            }
+            value @ QueryEvaluationValue::FileSet(..) => Ok(value),
+        }
+    }
+
+    match result {
+        QueryEvaluationResult::Single(value) => Ok(QueryEvaluationResult::Single(
+            value_with_inputs_digests(ctx, value).await?,
+        )),
+        QueryEvaluationResult::Multiple(results) => {
+            let mut values = Vec::with_capacity(results.0.len());
+            for (literal, value) in results.0 {
+                let value = match value {
+                    Ok(value) => value_with_inputs_digests(ctx, value)
+                        .await
+                        .map_err(buck2_error::Error::from),
+                    Err(e) => Err(e),
+                };
+                values.push((literal, value));
+            }
+            Ok(QueryEvaluationResult::Multiple(MultiQueryResult(
+                values.into_iter().collect(),
+            )))
+        }
+    }
 }
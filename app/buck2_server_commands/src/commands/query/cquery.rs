@@ -10,23 +10,28 @@
 use std::io::Write;
 
 use async_trait::async_trait;
+use buck2_build_api::actions::artifact::get_artifact_fs::GetArtifactFs;
 use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
 use buck2_build_api::interpreter::rule_defs::provider::collection::FrozenProviderCollectionValue;
 use buck2_build_api::query::oneshot::CqueryOwnerBehavior;
 use buck2_build_api::query::oneshot::QUERY_FRONTEND;
 use buck2_cli_proto::CqueryRequest;
 use buck2_cli_proto::CqueryResponse;
+use buck2_cli_proto::QueryOutputFormat;
 use buck2_common::dice::cells::HasCellResolver;
 use buck2_core::configuration::compatibility::MaybeCompatible;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
 use buck2_core::provider::label::ProvidersName;
 use buck2_error::BuckErrorContext;
+use buck2_execute::artifact::artifact_dyn::ArtifactDyn;
 use buck2_node::attrs::display::AttrDisplayWithContext;
 use buck2_node::attrs::display::AttrDisplayWithContextExt;
 use buck2_node::attrs::fmt_context::AttrFmtContext;
 use buck2_node::attrs::serialize::AttrSerializeWithContext;
 use buck2_node::nodes::configured::ConfiguredTargetNode;
 use buck2_query::query::environment::AttrFmtOptions;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfile;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::global_cfg_options::global_cfg_options_from_client_context;
@@ -38,6 +43,7 @@ use dice::DiceTransaction;
 use dice::LinearRecomputeDiceComputations;
 use dupe::Dupe;
 
+use crate::commands::query::print_profile_on_error;
 use crate::commands::query::printer::ProviderLookUp;
 use crate::commands::query::printer::QueryResultPrinter;
 use crate::commands::query::printer::ShouldPrintProviders;
@@ -143,10 +149,12 @@ async fn cquery(
     request: &CqueryRequest,
 ) -> anyhow::Result<CqueryResponse> {
     let cell_resolver = ctx.get_cell_resolver().await?;
+    let files_output = request.unstable_output_format == QueryOutputFormat::Files as i32;
     let output_configuration = QueryResultPrinter::from_request_options(
         &cell_resolver,
         &request.output_attributes,
         request.unstable_output_format,
+        &request.dot_edges,
     )?;
 
     let CqueryRequest {
@@ -183,6 +191,7 @@ async fn cquery(
         false => CqueryOwnerBehavior::Deprecated,
     };
 
+    let profile = request.profile.then(QueryProfile::default);
     let query_result = QUERY_FRONTEND
         .get()?
         .eval_cquery(
@@ -193,11 +202,14 @@ async fn cquery(
             query_args,
             global_cfg_options,
             target_universe,
+            profile.as_ref(),
         )
-        .await?;
+        .await;
+    let query_result = print_profile_on_error(server_ctx, profile.as_ref(), query_result)?;
 
     ctx.with_linear_recompute(|ctx| async move {
-        let should_print_providers = if *show_providers {
+        // The files output also prints the outputs of the targets, found in their providers.
+        let should_print_providers = if *show_providers || files_output {
             ShouldPrintProviders::Yes(&ctx as &dyn ProviderLookUp<ConfiguredTargetNode>)
         } else {
             ShouldPrintProviders::No
@@ -229,7 +241,9 @@ async fn cquery(
     })
     .await?;
 
-    Ok(CqueryResponse {})
+    Ok(CqueryResponse {
+        profile: profile.map(|profile| profile.to_string()),
+    })
 }
 
 #[async_trait]
@@ -245,4 +259,24 @@ impl ProviderLookUp<ConfiguredTargetNode> for LinearRecomputeDiceComputations<'_
             ))
             .await
     }
+
+    async fn default_output_paths(
+        &self,
+        t: &ConfiguredTargetNode,
+    ) -> anyhow::Result<Vec<ProjectRelativePathBuf>> {
+        let providers = match self.lookup(t).await? {
+            MaybeCompatible::Compatible(providers) => providers,
+            MaybeCompatible::Incompatible(_) => return Ok(Vec::new()),
+        };
+        let artifact_fs = self.get().get_artifact_fs().await?;
+        let mut artifacts = Vec::new();
+        providers
+            .provider_collection()
+            .default_info()
+            .for_each_default_output_artifact_only(&mut |a| artifacts.push(a))?;
+        artifacts
+            .iter()
+            .map(|a| a.resolve_path(&artifact_fs))
+            .collect()
+    }
 }
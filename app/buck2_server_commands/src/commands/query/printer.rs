@@ -10,6 +10,7 @@
 #![allow(clippy::drop_non_drop)] // FIXME?
 
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::io::Write;
@@ -18,9 +19,11 @@ use async_trait::async_trait;
 use buck2_build_api::actions::query::ActionQueryNode;
 use buck2_build_api::actions::query::PRINT_ACTION_NODE;
 use buck2_build_api::interpreter::rule_defs::provider::collection::FrozenProviderCollectionValue;
+use buck2_cli_proto::QueryDepKind;
 use buck2_cli_proto::QueryOutputFormat;
 use buck2_core::cells::CellResolver;
 use buck2_core::configuration::compatibility::MaybeCompatible;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_query::query::environment::AttrFmtOptions;
 use buck2_query::query::environment::QueryTarget;
 use buck2_query::query::environment::QueryTargets;
@@ -32,6 +35,7 @@ use buck2_util::indent::indent;
 use dupe::Clone_;
 use dupe::Copy_;
 use dupe::Dupe_;
+use futures::StreamExt;
 use gazebo::variants::UnpackVariants;
 use indent_write::fmt::IndentWriter;
 use indent_write::io::IndentWriter as IoIndentWriter;
@@ -47,6 +51,9 @@ use crate::dot::targets::DotTargetGraph;
 use crate::dot::Dot;
 use crate::dot::DotCompact;
 
+/// How many targets `json_lines` output looks up the providers of at once.
+const JSON_LINES_CONCURRENCY: usize = 100;
+
 #[derive(Copy_, Dupe_, Clone_, UnpackVariants)]
 pub enum ShouldPrintProviders<'a, T> {
     No,
@@ -57,6 +64,9 @@ pub enum ShouldPrintProviders<'a, T> {
 pub trait ProviderLookUp<T: QueryTarget>: Send + Sync {
     async fn lookup(&self, t: &T)
     -> anyhow::Result<MaybeCompatible<FrozenProviderCollectionValue>>;
+
+    /// The paths of the default outputs of `t`, which `--output-format files` prints.
+    async fn default_output_paths(&self, t: &T) -> anyhow::Result<Vec<ProjectRelativePathBuf>>;
 }
 
 #[derive(Debug)]
@@ -64,6 +74,8 @@ pub(crate) struct QueryResultPrinter<'a> {
     resolver: &'a CellResolver,
     attributes: Option<RegexSet>,
     output_format: QueryOutputFormat,
+    /// The edges drawn in graph output. Empty means all of them.
+    dot_edges: Vec<QueryDepKind>,
 }
 
 struct TargetSetJsonPrinter<'a, T: QueryTarget> {
@@ -81,13 +93,20 @@ impl<'a, T: QueryTarget> TargetSetJsonPrinter<'a, T> {
         Ok(TargetSetJsonPrinter {
             value: printable_targets(targets, print_providers, attributes, target_call_stacks)
                 .await?,
-            is_complex: attributes.is_some()
-                || target_call_stacks
-                || print_providers.unpack_yes().is_some(),
+            is_complex: is_complex(attributes, target_call_stacks, print_providers),
         })
     }
 }
 
+/// Whether targets are printed with their details, rather than only their labels.
+fn is_complex<T>(
+    attributes: &Option<RegexSet>,
+    target_call_stacks: bool,
+    print_providers: ShouldPrintProviders<'_, T>,
+) -> bool {
+    attributes.is_some() || target_call_stacks || print_providers.unpack_yes().is_some()
+}
+
 struct PrintableQueryTarget<'a, T: QueryTarget> {
     value: &'a T,
     attributes: &'a Option<RegexSet>,
@@ -224,12 +243,19 @@ impl<'a> QueryResultPrinter<'a> {
         resolver: &'a CellResolver,
         attributes: &[String],
         output_format: i32,
+        dot_edges: &[i32],
     ) -> anyhow::Result<Self> {
         Self::from_options(
             resolver,
             attributes,
             QueryOutputFormat::from_i32(output_format)
                 .expect("cli should send a valid output_format enum"),
+            dot_edges
+                .iter()
+                .map(|kind| {
+                    QueryDepKind::from_i32(*kind).expect("cli should send a valid dep kind enum")
+                })
+                .collect(),
         )
     }
 
@@ -237,10 +263,14 @@ impl<'a> QueryResultPrinter<'a> {
         resolver: &'a CellResolver,
         attributes: &[String],
         output_format: QueryOutputFormat,
+        dot_edges: Vec<QueryDepKind>,
     ) -> anyhow::Result<Self> {
         let output_format = match (output_format, attributes.is_empty()) {
             // following buck1's behavior, if any attributes are requested we use json output instead of list output
             (QueryOutputFormat::Default, false) => QueryOutputFormat::Json,
+            (QueryOutputFormat::Files, false) => {
+                return Err(QueryCommandError::FilesOutputHasNoAttributes.into());
+            }
             (v, _) => v,
         };
 
@@ -254,6 +284,7 @@ impl<'a> QueryResultPrinter<'a> {
             resolver,
             attributes,
             output_format,
+            dot_edges,
         })
     }
 
@@ -390,11 +421,16 @@ impl<'a> QueryResultPrinter<'a> {
                     // need to add a newline to flush the output.
                     writeln!(&mut output)?
                 }
+                QueryOutputFormat::JsonLines => {
+                    self.print_json_lines(&mut output, &targets, call_stack, print_providers)
+                        .await?;
+                }
                 QueryOutputFormat::Dot => {
                     Dot::render(
                         &DotTargetGraph {
                             targets,
                             attributes: self.attributes.clone(),
+                            edges: self.dot_edges.clone(),
                         },
                         &mut output,
                     )?;
@@ -404,17 +440,35 @@ impl<'a> QueryResultPrinter<'a> {
                         &DotTargetGraph {
                             targets,
                             attributes: self.attributes.clone(),
+                            edges: self.dot_edges.clone(),
                         },
                         &mut output,
                     )?;
                 }
+                QueryOutputFormat::Files => {
+                    let mut files = BTreeSet::new();
+                    for target in targets.iter() {
+                        target.files_for_each(self.resolver, &mut |file| {
+                            files.insert(file);
+                        })?;
+                        // The files generated by the target, when they can be found.
+                        if let Some(lookup) = print_providers.unpack_yes() {
+                            files.extend(lookup.default_output_paths(target).await?);
+                        }
+                    }
+                    for file in files {
+                        writeln!(&mut output, "{}", file)?;
+                    }
+                }
             },
             QueryEvaluationValue::FileSet(files) => {
                 if self.attributes.is_some() {
                     return Err(QueryCommandError::FileSetHasNoAttributes.into());
                 }
                 match self.output_format {
-                    QueryOutputFormat::Default | QueryOutputFormat::Starlark => {
+                    QueryOutputFormat::Default
+                    | QueryOutputFormat::Starlark
+                    | QueryOutputFormat::Files => {
                         for file in files.iter() {
                             writeln!(
                                 &mut output,
@@ -434,6 +488,15 @@ impl<'a> QueryResultPrinter<'a> {
                         // need to add a newline to flush the output.
                         writeln!(&mut output)?;
                     }
+                    QueryOutputFormat::JsonLines => {
+                        for file in files.iter() {
+                            serde_json::to_writer(
+                                &mut output,
+                                &self.resolver.resolve_path(file.as_ref())?.to_string(),
+                            )?;
+                            writeln!(&mut output)?;
+                        }
+                    }
                     QueryOutputFormat::Dot => {
                         unimplemented!("dot output for files not implemented yet")
                     }
@@ -446,6 +509,51 @@ impl<'a> QueryResultPrinter<'a> {
 
         Ok(())
     }
+
+    /// Like `json`, but prints each target on its own line as soon as it is ready, rather than
+    /// holding all the printable targets (and their providers) in memory first. Each line is the
+    /// label of a target or, if details were requested, a map from its label to the details.
+    async fn print_json_lines<'b, T: QueryCommandTarget>(
+        &self,
+        mut output: impl std::io::Write,
+        targets: &TargetSet<T>,
+        target_call_stacks: bool,
+        print_providers: ShouldPrintProviders<'b, T>,
+    ) -> anyhow::Result<()> {
+        let is_complex = is_complex(&self.attributes, target_call_stacks, print_providers);
+        let mut printable = futures::stream::iter(targets.iter())
+            .map(|t| printable_target(t, print_providers, &self.attributes, target_call_stacks))
+            .buffered(JSON_LINES_CONCURRENCY);
+        while let Some(target) = printable.next().await {
+            let target = target?;
+            if is_complex {
+                serde_json::to_writer(&mut output, &BTreeMap::from([(target.label(), &target)]))?;
+            } else {
+                serde_json::to_writer(&mut output, &target.label())?;
+            }
+            writeln!(&mut output)?;
+        }
+        Ok(())
+    }
+}
+
+async fn printable_target<'a, T: QueryTarget>(
+    target: &'a T,
+    print_providers: ShouldPrintProviders<'a, T>,
+    attributes: &'a Option<RegexSet>,
+    target_call_stacks: bool,
+) -> anyhow::Result<PrintableQueryTarget<'a, T>> {
+    Ok(PrintableQueryTarget {
+        value: target,
+        attributes,
+        target_call_stacks,
+        providers: match print_providers {
+            ShouldPrintProviders::No => None,
+            ShouldPrintProviders::Yes(lookup) => {
+                Some(lookup.lookup(target).await?.require_compatible()?)
+            }
+        },
+    })
 }
 
 async fn printable_targets<'a, T: QueryTarget>(
@@ -454,19 +562,11 @@ async fn printable_targets<'a, T: QueryTarget>(
     attributes: &'a Option<RegexSet>,
     target_call_stacks: bool,
 ) -> anyhow::Result<Vec<PrintableQueryTarget<'a, T>>> {
-    futures::future::join_all(targets.iter().map(|t| async move {
-        Ok(PrintableQueryTarget {
-            value: t,
-            attributes,
-            target_call_stacks,
-            providers: match print_providers {
-                ShouldPrintProviders::No => None,
-                ShouldPrintProviders::Yes(lookup) => {
-                    Some(lookup.lookup(t).await?.require_compatible()?)
-                }
-            },
-        })
-    }))
+    futures::future::join_all(
+        targets
+            .iter()
+            .map(|t| printable_target(t, print_providers, attributes, target_call_stacks)),
+    )
     .await
     .into_iter()
     .collect::<anyhow::Result<_>>()
@@ -490,6 +590,7 @@ async fn print_action_node(
         cell_resolver,
         output_attributes,
         unstable_output_format,
+        &[],
     )?;
 
     let mut result = TargetSet::new();
@@ -516,3 +617,318 @@ pub(crate) fn init_print_action_node() {
         ))
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::cells::cell_path::CellPath;
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::configuration::pair::ConfigurationNoExec;
+    use buck2_core::execution_types::execution::ExecutionPlatformResolution;
+    use buck2_core::plugins::PluginLists;
+    use buck2_core::target::configured_target_label::ConfiguredTargetLabel;
+    use buck2_node::configuration::resolved::ResolvedConfiguration;
+    use buck2_node::configuration::resolved::ResolvedConfigurationSettings;
+    use buck2_node::nodes::configured::ConfiguredTargetNode;
+    use buck2_node::nodes::unconfigured::testing::TargetNodeExt;
+    use buck2_node::nodes::unconfigured::TargetNode;
+    use buck2_node::rule_type::RuleType;
+    use buck2_node::rule_type::StarlarkRuleType;
+    use buck2_query::query::syntax::simple::eval::file_set::FileNode;
+    use dupe::Dupe;
+    use starlark_map::ordered_map::OrderedMap;
+
+    use super::*;
+
+    /// Every target generates its own output, and one they share.
+    struct FakeOutputs;
+
+    #[async_trait]
+    impl ProviderLookUp<ConfiguredTargetNode> for FakeOutputs {
+        async fn lookup(
+            &self,
+            _t: &ConfiguredTargetNode,
+        ) -> anyhow::Result<MaybeCompatible<FrozenProviderCollectionValue>> {
+            unimplemented!("Not used by the files output")
+        }
+
+        async fn default_output_paths(
+            &self,
+            t: &ConfiguredTargetNode,
+        ) -> anyhow::Result<Vec<ProjectRelativePathBuf>> {
+            Ok(vec![
+                ProjectRelativePathBuf::unchecked_new(format!(
+                    "buck-out/v2/gen/cell/{}/out",
+                    t.label().name()
+                )),
+                ProjectRelativePathBuf::unchecked_new("buck-out/v2/gen/cell/shared".to_owned()),
+            ])
+        }
+    }
+
+    fn target(label: &str) -> ConfiguredTargetNode {
+        ConfiguredTargetNode::testing_new(
+            ConfiguredTargetLabel::testing_parse(label, ConfigurationData::testing_new()),
+            "foo_lib",
+            ExecutionPlatformResolution::new(None, Vec::new()),
+            vec![],
+            vec![],
+        )
+    }
+
+    fn target_with_deps(
+        label: &str,
+        deps: Vec<ConfiguredTargetNode>,
+        exec_deps: Vec<ConfiguredTargetNode>,
+    ) -> ConfiguredTargetNode {
+        let label = ConfiguredTargetLabel::testing_parse(label, ConfigurationData::testing_new());
+        ConfiguredTargetNode::new(
+            label.dupe(),
+            TargetNode::testing_new(
+                label.unconfigured().dupe(),
+                RuleType::Starlark(Arc::new(StarlarkRuleType {
+                    import_path: ImportPath::testing_new("cell//pkg:rules.bzl"),
+                    name: "foo_lib".to_owned(),
+                })),
+                vec![],
+                vec![],
+            ),
+            ResolvedConfiguration::new(
+                ConfigurationNoExec::new(label.cfg().dupe()),
+                ResolvedConfigurationSettings::empty(),
+            ),
+            OrderedMap::new(),
+            ExecutionPlatformResolution::new(None, Vec::new()),
+            deps,
+            exec_deps,
+            OrderedMap::new(),
+            PluginLists::new(),
+        )
+    }
+
+    fn resolver() -> CellResolver {
+        CellResolver::testing_with_name_and_path(
+            CellName::testing_new("cell"),
+            CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("cell".to_owned())),
+        )
+    }
+
+    async fn print(
+        format: QueryOutputFormat,
+        attributes: &[String],
+        value: QueryEvaluationValue<ConfiguredTargetNode>,
+    ) -> anyhow::Result<String> {
+        let resolver = resolver();
+        let printer =
+            QueryResultPrinter::from_request_options(&resolver, attributes, format as i32, &[])?;
+        let mut output = Vec::new();
+        printer
+            .print_single_output(&mut output, value, false, ShouldPrintProviders::No)
+            .await?;
+        Ok(String::from_utf8(output)?)
+    }
+
+    fn targets() -> TargetSet<ConfiguredTargetNode> {
+        let mut targets = TargetSet::new();
+        targets.insert(target("cell//pkg:b"));
+        targets.insert(target("cell//pkg:a"));
+        targets
+    }
+
+    #[tokio::test]
+    async fn test_json_lines_output_labels() -> anyhow::Result<()> {
+        let output = print(
+            QueryOutputFormat::JsonLines,
+            &[],
+            QueryEvaluationValue::TargetSet(targets()),
+        )
+        .await?;
+        let labels: Vec<String> = targets()
+            .iter()
+            .map(|t| serde_json::to_string(&t.node_key().to_string()).unwrap() + "\n")
+            .collect();
+        assert_eq!(labels.concat(), output);
+
+        // The lines make up the same result as `json`.
+        let json: serde_json::Value = serde_json::from_str(
+            &print(
+                QueryOutputFormat::Json,
+                &[],
+                QueryEvaluationValue::TargetSet(targets()),
+            )
+            .await?,
+        )?;
+        let lines = output
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<serde_json::Value>, _>>()?;
+        assert_eq!(json, serde_json::Value::Array(lines));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_json_lines_output_attributes() -> anyhow::Result<()> {
+        let attributes = ["buck.type".to_owned()];
+        let output = print(
+            QueryOutputFormat::JsonLines,
+            &attributes,
+            QueryEvaluationValue::TargetSet(targets()),
+        )
+        .await?;
+        assert_eq!(2, output.lines().count());
+
+        // Each line maps a single label to its attributes, and together they make up the same
+        // map as `json`.
+        let json: serde_json::Value = serde_json::from_str(
+            &print(
+                QueryOutputFormat::Json,
+                &attributes,
+                QueryEvaluationValue::TargetSet(targets()),
+            )
+            .await?,
+        )?;
+        let mut merged = serde_json::Map::new();
+        for line in output.lines() {
+            match serde_json::from_str(line)? {
+                serde_json::Value::Object(map) => {
+                    assert_eq!(1, map.len(), "{}", line);
+                    merged.extend(map);
+                }
+                v => panic!("Expected a map, got {}", v),
+            }
+        }
+        assert_eq!(json, serde_json::Value::Object(merged));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_json_lines_output_files() -> anyhow::Result<()> {
+        let files = [
+            FileNode(CellPath::testing_new("cell//pkg/b.txt")),
+            FileNode(CellPath::testing_new("cell//pkg/a.txt")),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            "\"cell/pkg/b.txt\"\n\"cell/pkg/a.txt\"\n",
+            print(
+                QueryOutputFormat::JsonLines,
+                &[],
+                QueryEvaluationValue::FileSet(files),
+            )
+            .await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_files_output_prints_generated_files() -> anyhow::Result<()> {
+        let resolver = resolver();
+        let printer = QueryResultPrinter::from_request_options(
+            &resolver,
+            &[],
+            QueryOutputFormat::Files as i32,
+            &[],
+        )?;
+        let mut targets = TargetSet::new();
+        targets.insert(target("cell//pkg:b"));
+        targets.insert(target("cell//pkg:a"));
+
+        let mut output = Vec::new();
+        printer
+            .print_single_output(
+                &mut output,
+                QueryEvaluationValue::TargetSet(targets),
+                false,
+                ShouldPrintProviders::Yes(&FakeOutputs),
+            )
+            .await?;
+
+        assert_eq!(
+            String::from_utf8(output)?,
+            "buck-out/v2/gen/cell/a/out\n\
+            buck-out/v2/gen/cell/b/out\n\
+            buck-out/v2/gen/cell/shared\n"
+        );
+        Ok(())
+    }
+
+    /// The edges of the dot output, as `from -> to` target names.
+    async fn dot_edges(
+        targets: &TargetSet<ConfiguredTargetNode>,
+        dot_edges: &[QueryDepKind],
+    ) -> anyhow::Result<Vec<String>> {
+        let resolver = resolver();
+        let printer = QueryResultPrinter::from_request_options(
+            &resolver,
+            &[],
+            QueryOutputFormat::Dot as i32,
+            &dot_edges
+                .iter()
+                .map(|kind| *kind as i32)
+                .collect::<Vec<_>>(),
+        )?;
+        let mut output = Vec::new();
+        printer
+            .print_single_output(
+                &mut output,
+                QueryEvaluationValue::TargetSet(targets.clone()),
+                false,
+                ShouldPrintProviders::No,
+            )
+            .await?;
+        let name = |node: &str| {
+            let label = node.trim().trim_matches('"');
+            label[label.find(':').unwrap() + 1..label.find(' ').unwrap()].to_owned()
+        };
+        Ok(String::from_utf8(output)?
+            .lines()
+            .filter_map(|line| line.trim_end_matches(';').split_once(" -> "))
+            .map(|(from, to)| format!("{} -> {}", name(from), name(to)))
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn test_dot_edges() -> anyhow::Result<()> {
+        let lib = target("cell//pkg:lib");
+        let tool = target("cell//pkg:tool");
+        let app = target_with_deps("cell//pkg:app", vec![lib.dupe()], vec![tool.dupe()]);
+        let mut targets = TargetSet::new();
+        targets.insert(app);
+        targets.insert(lib);
+        targets.insert(tool);
+
+        assert_eq!(
+            vec!["app -> lib", "app -> tool"],
+            dot_edges(&targets, &[]).await?
+        );
+        assert_eq!(
+            vec!["app -> lib", "app -> tool"],
+            dot_edges(&targets, &[QueryDepKind::AllDeps]).await?
+        );
+        assert_eq!(
+            vec!["app -> lib"],
+            dot_edges(&targets, &[QueryDepKind::TargetDeps]).await?
+        );
+        assert_eq!(
+            vec!["app -> tool"],
+            dot_edges(&targets, &[QueryDepKind::ExecDeps]).await?
+        );
+        assert_eq!(
+            vec!["app -> lib", "app -> tool"],
+            dot_edges(
+                &targets,
+                &[QueryDepKind::TargetDeps, QueryDepKind::ExecDeps]
+            )
+            .await?
+        );
+        assert!(dot_edges(&targets, &[QueryDepKind::ConfigurationDeps])
+            .await?
+            .is_empty());
+        Ok(())
+    }
+}
@@ -9,6 +9,8 @@
 
 use std::fmt::Formatter;
 
+use buck2_core::cells::CellResolver;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_query::query::environment::AttrFmtOptions;
 use buck2_query::query::environment::QueryTarget;
 use dupe::Dupe;
@@ -17,6 +19,19 @@ use dupe::Dupe;
 pub(crate) trait QueryCommandTarget: QueryTarget {
     fn call_stack(&self) -> Option<String>;
 
+    /// The files printed for this target by `--output-format files`. By default these are the
+    /// sources of the target, as returned by `inputs()`.
+    fn files_for_each(
+        &self,
+        resolver: &CellResolver,
+        func: &mut dyn FnMut(ProjectRelativePathBuf),
+    ) -> anyhow::Result<()> {
+        self.inputs_for_each(|path| {
+            func(resolver.resolve_path(path.as_ref())?);
+            anyhow::Ok(())
+        })
+    }
+
     #[allow(dead_code)]
     fn attr_to_string_alternate(&self, _options: AttrFmtOptions, attr: &Self::Attr<'_>) -> String;
 
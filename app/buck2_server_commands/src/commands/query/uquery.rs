@@ -22,6 +22,7 @@ use buck2_node::attrs::serialize::AttrSerializeWithContext;
 use buck2_node::nodes::unconfigured::TargetNode;
 use buck2_node::nodes::unconfigured::TargetNodeData;
 use buck2_query::query::environment::AttrFmtOptions;
+use buck2_query::query::syntax::simple::eval::profile::QueryProfile;
 use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
@@ -30,6 +31,7 @@ use buck2_server_ctx::template::ServerCommandTemplate;
 use dice::DiceTransaction;
 use dupe::Dupe;
 
+use crate::commands::query::print_profile_on_error;
 use crate::commands::query::printer::QueryResultPrinter;
 use crate::commands::query::printer::ShouldPrintProviders;
 use crate::commands::query::query_target_ext::QueryCommandTarget;
@@ -130,6 +132,7 @@ async fn uquery(
         &cell_resolver,
         &request.output_attributes,
         request.unstable_output_format,
+        &request.dot_edges,
     )?;
 
     let UqueryRequest {
@@ -143,10 +146,18 @@ async fn uquery(
 
     let target_call_stacks = client_ctx.target_call_stacks;
 
+    let profile = request.profile.then(QueryProfile::default);
     let query_result = QUERY_FRONTEND
         .get()?
-        .eval_uquery(&mut ctx, server_ctx.working_dir(), query, query_args)
-        .await?;
+        .eval_uquery(
+            &mut ctx,
+            server_ctx.working_dir(),
+            query,
+            query_args,
+            profile.as_ref(),
+        )
+        .await;
+    let query_result = print_profile_on_error(server_ctx, profile.as_ref(), query_result)?;
 
     match query_result {
         QueryEvaluationResult::Single(targets) => {
@@ -171,5 +182,7 @@ async fn uquery(
         }
     };
 
-    Ok(UqueryResponse {})
+    Ok(UqueryResponse {
+        profile: profile.map(|profile| profile.to_string()),
+    })
 }
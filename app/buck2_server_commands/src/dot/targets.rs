@@ -7,12 +7,14 @@
  * of this source tree.
  */
 
+use buck2_cli_proto::QueryDepKind;
 use buck2_query::query::environment::AttrFmtOptions;
 use buck2_query::query::environment::QueryTarget;
 use buck2_query::query::environment::QueryTargets;
 use buck2_query::query::syntax::simple::eval::set::TargetSet;
 use regex::RegexSet;
 use starlark_map::small_map::SmallMap;
+use starlark_map::small_set::SmallSet;
 
 use crate::commands::query::query_target_ext::QueryCommandTarget;
 use crate::dot::DotDigraph;
@@ -26,6 +28,27 @@ pub struct DotTargetGraphNode<'a, T: QueryTarget>(&'a T, &'a DotTargetGraph<T>);
 pub struct DotTargetGraph<T: QueryTarget> {
     pub targets: TargetSet<T>,
     pub attributes: Option<RegexSet>,
+    /// The kinds of dependencies drawn as edges. Empty means all of them.
+    pub edges: Vec<QueryDepKind>,
+}
+
+impl<T: QueryTarget> DotTargetGraph<T> {
+    fn edge_deps<'a>(&self, node: &'a T) -> SmallSet<&'a T::Key> {
+        if self.edges.is_empty() {
+            return node.deps().collect();
+        }
+        let mut deps = SmallSet::new();
+        for kind in &self.edges {
+            match kind {
+                QueryDepKind::AllDeps => deps.extend(node.deps()),
+                QueryDepKind::TargetDeps => deps.extend(node.target_deps()),
+                QueryDepKind::ExecDeps => deps.extend(node.exec_deps()),
+                QueryDepKind::ConfigurationDeps => deps.extend(node.configuration_deps()),
+                QueryDepKind::ToolchainDeps => deps.extend(node.toolchain_deps()),
+            }
+        }
+        deps
+    }
 }
 
 impl<'a, T: QueryCommandTarget> DotDigraph<'a> for DotTargetGraph<T> {
@@ -50,7 +73,7 @@ impl<'a, T: QueryCommandTarget> DotDigraph<'a> for DotTargetGraph<T> {
         node: &Self::Node,
         mut f: F,
     ) -> anyhow::Result<()> {
-        for dep in node.0.deps() {
+        for dep in self.edge_deps(node.0) {
             // Only include edges to other nodes within the subgraph.
             if self.targets.contains(dep) {
                 f(&DotEdge {
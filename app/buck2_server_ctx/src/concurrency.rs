@@ -16,6 +16,7 @@
 use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::sync::Arc;
+use std::time::Duration;
 
 use allocative::Allocative;
 use anyhow::Context;
@@ -105,6 +106,10 @@ pub struct ConcurrencyHandler {
     dice: Arc<Dice>,
     /// Used to prevent commands (clean --stale) from running in parallel with dice commands
     exclusive_command_lock: Arc<ExclusiveCommandLock>,
+    /// How long a command waits for the computations of a cancelled or finished command on a
+    /// different state to terminate. Past this, the command runs alongside them (and is tagged as
+    /// tainted), so a computation that is slow to cancel does not hold up the daemon.
+    cleanup_timeout: Option<Duration>,
 }
 
 #[derive(Allocative)]
@@ -319,7 +324,7 @@ impl ExclusiveCommandLock {
 }
 
 impl ConcurrencyHandler {
-    pub fn new(dice: Arc<Dice>) -> Self {
+    pub fn new(dice: Arc<Dice>, cleanup_timeout: Option<Duration>) -> Self {
         ConcurrencyHandler {
             data: Arc::new(Mutex::new(ConcurrencyHandlerData {
                 dice_status: DiceStatus::idle(),
@@ -331,6 +336,7 @@ impl ConcurrencyHandler {
             cond: Default::default(),
             dice,
             exclusive_command_lock: Arc::new(ExclusiveCommandLock::new()),
+            cleanup_timeout,
         }
     }
 
@@ -456,12 +462,27 @@ impl ConcurrencyHandler {
 
                     // block while dice cleans up
                     drop(data);
-                    event_dispatcher
+                    let cleanup_timeout = self.cleanup_timeout;
+                    let timed_out = event_dispatcher
                         .span_async(
                             buck2_data::DiceCleanupStart { epoch: epoch as _ },
-                            async move { (future.await, buck2_data::DiceCleanupEnd {}) },
+                            async move {
+                                let timed_out = match cleanup_timeout {
+                                    Some(timeout) => {
+                                        tokio::time::timeout(timeout, future).await.is_err()
+                                    }
+                                    None => {
+                                        future.await;
+                                        false
+                                    }
+                                };
+                                (timed_out, buck2_data::DiceCleanupEnd { timed_out })
+                            },
                         )
                         .await;
+                    if timed_out {
+                        tracing::warn!("DICE cleanup timed out, running alongside it");
+                    }
                     data = self.data.lock().await;
 
                     data.transition_to_idle(epoch);
@@ -810,7 +831,7 @@ mod tests {
 
         let dice = Dice::builder().build(DetectCycles::Enabled);
 
-        let concurrency = ConcurrencyHandler::new(dice);
+        let concurrency = ConcurrencyHandler::new(dice, None);
 
         let traces1 = TraceId::new();
         let traces2 = TraceId::new();
@@ -880,7 +901,7 @@ mod tests {
     async fn nested_invocation_should_error() {
         let dice = Dice::builder().build(DetectCycles::Enabled);
 
-        let concurrency = ConcurrencyHandler::new(dice);
+        let concurrency = ConcurrencyHandler::new(dice, None);
 
         let traces1 = TraceId::new();
         let traces2 = TraceId::new();
@@ -935,7 +956,7 @@ mod tests {
     async fn parallel_invocation_same_transaction() {
         let dice = Dice::builder().build(DetectCycles::Enabled);
 
-        let concurrency = ConcurrencyHandler::new(dice);
+        let concurrency = ConcurrencyHandler::new(dice, None);
 
         let traces1 = TraceId::new();
         let traces2 = TraceId::new();
@@ -1005,7 +1026,7 @@ mod tests {
     async fn parallel_invocation_different_traceid_blocks() -> anyhow::Result<()> {
         let dice = Dice::builder().build(DetectCycles::Enabled);
 
-        let concurrency = ConcurrencyHandler::new(dice.dupe());
+        let concurrency = ConcurrencyHandler::new(dice.dupe(), None);
 
         let traces1 = TraceId::new();
         let traces2 = traces1.dupe();
@@ -1125,7 +1146,7 @@ mod tests {
     async fn parallel_invocation_exit_when_different_state() -> anyhow::Result<()> {
         let dice = Dice::builder().build(DetectCycles::Enabled);
 
-        let concurrency = ConcurrencyHandler::new(dice.dupe());
+        let concurrency = ConcurrencyHandler::new(dice.dupe(), None);
 
         let traces1 = TraceId::new();
         let traces2 = traces1.dupe();
@@ -1250,7 +1271,7 @@ mod tests {
     async fn parallel_invocation_exit_when_preemptible() -> anyhow::Result<()> {
         let dice = Dice::builder().build(DetectCycles::Enabled);
 
-        let concurrency = ConcurrencyHandler::new(dice.dupe());
+        let concurrency = ConcurrencyHandler::new(dice.dupe(), None);
 
         let traces1 = TraceId::new();
         let traces2 = traces1.dupe();
@@ -1375,6 +1396,9 @@ mod tests {
     struct CleanupTestKey {
         #[derivative(Debug = "ignore", Hash = "ignore", PartialEq = "ignore")]
         is_executing: Arc<Mutex<()>>,
+        /// How long the computation takes to terminate once cancelled.
+        #[derivative(Debug = "ignore", Hash = "ignore", PartialEq = "ignore")]
+        duration: Duration,
     }
 
     #[async_trait::async_trait]
@@ -1391,7 +1415,7 @@ mod tests {
             // TODO: use critical_section as it's simpler, but this stack doesn't have it and
             // this works equally well here :)
             cancellation
-                .with_structured_cancellation(|_obs| tokio::time::sleep(Duration::from_secs(1)))
+                .with_structured_cancellation(|_obs| tokio::time::sleep(self.duration))
                 .await;
         }
 
@@ -1404,13 +1428,14 @@ mod tests {
     async fn test_cleanup_stage() -> anyhow::Result<()> {
         let key = CleanupTestKey {
             is_executing: Arc::new(Mutex::new(())),
+            duration: Duration::from_secs(1),
         };
 
         let key = &key;
 
         let dice = Dice::builder().build(DetectCycles::Enabled);
 
-        let concurrency = ConcurrencyHandler::new(dice.dupe());
+        let concurrency = ConcurrencyHandler::new(dice.dupe(), None);
 
         // Kick off our computation and wait until it's running.
 
@@ -1491,6 +1516,76 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_cleanup_timeout() -> anyhow::Result<()> {
+        let key = CleanupTestKey {
+            is_executing: Arc::new(Mutex::new(())),
+            duration: Duration::from_secs(3600),
+        };
+
+        let key = &key;
+
+        let dice = Dice::builder().build(DetectCycles::Enabled);
+
+        let concurrency = ConcurrencyHandler::new(dice.dupe(), Some(Duration::from_millis(10)));
+
+        // Kick off a computation which takes an hour to cancel, and wait until it's running.
+
+        concurrency
+            .enter(
+                EventDispatcher::null(),
+                &TestDiceDataProvider,
+                &NoChanges,
+                |mut dice| async move {
+                    let compute = dice.compute(key).fuse();
+
+                    let started = async {
+                        while !key.is_executing.is_locked() {
+                            tokio::task::yield_now().await;
+                        }
+                    }
+                    .fuse();
+
+                    futures::pin_mut!(compute);
+                    futures::pin_mut!(started);
+
+                    futures::select! {
+                        _ = compute => panic!("compute finished before started?"),
+                        _ = started => {}
+                    }
+                },
+                false,
+                Vec::new(),
+                None,
+                false,
+                ExplicitCancellationContext::testing(),
+                PreemptibleWhen::Never,
+            )
+            .await?;
+
+        // Enter with a different context: we give up waiting for the cleanup and run while the
+        // cancelled computation is still terminating.
+
+        concurrency
+            .enter(
+                EventDispatcher::null(),
+                &TestDiceDataProvider,
+                &CtxDifferent,
+                |_dice| async move {
+                    assert!(key.is_executing.is_locked());
+                },
+                false,
+                Vec::new(),
+                None,
+                false,
+                ExplicitCancellationContext::testing(),
+                PreemptibleWhen::Never,
+            )
+            .await?;
+
+        Ok(())
+    }
+
     async fn wait_for_event<F>(
         source: &mut ChannelEventSource,
         matcher: Box<F>,
@@ -1562,7 +1657,7 @@ mod tests {
     #[tokio::test]
     async fn exclusive_command_lock() -> anyhow::Result<()> {
         let dice = Dice::builder().build(DetectCycles::Enabled);
-        let concurrency = ConcurrencyHandler::new(dice.dupe());
+        let concurrency = ConcurrencyHandler::new(dice.dupe(), None);
         let (mut source, sink) = create_source_sink_pair();
         let dispatcher = EventDispatcher::new(TraceId::new(), sink);
 
@@ -1649,7 +1744,7 @@ mod tests {
     async fn test_thundering_herd() -> anyhow::Result<()> {
         let dice = Dice::builder().build(DetectCycles::Enabled);
 
-        let concurrency = ConcurrencyHandler::new(dice.dupe());
+        let concurrency = ConcurrencyHandler::new(dice.dupe(), None);
 
         let concurrency = &concurrency;
 
@@ -1685,7 +1780,7 @@ mod tests {
     async fn test_updates_are_synchronized() -> anyhow::Result<()> {
         let dice = Dice::builder().build(DetectCycles::Enabled);
 
-        let concurrency = ConcurrencyHandler::new(dice.dupe());
+        let concurrency = ConcurrencyHandler::new(dice.dupe(), None);
 
         struct Updater {
             should_be_able_to_run: AtomicBool,
@@ -14,6 +14,7 @@ use buck2_cli_proto::DapRequest;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
 use buck2_client_ctx::common::ui::CommonConsoleOptions;
 use buck2_client_ctx::common::ui::ConsoleType;
+use buck2_client_ctx::common::ui::ErrorFormat;
 use buck2_client_ctx::common::CommonBuildConfigurationOptions;
 use buck2_client_ctx::common::CommonEventLogOptions;
 use buck2_client_ctx::common::CommonStarlarkOptions;
@@ -118,6 +119,7 @@ impl StreamingCommand for StarlarkDebugAttachCommand {
             console_type: ConsoleType::Simple,
             ui: vec![],
             no_interactive_console: true,
+            error_format: ErrorFormat::Text,
         });
         &SIMPLE_CONSOLE
     }
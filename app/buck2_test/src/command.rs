@@ -16,6 +16,7 @@ use std::time::Duration;
 
 use anyhow::Context;
 use async_trait::async_trait;
+use buck2_build_api::actions::artifact::get_artifact_fs::GetArtifactFs;
 use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
 use buck2_build_api::artifact_groups::calculation::ArtifactGroupCalculation;
 use buck2_build_api::artifact_groups::ArtifactGroup;
@@ -43,6 +44,7 @@ use buck2_core::cells::CellResolver;
 use buck2_core::configuration::compatibility::MaybeCompatible;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_core::package::PackageLabel;
 use buck2_core::pattern::pattern::PackageSpec;
@@ -70,6 +72,7 @@ use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 use buck2_server_ctx::template::run_server_command;
 use buck2_server_ctx::template::ServerCommandTemplate;
 use buck2_server_ctx::test_command::TEST_COMMAND;
+use buck2_test_api::data::ConfiguredTargetHandle;
 use buck2_test_api::data::TestResult;
 use buck2_test_api::data::TestStatus;
 use buck2_test_api::protocol::TestExecutor;
@@ -94,6 +97,11 @@ use crate::executor_launcher::ExecutorLaunch;
 use crate::executor_launcher::ExecutorLauncher;
 use crate::executor_launcher::OutOfProcessTestExecutor;
 use crate::executor_launcher::TestExecutorClientWrapper;
+use crate::flakiness::known_failures;
+use crate::flakiness::update_history_and_recommend;
+use crate::flakiness::TestRun;
+use crate::flakiness::DEFAULT_HISTORY_SIZE;
+use crate::flakiness::DEFAULT_MIN_FLIPS;
 use crate::local_resource_registry::LocalResourceRegistry;
 use crate::orchestrator::BuckTestOrchestrator;
 use crate::orchestrator::ExecutorMessage;
@@ -111,6 +119,7 @@ pub(crate) struct TestReport {
 struct TestOutcome {
     errors: Vec<buck2_data::ErrorReport>,
     executor_report: ExecutorReport,
+    test_runs: Vec<TestRun>,
     executor_stdout: String,
     executor_stderr: String,
 }
@@ -129,6 +138,14 @@ struct ExecutorReport {
     exit_code: Option<i32>,
     statuses: TestStatuses,
     info_messages: Vec<String>,
+    attempts: HashMap<(ConfiguredTargetHandle, String), TestAttempts>,
+}
+
+/// The attempts at running one test, which the test executor may have re-run after it failed.
+#[derive(Default)]
+struct TestAttempts {
+    reruns: u64,
+    passed: Option<bool>,
 }
 
 impl ExecutorReport {
@@ -136,6 +153,7 @@ impl ExecutorReport {
         match status {
             ExecutorMessage::TestResult(res) => {
                 self.statuses.ingest(res);
+                self.ingest_attempt(res);
             }
             ExecutorMessage::ExitCode(exit_code) => {
                 self.exit_code = Some(*exit_code);
@@ -145,6 +163,40 @@ impl ExecutorReport {
             }
         }
     }
+
+    fn ingest_attempt(&mut self, result: &TestResult) {
+        let passed = match result.status {
+            TestStatus::RERUN => None,
+            TestStatus::PASS => Some(true),
+            TestStatus::FAIL | TestStatus::TIMEOUT | TestStatus::FATAL => Some(false),
+            _ => return,
+        };
+        let attempts = self
+            .attempts
+            .entry((result.target, result.name.clone()))
+            .or_default();
+        match passed {
+            None => attempts.reruns += 1,
+            Some(passed) => attempts.passed = Some(passed),
+        }
+    }
+
+    /// The outcome of each test which finished running, by target and test name.
+    fn test_runs(&self, session: &TestSession) -> anyhow::Result<Vec<TestRun>> {
+        let mut runs = Vec::new();
+        for ((target, name), attempts) in &self.attempts {
+            if let Some(passed) = attempts.passed {
+                runs.push(TestRun {
+                    target: session.get(*target)?.to_string(),
+                    name: name.clone(),
+                    reruns: attempts.reruns,
+                    passed,
+                });
+            }
+        }
+        runs.sort_by(|a, b| (&a.target, &a.name).cmp(&(&b.target, &b.name)));
+        Ok(runs)
+    }
 }
 
 const MAX_EXAMPLE_VALUES: u64 = 10;
@@ -303,6 +355,46 @@ async fn test(
         .await?
         .filter(|s| !s.is_empty());
 
+    // Only the internal test runner re-runs failed tests, so that they can be told apart from flaky
+    // ones. Other test executors have their own options for this.
+    let rerun_failures = ctx
+        .parse_legacy_config_property::<u32>(
+            cell_resolver.root_cell(),
+            BuckconfigKeyRef {
+                section: "test",
+                property: "rerun_failures",
+            },
+        )
+        .await?
+        .unwrap_or(0);
+    let flaky_history_size = ctx
+        .parse_legacy_config_property(
+            cell_resolver.root_cell(),
+            BuckconfigKeyRef {
+                section: "test",
+                property: "flaky_history_size",
+            },
+        )
+        .await?
+        .unwrap_or(DEFAULT_HISTORY_SIZE);
+    let flaky_min_flips = ctx
+        .parse_legacy_config_property(
+            cell_resolver.root_cell(),
+            BuckconfigKeyRef {
+                section: "test",
+                property: "flaky_min_flips",
+            },
+        )
+        .await?
+        .unwrap_or(DEFAULT_MIN_FLIPS);
+
+    let test_history_dir = server_ctx
+        .project_root()
+        .resolve(ctx.get_artifact_fs().await?.buck_out_path_resolver().root())
+        .join(ForwardRelativePath::unchecked_new("test_history"));
+
+    let mut external_runner_args = request.test_executor_args.clone();
+
     let (test_executor, test_executor_args) = match test_executor_config {
         Some(config) => {
             let test_executor = post_process_test_executor(config.as_ref())
@@ -315,6 +407,19 @@ async fn test(
             // If no v2_test_executor config was set, fall back to the internal test runner.
             let test_executor = std::env::current_exe()?;
             let test_executor_args = vec!["internal-test-runner".to_owned()];
+            // Tests which already failed in their last run are not re-run: only new failures say
+            // anything about flakiness.
+            let mut runner_args = vec![format!("--rerun-failures={}", rerun_failures)];
+            if rerun_failures > 0 {
+                runner_args.extend(
+                    known_failures(&test_history_dir)
+                        .into_iter()
+                        .map(|name| format!("--known-failure={}", name)),
+                );
+            }
+            // The runner collects its trailing arguments as the test arguments, so these must
+            // come before the user's arguments.
+            external_runner_args.splice(0..0, runner_args);
             (test_executor, test_executor_args)
         }
     };
@@ -358,7 +463,7 @@ async fn test(
         ctx,
         resolved_pattern,
         global_cfg_options,
-        request.test_executor_args.clone(),
+        external_runner_args,
         Arc::new(TestLabelFiltering::new(
             request.included_labels.clone(),
             request.excluded_labels.clone(),
@@ -424,6 +529,18 @@ async fn test(
         ),
     };
 
+    // The history only feeds the recommendations, so failing to update it shouldn't fail the tests.
+    let quarantine_recommendations = update_history_and_recommend(
+        &test_history_dir,
+        &test_outcome.test_runs,
+        flaky_history_size,
+        flaky_min_flips,
+    )
+    .unwrap_or_else(|e| {
+        tracing::warn!("Failed to update test history: {:#}", e);
+        Vec::new()
+    });
+
     Ok(TestResponse {
         exit_code,
         errors: test_outcome.errors,
@@ -431,6 +548,7 @@ async fn test(
         executor_stdout: test_outcome.executor_stdout,
         executor_stderr: test_outcome.executor_stderr,
         executor_info_messages: test_outcome.executor_report.info_messages,
+        quarantine_recommendations,
     })
 }
 
@@ -567,6 +685,7 @@ async fn test_targets(
                     })
                     .await
                     .context("Did not receive all results from executor")?;
+                let test_runs = test_statuses.test_runs(&session)?;
 
                 // Shutdown our server. This is technically not *required* since dropping it would shut it
                 // down implicitly, but let's do it anyway so we can collect any errors.
@@ -583,7 +702,7 @@ async fn test_targets(
 
                 // And finally return our results;
 
-                anyhow::Ok((driver.build_errors, test_statuses))
+                anyhow::Ok((driver.build_errors, test_statuses, test_runs))
             },
         )
     });
@@ -607,7 +726,7 @@ async fn test_targets(
     )));
 
     // TODO(bobyf, torozco) we can use cancellation handle here instead of liveliness observer
-    let (build_errors, executor_report) = test_server
+    let (build_errors, executor_report, test_runs) = test_server
         .await
         .context("Failed to collect executor report")??;
 
@@ -628,6 +747,7 @@ async fn test_targets(
         executor_stdout: executor_output.stdout,
         executor_stderr: executor_output.stderr,
         executor_report,
+        test_runs,
     })
 }
 
@@ -0,0 +1,239 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Detects flaky tests, from the re-runs in the current test run and from a local history of
+//! earlier runs, so that CI systems can quarantine them.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use anyhow::Context;
+use buck2_cli_proto::test_response::quarantine_recommendation::Reason;
+use buck2_cli_proto::test_response::QuarantineRecommendation;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// How many of the most recent results are kept for each test.
+pub(crate) const DEFAULT_HISTORY_SIZE: usize = 20;
+
+/// How many times a test must go from passing to failing or back in its recent results to be
+/// considered flaky.
+pub(crate) const DEFAULT_MIN_FLIPS: usize = 3;
+
+/// The outcome of one test in this run.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TestRun {
+    pub(crate) target: String,
+    pub(crate) name: String,
+    /// Failed attempts which the test executor re-ran.
+    pub(crate) reruns: u64,
+    pub(crate) passed: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct TestHistory {
+    /// The results (`true` if it passed) of the most recent runs of each test, oldest first, by
+    /// target and then by test name.
+    tests: BTreeMap<String, BTreeMap<String, VecDeque<bool>>>,
+}
+
+impl TestHistory {
+    const FILE_NAME: &'static str = "history.json";
+
+    /// The history is only a record of earlier runs, so if it's missing or unreadable we start
+    /// over.
+    fn load(dir: &AbsNormPath) -> Self {
+        let path = dir.join(ForwardRelativePath::unchecked_new(Self::FILE_NAME));
+        let read = || -> anyhow::Result<Self> {
+            match fs_util::read_to_string_if_exists(&path)? {
+                Some(data) => Ok(serde_json::from_str(&data)
+                    .with_context(|| format!("Parsing JSON from `{}`", path))?),
+                None => Ok(Self::default()),
+            }
+        };
+        read().unwrap_or_else(|e| {
+            tracing::warn!("Discarding test history: {:#}", e);
+            Self::default()
+        })
+    }
+
+    fn save(&self, dir: &AbsNormPath) -> anyhow::Result<()> {
+        fs_util::create_dir_all(dir)?;
+        // Write to a temporary file first so that an interrupted write doesn't lose the history.
+        let tmp = dir.join(ForwardRelativePath::unchecked_new("history.json.tmp"));
+        fs_util::write(&tmp, serde_json::to_vec(self)?)?;
+        fs_util::rename(
+            &tmp,
+            dir.join(ForwardRelativePath::unchecked_new(Self::FILE_NAME)),
+        )?;
+        Ok(())
+    }
+
+    fn earlier_results(&self, run: &TestRun) -> impl Iterator<Item = bool> + '_ {
+        self.tests
+            .get(&run.target)
+            .and_then(|tests| tests.get(&run.name))
+            .into_iter()
+            .flatten()
+            .copied()
+    }
+
+    /// The names of the tests whose most recent result is a failure.
+    fn known_failures(&self) -> BTreeSet<&str> {
+        self.tests
+            .values()
+            .flatten()
+            .filter(|(_, results)| results.back() == Some(&false))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    fn record(&mut self, run: &TestRun, size: usize) {
+        let results = self
+            .tests
+            .entry(run.target.clone())
+            .or_default()
+            .entry(run.name.clone())
+            .or_default();
+        results.push_back(run.passed);
+        while results.len() > size {
+            results.pop_front();
+        }
+    }
+}
+
+fn recommend(
+    earlier_results: impl Iterator<Item = bool>,
+    run: &TestRun,
+    min_flips: usize,
+) -> Option<QuarantineRecommendation> {
+    let results: Vec<bool> = earlier_results.chain([run.passed]).collect();
+    let flips = results.windows(2).filter(|w| w[0] != w[1]).count();
+
+    let reason = if run.passed && run.reruns > 0 {
+        Reason::PassedOnRerun
+    } else if flips >= min_flips {
+        Reason::Flipping
+    } else {
+        return None;
+    };
+
+    Some(QuarantineRecommendation {
+        target: run.target.clone(),
+        name: run.name.clone(),
+        reason: reason as i32,
+        recent_runs: results.len() as u64,
+        recent_failures: results.iter().filter(|passed| !**passed).count() as u64,
+    })
+}
+
+/// The names of the tests which failed in their most recent run recorded in the history at `dir`.
+/// If they fail again it's not a new failure, so re-running them doesn't tell if they're flaky.
+pub(crate) fn known_failures(dir: &AbsNormPath) -> Vec<String> {
+    TestHistory::load(dir)
+        .known_failures()
+        .into_iter()
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Recommends which of the tests in this run to quarantine, and adds this run to the history at
+/// `dir`.
+pub(crate) fn update_history_and_recommend(
+    dir: &AbsNormPath,
+    runs: &[TestRun],
+    history_size: usize,
+    min_flips: usize,
+) -> anyhow::Result<Vec<QuarantineRecommendation>> {
+    // Test commands can run concurrently, and each one rewrites the whole history.
+    static LOCK: Mutex<()> = Mutex::new(());
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let mut history = TestHistory::load(dir);
+    let recommendations = runs
+        .iter()
+        .filter_map(|run| recommend(history.earlier_results(run), run, min_flips))
+        .collect();
+
+    for run in runs {
+        history.record(run, history_size);
+    }
+    history.save(dir)?;
+
+    Ok(recommendations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(passed: bool, reruns: u64) -> TestRun {
+        TestRun {
+            target: "root//:test".to_owned(),
+            name: "test".to_owned(),
+            reruns,
+            passed,
+        }
+    }
+
+    #[test]
+    fn test_passed_on_rerun_is_flaky() {
+        let recommendation = recommend([true, true].into_iter(), &run(true, 1), 3).unwrap();
+        assert_eq!(recommendation.reason, Reason::PassedOnRerun as i32);
+        assert_eq!(recommendation.recent_runs, 3);
+        assert_eq!(recommendation.recent_failures, 0);
+    }
+
+    #[test]
+    fn test_new_failure_is_not_flaky() {
+        assert_eq!(
+            recommend([true, true, true].into_iter(), &run(false, 2), 3),
+            None
+        );
+    }
+
+    #[test]
+    fn test_flipping_is_flaky() {
+        let recommendation = recommend([true, false, true].into_iter(), &run(false, 0), 3).unwrap();
+        assert_eq!(recommendation.reason, Reason::Flipping as i32);
+        assert_eq!(recommendation.recent_runs, 4);
+        assert_eq!(recommendation.recent_failures, 2);
+    }
+
+    #[test]
+    fn test_known_failures() {
+        let mut history = TestHistory::default();
+        let other = |passed| TestRun {
+            name: "other".to_owned(),
+            ..run(passed, 0)
+        };
+        history.record(&run(true, 0), 20);
+        history.record(&run(false, 0), 20);
+        history.record(&other(false), 20);
+        history.record(&other(true), 20);
+        assert_eq!(history.known_failures(), BTreeSet::from(["test"]));
+    }
+
+    #[test]
+    fn test_history_keeps_recent_results() {
+        let mut history = TestHistory::default();
+        for passed in [false, true, true] {
+            history.record(&run(passed, 0), 2);
+        }
+        assert_eq!(
+            history.earlier_results(&run(true, 0)).collect::<Vec<_>>(),
+            vec![true, true]
+        );
+    }
+}
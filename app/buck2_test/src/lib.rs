@@ -15,6 +15,7 @@
 pub mod command;
 pub mod downward_api;
 pub mod executor_launcher;
+pub(crate) mod flakiness;
 pub(crate) mod local_resource_api;
 pub(crate) mod local_resource_registry;
 pub(crate) mod local_resource_setup;
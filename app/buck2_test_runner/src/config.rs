@@ -23,6 +23,16 @@ pub struct Config {
     #[clap(long, default_value = "600", value_parser = try_parse_timeout_from_str)]
     pub timeout: Duration,
 
+    /// Re-run failing tests up to this many times, so that flaky tests can be told apart from
+    /// broken ones. Each failed attempt is reported as a re-run.
+    #[clap(long, default_value = "0")]
+    pub rerun_failures: u32,
+
+    /// A test which failed in its most recent run, and so is not re-run: only new failures can
+    /// tell flaky tests apart.
+    #[clap(long)]
+    pub known_failure: Vec<String>,
+
     /// Ignored arg included for backwards compatibility.
     #[clap(long, hide = true)]
     buck_test_info: String,
@@ -7,6 +7,9 @@
  * of this source tree.
  */
 
+use std::collections::HashSet;
+use std::future::Future;
+
 use anyhow::Context;
 use buck2_test_api::data::ArgValue;
 use buck2_test_api::data::ArgValueContent;
@@ -43,6 +46,7 @@ pub struct Buck2TestRunner {
     orchestrator_client: TestOrchestratorClient,
     spec_receiver: Mutex<Option<SpecReceiver>>,
     config: Config,
+    known_failures: HashSet<String>,
 }
 
 impl Buck2TestRunner {
@@ -52,10 +56,12 @@ impl Buck2TestRunner {
         args: Vec<String>,
     ) -> anyhow::Result<Self> {
         let config = Config::try_parse_from(args).context("Error parsing test runner arguments")?;
+        let known_failures = config.known_failure.iter().cloned().collect();
         Ok(Self {
             orchestrator_client,
             spec_receiver: Mutex::new(Some(spec_receiver)),
             config,
+            known_failures,
         })
     }
 
@@ -75,25 +81,37 @@ impl Buck2TestRunner {
                     spec.target.cell, spec.target.package, spec.target.target
                 );
                 let target_handle = spec.target.handle.to_owned();
-
-                let execution_response = self
-                    .execute_test_from_spec(spec)
-                    .await
-                    .expect("Test execution request failed");
-
-                let execution_result = match execution_response {
-                    ExecuteResponse::Result(r) => r,
-                    ExecuteResponse::Cancelled => return TestStatus::OMITTED,
+                let max_reruns = if self.known_failures.contains(&name) {
+                    0
+                } else {
+                    self.config.rerun_failures
                 };
 
-                let test_result = get_test_result(name, target_handle, execution_result);
-                let test_status = test_result.status.clone();
-
-                self.report_test_result(test_result)
-                    .await
-                    .expect("Test result reporting failed");
-
-                test_status
+                run_with_reruns(
+                    max_reruns,
+                    || {
+                        let spec = spec.clone();
+                        let name = name.clone();
+                        async move {
+                            let execution_response = self
+                                .execute_test_from_spec(spec)
+                                .await
+                                .expect("Test execution request failed");
+                            match execution_response {
+                                ExecuteResponse::Result(r) => {
+                                    Some(get_test_result(name, target_handle, r))
+                                }
+                                ExecuteResponse::Cancelled => None,
+                            }
+                        }
+                    },
+                    |test_result| async move {
+                        self.report_test_result(test_result)
+                            .await
+                            .expect("Test result reporting failed")
+                    },
+                )
+                .await
             })
             // Use an arbitrarily large buffer -- execution throttling will be handled by the Buck2
             // executor, so no need to hold back on requests here.
@@ -195,6 +213,39 @@ impl Buck2TestRunner {
     }
 }
 
+/// Runs a test with `execute` and reports its result with `report`, re-running it up to
+/// `max_reruns` times while it fails or times out. The failed attempts which are re-run are
+/// reported as re-runs. Returns the status of the last attempt, or `OMITTED` if it was cancelled.
+async fn run_with_reruns<E, R>(
+    max_reruns: u32,
+    mut execute: impl FnMut() -> E,
+    mut report: impl FnMut(TestResult) -> R,
+) -> TestStatus
+where
+    E: Future<Output = Option<TestResult>>,
+    R: Future<Output = ()>,
+{
+    let mut reruns = 0;
+    loop {
+        let Some(mut test_result) = execute().await else {
+            return TestStatus::OMITTED;
+        };
+        let test_status = test_result.status.clone();
+
+        let rerun =
+            matches!(test_status, TestStatus::FAIL | TestStatus::TIMEOUT) && reruns < max_reruns;
+        if rerun {
+            test_result.status = TestStatus::RERUN;
+        }
+        report(test_result).await;
+
+        if !rerun {
+            return test_status;
+        }
+        reruns += 1;
+    }
+}
+
 fn get_test_result(
     name: String,
     target: ConfiguredTargetHandle,
@@ -234,3 +285,73 @@ impl RunVerdict {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    fn test_result(status: TestStatus) -> TestResult {
+        TestResult {
+            target: ConfiguredTargetHandle::from(0),
+            name: "root//:test".to_owned(),
+            status,
+            msg: None,
+            duration: None,
+            details: String::new(),
+        }
+    }
+
+    /// Runs a test whose attempts have the statuses `attempts`, and returns the final status and
+    /// the reported statuses.
+    async fn run(max_reruns: u32, attempts: &[TestStatus]) -> (TestStatus, Vec<TestStatus>) {
+        let attempts = Mutex::new(attempts.iter().cloned().collect::<VecDeque<_>>());
+        let reported = Mutex::new(Vec::new());
+        let status = run_with_reruns(
+            max_reruns,
+            || {
+                let attempt = attempts.lock().pop_front();
+                async move { attempt.map(test_result) }
+            },
+            |result| {
+                reported.lock().push(result.status);
+                async {}
+            },
+        )
+        .await;
+        (status, reported.into_inner())
+    }
+
+    #[tokio::test]
+    async fn test_no_reruns() {
+        assert_eq!(
+            run(0, &[TestStatus::FAIL, TestStatus::PASS]).await,
+            (TestStatus::FAIL, vec![TestStatus::FAIL])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_passed_on_rerun() {
+        assert_eq!(
+            run(2, &[TestStatus::TIMEOUT, TestStatus::PASS]).await,
+            (TestStatus::PASS, vec![TestStatus::RERUN, TestStatus::PASS])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reruns_are_limited() {
+        assert_eq!(
+            run(1, &[TestStatus::FAIL, TestStatus::FAIL, TestStatus::PASS]).await,
+            (TestStatus::FAIL, vec![TestStatus::RERUN, TestStatus::FAIL])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancelled() {
+        assert_eq!(
+            run(1, &[TestStatus::FAIL]).await,
+            (TestStatus::OMITTED, vec![TestStatus::RERUN])
+        );
+    }
+}
@@ -92,12 +92,16 @@ fn call_transition_function<'v>(
     let new_platforms = eval
         .eval_function(transition.implementation.to_value(), &[], &args)
         .map_err(BuckStarlarkError::new)?;
+    // Creating the returned configurations fails if one collides with another configuration,
+    // which is easier to debug knowing where the transition was applied.
+    let context = || format!("Configuration returned by transition applied to `{}`", conf);
     if transition.split {
         match DictOf::<&str, &PlatformInfo>::unpack_value(new_platforms) {
             Some(dict) => {
                 let mut split = OrderedMap::new();
                 for (k, v) in dict.to_dict() {
-                    let prev = split.insert(k.to_owned(), v.to_configuration()?);
+                    let prev =
+                        split.insert(k.to_owned(), v.to_configuration().with_context(context)?);
                     assert!(prev.is_none());
                 }
                 Ok(TransitionApplied::Split(SortedMap::from(split)))
@@ -106,7 +110,9 @@ fn call_transition_function<'v>(
         }
     } else {
         match <&PlatformInfo>::unpack_value(new_platforms) {
-            Some(platform) => Ok(TransitionApplied::Single(platform.to_configuration()?)),
+            Some(platform) => Ok(TransitionApplied::Single(
+                platform.to_configuration().with_context(context)?,
+            )),
             None => Err(ApplyTransitionError::NonSplitTransitionMustReturnPlatformInfo.into()),
         }
     }
@@ -101,6 +101,13 @@ impl Default for HostSharingRequirements {
 pub struct HostSharingGuard {
     _run_guard: SharedSemaphoreReleaser,
     _name_guard: Option<SharedSemaphoreReleaser>,
+    _memory_guard: Option<SharedSemaphoreReleaser>,
+}
+
+/// A budget of memory, in megabytes, shared by the commands running on this host.
+struct MemoryBudget {
+    megabytes: SharedSemaphore,
+    total_mb: usize,
 }
 
 /// Used to ensure that host resources are properly reserved before executing a command spec.
@@ -108,6 +115,7 @@ pub struct HostSharingBroker {
     permits: SharedSemaphore,
     num_machine_permits: usize,
     named_semaphores: NamedSemaphores,
+    memory_budget: Option<MemoryBudget>,
 }
 
 pub struct RequestedPermits {
@@ -155,31 +163,67 @@ impl HostSharingBroker {
             permits,
             num_machine_permits,
             named_semaphores: NamedSemaphores::new(),
+            memory_budget: None,
         }
     }
 
+    /// Limits the sum of the memory declared by the commands running concurrently to
+    /// `total_mb`. Commands that don't declare how much memory they need aren't limited.
+    pub fn with_memory_budget_mb(mut self, total_mb: usize) -> Self {
+        self.memory_budget = Some(MemoryBudget {
+            megabytes: SharedSemaphore::new(false, total_mb),
+            total_mb,
+        });
+        self
+    }
+
     pub fn num_machine_permits(&self) -> usize {
         self.num_machine_permits
     }
 
+    // Same as for permits: a command requiring more memory than the whole budget is capped to the
+    // budget, otherwise it would never be allowed to run.
+    async fn acquire_memory(&self, memory_mb: Option<usize>) -> Option<SharedSemaphoreReleaser> {
+        let budget = self.memory_budget.as_ref()?;
+        let requested = memory_mb?.min(budget.total_mb);
+        Some(budget.megabytes.acquire(requested).await)
+    }
+
     pub async fn acquire(
         &self,
         host_sharing_requirements: &HostSharingRequirements,
+    ) -> HostSharingGuard {
+        self.acquire_with_memory(host_sharing_requirements, None)
+            .await
+    }
+
+    /// Like `acquire`, but also reserves `memory_mb` megabytes out of the memory budget (if
+    /// there is one) before reserving permits, so that memory-hungry commands don't get
+    /// co-scheduled. Memory is always reserved before permits so that they can't deadlock.
+    pub async fn acquire_with_memory(
+        &self,
+        host_sharing_requirements: &HostSharingRequirements,
+        memory_mb: Option<usize>,
     ) -> HostSharingGuard {
         match host_sharing_requirements {
             HostSharingRequirements::Shared(weight_class) => {
+                let _memory_guard = self.acquire_memory(memory_mb).await;
                 let permits = self.requested_permits(weight_class).into_count();
                 let _run_guard = self.permits.acquire(permits).await;
                 HostSharingGuard {
                     _run_guard,
                     _name_guard: None,
+                    _memory_guard,
                 }
             }
             HostSharingRequirements::ExclusiveAccess => {
+                let total_mb = self.memory_budget.as_ref().map(|budget| budget.total_mb);
+                let _memory_guard = self.acquire_memory(total_mb).await;
                 let _run_guard = self.permits.acquire(self.num_machine_permits).await;
                 HostSharingGuard {
                     _run_guard,
                     _name_guard: None,
+                    _memory_guard,
                 }
             }
             HostSharingRequirements::OnePerToken(identifier, weight_class) => {
@@ -189,11 +233,13 @@ impl HostSharingBroker {
                 // for the previous run on this identifier to finish.
                 let run_semaphore = self.named_semaphores.get(identifier);
                 let _name_guard = Some(run_semaphore.acquire(SINGLE_RUN).await);
+                let _memory_guard = self.acquire_memory(memory_mb).await;
                 let permits = self.requested_permits(weight_class).into_count();
                 let _run_guard = self.permits.acquire(permits).await;
                 HostSharingGuard {
                     _run_guard,
                     _name_guard,
+                    _memory_guard,
                 }
             }
         }
@@ -267,4 +313,23 @@ mod tests {
             10,
         );
     }
+
+    #[tokio::test]
+    async fn test_memory_budget() {
+        let broker = HostSharingBroker::new(HostSharingStrategy::SmallerTasksFirst, 10)
+            .with_memory_budget_mb(1000);
+        let shared = HostSharingRequirements::default();
+
+        let first = broker.acquire_with_memory(&shared, Some(600)).await;
+        // Permits are still available, but memory isn't.
+        assert!(
+            futures::poll!(Box::pin(broker.acquire_with_memory(&shared, Some(600)))).is_pending()
+        );
+        // Commands not declaring memory are not limited by the budget.
+        let _unlimited = broker.acquire(&shared).await;
+        drop(first);
+
+        // Requests larger than the budget are capped to it.
+        let _huge = broker.acquire_with_memory(&shared, Some(5000)).await;
+    }
 }
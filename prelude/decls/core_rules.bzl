@@ -679,7 +679,12 @@ genrule = prelude_rule(
                  is unstable. It is subject to removal, default reversal, and other arbitrary
                  changes in the future.
             """),
-            "cacheable": attrs.option(attrs.bool(), default = None),
+            "cacheable": attrs.option(attrs.bool(), default = None, doc = """
+                Set to `False` to keep the outputs of this genrule out of the caches. The
+                 action can still be served from the remote cache, but its results are never
+                 uploaded. Use this for actions whose outputs are nondeterministic or too large
+                 to be worth sharing.
+            """),
             "contacts": attrs.list(attrs.string(), default = []),
             "default_host_platform": attrs.option(attrs.configuration_label(), default = None),
             "labels": attrs.list(attrs.string(), default = []),
@@ -346,6 +346,7 @@ def process_genrule(
         local_only = local_only,
         weight = value_or(ctx.attrs.weight, 1),
         allow_cache_upload = cacheable,
+        remote_cache_read_only = ctx.attrs.cacheable == False,
         category = category,
         identifier = identifier,
         no_outputs_cleanup = ctx.attrs.no_outputs_cleanup,
@@ -9,6 +9,7 @@
 
 use std::collections::HashMap;
 use std::env::VarError;
+use std::io::SeekFrom;
 use std::num::NonZeroUsize;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -22,6 +23,7 @@ use buck2_re_configuration::HttpHeader;
 use dupe::Dupe;
 use futures::future::BoxFuture;
 use futures::future::Future;
+use futures::future::FutureExt;
 use futures::stream::BoxStream;
 use futures::stream::StreamExt;
 use futures::stream::TryStreamExt;
@@ -35,6 +37,7 @@ use re_grpc_proto::build::bazel::remote::execution::v2::batch_update_blobs_reque
 use re_grpc_proto::build::bazel::remote::execution::v2::capabilities_client::CapabilitiesClient;
 use re_grpc_proto::build::bazel::remote::execution::v2::compressor;
 use re_grpc_proto::build::bazel::remote::execution::v2::content_addressable_storage_client::ContentAddressableStorageClient;
+use re_grpc_proto::build::bazel::remote::execution::v2::digest_function;
 use re_grpc_proto::build::bazel::remote::execution::v2::execution_client::ExecutionClient;
 use re_grpc_proto::build::bazel::remote::execution::v2::execution_stage;
 use re_grpc_proto::build::bazel::remote::execution::v2::ActionResult;
@@ -52,19 +55,28 @@ use re_grpc_proto::build::bazel::remote::execution::v2::GetActionResultRequest;
 use re_grpc_proto::build::bazel::remote::execution::v2::GetCapabilitiesRequest;
 use re_grpc_proto::build::bazel::remote::execution::v2::RequestMetadata;
 use re_grpc_proto::build::bazel::remote::execution::v2::ResultsCachePolicy;
+use re_grpc_proto::build::bazel::remote::execution::v2::ServerCapabilities;
 use re_grpc_proto::build::bazel::remote::execution::v2::ToolDetails;
 use re_grpc_proto::google::bytestream::byte_stream_client::ByteStreamClient;
+use re_grpc_proto::google::bytestream::QueryWriteStatusRequest;
+use re_grpc_proto::google::bytestream::QueryWriteStatusResponse;
 use re_grpc_proto::google::bytestream::ReadRequest;
 use re_grpc_proto::google::bytestream::ReadResponse;
 use re_grpc_proto::google::bytestream::WriteRequest;
 use re_grpc_proto::google::bytestream::WriteResponse;
 use re_grpc_proto::google::longrunning::operation::Result as OpResult;
+use re_grpc_proto::google::longrunning::operations_client::OperationsClient;
+use re_grpc_proto::google::longrunning::CancelOperationRequest;
+use re_grpc_proto::google::longrunning::Operation;
 use re_grpc_proto::google::rpc::Code;
 use re_grpc_proto::google::rpc::Status;
 use regex::Regex;
 use tokio::fs::OpenOptions;
+use tokio::io::AsyncRead;
 use tokio::io::AsyncReadExt;
+use tokio::io::AsyncSeekExt;
 use tokio::io::AsyncWriteExt;
+use tokio::sync::Semaphore;
 use tonic::codegen::InterceptedService;
 use tonic::metadata;
 use tonic::metadata::MetadataKey;
@@ -88,6 +100,22 @@ const CONCURRENT_UPLOAD_LIMIT: usize = 64;
 
 const DEFAULT_MAX_MSG_SIZE: usize = 4 * 1000 * 1000;
 
+/// How many blobs too large to be batched are uploaded concurrently, by default. These are limited
+/// separately so that a few huge outputs don't hold up the uploads of everything else.
+const DEFAULT_MAX_CONCURRENT_LARGE_UPLOADS: usize = 4;
+
+/// How many times the upload of a large blob is attempted before giving up.
+const LARGE_UPLOAD_ATTEMPTS: usize = 3;
+
+/// How often (in bytes sent) the progress of the upload of a large blob is reported.
+const LARGE_UPLOAD_PROGRESS_INTERVAL: i64 = 64 << 20;
+
+/// Called with the digest of a large blob and the number of bytes of it sent so far.
+pub type UploadProgress = Arc<dyn Fn(&TDigest, i64) + Send + Sync>;
+
+/// `DigestFunction.BLAKE3`, which is newer than the REAPI protos vendored here.
+pub(crate) const DIGEST_FUNCTION_BLAKE3: i32 = 9;
+
 fn tdigest_to(tdigest: TDigest) -> Digest {
     Digest {
         hash: tdigest.hash,
@@ -206,6 +234,7 @@ fn prepare_uri(uri: Uri, tls: bool) -> anyhow::Result<Uri> {
 }
 
 /// Contains information queried from the the Remote Execution Capabilities service.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct RECapabilities {
     /// Largest size of a message before being uploaded using bytestream service.
     /// 0 indicates no limit beyond constraint of underlying transport (which is unknown).
@@ -218,6 +247,8 @@ pub struct RECapabilities {
 pub struct RERuntimeOpts {
     /// Use the Meta version of the request metadata
     use_fbcode_metadata: bool,
+    /// How many large blobs to upload concurrently.
+    max_concurrent_large_uploads: usize,
 }
 
 struct InstanceName(Option<String>);
@@ -238,6 +269,48 @@ impl InstanceName {
     }
 }
 
+/// The configuration that the capabilities of the server depend on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CapabilitiesKey {
+    engine_address: Option<String>,
+    instance_name: Option<String>,
+    digest_function: Option<String>,
+}
+
+impl CapabilitiesKey {
+    fn new(opts: &Buck2OssReConfiguration) -> Self {
+        Self {
+            engine_address: opts.engine_address.clone(),
+            instance_name: opts.instance_name.clone(),
+            digest_function: opts.digest_function.clone(),
+        }
+    }
+}
+
+/// The capabilities last negotiated with the server. Reconnecting with the same configuration
+/// (e.g. to refresh the credentials) reuses them, and only a changed configuration negotiates them
+/// again.
+struct CapabilitiesCache(Mutex<Option<(CapabilitiesKey, RECapabilities)>>);
+
+impl CapabilitiesCache {
+    const fn new() -> Self {
+        Self(Mutex::new(None))
+    }
+
+    fn get(&self, key: &CapabilitiesKey) -> Option<RECapabilities> {
+        match &*self.0.lock().unwrap() {
+            Some((k, capabilities)) if k == key => Some(*capabilities),
+            _ => None,
+        }
+    }
+
+    fn insert(&self, key: CapabilitiesKey, capabilities: RECapabilities) {
+        *self.0.lock().unwrap() = Some((key, capabilities));
+    }
+}
+
+static CAPABILITIES: CapabilitiesCache = CapabilitiesCache::new();
+
 pub struct REClientBuilder;
 
 impl REClientBuilder {
@@ -249,53 +322,47 @@ impl REClientBuilder {
 
         let tls_config = &tls_config;
 
-        let create_channel = |address: Option<String>| async move {
-            let address = address.as_ref().context("No address")?;
-            let address = substitute_env_vars(address).context("Invalid address")?;
-            let uri = address.parse().context("Invalid address")?;
-            let uri = prepare_uri(uri, opts.tls).context("Invalid URI")?;
-
-            let mut channel = Channel::builder(uri);
-            if opts.tls {
-                channel = channel.tls_config(tls_config.clone())?;
-            }
-
-            anyhow::Ok(
-                channel
-                    .connect()
-                    .await
-                    .with_context(|| format!("Error connecting to `{}`", address))?,
-            )
-        };
-
         let (cas, execution, action_cache, bytestream, capabilities) = futures::future::join5(
-            create_channel(opts.cas_address.clone()),
-            create_channel(opts.engine_address.clone()),
-            create_channel(opts.action_cache_address.clone()),
-            create_channel(opts.cas_address.clone()),
-            create_channel(opts.engine_address.clone()),
+            create_channel(opts, tls_config, opts.cas_address.as_deref()),
+            create_channel(opts, tls_config, opts.engine_address.as_deref()),
+            create_channel(opts, tls_config, opts.action_cache_address.as_deref()),
+            create_channel(opts, tls_config, opts.cas_address.as_deref()),
+            create_channel(opts, tls_config, opts.engine_address.as_deref()),
         )
         .await;
 
         let interceptor = InjectHeadersInterceptor::new(&opts.http_headers)?;
+        let execution = execution.context("Error creating Execution client")?;
 
+        // The size of the messages is negotiated through the capabilities of the server, so don't
+        // let gRPC apply its own (much lower) default limits on top.
         let mut grpc_clients = GRPCClients {
             cas_client: ContentAddressableStorageClient::with_interceptor(
                 cas.context("Error creating CAS client")?,
                 interceptor.dupe(),
-            ),
+            )
+            .max_decoding_message_size(usize::MAX)
+            .max_encoding_message_size(usize::MAX),
             execution_client: ExecutionClient::with_interceptor(
-                execution.context("Error creating Execution client")?,
+                execution.clone(),
                 interceptor.dupe(),
-            ),
+            )
+            .max_decoding_message_size(usize::MAX)
+            .max_encoding_message_size(usize::MAX),
+            // Operations are served by the execution engine.
+            operations_client: OperationsClient::with_interceptor(execution, interceptor.dupe()),
             action_cache_client: ActionCacheClient::with_interceptor(
                 action_cache.context("Error creating ActionCache client")?,
                 interceptor.dupe(),
-            ),
+            )
+            .max_decoding_message_size(usize::MAX)
+            .max_encoding_message_size(usize::MAX),
             bytestream_client: ByteStreamClient::with_interceptor(
                 bytestream.context("Error creating Bytestream client")?,
                 interceptor.dupe(),
-            ),
+            )
+            .max_decoding_message_size(usize::MAX)
+            .max_encoding_message_size(usize::MAX),
             capabilities_client: CapabilitiesClient::with_interceptor(
                 capabilities.context("Error creating Capabilities client")?,
                 interceptor.dupe(),
@@ -305,7 +372,20 @@ impl REClientBuilder {
         let instance_name = InstanceName(opts.instance_name.clone());
 
         let capabilities = if opts.capabilities.unwrap_or(true) {
-            Self::fetch_rbe_capabilities(&mut grpc_clients, &instance_name).await?
+            let key = CapabilitiesKey::new(opts);
+            match CAPABILITIES.get(&key) {
+                Some(capabilities) => capabilities,
+                None => {
+                    let capabilities = Self::fetch_rbe_capabilities(
+                        &mut grpc_clients,
+                        &instance_name,
+                        opts.digest_function.as_deref(),
+                    )
+                    .await?;
+                    CAPABILITIES.insert(key, capabilities);
+                    capabilities
+                }
+            }
         } else {
             RECapabilities {
                 exec_enabled: true,
@@ -320,6 +400,9 @@ impl REClientBuilder {
         Ok(REClient::new(
             RERuntimeOpts {
                 use_fbcode_metadata: opts.use_fbcode_metadata,
+                max_concurrent_large_uploads: opts
+                    .max_concurrent_large_uploads
+                    .unwrap_or(DEFAULT_MAX_CONCURRENT_LARGE_UPLOADS),
             },
             grpc_clients,
             capabilities,
@@ -327,12 +410,39 @@ impl REClientBuilder {
         ))
     }
 
+    /// Queries the digest functions the server supports for both caching and execution, named as
+    /// in `buck2.digest_algorithms` (e.g. `SHA256`). Returns `None` if the server doesn't advertise
+    /// them.
+    pub async fn fetch_digest_functions(
+        opts: &Buck2OssReConfiguration,
+    ) -> anyhow::Result<Option<Vec<String>>> {
+        let tls_config = create_tls_config(opts)
+            .await
+            .context("Invalid TLS config")?;
+        let channel = create_channel(opts, &tls_config, opts.engine_address.as_deref())
+            .await
+            .context("Error creating Capabilities client")?;
+        let mut capabilities_client = CapabilitiesClient::with_interceptor(
+            channel,
+            InjectHeadersInterceptor::new(&opts.http_headers)?,
+        );
+
+        let resp = capabilities_client
+            .get_capabilities(GetCapabilitiesRequest {
+                instance_name: InstanceName(opts.instance_name.clone()).as_str().to_owned(),
+            })
+            .await
+            .context("Failed to query capabilities of remote")?
+            .into_inner();
+
+        Ok(supported_digest_functions(&resp))
+    }
+
     async fn fetch_rbe_capabilities(
         clients: &mut GRPCClients,
         instance_name: &InstanceName,
+        digest_function: Option<&str>,
     ) -> anyhow::Result<RECapabilities> {
-        // TODO use more of the capabilities of the remote build executor
-
         let resp = clients
             .capabilities_client
             .get_capabilities(GetCapabilitiesRequest {
@@ -341,6 +451,11 @@ impl REClientBuilder {
             .await
             .context("Failed to query capabilities of remote")?
             .into_inner();
+
+        if let Some(digest_function) = digest_function {
+            check_digest_function(&resp, digest_function)?;
+        }
+
         // Default is a reasonable size for the gRPC transport
         // with enough room for headers.
         let mut max_msg_size = DEFAULT_MAX_MSG_SIZE;
@@ -365,6 +480,111 @@ impl REClientBuilder {
     }
 }
 
+async fn create_channel(
+    opts: &Buck2OssReConfiguration,
+    tls_config: &ClientTlsConfig,
+    address: Option<&str>,
+) -> anyhow::Result<Channel> {
+    // The fake backend serves all the services, without TLS.
+    let tls = opts.tls && !opts.fake;
+    let address = if opts.fake {
+        crate::fake::fake_address().await?.to_owned()
+    } else {
+        substitute_env_vars(address.context("No address")?).context("Invalid address")?
+    };
+    let uri = address.parse().context("Invalid address")?;
+    let uri = prepare_uri(uri, tls).context("Invalid URI")?;
+
+    let mut channel = Channel::builder(uri);
+    if tls {
+        channel = channel.tls_config(tls_config.clone())?;
+    }
+
+    channel
+        .connect()
+        .await
+        .with_context(|| format!("Error connecting to `{}`", address))
+}
+
+fn digest_function_name(value: i32) -> String {
+    match digest_function::Value::from_i32(value) {
+        Some(v) => v.as_str_name().to_owned(),
+        None if value == DIGEST_FUNCTION_BLAKE3 => "BLAKE3".to_owned(),
+        None => value.to_string(),
+    }
+}
+
+/// Checks that the server supports the digest function we use. We don't pass the digest function
+/// in requests, so it also has to be the one the server executes actions with.
+fn check_digest_function(
+    capabilities: &ServerCapabilities,
+    digest_function: &str,
+) -> anyhow::Result<()> {
+    let value = match digest_function {
+        "SHA1" => digest_function::Value::Sha1 as i32,
+        "SHA256" => digest_function::Value::Sha256 as i32,
+        "BLAKE3" => DIGEST_FUNCTION_BLAKE3,
+        // Not a digest function of the REAPI, so the server has to be configured for it.
+        _ => return Ok(()),
+    };
+
+    if let Some(cache_cap) = &capabilities.cache_capabilities {
+        if !cache_cap.digest_functions.is_empty() && !cache_cap.digest_functions.contains(&value) {
+            return Err(anyhow::anyhow!(
+                "The remote cache does not support the {} digest function (supported: {}), \
+                set `buck2.digest_algorithms` to one it supports",
+                digest_function,
+                cache_cap
+                    .digest_functions
+                    .map(|v| digest_function_name(*v))
+                    .join(", "),
+            ));
+        }
+    }
+
+    if let Some(exec_cap) = &capabilities.execution_capabilities {
+        if exec_cap.exec_enabled
+            && exec_cap.digest_function != digest_function::Value::Unknown as i32
+            && exec_cap.digest_function != value
+        {
+            return Err(anyhow::anyhow!(
+                "Remote execution uses the {} digest function but buck2 is configured to use {}, \
+                set `buck2.digest_algorithms` to match",
+                digest_function_name(exec_cap.digest_function),
+                digest_function,
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// The digest functions usable with this server: the one it executes actions with, provided the
+/// cache supports it, or else all the ones the cache supports.
+fn supported_digest_functions(capabilities: &ServerCapabilities) -> Option<Vec<String>> {
+    let cache = capabilities
+        .cache_capabilities
+        .as_ref()
+        .map(|cache_cap| &cache_cap.digest_functions)
+        .filter(|digest_functions| !digest_functions.is_empty());
+    let exec = capabilities
+        .execution_capabilities
+        .as_ref()
+        .filter(|exec_cap| {
+            exec_cap.exec_enabled
+                && exec_cap.digest_function != digest_function::Value::Unknown as i32
+        })
+        .map(|exec_cap| exec_cap.digest_function);
+
+    let values = match (exec, cache) {
+        (Some(exec), Some(cache)) if !cache.contains(&exec) => Vec::new(),
+        (Some(exec), _) => vec![exec],
+        (None, Some(cache)) => cache.clone(),
+        (None, None) => return None,
+    };
+    Some(values.into_iter().map(digest_function_name).collect())
+}
+
 #[derive(Clone, Dupe)]
 struct InjectHeadersInterceptor {
     headers: Arc<Vec<(MetadataKey<metadata::Ascii>, MetadataValue<metadata::Ascii>)>>,
@@ -414,6 +634,7 @@ pub struct GRPCClients {
     cas_client:
         ContentAddressableStorageClient<InterceptedService<Channel, InjectHeadersInterceptor>>,
     execution_client: ExecutionClient<InterceptedService<Channel, InjectHeadersInterceptor>>,
+    operations_client: OperationsClient<InterceptedService<Channel, InjectHeadersInterceptor>>,
     action_cache_client: ActionCacheClient<InterceptedService<Channel, InjectHeadersInterceptor>>,
     bytestream_client: ByteStreamClient<InterceptedService<Channel, InjectHeadersInterceptor>>,
     capabilities_client: CapabilitiesClient<InterceptedService<Channel, InjectHeadersInterceptor>>,
@@ -457,6 +678,56 @@ pub struct REClient {
     instance_name: InstanceName,
     // buck2 calls find_missing for same blobs
     find_missing_cache: Mutex<FindMissingCache>,
+    large_upload_semaphore: Semaphore,
+}
+
+/// Cancels the remote operation of an `Execute` call if its response stream is dropped before
+/// the operation is done, e.g. when a hybrid executor used the local result instead. Dropping the
+/// stream alone leaves the action running on the remote workers until it completes.
+struct CancelOperationOnDrop {
+    client: OperationsClient<InterceptedService<Channel, InjectHeadersInterceptor>>,
+    /// The name of the operation, once the server told us about it and while it is not done.
+    operation: Option<String>,
+}
+
+impl CancelOperationOnDrop {
+    fn new(
+        client: OperationsClient<InterceptedService<Channel, InjectHeadersInterceptor>>,
+    ) -> Self {
+        Self {
+            client,
+            operation: None,
+        }
+    }
+
+    /// Keep track of the operation from a message of the `Execute` stream.
+    fn observe(&mut self, msg: &Operation) {
+        if msg.done {
+            self.operation = None;
+        } else if self.operation.is_none() && !msg.name.is_empty() {
+            self.operation = Some(msg.name.clone());
+        }
+    }
+}
+
+impl Drop for CancelOperationOnDrop {
+    fn drop(&mut self) {
+        let Some(name) = self.operation.take() else {
+            return;
+        };
+        // Best effort: the server might not support cancellation, and we can't wait for it here.
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let mut client = self.client.clone();
+            runtime.spawn(async move {
+                if let Err(e) = client
+                    .cancel_operation(CancelOperationRequest { name: name.clone() })
+                    .await
+                {
+                    tracing::debug!("Failed to cancel RE operation `{}`: {}", name, e);
+                }
+            });
+        }
+    }
 }
 
 impl Drop for REClient {
@@ -522,6 +793,7 @@ impl REClient {
         instance_name: InstanceName,
     ) -> Self {
         REClient {
+            large_upload_semaphore: Semaphore::new(runtime_opts.max_concurrent_large_uploads),
             runtime_opts,
             grpc_clients,
             capabilities,
@@ -596,12 +868,17 @@ impl REClient {
             .await?
             .into_inner();
 
-        let stream = futures::stream::try_unfold(stream, move |mut stream| async {
+        let guard = CancelOperationOnDrop::new(self.grpc_clients.operations_client.clone());
+
+        let state = (stream, guard);
+        let stream = futures::stream::try_unfold(state, move |(mut stream, mut guard)| async {
             let msg = match stream.try_next().await.context("RE channel error")? {
                 Some(msg) => msg,
                 None => return Ok(None),
             };
 
+            guard.observe(&msg);
+
             let status = if msg.done {
                 match msg
                     .result
@@ -661,11 +938,15 @@ impl REClient {
                 ExecuteWithProgressResponse {
                     stage,
                     execute_response: None,
-                    ..Default::default()
+                    metadata: OperationMetadata {
+                        stdout_stream_name: meta.stdout_stream_name,
+                        stderr_stream_name: meta.stderr_stream_name,
+                        ..Default::default()
+                    },
                 }
             };
 
-            anyhow::Ok(Some((status, stream)))
+            anyhow::Ok(Some((status, (stream, guard))))
         });
 
         // We fill in the action digest a little later here. We do it this way so we don't have to
@@ -688,10 +969,48 @@ impl REClient {
         Ok(stream.boxed())
     }
 
+    /// Reads a log of an executing action (its `stdout_stream_name` or `stderr_stream_name`) from
+    /// `offset`. Nothing is requested until the stream is polled. Chunks are yielded as the server
+    /// makes them available, and the stream ends once the action has finished writing the log.
+    pub fn read_log_stream(
+        &self,
+        metadata: RemoteExecutionMetadata,
+        resource_name: String,
+        offset: i64,
+    ) -> BoxStream<'static, anyhow::Result<Vec<u8>>> {
+        let mut client = self.grpc_clients.bytestream_client.clone();
+        let use_fbcode_metadata = self.runtime_opts.use_fbcode_metadata;
+        futures::stream::once(async move {
+            let request = ReadRequest {
+                resource_name,
+                read_offset: offset,
+                read_limit: 0,
+            };
+            let stream = client
+                .read(with_re_metadata(request, metadata, use_fbcode_metadata))
+                .await?
+                .into_inner();
+            anyhow::Ok(stream.map(|r| Ok(r.context("RE log stream error")?.data)))
+        })
+        .try_flatten()
+        .boxed()
+    }
+
     pub async fn upload(
         &self,
         metadata: RemoteExecutionMetadata,
         request: UploadRequest,
+    ) -> anyhow::Result<UploadResponse> {
+        self.upload_with_progress(metadata, request, Arc::new(|_: &TDigest, _: i64| {}))
+            .await
+    }
+
+    /// Like `upload`, but reports the progress of the blobs which are too large to be batched.
+    pub async fn upload_with_progress(
+        &self,
+        metadata: RemoteExecutionMetadata,
+        request: UploadRequest,
+        progress: UploadProgress,
     ) -> anyhow::Result<UploadResponse> {
         upload_impl(
             &self.instance_name,
@@ -711,11 +1030,11 @@ impl REClient {
             },
             |segments| async {
                 let metadata = metadata.clone();
+                let _permit = self.large_upload_semaphore.acquire().await?;
                 let mut bytestream_client = self.grpc_clients.bytestream_client.clone();
-                let requests = futures::stream::iter(segments);
                 let resp = bytestream_client
                     .write(with_re_metadata(
-                        requests,
+                        segments,
                         metadata,
                         self.runtime_opts.use_fbcode_metadata,
                     ))
@@ -723,6 +1042,20 @@ impl REClient {
 
                 Ok(resp.into_inner())
             },
+            |resource_name| async {
+                let metadata = metadata.clone();
+                let mut bytestream_client = self.grpc_clients.bytestream_client.clone();
+                let resp = bytestream_client
+                    .query_write_status(with_re_metadata(
+                        QueryWriteStatusRequest { resource_name },
+                        metadata,
+                        self.runtime_opts.use_fbcode_metadata,
+                    ))
+                    .await?;
+
+                Ok(resp.into_inner())
+            },
+            progress,
         )
         .await
     }
@@ -1152,16 +1485,253 @@ where
     })
 }
 
-async fn upload_impl<Byt, Cas>(
+/// A blob too large for `BatchUpdateBlobs`, which is streamed through the ByteStream API instead.
+enum LargeBlob {
+    Inlined(Arc<[u8]>),
+    File(String),
+}
+
+impl LargeBlob {
+    fn description(&self) -> String {
+        match self {
+            LargeBlob::Inlined(_) => "inline blob".to_owned(),
+            LargeBlob::File(name) => format!("`{name}`"),
+        }
+    }
+
+    async fn reader(&self, offset: i64) -> anyhow::Result<Box<dyn AsyncRead + Send + Unpin>> {
+        match self {
+            LargeBlob::Inlined(data) => {
+                let mut cursor = std::io::Cursor::new(data.dupe());
+                cursor.set_position(offset as u64);
+                Ok(Box::new(cursor))
+            }
+            LargeBlob::File(name) => {
+                let mut file = tokio::fs::File::open(name)
+                    .await
+                    .with_context(|| format!("Opening `{name}` for reading failed"))?;
+                file.seek(SeekFrom::Start(offset as u64))
+                    .await
+                    .with_context(|| format!("Error seeking in `{name}`"))?;
+                Ok(Box::new(file))
+            }
+        }
+    }
+}
+
+/// Reads the segment of a large blob which starts at `offset`, of at most `chunk_size` bytes.
+async fn read_segment(
+    reader: &mut Box<dyn AsyncRead + Send + Unpin>,
+    description: &str,
+    resource_name: &str,
+    offset: i64,
+    size: i64,
+    chunk_size: usize,
+) -> anyhow::Result<WriteRequest> {
+    let mut data = Vec::with_capacity(chunk_size);
+    reader
+        .take(chunk_size as u64)
+        .read_to_end(&mut data)
+        .await
+        .with_context(|| format!("Error reading from {description}"))?;
+    if data.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Read no data from {description} at offset {offset}, expected {size} bytes"
+        ));
+    }
+    Ok(WriteRequest {
+        resource_name: resource_name.to_owned(),
+        write_offset: offset,
+        finish_write: offset + data.len() as i64 >= size,
+        data,
+    })
+}
+
+/// The `WriteRequest`s which upload a large blob from `offset`. The blob is read lazily as the
+/// requests are sent, so it is never held in memory in full. The first segment is read eagerly so
+/// that failing to open the blob is reported directly, later read errors are stored in
+/// `read_error` and end the stream.
+async fn large_blob_segments(
+    blob: &LargeBlob,
+    resource_name: &str,
+    digest: &TDigest,
+    offset: i64,
+    chunk_size: usize,
+    progress: &UploadProgress,
+    read_error: &Arc<Mutex<Option<anyhow::Error>>>,
+) -> anyhow::Result<BoxStream<'static, WriteRequest>> {
+    let size = digest.size_in_bytes;
+    let description = blob.description();
+    let mut reader = blob.reader(offset).await?;
+    let first = read_segment(
+        &mut reader,
+        &description,
+        resource_name,
+        offset,
+        size,
+        chunk_size,
+    )
+    .await?;
+
+    let next = (!first.finish_write).then(|| (reader, offset + first.data.len() as i64));
+    let rest = futures::stream::unfold(next, {
+        let resource_name = resource_name.to_owned();
+        let read_error = read_error.dupe();
+        move |next| {
+            let description = description.clone();
+            let resource_name = resource_name.clone();
+            let read_error = read_error.dupe();
+            async move {
+                let (mut reader, offset) = next?;
+                match read_segment(
+                    &mut reader,
+                    &description,
+                    &resource_name,
+                    offset,
+                    size,
+                    chunk_size,
+                )
+                .await
+                {
+                    Ok(segment) => {
+                        let next = (!segment.finish_write)
+                            .then(|| (reader, offset + segment.data.len() as i64));
+                        Some((segment, next))
+                    }
+                    Err(e) => {
+                        *read_error.lock().unwrap() = Some(e);
+                        None
+                    }
+                }
+            }
+        }
+    });
+
+    let digest = digest.clone();
+    let progress = progress.dupe();
+    Ok(futures::stream::once(futures::future::ready(first))
+        .chain(rest)
+        .inspect(move |segment| {
+            let sent = segment.write_offset + segment.data.len() as i64;
+            if segment.finish_write
+                || sent / LARGE_UPLOAD_PROGRESS_INTERVAL
+                    != segment.write_offset / LARGE_UPLOAD_PROGRESS_INTERVAL
+            {
+                progress(&digest, sent);
+            }
+        })
+        .boxed())
+}
+
+/// Reports a large upload as fully sent when dropped, so that it stops being shown as in progress
+/// however the upload ends: success, failure or cancellation.
+struct LargeUploadDone {
+    digest: TDigest,
+    progress: UploadProgress,
+}
+
+impl Drop for LargeUploadDone {
+    fn drop(&mut self) {
+        (self.progress)(&self.digest, self.digest.size_in_bytes);
+    }
+}
+
+/// Whether a failed ByteStream write may succeed if resumed, as opposed to the server rejecting
+/// it.
+fn is_transient_write_error(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<tonic::Status>().is_some_and(|status| {
+        matches!(
+            status.code(),
+            tonic::Code::Unavailable
+                | tonic::Code::DeadlineExceeded
+                | tonic::Code::ResourceExhausted
+                | tonic::Code::Aborted
+                | tonic::Code::Internal
+                | tonic::Code::Unknown
+        )
+    })
+}
+
+/// Uploads a large blob through the ByteStream API. If a write fails with a transient error, it is
+/// resumed from the size the server says it committed, or restarted if the server can't tell.
+async fn upload_large_blob<Byt, Qws>(
+    blob: LargeBlob,
+    resource_name: String,
+    digest: TDigest,
+    chunk_size: usize,
+    bystream_fut: impl Fn(BoxStream<'static, WriteRequest>) -> Byt,
+    query_write_status: impl Fn(String) -> Qws,
+    progress: UploadProgress,
+) -> anyhow::Result<()>
+where
+    Byt: Future<Output = anyhow::Result<WriteResponse>>,
+    Qws: Future<Output = anyhow::Result<QueryWriteStatusResponse>>,
+{
+    let _done = LargeUploadDone {
+        digest: digest.clone(),
+        progress: progress.dupe(),
+    };
+    let size = digest.size_in_bytes;
+    let description = blob.description();
+    let mut offset = 0;
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let read_error = Arc::new(Mutex::new(None));
+        let segments = large_blob_segments(
+            &blob,
+            &resource_name,
+            &digest,
+            offset,
+            chunk_size,
+            &progress,
+            &read_error,
+        )
+        .await?;
+        let res = bystream_fut(segments).await;
+        if let Some(e) = read_error.lock().unwrap().take() {
+            return Err(e);
+        }
+        let e = match res {
+            Ok(resp) if resp.committed_size == size => return Ok(()),
+            Ok(_) => {
+                return Err(anyhow::anyhow!(
+                    "Failed to upload {description}: invalid committed_size from WriteResponse"
+                ));
+            }
+            Err(e) => e,
+        };
+        if attempt >= LARGE_UPLOAD_ATTEMPTS || !is_transient_write_error(&e) {
+            return Err(e);
+        }
+
+        offset = match query_write_status(resource_name.clone()).await {
+            Ok(status) if status.complete => return Ok(()),
+            Ok(status) if (0..size).contains(&status.committed_size) => status.committed_size,
+            _ => 0,
+        };
+        tracing::debug!(
+            "Resuming upload of {} at offset {} after error: {:#}",
+            description,
+            offset,
+            e
+        );
+    }
+}
+
+async fn upload_impl<Byt, Cas, Qws>(
     instance_name: &InstanceName,
     request: UploadRequest,
     max_msg_size: usize,
     cas_f: impl Fn(BatchUpdateBlobsRequest) -> Cas + Sync + Send + Copy,
-    bystream_fut: impl Fn(Vec<WriteRequest>) -> Byt + Sync + Send + Copy,
+    bystream_fut: impl Fn(BoxStream<'static, WriteRequest>) -> Byt + Sync + Send + Copy,
+    query_write_status: impl Fn(String) -> Qws + Sync + Send + Copy,
+    progress: UploadProgress,
 ) -> anyhow::Result<UploadResponse>
 where
     Cas: Future<Output = anyhow::Result<BatchUpdateBlobsResponse>> + Send,
     Byt: Future<Output = anyhow::Result<WriteResponse>> + Send,
+    Qws: Future<Output = anyhow::Result<QueryWriteStatusResponse>> + Send,
 {
     // NOTE if we stop recording blob_hashes, we can drop out a lot of allocations.
     let mut upload_futures: Vec<BoxFuture<anyhow::Result<Vec<String>>>> = vec![];
@@ -1180,7 +1750,6 @@ where
             continue;
         }
 
-        let data = blob.blob;
         let client_uuid = uuid::Uuid::new_v4().to_string();
         let resource_name = format!(
             "{}uploads/{}/blobs/{}/{}",
@@ -1189,28 +1758,16 @@ where
             hash,
             size
         );
-        let fut = async move {
-            // Number of complete (non-partial) messages
-            let mut upload_segments = vec![];
-            for (i, chunk) in data.chunks(max_msg_size).enumerate() {
-                upload_segments.push(WriteRequest {
-                    resource_name: resource_name.to_owned(),
-                    write_offset: (i * max_msg_size) as i64,
-                    finish_write: false,
-                    data: chunk.to_owned(),
-                });
-            }
-            upload_segments.last_mut().unwrap().finish_write = true;
-
-            let resp = bystream_fut(upload_segments).await?;
-            if resp.committed_size != size {
-                return Err(anyhow::anyhow!(
-                    "Failed to upload inline blob: invalid committed_size from WriteResponse"
-                ));
-            }
-
-            Ok(vec![hash])
-        };
+        let fut = upload_large_blob(
+            LargeBlob::Inlined(blob.blob.into()),
+            resource_name,
+            blob.digest,
+            max_msg_size,
+            bystream_fut,
+            query_write_status,
+            progress.dupe(),
+        )
+        .map(|res| res.map(|()| vec![hash]));
         upload_futures.push(Box::pin(fut));
     }
 
@@ -1218,7 +1775,6 @@ where
     for file in request.files_with_digest.unwrap_or_default() {
         let hash = file.digest.hash.clone();
         let size = file.digest.size_in_bytes;
-        let name = file.name.clone();
         if size < max_msg_size as i64 {
             batched_blob_updates.push(BatchUploadRequest::File(file));
             continue;
@@ -1231,43 +1787,16 @@ where
             hash.clone(),
             size
         );
-        let fut = async move {
-            let mut file = tokio::fs::File::open(&name)
-                .await
-                .with_context(|| format!("Opening `{name}` for reading failed"))?;
-            let mut data = vec![0; max_msg_size];
-
-            let mut write_offset = 0;
-            let mut upload_segments = Vec::new();
-            loop {
-                let length = file
-                    .read(&mut data)
-                    .await
-                    .with_context(|| format!("Error reading from {name}"))?;
-                if length == 0 {
-                    break;
-                }
-                upload_segments.push(WriteRequest {
-                    resource_name: resource_name.to_owned(),
-                    write_offset,
-                    finish_write: false,
-                    data: data[..length].to_owned(),
-                });
-                write_offset += length as i64;
-            }
-            upload_segments
-                .last_mut()
-                .with_context(|| format!("Read no segments from `{name} "))?
-                .finish_write = true;
-
-            let resp = bystream_fut(upload_segments).await?;
-            if resp.committed_size != size {
-                return Err(anyhow::anyhow!(
-                    "Failed to upload `{name}`: invalid committed_size from WriteResponse"
-                ));
-            }
-            Ok(vec![hash])
-        };
+        let fut = upload_large_blob(
+            LargeBlob::File(file.name),
+            resource_name,
+            file.digest,
+            max_msg_size,
+            bystream_fut,
+            query_write_status,
+            progress.dupe(),
+        )
+        .map(|res| res.map(|()| vec![hash]));
         upload_futures.push(Box::pin(fut));
     }
 
@@ -1465,6 +1994,13 @@ mod tests {
 
     use re_grpc_proto::build::bazel::remote::execution::v2::batch_read_blobs_response;
     use re_grpc_proto::build::bazel::remote::execution::v2::batch_update_blobs_response;
+    use re_grpc_proto::google::longrunning::operations_server::Operations;
+    use re_grpc_proto::google::longrunning::operations_server::OperationsServer;
+    use re_grpc_proto::google::longrunning::DeleteOperationRequest;
+    use re_grpc_proto::google::longrunning::GetOperationRequest;
+    use re_grpc_proto::google::longrunning::ListOperationsRequest;
+    use re_grpc_proto::google::longrunning::ListOperationsResponse;
+    use re_grpc_proto::google::longrunning::WaitOperationRequest;
 
     use super::*;
 
@@ -2051,6 +2587,10 @@ mod tests {
                 }
             },
             |_req| async { panic!("A Bytestream upload should not be triggered") },
+            |_resource_name| async move {
+                panic!("Not called");
+            },
+            Arc::new(|_: &TDigest, _: i64| {}),
         )
         .await?;
 
@@ -2132,6 +2672,7 @@ mod tests {
             |write_reqs| {
                 let blob_data = blob_data.clone();
                 async move {
+                    let write_reqs = write_reqs.collect::<Vec<_>>().await;
                     assert_eq!(write_reqs.len(), 2);
                     assert_eq!(write_reqs[0].write_offset, 0);
                     assert!(!write_reqs[0].finish_write);
@@ -2142,6 +2683,10 @@ mod tests {
                     anyhow::Ok(WriteResponse { committed_size: 18 })
                 }
             },
+            |_resource_name| async move {
+                panic!("Not called");
+            },
+            Arc::new(|_: &TDigest, _: i64| {}),
         )
         .await?;
         Ok(())
@@ -2206,6 +2751,7 @@ mod tests {
             |write_reqs| {
                 let blob_data2 = blob_data2.clone();
                 async move {
+                    let write_reqs = write_reqs.collect::<Vec<_>>().await;
                     assert_eq!(write_reqs.len(), 2);
                     assert_eq!(write_reqs[0].write_offset, 0);
                     assert!(!write_reqs[0].finish_write);
@@ -2216,6 +2762,10 @@ mod tests {
                     anyhow::Ok(WriteResponse { committed_size: 18 })
                 }
             },
+            |_resource_name| async move {
+                panic!("Not called");
+            },
+            Arc::new(|_: &TDigest, _: i64| {}),
         )
         .await?;
         Ok(())
@@ -2259,6 +2809,10 @@ mod tests {
                 // Not the right size
                 anyhow::Ok(WriteResponse { committed_size: 10 })
             },
+            |_resource_name| async move {
+                panic!("Not called");
+            },
+            Arc::new(|_: &TDigest, _: i64| {}),
         )
         .await;
 
@@ -2273,6 +2827,170 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_upload_large_resumes() -> anyhow::Result<()> {
+        let digest = TDigest {
+            hash: "xl".to_owned(),
+            size_in_bytes: 18,
+            ..Default::default()
+        };
+        let blob_data = vec![
+            1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18,
+        ];
+
+        let req = UploadRequest {
+            inlined_blobs_with_digest: Some(vec![InlinedBlobWithDigest {
+                blob: blob_data.clone(),
+                digest: digest.clone(),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+
+        let attempts = AtomicU16::new(0);
+        let progress = Arc::new(Mutex::new(Vec::new()));
+
+        upload_impl(
+            &InstanceName(None),
+            req,
+            10,
+            |_req| async move {
+                panic!("Not called");
+            },
+            |write_reqs| {
+                let attempt = attempts.fetch_add(1, Ordering::Relaxed);
+                let blob_data = blob_data.clone();
+                async move {
+                    let write_reqs = write_reqs.collect::<Vec<_>>().await;
+                    if attempt == 0 {
+                        assert_eq!(write_reqs.len(), 2);
+                        return Err(tonic::Status::unavailable("Connection reset").into());
+                    }
+                    // Resumed from what the server committed.
+                    assert_eq!(write_reqs.len(), 1);
+                    assert_eq!(write_reqs[0].write_offset, 10);
+                    assert!(write_reqs[0].finish_write);
+                    assert_eq!(write_reqs[0].data, blob_data[10..]);
+                    anyhow::Ok(WriteResponse { committed_size: 18 })
+                }
+            },
+            |_resource_name| async move {
+                anyhow::Ok(QueryWriteStatusResponse {
+                    committed_size: 10,
+                    complete: false,
+                })
+            },
+            {
+                let progress = progress.dupe();
+                Arc::new(move |_: &TDigest, sent: i64| progress.lock().unwrap().push(sent))
+            },
+        )
+        .await?;
+
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+        assert_eq!(*progress.lock().unwrap(), vec![18, 18, 18]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_upload_large_fails_without_retrying_rejected_write() -> anyhow::Result<()> {
+        let digest = TDigest {
+            hash: "xl".to_owned(),
+            size_in_bytes: 18,
+            ..Default::default()
+        };
+
+        let req = UploadRequest {
+            inlined_blobs_with_digest: Some(vec![InlinedBlobWithDigest {
+                blob: vec![0; 18],
+                digest,
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+
+        let attempts = AtomicU16::new(0);
+        let progress = Arc::new(Mutex::new(Vec::new()));
+
+        let res = upload_impl(
+            &InstanceName(None),
+            req,
+            10,
+            |_req| async move {
+                panic!("Not called");
+            },
+            |write_reqs| {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                async move {
+                    // Stop before the last segment.
+                    write_reqs.take(1).collect::<Vec<_>>().await;
+                    Err(tonic::Status::permission_denied("Denied").into())
+                }
+            },
+            |_resource_name| async move {
+                panic!("Not called");
+            },
+            {
+                let progress = progress.dupe();
+                Arc::new(move |_: &TDigest, sent: i64| progress.lock().unwrap().push(sent))
+            },
+        )
+        .await;
+
+        assert!(res.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+        // The upload is reported as done even though it failed.
+        assert_eq!(*progress.lock().unwrap(), vec![18]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_upload_large_complete_after_failed_write() -> anyhow::Result<()> {
+        let digest = TDigest {
+            hash: "xl".to_owned(),
+            size_in_bytes: 18,
+            ..Default::default()
+        };
+
+        let req = UploadRequest {
+            inlined_blobs_with_digest: Some(vec![InlinedBlobWithDigest {
+                blob: vec![0; 18],
+                digest,
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+
+        let progress = Arc::new(Mutex::new(Vec::new()));
+
+        upload_impl(
+            &InstanceName(None),
+            req,
+            10,
+            |_req| async move {
+                panic!("Not called");
+            },
+            |write_reqs| async move {
+                write_reqs.take(1).collect::<Vec<_>>().await;
+                Err(tonic::Status::unavailable("Connection reset").into())
+            },
+            |_resource_name| async move {
+                anyhow::Ok(QueryWriteStatusResponse {
+                    committed_size: 18,
+                    complete: true,
+                })
+            },
+            {
+                let progress = progress.dupe();
+                Arc::new(move |_: &TDigest, sent: i64| progress.lock().unwrap().push(sent))
+            },
+        )
+        .await?;
+
+        assert_eq!(*progress.lock().unwrap(), vec![18]);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_upload_exact() -> anyhow::Result<()> {
         let work = tempfile::tempdir()?;
@@ -2316,10 +3034,15 @@ mod tests {
                 panic!("Not called");
             },
             |write_reqs| async move {
+                let write_reqs = write_reqs.collect::<Vec<_>>().await;
                 assert_eq!(write_reqs.len(), 2);
                 assert!(write_reqs[1].finish_write);
                 anyhow::Ok(WriteResponse { committed_size: 6 })
             },
+            |_resource_name| async move {
+                panic!("Not called");
+            },
+            Arc::new(|_: &TDigest, _: i64| {}),
         )
         .await?;
         Ok(())
@@ -2358,6 +3081,10 @@ mod tests {
             |_write_reqs| async move {
                 panic!("Not called");
             },
+            |_resource_name| async move {
+                panic!("Not called");
+            },
+            Arc::new(|_: &TDigest, _: i64| {}),
         )
         .await;
 
@@ -2401,16 +3128,117 @@ mod tests {
                 panic!("Not called");
             },
             |write_reqs| async move {
+                let write_reqs = write_reqs.collect::<Vec<_>>().await;
                 assert!(write_reqs[0].resource_name.starts_with("instance/uploads/"));
                 assert!(write_reqs[0].resource_name.ends_with("/blobs/aa/3"));
                 anyhow::Ok(WriteResponse { committed_size: 3 })
             },
+            |_resource_name| async move {
+                panic!("Not called");
+            },
+            Arc::new(|_: &TDigest, _: i64| {}),
         )
         .await?;
 
         Ok(())
     }
 
+    #[test]
+    fn test_check_digest_function() {
+        use re_grpc_proto::build::bazel::remote::execution::v2::CacheCapabilities;
+        use re_grpc_proto::build::bazel::remote::execution::v2::ExecutionCapabilities;
+
+        let capabilities = ServerCapabilities {
+            cache_capabilities: Some(CacheCapabilities {
+                digest_functions: vec![
+                    digest_function::Value::Sha256 as i32,
+                    DIGEST_FUNCTION_BLAKE3,
+                ],
+                ..Default::default()
+            }),
+            execution_capabilities: Some(ExecutionCapabilities {
+                digest_function: digest_function::Value::Sha256 as i32,
+                exec_enabled: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert!(check_digest_function(&capabilities, "SHA256").is_ok());
+        // Supported by the cache, but actions are executed with SHA256.
+        assert!(check_digest_function(&capabilities, "BLAKE3").is_err());
+        let err = check_digest_function(&capabilities, "SHA1").unwrap_err();
+        assert!(err.to_string().contains("supported: SHA256, BLAKE3"));
+        // Not a REAPI digest function, so there is nothing to check.
+        assert!(check_digest_function(&capabilities, "BLAKE3-KEYED").is_ok());
+        // Servers which don't advertise anything are assumed to be configured correctly.
+        assert!(check_digest_function(&ServerCapabilities::default(), "SHA1").is_ok());
+    }
+
+    #[test]
+    fn test_capabilities_cache() {
+        let key = |instance_name: &str| CapabilitiesKey {
+            engine_address: Some("grpc://localhost:8980".to_owned()),
+            instance_name: Some(instance_name.to_owned()),
+            digest_function: None,
+        };
+        let capabilities = RECapabilities {
+            max_msg_size: 1024,
+            exec_enabled: true,
+        };
+
+        let cache = CapabilitiesCache::new();
+        assert_eq!(cache.get(&key("main")), None);
+        cache.insert(key("main"), capabilities);
+        assert_eq!(cache.get(&key("main")), Some(capabilities));
+        // A changed configuration negotiates the capabilities again.
+        assert_eq!(cache.get(&key("other")), None);
+        cache.insert(key("other"), capabilities);
+        assert_eq!(cache.get(&key("main")), None);
+    }
+
+    #[test]
+    fn test_supported_digest_functions() {
+        use re_grpc_proto::build::bazel::remote::execution::v2::CacheCapabilities;
+        use re_grpc_proto::build::bazel::remote::execution::v2::ExecutionCapabilities;
+
+        let cache_capabilities = Some(CacheCapabilities {
+            digest_functions: vec![
+                digest_function::Value::Sha256 as i32,
+                DIGEST_FUNCTION_BLAKE3,
+            ],
+            ..Default::default()
+        });
+
+        let cache_only = ServerCapabilities {
+            cache_capabilities: cache_capabilities.clone(),
+            ..Default::default()
+        };
+        assert_eq!(
+            Some(vec!["SHA256".to_owned(), "BLAKE3".to_owned()]),
+            supported_digest_functions(&cache_only)
+        );
+
+        let with_execution = ServerCapabilities {
+            cache_capabilities,
+            execution_capabilities: Some(ExecutionCapabilities {
+                digest_function: DIGEST_FUNCTION_BLAKE3,
+                exec_enabled: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            Some(vec!["BLAKE3".to_owned()]),
+            supported_digest_functions(&with_execution)
+        );
+
+        assert_eq!(
+            None,
+            supported_digest_functions(&ServerCapabilities::default())
+        );
+    }
+
     #[test]
     fn test_substitute_env_vars() {
         let getter = |s: &str| match s {
@@ -2436,4 +3264,82 @@ mod tests {
         assert_eq!(substitute_env_vars_impl("FOO", getter).unwrap(), "FOO");
         assert!(substitute_env_vars_impl("$FOO$BAZ", getter).is_err());
     }
+
+    /// Operations service which records the operations it is asked to cancel.
+    struct RecordCancellations(tokio::sync::mpsc::UnboundedSender<String>);
+
+    #[tonic::async_trait]
+    impl Operations for RecordCancellations {
+        async fn list_operations(
+            &self,
+            _request: tonic::Request<ListOperationsRequest>,
+        ) -> Result<tonic::Response<ListOperationsResponse>, tonic::Status> {
+            Err(tonic::Status::unimplemented("list_operations"))
+        }
+
+        async fn get_operation(
+            &self,
+            _request: tonic::Request<GetOperationRequest>,
+        ) -> Result<tonic::Response<Operation>, tonic::Status> {
+            Err(tonic::Status::unimplemented("get_operation"))
+        }
+
+        async fn delete_operation(
+            &self,
+            _request: tonic::Request<DeleteOperationRequest>,
+        ) -> Result<tonic::Response<()>, tonic::Status> {
+            Err(tonic::Status::unimplemented("delete_operation"))
+        }
+
+        async fn cancel_operation(
+            &self,
+            request: tonic::Request<CancelOperationRequest>,
+        ) -> Result<tonic::Response<()>, tonic::Status> {
+            let _ignored = self.0.send(request.into_inner().name);
+            Ok(tonic::Response::new(()))
+        }
+
+        async fn wait_operation(
+            &self,
+            _request: tonic::Request<WaitOperationRequest>,
+        ) -> Result<tonic::Response<Operation>, tonic::Status> {
+            Err(tonic::Status::unimplemented("wait_operation"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancel_operation_on_drop() -> anyhow::Result<()> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(OperationsServer::new(RecordCancellations(tx)))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+        let channel = Channel::from_shared(format!("http://{}", address))?
+            .connect()
+            .await?;
+        let client =
+            OperationsClient::with_interceptor(channel, InjectHeadersInterceptor::new(&[])?);
+        let operation = |name: &str, done: bool| Operation {
+            name: name.to_owned(),
+            done,
+            ..Default::default()
+        };
+
+        // Operations which are done are not cancelled.
+        let mut guard = CancelOperationOnDrop::new(client.clone());
+        guard.observe(&operation("done", false));
+        guard.observe(&operation("done", true));
+        drop(guard);
+
+        let mut guard = CancelOperationOnDrop::new(client);
+        guard.observe(&operation("", false));
+        guard.observe(&operation("running", false));
+        drop(guard);
+
+        assert_eq!(Some("running".to_owned()), rx.recv().await);
+        Ok(())
+    }
 }
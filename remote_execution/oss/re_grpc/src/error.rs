@@ -42,8 +42,11 @@ pub struct TCode(pub i32);
 impl TCode {
     pub const OK: Self = TCode(0i32);
     pub const INVALID_ARGUMENT: Self = TCode(3i32);
+    pub const DEADLINE_EXCEEDED: Self = TCode(4i32);
     pub const NOT_FOUND: Self = TCode(5i32);
     pub const PERMISSION_DENIED: Self = TCode(7i32);
+    pub const RESOURCE_EXHAUSTED: Self = TCode(8i32);
+    pub const UNAVAILABLE: Self = TCode(14i32);
 }
 
 impl Display for TCode {
@@ -57,3 +60,16 @@ impl Display for TCode {
         }
     }
 }
+
+/// The gRPC code of an error returned by this client, if it came from the server or the
+/// transport rather than from Buck2 itself.
+pub fn error_code(error: &anyhow::Error) -> Option<TCode> {
+    error.chain().find_map(|e| {
+        if let Some(e) = e.downcast_ref::<REClientError>() {
+            Some(e.code.dupe())
+        } else {
+            e.downcast_ref::<tonic::Status>()
+                .map(|status| TCode(status.code() as i32))
+        }
+    })
+}
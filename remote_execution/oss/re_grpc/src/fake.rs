@@ -0,0 +1,907 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A remote execution backend running in the Buck2 process, used when `buck2_re_client.fake` is
+//! set, to exercise the remote code paths (uploads, input trees, downloads, cache hits) without an
+//! RE cluster. It serves the REv2 API on localhost, keeps the CAS and the action cache in memory,
+//! and runs actions locally in a temporary directory. Results of successful actions are cached, so
+//! running an action again is a cache hit. Action timeouts and platforms are ignored.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use futures::stream::BoxStream;
+use futures::stream::StreamExt;
+use prost::Message;
+use re_grpc_proto::build::bazel::remote::execution::v2::action_cache_server::ActionCache;
+use re_grpc_proto::build::bazel::remote::execution::v2::action_cache_server::ActionCacheServer;
+use re_grpc_proto::build::bazel::remote::execution::v2::batch_read_blobs_response;
+use re_grpc_proto::build::bazel::remote::execution::v2::batch_update_blobs_response;
+use re_grpc_proto::build::bazel::remote::execution::v2::capabilities_server::Capabilities;
+use re_grpc_proto::build::bazel::remote::execution::v2::capabilities_server::CapabilitiesServer;
+use re_grpc_proto::build::bazel::remote::execution::v2::content_addressable_storage_server::ContentAddressableStorage;
+use re_grpc_proto::build::bazel::remote::execution::v2::content_addressable_storage_server::ContentAddressableStorageServer;
+use re_grpc_proto::build::bazel::remote::execution::v2::digest_function;
+use re_grpc_proto::build::bazel::remote::execution::v2::execution_server::Execution;
+use re_grpc_proto::build::bazel::remote::execution::v2::execution_server::ExecutionServer;
+use re_grpc_proto::build::bazel::remote::execution::v2::Action;
+use re_grpc_proto::build::bazel::remote::execution::v2::ActionCacheUpdateCapabilities;
+use re_grpc_proto::build::bazel::remote::execution::v2::ActionResult;
+use re_grpc_proto::build::bazel::remote::execution::v2::BatchReadBlobsRequest;
+use re_grpc_proto::build::bazel::remote::execution::v2::BatchReadBlobsResponse;
+use re_grpc_proto::build::bazel::remote::execution::v2::BatchUpdateBlobsRequest;
+use re_grpc_proto::build::bazel::remote::execution::v2::BatchUpdateBlobsResponse;
+use re_grpc_proto::build::bazel::remote::execution::v2::CacheCapabilities;
+use re_grpc_proto::build::bazel::remote::execution::v2::Command;
+use re_grpc_proto::build::bazel::remote::execution::v2::Digest;
+use re_grpc_proto::build::bazel::remote::execution::v2::Directory;
+use re_grpc_proto::build::bazel::remote::execution::v2::DirectoryNode;
+use re_grpc_proto::build::bazel::remote::execution::v2::ExecuteRequest;
+use re_grpc_proto::build::bazel::remote::execution::v2::ExecuteResponse;
+use re_grpc_proto::build::bazel::remote::execution::v2::ExecutedActionMetadata;
+use re_grpc_proto::build::bazel::remote::execution::v2::ExecutionCapabilities;
+use re_grpc_proto::build::bazel::remote::execution::v2::FileNode;
+use re_grpc_proto::build::bazel::remote::execution::v2::FindMissingBlobsRequest;
+use re_grpc_proto::build::bazel::remote::execution::v2::FindMissingBlobsResponse;
+use re_grpc_proto::build::bazel::remote::execution::v2::GetActionResultRequest;
+use re_grpc_proto::build::bazel::remote::execution::v2::GetCapabilitiesRequest;
+use re_grpc_proto::build::bazel::remote::execution::v2::GetTreeRequest;
+use re_grpc_proto::build::bazel::remote::execution::v2::GetTreeResponse;
+use re_grpc_proto::build::bazel::remote::execution::v2::OutputDirectory;
+use re_grpc_proto::build::bazel::remote::execution::v2::OutputFile;
+use re_grpc_proto::build::bazel::remote::execution::v2::OutputSymlink;
+use re_grpc_proto::build::bazel::remote::execution::v2::ServerCapabilities;
+use re_grpc_proto::build::bazel::remote::execution::v2::SymlinkNode;
+use re_grpc_proto::build::bazel::remote::execution::v2::Tree;
+use re_grpc_proto::build::bazel::remote::execution::v2::UpdateActionResultRequest;
+use re_grpc_proto::build::bazel::remote::execution::v2::WaitExecutionRequest;
+use re_grpc_proto::google::bytestream::byte_stream_server::ByteStream;
+use re_grpc_proto::google::bytestream::byte_stream_server::ByteStreamServer;
+use re_grpc_proto::google::bytestream::QueryWriteStatusRequest;
+use re_grpc_proto::google::bytestream::QueryWriteStatusResponse;
+use re_grpc_proto::google::bytestream::ReadRequest;
+use re_grpc_proto::google::bytestream::ReadResponse;
+use re_grpc_proto::google::bytestream::WriteRequest;
+use re_grpc_proto::google::bytestream::WriteResponse;
+use re_grpc_proto::google::longrunning::operation;
+use re_grpc_proto::google::longrunning::Operation;
+use re_grpc_proto::google::rpc::Code;
+use re_grpc_proto::google::rpc::Status as RpcStatus;
+use sha2::Digest as _;
+use tonic::Request;
+use tonic::Response;
+use tonic::Status;
+use tonic::Streaming;
+
+use crate::client::DIGEST_FUNCTION_BLAKE3;
+
+/// Advertised to clients, which stream larger blobs through the ByteStream API.
+const MAX_BATCH_TOTAL_SIZE: usize = 4 * 1024 * 1024;
+
+/// Size of the chunks blobs are read in through the ByteStream API.
+const READ_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The address of the fake backend, which is started the first time it is needed and then serves
+/// every client of the process, so that the CAS and the action cache outlive reconnections.
+static ADDRESS: tokio::sync::OnceCell<String> = tokio::sync::OnceCell::const_new();
+
+pub(crate) async fn fake_address() -> anyhow::Result<&'static str> {
+    let address = ADDRESS
+        .get_or_try_init(|| async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+                .await
+                .context("Error binding the fake RE backend")?;
+            let address = listener.local_addr()?.to_string();
+            let fake = FakeRe::default();
+            tokio::spawn(async move {
+                let res = tonic::transport::Server::builder()
+                    .add_service(CapabilitiesServer::new(fake.clone()))
+                    .add_service(
+                        ContentAddressableStorageServer::new(fake.clone())
+                            .max_decoding_message_size(usize::MAX)
+                            .max_encoding_message_size(usize::MAX),
+                    )
+                    .add_service(
+                        ByteStreamServer::new(fake.clone())
+                            .max_decoding_message_size(usize::MAX)
+                            .max_encoding_message_size(usize::MAX),
+                    )
+                    .add_service(
+                        ActionCacheServer::new(fake.clone())
+                            .max_decoding_message_size(usize::MAX)
+                            .max_encoding_message_size(usize::MAX),
+                    )
+                    .add_service(
+                        ExecutionServer::new(fake)
+                            .max_decoding_message_size(usize::MAX)
+                            .max_encoding_message_size(usize::MAX),
+                    )
+                    .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+                    .await;
+                if let Err(e) = res {
+                    tracing::warn!("Fake RE backend stopped: {:#}", e);
+                }
+            });
+            anyhow::Ok(address)
+        })
+        .await?;
+    Ok(address)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DigestAlgorithm {
+    Sha1,
+    Sha256,
+    Blake3,
+}
+
+impl DigestAlgorithm {
+    /// Requests don't say which digest function they use, so it is found from the action, whose
+    /// digest is known.
+    fn of(digest: &Digest, content: &[u8]) -> Option<Self> {
+        [Self::Sha1, Self::Sha256, Self::Blake3]
+            .into_iter()
+            .find(|algorithm| algorithm.digest(content).hash == digest.hash)
+    }
+
+    fn digest(self, content: &[u8]) -> Digest {
+        let hash = match self {
+            Self::Sha1 => hex::encode(sha1::Sha1::digest(content)),
+            Self::Sha256 => hex::encode(sha2::Sha256::digest(content)),
+            Self::Blake3 => blake3::hash(content).to_hex().to_string(),
+        };
+        Digest {
+            hash,
+            size_bytes: content.len() as i64,
+        }
+    }
+}
+
+#[derive(Default)]
+struct FakeReState {
+    /// Blobs by hash.
+    cas: Mutex<HashMap<String, Arc<Vec<u8>>>>,
+    /// Action results by action hash.
+    action_cache: Mutex<HashMap<String, ActionResult>>,
+}
+
+#[derive(Clone, Default)]
+struct FakeRe(Arc<FakeReState>);
+
+fn rpc_status(code: Code, message: String) -> RpcStatus {
+    RpcStatus {
+        code: code as i32,
+        message,
+        details: Vec::new(),
+    }
+}
+
+fn internal(e: anyhow::Error) -> Status {
+    Status::internal(format!("{:#}", e))
+}
+
+/// The digest of the blob in a ByteStream resource name, e.g.
+/// `instance/uploads/<uuid>/blobs/<hash>/<size>`.
+fn resource_digest(resource_name: &str) -> Result<Digest, Status> {
+    let mut parts = resource_name.split('/');
+    parts
+        .by_ref()
+        .find(|part| *part == "blobs")
+        .and_then(|_| {
+            Some(Digest {
+                hash: parts.next()?.to_owned(),
+                size_bytes: parts.next()?.parse().ok()?,
+            })
+        })
+        .ok_or_else(|| {
+            Status::invalid_argument(format!("Invalid resource name: `{}`", resource_name))
+        })
+}
+
+/// Checks that `content` has the size and hash of `digest`, so that clients uploading blobs
+/// under the wrong digest are caught.
+fn verify_blob(digest: &Digest, content: &[u8]) -> Result<(), String> {
+    if digest.size_bytes != content.len() as i64 {
+        return Err(format!(
+            "Blob `{}` has size {}, but its digest has size {}",
+            digest.hash,
+            content.len(),
+            digest.size_bytes
+        ));
+    }
+    if DigestAlgorithm::of(digest, content).is_none() {
+        return Err(format!(
+            "Blob content does not match hash `{}`",
+            digest.hash
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> anyhow::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(target: &str, path: &Path) -> anyhow::Result<()> {
+    std::os::unix::fs::symlink(target, path)?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn symlink(target: &str, path: &Path) -> anyhow::Result<()> {
+    std::os::windows::fs::symlink_file(target, path)?;
+    Ok(())
+}
+
+impl FakeRe {
+    fn blob(&self, digest: Option<&Digest>) -> anyhow::Result<Arc<Vec<u8>>> {
+        let digest = digest.context("Missing digest")?;
+        self.0
+            .cas
+            .lock()
+            .unwrap()
+            .get(&digest.hash)
+            .cloned()
+            .with_context(|| format!("Blob `{}` is not in the CAS", digest.hash))
+    }
+
+    fn put(&self, algorithm: DigestAlgorithm, content: Vec<u8>) -> Digest {
+        let digest = algorithm.digest(&content);
+        self.0
+            .cas
+            .lock()
+            .unwrap()
+            .insert(digest.hash.clone(), Arc::new(content));
+        digest
+    }
+
+    fn materialize(&self, digest: Option<&Digest>, dir: &Path) -> anyhow::Result<()> {
+        let directory = Directory::decode(&self.blob(digest)?[..])?;
+        fs::create_dir_all(dir)?;
+        for file in &directory.files {
+            let path = dir.join(&file.name);
+            fs::write(&path, &*self.blob(file.digest.as_ref())?)?;
+            if file.is_executable {
+                set_executable(&path)?;
+            }
+        }
+        for node in &directory.symlinks {
+            symlink(&node.target, &dir.join(&node.name))?;
+        }
+        for node in &directory.directories {
+            self.materialize(node.digest.as_ref(), &dir.join(&node.name))?;
+        }
+        Ok(())
+    }
+
+    /// Uploads the contents of `dir`, adding its subdirectories to `children` as needed for a
+    /// `Tree`.
+    fn upload_directory(
+        &self,
+        algorithm: DigestAlgorithm,
+        dir: &Path,
+        children: &mut Vec<Directory>,
+    ) -> anyhow::Result<Directory> {
+        let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        let mut directory = Directory::default();
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_symlink() {
+                directory.symlinks.push(SymlinkNode {
+                    name,
+                    target: fs::read_link(&path)?.to_string_lossy().into_owned(),
+                    ..Default::default()
+                });
+            } else if file_type.is_dir() {
+                let child = self.upload_directory(algorithm, &path, children)?;
+                let digest = self.put(algorithm, child.encode_to_vec());
+                children.push(child);
+                directory.directories.push(DirectoryNode {
+                    name,
+                    digest: Some(digest),
+                });
+            } else {
+                directory.files.push(FileNode {
+                    name,
+                    digest: Some(self.put(algorithm, fs::read(&path)?)),
+                    is_executable: is_executable(&entry.metadata()?),
+                    ..Default::default()
+                });
+            }
+        }
+        Ok(directory)
+    }
+
+    fn collect_output(
+        &self,
+        algorithm: DigestAlgorithm,
+        workdir: &Path,
+        path: &str,
+        result: &mut ActionResult,
+    ) -> anyhow::Result<()> {
+        let full_path = workdir.join(path);
+        let metadata = match fs::symlink_metadata(&full_path) {
+            Ok(metadata) => metadata,
+            // Missing outputs are for the client to report.
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+
+        if metadata.file_type().is_symlink() {
+            result.output_symlinks.push(OutputSymlink {
+                path: path.to_owned(),
+                target: fs::read_link(&full_path)?.to_string_lossy().into_owned(),
+                ..Default::default()
+            });
+        } else if metadata.is_dir() {
+            let mut children = Vec::new();
+            let root = self.upload_directory(algorithm, &full_path, &mut children)?;
+            let tree = Tree {
+                root: Some(root),
+                children,
+            };
+            result.output_directories.push(OutputDirectory {
+                path: path.to_owned(),
+                tree_digest: Some(self.put(algorithm, tree.encode_to_vec())),
+                ..Default::default()
+            });
+        } else {
+            result.output_files.push(OutputFile {
+                path: path.to_owned(),
+                digest: Some(self.put(algorithm, fs::read(&full_path)?)),
+                is_executable: is_executable(&metadata),
+                ..Default::default()
+            });
+        }
+        Ok(())
+    }
+
+    /// Runs the action in a temporary directory, and caches its result if it succeeded.
+    fn run_action(&self, action_digest: &Digest) -> anyhow::Result<ActionResult> {
+        let action_blob = self.blob(Some(action_digest))?;
+        let algorithm = DigestAlgorithm::of(action_digest, &action_blob).with_context(|| {
+            format!(
+                "The digest of action `{}` uses an unsupported digest function",
+                action_digest.hash
+            )
+        })?;
+        let action = Action::decode(&action_blob[..])?;
+        let command = Command::decode(&self.blob(action.command_digest.as_ref())?[..])?;
+
+        let start = SystemTime::now();
+        let tempdir = tempfile::tempdir()?;
+        let root = tempdir.path();
+        self.materialize(action.input_root_digest.as_ref(), root)?;
+
+        let workdir = root.join(&command.working_directory);
+        let output_paths = if command.output_paths.is_empty() {
+            command
+                .output_files
+                .iter()
+                .chain(&command.output_directories)
+                .cloned()
+                .collect()
+        } else {
+            command.output_paths.clone()
+        };
+        for path in &output_paths {
+            if let Some(parent) = workdir.join(path).parent() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let (program, args) = command
+            .arguments
+            .split_first()
+            .context("The command has no arguments")?;
+        let execution_start = SystemTime::now();
+        let output = std::process::Command::new(program)
+            .args(args)
+            .env_clear()
+            .envs(
+                command
+                    .environment_variables
+                    .iter()
+                    .map(|var| (&var.name, &var.value)),
+            )
+            .current_dir(&workdir)
+            .output()
+            .with_context(|| format!("Error running `{}`", program))?;
+        let execution_end = SystemTime::now();
+
+        let mut result = ActionResult {
+            exit_code: output.status.code().unwrap_or(-1),
+            stdout_digest: Some(self.put(algorithm, output.stdout)),
+            stderr_digest: Some(self.put(algorithm, output.stderr)),
+            ..Default::default()
+        };
+        for path in &output_paths {
+            self.collect_output(algorithm, &workdir, path, &mut result)?;
+        }
+        result.execution_metadata = Some(ExecutedActionMetadata {
+            worker: "fake".to_owned(),
+            queued_timestamp: Some(start.into()),
+            worker_start_timestamp: Some(start.into()),
+            input_fetch_start_timestamp: Some(start.into()),
+            input_fetch_completed_timestamp: Some(execution_start.into()),
+            execution_start_timestamp: Some(execution_start.into()),
+            execution_completed_timestamp: Some(execution_end.into()),
+            output_upload_start_timestamp: Some(execution_end.into()),
+            output_upload_completed_timestamp: Some(SystemTime::now().into()),
+            worker_completed_timestamp: Some(SystemTime::now().into()),
+            ..Default::default()
+        });
+
+        if result.exit_code == 0 && !action.do_not_cache {
+            self.0
+                .action_cache
+                .lock()
+                .unwrap()
+                .insert(action_digest.hash.clone(), result.clone());
+        }
+        Ok(result)
+    }
+}
+
+#[tonic::async_trait]
+impl Capabilities for FakeRe {
+    async fn get_capabilities(
+        &self,
+        _request: Request<GetCapabilitiesRequest>,
+    ) -> Result<Response<ServerCapabilities>, Status> {
+        Ok(Response::new(ServerCapabilities {
+            cache_capabilities: Some(CacheCapabilities {
+                digest_functions: vec![
+                    digest_function::Value::Sha1 as i32,
+                    digest_function::Value::Sha256 as i32,
+                    DIGEST_FUNCTION_BLAKE3,
+                ],
+                action_cache_update_capabilities: Some(ActionCacheUpdateCapabilities {
+                    update_enabled: true,
+                }),
+                max_batch_total_size_bytes: MAX_BATCH_TOTAL_SIZE as i64,
+                ..Default::default()
+            }),
+            // The digest function is left unset: actions run with the one they were hashed with.
+            execution_capabilities: Some(ExecutionCapabilities {
+                exec_enabled: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }))
+    }
+}
+
+#[tonic::async_trait]
+impl ContentAddressableStorage for FakeRe {
+    type GetTreeStream = BoxStream<'static, Result<GetTreeResponse, Status>>;
+
+    async fn find_missing_blobs(
+        &self,
+        request: Request<FindMissingBlobsRequest>,
+    ) -> Result<Response<FindMissingBlobsResponse>, Status> {
+        let cas = self.0.cas.lock().unwrap();
+        Ok(Response::new(FindMissingBlobsResponse {
+            missing_blob_digests: request
+                .into_inner()
+                .blob_digests
+                .into_iter()
+                .filter(|digest| !cas.contains_key(&digest.hash))
+                .collect(),
+        }))
+    }
+
+    async fn batch_update_blobs(
+        &self,
+        request: Request<BatchUpdateBlobsRequest>,
+    ) -> Result<Response<BatchUpdateBlobsResponse>, Status> {
+        let mut cas = self.0.cas.lock().unwrap();
+        let responses = request
+            .into_inner()
+            .requests
+            .into_iter()
+            .map(|req| {
+                let digest = req.digest.unwrap_or_default();
+                let status = match verify_blob(&digest, &req.data) {
+                    Ok(()) => {
+                        cas.insert(digest.hash.clone(), Arc::new(req.data));
+                        rpc_status(Code::Ok, String::new())
+                    }
+                    Err(message) => rpc_status(Code::InvalidArgument, message),
+                };
+                batch_update_blobs_response::Response {
+                    digest: Some(digest),
+                    status: Some(status),
+                }
+            })
+            .collect();
+        Ok(Response::new(BatchUpdateBlobsResponse { responses }))
+    }
+
+    async fn batch_read_blobs(
+        &self,
+        request: Request<BatchReadBlobsRequest>,
+    ) -> Result<Response<BatchReadBlobsResponse>, Status> {
+        let cas = self.0.cas.lock().unwrap();
+        let responses = request
+            .into_inner()
+            .digests
+            .into_iter()
+            .map(|digest| match cas.get(&digest.hash) {
+                Some(data) => batch_read_blobs_response::Response {
+                    digest: Some(digest),
+                    data: (**data).clone(),
+                    status: Some(rpc_status(Code::Ok, String::new())),
+                    ..Default::default()
+                },
+                None => batch_read_blobs_response::Response {
+                    status: Some(rpc_status(
+                        Code::NotFound,
+                        format!("Blob `{}` is not in the CAS", digest.hash),
+                    )),
+                    digest: Some(digest),
+                    ..Default::default()
+                },
+            })
+            .collect();
+        Ok(Response::new(BatchReadBlobsResponse { responses }))
+    }
+
+    async fn get_tree(
+        &self,
+        _request: Request<GetTreeRequest>,
+    ) -> Result<Response<Self::GetTreeStream>, Status> {
+        Err(Status::unimplemented(
+            "GetTree is not supported by the fake RE backend",
+        ))
+    }
+}
+
+#[tonic::async_trait]
+impl ByteStream for FakeRe {
+    type ReadStream = BoxStream<'static, Result<ReadResponse, Status>>;
+
+    async fn read(
+        &self,
+        request: Request<ReadRequest>,
+    ) -> Result<Response<Self::ReadStream>, Status> {
+        let request = request.into_inner();
+        let hash = resource_digest(&request.resource_name)?.hash;
+        let blob = self
+            .0
+            .cas
+            .lock()
+            .unwrap()
+            .get(&hash)
+            .cloned()
+            .ok_or_else(|| Status::not_found(format!("Blob `{}` is not in the CAS", hash)))?;
+
+        let offset = request.read_offset as usize;
+        if offset > blob.len() {
+            return Err(Status::out_of_range(
+                "Read offset is past the end of the blob",
+            ));
+        }
+        let mut end = blob.len();
+        if request.read_limit > 0 {
+            end = end.min(offset + request.read_limit as usize);
+        }
+        let chunks: Vec<_> = blob[offset..end]
+            .chunks(READ_CHUNK_SIZE)
+            .map(|chunk| {
+                Ok(ReadResponse {
+                    data: chunk.to_vec(),
+                })
+            })
+            .collect();
+        Ok(Response::new(futures::stream::iter(chunks).boxed()))
+    }
+
+    async fn write(
+        &self,
+        request: Request<Streaming<WriteRequest>>,
+    ) -> Result<Response<WriteResponse>, Status> {
+        let mut stream = request.into_inner();
+        let mut resource_name = None;
+        let mut data = Vec::new();
+        while let Some(req) = stream.message().await? {
+            if req.write_offset as usize != data.len() {
+                return Err(Status::invalid_argument(format!(
+                    "Write offset {} does not follow the {} bytes written",
+                    req.write_offset,
+                    data.len()
+                )));
+            }
+            resource_name.get_or_insert(req.resource_name);
+            data.extend(req.data);
+            if req.finish_write {
+                break;
+            }
+        }
+
+        let digest = resource_digest(resource_name.as_deref().unwrap_or_default())?;
+        verify_blob(&digest, &data).map_err(Status::invalid_argument)?;
+        let committed_size = data.len() as i64;
+        self.0
+            .cas
+            .lock()
+            .unwrap()
+            .insert(digest.hash, Arc::new(data));
+        Ok(Response::new(WriteResponse { committed_size }))
+    }
+
+    async fn query_write_status(
+        &self,
+        request: Request<QueryWriteStatusRequest>,
+    ) -> Result<Response<QueryWriteStatusResponse>, Status> {
+        let request = request.into_inner();
+        let hash = resource_digest(&request.resource_name)?.hash;
+        // Writes are only stored once complete.
+        let committed_size = self.0.cas.lock().unwrap().get(&hash).map(|blob| blob.len());
+        Ok(Response::new(QueryWriteStatusResponse {
+            committed_size: committed_size.unwrap_or_default() as i64,
+            complete: committed_size.is_some(),
+        }))
+    }
+}
+
+#[tonic::async_trait]
+impl ActionCache for FakeRe {
+    async fn get_action_result(
+        &self,
+        request: Request<GetActionResultRequest>,
+    ) -> Result<Response<ActionResult>, Status> {
+        let digest = request.into_inner().action_digest.unwrap_or_default();
+        match self.0.action_cache.lock().unwrap().get(&digest.hash) {
+            Some(result) => Ok(Response::new(result.clone())),
+            None => Err(Status::not_found(format!(
+                "Action `{}` is not in the action cache",
+                digest.hash
+            ))),
+        }
+    }
+
+    async fn update_action_result(
+        &self,
+        request: Request<UpdateActionResultRequest>,
+    ) -> Result<Response<ActionResult>, Status> {
+        let request = request.into_inner();
+        let digest = request.action_digest.unwrap_or_default();
+        let result = request.action_result.unwrap_or_default();
+        self.0
+            .action_cache
+            .lock()
+            .unwrap()
+            .insert(digest.hash, result.clone());
+        Ok(Response::new(result))
+    }
+}
+
+#[tonic::async_trait]
+impl Execution for FakeRe {
+    type ExecuteStream = BoxStream<'static, Result<Operation, Status>>;
+    type WaitExecutionStream = BoxStream<'static, Result<Operation, Status>>;
+
+    async fn execute(
+        &self,
+        request: Request<ExecuteRequest>,
+    ) -> Result<Response<Self::ExecuteStream>, Status> {
+        let request = request.into_inner();
+        let action_digest = request
+            .action_digest
+            .ok_or_else(|| Status::invalid_argument("Missing action digest"))?;
+
+        let cached = if request.skip_cache_lookup {
+            None
+        } else {
+            self.0
+                .action_cache
+                .lock()
+                .unwrap()
+                .get(&action_digest.hash)
+                .cloned()
+        };
+        let response = match cached {
+            Some(result) => ExecuteResponse {
+                result: Some(result),
+                cached_result: true,
+                ..Default::default()
+            },
+            None => {
+                let fake = self.clone();
+                let digest = action_digest.clone();
+                let result = tokio::task::spawn_blocking(move || fake.run_action(&digest))
+                    .await
+                    .map_err(|e| internal(e.into()))?
+                    .map_err(internal)?;
+                ExecuteResponse {
+                    result: Some(result),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let operation = Operation {
+            name: format!("fake/{}", action_digest.hash),
+            metadata: None,
+            done: true,
+            result: Some(operation::Result::Response(prost_types::Any {
+                type_url: "type.googleapis.com/build.bazel.remote.execution.v2.ExecuteResponse"
+                    .to_owned(),
+                value: response.encode_to_vec(),
+            })),
+        };
+        Ok(Response::new(
+            futures::stream::once(async move { Ok(operation) }).boxed(),
+        ))
+    }
+
+    async fn wait_execution(
+        &self,
+        _request: Request<WaitExecutionRequest>,
+    ) -> Result<Response<Self::WaitExecutionStream>, Status> {
+        // Operations are complete by the time `Execute` returns them.
+        Err(Status::not_found("Unknown operation"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use re_grpc_proto::build::bazel::remote::execution::v2::batch_update_blobs_request;
+
+    use super::*;
+
+    #[test]
+    fn test_resource_digest() {
+        let digest = Digest {
+            hash: "abc".to_owned(),
+            size_bytes: 3,
+        };
+        assert_eq!(
+            digest,
+            resource_digest("instance/uploads/some-uuid/blobs/abc/3").unwrap()
+        );
+        assert_eq!(digest, resource_digest("blobs/abc/3").unwrap());
+        assert!(resource_digest("instance/abc/3").is_err());
+        assert!(resource_digest("blobs/abc").is_err());
+    }
+
+    #[test]
+    fn test_verify_blob() {
+        let digest = DigestAlgorithm::Sha256.digest(b"blob");
+        assert!(verify_blob(&digest, b"blob").is_ok());
+        assert!(verify_blob(&digest, b"blub").is_err());
+        let digest = Digest {
+            size_bytes: 5,
+            ..digest
+        };
+        assert!(verify_blob(&digest, b"blob").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_batch_update_blobs_rejects_mismatches() {
+        let fake = FakeRe::default();
+        let good = DigestAlgorithm::Sha256.digest(b"good");
+        let request = BatchUpdateBlobsRequest {
+            requests: vec![
+                batch_update_blobs_request::Request {
+                    digest: Some(good.clone()),
+                    data: b"good".to_vec(),
+                    ..Default::default()
+                },
+                // Doesn't overwrite the blob uploaded under this digest.
+                batch_update_blobs_request::Request {
+                    digest: Some(good.clone()),
+                    data: b"evil".to_vec(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let responses = fake
+            .batch_update_blobs(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner()
+            .responses;
+        let codes: Vec<_> = responses
+            .iter()
+            .map(|response| response.status.as_ref().unwrap().code)
+            .collect();
+        assert_eq!(vec![Code::Ok as i32, Code::InvalidArgument as i32], codes);
+        assert_eq!(
+            b"good".as_slice(),
+            fake.blob(Some(&good)).unwrap().as_slice()
+        );
+    }
+
+    #[test]
+    fn test_digest_algorithm() {
+        for algorithm in [
+            DigestAlgorithm::Sha1,
+            DigestAlgorithm::Sha256,
+            DigestAlgorithm::Blake3,
+        ] {
+            let digest = algorithm.digest(b"action");
+            assert_eq!(Some(algorithm), DigestAlgorithm::of(&digest, b"action"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_action() -> anyhow::Result<()> {
+        let fake = FakeRe::default();
+        let algorithm = DigestAlgorithm::Sha256;
+        let input = Directory {
+            files: vec![FileNode {
+                name: "in.txt".to_owned(),
+                digest: Some(fake.put(algorithm, b"hello".to_vec())),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let command = Command {
+            arguments: vec![
+                "cp".to_owned(),
+                "in.txt".to_owned(),
+                "out/out.txt".to_owned(),
+            ],
+            output_paths: vec!["out/out.txt".to_owned()],
+            ..Default::default()
+        };
+        let action = Action {
+            command_digest: Some(fake.put(algorithm, command.encode_to_vec())),
+            input_root_digest: Some(fake.put(algorithm, input.encode_to_vec())),
+            ..Default::default()
+        };
+        let action_digest = fake.put(algorithm, action.encode_to_vec());
+
+        let result = fake.run_action(&action_digest)?;
+        assert_eq!(0, result.exit_code);
+        assert_eq!("out/out.txt", result.output_files[0].path);
+        assert_eq!(
+            b"hello".as_slice(),
+            fake.blob(result.output_files[0].digest.as_ref())?
+                .as_slice()
+        );
+        let action_cache = fake.0.action_cache.lock().unwrap();
+        assert!(action_cache.contains_key(&action_digest.hash));
+        Ok(())
+    }
+}
@@ -10,11 +10,14 @@
 pub use re_grpc_proto::build::bazel::remote::execution::v2::command::EnvironmentVariable;
 pub use re_grpc_proto::build::bazel::remote::execution::v2::platform::Property;
 pub use re_grpc_proto::build::bazel::remote::execution::v2::Action;
+pub use re_grpc_proto::build::bazel::remote::execution::v2::ActionResult;
 pub use re_grpc_proto::build::bazel::remote::execution::v2::Command;
 pub use re_grpc_proto::build::bazel::remote::execution::v2::Digest;
 pub use re_grpc_proto::build::bazel::remote::execution::v2::Directory;
 pub use re_grpc_proto::build::bazel::remote::execution::v2::DirectoryNode;
 pub use re_grpc_proto::build::bazel::remote::execution::v2::FileNode;
+pub use re_grpc_proto::build::bazel::remote::execution::v2::OutputDirectory;
+pub use re_grpc_proto::build::bazel::remote::execution::v2::OutputFile;
 pub use re_grpc_proto::build::bazel::remote::execution::v2::Platform;
 pub use re_grpc_proto::build::bazel::remote::execution::v2::SymlinkNode;
 pub use re_grpc_proto::build::bazel::remote::execution::v2::Tree;
@@ -10,6 +10,7 @@
 mod client;
 mod digest;
 mod error;
+mod fake;
 mod grpc;
 mod metadata;
 mod request;
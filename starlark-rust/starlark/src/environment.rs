@@ -35,7 +35,7 @@ pub use modules::*;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
-enum EnvironmentError {
+pub(crate) enum EnvironmentError {
     /// Cannot import private symbol, i.e. underscore prefixed
     #[error("Cannot import private symbol `{0}`")]
     CannotImportPrivateSymbol(String),
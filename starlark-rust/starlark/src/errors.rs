@@ -24,3 +24,5 @@ pub use crate::analysis::EvalSeverity;
 pub use crate::analysis::Lint;
 
 pub(crate) mod did_you_mean;
+
+pub use crate::errors::did_you_mean::suggested_name;
@@ -19,6 +19,11 @@
 
 use strsim::levenshtein;
 
+use crate::environment::EnvironmentError;
+use crate::eval::compiler::scope::ScopeError;
+use crate::values::ValueError;
+use crate::ErrorKind;
+
 /// Find a suggestion for a typo.
 pub(crate) fn did_you_mean<'a>(
     value: &str,
@@ -43,9 +48,29 @@ pub(crate) fn did_you_mean<'a>(
         .map(|(v, _)| v)
 }
 
+/// The name suggested by an error for a typo, such as
+/// "Variable `x` not found, did you mean `y`?".
+pub fn suggested_name(error: &crate::Error) -> Option<&str> {
+    let e = match error.kind() {
+        ErrorKind::Scope(e) | ErrorKind::Value(e) | ErrorKind::Other(e) => e,
+        _ => return None,
+    };
+    if let Some(ScopeError::VariableNotFoundDidYouMean(_, name)) = e.downcast_ref() {
+        Some(name)
+    } else if let Some(ValueError::NoAttrDidYouMean(_, _, name)) = e.downcast_ref() {
+        Some(name)
+    } else if let Some(EnvironmentError::ModuleHasNoSymbolDidYouMean(_, name)) = e.downcast_ref() {
+        Some(name)
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::assert;
     use crate::errors::did_you_mean::did_you_mean;
+    use crate::errors::did_you_mean::suggested_name;
 
     #[test]
     fn prefixes() {
@@ -92,4 +117,14 @@ mod tests {
         assert_eq!(Some("aaaay"), did_you_mean("aaaax", vec!["aaaay", "aaaaz"]));
         assert_eq!(Some("aaaaz"), did_you_mean("aaaax", vec!["aaaaz", "aaaay"]));
     }
+
+    #[test]
+    fn suggested_names() {
+        let e = assert::fail("discreet = 1\ndiscrete", "not found");
+        assert_eq!(Some("discreet"), suggested_name(&e));
+        let e = assert::fail("[].appen", "has no attribute");
+        assert_eq!(Some("append"), suggested_name(&e));
+        let e = assert::fail("x", "not found");
+        assert_eq!(None, suggested_name(&e));
+    }
 }
@@ -74,7 +74,7 @@ use crate::values::FrozenStringValue;
 use crate::values::FrozenValue;
 
 #[derive(Debug, thiserror::Error)]
-enum ScopeError {
+pub(crate) enum ScopeError {
     #[error("Variable `{0}` not found")]
     VariableNotFound(String),
     #[error("Variable `{0}` not found, did you mean `{1}`?")]
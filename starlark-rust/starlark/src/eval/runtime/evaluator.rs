@@ -437,6 +437,11 @@ impl<'v, 'a, 'e: 'a> Evaluator<'v, 'a, 'e> {
         self.print_handler = handler;
     }
 
+    /// The handler invoked when `print` function is used, for other functions which print.
+    pub fn print_handler(&self) -> &'a (dyn PrintHandler + 'a) {
+        self.print_handler
+    }
+
     /// Set deprecation handler. If not set, deprecations are treated as hard errors.
     pub fn set_soft_error_handler(&mut self, handler: &'a (dyn SoftErrorHandler + 'a)) {
         self.soft_error_handler = handler;
@@ -127,6 +127,10 @@ const manualSidebar = [
           'users/advanced/deferred_materialization',
           'users/advanced/restarter',
           'users/advanced/in_memory_cache',
+          'users/advanced/heap_snapshots',
+          'users/advanced/phase_concurrency',
+          'users/advanced/offline_mode',
+          'users/advanced/flaky_tests',
           'users/advanced/external_cells',
           isInternal() ? 'users/advanced/offline_build_archives' : [],
           isInternal() ? 'users/advanced/vpnless' : [],